@@ -1,11 +1,33 @@
 //! toolbox CLI - Display development tool versions
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
-use toolbox_core::{Config, ResolvedTheme, ToolDetector};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use miette::{LabeledSpan, NamedSource};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use toolbox_core::capabilities::Capabilities;
+use toolbox_core::color::THEME_PRESETS;
+use toolbox_core::config::{expand_home, ConfigFormat, PowerlineLayout, Section};
+use toolbox_core::config_validate::validate_config;
+use toolbox_core::config_watch::{self, ConfigWatcher};
+use toolbox_core::export::{format_rfc3339, SbomFormat};
+use toolbox_core::history::History;
+use toolbox_core::info::SchemaVersion;
+use toolbox_core::mcp::McpServer;
+use toolbox_core::prompt::{self, PromptCache, Shell};
+use toolbox_core::require::Requirement;
+use toolbox_core::serve::ApiServer;
+use toolbox_core::snapshot::{Snapshot, ToolChange, DEFAULT_SNAPSHOT_FILE};
+use toolbox_core::trust::{self, TrustStore};
+use toolbox_core::{
+    config_edit, config_migrate, config_template, format, import, messages, package, update, zellij,
+};
+use toolbox_core::{
+    CacheState, CachedVersion, Config, DetectEvent, FormatOptions, FormatRegistry, GitInfo,
+    PowerlineOptions, ResolvedTheme, ToolDetector, ToolInfo, ToolboxError, ToolboxInfo,
+};
 
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 #[command(name = "toolbox")]
 #[command(about = "Display development tool versions and system info")]
 #[command(version)]
@@ -22,14 +44,46 @@ struct Cli {
     #[arg(short, long, default_value = "text")]
     format: OutputFormat,
 
+    /// Render the output with the formatter of a `[plugins.<name>]` WASM plugin
+    #[arg(long, value_name = "NAME")]
+    format_plugin: Option<String>,
+
     /// Compact output
     #[arg(long)]
     compact: bool,
 
+    /// Full version strings, even if the config enables compact mode
+    #[arg(long, conflicts_with = "compact")]
+    no_compact: bool,
+
     /// Hide icons
     #[arg(long)]
     no_icons: bool,
 
+    /// Only detect these tools (names or short names, comma-separated), even if disabled
+    #[arg(long, value_delimiter = ',')]
+    only: Vec<String>,
+
+    /// Sections to show, in order (comma-separated: dir, git, tools, venv, system) - overrides config
+    #[arg(long, value_delimiter = ',')]
+    sections: Option<Vec<Section>>,
+
+    /// Show system memory and CPU usage (text output)
+    #[arg(long)]
+    system: bool,
+
+    /// Show tool versions only: no directory, git, venv or system info
+    #[arg(long, conflicts_with_all = ["extras_only", "system"])]
+    tools_only: bool,
+
+    /// Show everything but the tool versions (skips tool detection)
+    #[arg(long)]
+    extras_only: bool,
+
+    /// Show unavailable tools (marked with ✗) instead of hiding them
+    #[arg(long)]
+    show_missing: bool,
+
     /// Powerline style output (colored segments with separators)
     #[arg(long)]
     powerline: bool,
@@ -38,6 +92,14 @@ struct Cli {
     #[arg(long)]
     single_line: bool,
 
+    /// Maximum output width in columns (only with --powerline); abbreviates, drops or truncates segments to fit
+    #[arg(long)]
+    max_width: Option<usize>,
+
+    /// Single-line powerline layout: left, right, split - overrides config (right and split align to --max-width)
+    #[arg(long)]
+    layout: Option<PowerlineLayout>,
+
     /// Color mode: auto, always, never
     #[arg(long, default_value = "auto")]
     color: String,
@@ -50,10 +112,44 @@ struct Cli {
     #[arg(long)]
     refresh: bool,
 
-    /// Theme preset (default, dark, light, solarized) - overrides config
+    /// Theme preset (default, dark, light, solarized, nord, dracula, gruvbox, catppuccin) - overrides config
     #[arg(long)]
     theme: Option<String>,
 
+    /// Theme color overrides, e.g. "directory_bg=#3465A4,tool_bg=cyan;magenta" - applied on top of the preset
+    #[arg(long)]
+    theme_colors: Option<String>,
+
+    /// Apply a `[profiles.<name>]` section of the config
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Embed doctor's diagnostic of each tool (status, command, shim, PATH
+    /// matches, suggestion) in the JSON output; runs the version commands twice
+    #[arg(long)]
+    with_diagnostics: bool,
+
+    /// Pin the JSON output to this schema version (e.g. v1), so consumers keep
+    /// working when the output evolves
+    #[arg(long, value_name = "VERSION")]
+    schema: Option<SchemaVersion>,
+
+    /// Print per-tool detection times (slowest first) to stderr
+    #[arg(long)]
+    timings: bool,
+
+    /// Log detection details to stderr (-v debug, -vv trace)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Write logs to this file instead of stderr (debug level unless -vv)
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Print supported formats and flags as JSON (used by the Zellij plugin)
+    #[arg(long)]
+    capabilities: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -63,30 +159,386 @@ enum OutputFormat {
     Text,
     Json,
     JsonPretty,
+    Jsonl,
+    Markdown,
+    Html,
+    Csv,
+    Tsv,
 }
 
-#[derive(Subcommand)]
+#[derive(Clone, Subcommand)]
 enum Commands {
-    /// Initialize configuration file
+    /// Initialize configuration file (TOML: every option commented, unless --minimal)
     Init {
         /// Force overwrite existing config
         #[arg(short, long)]
         force: bool,
+        /// Write every option with an explanation (the default)
+        #[arg(long, conflicts_with = "minimal")]
+        full: bool,
+        /// Write only the layout version; every option keeps its default
+        #[arg(long)]
+        minimal: bool,
+        /// Detect the built-in tools first and enable exactly the ones found
+        #[arg(long)]
+        from_detect: bool,
     },
     /// Show current configuration
     ShowConfig,
+    /// Read or modify individual config keys (e.g. display.compact)
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Import tool definitions from a starship.toml, mise.toml or .tool-versions
+    Import {
+        /// Format to import: starship, mise or asdf
+        source: import::ImportSource,
+        /// File to read (default: $STARSHIP_CONFIG or ~/.config/starship.toml,
+        /// mise.toml or .tool-versions in --dir)
+        file: Option<PathBuf>,
+        /// Add the entries to the config file instead of printing them
+        #[arg(long)]
+        write: bool,
+    },
     /// List available tools
     ListTools,
+    /// Inspect a single tool
+    Tool {
+        #[command(subcommand)]
+        action: ToolCommand,
+    },
     /// Diagnose tool detection environment
     Doctor {
         /// Output as JSON
         #[arg(long)]
         json: bool,
+        /// Only list tools with errors (the counts still cover every tool)
+        #[arg(long)]
+        only_errors: bool,
+        /// Only diagnose these tools (names or short names, comma-separated or repeated)
+        #[arg(long, value_delimiter = ',')]
+        tool: Vec<String>,
+        /// Exit with status 1 if any tool has this severity or worse: warning, error
+        #[arg(long)]
+        fail_on: Option<FailOn>,
+        /// Look up the package that installed each tool (Homebrew, apt, Scoop,
+        /// winget) and how to upgrade it
+        #[arg(long)]
+        packages: bool,
+    },
+    /// Explain how a tool is detected: command line, raw output, regex
+    /// captures, cache state, resolved path and where its definition came from
+    Explain {
+        /// Tool name or short name
+        name: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Export the detected toolchain as an SBOM document (JSON)
+    Export {
+        /// SBOM format: cyclonedx, spdx
+        #[arg(long)]
+        sbom: SbomFormat,
+    },
+    /// Record detected tool versions to a lockfile
+    Snapshot {
+        /// Lockfile path (default: toolbox.lock.json in the working directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Compare the live environment against a lockfile (exits 1 on drift)
+    Diff {
+        /// Lockfile path (default: toolbox.lock.json in the working directory)
+        lockfile: Option<PathBuf>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-detect periodically and report version changes
+    Watch {
+        /// Seconds between checks (at least 1)
+        #[arg(long, default_value = "60", value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Send a desktop notification when a version changes or a tool disappears
+        #[arg(long)]
+        notify: bool,
+        /// Stop after this many checks (default: run until interrupted)
+        #[arg(long)]
+        count: Option<u64>,
     },
+    /// Check tool versions against constraints (exits 1 if any fail)
+    Require {
+        /// Constraints as tool=constraint (e.g. node=">=20" rust="^1.75");
+        /// defaults to the [requirements] table in the config file
+        requirements: Vec<Requirement>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Trust the project config (.toolbox.toml) at or above a directory, so it
+    /// may define commands; untrusted ones only change display settings
+    Trust {
+        /// Directory (default: the working directory)
+        dir: Option<PathBuf>,
+        /// Stop trusting the config
+        #[arg(long)]
+        revoke: bool,
+    },
+    /// Benchmark detection (uncached and cached) and report min/mean/max per tool
+    Bench {
+        /// Measured runs per mode
+        #[arg(short = 'n', long, default_value = "10")]
+        iterations: usize,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show when tool versions changed on this machine
+    History {
+        /// Only show changes of this tool
+        tool: Option<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the JSON Schema of the config file or the JSON output
+    Schema {
+        /// Which schema: config, output
+        kind: SchemaKind,
+    },
+    /// List and preview themes
+    Themes {
+        #[command(subcommand)]
+        action: ThemesCommand,
+    },
+    /// Serve tool versions, diagnostics and requirement checks to coding agents
+    /// (Model Context Protocol over stdio)
+    Mcp,
+    /// Serve /info, /doctor and /cache/stats as JSON over HTTP (the config is
+    /// reloaded when it changes)
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = toolbox_core::serve::DEFAULT_ADDR)]
+        http: String,
+    },
+    /// Control a running `toolbox serve`
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonCommand,
+    },
+    /// Print a Zellij layout (KDL) that loads the toolbox plugin
+    ZellijLayout {
+        /// One-line powerline bar above the panes (default)
+        #[arg(long, conflicts_with = "sidebar")]
+        statusbar: bool,
+        /// Multi-line pane to the right of the panes
+        #[arg(long)]
+        sidebar: bool,
+        /// Plugin location (default: the plugin in the Zellij plugins directory)
+        #[arg(long)]
+        plugin: Option<String>,
+    },
+    /// Print a shell hook that shows `toolbox prompt` in front of the prompt
+    InitShell {
+        /// Shell: bash, zsh, fish
+        shell: Shell,
+    },
+    /// Print a one-line prompt segment (results are cached per directory)
+    Prompt {
+        /// Escape the output for this shell's prompt string
+        #[arg(long)]
+        shell: Option<Shell>,
+        /// Detect and store the result without printing (the background refresh)
+        #[arg(long, hide = true)]
+        update_cache: bool,
+    },
+    /// Copy the built plugin into the Zellij plugins directory
+    ZellijInstall {
+        /// Plugin file (default: next to the toolbox binary, or the cargo build output)
+        wasm: Option<PathBuf>,
+        /// Plugins directory (default: $ZELLIJ_CONFIG_DIR/plugins or ~/.config/zellij/plugins)
+        #[arg(long)]
+        plugins_dir: Option<PathBuf>,
+        /// Overwrite an installed plugin
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Bring the installed Zellij plugin to this CLI's version: copy a
+    /// matching local build, else download the one from the matching release
+    ZellijUpdate {
+        /// Plugin file to install (default: next to the toolbox binary, or the
+        /// cargo build output, when its version matches)
+        wasm: Option<PathBuf>,
+        /// Plugins directory (default: $ZELLIJ_CONFIG_DIR/plugins or ~/.config/zellij/plugins)
+        #[arg(long)]
+        plugins_dir: Option<PathBuf>,
+        /// Only report whether the installed plugin matches the CLI
+        #[arg(long)]
+        check: bool,
+    },
+    /// Update toolbox to the latest GitHub release (over https, checked against
+    /// the release's SHA256SUMS; there is no signature check)
+    SelfUpdate {
+        /// Only report whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum FailOn {
+    /// Warnings or errors
+    Warning,
+    /// Errors only
+    Error,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum SchemaKind {
+    /// config.toml
+    Config,
+    /// `--format json` output
+    Output,
+}
+
+#[derive(Clone, Subcommand)]
+enum DaemonCommand {
+    /// Reload the server's config now and print what changed
+    Reload {
+        /// Address the server listens on
+        #[arg(long, value_name = "ADDR", default_value = toolbox_core::serve::DEFAULT_ADDR)]
+        http: String,
+    },
+}
+
+#[derive(Clone, Subcommand)]
+enum ConfigCommand {
+    /// Print a key's effective value (defaults included)
+    Get {
+        /// Dotted key, e.g. cache.default_ttl
+        key: String,
+    },
+    /// Set a key in the config file, keeping comments and formatting
+    Set {
+        /// Dotted key, e.g. display.compact
+        key: String,
+        /// TOML value (true, 600, ["dir", "git"]); anything else is a string
+        value: String,
+    },
+    /// Remove a key from the config file (reverting it to the default)
+    Unset {
+        /// Dotted key, e.g. display.compact
+        key: String,
+    },
+    /// Open the config file in $VISUAL/$EDITOR and validate it before saving
+    Edit,
+    /// Check the config file for syntax errors, unknown keys, invalid regexes and colors
+    Validate,
+    /// Upgrade an older config file to the current layout (prints the result unless --write)
+    Migrate {
+        /// Rewrite the config file in place
+        #[arg(long)]
+        write: bool,
+    },
+}
+
+#[derive(Clone, Subcommand)]
+enum ToolCommand {
+    /// Show every installed version of a tool and mark the active one
+    Info {
+        /// Tool name or short name
+        name: String,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Subcommand)]
+enum ThemesCommand {
+    /// List built-in presets and user-defined themes
+    List,
+    /// Render sample powerline output with a theme (all themes if omitted)
+    Preview {
+        /// Theme name (built-in preset or user-defined theme)
+        name: Option<String>,
+    },
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            report_error(&e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Print an error to stderr: a config file that doesn't parse as a snippet
+/// with the offending part underlined, anything else as `main` would
+fn report_error(error: &anyhow::Error) {
+    let parse = error
+        .chain()
+        .find_map(|cause| match cause.downcast_ref::<ToolboxError>() {
+            Some(ToolboxError::ConfigParse(parse)) => Some(parse),
+            _ => None,
+        });
+    match parse {
+        Some(parse) => {
+            let message = parse.error.message();
+            let labels: Vec<LabeledSpan> = parse
+                .error
+                .span()
+                .map(|span| LabeledSpan::at(span, "here"))
+                .into_iter()
+                .collect();
+            let report =
+                miette::miette!(labels = labels, help = parse_help(message), "{}", message)
+                    .with_source_code(NamedSource::new(
+                        parse.path.display().to_string(),
+                        parse.content.clone(),
+                    ));
+            eprintln!("{:?}", report);
+        }
+        None => eprintln!("Error: {:?}", error),
+    }
+}
+
+/// Help note for a TOML parse error message
+fn parse_help(message: &str) -> &'static str {
+    if message.contains("expected a boolean") {
+        "booleans are written `true` or `false`, without quotes"
+    } else if message.contains("expected a string") {
+        "strings are quoted: \"like this\" or 'like this'"
+    } else if message.contains("unknown variant") {
+        "use one of the values listed above"
+    } else if message.contains("duplicate key") {
+        "each key may appear once per table; remove or merge the other one"
+    } else if message.starts_with("expected") || message.starts_with("invalid") {
+        "each line is a `[table]` header or `key = value`; strings need quotes"
+    } else {
+        "fix the marked part; `toolbox config validate` checks the whole file"
+    }
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn run() -> Result<()> {
+    let mut cli = Cli::parse();
+    // `~` is not expanded when the CLI is run without a shell (e.g. by the Zellij plugin)
+    cli.dir = cli
+        .dir
+        .map(|dir| expand_home(&dir).to_string_lossy().into_owned());
+    if cli.capabilities {
+        println!("{}", serde_json::to_string(&capabilities())?);
+        return Ok(());
+    }
+    init_logging(cli.verbose, cli.log_file.as_deref())?;
+    if cli.verbose > 0 {
+        lint_config(&cli);
+    }
+    set_language(&cli);
 
     // Handle subcommands
     if let Some(ref command) = cli.command {
@@ -94,18 +546,30 @@ fn main() -> Result<()> {
     }
 
     // Load config
-    let config = if let Some(ref config_path) = cli.config {
-        Config::load_from_path(config_path)?
-    } else {
-        Config::load()?
-    };
+    let mut config = load_config(&cli)?;
+    if let Some(ref sections) = cli.sections {
+        config.display.sections = sections.clone();
+    }
+    if cli.system {
+        config.extras.system_memory = true;
+        config.extras.system_cpu = true;
+    }
+    apply_only_toggles(&cli, &mut config);
+    if !cli.only.is_empty() {
+        config.only_tools(&cli.only)?;
+    }
 
     // Resolve theme before moving config into detector
     let mut theme_config = config.theme.clone();
     if let Some(ref preset) = cli.theme {
         theme_config.preset = preset.clone();
     }
-    let theme = ResolvedTheme::from_config(&theme_config);
+    if let Some(ref spec) = cli.theme_colors {
+        let mut custom = theme_config.custom.take().unwrap_or_default();
+        custom.apply_spec(spec).map_err(|e| anyhow::anyhow!(e))?;
+        theme_config.custom = Some(custom);
+    }
+    let theme = ResolvedTheme::from_config_with_themes(&theme_config, &config.themes);
 
     // Create detector
     let mut detector = ToolDetector::new(config);
@@ -120,7 +584,53 @@ fn main() -> Result<()> {
     }
 
     // Detect all tools
-    let info = detector.detect_all();
+    let mut info = if cli.format == OutputFormat::Jsonl && cli.format_plugin.is_none() {
+        detector.detect_streaming(|event| {
+            if let DetectEvent::Tool(_) = event {
+                if let Ok(line) = serde_json::to_string(&event) {
+                    println!("{}", line);
+                }
+            }
+        })
+    } else {
+        detector.detect_all()
+    };
+    if cli.timings {
+        eprintln!("{}", info.format_timings());
+    }
+
+    // Journal version changes (best effort: never fails the main output)
+    if let Some(history) = history_journal(detector.config()) {
+        if let Err(e) = history.record(&info) {
+            tracing::warn!(path = %history.path().display(), error = %e, "could not record history");
+        }
+    }
+
+    // Compare with the snapshot lockfile, if any (colors versions by drift)
+    let lockfile = default_lockfile(&cli);
+    if lockfile.exists() {
+        match Snapshot::load(&lockfile) {
+            Ok(snapshot) => snapshot.annotate(&mut info),
+            Err(e) => {
+                tracing::warn!(path = %lockfile.display(), error = %e, "could not read lockfile")
+            }
+        }
+    }
+
+    if cli.with_diagnostics {
+        detector.attach_diagnostics(&mut info);
+    }
+    // Lets the Zellij plugin switch between the config's profiles
+    info.profile = cli.profile.clone();
+    info.profiles = detector.config().profiles.keys().cloned().collect();
+    if let Some(schema) = cli.schema {
+        info.pin_schema(schema);
+    }
+
+    if let Some(ref name) = cli.format_plugin {
+        print!("{}", format_with_plugin(detector.config(), name, &info)?);
+        return Ok(());
+    }
 
     // Parse color mode
     let color_mode: toolbox_core::color::ColorMode = cli
@@ -130,34 +640,70 @@ fn main() -> Result<()> {
     let use_color = toolbox_core::color::should_use_color(color_mode);
 
     // Output
-    match cli.format {
-        OutputFormat::Text => {
-            let compact = cli.compact || detector.config().display.compact;
-            let show_icons = !cli.no_icons && detector.config().display.show_icons;
-
-            if cli.powerline {
-                println!(
-                    "{}",
-                    info.format_powerline(compact, show_icons, use_color, cli.single_line, &theme)
-                );
-            } else {
-                println!("{}", info.format_display(compact, show_icons));
-            }
-        }
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string(&info)?);
-        }
-        OutputFormat::JsonPretty => {
-            println!("{}", serde_json::to_string_pretty(&info)?);
-        }
-    }
+    let compact = !cli.no_compact && (cli.compact || detector.config().display.compact);
+    let show_icons = !cli.no_icons && detector.config().display.show_icons;
+    let show_unavailable = cli.show_missing || detector.config().display.show_unavailable;
+    let options = FormatOptions {
+        display: PowerlineOptions {
+            compact,
+            show_icons,
+            use_color,
+            single_line: cli.single_line,
+            sections: &detector.config().display.sections,
+            max_width: cli.max_width,
+            show_unavailable,
+            group_tools: detector.config().display.group_tools,
+            layout: cli.layout.unwrap_or(detector.config().display.layout),
+            right_sections: &detector.config().display.right_sections,
+        },
+        theme: &theme,
+    };
+    let name = if cli.format == OutputFormat::Text && cli.powerline {
+        "powerline".to_string()
+    } else {
+        format_name(cli.format)
+    };
+    let registry = FormatRegistry::new();
+    let formatter = registry
+        .get(&name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown format: {}", name))?;
+    print!("{}", formatter.format(&info, &options)?);
 
     Ok(())
 }
 
+/// The built-in tools installed on this machine (`init --from-detect`),
+/// detected with all of them enabled
+fn detect_installed_tools(cli: &Cli) -> Result<Vec<String>> {
+    let mut probe = Config::default();
+    let names: Vec<String> = probe
+        .effective_tools()
+        .into_iter()
+        .map(|tool| tool.name)
+        .collect();
+    probe.only_tools(&names)?;
+
+    let mut detector = ToolDetector::new(probe).with_cache_disabled();
+    if let Some(ref dir) = cli.dir {
+        detector = detector.with_working_dir(dir.clone());
+    }
+    Ok(detector
+        .detect_all()
+        .tools
+        .into_iter()
+        .filter(|tool| tool.available)
+        .map(|tool| tool.name)
+        .collect())
+}
+
 fn handle_command(command: &Commands, cli: &Cli) -> Result<()> {
     match command {
-        Commands::Init { force } => {
+        Commands::Init {
+            force,
+            full: _,
+            minimal,
+            from_detect,
+        } => {
             let force = *force;
             let config_path = if let Some(ref path) = cli.config {
                 path.clone()
@@ -172,8 +718,27 @@ fn handle_command(command: &Commands, cli: &Cli) -> Result<()> {
                 return Ok(());
             }
 
-            let config = Config::default();
-            config.save_to_path(&config_path)?;
+            let mut config = Config::default();
+            if *from_detect {
+                let found = detect_installed_tools(cli)?;
+                println!("Found {} tools: {}", found.len(), found.join(", "));
+                config.enable_found_tools(&found);
+            }
+
+            // Comments are TOML-only; YAML/JSON get the plain config
+            if ConfigFormat::from_path(&config_path) == ConfigFormat::Toml {
+                let content = if *minimal {
+                    config_template::minimal(&config)?
+                } else {
+                    config_template::full(&config)?
+                };
+                if let Some(parent) = config_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&config_path, content)?;
+            } else {
+                config.save_to_path(&config_path)?;
+            }
             println!("Created config file at: {}", config_path.display());
         }
 
@@ -188,6 +753,189 @@ fn handle_command(command: &Commands, cli: &Cli) -> Result<()> {
             println!("{}", toml_str);
         }
 
+        Commands::Config { action } => {
+            let config_path = if let Some(ref path) = cli.config {
+                path.clone()
+            } else {
+                Config::find_config_path()
+                    .or_else(Config::config_path)
+                    .ok_or_else(|| anyhow::anyhow!("Could not determine config path"))?
+            };
+            let is_toml = ConfigFormat::from_path(&config_path) == ConfigFormat::Toml;
+            let editable = matches!(
+                action,
+                ConfigCommand::Set { .. }
+                    | ConfigCommand::Unset { .. }
+                    | ConfigCommand::Edit
+                    | ConfigCommand::Migrate { .. }
+            );
+            if editable && !is_toml {
+                anyhow::bail!(
+                    "Editing is only supported for TOML config files: {}",
+                    config_path.display()
+                );
+            }
+
+            match action {
+                ConfigCommand::Get { key } => {
+                    let config = if config_path.exists() {
+                        Config::load_from_path(&config_path)?
+                    } else {
+                        Config::default()
+                    };
+                    let value = config_edit::get_key(&config, key)?;
+                    println!("{}", config_edit::format_value(&value));
+                }
+                ConfigCommand::Set { key, value } => {
+                    let content = if config_path.exists() {
+                        std::fs::read_to_string(&config_path)?
+                    } else {
+                        String::new()
+                    };
+                    let edited = config_edit::set_key(&content, key, value)?;
+                    if let Some(parent) = config_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    std::fs::write(&config_path, edited)?;
+                }
+                ConfigCommand::Unset { key } => {
+                    let content = std::fs::read_to_string(&config_path).map_err(|e| {
+                        anyhow::anyhow!(
+                            "Could not read config file {}: {}",
+                            config_path.display(),
+                            e
+                        )
+                    })?;
+                    let edited = config_edit::unset_key(&content, key)?;
+                    std::fs::write(&config_path, edited)?;
+                }
+                ConfigCommand::Edit => edit_config(&config_path)?,
+                ConfigCommand::Validate => {
+                    let content = std::fs::read_to_string(&config_path).map_err(|e| {
+                        anyhow::anyhow!(
+                            "Could not read config file {}: {}",
+                            config_path.display(),
+                            e
+                        )
+                    })?;
+                    // YAML/JSON have no line-level checks; a successful load is the check
+                    if !is_toml {
+                        Config::load_from_path(&config_path)?;
+                        println!("Config OK: {}", config_path.display());
+                        return Ok(());
+                    }
+                    let issues = validate_config(&content);
+                    if issues.is_empty() {
+                        println!("Config OK: {}", config_path.display());
+                    } else {
+                        for issue in &issues {
+                            eprintln!("{}:{}", config_path.display(), issue);
+                        }
+                        std::process::exit(1);
+                    }
+                }
+                ConfigCommand::Migrate { write } => {
+                    let content = std::fs::read_to_string(&config_path).map_err(|e| {
+                        anyhow::anyhow!(
+                            "Could not read config file {}: {}",
+                            config_path.display(),
+                            e
+                        )
+                    })?;
+                    let migrated = config_migrate::migrate(&content)?;
+                    if !migrated.changed() {
+                        eprintln!(
+                            "Config is up to date (version {}): {}",
+                            migrated.to,
+                            config_path.display()
+                        );
+                        return Ok(());
+                    }
+
+                    for step in &migrated.applied {
+                        eprintln!("  - {}", step);
+                    }
+                    if *write {
+                        std::fs::write(&config_path, &migrated.content)?;
+                        eprintln!(
+                            "Migrated {} from version {} to {}",
+                            config_path.display(),
+                            migrated.from,
+                            migrated.to
+                        );
+                    } else {
+                        print!("{}", migrated.content);
+                    }
+                }
+            }
+        }
+
+        Commands::Import {
+            source,
+            file,
+            write,
+        } => {
+            let path = match file {
+                Some(file) => file.clone(),
+                None => {
+                    let dir = match cli.dir {
+                        Some(ref dir) => PathBuf::from(dir),
+                        None => std::env::current_dir()?,
+                    };
+                    source
+                        .default_path(&dir)
+                        .ok_or_else(|| anyhow::anyhow!("Could not determine the file to import"))?
+                }
+            };
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+            let imported = import::Import::parse(*source, &content)?;
+            for name in &imported.skipped {
+                eprintln!("Skipped {}: no version command for it", name);
+            }
+            if imported.is_empty() {
+                eprintln!("Nothing to import from {}", path.display());
+                return Ok(());
+            }
+
+            if !*write {
+                print!("{}", imported.to_toml()?);
+                return Ok(());
+            }
+            let config_path = if let Some(ref path) = cli.config {
+                path.clone()
+            } else {
+                Config::find_config_path()
+                    .or_else(Config::config_path)
+                    .ok_or_else(|| anyhow::anyhow!("Could not determine config path"))?
+            };
+            if ConfigFormat::from_path(&config_path) == ConfigFormat::Toml {
+                let content = if config_path.exists() {
+                    std::fs::read_to_string(&config_path)?
+                } else {
+                    String::new()
+                };
+                let edited = imported.apply_to_toml(&content)?;
+                if let Some(parent) = config_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&config_path, edited)?;
+            } else {
+                let mut config = if config_path.exists() {
+                    Config::load_from_path(&config_path)?
+                } else {
+                    Config::default()
+                };
+                imported.apply(&mut config);
+                config.save_to_path(&config_path)?;
+            }
+            println!(
+                "Imported {} tools into: {}",
+                imported.tool_overrides.len() + imported.custom_tools.len(),
+                config_path.display()
+            );
+        }
+
         Commands::ListTools => {
             let config = Config::default();
             println!("Available tools:\n");
@@ -202,27 +950,1157 @@ fn handle_command(command: &Commands, cli: &Cli) -> Result<()> {
             }
         }
 
-        Commands::Doctor { json } => {
-            let config = if let Some(ref config_path) = cli.config {
-                Config::load_from_path(config_path)?
-            } else {
-                Config::load()?
+        Commands::Tool {
+            action: ToolCommand::Info { name, json },
+        } => {
+            let config = load_config(cli)?;
+            let Some(tool_config) = config.find_tool(name) else {
+                anyhow::bail!("Unknown tool: {} (see `toolbox list-tools`)", name);
             };
 
+            let mut detector = ToolDetector::new(config).with_cache_disabled();
+            if let Some(ref dir) = cli.dir {
+                detector = detector.with_working_dir(dir.clone());
+            }
+            let mut tool = detector.detect_tool(&tool_config);
+            tool.installed_versions = detector.installed_versions(&tool_config, &tool);
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&tool)?);
+            } else {
+                print!("{}", tool.format_installed());
+            }
+        }
+
+        Commands::Doctor {
+            json,
+            only_errors,
+            tool,
+            fail_on,
+            packages,
+        } => {
+            let config = load_config(cli)?;
+            let update_check = config.update.check;
+
             let mut detector = ToolDetector::new(config);
             if let Some(ref dir) = cli.dir {
                 detector = detector.with_working_dir(dir.clone());
             }
 
-            let summary = detector.diagnose_all();
+            let mut summary = if tool.is_empty() {
+                detector.diagnose_all()
+            } else {
+                detector.diagnose_tools(tool)?
+            };
+            if update_check {
+                summary.update_available =
+                    update::check_cache_path().and_then(|path| update::available_update(&path));
+            }
+            if *packages {
+                package::annotate(&mut summary);
+            }
+            let failed = match fail_on {
+                Some(FailOn::Warning) => summary.warning_count + summary.error_count > 0,
+                Some(FailOn::Error) => summary.error_count > 0,
+                None => false,
+            };
+            if *only_errors {
+                summary
+                    .tools
+                    .retain(|d| d.status == toolbox_core::DiagnosticStatus::Error);
+            }
 
             if *json {
                 println!("{}", serde_json::to_string_pretty(&summary)?);
             } else {
                 println!("{}", summary.format_display());
             }
+
+            if failed {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Explain { name, json } => {
+            let config = load_config(cli)?;
+            let dir = cli.dir.clone().or_else(|| {
+                std::env::current_dir()
+                    .ok()
+                    .map(|dir| dir.to_string_lossy().into_owned())
+            });
+
+            let mut detector = ToolDetector::new(config.clone()).with_cache_disabled();
+            if let Some(ref dir) = cli.dir {
+                detector = detector.with_working_dir(dir.clone());
+            }
+            let mut explanation = detector.explain_tool(name)?;
+
+            // The only cache that outlives a run is the one `toolbox prompt`
+            // keeps per directory
+            let enabled = config.cache.enabled && !cli.no_cache;
+            let key = prompt_cache_key(cli, dir.as_deref());
+            let cache = PromptCache::from_config(&config)?.filter(|_| enabled);
+            explanation.cache = CacheState {
+                enabled,
+                ttl_seconds: config.cache.default_ttl,
+                entry: cache.and_then(|cache| {
+                    let info = cache.load_stale(&key)?;
+                    let tool = info
+                        .tools
+                        .into_iter()
+                        .find(|t| t.name == explanation.name)?;
+                    let age_seconds = cache.age(&key)?.as_secs();
+                    Some(CachedVersion {
+                        version: tool.display_version.or(tool.version),
+                        age_seconds,
+                        expired: age_seconds >= config.cache.default_ttl,
+                    })
+                }),
+            };
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&explanation)?);
+            } else {
+                println!("{}", explanation.format_display());
+            }
+        }
+
+        Commands::Export { sbom } => {
+            let config = load_config(cli)?;
+
+            let mut detector = ToolDetector::new(config);
+            if let Some(ref dir) = cli.dir {
+                detector = detector.with_working_dir(dir.clone());
+            }
+
+            let info = detector.detect_all();
+            let document = toolbox_core::export::to_sbom(&info, *sbom);
+            println!("{}", serde_json::to_string_pretty(&document)?);
+        }
+
+        Commands::Snapshot { output } => {
+            let config = load_config(cli)?;
+
+            let mut detector = ToolDetector::new(config).with_cache_disabled();
+            if let Some(ref dir) = cli.dir {
+                detector = detector.with_working_dir(dir.clone());
+            }
+
+            let snapshot = Snapshot::from_info(&detector.detect_all());
+            let path = output.clone().unwrap_or_else(|| default_lockfile(cli));
+            snapshot.save(&path)?;
+            // Every snapshot is also kept in the database, if there is one
+            if let Some(db) = detector.config().storage.database()? {
+                save_snapshot_to_store(&db, &path, &snapshot)?;
+            }
+            println!(
+                "Recorded {} tools to: {}",
+                snapshot.tools.len(),
+                path.display()
+            );
+        }
+
+        Commands::Diff { lockfile, json } => {
+            let path = lockfile.clone().unwrap_or_else(|| default_lockfile(cli));
+            let recorded = Snapshot::load(&path).map_err(|e| {
+                anyhow::anyhow!("Could not read lockfile {}: {}", path.display(), e)
+            })?;
+
+            let config = load_config(cli)?;
+
+            let mut detector = ToolDetector::new(config).with_cache_disabled();
+            if let Some(ref dir) = cli.dir {
+                detector = detector.with_working_dir(dir.clone());
+            }
+
+            let current = Snapshot::from_info(&detector.detect_all());
+            let changes = recorded.diff(&current);
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&changes)?);
+            } else if changes.is_empty() {
+                println!("No drift from {} ({})", path.display(), recorded.created_at);
+            } else {
+                let color_mode: toolbox_core::color::ColorMode = cli
+                    .color
+                    .parse()
+                    .unwrap_or(toolbox_core::color::ColorMode::Auto);
+                let use_color = toolbox_core::color::should_use_color(color_mode);
+                for change in &changes {
+                    println!("{}", change.format_display(use_color));
+                }
+            }
+
+            if !changes.is_empty() {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Watch {
+            interval,
+            notify,
+            count,
+        } => {
+            if *notify && !cfg!(feature = "notify") {
+                anyhow::bail!("toolbox was built without desktop notification support");
+            }
+
+            let config = load_config(cli)?;
+            let mut history = history_journal(&config);
+            let mut watcher = config_watcher(cli)?;
+
+            let mut detector = ToolDetector::new(config).with_cache_disabled();
+            if let Some(ref dir) = cli.dir {
+                detector = detector.with_working_dir(dir.clone());
+            }
+
+            let color_mode: toolbox_core::color::ColorMode = cli
+                .color
+                .parse()
+                .unwrap_or(toolbox_core::color::ColorMode::Auto);
+            let use_color = toolbox_core::color::should_use_color(color_mode);
+
+            let mut previous: Option<Snapshot> = None;
+            let mut checks = 0;
+            loop {
+                // Edits to the config apply from the next check on
+                if watcher.changed() {
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|elapsed| elapsed.as_secs())
+                        .unwrap_or(0);
+                    let message = match load_config(cli) {
+                        Ok(config) => {
+                            let changes = config_watch::config_changes(detector.config(), &config);
+                            history = history_journal(&config);
+                            detector.set_config(config);
+                            config_watch::reload_message(&changes)
+                        }
+                        Err(e) => config_watch::reload_failed_message(&e),
+                    };
+                    println!("{}  {}", format_rfc3339(now), message);
+                }
+
+                let info = detector.detect_all();
+                if let Some(ref history) = history {
+                    if let Err(e) = history.record(&info) {
+                        tracing::warn!(path = %history.path().display(), error = %e, "could not record history");
+                    }
+                }
+                let current = Snapshot::from_info(&info);
+
+                match previous {
+                    None => println!("Watching {} tools every {}s", current.tools.len(), interval),
+                    Some(ref previous) => {
+                        let changes = previous.diff(&current);
+                        for change in &changes {
+                            println!(
+                                "{}  {}",
+                                current.created_at,
+                                change.format_display(use_color)
+                            );
+                        }
+
+                        let alerts: Vec<String> = changes
+                            .iter()
+                            .filter(|c| !matches!(c, ToolChange::Added { .. }))
+                            .map(|c| c.format_display(false))
+                            .collect();
+                        if *notify && !alerts.is_empty() {
+                            if let Err(e) =
+                                send_notification("Tool versions changed", &alerts.join("\n"))
+                            {
+                                eprintln!("Failed to send notification: {}", e);
+                            }
+                        }
+                    }
+                }
+                previous = Some(current);
+
+                checks += 1;
+                if count.is_some_and(|count| checks >= count) {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_secs(*interval));
+            }
+        }
+
+        Commands::Require { requirements, json } => {
+            let config = load_config(cli)?;
+
+            let requirements = if requirements.is_empty() {
+                // The config table, plus the package manager the project pins
+                let mut requirements = Requirement::from_config(&config);
+                let dir = match cli.dir {
+                    Some(ref dir) => Some(PathBuf::from(dir)),
+                    None => std::env::current_dir().ok(),
+                };
+                if let Some(pinned) = dir.and_then(|dir| Requirement::from_package_manager(&dir)) {
+                    if !requirements
+                        .iter()
+                        .any(|r| r.tool.eq_ignore_ascii_case(&pinned.tool))
+                    {
+                        requirements.push(pinned);
+                    }
+                }
+                requirements
+            } else {
+                requirements.clone()
+            };
+            if requirements.is_empty() {
+                anyhow::bail!(
+                    "No requirements given (pass tool=constraint, add a [requirements] table to the config or pin packageManager in package.json)"
+                );
+            }
+
+            let mut detector = ToolDetector::new(config);
+            if let Some(ref dir) = cli.dir {
+                detector = detector.with_working_dir(dir.clone());
+            }
+            if cli.no_cache {
+                detector = detector.with_cache_disabled();
+            }
+
+            let results = toolbox_core::require::check_requirements(&mut detector, &requirements);
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            } else {
+                let color_mode: toolbox_core::color::ColorMode = cli
+                    .color
+                    .parse()
+                    .unwrap_or(toolbox_core::color::ColorMode::Auto);
+                let use_color = toolbox_core::color::should_use_color(color_mode);
+                println!(
+                    "{}",
+                    toolbox_core::require::format_requirements(&results, use_color)
+                );
+            }
+
+            if !results.iter().all(|r| r.passed()) {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Trust { dir, revoke } => {
+            let start = match (dir, &cli.dir) {
+                (Some(dir), _) => dir.clone(),
+                (None, Some(dir)) => PathBuf::from(dir),
+                (None, None) => std::env::current_dir()?,
+            };
+            let Some(project_config) = trust::find_project_config(&start) else {
+                anyhow::bail!(
+                    "No {} found at or above {}",
+                    trust::PROJECT_CONFIG_FILE,
+                    start.display()
+                );
+            };
+            let store_path = TrustStore::default_path()
+                .ok_or_else(|| anyhow::anyhow!("Could not determine the data directory"))?;
+            let mut store = TrustStore::load(&store_path)?;
+            if *revoke {
+                if store.revoke(&project_config) {
+                    store.save(&store_path)?;
+                    println!("No longer trusted: {}", project_config.display());
+                } else {
+                    println!("Not trusted: {}", project_config.display());
+                }
+            } else {
+                store.trust(&project_config)?;
+                store.save(&store_path)?;
+                println!("Trusted: {}", project_config.display());
+            }
+        }
+
+        Commands::Bench { iterations, json } => {
+            let config = load_config(cli)?;
+
+            let report = toolbox_core::bench::run_bench(&config, cli.dir.clone(), *iterations);
+
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", report.format_display());
+            }
+        }
+
+        Commands::History { tool, json } => {
+            let config = if let Some(ref config_path) = cli.config {
+                Config::load_from_path(config_path)?
+            } else {
+                Config::load()?
+            };
+
+            let history = History::from_config(&config)?
+                .ok_or_else(|| anyhow::anyhow!("Could not determine history path"))?;
+            let entries = history.entries()?;
+
+            if *json {
+                let entries: Vec<_> = entries
+                    .iter()
+                    .filter(|e| match tool {
+                        Some(t) => t.eq_ignore_ascii_case(&e.tool),
+                        None => true,
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else {
+                let output = toolbox_core::history::format_history(&entries, tool.as_deref());
+                if output.is_empty() && !config.history.enabled {
+                    println!(
+                        "No version changes recorded (set history.enabled = true to record them)"
+                    );
+                } else if output.is_empty() {
+                    println!(
+                        "No version changes recorded in: {}",
+                        history.path().display()
+                    );
+                } else {
+                    println!("{}", output);
+                }
+            }
+        }
+
+        Commands::Schema { kind } => {
+            let schema = match kind {
+                SchemaKind::Config => toolbox_core::schema::config_schema(),
+                SchemaKind::Output => toolbox_core::schema::output_schema(),
+            };
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+        }
+
+        Commands::Themes { action } => {
+            let config = if let Some(ref config_path) = cli.config {
+                Config::load_from_path(config_path)?
+            } else {
+                Config::load()?
+            };
+            let active = cli.theme.as_deref().unwrap_or(&config.theme.preset);
+
+            match action {
+                ThemesCommand::List => {
+                    println!("Built-in themes:\n");
+                    for name in THEME_PRESETS {
+                        let marker = if *name == active { "*" } else { " " };
+                        println!("  {} {}", marker, name);
+                    }
+                    if !config.themes.is_empty() {
+                        println!("\nUser themes:\n");
+                        for (name, theme) in &config.themes {
+                            let marker = if name == active { "*" } else { " " };
+                            println!("  {} {} (based on {})", marker, name, theme.preset);
+                        }
+                    }
+                    println!("\nPreview with: toolbox themes preview [NAME]");
+                }
+
+                ThemesCommand::Preview { name } => {
+                    let names: Vec<String> = match name {
+                        Some(name) => {
+                            if !ResolvedTheme::is_preset(name) && !config.themes.contains_key(name)
+                            {
+                                anyhow::bail!(
+                                    "Unknown theme: {} (see `toolbox themes list`)",
+                                    name
+                                );
+                            }
+                            vec![name.clone()]
+                        }
+                        None => THEME_PRESETS
+                            .iter()
+                            .map(|name| name.to_string())
+                            .chain(config.themes.keys().cloned())
+                            .collect(),
+                    };
+
+                    let color_mode: toolbox_core::color::ColorMode = cli
+                        .color
+                        .parse()
+                        .unwrap_or(toolbox_core::color::ColorMode::Auto);
+                    let use_color = toolbox_core::color::should_use_color(color_mode);
+                    let show_icons = !cli.no_icons && config.display.show_icons;
+
+                    for (i, name) in names.iter().enumerate() {
+                        let mut theme_config = config.theme.clone();
+                        theme_config.preset = name.clone();
+                        let theme =
+                            ResolvedTheme::from_config_with_themes(&theme_config, &config.themes);
+
+                        if i > 0 {
+                            println!();
+                        }
+                        println!("{}:", name);
+                        for info in [preview_info(false), preview_info(true)] {
+                            println!(
+                                "{}",
+                                format::powerline(
+                                    &info,
+                                    &PowerlineOptions {
+                                        compact: cli.compact,
+                                        show_icons,
+                                        use_color,
+                                        single_line: true,
+                                        sections: &config.display.sections,
+                                        max_width: cli.max_width,
+                                        show_unavailable: false,
+                                        group_tools: config.display.group_tools,
+                                        layout: cli.layout.unwrap_or(config.display.layout),
+                                        right_sections: &config.display.right_sections,
+                                    },
+                                    &theme
+                                )
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Mcp => {
+            let config = load_config(cli)?;
+            let mut server = McpServer::new(config).with_dir_config(dir_config_loader(cli));
+            if let Some(ref dir) = cli.dir {
+                server = server.with_working_dir(dir.clone());
+            }
+            if cli.no_cache {
+                server = server.with_cache_disabled();
+            }
+            server.serve(std::io::stdin().lock(), std::io::stdout().lock())?;
+        }
+
+        Commands::Serve { http } => {
+            let watcher = config_watcher(cli)?;
+            let config = watcher.load()?;
+
+            let mut detector = ToolDetector::new(config);
+            if cli.no_cache {
+                detector = detector.with_cache_disabled();
+            }
+            let listener = std::net::TcpListener::bind(http)
+                .map_err(|err| anyhow::anyhow!("Cannot listen on {}: {}", http, err))?;
+            eprintln!("Serving toolbox API on http://{}", listener.local_addr()?);
+            ApiServer::new(detector, cli.dir.clone())
+                .with_dir_config(dir_config_loader(cli))
+                .with_config_reload(watcher, |message| {
+                    // A closed stderr must not take the server down
+                    use std::io::Write;
+                    let _ = writeln!(std::io::stderr(), "{}", message);
+                })
+                .serve(&listener)?;
+        }
+
+        Commands::Daemon { action } => match action {
+            DaemonCommand::Reload { http } => {
+                let response = toolbox_core::serve::request(http, "POST", "/reload")?;
+                let body: serde_json::Value =
+                    serde_json::from_str(&response.body).unwrap_or_default();
+                if response.status != 200 {
+                    anyhow::bail!(
+                        "{}",
+                        body["error"].as_str().unwrap_or("Config reload failed")
+                    );
+                }
+                let changes: Vec<String> = body["changes"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|change| change.as_str().map(str::to_string))
+                    .collect();
+                println!("{}", config_watch::reload_message(&changes));
+            }
+        },
+
+        Commands::InitShell { shell } => {
+            let exe = std::env::current_exe()
+                .map(|exe| exe.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| "toolbox".to_string());
+            print!("{}", prompt::init_script(*shell, &exe));
+        }
+
+        Commands::Prompt {
+            shell,
+            update_cache,
+        } => {
+            let mut config = load_config(cli)?;
+            if let Some(ref sections) = cli.sections {
+                config.display.sections = sections.clone();
+            }
+            apply_only_toggles(cli, &mut config);
+            prompt::apply_exclusions(&mut config);
+            let mut theme_config = config.theme.clone();
+            if let Some(ref preset) = cli.theme {
+                theme_config.preset = preset.clone();
+            }
+            let theme = ResolvedTheme::from_config_with_themes(&theme_config, &config.themes);
+
+            let dir = cli.dir.clone().or_else(|| {
+                std::env::current_dir()
+                    .ok()
+                    .map(|dir| dir.to_string_lossy().into_owned())
+            });
+            let key = prompt_cache_key(cli, dir.as_deref());
+            let cache = PromptCache::from_config(&config)?
+                .filter(|_| config.cache.enabled && !cli.no_cache);
+            let detect = |config: &Config| {
+                let mut detector = ToolDetector::new(config.clone()).with_cache_disabled();
+                if let Some(ref dir) = dir {
+                    detector = detector.with_working_dir(dir.clone());
+                }
+                detector.detect_all()
+            };
+
+            let info = match cache {
+                None => detect(&config),
+                Some(ref cache) if *update_cache || cli.refresh => {
+                    let info = detect(&config);
+                    if let Err(e) = cache.store(&key, &info) {
+                        tracing::warn!(error = %e, "could not cache the prompt");
+                    }
+                    if *update_cache {
+                        cache.unlock(&key);
+                        return Ok(());
+                    }
+                    info
+                }
+                Some(ref cache) => {
+                    let info = match cache.load(&key) {
+                        Some(info) => info,
+                        None => {
+                            if cache.try_lock(&key) {
+                                if let Err(e) = spawn_prompt_refresh() {
+                                    tracing::warn!(error = %e, "could not refresh the prompt");
+                                    cache.unlock(&key);
+                                }
+                            }
+                            // Within the budget, else the last result (or nothing)
+                            cache
+                                .wait_fresh(
+                                    &key,
+                                    std::time::Duration::from_millis(config.prompt.budget_ms),
+                                )
+                                .or_else(|| cache.load_stale(&key))
+                                .unwrap_or_default()
+                        }
+                    };
+                    // Once per prompt, for the eviction order
+                    cache.touch(&key);
+                    info
+                }
+            };
+
+            let color_mode = cli
+                .color
+                .parse()
+                .unwrap_or(toolbox_core::color::ColorMode::Auto);
+            let segment = format::powerline(
+                &info,
+                &PowerlineOptions {
+                    compact: true,
+                    show_icons: !cli.no_icons && config.display.show_icons,
+                    use_color: prompt::use_color(color_mode),
+                    single_line: true,
+                    sections: &config.display.sections,
+                    max_width: cli.max_width,
+                    show_unavailable: false,
+                    group_tools: config.display.group_tools,
+                    layout: PowerlineLayout::Left,
+                    right_sections: &[],
+                },
+                &theme,
+            );
+            if !segment.is_empty() {
+                let segment = match shell {
+                    Some(shell) => prompt::escape(*shell, &segment),
+                    None => segment,
+                };
+                print!("{} ", segment);
+            }
+        }
+
+        Commands::ZellijLayout {
+            statusbar: _,
+            sidebar,
+            plugin,
+        } => {
+            let style = if *sidebar {
+                zellij::LayoutStyle::Sidebar
+            } else {
+                zellij::LayoutStyle::Statusbar
+            };
+            let location = match plugin {
+                Some(location) => location.clone(),
+                None => zellij::plugins_dir()
+                    .map(|dir| zellij::plugin_location(&dir.join(zellij::PLUGIN_FILE)))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Could not determine the Zellij config directory; pass --plugin"
+                        )
+                    })?,
+            };
+            print!("{}", zellij::layout(style, &location));
+        }
+
+        Commands::ZellijInstall {
+            wasm,
+            plugins_dir,
+            force,
+        } => {
+            let candidates = match wasm {
+                Some(path) => vec![path.clone()],
+                None => plugin_candidates(),
+            };
+            let plugin = zellij::find_plugin(&candidates)?;
+            let dir = zellij_plugins_dir(plugins_dir.as_deref())?;
+            let installed = zellij::install(&plugin, &dir, *force)?;
+            println!("Installed {} to {}", plugin.display(), installed.display());
+            println!("Generate a layout with: toolbox zellij-layout > <zellij config>/layouts/toolbox.kdl");
+        }
+
+        Commands::ZellijUpdate {
+            wasm,
+            plugins_dir,
+            check,
+        } => {
+            let dir = zellij_plugins_dir(plugins_dir.as_deref())?;
+            let wanted = update::current_version().to_string();
+            let installed = std::fs::read(dir.join(zellij::PLUGIN_FILE))
+                .ok()
+                .map(|plugin| zellij::plugin_version(&plugin));
+            let installed = match installed {
+                Some(Some(version)) if version == wanted => {
+                    println!("Plugin {} matches the CLI", version);
+                    return Ok(());
+                }
+                Some(Some(version)) => version,
+                Some(None) => "of unknown version".to_string(),
+                None => "not installed".to_string(),
+            };
+            if *check {
+                println!(
+                    "Plugin {} (CLI {}); run `toolbox zellij-update`",
+                    installed, wanted
+                );
+                return Ok(());
+            }
+
+            let candidates = match wasm {
+                Some(path) => vec![path.clone()],
+                None => plugin_candidates(),
+            };
+            let local = candidates.iter().find_map(|path| {
+                let plugin = std::fs::read(path).ok()?;
+                (zellij::plugin_version(&plugin).as_deref() == Some(wanted.as_str()))
+                    .then(|| (path.display().to_string(), plugin))
+            });
+            let (source, plugin) = match (local, wasm) {
+                (Some(local), _) => local,
+                (None, Some(path)) => {
+                    anyhow::bail!("{} is not a {} build of the plugin", path.display(), wanted)
+                }
+                (None, None) => {
+                    let tag = format!("v{}", wanted);
+                    let release = update::release(&tag)?;
+                    let plugin = update::download_verified(&release, zellij::PLUGIN_FILE)?;
+                    (format!("release {}", tag), plugin)
+                }
+            };
+            let path = zellij::write_plugin(&dir, &plugin)?;
+            println!(
+                "Updated plugin ({}) to {} from {}: {}",
+                installed,
+                wanted,
+                source,
+                path.display()
+            );
+        }
+
+        Commands::SelfUpdate { check } => {
+            let current = update::current_version();
+            let release = update::latest_release()?;
+            let Some(version) = release.version().filter(|_| release.is_newer()) else {
+                println!("toolbox {} is up to date", current);
+                return Ok(());
+            };
+            if *check {
+                println!("toolbox {} is available (current: {})", version, current);
+                return Ok(());
+            }
+            let asset = update::platform_asset().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No release binary for {}-{}",
+                    std::env::consts::OS,
+                    std::env::consts::ARCH
+                )
+            })?;
+            let binary = update::download_verified(&release, &asset)?;
+            update::replace_executable(&std::env::current_exe()?, &binary)?;
+            println!("Updated toolbox {} -> {}", current, version);
         }
     }
 
     Ok(())
 }
+
+/// Apply `--tools-only` / `--extras-only`
+fn apply_only_toggles(cli: &Cli, config: &mut Config) {
+    if cli.tools_only {
+        config.tools_only();
+    }
+    if cli.extras_only {
+        config.extras_only();
+    }
+}
+
+/// Where to look for a built plugin: next to the toolbox binary, then the
+/// cargo build output
+fn plugin_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        candidates.push(dir.join(zellij::PLUGIN_FILE));
+    }
+    candidates.push(PathBuf::from(zellij::BUILD_OUTPUT));
+    candidates
+}
+
+/// The Zellij plugins directory, as given or the default one
+fn zellij_plugins_dir(dir: Option<&Path>) -> Result<PathBuf> {
+    match dir {
+        Some(dir) => Ok(dir.to_path_buf()),
+        None => zellij::plugins_dir().ok_or_else(|| {
+            anyhow::anyhow!("Could not determine the Zellij config directory; pass --plugins-dir")
+        }),
+    }
+}
+
+/// Show a desktop notification
+#[cfg(feature = "notify")]
+fn send_notification(summary: &str, body: &str) -> Result<()> {
+    notify_rust::Notification::new()
+        .appname("toolbox")
+        .summary(summary)
+        .body(body)
+        .show()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "notify"))]
+fn send_notification(_summary: &str, _body: &str) -> Result<()> {
+    anyhow::bail!("toolbox was built without desktop notification support")
+}
+
+/// Record a snapshot in the `[storage]` database
+#[cfg(feature = "sqlite")]
+fn save_snapshot_to_store(db: &Path, lockfile: &Path, snapshot: &Snapshot) -> Result<()> {
+    let lockfile = std::path::absolute(lockfile)?;
+    toolbox_core::store::Store::open(db)?.save_snapshot(&lockfile, snapshot)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn save_snapshot_to_store(_db: &Path, _lockfile: &Path, _snapshot: &Snapshot) -> Result<()> {
+    anyhow::bail!("toolbox was built without SQLite support")
+}
+
+#[cfg(feature = "plugins")]
+fn format_with_plugin(config: &Config, name: &str, info: &ToolboxInfo) -> Result<String> {
+    let plugin_config = config.plugins.get(name).ok_or_else(|| {
+        anyhow::anyhow!("Unknown plugin: {} (add a [plugins.{}] table)", name, name)
+    })?;
+    let plugin = toolbox_core::plugin::WasmPlugin::load(name, plugin_config)?;
+    if !plugin.can_format() {
+        anyhow::bail!("Plugin {} has no formatter", name);
+    }
+    Ok(plugin.format(info)?)
+}
+
+#[cfg(not(feature = "plugins"))]
+fn format_with_plugin(_config: &Config, _name: &str, _info: &ToolboxInfo) -> Result<String> {
+    anyhow::bail!("toolbox was built without WASM plugin support")
+}
+
+/// Edit a copy of the config in the user's editor and only replace the
+/// config once the copy validates (like visudo)
+fn edit_config(config_path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+
+    let original = if config_path.exists() {
+        std::fs::read_to_string(config_path)?
+    } else {
+        toml::to_string_pretty(&Config::default())?
+    };
+    let dir = config_path.parent().unwrap_or_else(|| Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    // Keep the .toml extension so editors pick the right syntax
+    let draft = dir.join(".config.edit.toml");
+    std::fs::write(&draft, &original)?;
+
+    let result = edit_until_valid(&editor, &draft, config_path, &original);
+    let _ = std::fs::remove_file(&draft);
+    result
+}
+
+/// Re-open the draft until it validates or the user gives up, then save it
+fn edit_until_valid(editor: &str, draft: &Path, config_path: &Path, original: &str) -> Result<()> {
+    let mut editor_parts = editor.split_whitespace();
+    let editor_cmd = editor_parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No editor configured (set $EDITOR)"))?;
+    let editor_args: Vec<&str> = editor_parts.collect();
+
+    loop {
+        let status = std::process::Command::new(editor_cmd)
+            .args(&editor_args)
+            .arg(draft)
+            .status()
+            .map_err(|e| anyhow::anyhow!("Could not run editor {}: {}", editor, e))?;
+        if !status.success() {
+            anyhow::bail!("Editor exited with {}", status);
+        }
+
+        let edited = std::fs::read_to_string(draft)?;
+        let issues = validate_config(&edited);
+        if issues.is_empty() {
+            if edited == original && config_path.exists() {
+                println!("No changes");
+            } else {
+                std::fs::write(config_path, edited)?;
+                println!("Saved {}", config_path.display());
+            }
+            return Ok(());
+        }
+
+        for issue in &issues {
+            eprintln!("{}:{}", config_path.display(), issue);
+        }
+        eprint!("Edit again? [Y/n] ");
+        let mut answer = String::new();
+        if std::io::stdin().read_line(&mut answer)? == 0 || answer.trim().eq_ignore_ascii_case("n")
+        {
+            anyhow::bail!("Config not saved: {} problem(s)", issues.len());
+        }
+    }
+}
+
+/// Install the log subscriber: warnings only by default, -v debug, -vv trace
+/// Logs never go to stdout, which carries the output the plugin parses.
+fn init_logging(verbose: u8, log_file: Option<&Path>) -> Result<()> {
+    let level = match (verbose, log_file.is_some()) {
+        (0, false) => tracing::Level::WARN,
+        (0, true) | (1, _) => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_target(false);
+
+    if let Some(path) = log_file {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Could not open log file {}: {}", path.display(), e))?;
+        builder
+            .with_ansi(false)
+            .with_writer(std::sync::Mutex::new(file))
+            .init();
+    } else {
+        builder.with_writer(std::io::stderr).init();
+    }
+    Ok(())
+}
+
+/// Load the config (`--config` or the default path) with the
+/// `[[dir_overrides]]` of the working directory (`--dir` or the current one)
+/// and the `--profile`
+fn load_config(cli: &Cli) -> Result<Config> {
+    let mut config = match cli.config {
+        Some(ref path) => Config::load_from_path(path)?,
+        None => Config::load()?,
+    };
+    let dir = working_dir(cli)?;
+    let trusted = match TrustStore::default_path() {
+        Some(path) => TrustStore::load(&path)?,
+        None => TrustStore::default(),
+    };
+    config.apply_project_config(&dir, &trusted)?;
+    config.apply_dir_overrides(&dir)?;
+    if let Some(ref profile) = cli.profile {
+        config.apply_profile(profile)?;
+    }
+    Ok(config)
+}
+
+/// The working directory: `--dir` (relative to the current one) or the current one
+fn working_dir(cli: &Cli) -> Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    Ok(match cli.dir {
+        Some(ref dir) => cwd.join(dir),
+        None => cwd,
+    })
+}
+
+/// Watches the files `load_config` reads and reloads the config through it
+fn config_watcher(cli: &Cli) -> Result<ConfigWatcher> {
+    let args = cli.clone();
+    Ok(ConfigWatcher::new(cli.config.clone())
+        .with_project_dir(working_dir(cli)?)
+        .with_loader(move || load_config(&args).map_err(config_error)))
+}
+
+/// Loads the config `load_config` would with another `--dir` (`None`: the
+/// given one), for servers answering requests about any directory
+fn dir_config_loader(
+    cli: &Cli,
+) -> impl Fn(Option<&str>) -> toolbox_core::error::Result<Config> + 'static {
+    let args = cli.clone();
+    move |dir| {
+        let mut args = args.clone();
+        if let Some(dir) = dir {
+            args.dir = Some(dir.to_string());
+        }
+        load_config(&args).map_err(config_error)
+    }
+}
+
+/// A `load_config` error as a config error of the core crate
+fn config_error(err: anyhow::Error) -> ToolboxError {
+    match err.downcast::<ToolboxError>() {
+        Ok(err) => err,
+        Err(err) => ToolboxError::Config(format!("{:#}", err)),
+    }
+}
+
+/// Use the configured language for labels and messages (English if the
+/// config can't be loaded; the error surfaces where the config is used)
+fn set_language(cli: &Cli) {
+    let config = match cli.config {
+        Some(ref path) => Config::load_from_path(path),
+        None => Config::load(),
+    };
+    if let Ok(config) = config {
+        messages::set_language(config.language);
+    }
+}
+
+/// Log config problems (unknown keys with suggestions, bad regexes, ...) as warnings
+fn lint_config(cli: &Cli) {
+    let Some(path) = cli.config.clone().or_else(Config::find_config_path) else {
+        return;
+    };
+    if ConfigFormat::from_path(&path) != ConfigFormat::Toml {
+        return;
+    }
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    for issue in validate_config(&content) {
+        tracing::warn!("{}:{}", path.display(), issue);
+    }
+}
+
+/// History journal to record to, if enabled in config
+fn history_journal(config: &Config) -> Option<History> {
+    if !config.history.enabled {
+        return None;
+    }
+    History::from_config(config)
+        .map_err(|e| tracing::warn!(error = %e, "could not open history"))
+        .ok()
+        .flatten()
+}
+
+/// Key of a `toolbox prompt` result: one per directory, config file, profile
+/// and set of flags changing what is detected
+fn prompt_cache_key(cli: &Cli, dir: Option<&str>) -> String {
+    let sections = cli.sections.as_ref().map(|sections| {
+        sections
+            .iter()
+            .map(|section| format!("{:?}", section))
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+    format!(
+        "{}\n{}\nprofile={}\ntools_only={}\nextras_only={}\nsections={}",
+        dir.unwrap_or_default(),
+        cli.config.as_deref().unwrap_or(Path::new("")).display(),
+        cli.profile.as_deref().unwrap_or_default(),
+        cli.tools_only,
+        cli.extras_only,
+        sections.unwrap_or_default()
+    )
+}
+
+/// Re-run this `toolbox prompt` in the background to refresh its cached result
+fn spawn_prompt_refresh() -> std::io::Result<()> {
+    std::process::Command::new(std::env::current_exe()?)
+        .args(std::env::args_os().skip(1))
+        .arg("--update-cache")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// `--format` value of an output format (its formatter's registry name)
+fn format_name(format: OutputFormat) -> String {
+    format
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// Output formats and long flags of this CLI
+fn capabilities() -> Capabilities {
+    let formats = OutputFormat::value_variants()
+        .iter()
+        .map(|format| format_name(*format))
+        .collect();
+    let flags = Cli::command()
+        .get_arguments()
+        .filter_map(|arg| arg.get_long())
+        .map(|long| format!("--{}", long))
+        .collect();
+    Capabilities::new(formats, flags)
+}
+
+/// Default lockfile location: toolbox.lock.json in --dir or the current directory
+fn default_lockfile(cli: &Cli) -> PathBuf {
+    cli.dir
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(DEFAULT_SNAPSHOT_FILE)
+}
+
+/// Sample data for theme previews, with a clean or dirty git repository
+fn preview_info(dirty: bool) -> ToolboxInfo {
+    let tool = |name: &str, version: &str, icon: &str| {
+        ToolInfo::available(name.to_string(), version.to_string()).with_icon(Some(icon.to_string()))
+    };
+
+    ToolboxInfo {
+        current_dir: Some("~/projects/demo".to_string()),
+        git: Some(GitInfo {
+            branch: "main".to_string(),
+            modified_count: dirty.then_some(2),
+            staged_count: None,
+            untracked_count: dirty.then_some(1),
+            is_dirty: dirty,
+            ahead: None,
+            behind: None,
+        }),
+        tools: vec![
+            tool("Node.js", "22.11.0", "⬢"),
+            tool("Python", "3.12.7", "🐍"),
+            tool("Rust", "1.82.0", "🦀"),
+        ],
+        virtual_env: Some(".venv".to_string()),
+        ..Default::default()
+    }
+}
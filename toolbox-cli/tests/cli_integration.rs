@@ -23,6 +23,15 @@ fn test_default_output_compact() {
     toolbox_cmd().arg("--compact").assert().success();
 }
 
+#[test]
+fn test_default_output_no_compact() {
+    toolbox_cmd().arg("--no-compact").assert().success();
+    toolbox_cmd()
+        .args(["--compact", "--no-compact"])
+        .assert()
+        .failure();
+}
+
 // --- JSON output ---
 
 #[test]
@@ -67,6 +76,82 @@ fn test_json_pretty_output_is_valid() {
     assert!(parsed.is_ok(), "Output is not valid JSON: {}", stdout);
 }
 
+#[test]
+fn test_jsonl_output_streams_tools() {
+    let temp_file = echo_tool_config();
+    let output = toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--no-cache", "--format", "jsonl"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let events: Vec<(&str, Option<&str>)> = lines
+        .iter()
+        .map(|line| (line["event"].as_str().unwrap(), line["name"].as_str()))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            ("tool", Some("Echo")),
+            ("tool", Some("Ghost")),
+            ("done", None)
+        ]
+    );
+    assert_eq!(lines[0]["version"], "2.0.0");
+    assert_eq!(lines[2]["tools"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_json_schema_version() {
+    let temp_file = echo_tool_config();
+    let json = |extra: &[&str]| {
+        let output = toolbox_cmd()
+            .args(["--config", temp_file.path().to_str().unwrap()])
+            .args(["--format", "json"])
+            .args(extra)
+            .output()
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    assert_eq!(json(&[])["schema_version"], 1);
+    assert_eq!(json(&["--schema", "v1"])["schema_version"], 1);
+
+    toolbox_cmd()
+        .args(["--format", "json", "--schema", "v9"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unsupported schema version: v9"));
+}
+
+#[test]
+fn test_json_with_diagnostics() {
+    let temp_file = echo_tool_config();
+    let tools = |extra: &[&str]| {
+        let output = toolbox_cmd()
+            .args(["--config", temp_file.path().to_str().unwrap()])
+            .args(["--no-cache", "--format", "json"])
+            .args(extra)
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["tools"].as_array().unwrap().clone()
+    };
+
+    assert!(tools(&[])[0].get("diagnostic").is_none());
+    let tools = tools(&["--with-diagnostics"]);
+    assert_eq!(tools[0]["name"], "Echo");
+    assert_eq!(tools[0]["diagnostic"]["status"], "Ok");
+    assert_eq!(tools[1]["name"], "Ghost");
+    assert_eq!(tools[1]["diagnostic"]["status"], "Error");
+    assert!(tools[1]["diagnostic"]["suggestion"].is_string());
+}
+
 // --- Powerline output ---
 
 #[test]
@@ -177,6 +262,152 @@ fn test_init_subcommand_force_overwrite() {
         .stdout(predicate::str::contains("Created config file"));
 }
 
+#[test]
+fn test_init_full_and_minimal() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    let init = |flag: &str| {
+        toolbox_cmd()
+            .args(["--config", path.to_str().unwrap(), "init", "--force", flag])
+            .assert()
+            .success();
+        std::fs::read_to_string(&path).unwrap()
+    };
+
+    let full = init("--full");
+    assert!(full.contains("# Display settings\n[display]\n"));
+    assert!(full.contains("# [[tool_overrides]]\n# name = "));
+    toolbox_cmd()
+        .args(["--config", path.to_str().unwrap(), "config", "validate"])
+        .assert()
+        .success();
+
+    let minimal = init("--minimal");
+    assert!(!minimal.contains("[display]"));
+    assert!(minimal.contains("config_version = "));
+
+    toolbox_cmd()
+        .args(["init", "--full", "--minimal"])
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_init_from_detect() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Node is enabled by default, Java isn't
+    let bin = tempfile::tempdir().unwrap();
+    for (program, output) in [("node", "v20.11.0"), ("java", "openjdk 21.0.2 2024-01-16")] {
+        let path = bin.path().join(program);
+        std::fs::write(&path, format!("#!/bin/sh\necho \"{}\"\n", output)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let dir = tempfile::tempdir().unwrap();
+
+    for (file, flag) in [
+        ("full.toml", "--full"),
+        ("minimal.toml", "--minimal"),
+        ("config.json", "--full"),
+    ] {
+        let path = dir.path().join(file);
+        toolbox_cmd()
+            .env("PATH", bin.path())
+            .args([
+                "--config",
+                path.to_str().unwrap(),
+                "init",
+                "--from-detect",
+                flag,
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Found 2 tools: Node, Java"));
+
+        let output = toolbox_cmd()
+            .env("PATH", bin.path())
+            .args(["--config", path.to_str().unwrap(), "--format", "json"])
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let tools: Vec<&str> = json["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(tools, ["Node", "Java"], "{}", file);
+    }
+}
+
+#[test]
+fn test_import() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join(".tool-versions"),
+        "java 21.0.2\njust 1.25.0\n",
+    )
+    .unwrap();
+    let starship = dir.path().join("starship.toml");
+    std::fs::write(&starship, "[python]\nsymbol = \"🐍 \"\n\n[git_branch]\n").unwrap();
+
+    // Printed by default, read from --dir
+    toolbox_cmd()
+        .args(["--dir", dir.path().to_str().unwrap(), "import", "asdf"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[[tool_overrides]]\nname = \"Java\"\nenabled = true\n",
+        ))
+        .stdout(predicate::str::contains(
+            "[[custom_tools]]\nname = \"just\"\ncommand = \"just --version\"\n",
+        ));
+    toolbox_cmd()
+        .args(["import", "starship", starship.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "name = \"Python\"\nenabled = true\nicon = \"🐍\"\n",
+        ))
+        .stderr(predicate::str::contains("Skipped git_branch"));
+
+    // --write keeps what the config file already has
+    let config = dir.path().join("config.toml");
+    std::fs::write(&config, "# mine\n[display]\ncompact = true\n").unwrap();
+    toolbox_cmd()
+        .args([
+            "--config",
+            config.to_str().unwrap(),
+            "--dir",
+            dir.path().to_str().unwrap(),
+        ])
+        .args(["import", "asdf", "--write"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 2 tools into:"));
+    let content = std::fs::read_to_string(&config).unwrap();
+    assert!(content.starts_with("# mine\n[display]\ncompact = true\n"));
+    assert!(content.contains("name = \"just\""));
+    toolbox_cmd()
+        .args([
+            "--config",
+            config.to_str().unwrap(),
+            "config",
+            "get",
+            "display.compact",
+        ])
+        .assert()
+        .success()
+        .stdout("true\n");
+
+    toolbox_cmd()
+        .args(["import", "rtx"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown import source"));
+}
+
 // --- Custom config file ---
 
 #[test]
@@ -238,6 +469,24 @@ fn test_invalid_config_file_errors() {
         .failure();
 }
 
+#[test]
+fn test_config_parse_error_shows_snippet() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(temp_file, "[display]\ncompact = \"yes\"").unwrap();
+
+    toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .env("NO_COLOR", "1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected a boolean"))
+        .stderr(predicate::str::contains("compact = \"yes\""))
+        .stderr(predicate::str::contains("here"))
+        .stderr(predicate::str::contains(
+            "help: booleans are written `true` or `false`",
+        ));
+}
+
 // --- --help and --version ---
 
 #[test]
@@ -260,6 +509,25 @@ fn test_version_flag() {
         .stdout(predicate::str::contains("toolbox"));
 }
 
+#[test]
+fn test_capabilities_flag() {
+    let output = toolbox_cmd()
+        .arg("--capabilities")
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(parsed["protocol"], 1);
+    let formats = parsed["formats"].as_array().unwrap();
+    assert!(formats.contains(&serde_json::json!("json-pretty")));
+    let flags = parsed["flags"].as_array().unwrap();
+    for flag in ["--format", "--max-width", "--theme-colors", "--only"] {
+        assert!(flags.contains(&serde_json::json!(flag)), "{} missing", flag);
+    }
+}
+
 // --- Working directory ---
 
 #[test]
@@ -270,6 +538,128 @@ fn test_working_dir_option() {
         .success();
 }
 
+#[test]
+fn test_working_dir_expands_home() {
+    let home = tempfile::tempdir().unwrap();
+    let output = toolbox_cmd()
+        .env("HOME", home.path())
+        .args(["--dir", "~", "--format", "json"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["current_dir"], home.path().to_str().unwrap());
+}
+
+#[test]
+fn test_dir_overrides() {
+    let root = tempfile::tempdir().unwrap();
+    let infra = root.path().join("infra/modules");
+    std::fs::create_dir_all(&infra).unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "App"
+command = "echo v1.0.0"
+enabled = true
+
+[[custom_tools]]
+name = "Infra"
+command = "echo v2.0.0"
+enabled = false
+
+[[dir_overrides]]
+path = "{}/infra/**"
+enable_tools = ["Infra"]
+disable_tools = ["App"]
+"#,
+        root.path().display()
+    )
+    .unwrap();
+    let tools = |dir: &std::path::Path| {
+        let output = toolbox_cmd()
+            .args(["--config", temp_file.path().to_str().unwrap()])
+            .args(["--dir", dir.to_str().unwrap(), "--format", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(tools(root.path()), ["App"]);
+    assert_eq!(tools(&infra), ["Infra"]);
+}
+
+#[test]
+fn test_profile() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "App"
+command = "echo v1.0.0"
+enabled = true
+
+[[custom_tools]]
+name = "Infra"
+command = "echo v2.0.0"
+enabled = false
+
+[profiles.infra]
+enable_tools = ["Infra"]
+disable_tools = ["App"]
+"#
+    )
+    .unwrap();
+    let json = |args: &[&str]| {
+        let output = toolbox_cmd()
+            .args([
+                "--config",
+                temp_file.path().to_str().unwrap(),
+                "--format",
+                "json",
+            ])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let json_default = json(&[]);
+    assert_eq!(json_default["tools"][0]["name"], "App");
+    assert_eq!(json_default["profiles"], serde_json::json!(["infra"]));
+    assert!(json_default.get("profile").is_none());
+
+    let json_infra = json(&["--profile", "infra"]);
+    assert_eq!(json_infra["tools"].as_array().unwrap().len(), 1);
+    assert_eq!(json_infra["tools"][0]["name"], "Infra");
+    assert_eq!(json_infra["profile"], "infra");
+
+    toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--profile", "web"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unknown profile: web (available: infra)",
+        ));
+}
+
 // --- Color modes ---
 
 #[test]
@@ -287,6 +677,38 @@ fn test_color_never() {
     toolbox_cmd().args(["--color", "never"]).assert().success();
 }
 
+#[test]
+fn test_no_color_env_disables_powerline_color() {
+    toolbox_cmd()
+        .args(["--powerline", "--color", "auto"])
+        .env("NO_COLOR", "1")
+        .env("CLICOLOR_FORCE", "1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn test_clicolor_force_env_enables_powerline_color() {
+    toolbox_cmd()
+        .args(["--powerline"])
+        .env_remove("NO_COLOR")
+        .env("CLICOLOR_FORCE", "1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b["));
+}
+
+#[test]
+fn test_color_always_overrides_no_color_env() {
+    toolbox_cmd()
+        .args(["--powerline", "--color", "always"])
+        .env("NO_COLOR", "1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b["));
+}
+
 // --- Theme flag ---
 
 #[test]
@@ -321,6 +743,43 @@ fn test_theme_solarized_succeeds() {
         .success();
 }
 
+#[test]
+fn test_theme_additional_presets_succeed() {
+    for preset in ["nord", "dracula", "gruvbox", "catppuccin"] {
+        toolbox_cmd()
+            .args(["--powerline", "--theme", preset, "--color", "always"])
+            .assert()
+            .success();
+    }
+}
+
+#[test]
+fn test_theme_colors_override_applied() {
+    let output = toolbox_cmd()
+        .args([
+            "--powerline",
+            "--color",
+            "always",
+            "--theme-colors",
+            "directory_bg=#010203",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("\x1b[48;2;1;2;3m"));
+}
+
+#[test]
+fn test_theme_colors_invalid_spec_fails() {
+    toolbox_cmd()
+        .args(["--theme-colors", "directory_bg=notacolor"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid color"));
+}
+
 #[test]
 fn test_theme_with_config_preset() {
     let mut temp_file = NamedTempFile::new().unwrap();
@@ -376,103 +835,140 @@ preset = "dark"
         .success();
 }
 
-// --- Doctor subcommand ---
-
 #[test]
-fn test_doctor_subcommand_succeeds() {
-    toolbox_cmd()
-        .arg("doctor")
-        .assert()
-        .success()
-        .stdout(predicate::str::contains("Toolbox Doctor"))
-        .stdout(predicate::str::contains("Tool Status:"))
-        .stdout(predicate::str::contains("tools checked"));
-}
+fn test_display_sections_from_config() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
 
-#[test]
-fn test_doctor_shows_config_info() {
-    toolbox_cmd()
-        .arg("doctor")
-        .assert()
-        .success()
-        .stdout(predicate::str::contains("Config:"));
-}
+[display]
+sections = ["venv", "tools"]
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#
+    )
+    .unwrap();
 
-#[test]
-fn test_doctor_json_output_is_valid() {
     let output = toolbox_cmd()
-        .args(["doctor", "--json"])
+        .env("VIRTUAL_ENV", "/tmp/sectionsenv")
+        .args(["--config", temp_file.path().to_str().unwrap(), "--no-icons"])
         .output()
         .expect("failed to execute");
     assert!(output.status.success());
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: Result<serde_json::Value, _> = serde_json::from_str(stdout.trim());
-    assert!(
-        parsed.is_ok(),
-        "Doctor JSON output is not valid: {}",
-        stdout
-    );
-
-    let value = parsed.unwrap();
-    assert!(value.get("total").is_some());
-    assert!(value.get("ok_count").is_some());
-    assert!(value.get("tools").is_some());
+    let venv = stdout.find("venv: sectionsenv").unwrap();
+    let echo = stdout.find("Echo 2.0.0").unwrap();
+    assert!(venv < echo);
 }
 
 #[test]
-fn test_doctor_json_has_tool_details() {
-    let output = toolbox_cmd()
-        .args(["doctor", "--json"])
-        .output()
-        .expect("failed to execute");
-    assert!(output.status.success());
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
-    let tools = parsed["tools"].as_array().unwrap();
+fn test_powerline_max_width_fits_output() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
 
-    // With default config, there should be tools
-    assert!(!tools.is_empty());
+[[custom_tools]]
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
 
-    // Each tool should have required fields
-    for tool in tools {
-        assert!(tool.get("name").is_some());
-        assert!(tool.get("status").is_some());
-        assert!(tool.get("command").is_some());
-        assert!(tool.get("enabled").is_some());
-    }
+[extras]
+git_branch = false
+git_status = false
+current_directory = true
+virtual_env = false
+"#
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--powerline",
+            "--single-line",
+            "--no-icons",
+            "--color",
+            "never",
+            "--max-width",
+            "8",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim_end(), "Echo 2");
 }
 
 #[test]
-fn test_doctor_with_custom_config() {
+fn test_powerline_split_layout() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
 use_default_tools = false
 
+[display]
+sections = ["tools", "venv"]
+right_sections = ["venv"]
+
 [[custom_tools]]
 name = "Echo"
-command = "echo v1.0.0"
+command = "echo v2.0.0"
 parse_regex = 'v?(\d+\.\d+\.\d+)'
 enabled = true
 "#
     )
     .unwrap();
 
-    let path = temp_file.path().to_path_buf();
+    let output = toolbox_cmd()
+        .env("VIRTUAL_ENV", "/tmp/splitenv")
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--powerline",
+            "--single-line",
+            "--no-icons",
+            "--color",
+            "never",
+            "--layout",
+            "split",
+            "--max-width",
+            "40",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.trim_end_matches('\n');
+    assert!(line.starts_with("Echo 2.0.0 "), "{}", line);
+    assert!(line.ends_with(" venv: splitenv"), "{}", line);
+    assert_eq!(line.chars().count(), 40);
+}
 
+#[test]
+fn test_invalid_layout_fails() {
     toolbox_cmd()
-        .args(["--config", path.to_str().unwrap(), "doctor"])
+        .args(["--powerline", "--layout", "center"])
         .assert()
-        .success()
-        .stdout(predicate::str::contains("Echo"))
-        .stdout(predicate::str::contains("1 tools checked: 1 ok"));
+        .failure()
+        .stderr(predicate::str::contains("Invalid layout"));
 }
 
 #[test]
-fn test_doctor_shows_unavailable_tools() {
+fn test_show_missing_flag() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
@@ -480,157 +976,3735 @@ fn test_doctor_shows_unavailable_tools() {
 use_default_tools = false
 
 [[custom_tools]]
-name = "FakeTool"
-command = "nonexistent_fakecmd_12345 --version"
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+
+[[custom_tools]]
+name = "Ghost"
+command = "nonexistent-toolbox-command-xyz --version"
 enabled = true
 "#
     )
     .unwrap();
+    let path = temp_file.path().to_str().unwrap();
 
-    let path = temp_file.path().to_path_buf();
+    toolbox_cmd()
+        .args(["--config", path, "--no-icons"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Ghost").not());
 
     toolbox_cmd()
-        .args(["--config", path.to_str().unwrap(), "doctor"])
+        .args(["--config", path, "--no-icons", "--show-missing"])
         .assert()
         .success()
-        .stdout(predicate::str::contains("ERR"))
-        .stdout(predicate::str::contains("FakeTool"))
-        .stdout(predicate::str::contains(
-            "1 tools checked: 0 ok, 0 warning, 1 error",
-        ));
+        .stdout(predicate::str::contains("Echo 2.0.0"))
+        .stdout(predicate::str::contains("Ghost ✗"));
 }
 
-// --- Cache options ---
-
 #[test]
-fn test_no_cache_flag_succeeds() {
-    toolbox_cmd().arg("--no-cache").assert().success();
-}
+fn test_only_and_sections_flags() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
 
-#[test]
-fn test_refresh_flag_succeeds() {
-    toolbox_cmd().arg("--refresh").assert().success();
-}
+[[custom_tools]]
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
 
-#[test]
-fn test_no_cache_with_json_output() {
-    let output = toolbox_cmd()
-        .args(["--no-cache", "--format", "json"])
-        .output()
-        .expect("failed to execute");
-    assert!(output.status.success());
+[[custom_tools]]
+name = "Other"
+short_name = "oth"
+command = "echo v3.1.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = false
+"#
+    )
+    .unwrap();
+    let path = temp_file.path().to_str().unwrap();
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: Result<serde_json::Value, _> = serde_json::from_str(stdout.trim());
-    assert!(parsed.is_ok(), "Output is not valid JSON: {}", stdout);
-}
+    // Disabled tools are detected when named explicitly
+    toolbox_cmd()
+        .args(["--config", path, "--no-icons", "--only", "oth"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("oth 3.1.0"))
+        .stdout(predicate::str::contains("Echo").not());
 
-#[test]
-fn test_refresh_with_json_output() {
-    let output = toolbox_cmd()
-        .args(["--refresh", "--format", "json"])
-        .output()
-        .expect("failed to execute");
-    assert!(output.status.success());
+    toolbox_cmd()
+        .args(["--config", path, "--only", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown tool: nope"));
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: Result<serde_json::Value, _> = serde_json::from_str(stdout.trim());
-    assert!(parsed.is_ok(), "Output is not valid JSON: {}", stdout);
+    toolbox_cmd()
+        .args(["--config", path, "--no-icons", "--sections", "tools"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Echo 2.0.0"));
+
+    toolbox_cmd()
+        .args(["--config", path, "--sections", "tools,network"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid section: network"));
 }
 
 #[test]
-fn test_no_cache_and_refresh_combined() {
-    // --no-cache takes priority: cache is disabled, --refresh is a no-op
+fn test_tools_only_and_extras_only_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("toolbox-project");
+    std::fs::create_dir(&project).unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#
+    )
+    .unwrap();
+    let run = |flag: &str| {
+        toolbox_cmd()
+            .args(["--config", temp_file.path().to_str().unwrap(), "--no-icons"])
+            .args(["--dir", project.to_str().unwrap(), flag])
+            .assert()
+            .success()
+    };
+
+    run("--tools-only")
+        .stdout(predicate::str::contains("Echo 2.0.0"))
+        .stdout(predicate::str::contains("toolbox-project").not());
+    run("--extras-only")
+        .stdout(predicate::str::contains("toolbox-project"))
+        .stdout(predicate::str::contains("Echo").not());
+
     toolbox_cmd()
-        .args(["--no-cache", "--refresh"])
+        .args(["--tools-only", "--extras-only"])
         .assert()
-        .success();
+        .failure();
 }
 
 #[test]
-fn test_cache_disabled_in_config() {
+fn test_group_tools_from_config() {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
 use_default_tools = false
 
-[cache]
-enabled = false
+[display]
+sections = ["tools"]
+group_tools = true
 
 [[custom_tools]]
 name = "Echo"
-command = "echo v1.0.0"
+command = "echo v2.0.0"
 parse_regex = 'v?(\d+\.\d+\.\d+)'
 enabled = true
 
-[extras]
-git_branch = false
-git_status = false
-current_directory = false
-virtual_env = false
-system_memory = false
-system_cpu = false
+[[custom_tools]]
+name = "Other"
+command = "echo v3.1.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
 "#
     )
     .unwrap();
 
-    let path = temp_file.path().to_path_buf();
-
     let output = toolbox_cmd()
-        .args(["--config", path.to_str().unwrap(), "--format", "json"])
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--powerline",
+            "--single-line",
+            "--no-icons",
+            "--color",
+            "always",
+        ])
         .output()
         .expect("failed to execute");
     assert!(output.status.success());
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
-    let tools = parsed["tools"].as_array().unwrap();
-    assert_eq!(tools.len(), 1);
-    assert_eq!(tools[0]["name"], "Echo");
-    assert_eq!(tools[0]["version"], "1.0.0");
+    assert!(stdout.contains("Echo 2.0.0 \u{e0b1} Other 3.1.0"));
+    assert_eq!(stdout.matches('\u{e0b0}').count(), 1);
 }
 
-#[test]
-fn test_cache_custom_ttl_in_config() {
+fn echo_tool_config() -> NamedTempFile {
     let mut temp_file = NamedTempFile::new().unwrap();
     writeln!(
         temp_file,
         r#"
 use_default_tools = false
 
-[cache]
-enabled = true
-default_ttl = 60
-
 [[custom_tools]]
 name = "Echo"
 command = "echo v2.0.0"
 parse_regex = 'v?(\d+\.\d+\.\d+)'
 enabled = true
 
-[extras]
-git_branch = false
-git_status = false
-current_directory = false
-virtual_env = false
-system_memory = false
-system_cpu = false
+[[custom_tools]]
+name = "Ghost"
+command = "nonexistent-toolbox-command-xyz --version"
+enabled = true
 "#
     )
     .unwrap();
+    temp_file
+}
 
-    let path = temp_file.path().to_path_buf();
+#[test]
+fn test_markdown_format() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "markdown",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("| Tool | Version | Path |"))
+        .stdout(predicate::str::is_match(r"\| Echo \| 2\.0\.0 \| `[^`]*echo` \|").unwrap())
+        .stdout(predicate::str::contains("| Ghost | ✗ not found |"));
+}
+
+#[test]
+fn test_html_format() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "html",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("<!DOCTYPE html>"))
+        .stdout(predicate::str::contains("<td>Echo</td><td>2.0.0</td>"));
+}
+
+#[test]
+fn test_csv_format() {
+    let temp_file = echo_tool_config();
 
     let output = toolbox_cmd()
-        .args(["--config", path.to_str().unwrap(), "--format", "json"])
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "csv",
+        ])
         .output()
         .expect("failed to execute");
     assert!(output.status.success());
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let parsed: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
-    let tools = parsed["tools"].as_array().unwrap();
-    assert_eq!(tools.len(), 1);
-    assert_eq!(tools[0]["version"], "2.0.0");
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "name,version,available,path,error");
+    assert!(lines[1].starts_with("Echo,2.0.0,true,"));
+    assert!(lines[2].starts_with("Ghost,,false,,"));
+}
+
+#[test]
+fn test_tsv_format() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "tsv",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "name\tversion\tavailable\tpath\terror\n",
+        ))
+        .stdout(predicate::str::contains("Echo\t2.0.0\ttrue\t"));
+}
+
+// --- Export subcommand ---
+
+#[test]
+fn test_export_sbom_cyclonedx() {
+    let temp_file = echo_tool_config();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "export",
+            "--sbom",
+            "cyclonedx",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["bomFormat"], "CycloneDX");
+    let components = doc["components"].as_array().unwrap();
+    assert_eq!(components.len(), 1);
+    assert_eq!(components[0]["name"], "Echo");
+    assert_eq!(components[0]["version"], "2.0.0");
+}
+
+#[test]
+fn test_export_sbom_spdx() {
+    let temp_file = echo_tool_config();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "export",
+            "--sbom",
+            "spdx",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+    assert_eq!(doc["packages"][0]["name"], "Echo");
+}
+
+#[test]
+fn test_export_invalid_sbom_format_fails() {
+    toolbox_cmd()
+        .args(["export", "--sbom", "swid"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid SBOM format"));
+}
+
+// --- Snapshot / diff subcommands ---
+
+#[test]
+fn test_snapshot_then_diff_no_drift() {
+    let temp_file = echo_tool_config();
+    let dir = tempfile::tempdir().unwrap();
+    let config = temp_file.path().to_str().unwrap();
+    let dir_str = dir.path().to_str().unwrap();
+
+    toolbox_cmd()
+        .args(["--config", config, "--dir", dir_str, "snapshot"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Recorded 1 tools"));
+
+    let lock = std::fs::read_to_string(dir.path().join("toolbox.lock.json")).unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(&lock).unwrap();
+    assert_eq!(parsed["tools"]["Echo"], "2.0.0");
+
+    toolbox_cmd()
+        .args(["--config", config, "--dir", dir_str, "diff"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No drift"));
+}
+
+#[test]
+fn test_diff_reports_drift_and_fails() {
+    let temp_file = echo_tool_config();
+    let dir = tempfile::tempdir().unwrap();
+    let lockfile = dir.path().join("env.lock.json");
+    std::fs::write(
+        &lockfile,
+        r#"{"created_at": "2024-01-01T00:00:00Z", "tools": {"Echo": "1.0.0", "Gone": "3.0.0"}}"#,
+    )
+    .unwrap();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--color",
+            "never",
+            "diff",
+            lockfile.to_str().unwrap(),
+        ])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("~ Echo 1.0.0 -> 2.0.0"))
+        .stdout(predicate::str::contains("- Gone 3.0.0"));
+}
+
+#[test]
+fn test_output_colors_versions_by_lockfile_drift() {
+    let temp_file = echo_tool_config();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("toolbox.lock.json"),
+        r#"{"created_at": "2024-01-01T00:00:00Z", "tools": {"Echo": "1.0.0"}}"#,
+    )
+    .unwrap();
+    let config = temp_file.path().to_str().unwrap();
+    let dir_str = dir.path().to_str().unwrap();
+
+    toolbox_cmd()
+        .args(["--config", config, "--dir", dir_str, "--color", "always"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[33m2.0.0\x1b[0m"));
+
+    toolbox_cmd()
+        .args(["--config", config, "--dir", dir_str, "--color", "never"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Echo 2.0.0"))
+        .stdout(predicate::str::contains("\x1b[").not());
+
+    let output = toolbox_cmd()
+        .args(["--config", config, "--dir", dir_str, "--format", "json"])
+        .output()
+        .expect("failed to execute");
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(parsed["tools"][0]["drift"], "changed");
+}
+
+#[test]
+fn test_diff_missing_lockfile_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    toolbox_cmd()
+        .args(["--dir", dir.path().to_str().unwrap(), "diff"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Could not read lockfile"));
+}
+
+// --- Watch subcommand ---
+
+#[test]
+fn test_watch_single_check() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "watch",
+            "--count",
+            "1",
+            "--interval",
+            "1",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Watching 1 tools every 1s"));
+}
+
+#[test]
+fn test_watch_rejects_zero_interval() {
+    toolbox_cmd()
+        .args(["watch", "--interval", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--interval"));
+}
+
+#[test]
+fn test_watch_no_changes_between_checks() {
+    let temp_file = echo_tool_config();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "watch",
+            "--count",
+            "2",
+            "--interval",
+            "1",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+}
+
+// --- History subcommand ---
+
+fn history_config(dir: &std::path::Path, command: &str) -> std::path::PathBuf {
+    let config = dir.join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+use_default_tools = false
+
+[history]
+enabled = true
+path = "{}"
+
+[[custom_tools]]
+name = "Echo"
+command = "{}"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#,
+            dir.join("history.jsonl").display(),
+            command
+        ),
+    )
+    .unwrap();
+    config
+}
+
+#[test]
+fn test_history_records_version_changes() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let config = history_config(dir.path(), "echo v1.0.0");
+    toolbox_cmd()
+        .args(["--config", config.to_str().unwrap()])
+        .assert()
+        .success();
+    // Unchanged version: not recorded again
+    toolbox_cmd()
+        .args(["--config", config.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let config = history_config(dir.path(), "echo v1.1.0");
+    toolbox_cmd()
+        .args(["--config", config.to_str().unwrap()])
+        .assert()
+        .success();
+
+    toolbox_cmd()
+        .args(["--config", config.to_str().unwrap(), "history", "echo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Echo  1.0.0 (first seen)"))
+        .stdout(predicate::str::contains("Echo  1.0.0 -> 1.1.0"));
+
+    let output = toolbox_cmd()
+        .args(["--config", config.to_str().unwrap(), "history", "--json"])
+        .output()
+        .expect("failed to execute");
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_history_empty() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = history_config(dir.path(), "echo v1.0.0");
+
+    toolbox_cmd()
+        .args(["--config", config.to_str().unwrap(), "history"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No version changes recorded"));
+}
+
+#[test]
+fn test_history_sqlite_storage() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("toolbox.db");
+    let config = dir.path().join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+use_default_tools = false
+
+[history]
+enabled = true
+
+[storage]
+backend = "sqlite"
+path = "{}"
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v1.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#,
+            db.display()
+        ),
+    )
+    .unwrap();
+
+    if cfg!(feature = "sqlite") {
+        toolbox_cmd()
+            .args(["--config", config.to_str().unwrap()])
+            .assert()
+            .success();
+        toolbox_cmd()
+            .args(["--config", config.to_str().unwrap(), "history", "echo"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Echo  1.0.0 (first seen)"));
+        assert!(db.exists());
+    } else {
+        toolbox_cmd()
+            .args(["--config", config.to_str().unwrap(), "history"])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "needs toolbox built with the `sqlite` feature",
+            ));
+    }
+}
+
+// --- Themes subcommand ---
+
+fn user_theme_config() -> NamedTempFile {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r##"
+[theme]
+preset = "work"
+
+[themes.work]
+preset = "nord"
+
+[themes.work.custom]
+directory_bg = "#010203"
+"##
+    )
+    .unwrap();
+    temp_file
+}
+
+#[test]
+fn test_themes_list_shows_presets_and_user_themes() {
+    let temp_file = user_theme_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "themes",
+            "list",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("dracula"))
+        .stdout(predicate::str::contains("catppuccin"))
+        .stdout(predicate::str::contains("* work (based on nord)"));
+}
+
+#[test]
+fn test_themes_preview_user_theme() {
+    let temp_file = user_theme_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--color",
+            "always",
+            "themes",
+            "preview",
+            "work",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work:"))
+        .stdout(predicate::str::contains("\x1b[48;2;1;2;3m"))
+        .stdout(predicate::str::contains("Node.js"));
+}
+
+#[test]
+fn test_themes_preview_all_without_color() {
+    let temp_file = user_theme_config();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--color",
+            "never",
+            "themes",
+            "preview",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('\x1b'));
+    for name in ["default:", "gruvbox:", "work:"] {
+        assert!(stdout.contains(name), "missing {} in preview", name);
+    }
+}
+
+#[test]
+fn test_themes_preview_unknown_theme_fails() {
+    let temp_file = user_theme_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "themes",
+            "preview",
+            "nope",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Unknown theme: nope"));
+}
+
+#[test]
+fn test_user_theme_selected_by_config() {
+    let temp_file = user_theme_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--powerline",
+            "--color",
+            "always",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\x1b[48;2;1;2;3m"));
+}
+
+// --- Doctor subcommand ---
+
+#[test]
+fn test_doctor_subcommand_succeeds() {
+    toolbox_cmd()
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Toolbox Doctor"))
+        .stdout(predicate::str::contains("Tool Status:"))
+        .stdout(predicate::str::contains("tools checked"));
+}
+
+#[test]
+fn test_doctor_shows_config_info() {
+    toolbox_cmd()
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Config:"));
+}
+
+#[test]
+fn test_doctor_json_output_is_valid() {
+    let output = toolbox_cmd()
+        .args(["doctor", "--json"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parsed: Result<serde_json::Value, _> = serde_json::from_str(stdout.trim());
+    assert!(
+        parsed.is_ok(),
+        "Doctor JSON output is not valid: {}",
+        stdout
+    );
+
+    let value = parsed.unwrap();
+    assert!(value.get("total").is_some());
+    assert!(value.get("ok_count").is_some());
+    assert!(value.get("tools").is_some());
+}
+
+#[test]
+fn test_doctor_json_has_tool_details() {
+    let output = toolbox_cmd()
+        .args(["doctor", "--json"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parsed: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    let tools = parsed["tools"].as_array().unwrap();
+
+    // With default config, there should be tools
+    assert!(!tools.is_empty());
+
+    // Each tool should have required fields
+    for tool in tools {
+        assert!(tool.get("name").is_some());
+        assert!(tool.get("status").is_some());
+        assert!(tool.get("command").is_some());
+        assert!(tool.get("enabled").is_some());
+    }
+}
+
+#[test]
+fn test_doctor_with_custom_config() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v1.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let path = temp_file.path().to_path_buf();
+
+    toolbox_cmd()
+        .args(["--config", path.to_str().unwrap(), "doctor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Echo"))
+        .stdout(predicate::str::contains("1 tools checked: 1 ok"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_resolves_asdf_shims() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = tempfile::tempdir().unwrap();
+    let shims = root.path().join(".asdf/shims");
+    let bin = root.path().join("bin");
+    let app = root.path().join("app");
+    for dir in [&shims, &bin, &app] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    let installs = root.path().join(".asdf/installs");
+    for (path, body) in [
+        (shims.join("faketool"), "echo v9.1.0".to_string()),
+        (
+            bin.join("asdf"),
+            format!(
+                "case \"$1\" in\n\
+                 which) echo \"{}/fakeplugin/9.1.0/bin/$2\" ;;\n\
+                 current) echo \"$2 9.1.0 $PWD/.tool-versions\" ;;\n\
+                 *) exit 1 ;;\n\
+                 esac",
+                installs.display()
+            ),
+        ),
+    ] {
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let path = std::env::join_paths(
+        [shims.clone(), bin]
+            .into_iter()
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Fake"
+command = "faketool --version"
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .env_remove("ASDF_DATA_DIR")
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--dir", app.to_str().unwrap(), "doctor", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let fake = &summary["tools"][0];
+    assert_eq!(
+        fake["command_path"],
+        shims.join("faketool").to_str().unwrap()
+    );
+    assert_eq!(fake["shim"]["manager"], "asdf");
+    assert_eq!(
+        fake["shim"]["real_path"],
+        installs
+            .join("fakeplugin/9.1.0/bin/faketool")
+            .to_str()
+            .unwrap()
+    );
+    assert_eq!(fake["shim"]["plugin"], "fakeplugin");
+    assert_eq!(fake["shim"]["version"], "9.1.0");
+    assert_eq!(
+        fake["shim"]["source"],
+        app.join(".tool-versions").to_str().unwrap()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_flags_path_shadowing() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("bin");
+    let shims = root.path().join(".asdf/shims");
+    for (dir, version) in [(&bin, "1.0.0"), (&shims, "2.0.0")] {
+        std::fs::create_dir_all(dir).unwrap();
+        let tool = dir.join("faketool");
+        std::fs::write(&tool, format!("#!/bin/sh\necho v{}\n", version)).unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let path = std::env::join_paths(
+        [bin.clone(), shims.clone()]
+            .into_iter()
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Fake"
+command = "faketool --version"
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .env_remove("ASDF_DATA_DIR")
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["doctor", "--json"])
+        .output()
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let fake = &summary["tools"][0];
+    assert_eq!(fake["status"], "Warning");
+    assert_eq!(fake["version"], "v1.0.0");
+    let matches: Vec<&str> = fake["path_matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m.as_str().unwrap())
+        .collect();
+    let winner = bin.join("faketool");
+    let shim = shims.join("faketool");
+    assert_eq!(
+        matches,
+        vec![winner.to_str().unwrap(), shim.to_str().unwrap()]
+    );
+    assert!(fake["error_detail"]
+        .as_str()
+        .unwrap()
+        .contains("shadows the asdf binary"));
+    assert!(fake["suggestion"]
+        .as_str()
+        .unwrap()
+        .contains(&format!("move {} earlier", shims.display())));
+
+    toolbox_cmd()
+        .env("PATH", &path)
+        .env_remove("ASDF_DATA_DIR")
+        .args(["--config", temp_file.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .stdout(predicate::str::contains(format!(
+            "also in PATH: {}",
+            shim.display()
+        )));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tool_source_attribution() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join(".local/bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let tool = bin.join("faketool");
+    std::fs::write(&tool, "#!/bin/sh\necho v3.0.0\n").unwrap();
+    std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(bin.clone())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Fake"
+command = "faketool --version"
+enabled = true
+"#
+    )
+    .unwrap();
+    let config = temp_file.path().to_str().unwrap();
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .args(["--config", config, "--no-cache", "--format", "json"])
+        .output()
+        .unwrap();
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(info["tools"][0]["source"], "user");
+
+    toolbox_cmd()
+        .env("PATH", &path)
+        .args(["--config", config, "doctor"])
+        .assert()
+        .stdout(predicate::str::contains(format!(
+            "({}, user) v3.0.0",
+            tool.display()
+        )));
+}
+
+#[test]
+fn test_doctor_shows_unavailable_tools() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "FakeTool"
+command = "nonexistent_fakecmd_12345 --version"
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let path = temp_file.path().to_path_buf();
+
+    toolbox_cmd()
+        .args(["--config", path.to_str().unwrap(), "doctor"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ERR"))
+        .stdout(predicate::str::contains("FakeTool"))
+        .stdout(predicate::str::contains(
+            "1 tools checked: 0 ok, 0 warning, 1 error",
+        ));
+}
+
+// --- Cache options ---
+
+#[test]
+fn test_no_cache_flag_succeeds() {
+    toolbox_cmd().arg("--no-cache").assert().success();
+}
+
+#[test]
+fn test_refresh_flag_succeeds() {
+    toolbox_cmd().arg("--refresh").assert().success();
+}
+
+#[test]
+fn test_no_cache_with_json_output() {
+    let output = toolbox_cmd()
+        .args(["--no-cache", "--format", "json"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parsed: Result<serde_json::Value, _> = serde_json::from_str(stdout.trim());
+    assert!(parsed.is_ok(), "Output is not valid JSON: {}", stdout);
+}
+
+#[test]
+fn test_refresh_with_json_output() {
+    let output = toolbox_cmd()
+        .args(["--refresh", "--format", "json"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parsed: Result<serde_json::Value, _> = serde_json::from_str(stdout.trim());
+    assert!(parsed.is_ok(), "Output is not valid JSON: {}", stdout);
+}
+
+#[test]
+fn test_no_cache_and_refresh_combined() {
+    // --no-cache takes priority: cache is disabled, --refresh is a no-op
+    toolbox_cmd()
+        .args(["--no-cache", "--refresh"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_cache_disabled_in_config() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[cache]
+enabled = false
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v1.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+
+[extras]
+git_branch = false
+git_status = false
+current_directory = false
+virtual_env = false
+system_memory = false
+system_cpu = false
+"#
+    )
+    .unwrap();
+
+    let path = temp_file.path().to_path_buf();
+
+    let output = toolbox_cmd()
+        .args(["--config", path.to_str().unwrap(), "--format", "json"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parsed: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    let tools = parsed["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "Echo");
+    assert_eq!(tools[0]["version"], "1.0.0");
+}
+
+#[test]
+fn test_cache_custom_ttl_in_config() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[cache]
+enabled = true
+default_ttl = 60
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+
+[extras]
+git_branch = false
+git_status = false
+current_directory = false
+virtual_env = false
+system_memory = false
+system_cpu = false
+"#
+    )
+    .unwrap();
+
+    let path = temp_file.path().to_path_buf();
+
+    let output = toolbox_cmd()
+        .args(["--config", path.to_str().unwrap(), "--format", "json"])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let parsed: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    let tools = parsed["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["version"], "2.0.0");
+}
+
+// --- Require subcommand ---
+
+#[test]
+fn test_require_passes() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--color",
+            "never",
+            "require",
+            "echo=>=2",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("PASS  Echo  >=2  2.0.0"))
+        .stdout(predicate::str::contains(
+            "1 requirements: 1 passed, 0 failed",
+        ));
+}
+
+#[test]
+fn test_require_fails_on_unsatisfied_or_missing() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--color",
+            "never",
+            "require",
+            "echo=^3",
+            "ghost=>=1",
+        ])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("FAIL  Echo"))
+        .stdout(predicate::str::contains("MISS  Ghost"));
+}
+
+#[test]
+fn test_require_from_config_json() {
+    let mut temp_file = echo_tool_config();
+    writeln!(temp_file, "[requirements]\nEcho = \">=1.5, <3\"").unwrap();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "require",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results[0]["tool"], "Echo");
+    assert_eq!(results[0]["status"], "pass");
+}
+
+#[test]
+fn test_require_without_requirements_fails() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap(), "require"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No requirements given"));
+}
+
+#[test]
+fn test_require_invalid_spec() {
+    toolbox_cmd()
+        .args(["require", "node"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid requirement"));
+}
+
+// --- Logging ---
+
+#[test]
+fn test_verbose_logs_to_stderr() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--no-cache",
+            "-vv",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Echo").and(predicate::str::contains("DEBUG").not()))
+        .stderr(predicate::str::contains("spawning"))
+        .stderr(predicate::str::contains("detection finished"));
+}
+
+#[test]
+fn test_quiet_by_default() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap(), "--no-cache"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("detected").not());
+}
+
+#[test]
+fn test_log_file() {
+    let temp_file = echo_tool_config();
+    let dir = tempfile::tempdir().unwrap();
+    let log_path = dir.path().join("toolbox.log");
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--log-file",
+            log_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("detected").not());
+
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.contains("loading config"));
+    assert!(log.contains("tool=Echo"));
+}
+
+// --- Timings ---
+
+#[test]
+fn test_timings_report_on_stderr() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "json",
+            "--timings",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"duration_ms\""))
+        .stderr(predicate::str::contains(
+            "Detection timings (slowest first):",
+        ))
+        .stderr(predicate::str::contains("Echo"))
+        .stderr(predicate::str::contains("Total"));
+}
+
+// --- Bench subcommand ---
+
+#[test]
+fn test_bench() {
+    let temp_file = echo_tool_config();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "bench",
+            "--iterations",
+            "2",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Benchmark: 2 iterations, 2 tools"))
+        .stdout(predicate::str::contains("Uncached"))
+        .stdout(predicate::str::contains("Cached"))
+        .stdout(predicate::str::contains("  Echo "))
+        .stdout(predicate::str::contains("  Total "));
+}
+
+#[test]
+fn test_bench_json() {
+    let temp_file = echo_tool_config();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "bench",
+            "-n",
+            "1",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["iterations"], 1);
+    assert_eq!(report["uncached"][0]["name"], "Echo");
+    assert_eq!(report["cached"][2]["name"], "Total");
+    assert!(report["uncached"][0]["mean_ms"].is_number());
+}
+
+// --- Config subcommand ---
+
+#[test]
+fn test_config_set_get_unset() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(&config_path, "# my settings\n[display]\ncompact = true\n").unwrap();
+    let path = config_path.to_str().unwrap();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            path,
+            "config",
+            "set",
+            "display.compact",
+            "false",
+        ])
+        .assert()
+        .success();
+    toolbox_cmd()
+        .args([
+            "--config",
+            path,
+            "config",
+            "set",
+            "cache.default_ttl",
+            "600",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&config_path).unwrap();
+    assert!(content.starts_with("# my settings\n[display]\ncompact = false\n"));
+    assert!(content.contains("[cache]\ndefault_ttl = 600"));
+
+    toolbox_cmd()
+        .args(["--config", path, "config", "get", "cache.default_ttl"])
+        .assert()
+        .success()
+        .stdout("600\n");
+
+    toolbox_cmd()
+        .args(["--config", path, "config", "unset", "cache.default_ttl"])
+        .assert()
+        .success();
+    // Unset keys fall back to the default
+    toolbox_cmd()
+        .args(["--config", path, "config", "get", "cache.default_ttl"])
+        .assert()
+        .success()
+        .stdout("300\n");
+}
+
+#[test]
+fn test_config_set_creates_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("nested").join("config.toml");
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "set",
+            "theme.preset",
+            "nord",
+        ])
+        .assert()
+        .success();
+
+    let content = std::fs::read_to_string(&config_path).unwrap();
+    assert_eq!(content, "[theme]\npreset = \"nord\"\n");
+}
+
+#[test]
+fn test_config_set_invalid_value_leaves_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(&config_path, "[display]\ncompact = true\n").unwrap();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "set",
+            "display.compact",
+            "sometimes",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Invalid value for display.compact",
+        ));
+
+    let content = std::fs::read_to_string(&config_path).unwrap();
+    assert_eq!(content, "[display]\ncompact = true\n");
+}
+
+#[test]
+fn test_config_get_unknown_key() {
+    toolbox_cmd()
+        .args(["config", "get", "display.nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Config key not set: display.nope"));
+}
+
+#[test]
+fn test_config_validate_ok() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(&config_path, "[display]\ncompact = true\n").unwrap();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "validate",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Config OK"));
+}
+
+#[test]
+fn test_config_validate_reports_locations() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        "[display]\ncompcat = true\n\n[theme.custom]\ndirectory_bg = \"bluish\"\n",
+    )
+    .unwrap();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "validate",
+        ])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "config.toml:5:16: Invalid color: bluish",
+        ));
+
+    std::fs::write(&config_path, "[display]\ncompcat = true\n").unwrap();
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "validate",
+        ])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "config.toml:2:1: unknown key `display.compcat` (did you mean `compact`?)",
+        ));
+}
+
+#[test]
+fn test_verbose_lints_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        "use_default_tools = false\n\n[[custom_tools]]\nname = \"Echo\"\ncommand = \"echo 1.0.0\"\nparse_regx = \"(.*)\"\n",
+    )
+    .unwrap();
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "-v",
+            "list-tools",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "unknown key `custom_tools.0.parse_regx` (did you mean `parse_regex`?)",
+        ));
+
+    // Without -v unknown keys stay silent
+    toolbox_cmd()
+        .args(["--config", config_path.to_str().unwrap(), "list-tools"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("parse_regx").not());
+}
+
+#[test]
+fn test_yaml_and_json_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let yaml_path = dir.path().join("config.yaml");
+    std::fs::write(&yaml_path, "cache:\n  default_ttl: 42\n").unwrap();
+    let json_path = dir.path().join("config.json");
+    std::fs::write(&json_path, r#"{"cache": {"default_ttl": 43}}"#).unwrap();
+
+    toolbox_cmd()
+        .args(["--config", yaml_path.to_str().unwrap(), "show-config"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("default_ttl = 42"));
+    toolbox_cmd()
+        .args([
+            "--config",
+            json_path.to_str().unwrap(),
+            "config",
+            "get",
+            "cache.default_ttl",
+        ])
+        .assert()
+        .success()
+        .stdout("43\n");
+    toolbox_cmd()
+        .args([
+            "--config",
+            json_path.to_str().unwrap(),
+            "config",
+            "validate",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Config OK"));
+
+    // Edits go through toml_edit, so they are TOML-only
+    toolbox_cmd()
+        .args([
+            "--config",
+            yaml_path.to_str().unwrap(),
+            "config",
+            "set",
+            "cache.default_ttl",
+            "1",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only supported for TOML"));
+}
+
+#[test]
+fn test_config_include_and_target() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("base.toml"), "[cache]\ndefault_ttl = 42\n").unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        "include = [\"base.toml\"]\n\n[target.'cfg(any(unix, windows))'.display]\ncompact = false\n",
+    )
+    .unwrap();
+
+    toolbox_cmd()
+        .args(["--config", config_path.to_str().unwrap(), "show-config"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("default_ttl = 42"))
+        .stdout(predicate::str::contains("compact = false"))
+        .stdout(predicate::str::contains("include").not());
+
+    std::fs::write(&config_path, "include = [\"missing.toml\"]\n").unwrap();
+    toolbox_cmd()
+        .args(["--config", config_path.to_str().unwrap(), "show-config"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Could not read included config"));
+}
+
+#[test]
+fn test_config_migrate() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    let original = "# my tools\n[display]\nuse_default_tools = false\n";
+    std::fs::write(&config_path, original).unwrap();
+
+    // Without --write the migrated config is printed and the file is untouched
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "migrate",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("config_version = 1"))
+        .stdout(predicate::str::contains("# my tools"))
+        .stderr(predicate::str::contains("use_default_tools"));
+    assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "migrate",
+            "--write",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("from version 0 to 1"));
+    let migrated = std::fs::read_to_string(&config_path).unwrap();
+    assert!(migrated.contains("config_version = 1"));
+
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "migrate",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("Config is up to date (version 1)"));
+
+    // The migrated file validates cleanly
+    toolbox_cmd()
+        .args([
+            "--config",
+            config_path.to_str().unwrap(),
+            "config",
+            "validate",
+        ])
+        .assert()
+        .success();
+}
+
+#[cfg(unix)]
+fn editor_script(dir: &std::path::Path, body: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let script = dir.join("editor.sh");
+    std::fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    script
+}
+
+#[cfg(unix)]
+#[test]
+fn test_config_edit_saves_valid_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(&config_path, "[display]\ncompact = true\n").unwrap();
+    let editor = editor_script(
+        dir.path(),
+        "printf '[cache]\\ndefault_ttl = 42\\n' >> \"$1\"",
+    );
+
+    toolbox_cmd()
+        .env_remove("VISUAL")
+        .env("EDITOR", &editor)
+        .args(["--config", config_path.to_str().unwrap(), "config", "edit"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved"));
+
+    let content = std::fs::read_to_string(&config_path).unwrap();
+    assert_eq!(
+        content,
+        "[display]\ncompact = true\n[cache]\ndefault_ttl = 42\n"
+    );
+    assert!(!dir.path().join(".config.edit.toml").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_config_edit_rejects_invalid_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_path = dir.path().join("config.toml");
+    std::fs::write(&config_path, "[display]\ncompact = true\n").unwrap();
+    let editor = editor_script(dir.path(), "printf 'compact = \"yes\"\\n' >> \"$1\"");
+
+    toolbox_cmd()
+        .env_remove("VISUAL")
+        .env("EDITOR", &editor)
+        .args(["--config", config_path.to_str().unwrap(), "config", "edit"])
+        .write_stdin("n\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("config.toml:3:"))
+        .stderr(predicate::str::contains("Config not saved"));
+
+    let content = std::fs::read_to_string(&config_path).unwrap();
+    assert_eq!(content, "[display]\ncompact = true\n");
+}
+
+// --- Schema subcommand ---
+
+#[test]
+fn test_schema_config() {
+    let output = toolbox_cmd().args(["schema", "config"]).output().unwrap();
+    assert!(output.status.success());
+
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+    assert!(schema["properties"]["display"].is_object());
+}
+
+#[test]
+fn test_schema_output_matches_json_format() {
+    let output = toolbox_cmd().args(["schema", "output"]).output().unwrap();
+    assert!(output.status.success());
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    // Every field emitted by --format json is described by the schema
+    let temp_file = echo_tool_config();
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    for key in info.as_object().unwrap().keys() {
+        assert!(schema["properties"].get(key).is_some(), "{}", key);
+    }
+    let tool_properties = &schema["definitions"]["ToolInfo"]["properties"];
+    for key in info["tools"][0].as_object().unwrap().keys() {
+        assert!(tool_properties.get(key).is_some(), "{}", key);
+    }
+}
+
+#[test]
+fn test_schema_invalid_kind() {
+    toolbox_cmd().args(["schema", "nope"]).assert().failure();
+}
+
+// --- Zellij subcommands ---
+
+#[test]
+fn test_zellij_layout_statusbar_by_default() {
+    toolbox_cmd()
+        .args(["zellij-layout", "--plugin", "file:/opt/toolbox_zellij.wasm"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#"plugin location="file:/opt/toolbox_zellij.wasm""#,
+        ))
+        .stdout(predicate::str::contains(r#"single_line "true""#));
+}
+
+#[test]
+fn test_zellij_layout_sidebar() {
+    toolbox_cmd()
+        .args(["zellij-layout", "--sidebar"])
+        .env("ZELLIJ_CONFIG_DIR", "/etc/zellij")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#"plugin location="file:/etc/zellij/plugins/toolbox_zellij.wasm""#,
+        ))
+        .stdout(predicate::str::contains(r#"split_direction="vertical""#));
+
+    toolbox_cmd()
+        .args(["zellij-layout", "--sidebar", "--statusbar"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_zellij_install() {
+    let dir = tempfile::tempdir().unwrap();
+    let wasm = dir.path().join("toolbox_zellij.wasm");
+    std::fs::write(&wasm, b"\0asm").unwrap();
+    let config_dir = dir.path().join("zellij");
+
+    toolbox_cmd()
+        .args(["zellij-install", wasm.to_str().unwrap()])
+        .env("ZELLIJ_CONFIG_DIR", &config_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Installed"));
+    let installed = config_dir.join("plugins").join("toolbox_zellij.wasm");
+    assert_eq!(std::fs::read(&installed).unwrap(), b"\0asm");
+
+    // Existing plugin is kept unless --force
+    toolbox_cmd()
+        .args(["zellij-install", wasm.to_str().unwrap()])
+        .env("ZELLIJ_CONFIG_DIR", &config_dir)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+    toolbox_cmd()
+        .args(["zellij-install", "--force", wasm.to_str().unwrap()])
+        .env("ZELLIJ_CONFIG_DIR", &config_dir)
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_zellij_install_missing_plugin() {
+    let dir = tempfile::tempdir().unwrap();
+    toolbox_cmd()
+        .args(["zellij-install", "--plugins-dir"])
+        .arg(dir.path())
+        .arg(dir.path().join("nope.wasm"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("toolbox_zellij.wasm not found"));
+}
+
+/// A minimal wasm module with a `toolbox_version` custom section
+fn plugin_wasm(version: &str) -> Vec<u8> {
+    let name = b"toolbox_version";
+    let mut wasm = b"\0asm\x01\0\0\0\0".to_vec();
+    wasm.push((1 + name.len() + version.len()) as u8);
+    wasm.push(name.len() as u8);
+    wasm.extend_from_slice(name);
+    wasm.extend_from_slice(version.as_bytes());
+    wasm
+}
+
+#[test]
+fn test_zellij_update_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let check = || {
+        toolbox_cmd()
+            .args(["zellij-update", "--check", "--plugins-dir"])
+            .arg(dir.path())
+            .assert()
+            .success()
+    };
+
+    check().stdout(predicate::str::contains("Plugin not installed"));
+    std::fs::write(dir.path().join("toolbox_zellij.wasm"), plugin_wasm("0.0.1")).unwrap();
+    check().stdout(predicate::str::contains("Plugin 0.0.1 (CLI"));
+    std::fs::write(
+        dir.path().join("toolbox_zellij.wasm"),
+        plugin_wasm(env!("CARGO_PKG_VERSION")),
+    )
+    .unwrap();
+    check().stdout(predicate::str::contains("matches the CLI"));
+}
+
+#[test]
+fn test_zellij_update_local_build() {
+    let dir = tempfile::tempdir().unwrap();
+    let plugins = dir.path().join("plugins");
+    let update = |wasm: &std::path::Path| {
+        toolbox_cmd()
+            .arg("zellij-update")
+            .arg(wasm)
+            .arg("--plugins-dir")
+            .arg(&plugins)
+            .assert()
+    };
+
+    let stale = dir.path().join("stale.wasm");
+    std::fs::write(&stale, plugin_wasm("0.0.1")).unwrap();
+    update(&stale)
+        .failure()
+        .stderr(predicate::str::contains("is not a"));
+
+    let current = dir.path().join("current.wasm");
+    let wasm = plugin_wasm(env!("CARGO_PKG_VERSION"));
+    std::fs::write(&current, &wasm).unwrap();
+    update(&current)
+        .success()
+        .stdout(predicate::str::contains("Updated plugin (not installed)"));
+    assert_eq!(
+        std::fs::read(plugins.join("toolbox_zellij.wasm")).unwrap(),
+        wasm
+    );
+}
+
+#[test]
+fn test_zellij_update_download() {
+    let wasm = plugin_wasm(env!("CARGO_PKG_VERSION"));
+    let tag = format!("v{}", env!("CARGO_PKG_VERSION"));
+    let (_release, url) = fake_release(&tag, &[("toolbox_zellij.wasm", &wasm)], None);
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("toolbox_zellij.wasm"), plugin_wasm("0.0.1")).unwrap();
+
+    toolbox_cmd()
+        .args(["zellij-update", "--plugins-dir"])
+        .arg(dir.path())
+        .env("TOOLBOX_RELEASES_URL", &url)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Updated plugin (0.0.1) to {} from release {}",
+            env!("CARGO_PKG_VERSION"),
+            tag
+        )));
+    assert_eq!(
+        std::fs::read(dir.path().join("toolbox_zellij.wasm")).unwrap(),
+        wasm
+    );
+}
+
+// --- MCP server ---
+
+#[test]
+fn test_mcp_stdio_session() {
+    let temp_file = echo_tool_config();
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2024-11-05","capabilities":{},"clientInfo":{"name":"test","version":"0"}}}"#,
+        r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"get_tool_versions","arguments":{"tools":["Echo"]}}}"#,
+    ]
+    .join("\n");
+    let output = toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap(), "mcp"])
+        .write_stdin(input)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let responses: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["result"]["serverInfo"]["name"], "toolbox");
+    let text = responses[1]["result"]["content"][0]["text"]
+        .as_str()
+        .unwrap();
+    let info: serde_json::Value = serde_json::from_str(text).unwrap();
+    assert_eq!(info["tools"][0]["name"], "Echo");
+    assert_eq!(info["tools"][0]["version"], "2.0.0");
+}
+
+#[test]
+fn test_mcp_applies_profile() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "App"
+command = "echo v1.0.0"
+enabled = true
+
+[[custom_tools]]
+name = "Infra"
+command = "echo v2.0.0"
+enabled = false
+
+[profiles.infra]
+enable_tools = ["Infra"]
+disable_tools = ["App"]
+"#
+    )
+    .unwrap();
+    let output = toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--profile", "infra", "mcp"])
+        .write_stdin(
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"get_tool_versions","arguments":{}}}"#,
+        )
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let text = response["result"]["content"][0]["text"].as_str().unwrap();
+    let info: serde_json::Value = serde_json::from_str(text).unwrap();
+    assert_eq!(info["tools"].as_array().unwrap().len(), 1);
+    assert_eq!(info["tools"][0]["name"], "Infra");
+}
+
+// --- HTTP API server ---
+
+#[test]
+fn test_serve_http_info() {
+    use std::io::{BufRead, BufReader, Read};
+
+    let temp_file = echo_tool_config();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("toolbox"))
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "serve",
+            "--http",
+            "127.0.0.1:0",
+        ])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // "Serving toolbox API on http://127.0.0.1:PORT"
+    let mut line = String::new();
+    BufReader::new(child.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line.trim().rsplit("http://").next().unwrap().to_string();
+
+    let mut stream = std::net::TcpStream::connect(&addr).unwrap();
+    stream
+        .write_all(b"GET /info HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    let info: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(info["tools"][0]["version"], "2.0.0");
+}
+
+#[test]
+fn test_serve_applies_dir_overrides_per_request() {
+    use std::io::{BufRead, BufReader, Read};
+
+    let root = tempfile::tempdir().unwrap();
+    let infra = root.path().join("infra");
+    std::fs::create_dir_all(&infra).unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "App"
+command = "echo v1.0.0"
+enabled = true
+
+[[custom_tools]]
+name = "Infra"
+command = "echo v2.0.0"
+enabled = false
+
+[[dir_overrides]]
+path = "{}/infra/**"
+enable_tools = ["Infra"]
+disable_tools = ["App"]
+"#,
+        root.path().display()
+    )
+    .unwrap();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("toolbox"))
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--dir", root.path().to_str().unwrap()])
+        .args(["serve", "--http", "127.0.0.1:0"])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line.trim().rsplit("http://").next().unwrap().to_string();
+
+    let tools = |target: String| {
+        let mut stream = std::net::TcpStream::connect(&addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let info: serde_json::Value = serde_json::from_str(body).unwrap();
+        info["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    let in_infra = tools(format!("/info?dir={}", infra.display()));
+    let default = tools("/info".to_string());
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(in_infra, ["Infra"]);
+    assert_eq!(default, ["App"]);
+}
+
+#[test]
+fn test_daemon_reload() {
+    use std::io::{BufRead, BufReader};
+
+    let temp_file = echo_tool_config();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("toolbox"))
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "serve",
+            "--http",
+            "127.0.0.1:0",
+        ])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let addr = line.trim().rsplit("http://").next().unwrap().to_string();
+
+    writeln!(temp_file.as_file(), "\n[display]\ncompact = false").unwrap();
+    let reload = toolbox_cmd()
+        .args(["daemon", "reload", "--http", &addr])
+        .assert();
+    // The server logs the reload too
+    line.clear();
+    stderr.read_line(&mut line).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    reload.success().stdout(predicate::str::contains(
+        "Config reloaded: display.compact: true -> false",
+    ));
+    assert_eq!(
+        line.trim(),
+        "Config reloaded: display.compact: true -> false"
+    );
+
+    toolbox_cmd()
+        .args(["daemon", "reload", "--http", "127.0.0.1:1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No toolbox server at 127.0.0.1:1"));
+}
+
+#[test]
+fn test_daemon_reload_keeps_profile() {
+    use std::io::{BufRead, BufReader, Read};
+
+    let mut temp_file = echo_tool_config();
+    writeln!(temp_file, "[profiles.quiet]\ndisable_tools = [\"Echo\"]").unwrap();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("toolbox"))
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--profile", "quiet", "serve", "--http", "127.0.0.1:0"])
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let addr = line.trim().rsplit("http://").next().unwrap().to_string();
+
+    writeln!(temp_file.as_file(), "\n[display]\ncompact = false").unwrap();
+    let reload = toolbox_cmd()
+        .args(["daemon", "reload", "--http", &addr])
+        .assert();
+    let mut stream = std::net::TcpStream::connect(&addr).unwrap();
+    stream
+        .write_all(b"GET /info HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    // Only the edit changed: the profile still applies after the reload
+    reload.success().stdout(predicate::str::contains(
+        "Config reloaded: display.compact: true -> false",
+    ));
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    let info: serde_json::Value = serde_json::from_str(body).unwrap();
+    let tools = info["tools"].as_array().unwrap();
+    assert!(tools.iter().all(|tool| tool["name"] != "Echo"), "{}", body);
+}
+
+// --- Shell prompt ---
+
+#[test]
+fn test_init_shell_scripts() {
+    for (shell, hook) in [
+        ("bash", "PROMPT_COMMAND"),
+        ("zsh", "add-zsh-hook precmd"),
+        ("fish", "function fish_prompt"),
+    ] {
+        toolbox_cmd()
+            .args(["init-shell", shell])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "prompt --shell {}",
+                shell
+            )))
+            .stdout(predicate::str::contains(hook));
+    }
+    toolbox_cmd().args(["init-shell", "nu"]).assert().failure();
+}
+
+fn prompt_config(dir: &std::path::Path, prompt: &str) -> std::path::PathBuf {
+    let config = dir.join("config.toml");
+    std::fs::write(
+        &config,
+        format!(
+            r#"
+use_default_tools = false
+
+[display]
+sections = ["dir", "tools"]
+
+[prompt]
+{}
+
+[[custom_tools]]
+name = "Echo"
+command = "cat version.txt"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#,
+            prompt
+        ),
+    )
+    .unwrap();
+    config
+}
+
+fn run_prompt(dir: &std::path::Path, config: &std::path::Path, extra: &[&str]) -> String {
+    let output = toolbox_cmd()
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .args(["--config", config.to_str().unwrap()])
+        .args(["--dir", dir.to_str().unwrap(), "--color", "never"])
+        .args(extra)
+        .arg("prompt")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_prompt_is_cached_per_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    // Wait for the first detection instead of printing nothing
+    let config = prompt_config(dir.path(), "budget_ms = 10000\nexclude = [\"dir\"]");
+
+    std::fs::write(dir.path().join("version.txt"), "v1.0.0").unwrap();
+    let first = run_prompt(dir.path(), &config, &[]);
+    assert!(first.contains("1.0.0"), "{}", first);
+    assert!(!first.contains('\n'));
+    // Excluded sections are not shown
+    assert!(!first.contains(dir.path().file_name().unwrap().to_str().unwrap()));
+
+    // Served from the cache until refreshed
+    std::fs::write(dir.path().join("version.txt"), "v1.1.0").unwrap();
+    assert_eq!(run_prompt(dir.path(), &config, &[]), first);
+    assert!(run_prompt(dir.path(), &config, &["--refresh"]).contains("1.1.0"));
+    assert!(run_prompt(dir.path(), &config, &["--no-cache"]).contains("1.1.0"));
+}
+
+#[test]
+fn test_prompt_is_cached_per_profile_and_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = prompt_config(
+        dir.path(),
+        "budget_ms = 10000\n\n[profiles.quiet]\ndisable_tools = [\"Echo\"]",
+    );
+    std::fs::write(dir.path().join("version.txt"), "v1.0.0").unwrap();
+
+    assert!(run_prompt(dir.path(), &config, &[]).contains("1.0.0"));
+    // Each gets its own result instead of the one cached above
+    let quiet = run_prompt(dir.path(), &config, &["--profile", "quiet"]);
+    assert!(!quiet.contains("1.0.0"), "{}", quiet);
+    let extras = run_prompt(dir.path(), &config, &["--extras-only"]);
+    assert!(!extras.contains("1.0.0"), "{}", extras);
+    let sections = run_prompt(dir.path(), &config, &["--sections", "dir"]);
+    assert!(!sections.contains("1.0.0"), "{}", sections);
+    // And they don't replace it
+    assert!(run_prompt(dir.path(), &config, &[]).contains("1.0.0"));
+}
+
+#[test]
+fn test_prompt_refreshes_in_background() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = prompt_config(dir.path(), "budget_ms = 0");
+    // Every result is expired, so each prompt shows the last one and refreshes it
+    let mut config_text = std::fs::read_to_string(&config).unwrap();
+    config_text.push_str("\n[cache]\ndefault_ttl = 0\n");
+    std::fs::write(&config, config_text).unwrap();
+
+    let wait_for = |version: &str| {
+        for _ in 0..200 {
+            if run_prompt(dir.path(), &config, &[]).contains(version) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("prompt never showed {}", version);
+    };
+
+    std::fs::write(dir.path().join("version.txt"), "v1.0.0").unwrap();
+    // Nothing is cached yet and the budget is zero: an empty prompt
+    assert_eq!(run_prompt(dir.path(), &config, &[]), "");
+    wait_for("1.0.0");
+    std::fs::write(dir.path().join("version.txt"), "v1.1.0").unwrap();
+    wait_for("1.1.0");
+}
+
+#[test]
+fn test_prompt_escapes_colors_for_the_shell() {
+    let temp_file = echo_tool_config();
+    let output = toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args([
+            "--no-cache",
+            "--color",
+            "always",
+            "prompt",
+            "--shell",
+            "bash",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\\[\x1b["), "{:?}", stdout);
+    assert!(stdout.contains("2.0.0"));
+}
+
+// --- JSON version output ---
+
+#[test]
+fn test_parse_json_pointer() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Kube"
+command = 'echo {{"clientVersion":{{"gitVersion":"v1.29.2"}}}}'
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+parse_json_pointer = "/clientVersion/gitVersion"
+enabled = true
+
+[[custom_tools]]
+name = "Broken"
+command = "echo not-json"
+parse_json_pointer = "/version"
+enabled = true
+"#
+    )
+    .unwrap();
+    let config = temp_file.path().to_str().unwrap();
+
+    let output = toolbox_cmd()
+        .args(["--config", config, "--format", "json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["tools"][0]["version"], "1.29.2");
+    assert_eq!(json["tools"][1]["available"], false);
+
+    let output = toolbox_cmd()
+        .args(["--config", config, "doctor", "--json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let broken = json["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["name"] == "Broken")
+        .unwrap();
+    assert_eq!(broken["status"], "Warning");
+    assert!(broken["suggestion"]
+        .as_str()
+        .unwrap()
+        .contains("parse_json_pointer"));
+}
+
+// --- Language ---
+
+#[test]
+fn test_language_ja_doctor() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        "language = \"ja\"\nuse_default_tools = false\n\n[[custom_tools]]\nname = \"Missing\"\ncommand = \"toolbox-no-such-command --version\""
+    )
+    .unwrap();
+
+    toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap(), "doctor"])
+        .assert()
+        .stdout(predicate::str::contains("Toolbox 診断"))
+        .stdout(predicate::str::contains("ツールの状態:"))
+        .stdout(predicate::str::contains("コマンドが見つかりません"))
+        .stdout(predicate::str::contains("Tool Status:").not());
+}
+
+// --- Sort and pinned tools ---
+
+#[test]
+fn test_display_sort_and_pinned() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    let tool = |name: &str| {
+        format!(
+            "[[custom_tools]]\nname = \"{}\"\ncommand = \"echo v1.0.0\"\nparse_regex = 'v?(\\d+\\.\\d+\\.\\d+)'\n",
+            name
+        )
+    };
+    writeln!(
+        temp_file,
+        "use_default_tools = false\n\n[display]\nsort = \"name\"\npinned = [\"zeta\"]\n\n{}\n{}\n{}",
+        tool("Zeta"),
+        tool("Beta"),
+        tool("Alpha")
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .expect("failed to execute");
+    assert!(output.status.success());
+
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<&str> = parsed["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Zeta", "Alpha", "Beta"]);
+}
+
+// --- Self-update ---
+
+/// The release asset of the toolbox binary for this platform
+fn binary_asset() -> String {
+    format!(
+        "toolbox-{}-{}",
+        std::env::consts::ARCH,
+        match std::env::consts::OS {
+            "macos" => "apple-darwin",
+            "windows" => "pc-windows-msvc.exe",
+            _ => "unknown-linux-gnu",
+        }
+    )
+}
+
+/// Serve the files of a directory over HTTP on a loopback port (in a
+/// background thread); returns the base URL
+fn serve_dir(dir: std::path::PathBuf) -> String {
+    use std::io::BufRead;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            // Headers, up to the blank line
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap_or(0) <= 2 {
+                    break;
+                }
+            }
+            let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+            let response = match std::fs::read(dir.join(path.trim_start_matches('/'))) {
+                Ok(body) => {
+                    let mut response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend(body);
+                    response
+                }
+                Err(_) => {
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_vec()
+                }
+            };
+            let _ = stream.write_all(&response);
+        }
+    });
+    format!("http://{}", addr)
+}
+
+/// A releases API served over loopback HTTP (`latest` and `tags/<tag>`), with
+/// the given assets and their checksums (or `checksum` for all of them)
+fn fake_release(
+    tag: &str,
+    assets: &[(&str, &[u8])],
+    checksum: Option<&str>,
+) -> (tempfile::TempDir, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let mut sums = String::new();
+    for (name, content) in assets {
+        std::fs::write(dir.path().join(name), content).unwrap();
+        let sum = checksum
+            .map(String::from)
+            .unwrap_or_else(|| toolbox_core::trust::sha256_hex(content));
+        sums.push_str(&format!("{}  {}\n", sum, name));
+    }
+    std::fs::write(dir.path().join("SHA256SUMS"), sums).unwrap();
+    let base = serve_dir(dir.path().to_path_buf());
+    let url = |name: &str| format!("{}/{}", base, name);
+    let assets: Vec<_> = assets
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(["SHA256SUMS"])
+        .map(|name| serde_json::json!({"name": name, "browser_download_url": url(name)}))
+        .collect();
+    let release = serde_json::json!({"tag_name": tag, "assets": assets}).to_string();
+    let releases = dir.path().join("releases");
+    std::fs::create_dir_all(releases.join("tags")).unwrap();
+    std::fs::write(releases.join("latest"), &release).unwrap();
+    std::fs::write(releases.join("tags").join(tag), &release).unwrap();
+    let releases_url = url("releases");
+    (dir, releases_url)
+}
+
+#[test]
+fn test_self_update_check() {
+    let (_dir, url) = fake_release("v99.0.0", &[(&binary_asset(), b"new binary")], None);
+    toolbox_cmd()
+        .args(["self-update", "--check"])
+        .env("TOOLBOX_RELEASES_URL", &url)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("toolbox 99.0.0 is available"));
+
+    let (_dir, url) = fake_release("v0.0.1", &[(&binary_asset(), b"new binary")], None);
+    toolbox_cmd()
+        .arg("self-update")
+        .env("TOOLBOX_RELEASES_URL", &url)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("is up to date"));
+
+    let dir = tempfile::tempdir().unwrap();
+    toolbox_cmd()
+        .args(["self-update", "--check"])
+        .env(
+            "TOOLBOX_RELEASES_URL",
+            format!("file://{}", dir.path().display()),
+        )
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only https URLs are allowed"));
+}
+
+#[test]
+fn test_self_update_rejects_checksum_mismatch() {
+    let (_dir, url) = fake_release(
+        "v99.0.0",
+        &[(&binary_asset(), b"new binary")],
+        Some(&"0".repeat(64)),
+    );
+    toolbox_cmd()
+        .arg("self-update")
+        .env("TOOLBOX_RELEASES_URL", &url)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Checksum mismatch"));
+}
+
+// The cache directory follows XDG_CACHE_HOME on Linux only
+#[cfg(target_os = "linux")]
+#[test]
+fn test_doctor_update_notice() {
+    let (_dir, url) = fake_release("v99.0.0", &[(&binary_asset(), b"new binary")], None);
+    let cache = tempfile::tempdir().unwrap();
+    let doctor = |check: bool| {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(
+            temp_file,
+            "use_default_tools = false\n\n[update]\ncheck = {}",
+            check
+        )
+        .unwrap();
+        let output = toolbox_cmd()
+            .args(["--config", temp_file.path().to_str().unwrap(), "doctor"])
+            .env("TOOLBOX_RELEASES_URL", &url)
+            .env("XDG_CACHE_HOME", cache.path())
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert!(!doctor(false).contains("is available"));
+    assert!(doctor(true).contains("toolbox 99.0.0 is available (run `toolbox self-update`)"));
+}
+
+// --- Explain ---
+
+#[test]
+fn test_explain_tool() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = prompt_config(dir.path(), "budget_ms = 10000");
+    std::fs::write(dir.path().join("version.txt"), "v1.0.0").unwrap();
+    let explain = |args: &[&str]| {
+        toolbox_cmd()
+            .env("XDG_CACHE_HOME", dir.path().join("cache"))
+            .args(["--config", config.to_str().unwrap()])
+            .args(["--dir", dir.path().to_str().unwrap(), "explain"])
+            .args(args)
+            .assert()
+    };
+
+    explain(&["echo"])
+        .success()
+        .stdout(predicate::str::contains("Command:   cat version.txt"))
+        .stdout(predicate::str::contains("Exit code: 0"))
+        .stdout(predicate::str::contains("   v1.0.0"))
+        .stdout(predicate::str::contains(r#"1 = "1.0.0""#))
+        .stdout(predicate::str::contains("Version:   1.0.0"))
+        .stdout(predicate::str::contains("Cache:     not cached"));
+
+    // The result `toolbox prompt` cached for the directory
+    run_prompt(dir.path(), &config, &[]);
+    let output = explain(&["Echo", "--json"]).success();
+    let json: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+    assert_eq!(json["stdout"], "v1.0.0");
+    assert_eq!(json["provenance"]["origin"], "custom_tools");
+    assert_eq!(json["cache"]["entry"]["version"], "1.0.0");
+
+    explain(&["missing"])
+        .failure()
+        .stderr(predicate::str::contains("Unknown tool: missing"));
+}
+
+// --- Doctor filters ---
+
+#[test]
+fn test_doctor_filters_and_fail_on() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Good"
+command = "echo 1.0.0"
+enabled = true
+
+[[custom_tools]]
+name = "Broken"
+short_name = "brk"
+command = "nonexistent_cmd_12345 --version"
+enabled = true
+"#
+    )
+    .unwrap();
+    let doctor = |args: &[&str]| {
+        toolbox_cmd()
+            .args(["--config", temp_file.path().to_str().unwrap(), "doctor"])
+            .args(args)
+            .assert()
+    };
+
+    // Without --fail-on doctor only reports
+    doctor(&[]).success();
+    doctor(&["--fail-on", "error"]).failure().code(1);
+    doctor(&["--fail-on", "warning", "--tool", "good"]).success();
+
+    doctor(&["--only-errors"])
+        .success()
+        .stdout(predicate::str::contains("Broken"))
+        .stdout(predicate::str::contains(" OK ").not())
+        .stdout(predicate::str::contains(
+            "2 tools checked: 1 ok, 0 warning, 1 error",
+        ));
+
+    let output = doctor(&["--tool", "brk", "--json"])
+        .get_output()
+        .stdout
+        .clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["total"], 1);
+    assert_eq!(json["tools"][0]["name"], "Broken");
+
+    doctor(&["--tool", "missing"])
+        .failure()
+        .stderr(predicate::str::contains("Unknown tool: missing"));
+}
+
+// --- Doctor environment ---
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_environment_section() {
+    let bin = tempfile::tempdir().unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Shell"
+command = "sh -c true"
+enabled = true
+"#
+    )
+    .unwrap();
+    let path = format!("/bin:/usr/bin:{}", bin.path().display());
+    let doctor = |json: bool| {
+        let mut cmd = toolbox_cmd();
+        cmd.args(["--config", temp_file.path().to_str().unwrap(), "doctor"])
+            .env("PATH", &path)
+            .env("DIRENV_DIR", "-/work/app");
+        if json {
+            cmd.arg("--json");
+        }
+        cmd.output().unwrap()
+    };
+
+    let output = doctor(true);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let environment = &json["environment"];
+    let entries = environment["path"].as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+    assert!(entries
+        .iter()
+        .any(|e| e["tools"] == serde_json::json!(["Shell"])));
+    assert_eq!(environment["variables"]["DIRENV_DIR"], "-/work/app");
+    let direnv = environment["managers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["name"] == "direnv")
+        .unwrap();
+    assert_eq!(direnv["active"], true);
+
+    let text = String::from_utf8_lossy(&doctor(false).stdout).into_owned();
+    assert!(text.contains("Environment:"));
+    assert!(text.contains("direnv  active (loaded /work/app)"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_packages() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = tempfile::tempdir().unwrap();
+    let bin = root.path().join("Cellar/faketool/1.2.3/bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let tool = bin.join("faketool");
+    std::fs::write(&tool, "#!/bin/sh\necho v1.2.3\n").unwrap();
+    std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        "use_default_tools = false\n\n[[custom_tools]]\nname = \"Fake\"\ncommand = \"{} --version\"",
+        tool.display()
+    )
+    .unwrap();
+    let doctor = |packages: bool| {
+        let mut cmd = toolbox_cmd();
+        cmd.args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "doctor",
+            "--json",
+        ]);
+        if packages {
+            cmd.arg("--packages");
+        }
+        let output = cmd.output().unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    assert!(doctor(false)["tools"][0].get("package").is_none());
+    assert_eq!(
+        doctor(true)["tools"][0]["package"],
+        serde_json::json!({"manager": "homebrew", "package": "faketool", "version": "1.2.3"})
+    );
+}
+
+// --- Trust ---
+
+#[test]
+fn test_trust_records_project_config() {
+    let data = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let sub = project.path().join("src");
+    std::fs::create_dir(&sub).unwrap();
+    let trust = |args: &[&str]| {
+        toolbox_cmd()
+            .arg("trust")
+            .args(args)
+            .env("XDG_DATA_HOME", data.path())
+            .assert()
+    };
+
+    trust(&[sub.to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains("No .toolbox.toml found"));
+
+    std::fs::write(
+        project.path().join(".toolbox.toml"),
+        "[display]
+",
+    )
+    .unwrap();
+    trust(&[sub.to_str().unwrap()])
+        .success()
+        .stdout(predicate::str::contains("Trusted:"));
+    let store = std::fs::read_to_string(data.path().join("toolbox").join("trusted.json")).unwrap();
+    assert!(store.contains(".toolbox.toml"));
+
+    trust(&[sub.to_str().unwrap(), "--revoke"])
+        .success()
+        .stdout(predicate::str::contains("No longer trusted:"));
+    trust(&[sub.to_str().unwrap(), "--revoke"])
+        .success()
+        .stdout(predicate::str::contains("Not trusted:"));
+}
+
+#[test]
+fn test_untrusted_project_config_runs_no_commands() {
+    let data = tempfile::tempdir().unwrap();
+    let project = tempfile::tempdir().unwrap();
+    let config = project.path().join("user.toml");
+    std::fs::write(&config, "use_default_tools = false\n").unwrap();
+    std::fs::write(
+        project.path().join(".toolbox.toml"),
+        r#"
+[display]
+show_icons = false
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v1.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#,
+    )
+    .unwrap();
+    let tools = || {
+        let output = toolbox_cmd()
+            .args(["--config", config.to_str().unwrap(), "--format", "json"])
+            .arg("--dir")
+            .arg(project.path())
+            .env("XDG_DATA_HOME", data.path())
+            .output()
+            .expect("failed to execute");
+        let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        info["tools"].as_array().unwrap().len()
+    };
+
+    assert_eq!(tools(), 0);
+    toolbox_cmd()
+        .arg("trust")
+        .arg(project.path())
+        .env("XDG_DATA_HOME", data.path())
+        .assert()
+        .success();
+    assert_eq!(tools(), 1);
+}
+
+// --- Sandbox ---
+
+#[cfg(unix)]
+#[test]
+fn test_sandbox_hides_environment() {
+    let config = |enabled: bool| {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(
+            temp_file,
+            r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Secret"
+command = "printenv TOOLBOX_TEST_SECRET"
+enabled = true
+
+[sandbox]
+enabled = {}
+allow_network = true
+"#,
+            enabled
+        )
+        .unwrap();
+        temp_file
+    };
+    let run = |temp_file: &NamedTempFile| {
+        let output = toolbox_cmd()
+            .args([
+                "--config",
+                temp_file.path().to_str().unwrap(),
+                "--no-cache",
+                "--format",
+                "json",
+            ])
+            .env("TOOLBOX_TEST_SECRET", "hunter2")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["tools"][0].clone()
+    };
+
+    let tool = run(&config(false));
+    assert_eq!(tool["version"], "hunter2");
+
+    // Not in the allowlist: the command doesn't see it
+    let tool = run(&config(true));
+    assert_eq!(tool["available"], false);
+}
+
+// --- Retries ---
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_reports_retries() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("flaky");
+    let count = dir.path().join("count");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\nn=$(cat {count} 2>/dev/null || echo 0)\necho $((n + 1)) > {count}\n[ $n -ge 1 ] || exit 1\necho 2.0.0\n",
+            count = count.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Flaky"
+command = "{}"
+retries = 3
+retry_delay_ms = 1
+enabled = true
+"#,
+        script.display()
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "doctor",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["tools"][0]["status"], "Ok");
+    assert_eq!(json["tools"][0]["version"], "2.0.0");
+    assert_eq!(json["tools"][0]["retries"], 1);
+}
+
+// --- Go modules ---
+
+#[cfg(unix)]
+#[test]
+fn test_go_module_older_than_directive() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let bin = dir.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let go = bin.join("go");
+    std::fs::write(&go, "#!/bin/sh\necho 'go version go1.21.5 linux/amd64'\n").unwrap();
+    std::fs::set_permissions(&go, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(bin).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let project = dir.path().join("service");
+    std::fs::create_dir(&project).unwrap();
+    std::fs::write(
+        project.join("go.mod"),
+        "module github.com/acme/service\n\ngo 1.22\n",
+    )
+    .unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[extras]
+go_module = true
+
+[[custom_tools]]
+name = "Go"
+command = "go version"
+parse_regex = 'go(\d+\.\d+(?:\.\d+)?)'
+enabled = true
+"#
+    )
+    .unwrap();
+    let config = temp_file.path().to_str().unwrap();
+    let project_dir = project.to_str().unwrap();
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .args(["--config", config, "--dir", project_dir, "--format", "json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let tool = &json["tools"][0];
+    assert_eq!(tool["go_module"]["path"], "github.com/acme/service");
+    assert_eq!(tool["go_module"]["go"], "1.22");
+    assert_eq!(tool["display_version"], "1.21.5 (go.mod requires 1.22)");
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .args(["--config", config, "--dir", project_dir, "doctor", "--json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["tools"][0]["status"], "Warning");
+    assert_eq!(json["tools"][0]["error_detail"], "go.mod requires go 1.22");
+}
+
+// --- Rust toolchains ---
+
+#[cfg(unix)]
+#[test]
+fn test_rustup_toolchain_from_toolchain_file() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cargo_home = dir.path().join("cargo");
+    let cargo_bin = cargo_home.join("bin");
+    std::fs::create_dir_all(&cargo_bin).unwrap();
+    let rustc = cargo_bin.join("rustc");
+    std::fs::write(
+        &rustc,
+        "#!/bin/sh\necho 'rustc 1.85.0-nightly (d4025ee45 2024-11-28)'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&rustc, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let rustup_home = dir.path().join("rustup");
+    std::fs::create_dir(&rustup_home).unwrap();
+    std::fs::write(
+        rustup_home.join("settings.toml"),
+        "default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n",
+    )
+    .unwrap();
+    let project = dir.path().join("project");
+    std::fs::create_dir(&project).unwrap();
+    std::fs::write(
+        project.join("rust-toolchain.toml"),
+        "[toolchain]\nchannel = \"nightly-2024-11-28\"\n",
+    )
+    .unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(cargo_bin).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Rust"
+command = "rustc --version"
+parse_regex = 'rustc (\S+)'
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let run = |dir: &std::path::Path| {
+        let output = toolbox_cmd()
+            .env("PATH", &path)
+            .env("CARGO_HOME", &cargo_home)
+            .env("RUSTUP_HOME", &rustup_home)
+            .env_remove("RUSTUP_TOOLCHAIN")
+            .args([
+                "--config",
+                temp_file.path().to_str().unwrap(),
+                "--dir",
+                dir.to_str().unwrap(),
+                "--format",
+                "json",
+            ])
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["tools"][0].clone()
+    };
+
+    let tool = run(&project);
+    assert_eq!(tool["source"], "rustup");
+    assert_eq!(tool["toolchain"]["name"], "nightly-2024-11-28");
+    assert_eq!(tool["toolchain"]["selected_by"], "rust-toolchain.toml");
+    assert_eq!(
+        tool["display_version"],
+        "1.85.0-nightly (nightly-2024-11-28 via rust-toolchain.toml)"
+    );
+
+    // The default toolchain isn't repeated next to the version
+    let tool = run(&rustup_home);
+    assert_eq!(tool["toolchain"]["selected_by"], "default");
+    assert!(tool.get("display_version").is_none());
+}
+
+// --- Virtual environments ---
+
+#[test]
+fn test_virtual_env_details() {
+    let dir = tempfile::tempdir().unwrap();
+    let venv = dir.path().join(".venv");
+    std::fs::create_dir(&venv).unwrap();
+    std::fs::write(
+        venv.join("pyvenv.cfg"),
+        "home = /usr/bin\nuv = 0.4.18\nversion_info = 3.12.1.final.0\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("uv.lock"), "").unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[extras]
+virtual_env = true
+virtual_env_python = true
+virtual_env_manager = true
+"#
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .env("VIRTUAL_ENV", &venv)
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--dir",
+            dir.path().to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["virtual_env"], ".venv");
+    assert_eq!(json["virtual_env_python"], "3.12.1");
+    assert_eq!(json["virtual_env_manager"], "uv");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_conda_env_details() {
+    let home = tempfile::tempdir().unwrap();
+    let prefix = home.path().join("miniforge3/envs/ml");
+    std::fs::create_dir_all(prefix.join("conda-meta")).unwrap();
+    std::fs::write(
+        prefix.join("conda-meta/python-3.11.5-h955ad1f_0.json"),
+        "{}",
+    )
+    .unwrap();
+    std::fs::create_dir(home.path().join(".conda")).unwrap();
+    std::fs::write(
+        home.path().join(".conda/environments.txt"),
+        format!(
+            "{}\n{}\n",
+            home.path().join("miniforge3").display(),
+            prefix.display()
+        ),
+    )
+    .unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[extras]
+virtual_env = true
+virtual_env_python = true
+virtual_env_manager = true
+conda_env_count = true
+"#
+    )
+    .unwrap();
+    let config = temp_file.path().to_str().unwrap();
+    let run = |venv: Option<&std::path::Path>| {
+        let mut cmd = toolbox_cmd();
+        cmd.env("HOME", home.path())
+            .env("CONDA_DEFAULT_ENV", "ml")
+            .env("CONDA_PREFIX", &prefix)
+            .env("CONDA_EXE", home.path().join("miniforge3/bin/mamba"))
+            .env_remove("VIRTUAL_ENV")
+            .args(["--config", config, "--format", "json"]);
+        if let Some(venv) = venv {
+            cmd.env("VIRTUAL_ENV", venv);
+        }
+        let output = cmd.output().unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let json = run(None);
+    assert_eq!(json["virtual_env"], "ml");
+    assert_eq!(json["virtual_env_python"], "3.11.5");
+    assert_eq!(json["virtual_env_manager"], "mamba");
+    assert_eq!(json["conda_env_count"], 2);
+    assert!(json.get("conda_env").is_none());
+
+    // A venv activated inside the conda environment wins, the conda env is noted
+    let venv = home.path().join("app/.venv");
+    std::fs::create_dir_all(&venv).unwrap();
+    std::fs::write(venv.join("pyvenv.cfg"), "version = 3.12.1\n").unwrap();
+    let json = run(Some(&venv));
+    assert_eq!(json["virtual_env"], ".venv");
+    assert_eq!(json["virtual_env_python"], "3.12.1");
+    assert_eq!(json["conda_env"], "ml");
+}
+
+// --- Corepack ---
+
+#[cfg(unix)]
+#[test]
+fn test_package_manager_pin_mismatch() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    std::fs::create_dir(&project).unwrap();
+    std::fs::write(
+        project.join("package.json"),
+        r#"{"name": "app", "packageManager": "pnpm@8.15.1+sha512.abc"}"#,
+    )
+    .unwrap();
+    let bin = dir.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let pnpm = bin.join("pnpm");
+    std::fs::write(&pnpm, "#!/bin/sh\necho 9.0.0\n").unwrap();
+    std::fs::set_permissions(&pnpm, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(bin).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "pnpm"
+command = "pnpm --version"
+enabled = true
+"#
+    )
+    .unwrap();
+    let config = temp_file.path().to_str().unwrap();
+    let project_dir = project.to_str().unwrap();
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .args(["--config", config, "--dir", project_dir, "doctor", "--json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let tool = &json["tools"][0];
+    assert_eq!(tool["status"], "Warning");
+    assert_eq!(tool["pinned"]["version"], "8.15.1");
+    assert!(tool["error_detail"]
+        .as_str()
+        .unwrap()
+        .contains("packageManager pins pnpm 8.15.1"));
+
+    // `require` checks the pin without a [requirements] table
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .args([
+            "--config",
+            config,
+            "--dir",
+            project_dir,
+            "require",
+            "--json",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results[0]["tool"], "pnpm");
+    assert_eq!(results[0]["constraint"], "=8.15.1");
+    assert_eq!(results[0]["status"], "fail");
+}
+
+// --- Project environments ---
+
+#[cfg(unix)]
+#[test]
+fn test_direnv_environment_wraps_version_commands() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let project = dir.path().join("project");
+    std::fs::create_dir(&project).unwrap();
+    std::fs::write(project.join(".envrc"), "use flake\n").unwrap();
+    let log = dir.path().join("direnv.log");
+    let bin = dir.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let direnv = bin.join("direnv");
+    std::fs::write(
+        &direnv,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> {}\nshift 2\nexec \"$@\"\n",
+            log.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&direnv, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(bin).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[environment]
+direnv = true
+
+[[custom_tools]]
+name = "Echo"
+command = "echo 1.2.3"
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--dir",
+            project.to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["tools"][0]["version"], "1.2.3");
+    let calls = std::fs::read_to_string(&log).unwrap();
+    assert!(calls.contains(&format!("exec {} echo 1.2.3", project.display())));
+}
+
+// --- Container images ---
+
+#[cfg(unix)]
+#[test]
+fn test_container_image_runs_in_docker_and_caches() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("docker.log");
+    let bin = dir.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let docker = bin.join("docker");
+    std::fs::write(
+        &docker,
+        format!(
+            "#!/bin/sh\necho \"$@\" >> {}\ncase \"$1\" in\n  image) echo sha256:abc123 ;;\n  run) echo v20.11.0 ;;\nesac\n",
+            log.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&docker, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(bin).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Node"
+command = "node --version"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+container_image = "node:20-bookworm"
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let run = || {
+        let output = toolbox_cmd()
+            .env("PATH", &path)
+            .env("XDG_CACHE_HOME", dir.path().join("cache"))
+            .args([
+                "--config",
+                temp_file.path().to_str().unwrap(),
+                "--format",
+                "json",
+            ])
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["tools"][0].clone()
+    };
+
+    let tool = run();
+    assert_eq!(tool["version"], "20.11.0");
+    assert_eq!(tool["source"], "docker");
+    assert!(tool.get("path").is_none());
+    let calls = std::fs::read_to_string(&log).unwrap();
+    assert!(calls.contains("run --rm node:20-bookworm node --version"));
+
+    // The second run is answered from the cache for the same image ID
+    let tool = run();
+    assert_eq!(tool["version"], "20.11.0");
+    let calls = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(calls.matches("run --rm").count(), 1);
+    assert_eq!(calls.matches("image inspect").count(), 2);
+}
+
+// --- Parse scripts ---
+
+#[cfg(feature = "scripting")]
+#[test]
+fn test_parse_script_extracts_version() {
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Cloud"
+command = "echo cloud-cli/2.15.10 Python/3.11.6"
+parse_script = 'let parts = stdout.split(" "); #{{ version: parts[0].split("/")[1], runtime: parts[1] }}'
+parse_template = "{{version}} ({{runtime}})"
+enabled = true
+"#
+    )
+    .unwrap();
+
+    let output = toolbox_cmd()
+        .args([
+            "--config",
+            temp_file.path().to_str().unwrap(),
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let tool = &json["tools"][0];
+    assert_eq!(tool["version"], "2.15.10");
+    assert_eq!(tool["runtime"], "Python/3.11.6");
+    assert_eq!(tool["display_version"], "2.15.10 (Python/3.11.6)");
+}
+
+// --- Tool info ---
+
+#[cfg(unix)]
+#[test]
+fn test_tool_info_lists_installed_versions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let home = tempfile::tempdir().unwrap();
+    let shims = home.path().join(".asdf/shims");
+    let bin = home.path().join("bin");
+    let installs = home.path().join(".asdf/installs/fakeplugin");
+    for (path, version) in [
+        (shims.join("faketool"), "2.0.0"),
+        (bin.join("faketool1.5"), "1.5.0"),
+        (installs.join("1.0.0/bin/faketool"), "1.0.0"),
+        (installs.join("2.0.0/bin/faketool"), "2.0.0"),
+    ] {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, format!("#!/bin/sh\necho v{}\n", version)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let path = std::env::join_paths(
+        [shims, bin.clone()]
+            .into_iter()
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let mut temp_file = NamedTempFile::new().unwrap();
+    writeln!(
+        temp_file,
+        r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Fake"
+command = "faketool --version"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+short_name = "fk"
+enabled = true
+"#
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = toolbox_cmd();
+        cmd.env("PATH", &path).env("HOME", home.path());
+        for var in [
+            "ASDF_DATA_DIR",
+            "MISE_DATA_DIR",
+            "NVM_DIR",
+            "PYENV_ROOT",
+            "RBENV_ROOT",
+            "RUSTUP_HOME",
+        ] {
+            cmd.env_remove(var);
+        }
+        cmd.args(["--config", temp_file.path().to_str().unwrap()])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["tool", "info", "fk", "--json"]);
+    assert!(output.status.success());
+    let tool: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tool["version"], "2.0.0");
+    let versions: Vec<(&str, &str, bool)> = tool["installed_versions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| {
+            (
+                v["version"].as_str().unwrap(),
+                v["source"].as_str().unwrap_or(""),
+                v["active"].as_bool().unwrap_or(false),
+            )
+        })
+        .collect();
+    assert_eq!(
+        versions,
+        vec![
+            ("1.5.0", "user", false),
+            ("1.0.0", "asdf", false),
+            ("2.0.0", "asdf", true)
+        ]
+    );
+
+    let output = run(&["tool", "info", "Fake"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!(
+        "  * 2.0.0  {} (asdf)",
+        installs.join("2.0.0/bin/faketool").display()
+    )));
+
+    let output = run(&["tool", "info", "nope"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown tool: nope"));
+}
+
+// --- External detectors ---
+
+#[cfg(unix)]
+#[test]
+fn test_external_detector_in_path() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let detector = dir.path().join("toolbox-detect-corp");
+    std::fs::write(
+        &detector,
+        "#!/bin/sh\necho '{\"name\": \"Corp CLI\", \"version\": \"4.2.0\"}'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&detector, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::join_paths(
+        std::iter::once(dir.path().to_path_buf())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+    let mut temp_file = echo_tool_config();
+    writeln!(temp_file, "[detectors]\nexternal = true").unwrap();
+
+    let output = toolbox_cmd()
+        .env("PATH", &path)
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--no-cache", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let corp = info["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|tool| tool["name"] == "Corp CLI")
+        .expect("detector result missing");
+    assert_eq!(corp["version"], "4.2.0");
+    assert_eq!(corp["path"], detector.to_str().unwrap());
+
+    // --only restricts detection to the named tools
+    toolbox_cmd()
+        .env("PATH", &path)
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--no-cache", "--format", "json", "--only", "Echo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Corp CLI").not());
+}
+
+// --- WASM plugins ---
+
+#[test]
+fn test_format_plugin_errors() {
+    let temp_file = echo_tool_config();
+    let expected = if cfg!(feature = "plugins") {
+        "Unknown plugin: nope"
+    } else {
+        "built without WASM plugin support"
+    };
+    toolbox_cmd()
+        .args(["--config", temp_file.path().to_str().unwrap()])
+        .args(["--no-cache", "--format-plugin", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(expected));
 }
@@ -0,0 +1,240 @@
+//! Detection benchmark
+//!
+//! Runs tool detection repeatedly, with and without the version cache, and
+//! reports min/mean/max timings per tool so changes to detection, caching or
+//! parallelism can be measured.
+
+use crate::cache::VersionCache;
+use crate::config::{Config, ToolConfig};
+use crate::detector::ToolDetector;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Timing statistics for one tool (or the total) over all iterations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchStats {
+    /// Tool name ("Total" for the sum of all tools)
+    pub name: String,
+    /// Fastest run in milliseconds
+    pub min_ms: f64,
+    /// Average run in milliseconds
+    pub mean_ms: f64,
+    /// Slowest run in milliseconds
+    pub max_ms: f64,
+}
+
+impl BenchStats {
+    /// Compute statistics from timing samples (all zero if there are none)
+    pub fn from_samples(name: &str, samples: &[Duration]) -> Self {
+        let ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        let (min_ms, max_ms, mean_ms) = if ms.is_empty() {
+            (0.0, 0.0, 0.0)
+        } else {
+            (
+                ms.iter().copied().fold(f64::INFINITY, f64::min),
+                ms.iter().copied().fold(0.0, f64::max),
+                ms.iter().sum::<f64>() / ms.len() as f64,
+            )
+        };
+        Self {
+            name: name.to_string(),
+            min_ms,
+            mean_ms,
+            max_ms,
+        }
+    }
+}
+
+/// Benchmark results for uncached and cached detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchReport {
+    /// Number of measured iterations per mode
+    pub iterations: usize,
+    /// Detection with the cache disabled (every tool is spawned)
+    pub uncached: Vec<BenchStats>,
+    /// Detection from a warm cache
+    pub cached: Vec<BenchStats>,
+    /// Lookups of the cached pass answered from the cache
+    #[serde(default)]
+    pub cache_hits: u64,
+}
+
+impl BenchReport {
+    /// Format both modes as min/mean/max tables
+    pub fn format_display(&self) -> String {
+        let name_width = self
+            .uncached
+            .iter()
+            .chain(&self.cached)
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Uncached".len());
+
+        let mut lines = vec![format!(
+            "Benchmark: {} iterations, {} tools",
+            self.iterations,
+            self.uncached.len().saturating_sub(1)
+        )];
+        let cached_title = format!("Cached ({} hits)", self.cache_hits);
+        for (title, stats) in [
+            ("Uncached", &self.uncached),
+            (cached_title.as_str(), &self.cached),
+        ] {
+            lines.push(String::new());
+            lines.push(format!(
+                "{:<width$}  {:>10}  {:>10}  {:>10}",
+                title,
+                "min",
+                "mean",
+                "max",
+                width = name_width + 2
+            ));
+            for stat in stats {
+                lines.push(format!(
+                    "  {:<width$}  {:>7.2} ms  {:>7.2} ms  {:>7.2} ms",
+                    stat.name,
+                    stat.min_ms,
+                    stat.mean_ms,
+                    stat.max_ms,
+                    width = name_width
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Benchmark detection of all enabled tools
+/// The cached pass is always measured (after one warm-up run), even if the
+/// cache is disabled in the config.
+pub fn run_bench(config: &Config, working_dir: Option<String>, iterations: usize) -> BenchReport {
+    let tools = config.enabled_tools();
+
+    let mut uncached = ToolDetector::new(config.clone()).with_cache_disabled();
+    let mut cached_config = config.clone();
+    cached_config.cache.enabled = true;
+    let mut cached = ToolDetector::new(cached_config);
+    if let Some(dir) = working_dir {
+        uncached = uncached.with_working_dir(dir.clone());
+        cached = cached.with_working_dir(dir);
+    }
+
+    let uncached_stats = measure(&mut uncached, &tools, iterations);
+    measure(&mut cached, &tools, 1);
+    let hits = |detector: &ToolDetector| detector.cache().map_or(0, VersionCache::hits);
+    let warm_hits = hits(&cached);
+    let cached_stats = measure(&mut cached, &tools, iterations);
+
+    BenchReport {
+        iterations,
+        uncached: uncached_stats,
+        cached: cached_stats,
+        cache_hits: hits(&cached) - warm_hits,
+    }
+}
+
+/// Time each tool per iteration; the last entry is the per-iteration total
+fn measure(
+    detector: &mut ToolDetector,
+    tools: &[ToolConfig],
+    iterations: usize,
+) -> Vec<BenchStats> {
+    let mut samples: Vec<Vec<Duration>> = vec![Vec::with_capacity(iterations); tools.len()];
+    let mut totals = Vec::with_capacity(iterations);
+
+    for _ in 0..iterations {
+        let run = Instant::now();
+        for (tool, tool_samples) in tools.iter().zip(samples.iter_mut()) {
+            let start = Instant::now();
+            detector.detect_tool(tool);
+            tool_samples.push(start.elapsed());
+        }
+        totals.push(run.elapsed());
+    }
+
+    tools
+        .iter()
+        .zip(&samples)
+        .map(|(tool, tool_samples)| BenchStats::from_samples(&tool.name, tool_samples))
+        .chain([BenchStats::from_samples("Total", &totals)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo_config() -> Config {
+        let mut config = Config {
+            use_default_tools: false,
+            ..Default::default()
+        };
+        config.custom_tools.push(ToolConfig {
+            name: "Echo".to_string(),
+            command: "echo v1.0.0".to_string(),
+            parse_regex: Some(r"v?(\d+\.\d+\.\d+)".to_string()),
+            icon: None,
+            enabled: true,
+            short_name: None,
+            ..Default::default()
+        });
+        config
+    }
+
+    #[test]
+    fn test_bench_stats_from_samples() {
+        let samples = [
+            Duration::from_millis(2),
+            Duration::from_millis(4),
+            Duration::from_millis(9),
+        ];
+        let stats = BenchStats::from_samples("Go", &samples);
+        assert_eq!(stats.name, "Go");
+        assert_eq!(stats.min_ms, 2.0);
+        assert_eq!(stats.mean_ms, 5.0);
+        assert_eq!(stats.max_ms, 9.0);
+
+        let empty = BenchStats::from_samples("Go", &[]);
+        assert_eq!(empty.min_ms, 0.0);
+        assert_eq!(empty.max_ms, 0.0);
+    }
+
+    #[test]
+    fn test_run_bench() {
+        let mut config = echo_config();
+        config.cache.enabled = false;
+
+        let report = run_bench(&config, None, 3);
+        assert_eq!(report.iterations, 3);
+        let names: Vec<&str> = report.uncached.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Echo", "Total"]);
+        assert_eq!(report.cached.len(), 2);
+
+        let echo = &report.uncached[0];
+        assert!(echo.min_ms <= echo.mean_ms && echo.mean_ms <= echo.max_ms);
+        // Every measured cached lookup was a hit (no spawn)
+        assert_eq!(report.cache_hits, 3);
+    }
+
+    #[test]
+    fn test_bench_report_format() {
+        let stats =
+            |name: &str, ms: u64| BenchStats::from_samples(name, &[Duration::from_millis(ms)]);
+        let report = BenchReport {
+            iterations: 1,
+            uncached: vec![stats("Echo", 12), stats("Total", 12)],
+            cached: vec![stats("Echo", 0), stats("Total", 0)],
+            cache_hits: 1,
+        };
+
+        let output = report.format_display();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "Benchmark: 1 iterations, 1 tools");
+        assert!(lines[2].starts_with("Uncached"));
+        assert!(lines[2].ends_with("max"));
+        assert_eq!(lines[3], "  Echo        12.00 ms    12.00 ms    12.00 ms");
+        assert!(lines[6].starts_with("Cached (1 hits)"));
+        assert_eq!(lines.len(), 9);
+    }
+}
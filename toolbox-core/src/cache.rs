@@ -1,9 +1,12 @@
 //! Version detection cache for tool version results
 //!
 //! Provides in-memory caching with optional file persistence to avoid
-//! redundant version command executions.
+//! redundant version command executions. Entries are kept per tool and
+//! working directory; with a size cap, the least recently used entries are
+//! evicted so a long-lived server visiting many directories stays bounded.
 
 use crate::info::ToolInfo;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -30,21 +33,98 @@ impl CacheEntry {
         now.saturating_sub(self.detected_at) > self.ttl_seconds
     }
 
+    /// Seconds since the entry was detected
+    pub fn age(&self) -> u64 {
+        current_timestamp().saturating_sub(self.detected_at)
+    }
+
     /// Check if this entry matches the given working directory
     pub fn matches_working_dir(&self, working_dir: &Option<String>) -> bool {
         self.working_dir == *working_dir
     }
 }
 
+/// Snapshot of the cache counters (`/cache/stats` of `toolbox serve`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Whether caching is enabled
+    pub enabled: bool,
+    /// Entries currently cached
+    pub entries: usize,
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that ran the tool
+    pub misses: u64,
+    /// Entries evicted to stay within `max_entries`
+    pub evictions: u64,
+    /// Entries dropped because their TTL had passed
+    pub expirations: u64,
+    /// Hits as a percentage of lookups
+    pub hit_rate: f64,
+    /// Default TTL in seconds
+    pub default_ttl: u64,
+    /// Size cap (0: unlimited)
+    pub max_entries: usize,
+}
+
+impl CacheStats {
+    /// Stats of an optional cache (all zero when caching is disabled)
+    pub fn of(cache: Option<&VersionCache>) -> Self {
+        match cache {
+            Some(cache) => Self {
+                enabled: true,
+                entries: cache.len(),
+                hits: cache.hits(),
+                misses: cache.misses(),
+                evictions: cache.evictions(),
+                expirations: cache.expirations(),
+                hit_rate: cache.hit_rate(),
+                default_ttl: cache.default_ttl(),
+                max_entries: cache.max_entries(),
+            },
+            None => Self {
+                enabled: false,
+                entries: 0,
+                hits: 0,
+                misses: 0,
+                evictions: 0,
+                expirations: 0,
+                hit_rate: 0.0,
+                default_ttl: 0,
+                max_entries: 0,
+            },
+        }
+    }
+}
+
+/// Cache key: tool name and working directory
+type Key = (String, Option<String>);
+
+/// A cached entry and when it was last used
+#[derive(Debug)]
+struct Slot {
+    entry: CacheEntry,
+    /// Value of the use counter at the last lookup or store
+    used: u64,
+}
+
 /// In-memory cache for tool version detection results
 #[derive(Debug)]
 pub struct VersionCache {
-    entries: HashMap<String, CacheEntry>,
+    entries: HashMap<Key, Slot>,
     default_ttl: u64,
+    /// Most entries kept (0: unlimited)
+    max_entries: usize,
+    /// Incremented on every use, to order entries by recency
+    uses: u64,
     /// Statistics: number of cache hits
     hits: u64,
     /// Statistics: number of cache misses
     misses: u64,
+    /// Statistics: entries evicted to stay within `max_entries`
+    evictions: u64,
+    /// Statistics: expired entries dropped
+    expirations: u64,
 }
 
 impl Default for VersionCache {
@@ -59,34 +139,59 @@ impl VersionCache {
         Self {
             entries: HashMap::new(),
             default_ttl,
+            max_entries: 0,
+            uses: 0,
             hits: 0,
             misses: 0,
+            evictions: 0,
+            expirations: 0,
         }
     }
 
+    /// Cap the number of entries (0: unlimited)
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.set_max_entries(max_entries);
+        self
+    }
+
+    /// Change the size cap, evicting the least recently used entries beyond it
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries;
+        self.evict_over_limit();
+    }
+
     /// Look up a cached result for the given tool name and working directory.
-    /// Returns `None` if not found, expired, or working directory doesn't match.
+    /// Returns `None` if not found or expired.
     pub fn get(&mut self, tool_name: &str, working_dir: &Option<String>) -> Option<&ToolInfo> {
-        // Check if entry exists and is valid
-        let valid = self
-            .entries
-            .get(tool_name)
-            .map(|entry| !entry.is_expired() && entry.matches_working_dir(working_dir))
-            .unwrap_or(false);
-
-        if valid {
-            self.hits += 1;
-            self.entries.get(tool_name).map(|e| &e.tool_info)
-        } else {
-            self.misses += 1;
-            // Remove expired entry if present
-            if self.entries.contains_key(tool_name) {
-                self.entries.remove(tool_name);
+        let key = (tool_name.to_string(), working_dir.clone());
+        match self.entries.get(&key).map(|slot| slot.entry.is_expired()) {
+            Some(false) => {
+                self.hits += 1;
+                self.uses += 1;
+                let slot = self.entries.get_mut(&key)?;
+                slot.used = self.uses;
+                Some(&slot.entry.tool_info)
+            }
+            expired => {
+                self.misses += 1;
+                // Remove expired entry if present
+                if expired.is_some() {
+                    self.entries.remove(&key);
+                    self.expirations += 1;
+                }
+                None
             }
-            None
         }
     }
 
+    /// The entry for a tool and working directory, expired or not, without
+    /// counting a lookup
+    pub fn peek(&self, tool_name: &str, working_dir: &Option<String>) -> Option<&CacheEntry> {
+        self.entries
+            .get(&(tool_name.to_string(), working_dir.clone()))
+            .map(|slot| &slot.entry)
+    }
+
     /// Store a detection result in the cache
     pub fn put(&mut self, tool_name: String, tool_info: ToolInfo, working_dir: Option<String>) {
         self.put_with_ttl(tool_name, tool_info, working_dir, self.default_ttl);
@@ -103,10 +208,35 @@ impl VersionCache {
         let entry = CacheEntry {
             tool_info,
             detected_at: current_timestamp(),
-            working_dir,
+            working_dir: working_dir.clone(),
             ttl_seconds,
         };
-        self.entries.insert(tool_name, entry);
+        self.uses += 1;
+        let slot = Slot {
+            entry,
+            used: self.uses,
+        };
+        self.entries.insert((tool_name, working_dir), slot);
+        self.evict_over_limit();
+    }
+
+    /// Drop entries beyond `max_entries`: expired ones first, then the least
+    /// recently used
+    fn evict_over_limit(&mut self) {
+        if self.max_entries == 0 || self.entries.len() <= self.max_entries {
+            return;
+        }
+        self.evict_expired();
+        while self.entries.len() > self.max_entries {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, slot)| slot.used)
+                .map(|(key, _)| key.clone());
+            let Some(key) = oldest else { break };
+            self.entries.remove(&key);
+            self.evictions += 1;
+        }
     }
 
     /// Invalidate all entries (clear the entire cache)
@@ -114,14 +244,16 @@ impl VersionCache {
         self.entries.clear();
     }
 
-    /// Invalidate a specific tool's cache entry
+    /// Invalidate a specific tool's cache entries (in every working directory)
     pub fn invalidate(&mut self, tool_name: &str) {
-        self.entries.remove(tool_name);
+        self.entries.retain(|(tool, _), _| tool != tool_name);
     }
 
     /// Remove all expired entries
     pub fn evict_expired(&mut self) {
-        self.entries.retain(|_, entry| !entry.is_expired());
+        let before = self.entries.len();
+        self.entries.retain(|_, slot| !slot.entry.is_expired());
+        self.expirations += (before - self.entries.len()) as u64;
     }
 
     /// Get the number of entries currently in the cache
@@ -144,6 +276,16 @@ impl VersionCache {
         self.misses
     }
 
+    /// Get the number of entries evicted to stay within the size cap
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Get the number of expired entries dropped
+    pub fn expirations(&self) -> u64 {
+        self.expirations
+    }
+
     /// Get cache hit rate as a percentage (0.0 - 100.0)
     pub fn hit_rate(&self) -> f64 {
         let total = self.hits + self.misses;
@@ -158,12 +300,19 @@ impl VersionCache {
     pub fn reset_stats(&mut self) {
         self.hits = 0;
         self.misses = 0;
+        self.evictions = 0;
+        self.expirations = 0;
     }
 
     /// Get the default TTL
     pub fn default_ttl(&self) -> u64 {
         self.default_ttl
     }
+
+    /// Get the size cap (0: unlimited)
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
 }
 
 /// Get current unix timestamp in seconds
@@ -234,8 +383,11 @@ mod tests {
         let result = cache.get("Python", &Some("/home/user/project-b".to_string()));
         assert!(result.is_none());
 
-        // Mismatched entry should be removed
-        assert!(cache.is_empty());
+        // The other directory's entry is kept
+        assert_eq!(cache.len(), 1);
+        assert!(cache
+            .get("Python", &Some("/home/user/project-a".to_string()))
+            .is_some());
     }
 
     #[test]
@@ -327,12 +479,15 @@ mod tests {
 
         // Add an entry that's already expired (by manipulating directly)
         cache.entries.insert(
-            "OldTool".to_string(),
-            CacheEntry {
-                tool_info: make_tool_info("OldTool", "1.0.0"),
-                detected_at: current_timestamp().saturating_sub(600),
-                working_dir: None,
-                ttl_seconds: 300,
+            ("OldTool".to_string(), None),
+            Slot {
+                entry: CacheEntry {
+                    tool_info: make_tool_info("OldTool", "1.0.0"),
+                    detected_at: current_timestamp().saturating_sub(600),
+                    working_dir: None,
+                    ttl_seconds: 300,
+                },
+                used: 0,
             },
         );
 
@@ -342,9 +497,62 @@ mod tests {
         assert_eq!(cache.len(), 2);
         cache.evict_expired();
         assert_eq!(cache.len(), 1);
+        assert_eq!(cache.expirations(), 1);
         assert!(cache.get("Fresh", &None).is_some());
     }
 
+    // --- Size cap tests ---
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = VersionCache::new(300).with_max_entries(2);
+        let dir = |name: &str| Some(format!("/{}", name));
+        cache.put(
+            "Node".to_string(),
+            make_tool_info("Node", "20.0.0"),
+            dir("a"),
+        );
+        cache.put(
+            "Node".to_string(),
+            make_tool_info("Node", "21.0.0"),
+            dir("b"),
+        );
+        // a is used more recently than b
+        assert!(cache.get("Node", &dir("a")).is_some());
+
+        cache.put(
+            "Node".to_string(),
+            make_tool_info("Node", "22.0.0"),
+            dir("c"),
+        );
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.evictions(), 1);
+        assert!(cache.peek("Node", &dir("b")).is_none());
+        assert!(cache.peek("Node", &dir("a")).is_some());
+        assert!(cache.peek("Node", &dir("c")).is_some());
+
+        // Lowering the cap evicts right away
+        cache.set_max_entries(1);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.peek("Node", &dir("c")).is_some());
+        assert_eq!(cache.evictions(), 2);
+    }
+
+    #[test]
+    fn test_cache_unlimited_by_default() {
+        let mut cache = VersionCache::new(300);
+        assert_eq!(cache.max_entries(), 0);
+        for i in 0..100 {
+            cache.put(
+                "Node".to_string(),
+                make_tool_info("Node", "20.0.0"),
+                Some(format!("/project-{}", i)),
+            );
+        }
+        assert_eq!(cache.len(), 100);
+        assert_eq!(cache.evictions(), 0);
+    }
+
     // --- Statistics tests ---
 
     #[test]
@@ -378,6 +586,24 @@ mod tests {
         assert!((cache.hit_rate() - 50.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn test_cache_stats_snapshot() {
+        assert!(!CacheStats::of(None).enabled);
+
+        let mut cache = VersionCache::new(60).with_max_entries(1);
+        cache.put("Go".to_string(), make_tool_info("Go", "1.22.0"), None);
+        cache.put("Node".to_string(), make_tool_info("Node", "20.0.0"), None);
+        cache.get("Node", &None);
+        cache.get("Python", &None);
+        let stats = CacheStats::of(Some(&cache));
+        assert!(stats.enabled);
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 1, 1));
+        assert_eq!((stats.evictions, stats.expirations), (1, 0));
+        assert_eq!(stats.hit_rate, 50.0);
+        assert_eq!(stats.default_ttl, 60);
+        assert_eq!(stats.max_entries, 1);
+    }
+
     #[test]
     fn test_cache_stats_reset() {
         let mut cache = VersionCache::new(300);
@@ -435,10 +661,28 @@ mod tests {
             60, // 1 minute TTL
         );
 
-        let entry = cache.entries.get("Python").unwrap();
+        let entry = cache.peek("Python", &None).unwrap();
         assert_eq!(entry.ttl_seconds, 60);
     }
 
+    #[test]
+    fn test_cache_peek() {
+        let mut cache = VersionCache::new(300);
+        let dir = Some("/project".to_string());
+        cache.put(
+            "Python".to_string(),
+            make_tool_info("Python", "3.12.0"),
+            dir.clone(),
+        );
+
+        let entry = cache.peek("Python", &dir).unwrap();
+        assert_eq!(entry.tool_info.version.as_deref(), Some("3.12.0"));
+        assert_eq!(entry.age(), 0);
+        assert!(cache.peek("Python", &None).is_none());
+        // Not a lookup
+        assert_eq!(cache.hits() + cache.misses(), 0);
+    }
+
     #[test]
     fn test_cache_overwrite_entry() {
         let mut cache = VersionCache::new(300);
@@ -0,0 +1,161 @@
+//! Cancellation of a running detection
+//!
+//! A long-lived caller (a daemon answering a newer request, a watch loop
+//! whose debounce fires again) cancels the detection in flight instead of
+//! waiting for it: the running version command is killed, the remaining
+//! tools are skipped and nothing detected after the cancellation is cached.
+
+use std::io::{self, Read};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often a running command checks for cancellation
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Shared flag that cancels the detections it is attached to; clones share it
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// A token that isn't cancelled yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel every detection using this token (or a clone of it)
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// How a command run with [`output_within`] ended
+#[derive(Debug)]
+pub enum Finished {
+    /// It exited by itself
+    Exited(Output),
+    /// It was killed: the token was cancelled
+    Cancelled,
+    /// It was killed: it ran past the timeout
+    TimedOut,
+}
+
+/// Run a command like [`Command::output`], killing it if `token` is
+/// cancelled before it exits; `None` if it was
+pub fn output(cmd: &mut Command, token: &CancellationToken) -> io::Result<Option<Output>> {
+    Ok(match output_within(cmd, Some(token), None)? {
+        Finished::Exited(output) => Some(output),
+        Finished::Cancelled | Finished::TimedOut => None,
+    })
+}
+
+/// Run a command like [`Command::output`], killing it when `token` is
+/// cancelled or `timeout` has passed
+pub fn output_within(
+    cmd: &mut Command,
+    token: Option<&CancellationToken>,
+    timeout: Option<Duration>,
+) -> io::Result<Finished> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        let killed = if token.is_some_and(CancellationToken::is_cancelled) {
+            Some(Finished::Cancelled)
+        } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            Some(Finished::TimedOut)
+        } else {
+            None
+        };
+        if let Some(killed) = killed {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(killed);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+    Ok(Finished::Exited(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+/// Read a child's pipe to the end on its own thread, so a chatty command
+/// can't block on a full pipe while it's being polled
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_runs_to_completion() {
+        let token = CancellationToken::new();
+        let output = output(Command::new("echo").arg("1.2.3"), &token)
+            .unwrap()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "1.2.3");
+    }
+
+    #[test]
+    fn test_output_killed_on_cancel() {
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+
+        let start = Instant::now();
+        let output = output(Command::new("sleep").arg("5"), &token).unwrap();
+        assert!(output.is_none());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_output_within_timeout() {
+        let start = Instant::now();
+        let finished = output_within(
+            Command::new("sleep").arg("5"),
+            None,
+            Some(Duration::from_millis(100)),
+        )
+        .unwrap();
+        assert!(matches!(finished, Finished::TimedOut));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        let finished = output_within(
+            Command::new("true").arg(""),
+            None,
+            Some(Duration::from_secs(5)),
+        )
+        .unwrap();
+        assert!(matches!(finished, Finished::Exited(output) if output.status.success()));
+    }
+}
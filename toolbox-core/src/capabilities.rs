@@ -0,0 +1,112 @@
+//! CLI capabilities for the Zellij plugin handshake
+//!
+//! `toolbox --capabilities` prints what the installed CLI supports. The plugin
+//! compares it with the protocol and flags it was built against, so a version
+//! mismatch produces a clear warning instead of a cryptic argument error.
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the plugin ↔ CLI protocol (output formats and flags the plugin
+/// relies on); bumped on incompatible changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// What the installed CLI supports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// CLI version
+    pub version: String,
+    /// Plugin protocol version
+    pub protocol: u32,
+    /// Output formats accepted by `--format`
+    #[serde(default)]
+    pub formats: Vec<String>,
+    /// Long flags accepted on the command line (e.g. `--max-width`)
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+/// How a CLI relates to what the caller expects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Same protocol, every required flag present
+    Compatible,
+    /// The CLI speaks an older protocol
+    CliOlder,
+    /// The CLI speaks a newer protocol
+    CliNewer,
+    /// Same protocol, but these required flags are missing
+    MissingFlags(Vec<String>),
+}
+
+impl Capabilities {
+    /// Capabilities of this build with the given formats and flags
+    pub fn new(formats: Vec<String>, flags: Vec<String>) -> Self {
+        Capabilities {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol: PROTOCOL_VERSION,
+            formats,
+            flags,
+        }
+    }
+
+    /// Check against the protocol and flags a caller was built for
+    pub fn check(&self, protocol: u32, required_flags: &[&str]) -> Compatibility {
+        if self.protocol < protocol {
+            return Compatibility::CliOlder;
+        }
+        if self.protocol > protocol {
+            return Compatibility::CliNewer;
+        }
+        let missing: Vec<String> = required_flags
+            .iter()
+            .filter(|flag| !self.flags.iter().any(|f| f == *flag))
+            .map(|flag| flag.to_string())
+            .collect();
+        if missing.is_empty() {
+            Compatibility::Compatible
+        } else {
+            Compatibility::MissingFlags(missing)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(protocol: u32) -> Capabilities {
+        Capabilities {
+            protocol,
+            ..Capabilities::new(
+                vec!["text".to_string(), "json".to_string()],
+                vec!["--format".to_string(), "--dir".to_string()],
+            )
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(
+            caps(PROTOCOL_VERSION).check(PROTOCOL_VERSION, &["--dir"]),
+            Compatibility::Compatible
+        );
+        assert_eq!(caps(1).check(2, &[]), Compatibility::CliOlder);
+        assert_eq!(caps(3).check(2, &[]), Compatibility::CliNewer);
+        assert_eq!(
+            caps(1).check(1, &["--dir", "--only"]),
+            Compatibility::MissingFlags(vec!["--only".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let json = serde_json::to_string(&caps(1)).unwrap();
+        let parsed: Capabilities = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, caps(1));
+
+        // Fields added later default to empty
+        let minimal: Capabilities =
+            serde_json::from_str(r#"{"version":"0.1.0","protocol":1}"#).unwrap();
+        assert!(minimal.flags.is_empty());
+    }
+}
@@ -36,6 +36,12 @@ pub mod ansi {
 /// Powerline separator characters
 pub const SEPARATOR_RIGHT: char = '\u{E0B0}'; //
 pub const SEPARATOR_RIGHT_THIN: char = '\u{E0B1}'; //
+pub const SEPARATOR_LEFT: char = '\u{E0B2}'; //
+pub const SEPARATOR_LEFT_THIN: char = '\u{E0B3}'; //
+
+/// Thin separators between sub-segments, padded with spaces
+const THIN_RIGHT: &str = " \u{E0B1} ";
+const THIN_LEFT: &str = " \u{E0B3} ";
 
 /// Color mode for output
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -63,12 +69,28 @@ impl std::str::FromStr for ColorMode {
 }
 
 /// A colored segment in the powerline
+///
+/// Built from theme colors and refined with the `with_*` methods, e.g.
+/// `Segment::from_theme_colors("main", &fg, &bg).with_icon("🌿").with_priority(30)`.
 #[derive(Debug, Clone)]
 pub struct Segment {
+    /// Text shown after the icon
     pub text: String,
+    /// ANSI escape for the text color
     pub fg: String,
+    /// ANSI escape for the background color
     pub bg: String,
     pub bg_color_fg: String, // foreground color matching the background (for separator)
+    /// Icon shown before the text
+    pub icon: Option<String>,
+    /// Sub-segments after the text, divided by thin separators on the same background
+    pub parts: Vec<String>,
+    /// Abbreviated text used when space runs out (see [`fit_segments`])
+    pub short: Option<String>,
+    /// Importance when space runs out (higher is kept longer)
+    pub priority: u8,
+    /// Section the segment shows (`None` for custom segments)
+    pub section: Option<Section>,
 }
 
 impl Segment {
@@ -78,6 +100,11 @@ impl Segment {
             fg: fg.to_string(),
             bg: bg.to_string(),
             bg_color_fg: bg_color_fg.to_string(),
+            icon: None,
+            parts: Vec::new(),
+            short: None,
+            priority: 0,
+            section: None,
         }
     }
 
@@ -87,12 +114,109 @@ impl Segment {
         fg_color: &ThemeColor,
         bg_color: &ThemeColor,
     ) -> Self {
-        Self {
-            text: text.into(),
-            fg: fg_color.to_ansi_fg(),
-            bg: bg_color.to_ansi_bg(),
-            bg_color_fg: bg_color.to_ansi_fg(),
+        Self::new(
+            text,
+            &fg_color.to_ansi_fg(),
+            &bg_color.to_ansi_bg(),
+            &bg_color.to_ansi_fg(),
+        )
+    }
+
+    /// Set the text color
+    pub fn with_fg(mut self, color: &ThemeColor) -> Self {
+        self.fg = color.to_ansi_fg();
+        self
+    }
+
+    /// Set the background color (and the matching separator color)
+    pub fn with_bg(mut self, color: &ThemeColor) -> Self {
+        self.bg = color.to_ansi_bg();
+        self.bg_color_fg = color.to_ansi_fg();
+        self
+    }
+
+    /// Set the icon shown before the text
+    pub fn with_icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    /// Add a sub-segment after the text (and any earlier sub-segments)
+    pub fn with_part(mut self, part: impl Into<String>) -> Self {
+        self.parts.push(part.into());
+        self
+    }
+
+    /// Set the abbreviated text used when space runs out (sub-segments are dropped)
+    pub fn with_short(mut self, short: impl Into<String>) -> Self {
+        self.short = Some(short.into());
+        self
+    }
+
+    /// Replace the text and sub-segments with the abbreviation (no-op without one)
+    pub fn abbreviate(&mut self) {
+        if let Some(short) = self.short.take() {
+            self.text = short;
+            self.parts.clear();
+        }
+    }
+
+    /// Cut the content down to `width` columns, ending in `…` when it is cut
+    /// (the icon and sub-segments become part of the plain text)
+    pub fn truncate(&mut self, width: usize) {
+        let label = self.label();
+        self.icon = None;
+        self.parts.clear();
+        self.short = None;
+        if display_width(&label) <= width {
+            self.text = label;
+            return;
+        }
+        let mut text = String::new();
+        let mut used = 0;
+        for c in label.chars() {
+            let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+            if used + char_width >= width {
+                break;
+            }
+            text.push(c);
+            used += char_width;
+        }
+        self.text = text.trim_end().to_string();
+        if width > 0 {
+            self.text.push('…');
+        }
+    }
+
+    /// Set the priority (higher is kept longer when space runs out)
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set the section the segment shows
+    pub fn with_section(mut self, section: Section) -> Self {
+        self.section = Some(section);
+        self
+    }
+
+    /// Plain content: the icon (if any), the text and the sub-segments,
+    /// separated by spaces
+    pub fn label(&self) -> String {
+        self.label_with(" ")
+    }
+
+    /// Content with sub-segments divided by `separator`
+    fn label_with(&self, separator: &str) -> String {
+        let mut label = match self.icon.as_deref() {
+            Some(icon) if !icon.is_empty() => format!("{} {}", icon, self.text),
+            _ => self.text.clone(),
+        };
+        for part in &self.parts {
+            label.push_str(separator);
+            label.push_str(part);
         }
+        label
     }
 
     /// Create a blue segment (for directory)
@@ -137,7 +261,7 @@ pub fn render_powerline(segments: &[Segment], use_color: bool) -> String {
         // Plain text fallback
         return segments
             .iter()
-            .map(|s| s.text.clone())
+            .map(Segment::label)
             .collect::<Vec<_>>()
             .join(" | ");
     }
@@ -149,7 +273,7 @@ pub fn render_powerline(segments: &[Segment], use_color: bool) -> String {
         result.push_str(&segment.bg);
         result.push_str(&segment.fg);
         result.push(' ');
-        result.push_str(&segment.text);
+        result.push_str(&segment.label_with(THIN_RIGHT));
         result.push(' ');
 
         // Separator
@@ -172,13 +296,160 @@ pub fn render_powerline(segments: &[Segment], use_color: bool) -> String {
     result
 }
 
+/// Render segments as a right-aligned powerline string (left-pointing separators)
+pub fn render_powerline_right(segments: &[Segment], use_color: bool) -> String {
+    if !use_color || segments.is_empty() {
+        // Plain text fallback
+        return segments
+            .iter()
+            .map(Segment::label)
+            .collect::<Vec<_>>()
+            .join(" | ");
+    }
+
+    let mut result = String::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        // Separator on the previous segment's background (or terminal default)
+        result.push_str(ansi::RESET);
+        if i > 0 {
+            result.push_str(&segments[i - 1].bg);
+        }
+        result.push_str(&segment.bg_color_fg);
+        result.push(SEPARATOR_LEFT);
+
+        result.push_str(&segment.bg);
+        result.push_str(&segment.fg);
+        result.push(' ');
+        result.push_str(&segment.label_with(THIN_LEFT));
+        result.push(' ');
+    }
+    result.push_str(ansi::RESET);
+
+    result
+}
+
+/// Render a split powerline: `left` segments left-aligned, `right` segments
+/// right-aligned, padded to `width` when given (at least one space between)
+pub fn render_powerline_split(
+    left: &[Segment],
+    right: &[Segment],
+    use_color: bool,
+    width: Option<usize>,
+) -> String {
+    let left = render_powerline(left, use_color);
+    let right = render_powerline_right(right, use_color);
+
+    let used = display_width(&left) + display_width(&right);
+    let min_gap = usize::from(!left.is_empty() && !right.is_empty());
+    let gap = width.map_or(min_gap, |w| w.saturating_sub(used).max(min_gap));
+
+    format!("{}{}{}", left, " ".repeat(gap), right)
+}
+
+/// Fit segments into `max_width` columns of `render` output
+///
+/// Overflow policy, lowest priority first (rightmost among equals): drop
+/// segments below the highest priority, then abbreviate segments to their
+/// short text, then drop the rest down to one segment, then truncate that
+/// segment with `…`. Without `drop` (e.g. one segment per line) segments are
+/// only abbreviated, then each row still too wide is truncated.
+pub fn fit_segments(
+    mut segments: Vec<Segment>,
+    max_width: usize,
+    drop: bool,
+    render: impl Fn(&[Segment]) -> String,
+) -> Vec<Segment> {
+    let fits = |segments: &[Segment]| {
+        render(segments)
+            .lines()
+            .map(display_width)
+            .max()
+            .unwrap_or(0)
+            <= max_width
+    };
+
+    let top = segments.iter().map(|s| s.priority).max().unwrap_or(0);
+    while drop && !fits(&segments) {
+        match lowest_priority(&segments, |s| s.priority < top) {
+            Some(index) => segments.remove(index),
+            None => break,
+        };
+    }
+    while !fits(&segments) {
+        match lowest_priority(&segments, |s| s.short.is_some()) {
+            Some(index) => segments[index].abbreviate(),
+            None => break,
+        }
+    }
+    while drop && segments.len() > 1 && !fits(&segments) {
+        if let Some(index) = lowest_priority(&segments, |_| true) {
+            segments.remove(index);
+        }
+    }
+    if fits(&segments) {
+        return segments;
+    }
+    for segment in &mut segments {
+        let width = |segment: &Segment| {
+            render(std::slice::from_ref(segment))
+                .lines()
+                .map(display_width)
+                .max()
+                .unwrap_or(0)
+        };
+        if width(segment) > max_width {
+            // Flatten first so the space around the text can be measured
+            segment.truncate(usize::MAX);
+            let chrome = width(segment) - display_width(&segment.text);
+            segment.truncate(max_width.saturating_sub(chrome));
+        }
+    }
+    segments
+}
+
+/// Index of the lowest-priority segment matching `filter` (rightmost among equals)
+fn lowest_priority(segments: &[Segment], filter: impl Fn(&Segment) -> bool) -> Option<usize> {
+    segments
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| filter(s))
+        .min_by_key(|(i, s)| (s.priority, std::cmp::Reverse(*i)))
+        .map(|(i, _)| i)
+}
+
+/// Display width of a string in terminal columns
+/// ANSI escape sequences have zero width; wide characters (e.g. emojis) count as 2
+pub fn display_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip CSI sequences (ESC [ ... final letter)
+            if chars.peek() == Some(&'[') {
+                chars.next();
+                for next_c in chars.by_ref() {
+                    if next_c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        width += UnicodeWidthChar::width(c).unwrap_or(0);
+    }
+
+    width
+}
+
 /// Render segments as multiline powerline (each segment on its own line)
 pub fn render_powerline_multiline(segments: &[Segment], use_color: bool) -> String {
     if !use_color || segments.is_empty() {
         // Plain text fallback
         return segments
             .iter()
-            .map(|s| format!(" {}", s.text))
+            .map(|s| format!(" {}", s.label()))
             .collect::<Vec<_>>()
             .join("\n");
     }
@@ -192,7 +463,7 @@ pub fn render_powerline_multiline(segments: &[Segment], use_color: bool) -> Stri
         line.push_str(&segment.bg);
         line.push_str(&segment.fg);
         line.push(' ');
-        line.push_str(&segment.text);
+        line.push_str(&segment.label_with(THIN_RIGHT));
         line.push(' ');
 
         // End of line separator
@@ -207,7 +478,21 @@ pub fn render_powerline_multiline(segments: &[Segment], use_color: bool) -> Stri
     lines.join("\n")
 }
 
-use crate::config::{CustomThemeConfig, ThemeColor, ThemeConfig};
+use crate::config::{CustomThemeConfig, Section, ThemeColor, ThemeConfig};
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthChar;
+
+/// Names of the built-in theme presets accepted by `ResolvedTheme::from_preset`
+pub const THEME_PRESETS: &[&str] = &[
+    "default",
+    "dark",
+    "light",
+    "solarized",
+    "nord",
+    "dracula",
+    "gruvbox",
+    "catppuccin",
+];
 
 /// A fully resolved theme with all colors determined
 #[derive(Debug, Clone)]
@@ -309,19 +594,147 @@ impl ResolvedTheme {
         }
     }
 
+    /// Nord theme
+    pub fn nord_theme() -> Self {
+        Self {
+            directory_bg: ThemeColor::Rgb(0x5E, 0x81, 0xAC), // nord10
+            directory_fg: ThemeColor::Rgb(0xEC, 0xEF, 0xF4), // nord6
+            git_clean_bg: ThemeColor::Rgb(0xA3, 0xBE, 0x8C), // nord14
+            git_clean_fg: ThemeColor::Rgb(0x2E, 0x34, 0x40), // nord0
+            git_dirty_bg: ThemeColor::Rgb(0xEB, 0xCB, 0x8B), // nord13
+            git_dirty_fg: ThemeColor::Rgb(0x2E, 0x34, 0x40),
+            tool_colors: vec![
+                (
+                    ThemeColor::Rgb(0x88, 0xC0, 0xD0), // nord8
+                    ThemeColor::Rgb(0x2E, 0x34, 0x40),
+                ),
+                (
+                    ThemeColor::Rgb(0xB4, 0x8E, 0xAD), // nord15
+                    ThemeColor::Rgb(0x2E, 0x34, 0x40),
+                ),
+                (
+                    ThemeColor::Rgb(0x4C, 0x56, 0x6A), // nord3
+                    ThemeColor::Rgb(0xEC, 0xEF, 0xF4),
+                ),
+            ],
+            venv_bg: ThemeColor::Rgb(0xA3, 0xBE, 0x8C),
+            venv_fg: ThemeColor::Rgb(0x2E, 0x34, 0x40),
+        }
+    }
+
+    /// Dracula theme
+    pub fn dracula_theme() -> Self {
+        Self {
+            directory_bg: ThemeColor::Rgb(0xBD, 0x93, 0xF9), // purple
+            directory_fg: ThemeColor::Rgb(0x28, 0x2A, 0x36), // background
+            git_clean_bg: ThemeColor::Rgb(0x50, 0xFA, 0x7B), // green
+            git_clean_fg: ThemeColor::Rgb(0x28, 0x2A, 0x36),
+            git_dirty_bg: ThemeColor::Rgb(0xFF, 0xB8, 0x6C), // orange
+            git_dirty_fg: ThemeColor::Rgb(0x28, 0x2A, 0x36),
+            tool_colors: vec![
+                (
+                    ThemeColor::Rgb(0x8B, 0xE9, 0xFD), // cyan
+                    ThemeColor::Rgb(0x28, 0x2A, 0x36),
+                ),
+                (
+                    ThemeColor::Rgb(0xFF, 0x79, 0xC6), // pink
+                    ThemeColor::Rgb(0x28, 0x2A, 0x36),
+                ),
+                (
+                    ThemeColor::Rgb(0x44, 0x47, 0x5A), // current line
+                    ThemeColor::Rgb(0xF8, 0xF8, 0xF2), // foreground
+                ),
+            ],
+            venv_bg: ThemeColor::Rgb(0x50, 0xFA, 0x7B),
+            venv_fg: ThemeColor::Rgb(0x28, 0x2A, 0x36),
+        }
+    }
+
+    /// Gruvbox (dark) theme
+    pub fn gruvbox_theme() -> Self {
+        Self {
+            directory_bg: ThemeColor::Rgb(0x45, 0x85, 0x88), // blue
+            directory_fg: ThemeColor::Rgb(0xEB, 0xDB, 0xB2), // fg
+            git_clean_bg: ThemeColor::Rgb(0x98, 0x97, 0x1A), // green
+            git_clean_fg: ThemeColor::Rgb(0x28, 0x28, 0x28), // bg
+            git_dirty_bg: ThemeColor::Rgb(0xD7, 0x99, 0x21), // yellow
+            git_dirty_fg: ThemeColor::Rgb(0x28, 0x28, 0x28),
+            tool_colors: vec![
+                (
+                    ThemeColor::Rgb(0x68, 0x9D, 0x6A), // aqua
+                    ThemeColor::Rgb(0x28, 0x28, 0x28),
+                ),
+                (
+                    ThemeColor::Rgb(0xB1, 0x62, 0x86), // purple
+                    ThemeColor::Rgb(0xEB, 0xDB, 0xB2),
+                ),
+                (
+                    ThemeColor::Rgb(0x50, 0x49, 0x45), // bg2
+                    ThemeColor::Rgb(0xEB, 0xDB, 0xB2),
+                ),
+            ],
+            venv_bg: ThemeColor::Rgb(0x98, 0x97, 0x1A),
+            venv_fg: ThemeColor::Rgb(0x28, 0x28, 0x28),
+        }
+    }
+
+    /// Catppuccin (Mocha) theme
+    pub fn catppuccin_theme() -> Self {
+        Self {
+            directory_bg: ThemeColor::Rgb(0x89, 0xB4, 0xFA), // blue
+            directory_fg: ThemeColor::Rgb(0x1E, 0x1E, 0x2E), // base
+            git_clean_bg: ThemeColor::Rgb(0xA6, 0xE3, 0xA1), // green
+            git_clean_fg: ThemeColor::Rgb(0x1E, 0x1E, 0x2E),
+            git_dirty_bg: ThemeColor::Rgb(0xF9, 0xE2, 0xAF), // yellow
+            git_dirty_fg: ThemeColor::Rgb(0x1E, 0x1E, 0x2E),
+            tool_colors: vec![
+                (
+                    ThemeColor::Rgb(0x94, 0xE2, 0xD5), // teal
+                    ThemeColor::Rgb(0x1E, 0x1E, 0x2E),
+                ),
+                (
+                    ThemeColor::Rgb(0xCB, 0xA6, 0xF7), // mauve
+                    ThemeColor::Rgb(0x1E, 0x1E, 0x2E),
+                ),
+                (
+                    ThemeColor::Rgb(0x45, 0x47, 0x5A), // surface1
+                    ThemeColor::Rgb(0xCD, 0xD6, 0xF4), // text
+                ),
+            ],
+            venv_bg: ThemeColor::Rgb(0xA6, 0xE3, 0xA1),
+            venv_fg: ThemeColor::Rgb(0x1E, 0x1E, 0x2E),
+        }
+    }
+
     /// Get a preset theme by name
     pub fn from_preset(name: &str) -> Self {
         match name {
             "dark" => Self::dark_theme(),
             "light" => Self::light_theme(),
             "solarized" => Self::solarized_theme(),
+            "nord" => Self::nord_theme(),
+            "dracula" => Self::dracula_theme(),
+            "gruvbox" => Self::gruvbox_theme(),
+            "catppuccin" => Self::catppuccin_theme(),
             _ => Self::default_theme(),
         }
     }
 
     /// Resolve a theme from config: start with preset, apply custom overrides
     pub fn from_config(config: &ThemeConfig) -> Self {
-        let mut theme = Self::from_preset(&config.preset);
+        Self::from_config_with_themes(config, &BTreeMap::new())
+    }
+
+    /// Resolve a theme from config where the preset may also name a
+    /// user-defined theme (which itself builds on a built-in preset)
+    pub fn from_config_with_themes(
+        config: &ThemeConfig,
+        user_themes: &BTreeMap<String, ThemeConfig>,
+    ) -> Self {
+        let mut theme = match user_themes.get(&config.preset) {
+            Some(user_theme) => Self::from_config(user_theme),
+            None => Self::from_preset(&config.preset),
+        };
 
         if let Some(ref custom) = config.custom {
             Self::apply_custom(&mut theme, custom);
@@ -330,6 +743,11 @@ impl ResolvedTheme {
         theme
     }
 
+    /// Check whether a name refers to a built-in preset
+    pub fn is_preset(name: &str) -> bool {
+        THEME_PRESETS.contains(&name)
+    }
+
     fn apply_custom(theme: &mut Self, custom: &CustomThemeConfig) {
         if let Some(ref c) = custom.directory_bg {
             theme.directory_bg = c.clone();
@@ -418,14 +836,42 @@ impl ThemeColor {
 }
 
 /// Check if stdout is a terminal that supports colors
+///
+/// An explicit `Always`/`Never` wins. In `Auto` mode the `NO_COLOR`,
+/// `CLICOLOR_FORCE`, `CLICOLOR` and `TERM=dumb` conventions are honored
+/// before falling back to TTY detection.
 pub fn should_use_color(mode: ColorMode) -> bool {
+    // Check if stdout is a TTY using std::io::IsTerminal (Rust 1.70+)
+    use std::io::IsTerminal;
+    let is_terminal = std::io::stdout().is_terminal();
+    resolve_color(mode, |key| std::env::var(key).ok(), is_terminal)
+}
+
+/// Decide whether to use color given an environment lookup and TTY state
+pub(crate) fn resolve_color(
+    mode: ColorMode,
+    env: impl Fn(&str) -> Option<String>,
+    is_terminal: bool,
+) -> bool {
     match mode {
         ColorMode::Always => true,
         ColorMode::Never => false,
         ColorMode::Auto => {
-            // Check if stdout is a TTY using std::io::IsTerminal (Rust 1.70+)
-            use std::io::IsTerminal;
-            std::io::stdout().is_terminal()
+            // https://no-color.org: any non-empty value disables color
+            if env("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+                return false;
+            }
+            // https://bixense.com/clicolors: force color even when not a TTY
+            if env("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+                return true;
+            }
+            if env("CLICOLOR").is_some_and(|v| v == "0") {
+                return false;
+            }
+            if env("TERM").is_some_and(|v| v == "dumb") {
+                return false;
+            }
+            is_terminal
         }
     }
 }
@@ -434,6 +880,65 @@ pub fn should_use_color(mode: ColorMode) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_render_powerline_right_no_color() {
+        let segments = vec![Segment::blue("a"), Segment::green("b")];
+        assert_eq!(render_powerline_right(&segments, false), "a | b");
+    }
+
+    #[test]
+    fn test_render_powerline_right_with_color() {
+        let segments = vec![Segment::blue("a"), Segment::green("b")];
+        let result = render_powerline_right(&segments, true);
+        assert_eq!(result.matches(SEPARATOR_LEFT).count(), 2);
+        assert!(!result.contains(SEPARATOR_RIGHT));
+        assert!(result.starts_with(ansi::RESET));
+        assert!(result.ends_with(ansi::RESET));
+        assert_eq!(display_width(&result), 8);
+    }
+
+    #[test]
+    fn test_render_powerline_split_pads_to_width() {
+        let left = vec![Segment::blue("dir")];
+        let right = vec![Segment::green("tool")];
+        let result = render_powerline_split(&left, &right, false, Some(20));
+        assert_eq!(result, format!("dir{}tool", " ".repeat(13)));
+        assert_eq!(display_width(&result), 20);
+    }
+
+    #[test]
+    fn test_render_powerline_split_min_gap() {
+        let left = vec![Segment::blue("dir")];
+        let right = vec![Segment::green("tool")];
+        assert_eq!(
+            render_powerline_split(&left, &right, false, None),
+            "dir tool"
+        );
+        assert_eq!(
+            render_powerline_split(&left, &right, false, Some(3)),
+            "dir tool"
+        );
+        // Right-only output is pushed to the right edge
+        assert_eq!(
+            render_powerline_split(&[], &right, false, Some(6)),
+            "  tool"
+        );
+    }
+
+    #[test]
+    fn test_display_width_plain_and_wide() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("🐍 3.12"), 7);
+        assert_eq!(display_width(""), 0);
+    }
+
+    #[test]
+    fn test_display_width_skips_ansi() {
+        let s = format!("{}{} hi {}", ansi::BG_BLUE, ansi::FG_WHITE, ansi::RESET);
+        assert_eq!(display_width(&s), 4);
+        assert_eq!(display_width("\x1b[48;2;1;2;3mX\x1b[0m"), 1);
+    }
+
     // --- ColorMode ---
 
     #[test]
@@ -471,6 +976,92 @@ mod tests {
         assert_eq!(seg.text, "test");
     }
 
+    #[test]
+    fn test_segment_builder() {
+        let seg = Segment::blue("main")
+            .with_fg(&ThemeColor::Black)
+            .with_bg(&ThemeColor::Green)
+            .with_icon("🌿")
+            .with_priority(30)
+            .with_section(Section::Git);
+        assert_eq!(seg.fg.as_str(), ansi::FG_BLACK);
+        assert_eq!(seg.bg.as_str(), ansi::BG_GREEN);
+        assert_eq!(seg.bg_color_fg.as_str(), ansi::FG_GREEN);
+        assert_eq!(seg.label(), "🌿 main");
+        assert_eq!(seg.priority, 30);
+        assert_eq!(seg.section, Some(Section::Git));
+        assert_eq!(render_powerline(&[seg], false), "🌿 main");
+    }
+
+    #[test]
+    fn test_segment_parts() {
+        let segments = vec![Segment::green("main").with_part("+3").with_part("↑2")];
+        assert_eq!(segments[0].label(), "main +3 ↑2");
+        assert_eq!(render_powerline(&segments, false), "main +3 ↑2");
+        assert!(render_powerline(&segments, true).contains("main \u{E0B1} +3 \u{E0B1} ↑2"));
+        assert!(render_powerline_right(&segments, true).contains("main \u{E0B3} +3 \u{E0B3} ↑2"));
+        assert!(render_powerline_multiline(&segments, true).contains("main \u{E0B1} +3"));
+
+        let mut seg = segments[0].clone().with_short("main");
+        seg.abbreviate();
+        assert_eq!(seg.label(), "main");
+    }
+
+    #[test]
+    fn test_fit_segments() {
+        let segments = vec![
+            Segment::blue("/home/user/project").with_priority(10),
+            Segment::cyan("Node.js 22.11.0")
+                .with_short("22")
+                .with_priority(40),
+            Segment::cyan("Python 3.12.7")
+                .with_short("3")
+                .with_priority(40),
+        ];
+        let render = |segments: &[Segment]| render_powerline(segments, false);
+        let fit = |max_width| render(&fit_segments(segments.clone(), max_width, true, render));
+
+        assert_eq!(
+            fit(100),
+            "/home/user/project | Node.js 22.11.0 | Python 3.12.7"
+        );
+        // Lowest priority dropped before anything is abbreviated
+        assert_eq!(fit(40), "Node.js 22.11.0 | Python 3.12.7");
+        // Then abbreviated, rightmost first
+        assert_eq!(fit(20), "Node.js 22.11.0 | 3");
+        assert_eq!(fit(10), "22 | 3");
+        // Then dropped, keeping at least one
+        assert_eq!(fit(2), "22");
+        // Which is truncated last
+        assert_eq!(fit(1), "…");
+    }
+
+    #[test]
+    fn test_fit_segments_without_drop() {
+        let segments = vec![
+            Segment::blue("/home/user/project").with_short("project"),
+            Segment::cyan("Node.js 22.11.0").with_short("22"),
+        ];
+        let render = |segments: &[Segment]| render_powerline_multiline(segments, false);
+        let output = render(&fit_segments(segments, 12, false, render));
+        assert_eq!(output, " project\n 22");
+    }
+
+    #[test]
+    fn test_fit_segments_truncates() {
+        let segment = Segment::cyan("Node.js").with_icon("⬢").with_part("22.11.0");
+        let render = |segments: &[Segment]| render_powerline(segments, true);
+        let output = render(&fit_segments(vec![segment.clone()], 10, true, render));
+        assert_eq!(display_width(&output), 10);
+        assert!(output.contains(" ⬢ Node… "), "{:?}", output);
+
+        // Each row of multiline output is truncated on its own
+        let segments = vec![Segment::blue("/home/user/project"), segment];
+        let render = |segments: &[Segment]| render_powerline_multiline(segments, false);
+        let output = render(&fit_segments(segments, 8, false, render));
+        assert_eq!(output, " /home/…\n ⬢ Node…");
+    }
+
     #[test]
     fn test_segment_blue() {
         let seg = Segment::blue("dir");
@@ -681,6 +1272,64 @@ mod tests {
         assert!(!should_use_color(ColorMode::Never));
     }
 
+    fn env_from<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_resolve_color_auto_follows_terminal() {
+        assert!(resolve_color(ColorMode::Auto, env_from(&[]), true));
+        assert!(!resolve_color(ColorMode::Auto, env_from(&[]), false));
+    }
+
+    #[test]
+    fn test_resolve_color_no_color() {
+        let env = [("NO_COLOR", "1")];
+        assert!(!resolve_color(ColorMode::Auto, env_from(&env), true));
+        // Empty NO_COLOR is ignored
+        let env = [("NO_COLOR", "")];
+        assert!(resolve_color(ColorMode::Auto, env_from(&env), true));
+    }
+
+    #[test]
+    fn test_resolve_color_clicolor_force() {
+        let env = [("CLICOLOR_FORCE", "1")];
+        assert!(resolve_color(ColorMode::Auto, env_from(&env), false));
+        let env = [("CLICOLOR_FORCE", "0")];
+        assert!(!resolve_color(ColorMode::Auto, env_from(&env), false));
+        // NO_COLOR takes precedence over CLICOLOR_FORCE
+        let env = [("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")];
+        assert!(!resolve_color(ColorMode::Auto, env_from(&env), true));
+    }
+
+    #[test]
+    fn test_resolve_color_clicolor_zero() {
+        let env = [("CLICOLOR", "0")];
+        assert!(!resolve_color(ColorMode::Auto, env_from(&env), true));
+        let env = [("CLICOLOR", "1")];
+        assert!(resolve_color(ColorMode::Auto, env_from(&env), true));
+    }
+
+    #[test]
+    fn test_resolve_color_term_dumb() {
+        let env = [("TERM", "dumb")];
+        assert!(!resolve_color(ColorMode::Auto, env_from(&env), true));
+        let env = [("TERM", "dumb"), ("CLICOLOR_FORCE", "1")];
+        assert!(resolve_color(ColorMode::Auto, env_from(&env), true));
+    }
+
+    #[test]
+    fn test_resolve_color_explicit_mode_ignores_env() {
+        let env = [("NO_COLOR", "1"), ("TERM", "dumb")];
+        assert!(resolve_color(ColorMode::Always, env_from(&env), false));
+        let env = [("CLICOLOR_FORCE", "1")];
+        assert!(!resolve_color(ColorMode::Never, env_from(&env), true));
+    }
+
     // --- ThemeColor ANSI conversion ---
 
     #[test]
@@ -775,11 +1424,49 @@ mod tests {
             crate::config::ThemeColor::Rgb(0x26, 0x8B, 0xD2)
         );
 
+        let nord = ResolvedTheme::from_preset("nord");
+        assert_eq!(
+            nord.directory_bg,
+            crate::config::ThemeColor::Rgb(0x5E, 0x81, 0xAC)
+        );
+
+        let dracula = ResolvedTheme::from_preset("dracula");
+        assert_eq!(
+            dracula.directory_bg,
+            crate::config::ThemeColor::Rgb(0xBD, 0x93, 0xF9)
+        );
+
+        let gruvbox = ResolvedTheme::from_preset("gruvbox");
+        assert_eq!(
+            gruvbox.directory_bg,
+            crate::config::ThemeColor::Rgb(0x45, 0x85, 0x88)
+        );
+
+        let catppuccin = ResolvedTheme::from_preset("catppuccin");
+        assert_eq!(
+            catppuccin.directory_bg,
+            crate::config::ThemeColor::Rgb(0x89, 0xB4, 0xFA)
+        );
+
         // Unknown preset falls back to default
         let unknown = ResolvedTheme::from_preset("unknown");
         assert_eq!(unknown.directory_bg, crate::config::ThemeColor::Blue);
     }
 
+    #[test]
+    fn test_theme_presets_all_resolve() {
+        for name in THEME_PRESETS {
+            let theme = ResolvedTheme::from_preset(name);
+            assert_eq!(theme.tool_colors.len(), 3, "preset {}", name);
+        }
+        // Every listed preset other than "default" is distinct from the fallback
+        let fallback = ResolvedTheme::default_theme();
+        for name in THEME_PRESETS.iter().filter(|n| **n != "default") {
+            let theme = ResolvedTheme::from_preset(name);
+            assert_ne!(theme.directory_bg, fallback.directory_bg, "preset {}", name);
+        }
+    }
+
     #[test]
     fn test_resolved_theme_from_config_preset_only() {
         use crate::config::ThemeConfig;
@@ -829,6 +1516,43 @@ mod tests {
         assert_eq!(theme.tool_colors[1], (ThemeColor::Blue, ThemeColor::Black));
     }
 
+    #[test]
+    fn test_resolved_theme_user_theme() {
+        use crate::config::{CustomThemeConfig, ThemeColor, ThemeConfig};
+        let mut user_themes = BTreeMap::new();
+        user_themes.insert(
+            "work".to_string(),
+            ThemeConfig {
+                preset: "nord".to_string(),
+                custom: Some(CustomThemeConfig {
+                    directory_bg: Some(ThemeColor::Red),
+                    ..Default::default()
+                }),
+            },
+        );
+
+        let config = ThemeConfig {
+            preset: "work".to_string(),
+            custom: Some(CustomThemeConfig {
+                venv_bg: Some(ThemeColor::Blue),
+                ..Default::default()
+            }),
+        };
+        let theme = ResolvedTheme::from_config_with_themes(&config, &user_themes);
+        // From the user theme
+        assert_eq!(theme.directory_bg, ThemeColor::Red);
+        // From the user theme's base preset
+        assert_eq!(theme.git_clean_bg, ThemeColor::Rgb(0xA3, 0xBE, 0x8C));
+        // From the top-level overrides
+        assert_eq!(theme.venv_bg, ThemeColor::Blue);
+    }
+
+    #[test]
+    fn test_is_preset() {
+        assert!(ResolvedTheme::is_preset("dracula"));
+        assert!(!ResolvedTheme::is_preset("work"));
+    }
+
     #[test]
     fn test_resolved_theme_custom_tool_bg_only() {
         use crate::config::{CustomThemeConfig, ThemeColor, ThemeConfig};
@@ -1,9 +1,15 @@
 //! Configuration management for toolbox
 
-use crate::error::{Result, ToolboxError};
+use crate::config_merge;
+use crate::config_migrate::{self, CONFIG_VERSION};
+use crate::error::{ConfigParseError, Result, ToolboxError};
+use crate::info::ToolInfo;
+use crate::messages::Language;
+use crate::trust::{self, TrustStore};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 /// A color value: either a named ANSI color or an RGB hex value (#RRGGBB)
@@ -97,11 +103,38 @@ impl<'de> Deserialize<'de> for ThemeColor {
     }
 }
 
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for ThemeColor {
+    fn schema_name() -> String {
+        "ThemeColor".to_string()
+    }
+
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, Metadata, SchemaObject};
+
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "Named color (blue, green, yellow, cyan, magenta, gray, darkgray, red, white, black) or #RRGGBB"
+                        .to_string(),
+                ),
+                examples: vec!["blue".into(), "#3465A4".into()],
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 /// Theme configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(default)]
 pub struct ThemeConfig {
-    /// Preset theme name: "default", "dark", "light", "solarized"
+    /// Preset theme name: "default", "dark", "light", "solarized",
+    /// "nord", "dracula", "gruvbox", "catppuccin"
     pub preset: String,
     /// Custom color overrides (applied on top of preset)
     pub custom: Option<CustomThemeConfig>,
@@ -118,6 +151,7 @@ impl Default for ThemeConfig {
 
 /// Custom theme color overrides
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(default)]
 pub struct CustomThemeConfig {
     pub directory_bg: Option<ThemeColor>,
@@ -132,12 +166,92 @@ pub struct CustomThemeConfig {
     pub venv_fg: Option<ThemeColor>,
 }
 
+impl CustomThemeConfig {
+    /// Apply overrides from a compact spec like
+    /// `directory_bg=#3465A4,directory_fg=white,tool_bg=cyan;magenta`
+    ///
+    /// List-valued keys (`tool_bg`, `tool_fg`) take `;`-separated colors.
+    pub fn apply_spec(&mut self, spec: &str) -> std::result::Result<(), String> {
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid theme color override: {}", pair))?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "tool_bg" | "tool_fg" => {
+                    let colors = value
+                        .split(';')
+                        .map(|c| c.trim().parse::<ThemeColor>())
+                        .collect::<std::result::Result<Vec<_>, _>>()?;
+                    if key == "tool_bg" {
+                        self.tool_bg = Some(colors);
+                    } else {
+                        self.tool_fg = Some(colors);
+                    }
+                }
+                _ => {
+                    let color = value.parse::<ThemeColor>()?;
+                    let slot = match key {
+                        "directory_bg" => &mut self.directory_bg,
+                        "directory_fg" => &mut self.directory_fg,
+                        "git_clean_bg" => &mut self.git_clean_bg,
+                        "git_clean_fg" => &mut self.git_clean_fg,
+                        "git_dirty_bg" => &mut self.git_dirty_bg,
+                        "git_dirty_fg" => &mut self.git_dirty_fg,
+                        "venv_bg" => &mut self.venv_bg,
+                        "venv_fg" => &mut self.venv_fg,
+                        _ => return Err(format!("Unknown theme color key: {}", key)),
+                    };
+                    *slot = Some(color);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Config file format, chosen by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// `.toml` (and any unrecognized extension)
+    Toml,
+    /// `.yaml` / `.yml`
+    Yaml,
+    /// `.json`
+    Json,
+}
+
+impl ConfigFormat {
+    /// Detect the format from a path's extension (case-insensitive)
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+}
+
+/// Config file names looked up in the config directory, in order of preference
+const CONFIG_FILE_NAMES: &[&str] = &["config.toml", "config.yaml", "config.yml", "config.json"];
+
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(default)]
 pub struct Config {
+    /// Config layout version (files without it are treated as version 0)
+    #[serde(default)]
+    pub config_version: u32,
     /// Display settings
     pub display: DisplayConfig,
+    /// Language of labels and messages (en, ja)
+    pub language: Language,
     /// Tool definitions (if specified, replaces default tools entirely)
     #[serde(default)]
     pub tools: Vec<ToolConfig>,
@@ -151,6 +265,23 @@ pub struct Config {
     pub extras: ExtrasConfig,
     /// Cache settings for version detection
     pub cache: CacheConfig,
+    /// Version history journal settings
+    pub history: HistoryConfig,
+    /// Where the prompt cache, history and snapshots are kept
+    pub storage: StorageConfig,
+    /// Shell prompt settings (`toolbox prompt`)
+    pub prompt: PromptConfig,
+    /// Detector plugin settings
+    pub detectors: DetectorsConfig,
+    /// Project environment settings (direnv, Nix flakes)
+    pub environment: EnvironmentConfig,
+    /// Sandboxing of version commands
+    pub sandbox: SandboxConfig,
+    /// Release update settings
+    pub update: UpdateConfig,
+    /// WASM plugins by name (used with the `plugins` feature)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plugins: BTreeMap<String, PluginConfig>,
     /// Whether to use default tools as base (default: true)
     /// If false, only custom_tools will be used
     #[serde(default = "default_true")]
@@ -158,10 +289,31 @@ pub struct Config {
     /// Theme configuration for powerline output
     #[serde(default)]
     pub theme: ThemeConfig,
+    /// Named user-defined themes, selectable by name like a preset
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, ThemeConfig>,
+    /// Version constraints checked by `toolbox require` (tool name -> semver requirement)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub requirements: BTreeMap<String, String>,
+    /// Settings for working directories matching a glob, applied in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dir_overrides: Vec<DirOverride>,
+    /// Named sets of settings, selected with `--profile` (applied after
+    /// `dir_overrides`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Config files merged underneath this one, relative to it (resolved on load)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Sections merged on top when their `cfg(...)` predicate matches the OS
+    /// (resolved on load), e.g. `[target.'cfg(macos)'.display]`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub target: BTreeMap<String, Config>,
 }
 
 /// Override settings for a specific tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolOverride {
     /// Tool name to override (must match a default tool name)
     pub name: String,
@@ -174,25 +326,197 @@ pub struct ToolOverride {
     /// Override short name
     #[serde(default)]
     pub short_name: Option<String>,
+    /// Override retries of a failing version command
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Override the delay before the first retry
+    #[serde(default)]
+    pub retry_delay_ms: Option<u64>,
+}
+
+/// Where a tool definition comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ToolOrigin {
+    /// Built-in default tool
+    Default,
+    /// `[[custom_tools]]`
+    CustomTools,
+    /// `[[tools]]`, replacing the defaults
+    Tools,
+}
+
+impl ToolOrigin {
+    /// Name as shown by doctor
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToolOrigin::Default => "built-in default",
+            ToolOrigin::CustomTools => "custom_tools",
+            ToolOrigin::Tools => "tools",
+        }
+    }
+}
+
+/// Where an effective tool definition came from and what overrode it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ToolProvenance {
+    pub origin: ToolOrigin,
+    /// Settings changed by its `[[tool_overrides]]` entry (`enabled = false`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<String>,
+}
+
+impl ToolProvenance {
+    /// A built-in default nothing overrides
+    pub fn is_plain_default(&self) -> bool {
+        self.origin == ToolOrigin::Default && self.overrides.is_empty()
+    }
+
+    /// `built-in default, tool_overrides: enabled = false, icon = "🐍"`
+    pub fn format_display(&self) -> String {
+        if self.overrides.is_empty() {
+            self.origin.as_str().to_string()
+        } else {
+            format!(
+                "{}, tool_overrides: {}",
+                self.origin.as_str(),
+                self.overrides.join(", ")
+            )
+        }
+    }
+}
+
+impl ToolOverride {
+    /// Apply the override to a tool; the settings it changed
+    fn apply(&self, tool: &mut ToolConfig) -> Vec<String> {
+        let mut changed = Vec::new();
+        if let Some(enabled) = self.enabled {
+            tool.enabled = enabled;
+            changed.push(format!("enabled = {}", enabled));
+        }
+        if let Some(ref icon) = self.icon {
+            tool.icon = Some(icon.clone());
+            changed.push(format!("icon = {:?}", icon));
+        }
+        if let Some(ref short_name) = self.short_name {
+            tool.short_name = Some(short_name.clone());
+            changed.push(format!("short_name = {:?}", short_name));
+        }
+        if let Some(retries) = self.retries {
+            tool.retries = Some(retries);
+            changed.push(format!("retries = {}", retries));
+        }
+        if let Some(retry_delay_ms) = self.retry_delay_ms {
+            tool.retry_delay_ms = Some(retry_delay_ms);
+            changed.push(format!("retry_delay_ms = {}", retry_delay_ms));
+        }
+        changed
+    }
+}
+
+/// Settings for working directories matching a glob (`[[dir_overrides]]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct DirOverride {
+    /// Glob of the working directories, e.g. "~/work/infra/**"; directories
+    /// below a matching one match too, so "dir/**" includes dir itself
+    pub path: String,
+    /// Tools to enable there (names or short names)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enable_tools: Vec<String>,
+    /// Tools to disable there
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub disable_tools: Vec<String>,
+    /// Theme there, replacing `[theme]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemeConfig>,
+    /// Extras to turn on or off there, e.g. `{ git_status = false }`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extras: BTreeMap<String, bool>,
+}
+
+impl DirOverride {
+    /// Whether `dir` or one of its parents matches the glob
+    pub fn matches(&self, dir: &Path) -> Result<bool> {
+        let path = self.path.strip_suffix("/**").unwrap_or(&self.path);
+        let pattern = expand_home(path);
+        let glob = globset::GlobBuilder::new(&pattern.to_string_lossy())
+            .literal_separator(true)
+            .build()
+            .map_err(|e| {
+                ToolboxError::Config(format!("Invalid dir_overrides path {}: {}", self.path, e))
+            })?
+            .compile_matcher();
+        Ok(dir.ancestors().any(|dir| glob.is_match(dir)))
+    }
+
+    /// The settings this entry applies
+    fn settings(&self) -> Profile {
+        Profile {
+            enable_tools: self.enable_tools.clone(),
+            disable_tools: self.disable_tools.clone(),
+            theme: self.theme.clone(),
+            extras: self.extras.clone(),
+        }
+    }
+}
+
+/// A named set of settings selected with `--profile` (`[profiles.<name>]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct Profile {
+    /// Tools to enable (names or short names)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enable_tools: Vec<String>,
+    /// Tools to disable
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub disable_tools: Vec<String>,
+    /// Theme, replacing `[theme]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemeConfig>,
+    /// Extras to turn on or off, e.g. `{ git_status = false }`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub extras: BTreeMap<String, bool>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
             display: DisplayConfig::default(),
+            language: Language::default(),
             tools: Vec::new(),
             custom_tools: Vec::new(),
             tool_overrides: Vec::new(),
             extras: ExtrasConfig::default(),
             cache: CacheConfig::default(),
+            history: HistoryConfig::default(),
+            storage: StorageConfig::default(),
+            prompt: PromptConfig::default(),
+            detectors: DetectorsConfig::default(),
+            environment: EnvironmentConfig::default(),
+            sandbox: SandboxConfig::default(),
+            update: UpdateConfig::default(),
+            plugins: BTreeMap::new(),
             use_default_tools: true,
             theme: ThemeConfig::default(),
+            themes: BTreeMap::new(),
+            requirements: BTreeMap::new(),
+            dir_overrides: Vec::new(),
+            profiles: BTreeMap::new(),
+            include: Vec::new(),
+            target: BTreeMap::new(),
         }
     }
 }
 
 /// Display-related settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(default)]
 pub struct DisplayConfig {
     /// Refresh interval in seconds
@@ -201,6 +525,20 @@ pub struct DisplayConfig {
     pub show_icons: bool,
     /// Compact mode (shorter version strings)
     pub compact: bool,
+    /// Sections to render, in order (omitted sections are hidden)
+    pub sections: Vec<Section>,
+    /// Single-line powerline layout: left, right or split
+    pub layout: PowerlineLayout,
+    /// Sections placed on the right side in the split layout
+    pub right_sections: Vec<Section>,
+    /// Show unavailable tools (dimmed, marked with ✗) instead of hiding them
+    pub show_unavailable: bool,
+    /// Render all tools in a single powerline segment
+    pub group_tools: bool,
+    /// Tool order: config (catalog order), name or status (available first)
+    pub sort: ToolSort,
+    /// Tools (name or short name) always shown first, in this order
+    pub pinned: Vec<String>,
 }
 
 impl Default for DisplayConfig {
@@ -209,20 +547,160 @@ impl Default for DisplayConfig {
             refresh_interval: 5,
             show_icons: true,
             compact: true,
+            sections: DEFAULT_SECTIONS.to_vec(),
+            layout: PowerlineLayout::Left,
+            right_sections: vec![Section::Tools, Section::Venv],
+            show_unavailable: false,
+            group_tools: false,
+            sort: ToolSort::Config,
+            pinned: Vec::new(),
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Order detected tools by `sort`, with the `pinned` ones first
+    pub fn order_tools(&self, tools: &mut [ToolInfo]) {
+        match self.sort {
+            ToolSort::Config => {}
+            ToolSort::Name => tools.sort_by_cached_key(|tool| tool.name.to_lowercase()),
+            ToolSort::Status => tools.sort_by_key(|tool| !tool.available),
+        }
+        if !self.pinned.is_empty() {
+            tools.sort_by_key(|tool| {
+                self.pinned
+                    .iter()
+                    .position(|pin| {
+                        pin.eq_ignore_ascii_case(&tool.name)
+                            || tool
+                                .short_name
+                                .as_deref()
+                                .is_some_and(|short| pin.eq_ignore_ascii_case(short))
+                    })
+                    .unwrap_or(usize::MAX)
+            });
+        }
+    }
+}
+
+/// Order of tools in the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSort {
+    /// Catalog order: built-in tools, then custom tools and plugins
+    #[default]
+    Config,
+    /// Alphabetical by name
+    Name,
+    /// Available tools first, each group in catalog order
+    Status,
+}
+
+/// A section of the toolbox output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    /// Current directory
+    Dir,
+    /// Git branch and status
+    Git,
+    /// Tool versions
+    Tools,
+    /// Virtual environment
+    Venv,
+    /// System information (text output only)
+    System,
+}
+
+/// Alignment of single-line powerline output
+///
+/// Aligning to the right needs a width: without `max_width` the right and
+/// split layouts only change the separators, with no padding before them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum PowerlineLayout {
+    /// Left-aligned, right-pointing separators
+    #[default]
+    Left,
+    /// Right-aligned, left-pointing separators
+    Right,
+    /// Some sections on the left, `right_sections` on the right
+    Split,
+}
+
+impl std::str::FromStr for PowerlineLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "left" => Ok(PowerlineLayout::Left),
+            "right" => Ok(PowerlineLayout::Right),
+            "split" => Ok(PowerlineLayout::Split),
+            _ => Err(format!("Invalid layout: {}", s)),
+        }
+    }
+}
+
+impl std::str::FromStr for Section {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "dir" => Ok(Section::Dir),
+            "git" => Ok(Section::Git),
+            "tools" => Ok(Section::Tools),
+            "venv" => Ok(Section::Venv),
+            "system" => Ok(Section::System),
+            _ => Err(format!(
+                "Invalid section: {} (expected dir, git, tools, venv or system)",
+                s
+            )),
         }
     }
 }
 
+/// Default section order
+pub const DEFAULT_SECTIONS: &[Section] = &[
+    Section::Dir,
+    Section::Git,
+    Section::Tools,
+    Section::Venv,
+    Section::System,
+];
+
 /// Configuration for a single tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolConfig {
     /// Tool name for display
     pub name: String,
     /// Command to run to get version
     pub command: String,
-    /// Optional regex to extract version from output
+    /// Optional regex to extract version from output: the `version` named
+    /// group or the first group; `build` and `runtime` groups fill the fields
+    /// of the same name
     #[serde(default)]
     pub parse_regex: Option<String>,
+    /// Display string composed from named groups of `parse_regex` (or fields
+    /// of `parse_script`), e.g. `{version}+{build} ({runtime})`
+    #[serde(default)]
+    pub parse_template: Option<String>,
+    /// rhai script for outputs a regex can't handle, given `stdout`, `stderr`
+    /// and `output` and returning the version or a map of version fields
+    /// (`scripting` feature); replaces `parse_regex`
+    #[serde(default)]
+    pub parse_script: Option<String>,
+    /// JSON pointer to the version in JSON output, e.g.
+    /// `/clientVersion/gitVersion`; `parse_regex` then applies to the value
+    #[serde(default)]
+    pub parse_json_pointer: Option<String>,
+    /// Run the version command in this image (`docker run --rm <image>`),
+    /// for toolchains that live in a dev container
+    #[serde(default)]
+    pub container_image: Option<String>,
     /// Icon/emoji for display
     #[serde(default)]
     pub icon: Option<String>,
@@ -232,10 +710,80 @@ pub struct ToolConfig {
     /// Short name for compact display
     #[serde(default)]
     pub short_name: Option<String>,
+    /// Non-zero exit codes that still mean the version was printed (old
+    /// `java -version`, some proprietary CLIs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accept_exit_codes: Vec<i32>,
+    /// Output stream holding the version (default: stdout, or stderr when
+    /// stdout is empty)
+    #[serde(default)]
+    pub version_stream: Option<VersionStream>,
+    /// Times to retry a failing version command (network-dependent CLIs,
+    /// cold JVM starts) before the tool is reported unavailable
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Delay before the first retry in milliseconds, doubled for each
+    /// further one (default: 200)
+    #[serde(default)]
+    pub retry_delay_ms: Option<u64>,
+}
+
+impl Default for ToolConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            command: String::new(),
+            parse_regex: None,
+            parse_template: None,
+            parse_script: None,
+            parse_json_pointer: None,
+            container_image: None,
+            icon: None,
+            enabled: true,
+            short_name: None,
+            accept_exit_codes: Vec::new(),
+            version_stream: None,
+            retries: None,
+            retry_delay_ms: None,
+        }
+    }
+}
+
+impl ToolConfig {
+    /// Delay before a retry (1 for the first), doubling from `retry_delay_ms`
+    pub fn retry_delay(&self, retry: u32) -> std::time::Duration {
+        let base = self.retry_delay_ms.unwrap_or(DEFAULT_RETRY_DELAY_MS);
+        let factor = 1u64 << retry.saturating_sub(1).min(16);
+        std::time::Duration::from_millis(base.saturating_mul(factor))
+    }
+
+    /// Whether `name` is this tool's name or short name, case-insensitively
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self
+                .short_name
+                .as_deref()
+                .is_some_and(|s| s.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Delay before the first retry of a failing version command
+pub const DEFAULT_RETRY_DELAY_MS: u64 = 200;
+
+/// Output stream a tool prints its version to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum VersionStream {
+    Stdout,
+    Stderr,
+    /// stdout followed by stderr
+    Both,
 }
 
 /// Extra information settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(default)]
 pub struct ExtrasConfig {
     /// Show current git branch
@@ -246,14 +794,48 @@ pub struct ExtrasConfig {
     pub system_memory: bool,
     /// Show CPU usage
     pub system_cpu: bool,
+    /// Show swap usage
+    pub system_swap: bool,
+    /// Show per-core CPU usage as a sparkline (e.g. ▁▃▇▂)
+    pub system_cpu_cores: bool,
     /// Show current directory
     pub current_directory: bool,
     /// Show virtual environment name
     pub virtual_env: bool,
+    /// Show the virtual environment's Python version (from its pyvenv.cfg)
+    pub virtual_env_python: bool,
+    /// Show the environment's package manager (pip, poetry, uv, pipenv by the
+    /// project's lockfile; conda, mamba, micromamba for a conda environment)
+    pub virtual_env_manager: bool,
+    /// Show the number of conda environments (from ~/.conda/environments.txt)
+    pub conda_env_count: bool,
+    /// Show the go.mod module with Go, flagging a Go older than its `go` directive
+    pub go_module: bool,
     /// Show shell name
     pub shell: bool,
 }
 
+impl ExtrasConfig {
+    /// Every extra turned off
+    pub fn none() -> Self {
+        Self {
+            git_branch: false,
+            git_status: false,
+            system_memory: false,
+            system_cpu: false,
+            system_swap: false,
+            system_cpu_cores: false,
+            current_directory: false,
+            virtual_env: false,
+            virtual_env_python: false,
+            virtual_env_manager: false,
+            conda_env_count: false,
+            go_module: false,
+            shell: false,
+        }
+    }
+}
+
 impl Default for ExtrasConfig {
     fn default() -> Self {
         Self {
@@ -261,8 +843,14 @@ impl Default for ExtrasConfig {
             git_status: true,
             system_memory: false,
             system_cpu: false,
+            system_swap: false,
+            system_cpu_cores: false,
             current_directory: true,
             virtual_env: true,
+            virtual_env_python: false,
+            virtual_env_manager: false,
+            conda_env_count: false,
+            go_module: false,
             shell: false,
         }
     }
@@ -270,12 +858,17 @@ impl Default for ExtrasConfig {
 
 /// Cache settings for version detection results
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(default)]
 pub struct CacheConfig {
     /// Whether caching is enabled (default: true)
     pub enabled: bool,
     /// Default TTL in seconds for cache entries (default: 300 = 5 minutes)
     pub default_ttl: u64,
+    /// Most entries kept, per tool and directory in memory and per directory
+    /// for the prompt cache; the least recently used are evicted beyond it
+    /// (default: 1000, 0 = unlimited)
+    pub max_entries: usize,
 }
 
 impl Default for CacheConfig {
@@ -283,6 +876,183 @@ impl Default for CacheConfig {
         Self {
             enabled: true,
             default_ttl: 300,
+            max_entries: 1000,
+        }
+    }
+}
+
+/// Version history journal settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Record version changes on each detection run (default: false)
+    pub enabled: bool,
+    /// Journal path (default: <cache dir>/toolbox/history.jsonl)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+/// Storage of the prompt cache, history and snapshots
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct StorageConfig {
+    /// "files" (JSON files in the cache dir) or "sqlite" (one database,
+    /// safe to share between processes; needs the `sqlite` feature)
+    pub backend: StorageBackend,
+    /// Database path (default: <data dir>/toolbox/toolbox.db)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+}
+
+impl StorageConfig {
+    /// The database to use, or None for files
+    pub fn database(&self) -> Result<Option<PathBuf>> {
+        match self.backend {
+            StorageBackend::Files => Ok(None),
+            #[cfg(feature = "sqlite")]
+            StorageBackend::Sqlite => self
+                .path
+                .as_ref()
+                .map(|path| expand_home(&path.to_string_lossy()))
+                .or_else(crate::store::Store::default_path)
+                .map(Some)
+                .ok_or_else(|| {
+                    ToolboxError::Config("Could not determine the database path".to_string())
+                }),
+            #[cfg(not(feature = "sqlite"))]
+            StorageBackend::Sqlite => Err(ToolboxError::Config(
+                "storage.backend = \"sqlite\" needs toolbox built with the `sqlite` feature"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// Storage backend of the prompt cache, history and snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// JSON files: `history.jsonl` and one file per prompt cache entry
+    #[default]
+    Files,
+    /// A SQLite database
+    Sqlite,
+}
+
+/// Release update settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// Note a newer release in doctor output, looked up on GitHub at most
+    /// once a day (default: false)
+    pub check: bool,
+}
+
+/// Shell prompt settings (`toolbox prompt`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct PromptConfig {
+    /// Sections left out of the prompt and not detected for it (default: system)
+    pub exclude: Vec<Section>,
+    /// Milliseconds to wait for a detection before showing the last result (default: 50)
+    pub budget_ms: u64,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            exclude: vec![Section::System],
+            budget_ms: 50,
+        }
+    }
+}
+
+/// Detector plugin settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct DetectorsConfig {
+    /// Run `toolbox-detect-<name>` executables found in PATH (default: false)
+    pub external: bool,
+    /// Run the detectors of `[plugins]` WASM modules (default: true)
+    pub wasm: bool,
+}
+
+impl Default for DetectorsConfig {
+    fn default() -> Self {
+        Self {
+            external: false,
+            wasm: true,
+        }
+    }
+}
+
+/// Project environment settings: run version commands inside the project's
+/// direnv or Nix flake environment
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    /// Run through `direnv exec` under an `.envrc` (default: false)
+    pub direnv: bool,
+    /// Run through `nix develop --command` under a `flake.nix` (default: false)
+    pub nix: bool,
+}
+
+/// Sandboxing of version commands, for configs that aren't fully trusted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct SandboxConfig {
+    /// Run version commands sandboxed (default: false)
+    pub enabled: bool,
+    /// Environment variables passed to sandboxed commands; a trailing `*`
+    /// matches a prefix
+    pub env_allowlist: Vec<String>,
+    /// Let sandboxed commands use the network (default: false)
+    pub allow_network: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            env_allowlist: crate::sandbox::DEFAULT_ENV_ALLOWLIST
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            allow_network: false,
+        }
+    }
+}
+
+/// A sandboxed WASM plugin (`[plugins.<name>]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+pub struct PluginConfig {
+    /// Path of the `.wasm` module (`~` is expanded)
+    pub path: String,
+    /// Whether the plugin is used (default: true)
+    pub enabled: bool,
+    /// Directories the plugin may read besides the detection directory
+    pub allow_read: Vec<String>,
+    /// Instructions a single call may execute (default: 100000000)
+    pub fuel: u64,
+}
+
+impl Default for PluginConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            enabled: true,
+            allow_read: Vec::new(),
+            fuel: 100_000_000,
         }
     }
 }
@@ -291,6 +1061,81 @@ fn default_true() -> bool {
     true
 }
 
+/// Expand a leading `~` or `~/` to the home directory (other paths are unchanged)
+pub fn expand_home(path: &str) -> PathBuf {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) => match rest.strip_prefix('/') {
+            Some(rest) => rest,
+            None => return PathBuf::from(path),
+        },
+        None => return PathBuf::from(path),
+    };
+    match dirs::home_dir() {
+        Some(home) if rest.is_empty() => home,
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+/// Read a config file as current-layout TOML text
+/// YAML and JSON are converted to TOML so they share migrations and parsing.
+pub(crate) fn read_config_text(path: &Path) -> Result<String> {
+    let content = std::fs::read_to_string(path)?;
+    let content = match ConfigFormat::from_path(path) {
+        ConfigFormat::Toml => content,
+        ConfigFormat::Yaml => json_to_toml(serde_yaml::from_str(&content)?)?,
+        ConfigFormat::Json => json_to_toml(serde_json::from_str(&content)?)?,
+    };
+    match config_migrate::migrate(&content) {
+        Ok(migrated) => {
+            if migrated.changed() {
+                tracing::debug!(
+                    path = %path.display(),
+                    from = migrated.from,
+                    to = migrated.to,
+                    "migrated config in memory"
+                );
+            }
+            Ok(migrated.content)
+        }
+        // Newer layouts are loaded as-is; syntax errors are reported when parsing
+        Err(e) => {
+            if config_migrate::config_version(&content).is_ok() {
+                tracing::warn!(path = %path.display(), "{}", e);
+            }
+            Ok(content)
+        }
+    }
+}
+
+/// Convert a YAML/JSON document to TOML text, dropping nulls (TOML has none)
+fn json_to_toml(mut value: serde_json::Value) -> Result<String> {
+    fn strip_nulls(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.retain(|_, v| !v.is_null());
+                map.values_mut().for_each(strip_nulls);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+            _ => {}
+        }
+    }
+
+    match value {
+        // An empty YAML document
+        serde_json::Value::Null => return Ok(String::new()),
+        serde_json::Value::Object(_) => strip_nulls(&mut value),
+        _ => {
+            return Err(ToolboxError::Config(
+                "Config file must contain a mapping at the top level".to_string(),
+            ))
+        }
+    }
+    let value = toml::Value::try_from(value).map_err(|e| ToolboxError::Config(e.to_string()))?;
+    toml::to_string(&value).map_err(|e| ToolboxError::Config(e.to_string()))
+}
+
 /// Returns the default set of tools
 fn default_tools() -> Vec<ToolConfig> {
     vec![
@@ -301,6 +1146,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🐍".to_string()),
             enabled: true,
             short_name: Some("py".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Node".to_string(),
@@ -309,6 +1155,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("📦".to_string()),
             enabled: true,
             short_name: Some("node".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "npm".to_string(),
@@ -317,6 +1164,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("📦".to_string()),
             enabled: false, // disabled by default, often redundant with node
             short_name: Some("npm".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "pnpm".to_string(),
@@ -325,6 +1173,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("📦".to_string()),
             enabled: false,
             short_name: Some("pnpm".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "yarn".to_string(),
@@ -333,6 +1182,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🧶".to_string()),
             enabled: false,
             short_name: Some("yarn".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Docker".to_string(),
@@ -341,6 +1191,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🐳".to_string()),
             enabled: true,
             short_name: Some("docker".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Rust".to_string(),
@@ -349,6 +1200,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🦀".to_string()),
             enabled: true,
             short_name: Some("rust".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Go".to_string(),
@@ -357,6 +1209,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🔷".to_string()),
             enabled: true,
             short_name: Some("go".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Ruby".to_string(),
@@ -365,6 +1218,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("💎".to_string()),
             enabled: false,
             short_name: Some("ruby".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Java".to_string(),
@@ -373,6 +1227,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("☕".to_string()),
             enabled: false,
             short_name: Some("java".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "PHP".to_string(),
@@ -381,6 +1236,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🐘".to_string()),
             enabled: false,
             short_name: Some("php".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Elixir".to_string(),
@@ -389,6 +1245,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("💧".to_string()),
             enabled: false,
             short_name: Some("elixir".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Zig".to_string(),
@@ -397,6 +1254,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("⚡".to_string()),
             enabled: false,
             short_name: Some("zig".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Deno".to_string(),
@@ -405,6 +1263,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🦕".to_string()),
             enabled: false,
             short_name: Some("deno".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "Bun".to_string(),
@@ -413,24 +1272,28 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🥟".to_string()),
             enabled: false,
             short_name: Some("bun".to_string()),
+            ..Default::default()
         },
         // DevOps tools
         ToolConfig {
             name: "kubectl".to_string(),
-            command: "kubectl version --client --short 2>/dev/null || kubectl version --client"
-                .to_string(),
+            command: "kubectl version --client --output json".to_string(),
             parse_regex: Some(r"v?(\d+\.\d+(?:\.\d+)?)".to_string()),
+            parse_json_pointer: Some("/clientVersion/gitVersion".to_string()),
             icon: Some("☸️".to_string()),
             enabled: false,
             short_name: Some("k8s".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "terraform".to_string(),
-            command: "terraform --version".to_string(),
-            parse_regex: Some(r"Terraform\s+v?(\d+\.\d+(?:\.\d+)?)".to_string()),
+            command: "terraform version -json".to_string(),
+            parse_regex: None,
+            parse_json_pointer: Some("/terraform_version".to_string()),
             icon: Some("🏗️".to_string()),
             enabled: false,
             short_name: Some("tf".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "aws-cli".to_string(),
@@ -439,6 +1302,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("☁️".to_string()),
             enabled: false,
             short_name: Some("aws".to_string()),
+            ..Default::default()
         },
         // Version managers
         ToolConfig {
@@ -448,6 +1312,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🔧".to_string()),
             enabled: false,
             short_name: Some("mise".to_string()),
+            ..Default::default()
         },
         ToolConfig {
             name: "asdf".to_string(),
@@ -456,6 +1321,7 @@ fn default_tools() -> Vec<ToolConfig> {
             icon: Some("🔧".to_string()),
             enabled: false,
             short_name: Some("asdf".to_string()),
+            ..Default::default()
         },
     ]
 }
@@ -463,18 +1329,43 @@ fn default_tools() -> Vec<ToolConfig> {
 impl Config {
     /// Load configuration from the default path
     pub fn load() -> Result<Self> {
-        if let Some(path) = Self::config_path() {
-            if path.exists() {
-                return Self::load_from_path(&path);
-            }
+        if let Some(path) = Self::find_config_path() {
+            return Self::load_from_path(&path);
         }
         Ok(Self::default())
     }
 
-    /// Load configuration from a specific path
-    pub fn load_from_path(path: &PathBuf) -> Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+    /// Load configuration from a specific path (TOML, YAML or JSON by extension)
+    /// Includes and matching `target` sections are merged in.
+    pub fn load_from_path(path: &Path) -> Result<Self> {
+        tracing::debug!(path = %path.display(), "loading config");
+        let content = read_config_text(path)?;
+
+        if config_merge::is_layered(&content) {
+            let value = config_merge::resolve(path, &content, &read_config_text)?;
+            // Line numbers would not match any single file, so only keep the message
+            return Config::deserialize(value)
+                .map_err(|e| ToolboxError::Config(format!("{}: {}", path.display(), e.message())));
+        }
+
+        let config: Config = toml::from_str(&content).map_err(|error| {
+            // Positions point into the converted or migrated text; the file's
+            // own parse error, if any, points at what was written
+            let original = match ConfigFormat::from_path(path) {
+                ConfigFormat::Toml => std::fs::read_to_string(path).ok(),
+                _ => None,
+            };
+            match original.map(|original| (toml::from_str::<Config>(&original), original)) {
+                Some((Err(error), content)) => {
+                    ToolboxError::ConfigParse(Box::new(ConfigParseError {
+                        path: path.to_path_buf(),
+                        content,
+                        error,
+                    }))
+                }
+                _ => ToolboxError::Config(format!("{}: {}", path.display(), error.message())),
+            }
+        })?;
         Ok(config)
     }
 
@@ -491,8 +1382,13 @@ impl Config {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let content =
-            toml::to_string_pretty(self).map_err(|e| ToolboxError::Config(e.to_string()))?;
+        let content = match ConfigFormat::from_path(path) {
+            ConfigFormat::Toml => {
+                toml::to_string_pretty(self).map_err(|e| ToolboxError::Config(e.to_string()))?
+            }
+            ConfigFormat::Yaml => serde_yaml::to_string(self)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+        };
         std::fs::write(path, content)?;
         Ok(())
     }
@@ -502,42 +1398,267 @@ impl Config {
         dirs::config_dir().map(|p| p.join("toolbox").join("config.toml"))
     }
 
+    /// Find the existing config file in the config directory
+    /// `config.toml` wins over `config.yaml`, `config.yml` and `config.json`.
+    pub fn find_config_path() -> Option<PathBuf> {
+        let dir = Self::config_path()?.parent()?.to_path_buf();
+        CONFIG_FILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+    }
+
     /// Get the effective list of tools (merging defaults, custom, and overrides)
     pub fn effective_tools(&self) -> Vec<ToolConfig> {
+        self.effective_tools_with_provenance()
+            .into_iter()
+            .map(|(tool, _)| tool)
+            .collect()
+    }
+
+    /// Like [`effective_tools`](Self::effective_tools), with where each
+    /// definition came from
+    pub fn effective_tools_with_provenance(&self) -> Vec<(ToolConfig, ToolProvenance)> {
+        let plain = |origin| ToolProvenance {
+            origin,
+            overrides: Vec::new(),
+        };
+
         // If tools are explicitly specified, use them directly
         if !self.tools.is_empty() {
-            return self.tools.clone();
+            return self
+                .tools
+                .iter()
+                .map(|tool| (tool.clone(), plain(ToolOrigin::Tools)))
+                .collect();
         }
 
-        let mut result: Vec<ToolConfig> = Vec::new();
+        let mut result = Vec::new();
+
+        // Start with default tools if enabled
+        if self.use_default_tools {
+            for mut tool in default_tools() {
+                // Apply overrides
+                let overrides = self
+                    .tool_overrides
+                    .iter()
+                    .find(|o| o.name == tool.name)
+                    .map(|o| o.apply(&mut tool))
+                    .unwrap_or_default();
+                result.push((
+                    tool,
+                    ToolProvenance {
+                        origin: ToolOrigin::Default,
+                        overrides,
+                    },
+                ));
+            }
+        }
+
+        // Add custom tools
+        for tool in &self.custom_tools {
+            result.push((tool.clone(), plain(ToolOrigin::CustomTools)));
+        }
+
+        result
+    }
+
+    /// Find a tool (enabled or not) by name or short name, case-insensitively
+    pub fn find_tool(&self, name: &str) -> Option<ToolConfig> {
+        self.effective_tools()
+            .into_iter()
+            .find(|t| t.matches_name(name))
+    }
+
+    /// Restrict detection to the named tools (enabled or not), in the given order
+    pub fn only_tools(&mut self, names: &[String]) -> Result<()> {
+        let tools = names
+            .iter()
+            .map(|name| {
+                self.find_tool(name)
+                    .map(|tool| ToolConfig {
+                        enabled: true,
+                        ..tool
+                    })
+                    .ok_or_else(|| ToolboxError::Config(format!("Unknown tool: {}", name)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.tools = tools;
+        // Detector plugins report tools of their own choosing
+        self.detectors.external = false;
+        self.detectors.wasm = false;
+        Ok(())
+    }
+
+    /// Enable exactly the built-in tools named in `found` and disable the
+    /// rest, with a `tool_overrides` entry for each one that differs from its
+    /// default (`toolbox init --from-detect`)
+    pub fn enable_found_tools(&mut self, found: &[String]) {
+        for tool in default_tools() {
+            let enabled = found.contains(&tool.name);
+            if enabled == tool.enabled {
+                continue;
+            }
+            self.tool_overrides.retain(|o| o.name != tool.name);
+            self.tool_overrides.push(ToolOverride {
+                name: tool.name,
+                enabled: Some(enabled),
+                icon: None,
+                short_name: None,
+                retries: None,
+                retry_delay_ms: None,
+            });
+        }
+    }
+
+    /// Layer the project config (`.toolbox.toml`) at or above `dir` on top
+    /// of this one; unless `trust` has it as trusted and unchanged, only its
+    /// display settings apply. Returns the project config, if one was found
+    pub fn apply_project_config(
+        &mut self,
+        dir: &Path,
+        trust: &TrustStore,
+    ) -> Result<Option<PathBuf>> {
+        let Some(path) = trust::find_project_config(dir) else {
+            return Ok(None);
+        };
+        let content = std::fs::read_to_string(&path)?;
+        let mut table: toml::Table = toml::from_str(&content)
+            .map_err(|e| ToolboxError::Config(format!("{}: {}", path.display(), e.message())))?;
+        if !trust.is_trusted(&path) {
+            let ignored: Vec<&str> = table
+                .keys()
+                .map(String::as_str)
+                .filter(|key| !trust::DISPLAY_KEYS.contains(key))
+                .collect();
+            if !ignored.is_empty() {
+                tracing::info!(
+                    path = %path.display(),
+                    "untrusted project config, ignoring {} (`toolbox trust` allows them)",
+                    ignored.join(", ")
+                );
+            }
+            table = trust::display_only(table);
+        }
+        let mut merged =
+            toml::Value::try_from(&*self).map_err(|e| ToolboxError::Config(e.to_string()))?;
+        config_merge::merge(&mut merged, toml::Value::Table(table));
+        *self = Config::deserialize(merged)
+            .map_err(|e| ToolboxError::Config(format!("{}: {}", path.display(), e.message())))?;
+        Ok(Some(path))
+    }
+
+    /// Apply the `[[dir_overrides]]` matching the working directory `dir`
+    pub fn apply_dir_overrides(&mut self, dir: &Path) -> Result<()> {
+        for entry in self.dir_overrides.clone() {
+            if !entry.matches(dir)? {
+                continue;
+            }
+            tracing::debug!(path = %entry.path, dir = %dir.display(), "applying dir override");
+            self.apply_settings(&entry.settings())?;
+        }
+        Ok(())
+    }
+
+    /// Apply a named `[profiles.<name>]` section (`--profile`)
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Err(ToolboxError::Config(if available.is_empty() {
+                format!(
+                    "Unknown profile: {} (the config defines no [profiles])",
+                    name
+                )
+            } else {
+                format!(
+                    "Unknown profile: {} (available: {})",
+                    name,
+                    available.join(", ")
+                )
+            }));
+        };
+        self.apply_settings(&profile)
+    }
 
-        // Start with default tools if enabled
-        if self.use_default_tools {
-            for mut tool in default_tools() {
-                // Apply overrides
-                if let Some(override_config) =
-                    self.tool_overrides.iter().find(|o| o.name == tool.name)
-                {
-                    if let Some(enabled) = override_config.enabled {
-                        tool.enabled = enabled;
-                    }
-                    if let Some(ref icon) = override_config.icon {
-                        tool.icon = Some(icon.clone());
-                    }
-                    if let Some(ref short_name) = override_config.short_name {
-                        tool.short_name = Some(short_name.clone());
-                    }
-                }
-                result.push(tool);
+    fn apply_settings(&mut self, settings: &Profile) -> Result<()> {
+        for name in &settings.enable_tools {
+            self.set_tool_enabled(name, true)?;
+        }
+        for name in &settings.disable_tools {
+            self.set_tool_enabled(name, false)?;
+        }
+        if let Some(ref theme) = settings.theme {
+            self.theme = theme.clone();
+        }
+        if !settings.extras.is_empty() {
+            let mut extras = toml::Value::try_from(&self.extras)
+                .map_err(|e| ToolboxError::Config(e.to_string()))?;
+            for (name, value) in &settings.extras {
+                let extra = extras
+                    .get_mut(name)
+                    .ok_or_else(|| ToolboxError::Config(format!("Unknown extra: {}", name)))?;
+                *extra = toml::Value::Boolean(*value);
             }
+            self.extras = ExtrasConfig::deserialize(extras)
+                .map_err(|e| ToolboxError::Config(e.to_string()))?;
         }
+        Ok(())
+    }
 
-        // Add custom tools
-        for tool in &self.custom_tools {
-            result.push(tool.clone());
+    /// Enable or disable a tool wherever it is defined: `tools`,
+    /// `custom_tools`, or a `tool_overrides` entry for a default tool
+    fn set_tool_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        let defined = if self.tools.is_empty() {
+            &mut self.custom_tools
+        } else {
+            &mut self.tools
+        };
+        if let Some(tool) = defined.iter_mut().find(|t| t.matches_name(name)) {
+            tool.enabled = enabled;
+            return Ok(());
+        }
+        let tool = self
+            .find_tool(name)
+            .ok_or_else(|| ToolboxError::Config(format!("Unknown tool: {}", name)))?;
+        match self.tool_overrides.iter_mut().find(|o| o.name == tool.name) {
+            Some(entry) => entry.enabled = Some(enabled),
+            None => self.tool_overrides.push(ToolOverride {
+                name: tool.name,
+                enabled: Some(enabled),
+                icon: None,
+                short_name: None,
+                retries: None,
+                retry_delay_ms: None,
+            }),
         }
+        Ok(())
+    }
 
-        result
+    /// Show tool versions only: turn off the extras and drop every other section
+    pub fn tools_only(&mut self) {
+        self.extras = ExtrasConfig::none();
+        self.retain_sections(|section| section == Section::Tools);
+    }
+
+    /// Show the extras only: disable every tool (and the detector plugins,
+    /// which report tools) and drop the tools section
+    pub fn extras_only(&mut self) {
+        self.tools = self
+            .effective_tools()
+            .into_iter()
+            .map(|tool| ToolConfig {
+                enabled: false,
+                ..tool
+            })
+            .collect();
+        self.detectors.external = false;
+        self.detectors.wasm = false;
+        self.retain_sections(|section| section != Section::Tools);
+    }
+
+    fn retain_sections(&mut self, keep: impl Fn(Section) -> bool) {
+        self.display.sections.retain(|section| keep(*section));
+        self.display.right_sections.retain(|section| keep(*section));
     }
 
     /// Get only enabled tools
@@ -581,6 +1702,8 @@ mod tests {
         assert!(extras.git_status);
         assert!(!extras.system_memory);
         assert!(!extras.system_cpu);
+        assert!(!extras.system_swap);
+        assert!(!extras.system_cpu_cores);
         assert!(extras.current_directory);
         assert!(extras.virtual_env);
         assert!(!extras.shell);
@@ -643,6 +1766,7 @@ mod tests {
             icon: Some("🔧".to_string()),
             enabled: true,
             short_name: Some("t".to_string()),
+            ..Default::default()
         };
 
         let toml_str = toml::to_string(&tool).unwrap();
@@ -673,6 +1797,176 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_config_load_keeps_text_of_parse_errors() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "[display]\ncompact = \"yes\"").unwrap();
+
+        match Config::load_from_path(temp_file.path()).unwrap_err() {
+            ToolboxError::ConfigParse(parse) => {
+                let span = parse.error.span().unwrap();
+                assert_eq!(&parse.content[span], "\"yes\"");
+                assert!(parse.error.message().contains("expected a boolean"));
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_config_load_migrates_old_layout() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "[display]\nuse_default_tools = false").unwrap();
+        let path = temp_file.path().to_path_buf();
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert!(!config.use_default_tools);
+        assert_eq!(config.config_version, CONFIG_VERSION);
+
+        // The file itself is left alone
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("config_version"));
+    }
+
+    #[test]
+    fn test_config_load_newer_version() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(
+            temp_file,
+            "config_version = 99
+[display]
+compact = false"
+        )
+        .unwrap();
+        let path = temp_file.path().to_path_buf();
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert_eq!(config.config_version, 99);
+        assert!(!config.display.compact);
+    }
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("c.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("c.yaml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("c.YML")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("c.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config")),
+            ConfigFormat::Toml
+        );
+    }
+
+    #[test]
+    fn test_config_load_yaml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            "display:\n  compact: false\n  sections: [tools, git]\ncustom_tools:\n  - name: MyTool\n    command: my-tool --version\n    icon: null\nrequirements:\n  node: \">=20\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert!(!config.display.compact);
+        assert_eq!(config.display.sections.len(), 2);
+        assert_eq!(config.custom_tools[0].name, "MyTool");
+        assert_eq!(config.custom_tools[0].icon, None);
+        assert_eq!(config.requirements["node"], ">=20");
+
+        // An empty document is the default config
+        std::fs::write(&path, "").unwrap();
+        assert!(Config::load_from_path(&path).unwrap().display.compact);
+    }
+
+    #[test]
+    fn test_config_load_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(
+            &path,
+            r#"{"cache": {"default_ttl": 600}, "display": {"use_default_tools": false}}"#,
+        )
+        .unwrap();
+
+        // Migrations apply to JSON/YAML too
+        let config = Config::load_from_path(&path).unwrap();
+        assert_eq!(config.cache.default_ttl, 600);
+        assert!(!config.use_default_tools);
+
+        std::fs::write(&path, r#"{"display": {"compact": "yes"}}"#).unwrap();
+        let err = Config::load_from_path(&path).unwrap_err().to_string();
+        assert!(err.contains("config.json"));
+        assert!(err.contains("invalid type"));
+
+        std::fs::write(&path, "[1, 2]").unwrap();
+        assert!(Config::load_from_path(&path).is_err());
+    }
+
+    #[test]
+    fn test_config_save_and_load_yaml_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.display.compact = false;
+
+        for name in ["config.yaml", "config.json"] {
+            let path = dir.path().join(name);
+            config.save_to_path(&path).unwrap();
+            let loaded = Config::load_from_path(&path).unwrap();
+            assert!(!loaded.display.compact, "{}", name);
+            assert_eq!(
+                loaded.effective_tools().len(),
+                config.effective_tools().len()
+            );
+        }
+        let json = std::fs::read_to_string(dir.path().join("config.json")).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&json).is_ok());
+    }
+
+    #[test]
+    fn test_config_load_with_includes_and_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("base.yaml"),
+            "display:\n  compact: false\ncustom_tools:\n  - name: Base\n    command: base --version\n",
+        )
+        .unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            format!(
+                "include = [\"base.yaml\"]\n\n\
+                 [[custom_tools]]\nname = \"Local\"\ncommand = \"local --version\"\n\n\
+                 [target.'cfg({})'.cache]\ndefault_ttl = 5\n",
+                std::env::consts::OS
+            ),
+        )
+        .unwrap();
+
+        let config = Config::load_from_path(&path).unwrap();
+        assert!(!config.display.compact);
+        let names: Vec<&str> = config
+            .custom_tools
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Base", "Local"]);
+        assert_eq!(config.cache.default_ttl, 5);
+        assert!(config.include.is_empty());
+        assert!(config.target.is_empty());
+    }
+
     #[test]
     fn test_default_tools_have_required_fields() {
         let tools = default_tools();
@@ -700,6 +1994,7 @@ mod tests {
             icon: Some("🔧".to_string()),
             enabled: true,
             short_name: Some("mct".to_string()),
+            ..Default::default()
         });
 
         let tools = config.effective_tools();
@@ -719,6 +2014,8 @@ mod tests {
             enabled: Some(false),
             icon: None,
             short_name: None,
+            retries: None,
+            retry_delay_ms: None,
         });
         // Ruby is disabled by default, let's enable it
         config.tool_overrides.push(ToolOverride {
@@ -726,6 +2023,8 @@ mod tests {
             enabled: Some(true),
             icon: Some("💎💎".to_string()),
             short_name: None,
+            retries: Some(2),
+            retry_delay_ms: None,
         });
 
         let tools = config.effective_tools();
@@ -735,6 +2034,64 @@ mod tests {
         let ruby = tools.iter().find(|t| t.name == "Ruby").unwrap();
         assert!(ruby.enabled);
         assert_eq!(ruby.icon, Some("💎💎".to_string()));
+        assert_eq!(ruby.retries, Some(2));
+    }
+
+    #[test]
+    fn test_effective_tools_provenance() {
+        let mut config = Config::default();
+        config.tool_overrides.push(ToolOverride {
+            name: "Python".to_string(),
+            enabled: Some(false),
+            icon: Some("🐍".to_string()),
+            short_name: None,
+            retries: None,
+            retry_delay_ms: None,
+        });
+        config.custom_tools.push(ToolConfig {
+            name: "Deploy".to_string(),
+            command: "deploy --version".to_string(),
+            ..Default::default()
+        });
+
+        let tools = config.effective_tools_with_provenance();
+        let provenance = |name: &str| {
+            tools
+                .iter()
+                .find(|(tool, _)| tool.name == name)
+                .map(|(_, provenance)| provenance.clone())
+                .unwrap()
+        };
+        assert_eq!(
+            provenance("Python"),
+            ToolProvenance {
+                origin: ToolOrigin::Default,
+                overrides: vec!["enabled = false".to_string(), "icon = \"🐍\"".to_string()],
+            }
+        );
+        assert_eq!(
+            provenance("Python").format_display(),
+            "built-in default, tool_overrides: enabled = false, icon = \"🐍\""
+        );
+        assert!(provenance("Node").is_plain_default());
+        assert_eq!(provenance("Deploy").origin, ToolOrigin::CustomTools);
+
+        config.tools = vec![config.custom_tools[0].clone()];
+        let tools = config.effective_tools_with_provenance();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].1.origin, ToolOrigin::Tools);
+    }
+
+    #[test]
+    fn test_retry_delay_doubles() {
+        let tool = ToolConfig::default();
+        assert_eq!(tool.retry_delay(1).as_millis(), 200);
+        let tool = ToolConfig {
+            retry_delay_ms: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(tool.retry_delay(1).as_millis(), 50);
+        assert_eq!(tool.retry_delay(3).as_millis(), 200);
     }
 
     #[test]
@@ -750,6 +2107,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         });
 
         let tools = config.effective_tools();
@@ -768,6 +2126,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         });
 
         let tools = config.effective_tools();
@@ -944,6 +2303,446 @@ venv_fg = "black"
         assert_eq!(custom.venv_fg, Some(ThemeColor::Black));
     }
 
+    #[test]
+    fn test_custom_theme_apply_spec() {
+        let mut custom = CustomThemeConfig::default();
+        custom
+            .apply_spec("directory_bg=#3465A4, directory_fg=white,tool_bg=cyan;#112233")
+            .unwrap();
+        assert_eq!(custom.directory_bg, Some(ThemeColor::Rgb(0x34, 0x65, 0xA4)));
+        assert_eq!(custom.directory_fg, Some(ThemeColor::White));
+        assert_eq!(
+            custom.tool_bg,
+            Some(vec![ThemeColor::Cyan, ThemeColor::Rgb(0x11, 0x22, 0x33)])
+        );
+        assert!(custom.git_clean_bg.is_none());
+    }
+
+    #[test]
+    fn test_custom_theme_apply_spec_overrides_existing() {
+        let mut custom = CustomThemeConfig {
+            venv_bg: Some(ThemeColor::Red),
+            venv_fg: Some(ThemeColor::Black),
+            ..Default::default()
+        };
+        custom.apply_spec("venv_bg=green").unwrap();
+        assert_eq!(custom.venv_bg, Some(ThemeColor::Green));
+        assert_eq!(custom.venv_fg, Some(ThemeColor::Black));
+    }
+
+    #[test]
+    fn test_custom_theme_apply_spec_errors() {
+        let mut custom = CustomThemeConfig::default();
+        assert!(custom.apply_spec("directory_bg").is_err());
+        assert!(custom.apply_spec("unknown_key=blue").is_err());
+        assert!(custom.apply_spec("directory_bg=notacolor").is_err());
+        assert!(custom.apply_spec("tool_bg=blue;nope").is_err());
+        // Empty spec is a no-op
+        assert!(custom.apply_spec("").is_ok());
+    }
+
+    #[test]
+    fn test_prompt_config() {
+        let config = Config::default();
+        assert_eq!(config.prompt.exclude, vec![Section::System]);
+        assert_eq!(config.prompt.budget_ms, 50);
+
+        let config: Config =
+            toml::from_str("[prompt]\nexclude = [\"git\", \"system\"]\nbudget_ms = 20\n").unwrap();
+        assert_eq!(config.prompt.exclude, vec![Section::Git, Section::System]);
+        assert_eq!(config.prompt.budget_ms, 20);
+    }
+
+    #[test]
+    fn test_plugins_config() {
+        let config: Config = toml::from_str(
+            "[plugins.sdkman]\npath = \"~/plugins/sdkman.wasm\"\nallow_read = [\"~/.sdkman\"]\n",
+        )
+        .unwrap();
+        let plugin = &config.plugins["sdkman"];
+        assert_eq!(plugin.path, "~/plugins/sdkman.wasm");
+        assert!(plugin.enabled);
+        assert_eq!(plugin.allow_read, vec!["~/.sdkman"]);
+        assert_eq!(plugin.fuel, 100_000_000);
+        assert!(config.detectors.wasm);
+    }
+
+    #[test]
+    fn test_history_config() {
+        let config = Config::default();
+        assert!(!config.history.enabled);
+        assert!(config.history.path.is_none());
+
+        let config: Config =
+            toml::from_str("[history]\nenabled = true\npath = \"/tmp/h.jsonl\"\n").unwrap();
+        assert!(config.history.enabled);
+        assert_eq!(config.history.path, Some(PathBuf::from("/tmp/h.jsonl")));
+    }
+
+    #[test]
+    fn test_section_from_str() {
+        assert_eq!("git".parse::<Section>(), Ok(Section::Git));
+        assert_eq!(" System ".parse::<Section>(), Ok(Section::System));
+        assert!("network".parse::<Section>().is_err());
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~/src/app"), home.join("src/app"));
+        assert_eq!(expand_home("/tmp/x"), PathBuf::from("/tmp/x"));
+        assert_eq!(expand_home("~user/x"), PathBuf::from("~user/x"));
+        assert_eq!(expand_home("a/~"), PathBuf::from("a/~"));
+    }
+
+    #[test]
+    fn test_only_tools() {
+        let mut config = Config::default();
+        config
+            .only_tools(&["rust".to_string(), "Ruby".to_string()])
+            .unwrap();
+        let names: Vec<String> = config.enabled_tools().into_iter().map(|t| t.name).collect();
+        // Disabled tools are enabled when named explicitly
+        assert_eq!(names, vec!["Rust", "Ruby"]);
+
+        let err = config.only_tools(&["nope".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("Unknown tool: nope"));
+    }
+
+    #[test]
+    fn test_dir_override_matches() {
+        let entry = |path: &str| DirOverride {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        let infra = Path::new("/home/me/work/infra/modules/vpc");
+        assert!(entry("/home/me/work/infra").matches(infra).unwrap());
+        assert!(entry("/home/me/work/*").matches(infra).unwrap());
+        assert!(entry("/home/me/work/**").matches(infra).unwrap());
+        assert!(entry("/home/me/work/infra/**")
+            .matches(Path::new("/home/me/work/infra"))
+            .unwrap());
+        assert!(!entry("/home/me/work/app*").matches(infra).unwrap());
+        // `*` doesn't cross directories
+        assert!(!entry("/home/*/infra").matches(infra).unwrap());
+        let home = dirs::home_dir().unwrap();
+        assert!(entry("~/src/**").matches(&home.join("src/app")).unwrap());
+        assert!(entry("/work/[").matches(infra).is_err());
+    }
+
+    #[test]
+    fn test_apply_dir_overrides() {
+        let mut config: Config = toml::from_str(
+            r#"
+[[tool_overrides]]
+name = "kubectl"
+icon = "K"
+
+[[custom_tools]]
+name = "Deploy"
+command = "deploy --version"
+enabled = true
+
+[[dir_overrides]]
+path = "/work/infra/**"
+enable_tools = ["kubectl", "tf"]
+disable_tools = ["Node", "Deploy"]
+theme = { preset = "nord" }
+extras = { git_status = false }
+
+[[dir_overrides]]
+path = "/work/infra/legacy"
+disable_tools = ["terraform"]
+"#,
+        )
+        .unwrap();
+        let enabled = |config: &Config| -> Vec<String> {
+            config.enabled_tools().into_iter().map(|t| t.name).collect()
+        };
+
+        let mut elsewhere = config.clone();
+        elsewhere
+            .apply_dir_overrides(Path::new("/work/app"))
+            .unwrap();
+        assert!(enabled(&elsewhere).contains(&"Node".to_string()));
+        assert!(!enabled(&elsewhere).contains(&"kubectl".to_string()));
+        assert_eq!(elsewhere.theme.preset, "default");
+
+        config
+            .apply_dir_overrides(Path::new("/work/infra/net"))
+            .unwrap();
+        let tools = enabled(&config);
+        assert!(tools.contains(&"kubectl".to_string()));
+        assert!(tools.contains(&"terraform".to_string()));
+        assert!(!tools.contains(&"Node".to_string()));
+        assert!(!tools.contains(&"Deploy".to_string()));
+        // The existing override keeps its icon
+        assert_eq!(
+            config.find_tool("kubectl").unwrap().icon.as_deref(),
+            Some("K")
+        );
+        assert_eq!(config.theme.preset, "nord");
+        assert!(!config.extras.git_status);
+        assert!(config.extras.git_branch);
+
+        // Later entries win
+        let mut legacy: Config = config.clone();
+        legacy
+            .apply_dir_overrides(Path::new("/work/infra/legacy"))
+            .unwrap();
+        assert!(!enabled(&legacy).contains(&"terraform".to_string()));
+
+        config.dir_overrides[0]
+            .extras
+            .insert("git_stat".to_string(), true);
+        let error = config
+            .apply_dir_overrides(Path::new("/work/infra"))
+            .unwrap_err();
+        assert!(error.to_string().contains("Unknown extra: git_stat"));
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let mut config: Config = toml::from_str(
+            r#"
+[profiles.infra]
+enable_tools = ["kubectl"]
+disable_tools = ["py"]
+theme = { preset = "dracula" }
+
+[profiles.quiet.extras]
+git_status = false
+"#,
+        )
+        .unwrap();
+
+        let mut infra = config.clone();
+        infra.apply_profile("infra").unwrap();
+        let tools: Vec<String> = infra.enabled_tools().into_iter().map(|t| t.name).collect();
+        assert!(tools.contains(&"kubectl".to_string()));
+        assert!(!tools.contains(&"Python".to_string()));
+        assert_eq!(infra.theme.preset, "dracula");
+        assert!(infra.extras.git_status);
+
+        config.apply_profile("quiet").unwrap();
+        assert!(!config.extras.git_status);
+        assert_eq!(config.theme.preset, "default");
+
+        let error = config.apply_profile("work").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Configuration error: Unknown profile: work (available: infra, quiet)"
+        );
+        let error = Config::default().apply_profile("work").unwrap_err();
+        assert!(error.to_string().contains("defines no [profiles]"));
+    }
+
+    #[test]
+    fn test_apply_project_config() {
+        let project = tempfile::tempdir().unwrap();
+        let sub = project.path().join("src");
+        std::fs::create_dir(&sub).unwrap();
+        let path = project.path().join(".toolbox.toml");
+        std::fs::write(
+            &path,
+            r#"
+[display]
+compact = false
+
+[[custom_tools]]
+name = "App"
+command = "app --version"
+"#,
+        )
+        .unwrap();
+
+        // Untrusted: the display settings apply, the commands don't
+        let mut config = Config::default();
+        let store = TrustStore::default();
+        assert_eq!(
+            config.apply_project_config(&sub, &store).unwrap(),
+            Some(path.clone())
+        );
+        assert!(!config.display.compact);
+        assert!(config.custom_tools.is_empty());
+
+        let mut store = TrustStore::default();
+        store.trust(&path).unwrap();
+        let mut config = Config::default();
+        config.apply_project_config(&sub, &store).unwrap();
+        assert_eq!(config.custom_tools[0].name, "App");
+
+        // Edited since it was trusted: back to display settings only
+        std::fs::write(
+            &path,
+            "[[custom_tools]]\nname = \"Evil\"\ncommand = \"evil\"\n",
+        )
+        .unwrap();
+        let mut config = Config::default();
+        config.apply_project_config(&sub, &store).unwrap();
+        assert!(config.custom_tools.is_empty());
+
+        let mut config = Config::default();
+        let elsewhere = tempfile::tempdir().unwrap();
+        assert_eq!(
+            config
+                .apply_project_config(elsewhere.path(), &store)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_enable_found_tools() {
+        let mut config = Config::default();
+        // Node is on by default, Java off
+        config.enable_found_tools(&["Python".to_string(), "Java".to_string()]);
+        let names: Vec<String> = config.enabled_tools().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["Python", "Java"]);
+        // Only tools whose state changed get an override
+        assert!(config.tool_overrides.iter().all(|o| o.name != "Python"));
+        let java = config.tool_overrides.iter().find(|o| o.name == "Java");
+        assert_eq!(java.unwrap().enabled, Some(true));
+    }
+
+    #[test]
+    fn test_tools_only_and_extras_only() {
+        let mut config = Config::default();
+        config.tools_only();
+        assert_eq!(config.display.sections, vec![Section::Tools]);
+        assert_eq!(config.display.right_sections, vec![Section::Tools]);
+        assert!(!config.extras.git_branch && !config.extras.virtual_env);
+        assert!(!config.enabled_tools().is_empty());
+
+        let mut config = Config::default();
+        config.detectors.external = true;
+        config.extras_only();
+        assert!(!config.display.sections.contains(&Section::Tools));
+        assert_eq!(config.display.right_sections, vec![Section::Venv]);
+        assert!(config.extras.git_branch);
+        assert!(config.enabled_tools().is_empty());
+        assert!(!config.detectors.external);
+    }
+
+    #[test]
+    fn test_display_sections_default() {
+        let config = Config::default();
+        assert_eq!(config.display.sections, DEFAULT_SECTIONS);
+    }
+
+    #[test]
+    fn test_display_sections_from_toml() {
+        let toml_content = r#"
+[display]
+sections = ["tools", "git"]
+"#;
+        let config: Config = toml::from_str(toml_content).unwrap();
+        assert_eq!(config.display.sections, vec![Section::Tools, Section::Git]);
+    }
+
+    #[test]
+    fn test_display_sections_unknown_name_fails() {
+        let toml_content = r#"
+[display]
+sections = ["dir", "weather"]
+"#;
+        assert!(toml::from_str::<Config>(toml_content).is_err());
+    }
+
+    #[test]
+    fn test_powerline_layout_from_toml() {
+        let toml_content = r#"
+[display]
+layout = "split"
+right_sections = ["git"]
+"#;
+        let config: Config = toml::from_str(toml_content).unwrap();
+        assert_eq!(config.display.layout, PowerlineLayout::Split);
+        assert_eq!(config.display.right_sections, vec![Section::Git]);
+        assert_eq!(Config::default().display.layout, PowerlineLayout::Left);
+    }
+
+    #[test]
+    fn test_display_show_unavailable() {
+        assert!(!Config::default().display.show_unavailable);
+        let config: Config = toml::from_str("[display]\nshow_unavailable = true\n").unwrap();
+        assert!(config.display.show_unavailable);
+    }
+
+    #[test]
+    fn test_display_sort_and_pinned() {
+        let tool = |name: &str, short: &str, available: bool| {
+            let tool = if available {
+                ToolInfo::available(name.to_string(), "1.0".to_string())
+            } else {
+                ToolInfo::unavailable(name.to_string(), None)
+            };
+            tool.with_short_name(Some(short.to_string()))
+        };
+        let names = |tools: &[ToolInfo]| tools.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+        let sample = vec![
+            tool("Python", "py", true),
+            tool("Go", "go", false),
+            tool("Node.js", "node", true),
+            tool("Rust", "rust", true),
+        ];
+
+        let config: Config = toml::from_str("[display]\nsort = \"name\"\n").unwrap();
+        assert_eq!(config.display.sort, ToolSort::Name);
+        let mut tools = sample.clone();
+        config.display.order_tools(&mut tools);
+        assert_eq!(names(&tools), ["Go", "Node.js", "Python", "Rust"]);
+
+        let config: Config =
+            toml::from_str("[display]\nsort = \"status\"\npinned = [\"rust\", \"Node.js\"]\n")
+                .unwrap();
+        let mut tools = sample.clone();
+        config.display.order_tools(&mut tools);
+        assert_eq!(names(&tools), ["Rust", "Node.js", "Python", "Go"]);
+
+        let mut tools = sample.clone();
+        Config::default().display.order_tools(&mut tools);
+        assert_eq!(names(&tools), names(&sample));
+    }
+
+    #[test]
+    fn test_display_group_tools() {
+        assert!(!Config::default().display.group_tools);
+        let config: Config = toml::from_str("[display]\ngroup_tools = true\n").unwrap();
+        assert!(config.display.group_tools);
+    }
+
+    #[test]
+    fn test_powerline_layout_from_str() {
+        assert_eq!(
+            "Right".parse::<PowerlineLayout>(),
+            Ok(PowerlineLayout::Right)
+        );
+        assert!("center".parse::<PowerlineLayout>().is_err());
+    }
+
+    #[test]
+    fn test_config_with_user_themes() {
+        let toml_content = r##"
+[theme]
+preset = "work"
+
+[themes.work]
+preset = "nord"
+
+[themes.work.custom]
+directory_bg = "#112233"
+"##;
+        let config: Config = toml::from_str(toml_content).unwrap();
+        let work = config.themes.get("work").unwrap();
+        assert_eq!(work.preset, "nord");
+        assert_eq!(
+            work.custom.as_ref().unwrap().directory_bg,
+            Some(ThemeColor::Rgb(0x11, 0x22, 0x33))
+        );
+    }
+
     #[test]
     fn test_theme_config_toml_roundtrip() {
         let mut config = Config::default();
@@ -961,4 +2760,26 @@ venv_fg = "black"
         assert_eq!(custom.directory_bg, Some(ThemeColor::Rgb(0x34, 0x65, 0xA4)));
         assert_eq!(custom.directory_fg, Some(ThemeColor::White));
     }
+
+    #[test]
+    fn test_config_requirements() {
+        let config: Config =
+            toml::from_str("[requirements]\nnode = \">=20\"\nrust = \"^1.75\"\n").unwrap();
+        assert_eq!(config.requirements.len(), 2);
+        assert_eq!(config.requirements["node"], ">=20");
+
+        // Empty table is omitted when saving
+        let toml_str = toml::to_string_pretty(&Config::default()).unwrap();
+        assert!(!toml_str.contains("[requirements]"));
+    }
+
+    #[test]
+    fn test_find_tool_by_name_or_short_name() {
+        let config = Config::default();
+        assert_eq!(config.find_tool("node").unwrap().name, "Node");
+        assert_eq!(config.find_tool("PY").unwrap().name, "Python");
+        // Disabled tools are found too
+        assert_eq!(config.find_tool("ruby").unwrap().name, "Ruby");
+        assert!(config.find_tool("nonexistent").is_none());
+    }
 }
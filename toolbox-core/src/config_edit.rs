@@ -0,0 +1,227 @@
+//! Reading and editing individual config keys
+//!
+//! Keys are dotted paths into the TOML config (`display.compact`,
+//! `cache.default_ttl`, `themes.work.preset`). Edits go through `toml_edit`
+//! so comments and formatting of the rest of the file are preserved.
+
+use crate::config::Config;
+use crate::error::{Result, ToolboxError};
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+
+/// Look up a key in the effective configuration (defaults included)
+pub fn get_key(config: &Config, key: &str) -> Result<toml::Value> {
+    let mut value =
+        toml::Value::try_from(config).map_err(|e| ToolboxError::Config(e.to_string()))?;
+    for part in split_key(key)? {
+        value = match value {
+            toml::Value::Table(mut table) => table.remove(part),
+            _ => None,
+        }
+        .ok_or_else(|| ToolboxError::Config(format!("Config key not set: {}", key)))?;
+    }
+    Ok(value)
+}
+
+/// Format a value for printing: strings bare, tables as TOML, others as literals
+pub fn format_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Table(table) => toml::to_string_pretty(table)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Set a key in the config file contents, returning the edited contents
+/// The value is parsed as a TOML literal (`true`, `600`, `["dir", "git"]`)
+/// and falls back to a plain string; the result must still be a valid config.
+pub fn set_key(content: &str, key: &str, value: &str) -> Result<String> {
+    let parts = split_key(key)?;
+    let doc = parse_document(content)?;
+
+    let typed = value.parse::<toml_edit::Value>().ok();
+    let mut candidates: Vec<toml_edit::Value> = typed.into_iter().collect();
+    if !matches!(candidates.first(), Some(toml_edit::Value::String(_))) {
+        candidates.push(toml_edit::Value::from(value));
+    }
+
+    let mut last_error = None;
+    for candidate in candidates {
+        let mut edited = doc.clone();
+        insert_value(&mut edited, &parts, candidate, key)?;
+        let content = edited.to_string();
+        match toml::from_str::<Config>(&content) {
+            Ok(_) => return Ok(content),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(ToolboxError::Config(format!(
+        "Invalid value for {}: {}",
+        key,
+        last_error
+            .map(|e| e.message().to_string())
+            .unwrap_or_default()
+    )))
+}
+
+/// Remove a key from the config file contents, returning the edited contents
+pub fn unset_key(content: &str, key: &str) -> Result<String> {
+    let parts = split_key(key)?;
+    let mut doc = parse_document(content)?;
+    let not_set = || ToolboxError::Config(format!("Config key not set: {}", key));
+
+    let (last, parents) = parts.split_last().ok_or_else(not_set)?;
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for part in parents {
+        table = table
+            .get_mut(part)
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(not_set)?;
+    }
+    table.remove(last).ok_or_else(not_set)?;
+
+    Ok(doc.to_string())
+}
+
+fn parse_document(content: &str) -> Result<DocumentMut> {
+    content
+        .parse()
+        .map_err(|e| ToolboxError::Config(format!("Invalid config file: {}", e)))
+}
+
+fn split_key(key: &str) -> Result<Vec<&str>> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|p| p.trim().is_empty()) {
+        return Err(ToolboxError::Config(format!("Invalid config key: {}", key)));
+    }
+    Ok(parts)
+}
+
+/// Insert a value at a dotted path, creating missing tables
+fn insert_value(
+    doc: &mut DocumentMut,
+    parts: &[&str],
+    mut value: toml_edit::Value,
+    key: &str,
+) -> Result<()> {
+    let (last, parents) = parts
+        .split_last()
+        .ok_or_else(|| ToolboxError::Config(format!("Invalid config key: {}", key)))?;
+
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for part in parents {
+        if table.get(part).is_none() {
+            let mut new_table = Table::new();
+            new_table.set_implicit(true);
+            table.insert(part, Item::Table(new_table));
+        }
+        table = table
+            .get_mut(part)
+            .and_then(Item::as_table_like_mut)
+            .ok_or_else(|| {
+                ToolboxError::Config(format!("Cannot set {}: {} is not a table", key, part))
+            })?;
+    }
+
+    // Replace in place so comments around the key and value are kept
+    match table.get_mut(last) {
+        Some(item) => {
+            if let Some(old) = item.as_value() {
+                *value.decor_mut() = old.decor().clone();
+            }
+            *item = Item::Value(value);
+        }
+        None => {
+            table.insert(last, Item::Value(value));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_key_defaults() {
+        let config = Config::default();
+        assert_eq!(
+            get_key(&config, "display.compact").unwrap(),
+            toml::Value::Boolean(true)
+        );
+        assert_eq!(
+            format_value(&get_key(&config, "cache.default_ttl").unwrap()),
+            "300"
+        );
+        assert_eq!(
+            format_value(&get_key(&config, "theme.preset").unwrap()),
+            "default"
+        );
+        assert!(format_value(&get_key(&config, "display").unwrap()).contains("compact = true"));
+    }
+
+    #[test]
+    fn test_get_key_missing() {
+        let config = Config::default();
+        assert!(get_key(&config, "display.nope").is_err());
+        assert!(get_key(&config, "display.compact.deeper").is_err());
+        assert!(get_key(&config, "display..compact").is_err());
+    }
+
+    #[test]
+    fn test_set_key_preserves_comments() {
+        let content =
+            "# my config\n[display]\n# keep this\ncompact = false # inline\nshow_icons = true\n";
+        let edited = set_key(content, "display.compact", "true").unwrap();
+        assert_eq!(
+            edited,
+            "# my config\n[display]\n# keep this\ncompact = true # inline\nshow_icons = true\n"
+        );
+    }
+
+    #[test]
+    fn test_set_key_creates_tables() {
+        let edited = set_key("", "cache.default_ttl", "600").unwrap();
+        assert_eq!(edited, "[cache]\ndefault_ttl = 600\n");
+
+        let edited = set_key(&edited, "themes.work.preset", "nord").unwrap();
+        let config: Config = toml::from_str(&edited).unwrap();
+        assert_eq!(config.cache.default_ttl, 600);
+        assert_eq!(config.themes["work"].preset, "nord");
+    }
+
+    #[test]
+    fn test_set_key_value_types() {
+        let edited = set_key("", "display.sections", r#"["tools", "git"]"#).unwrap();
+        let config: Config = toml::from_str(&edited).unwrap();
+        assert_eq!(config.display.sections.len(), 2);
+
+        // A number where a string is expected falls back to a string
+        let edited = set_key("", "requirements.node", "20").unwrap();
+        let config: Config = toml::from_str(&edited).unwrap();
+        assert_eq!(config.requirements["node"], "20");
+    }
+
+    #[test]
+    fn test_set_key_rejects_invalid_values() {
+        let err = set_key("", "display.compact", "maybe").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Invalid value for display.compact"));
+        assert!(set_key("", "cache.default_ttl", "-5").is_err());
+        assert!(set_key("[display]\ncompact = true\n", "display.compact.x", "1").is_err());
+    }
+
+    #[test]
+    fn test_unset_key() {
+        let content = "[display]\ncompact = false\nshow_icons = false\n";
+        let edited = unset_key(content, "display.compact").unwrap();
+        assert_eq!(edited, "[display]\nshow_icons = false\n");
+
+        assert!(unset_key(&edited, "display.compact").is_err());
+        assert!(unset_key(&edited, "cache.default_ttl").is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! Config includes and OS-conditional sections
+//!
+//! `include = ["base.toml", "work.toml"]` merges other config files (relative
+//! to the including file) underneath the current one, in order, and
+//! `[target.'cfg(macos)']` tables are merged on top when their predicate
+//! matches the running OS. Tables merge key by key, arrays of tables
+//! (`custom_tools`, `tool_overrides`) are appended and other values are
+//! replaced, so a shared base config only needs per-machine tweaks.
+
+use crate::config::expand_home;
+use crate::error::{Result, ToolboxError};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// Whether TOML config contents use `include` or `target` at the top level
+pub fn is_layered(content: &str) -> bool {
+    content
+        .parse::<toml_edit::DocumentMut>()
+        .map(|doc| doc.contains_key("include") || doc.contains_key("target"))
+        .unwrap_or(false)
+}
+
+/// Resolve includes and matching targets of a config file into one table
+/// `read` turns a path into TOML text (format conversion and migrations).
+pub fn resolve(
+    path: &Path,
+    content: &str,
+    read: &dyn Fn(&Path) -> Result<String>,
+) -> Result<Value> {
+    resolve_inner(path, content, read, &mut Vec::new())
+}
+
+fn resolve_inner(
+    path: &Path,
+    content: &str,
+    read: &dyn Fn(&Path) -> Result<String>,
+    stack: &mut Vec<PathBuf>,
+) -> Result<Value> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(ToolboxError::Config(format!(
+            "Config include cycle: {}",
+            chain.join(" -> ")
+        )));
+    }
+    stack.push(canonical);
+
+    let mut own: toml::Table = toml::from_str(content)?;
+    let includes = match own.remove("include") {
+        None => Vec::new(),
+        Some(Value::Array(items)) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s),
+                _ => Err(ToolboxError::Config(
+                    "include must be a list of file paths".to_string(),
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Some(_) => {
+            return Err(ToolboxError::Config(
+                "include must be a list of file paths".to_string(),
+            ))
+        }
+    };
+    let targets = match own.remove("target") {
+        None => toml::Table::new(),
+        Some(Value::Table(targets)) => targets,
+        Some(_) => {
+            return Err(ToolboxError::Config(
+                "target must be a table of cfg(...) sections".to_string(),
+            ))
+        }
+    };
+
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut merged = Value::Table(toml::Table::new());
+    for include in includes {
+        let include_path = include_path(base_dir, &include);
+        let content = read(&include_path).map_err(|e| {
+            ToolboxError::Config(format!(
+                "Could not read included config {}: {}",
+                include_path.display(),
+                e
+            ))
+        })?;
+        let value = resolve_inner(&include_path, &content, read, stack)?;
+        merge(&mut merged, value);
+    }
+    merge(&mut merged, Value::Table(own));
+
+    for (predicate, section) in targets {
+        if cfg_matches(&predicate)? {
+            tracing::debug!(target = %predicate, "applying config target");
+            merge(&mut merged, section);
+        }
+    }
+
+    stack.pop();
+    Ok(merged)
+}
+
+/// Include paths are relative to the including file; `~/` is the home directory
+fn include_path(base_dir: &Path, include: &str) -> PathBuf {
+    base_dir.join(expand_home(include))
+}
+
+/// Merge `overlay` into `base`: tables recursively, arrays of tables appended,
+/// everything else replaced
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay))
+            if is_table_array(base) && is_table_array(&overlay) =>
+        {
+            base.extend(overlay);
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn is_table_array(items: &[Value]) -> bool {
+    !items.is_empty() && items.iter().all(Value::is_table)
+}
+
+/// Evaluate a `cfg(...)` target predicate against the running OS
+pub fn cfg_matches(predicate: &str) -> Result<bool> {
+    evaluate_cfg(predicate, std::env::consts::OS, std::env::consts::FAMILY)
+}
+
+/// Evaluate a `cfg(...)` predicate for a given OS and OS family
+/// Supports `unix`, `windows`, OS names (`linux`, `macos`, ...),
+/// `target_os = "..."`, `target_family = "..."`, `not(..)`, `any(..)`, `all(..)`.
+fn evaluate_cfg(predicate: &str, os: &str, family: &str) -> Result<bool> {
+    let invalid = |reason: &str| {
+        ToolboxError::Config(format!(
+            "Invalid target `{}`: {} (expected e.g. cfg(macos) or cfg(not(windows)))",
+            predicate, reason
+        ))
+    };
+
+    let inner = predicate
+        .trim()
+        .strip_prefix("cfg(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .ok_or_else(|| invalid("not a cfg(...) expression"))?;
+    let mut parser = CfgParser {
+        input: inner,
+        pos: 0,
+        os,
+        family,
+    };
+    let result = parser.predicate().map_err(|e| invalid(&e))?;
+    parser.skip_whitespace();
+    if parser.pos != parser.input.len() {
+        return Err(invalid("unexpected trailing input"));
+    }
+    Ok(result)
+}
+
+/// Recursive-descent parser that evaluates while parsing
+struct CfgParser<'a> {
+    input: &'a str,
+    pos: usize,
+    os: &'a str,
+    family: &'a str,
+}
+
+impl CfgParser<'_> {
+    fn predicate(&mut self) -> std::result::Result<bool, String> {
+        let ident = self.ident()?;
+        self.skip_whitespace();
+        match ident.as_str() {
+            "not" | "any" | "all" => {
+                self.expect('(')?;
+                let mut values = vec![self.predicate()?];
+                while self.eat(',') {
+                    // Allow a trailing comma
+                    self.skip_whitespace();
+                    if self.peek() == Some(')') {
+                        break;
+                    }
+                    values.push(self.predicate()?);
+                }
+                self.expect(')')?;
+                match ident.as_str() {
+                    "not" if values.len() == 1 => Ok(!values[0]),
+                    "not" => Err("not() takes exactly one predicate".to_string()),
+                    "any" => Ok(values.iter().any(|v| *v)),
+                    _ => Ok(values.iter().all(|v| *v)),
+                }
+            }
+            _ if self.eat('=') => {
+                let value = self.string()?;
+                match ident.as_str() {
+                    "target_os" => Ok(value == self.os),
+                    "target_family" => Ok(value == self.family),
+                    other => Err(format!("unknown key `{}`", other)),
+                }
+            }
+            "unix" | "windows" if ident == self.family => Ok(true),
+            "unix" => Ok(false),
+            _ => Ok(ident == self.os),
+        }
+    }
+
+    fn ident(&mut self) -> std::result::Result<String, String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                self.pos += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+        if start == self.pos {
+            return Err("expected a name".to_string());
+        }
+        Ok(self.input[start..self.pos].to_string())
+    }
+
+    fn string(&mut self) -> std::result::Result<String, String> {
+        self.skip_whitespace();
+        self.expect('"')?;
+        let start = self.pos;
+        let end = self.input[start..]
+            .find('"')
+            .ok_or_else(|| "unterminated string".to_string())?;
+        self.pos = start + end + 1;
+        Ok(self.input[start..start + end].to_string())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> std::result::Result<(), String> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`", expected))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> Value {
+        Value::Table(toml::from_str(content).unwrap())
+    }
+
+    fn read_file(path: &Path) -> Result<String> {
+        Ok(std::fs::read_to_string(path)?)
+    }
+
+    #[test]
+    fn test_evaluate_cfg() {
+        let linux = |p: &str| evaluate_cfg(p, "linux", "unix").unwrap();
+        assert!(linux("cfg(linux)"));
+        assert!(linux("cfg(unix)"));
+        assert!(!linux("cfg(macos)"));
+        assert!(!linux("cfg(windows)"));
+        assert!(linux("cfg(target_os = \"linux\")"));
+        assert!(linux("cfg(target_family=\"unix\")"));
+        assert!(linux("cfg(not(macos))"));
+        assert!(linux("cfg(any(macos, linux))"));
+        assert!(!linux("cfg(all(unix, not(linux)))"));
+        assert!(linux("cfg(any(macos, linux,))"));
+
+        assert!(evaluate_cfg("cfg(windows)", "windows", "windows").unwrap());
+        assert!(!evaluate_cfg("cfg(unix)", "windows", "windows").unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_cfg_invalid() {
+        for predicate in [
+            "macos",
+            "cfg()",
+            "cfg(not(macos, linux))",
+            "cfg(target_arch = \"x86\")",
+            "cfg(macos linux)",
+            "cfg(target_os = \"linux)",
+        ] {
+            let err = evaluate_cfg(predicate, "linux", "unix").unwrap_err();
+            assert!(
+                err.to_string().contains("Invalid target"),
+                "{}: {}",
+                predicate,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = table(
+            "[display]\ncompact = true\nsections = [\"dir\", \"git\"]\n\n\
+             [[custom_tools]]\nname = \"A\"\ncommand = \"a\"\n",
+        );
+        merge(
+            &mut base,
+            table(
+                "[display]\ncompact = false\nsections = [\"tools\"]\n\n\
+                 [[custom_tools]]\nname = \"B\"\ncommand = \"b\"\n",
+            ),
+        );
+
+        assert_eq!(base["display"]["compact"], Value::Boolean(false));
+        // Plain arrays are replaced, arrays of tables appended
+        assert_eq!(base["display"]["sections"].as_array().unwrap().len(), 1);
+        let tools = base["custom_tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[1]["name"].as_str(), Some("B"));
+    }
+
+    #[test]
+    fn test_is_layered() {
+        assert!(is_layered("include = [\"a.toml\"]\n"));
+        assert!(is_layered(
+            "[target.'cfg(macos)'.display]\ncompact = false\n"
+        ));
+        assert!(!is_layered("[display]\ncompact = false\n"));
+    }
+
+    #[test]
+    fn test_resolve_includes_and_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("base.toml"),
+            "[display]\ncompact = false\nshow_icons = false\n\n[cache]\ndefault_ttl = 10\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("work.toml"), "[cache]\ndefault_ttl = 20\n").unwrap();
+
+        let content = format!(
+            "include = [\"base.toml\", \"work.toml\"]\n\n\
+             [display]\nshow_icons = true\n\n\
+             [target.'cfg({})'.cache]\ndefault_ttl = 30\n\n\
+             [target.'cfg(not({}))'.cache]\nenabled = false\n",
+            std::env::consts::OS,
+            std::env::consts::OS
+        );
+        let path = dir.path().join("config.toml");
+        let value = resolve(&path, &content, &read_file).unwrap();
+
+        // Included files are the base, this file wins, then matching targets
+        assert_eq!(value["display"]["compact"], Value::Boolean(false));
+        assert_eq!(value["display"]["show_icons"], Value::Boolean(true));
+        assert_eq!(value["cache"]["default_ttl"], Value::Integer(30));
+        assert!(value["cache"].get("enabled").is_none());
+        assert!(value.get("include").is_none());
+        assert!(value.get("target").is_none());
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.toml");
+        let b = dir.path().join("b.toml");
+        std::fs::write(&a, "include = [\"b.toml\"]\n").unwrap();
+        std::fs::write(&b, "include = [\"a.toml\"]\n").unwrap();
+
+        let err = resolve(&a, "include = [\"b.toml\"]\n", &read_file).unwrap_err();
+        assert!(err.to_string().contains("include cycle"));
+
+        let err = resolve(&a, "include = [\"missing.toml\"]\n", &read_file).unwrap_err();
+        assert!(err.to_string().contains("missing.toml"));
+
+        assert!(resolve(&a, "include = \"b.toml\"\n", &read_file).is_err());
+        assert!(resolve(&a, "[target.macos]\nx = 1\n", &read_file).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Config file migrations
+//!
+//! Each config file records the layout it was written for in `config_version`
+//! (files without it are version 0). Older layouts are upgraded step by step
+//! on load, and `toolbox config migrate --write` rewrites the file so the
+//! upgrade sticks. Edits go through `toml_edit` to keep comments intact.
+
+use crate::error::{Result, ToolboxError};
+use toml_edit::{DocumentMut, Item};
+
+/// Config layout version written by this release
+pub const CONFIG_VERSION: u32 = 1;
+
+/// A single upgrade step from `from` to `from + 1`
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut DocumentMut),
+}
+
+/// All migrations, in order; add one here whenever the layout changes
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "move display.use_default_tools to the top level",
+    apply: move_use_default_tools,
+}];
+
+/// Result of migrating config file contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrated {
+    /// Migrated contents (unchanged if already current)
+    pub content: String,
+    /// Version the contents were written for
+    pub from: u32,
+    /// Version after migration
+    pub to: u32,
+    /// Descriptions of the applied steps
+    pub applied: Vec<&'static str>,
+}
+
+impl Migrated {
+    /// Whether any migration was applied
+    pub fn changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+/// Version recorded in config file contents (0 if absent)
+pub fn config_version(content: &str) -> Result<u32> {
+    let doc = parse_document(content)?;
+    version_of(&doc)
+}
+
+/// Upgrade config file contents to the current layout
+/// Contents from a newer release are an error, since their layout is unknown.
+pub fn migrate(content: &str) -> Result<Migrated> {
+    let mut doc = parse_document(content)?;
+    let from = version_of(&doc)?;
+    if from > CONFIG_VERSION {
+        return Err(ToolboxError::Config(format!(
+            "Config version {} is newer than supported version {}; upgrade toolbox",
+            from, CONFIG_VERSION
+        )));
+    }
+    if from == CONFIG_VERSION {
+        return Ok(Migrated {
+            content: content.to_string(),
+            from,
+            to: from,
+            applied: Vec::new(),
+        });
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= from) {
+        (migration.apply)(&mut doc);
+        applied.push(migration.description);
+    }
+    doc["config_version"] = toml_edit::value(i64::from(CONFIG_VERSION));
+
+    Ok(Migrated {
+        content: doc.to_string(),
+        from,
+        to: CONFIG_VERSION,
+        applied,
+    })
+}
+
+fn parse_document(content: &str) -> Result<DocumentMut> {
+    content
+        .parse()
+        .map_err(|e| ToolboxError::Config(format!("Invalid config file: {}", e)))
+}
+
+fn version_of(doc: &DocumentMut) -> Result<u32> {
+    match doc.get("config_version") {
+        None => Ok(0),
+        Some(item) => item
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                ToolboxError::Config("config_version must be a non-negative integer".to_string())
+            }),
+    }
+}
+
+/// 0 -> 1: `use_default_tools` was documented under `[display]` but only
+/// read at the top level
+fn move_use_default_tools(doc: &mut DocumentMut) {
+    let moved = doc
+        .get_mut("display")
+        .and_then(Item::as_table_like_mut)
+        .and_then(|display| display.remove("use_default_tools"));
+    if let Some(value) = moved {
+        if !doc.contains_key("use_default_tools") {
+            doc.insert("use_default_tools", value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_migrations_are_contiguous() {
+        assert_eq!(MIGRATIONS.len() as u32, CONFIG_VERSION);
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.from, index as u32);
+        }
+    }
+
+    #[test]
+    fn test_config_version() {
+        assert_eq!(config_version("").unwrap(), 0);
+        assert_eq!(config_version("config_version = 1\n").unwrap(), 1);
+        assert!(config_version("config_version = -1\n").is_err());
+        assert!(config_version("config_version = \"1\"\n").is_err());
+    }
+
+    #[test]
+    fn test_migrate_use_default_tools() {
+        let content = "# mine\n[display]\ncompact = false\nuse_default_tools = false\n";
+        let migrated = migrate(content).unwrap();
+        assert!(migrated.changed());
+        assert_eq!((migrated.from, migrated.to), (0, CONFIG_VERSION));
+        assert_eq!(migrated.applied.len(), 1);
+
+        let config: Config = toml::from_str(&migrated.content).unwrap();
+        assert!(!config.use_default_tools);
+        assert!(!config.display.compact);
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert!(migrated.content.contains("# mine\n"));
+        assert!(!migrated
+            .content
+            .contains("[display]\ncompact = false\nuse_default_tools"));
+    }
+
+    #[test]
+    fn test_migrate_keeps_existing_top_level_value() {
+        let content = "use_default_tools = true\n\n[display]\nuse_default_tools = false\n";
+        let config: Config = toml::from_str(&migrate(content).unwrap().content).unwrap();
+        assert!(config.use_default_tools);
+    }
+
+    #[test]
+    fn test_migrate_current_is_unchanged() {
+        let content = "config_version = 1\n[display]\ncompact = true\n";
+        let migrated = migrate(content).unwrap();
+        assert!(!migrated.changed());
+        assert_eq!(migrated.content, content);
+        assert!(migrated.applied.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_newer_version_fails() {
+        let err = migrate("config_version = 99\n").unwrap_err();
+        assert!(err.to_string().contains("newer than supported"));
+    }
+}
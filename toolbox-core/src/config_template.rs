@@ -0,0 +1,319 @@
+//! Commented config files for `toolbox init`
+//!
+//! The full template is the default config with every option explained by the
+//! description of its config JSON Schema entry (the doc comments of the config
+//! types), so the text can't drift from what the loader accepts. Options
+//! without a default value are listed commented out, and so are the built-in
+//! tools that are disabled by default. Both templates keep the `tool_overrides`
+//! of the config they are written from (`toolbox init --from-detect`).
+
+use crate::config::{Config, ToolOverride};
+use crate::config_migrate::CONFIG_VERSION;
+use crate::error::{Result, ToolboxError};
+use crate::schema;
+use serde_json::Value;
+use toml_edit::{DocumentMut, Item, Table};
+
+const FULL_HEADER: &str = "\
+# toolbox configuration
+#
+# Every option is listed with its default value. Lines starting with `#` are
+# comments; options shown commented out have no default. `toolbox config
+# validate` checks this file.
+
+";
+
+/// A config (usually the default one) with every option explained
+pub fn full(config: &Config) -> Result<String> {
+    let schema = schema::config_schema();
+    let text = toml::to_string_pretty(config).map_err(|e| ToolboxError::Config(e.to_string()))?;
+    let doc: DocumentMut = text
+        .parse()
+        .map_err(|e| ToolboxError::Config(format!("Invalid default config: {}", e)))?;
+
+    let mut out = FULL_HEADER.to_string();
+    let mut writer = Writer {
+        definitions: &schema["definitions"],
+        out: &mut out,
+    };
+    writer.table(&[], doc.as_table(), &schema);
+    writer.disabled_tools(config);
+    Ok(out)
+}
+
+/// Only the layout version and the tool overrides of a config: everything
+/// else uses the defaults
+pub fn minimal(config: &Config) -> Result<String> {
+    let mut out = format!(
+        "# toolbox configuration; options left out use their defaults\n\
+         # (`toolbox init --full` writes all of them, explained)\n\
+         config_version = {}\n",
+        CONFIG_VERSION
+    );
+    if !config.tool_overrides.is_empty() {
+        #[derive(serde::Serialize)]
+        struct Overrides<'a> {
+            tool_overrides: &'a [ToolOverride],
+        }
+        let overrides = toml::to_string(&Overrides {
+            tool_overrides: &config.tool_overrides,
+        })
+        .map_err(|e| ToolboxError::Config(e.to_string()))?;
+        out.push('\n');
+        out.push_str(&overrides);
+    }
+    Ok(out)
+}
+
+struct Writer<'a> {
+    definitions: &'a Value,
+    out: &'a mut String,
+}
+
+impl Writer<'_> {
+    /// Write a table's values, then its sub-tables, each with its description
+    fn table(&mut self, path: &[&str], table: &Table, schema: &Value) {
+        let schema = resolve(self.definitions, schema);
+        let properties = schema["properties"].as_object();
+        let property = |key: &str| properties.and_then(|p| p.get(key)).unwrap_or(&Value::Null);
+
+        let mut table_arrays = Vec::new();
+        for (key, item) in table.iter() {
+            if let Item::Value(value) = item {
+                // `[[key]]` entries can't follow `key = []`
+                let empty = value.as_array().is_some_and(|array| array.is_empty());
+                if empty && self.is_table_array(property(key)) {
+                    table_arrays.push((key, property(key)));
+                    continue;
+                }
+                let mut value = value.clone();
+                value.decor_mut().clear();
+                self.comment(property(key));
+                self.out.push_str(&format!("{} = {}\n", key, value));
+            }
+        }
+        // Options without a default
+        let mut missing_tables = Vec::new();
+        for (key, property) in properties.into_iter().flatten() {
+            if table.contains_key(key) {
+                continue;
+            }
+            if self.is_table(property) {
+                missing_tables.push((key, property));
+                continue;
+            }
+            self.comment(property);
+            self.out
+                .push_str(&format!("# {} = {}\n", key, self.placeholder(property)));
+        }
+
+        for (key, item) in table.iter() {
+            if let Item::Table(sub_table) = item {
+                let mut sub_path = path.to_vec();
+                sub_path.push(key);
+                self.out.push('\n');
+                self.comment(property(key));
+                self.out.push_str(&format!("[{}]\n", sub_path.join(".")));
+                self.table(&sub_path, sub_table, property(key));
+            }
+        }
+        for (key, item) in table.iter() {
+            if let Item::ArrayOfTables(array) = item {
+                let mut sub_path = path.to_vec();
+                sub_path.push(key);
+                self.out.push('\n');
+                self.comment(property(key));
+                for (index, entry) in array.iter().enumerate() {
+                    if index > 0 {
+                        self.out.push('\n');
+                    }
+                    self.out.push_str(&format!("[[{}]]\n", sub_path.join(".")));
+                    self.table(&sub_path, entry, &property(key)["items"]);
+                }
+            }
+        }
+        for (key, property) in missing_tables {
+            let mut sub_path = path.to_vec();
+            sub_path.push(key);
+            self.out.push('\n');
+            self.comment(property);
+            self.out.push_str(&format!("# [{}]\n", sub_path.join(".")));
+        }
+        for (key, property) in table_arrays {
+            let mut sub_path = path.to_vec();
+            sub_path.push(key);
+            self.out.push('\n');
+            self.comment(property);
+            self.out
+                .push_str(&format!("# [[{}]]\n", sub_path.join(".")));
+        }
+    }
+
+    /// The built-in tools that are off by default (and not overridden), ready
+    /// to enable
+    fn disabled_tools(&mut self, config: &Config) {
+        let disabled: Vec<String> = config
+            .effective_tools()
+            .into_iter()
+            .filter(|tool| !tool.enabled)
+            .map(|tool| tool.name)
+            .filter(|name| config.tool_overrides.iter().all(|o| &o.name != name))
+            .collect();
+        if disabled.is_empty() {
+            return;
+        }
+        self.out.push_str(
+            "\n# Built-in tools that are disabled by default; uncomment an entry to enable it\n",
+        );
+        for name in disabled {
+            self.out.push_str(&format!(
+                "# [[tool_overrides]]\n# name = \"{}\"\n# enabled = true\n",
+                name
+            ));
+        }
+    }
+
+    /// `# description` lines of a property
+    fn comment(&mut self, property: &Value) {
+        let description = property["description"]
+            .as_str()
+            .or_else(|| resolve(self.definitions, property)["description"].as_str());
+        for line in description.into_iter().flat_map(str::lines) {
+            self.out
+                .push_str(&format!("# {}\n", line).replace("# \n", "#\n"));
+        }
+    }
+
+    fn is_table(&self, property: &Value) -> bool {
+        self.types(property).any(|t| t == "object")
+    }
+
+    fn is_table_array(&self, property: &Value) -> bool {
+        self.types(property).any(|t| t == "array") && self.is_table(&property["items"])
+    }
+
+    /// The non-null types of a property
+    fn types<'v>(&'v self, property: &'v Value) -> impl Iterator<Item = &'v str> {
+        let schema = resolve(self.definitions, property);
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        types.into_iter().filter(|t| *t != "null")
+    }
+
+    /// An example value for a commented-out option
+    fn placeholder(&self, property: &Value) -> String {
+        let schema = resolve(self.definitions, property);
+        let first_variant = schema["oneOf"][0]["enum"][0]
+            .as_str()
+            .or_else(|| schema["enum"][0].as_str());
+        if let Some(variant) = first_variant {
+            return format!("\"{}\"", variant);
+        }
+        match self.types(property).next() {
+            Some("boolean") => "false",
+            Some("integer") | Some("number") => "0",
+            Some("array") => "[]",
+            _ => "\"\"",
+        }
+        .to_string()
+    }
+}
+
+/// The schema a property refers to (`$ref`, `allOf` with one `$ref`, or an
+/// optional one: `anyOf` a `$ref` and null)
+fn resolve<'v>(definitions: &'v Value, schema: &'v Value) -> &'v Value {
+    let reference = schema["$ref"]
+        .as_str()
+        .or_else(|| schema["allOf"][0]["$ref"].as_str())
+        .or_else(|| schema["anyOf"][0]["$ref"].as_str());
+    match reference.and_then(|r| r.strip_prefix("#/definitions/")) {
+        Some(name) => &definitions[name],
+        None => schema,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_is_the_default_config() {
+        let text = full(&Config::default()).unwrap();
+        let config: Config = toml::from_str(&text).unwrap();
+        assert_eq!(
+            toml::to_string(&config).unwrap(),
+            toml::to_string(&Config::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_full_explains_options() {
+        let text = full(&Config::default()).unwrap();
+        // Values and tables carry their doc comments
+        assert!(text.contains("# Display settings\n[display]\n"));
+        assert!(text.contains("\nshow_icons = true\n"));
+        let line = text.find("\nshow_icons = true").unwrap();
+        assert!(text[..line].ends_with(&format!(
+            "# {}",
+            schema::config_schema()["definitions"]["DisplayConfig"]["properties"]["show_icons"]
+                ["description"]
+                .as_str()
+                .unwrap()
+        )));
+        // Options without a default are commented out
+        assert!(text.contains("\n# include = []\n"));
+        assert!(text.contains("\n# [requirements]\n"));
+        assert!(text.contains("\n# [theme.custom]\n"));
+        // Empty lists of tables too, so entries can be added below
+        assert!(text.contains("\n# [[custom_tools]]\n"));
+        assert!(!text.contains("custom_tools = []"));
+    }
+
+    #[test]
+    fn test_full_lists_disabled_tools() {
+        let text = full(&Config::default()).unwrap();
+        let disabled: Vec<String> = Config::default()
+            .effective_tools()
+            .into_iter()
+            .filter(|tool| !tool.enabled)
+            .map(|tool| tool.name)
+            .collect();
+        assert!(!disabled.is_empty());
+        for name in disabled {
+            assert!(text.contains(&format!(
+                "# [[tool_overrides]]\n# name = \"{}\"\n# enabled = true\n",
+                name
+            )));
+        }
+    }
+
+    #[test]
+    fn test_minimal() {
+        let config: Config = toml::from_str(&minimal(&Config::default()).unwrap()).unwrap();
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert!(config.tool_overrides.is_empty());
+    }
+
+    #[test]
+    fn test_templates_keep_tool_overrides() {
+        let mut detected = Config::default();
+        detected.enable_found_tools(&["Python".to_string(), "Java".to_string()]);
+        let enabled = |config: &Config| -> Vec<String> {
+            config.enabled_tools().into_iter().map(|t| t.name).collect()
+        };
+
+        let text = full(&detected).unwrap();
+        assert!(text.contains("\n[[tool_overrides]]\n"));
+        assert!(text.contains("\nname = \"Java\"\n"));
+        // Java is enabled above, so it isn't offered commented out as well
+        assert!(!text.contains("# name = \"Java\""));
+        let config: Config = toml::from_str(&text).unwrap();
+        assert_eq!(enabled(&config), ["Python", "Java"]);
+
+        let config: Config = toml::from_str(&minimal(&detected).unwrap()).unwrap();
+        assert_eq!(enabled(&config), ["Python", "Java"]);
+    }
+}
@@ -0,0 +1,654 @@
+//! Config file validation
+//!
+//! A strict parse that reports problems plain loading would either reject
+//! with a terse error or silently ignore: syntax and type errors, unknown or
+//! misspelled keys (with suggestions), invalid `parse_regex` patterns and
+//! `parse_json_pointer`s, `parse_template` placeholders without a matching
+//! group and unknown theme presets, each with its line and column in the file.
+
+use crate::color::ResolvedTheme;
+use crate::config::{
+    CacheConfig, Config, CustomThemeConfig, DetectorsConfig, DirOverride, DisplayConfig,
+    EnvironmentConfig, ExtrasConfig, HistoryConfig, PluginConfig, Profile, PromptConfig,
+    SandboxConfig, StorageConfig, ThemeConfig, ToolConfig, ToolOverride, UpdateConfig,
+};
+use crate::config_merge;
+use crate::error::ToolboxError;
+use crate::template;
+use serde::de::{self, Deserialize, Visitor};
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use toml_edit::{ImDocument, Item, TableLike};
+
+/// A single problem found in a config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// 1-based line and column, if the problem can be located
+    pub location: Option<(usize, usize)>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "{}:{}: {}", line, column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// One step of a path into the config document
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Validate config file contents, returning every problem found
+pub fn validate_config(content: &str) -> Vec<ConfigIssue> {
+    let doc = match ImDocument::parse(content) {
+        Ok(doc) => doc,
+        Err(e) => {
+            return vec![ConfigIssue {
+                location: e.span().map(|span| line_column(content, span.start)),
+                message: e.message().to_string(),
+            }]
+        }
+    };
+
+    let mut unknown = Vec::new();
+    let deserializer = toml::Deserializer::new(content);
+    let config: Config = match serde_ignored::deserialize(deserializer, |path| {
+        unknown.push(segments(&path));
+    }) {
+        Ok(config) => config,
+        Err(e) => {
+            return vec![ConfigIssue {
+                location: e.span().map(|span| line_column(content, span.start)),
+                message: e.message().to_string(),
+            }]
+        }
+    };
+
+    // Extras and themes of dir overrides and profiles, by where they are
+    let settings: Vec<_> = config
+        .dir_overrides
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let path = vec![
+                Segment::Key("dir_overrides".to_string()),
+                Segment::Index(index),
+            ];
+            (path, &entry.extras, entry.theme.as_ref())
+        })
+        .chain(config.profiles.iter().map(|(name, profile)| {
+            let path = vec![
+                Segment::Key("profiles".to_string()),
+                Segment::Key(name.clone()),
+            ];
+            (path, &profile.extras, profile.theme.as_ref())
+        }))
+        .collect();
+
+    // Those extras are maps, so serde doesn't flag unknown names
+    let extras = struct_fields::<ExtrasConfig>();
+    for (path, names, _) in &settings {
+        for name in names.keys() {
+            if !extras.contains(&name.as_str()) {
+                let mut path = path.clone();
+                path.push(Segment::Key("extras".to_string()));
+                path.push(Segment::Key(name.clone()));
+                unknown.push(path);
+            }
+        }
+    }
+
+    let mut issues = Vec::new();
+    let issue = |path: &[Segment], message: String| ConfigIssue {
+        location: locate(&doc, path).map(|span| line_column(content, span.start)),
+        message,
+    };
+
+    for path in &unknown {
+        let mut message = format!("unknown key `{}`", display_path(path));
+        if let Some((Segment::Key(key), parent)) = path.split_last() {
+            if let Some(suggestion) = suggest(key, known_fields(parent)) {
+                message.push_str(&format!(" (did you mean `{}`?)", suggestion));
+            }
+        }
+        issues.push(issue(path, message));
+    }
+
+    for (table, tools) in [
+        ("tools", &config.tools),
+        ("custom_tools", &config.custom_tools),
+    ] {
+        for (index, tool) in tools.iter().enumerate() {
+            if let Some(ref pattern) = tool.parse_regex {
+                if let Err(e) = regex::Regex::new(pattern) {
+                    let path = [
+                        Segment::Key(table.to_string()),
+                        Segment::Index(index),
+                        Segment::Key("parse_regex".to_string()),
+                    ];
+                    let detail = e.to_string();
+                    let reason = detail.lines().last().unwrap_or("").trim();
+                    issues.push(issue(
+                        &path,
+                        format!("invalid parse_regex for {}: {}", tool.name, reason),
+                    ));
+                }
+            }
+            if let Some(ref pointer) = tool.parse_json_pointer {
+                if !pointer.is_empty() && !pointer.starts_with('/') {
+                    let path = [
+                        Segment::Key(table.to_string()),
+                        Segment::Index(index),
+                        Segment::Key("parse_json_pointer".to_string()),
+                    ];
+                    issues.push(issue(
+                        &path,
+                        format!(
+                            "invalid parse_json_pointer for {}: `{}` must start with `/`",
+                            tool.name, pointer
+                        ),
+                    ));
+                }
+            }
+            // parse_script fields are only known at run time
+            if let (Some(parse_template), None) = (&tool.parse_template, &tool.parse_script) {
+                let groups: Vec<String> = tool
+                    .parse_regex
+                    .as_deref()
+                    .and_then(|pattern| regex::Regex::new(pattern).ok())
+                    .map(|re| re.capture_names().flatten().map(String::from).collect())
+                    .unwrap_or_default();
+                let unknown = template::placeholders(parse_template)
+                    .into_iter()
+                    .find(|name| *name != "version" && !groups.iter().any(|g| g == name));
+                if let Some(name) = unknown {
+                    let path = [
+                        Segment::Key(table.to_string()),
+                        Segment::Index(index),
+                        Segment::Key("parse_template".to_string()),
+                    ];
+                    issues.push(issue(
+                        &path,
+                        format!(
+                            "parse_template for {} uses `{{{}}}`, which is not a named group of parse_regex",
+                            tool.name, name
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    let known_theme =
+        |name: &str| ResolvedTheme::is_preset(name) || config.themes.contains_key(name);
+    if !known_theme(&config.theme.preset) {
+        issues.push(issue(
+            &[
+                Segment::Key("theme".to_string()),
+                Segment::Key("preset".to_string()),
+            ],
+            format!("unknown theme preset `{}`", config.theme.preset),
+        ));
+    }
+    for (name, theme) in &config.themes {
+        if !ResolvedTheme::is_preset(&theme.preset) {
+            issues.push(issue(
+                &[
+                    Segment::Key("themes".to_string()),
+                    Segment::Key(name.clone()),
+                    Segment::Key("preset".to_string()),
+                ],
+                format!("unknown base preset `{}` for theme {}", theme.preset, name),
+            ));
+        }
+    }
+
+    for (index, entry) in config.dir_overrides.iter().enumerate() {
+        if let Err(ToolboxError::Config(message)) = entry.matches(Path::new("/")) {
+            let path = [
+                Segment::Key("dir_overrides".to_string()),
+                Segment::Index(index),
+                Segment::Key("path".to_string()),
+            ];
+            issues.push(issue(&path, message));
+        }
+    }
+    for (path, _, theme) in &settings {
+        if let Some(theme) = theme.filter(|theme| !known_theme(&theme.preset)) {
+            let mut path = path.clone();
+            path.push(Segment::Key("theme".to_string()));
+            issues.push(issue(
+                &path,
+                format!("unknown theme preset `{}`", theme.preset),
+            ));
+        }
+    }
+
+    for predicate in config.target.keys() {
+        if let Err(e) = config_merge::cfg_matches(predicate) {
+            let path = [
+                Segment::Key("target".to_string()),
+                Segment::Key(predicate.clone()),
+            ];
+            let message = match e {
+                ToolboxError::Config(message) => message,
+                other => other.to_string(),
+            };
+            issues.push(issue(&path, message));
+        }
+    }
+
+    issues.sort_by_key(|i| i.location);
+    issues
+}
+
+/// Flatten a serde_ignored path into document segments
+fn segments(path: &serde_ignored::Path) -> Vec<Segment> {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => Vec::new(),
+        Path::Seq { parent, index } => {
+            let mut segments = segments(parent);
+            segments.push(Segment::Index(*index));
+            segments
+        }
+        Path::Map { parent, key } => {
+            let mut segments = segments(parent);
+            segments.push(Segment::Key(key.clone()));
+            segments
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => segments(parent),
+    }
+}
+
+/// Keys accepted by the table at `parent` (empty for free-form tables)
+fn known_fields(parent: &[Segment]) -> &'static [&'static str] {
+    let keys: Vec<&str> = parent
+        .iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => key.as_str(),
+            Segment::Index(_) => "#",
+        })
+        .collect();
+
+    match keys.as_slice() {
+        // Target sections hold a nested config
+        ["target", _, ..] => known_fields(&parent[2..]),
+        [] => struct_fields::<Config>(),
+        ["display"] => struct_fields::<DisplayConfig>(),
+        ["tools", "#"] | ["custom_tools", "#"] => struct_fields::<ToolConfig>(),
+        ["tool_overrides", "#"] => struct_fields::<ToolOverride>(),
+        ["extras"] => struct_fields::<ExtrasConfig>(),
+        ["cache"] => struct_fields::<CacheConfig>(),
+        ["history"] => struct_fields::<HistoryConfig>(),
+        ["storage"] => struct_fields::<StorageConfig>(),
+        ["prompt"] => struct_fields::<PromptConfig>(),
+        ["detectors"] => struct_fields::<DetectorsConfig>(),
+        ["environment"] => struct_fields::<EnvironmentConfig>(),
+        ["sandbox"] => struct_fields::<SandboxConfig>(),
+        ["update"] => struct_fields::<UpdateConfig>(),
+        ["plugins", _] => struct_fields::<PluginConfig>(),
+        ["theme"] | ["themes", _] => struct_fields::<ThemeConfig>(),
+        ["dir_overrides", "#"] => struct_fields::<DirOverride>(),
+        ["profiles", _] => struct_fields::<Profile>(),
+        ["dir_overrides", "#", "extras"] | ["profiles", _, "extras"] => {
+            struct_fields::<ExtrasConfig>()
+        }
+        ["dir_overrides", "#", "theme"] | ["profiles", _, "theme"] => {
+            struct_fields::<ThemeConfig>()
+        }
+        ["dir_overrides", "#", "theme", "custom"] | ["profiles", _, "theme", "custom"] => {
+            struct_fields::<CustomThemeConfig>()
+        }
+        ["theme", "custom"] | ["themes", _, "custom"] => struct_fields::<CustomThemeConfig>(),
+        _ => &[],
+    }
+}
+
+/// Closest known key to a misspelled one, if any is similar enough
+fn suggest(key: &str, candidates: &[&'static str]) -> Option<&'static str> {
+    candidates
+        .iter()
+        .map(|candidate| (strsim::jaro_winkler(key, candidate), *candidate))
+        .filter(|(score, _)| *score > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+/// Field names a struct accepts, read from its derived `Deserialize` impl
+fn struct_fields<'de, T: Deserialize<'de>>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldCollector(&mut fields));
+    fields
+}
+
+/// Deserializer that only records the field list passed to `deserialize_struct`
+struct FieldCollector<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> de::Deserializer<'de> for FieldCollector<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("fields collected"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+fn display_path(path: &[Segment]) -> String {
+    path.iter()
+        .map(|segment| match segment {
+            Segment::Key(key) => key.clone(),
+            Segment::Index(index) => index.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Find the source span of the last key (or array element) on a path
+fn locate(doc: &ImDocument<&str>, path: &[Segment]) -> Option<Range<usize>> {
+    let mut item: Option<&Item> = None;
+    let mut table: &dyn TableLike = doc.as_table();
+    let mut span = None;
+
+    for segment in path {
+        match segment {
+            Segment::Key(key) => {
+                if let Some(current) = item {
+                    table = current.as_table_like()?;
+                }
+                let (key, value) = table.get_key_value(key)?;
+                span = key.span();
+                item = Some(value);
+            }
+            Segment::Index(index) => match item? {
+                Item::ArrayOfTables(array) => {
+                    let element = array.get(*index)?;
+                    span = element.span().or(span);
+                    table = element;
+                    item = None;
+                }
+                Item::Value(value) => {
+                    let element = value.as_array()?.get(*index)?;
+                    span = element.span();
+                    return span;
+                }
+                _ => return None,
+            },
+        }
+    }
+    span
+}
+
+/// Convert a byte offset to a 1-based (line, column)
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|l| l.chars().count())
+        .unwrap_or(0)
+        + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_config() {
+        let content = "[display]\ncompact = true\n\n[[custom_tools]]\nname = \"X\"\ncommand = \"x --version\"\nparse_regex = 'v(\\d+)'\n";
+        assert!(validate_config(content).is_empty());
+        assert!(validate_config("").is_empty());
+    }
+
+    #[test]
+    fn test_syntax_error_location() {
+        let issues = validate_config("[display]\ncompact = \n");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].location.map(|l| l.0), Some(2));
+    }
+
+    #[test]
+    fn test_type_error_location() {
+        let issues = validate_config("[display]\nshow_icons = true\ncompact = \"yes\"\n");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].location, Some((3, 11)));
+        assert!(issues[0].message.contains("bool"));
+    }
+
+    #[test]
+    fn test_invalid_color() {
+        let issues = validate_config("[theme.custom]\ndirectory_bg = \"#12\"\n");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].location.map(|l| l.0), Some(2));
+        assert!(issues[0].message.contains("Invalid color: #12"));
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let content = "[display]\ncompcat = true\n\n[[custom_tools]]\nname = \"X\"\ncommand = \"x\"\nparse_regx = \"v\"\n";
+        let issues = validate_config(content);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].to_string(),
+            "2:1: unknown key `display.compcat` (did you mean `compact`?)"
+        );
+        assert_eq!(
+            issues[1].to_string(),
+            "7:1: unknown key `custom_tools.0.parse_regx` (did you mean `parse_regex`?)"
+        );
+    }
+
+    #[test]
+    fn test_unknown_key_without_suggestion() {
+        let issues = validate_config("[cache]\nzzz = 1\n\n[history]\npth = \"/tmp/h\"\n");
+        assert_eq!(issues[0].to_string(), "2:1: unknown key `cache.zzz`");
+        assert_eq!(
+            issues[1].to_string(),
+            "5:1: unknown key `history.pth` (did you mean `path`?)"
+        );
+
+        // Free-form tables accept any key
+        assert!(validate_config("[requirements]\nanything = \">=1\"\n").is_empty());
+    }
+
+    #[test]
+    fn test_known_fields_cover_config_tables() {
+        assert!(struct_fields::<Config>().contains(&"display"));
+        assert!(struct_fields::<ToolConfig>().contains(&"parse_regex"));
+
+        // Every table of a fully populated config maps to its field list
+        let mut config = Config::default();
+        config.custom_tools.push(ToolConfig {
+            name: "X".to_string(),
+            command: "x".to_string(),
+            parse_regex: None,
+            icon: None,
+            enabled: true,
+            short_name: None,
+            ..Default::default()
+        });
+        config.theme.custom = Some(CustomThemeConfig::default());
+        config
+            .themes
+            .insert("work".to_string(), config.theme.clone());
+        config.profiles.insert(
+            "infra".to_string(),
+            Profile {
+                enable_tools: vec!["kubectl".to_string()],
+                disable_tools: vec!["Node".to_string()],
+                theme: Some(config.theme.clone()),
+                extras: [("git_status".to_string(), false)].into(),
+            },
+        );
+        config.dir_overrides.push(DirOverride {
+            path: "~/work/**".to_string(),
+            enable_tools: vec!["kubectl".to_string()],
+            disable_tools: vec!["Node".to_string()],
+            theme: Some(config.theme.clone()),
+            extras: [("git_status".to_string(), false)].into(),
+        });
+
+        fn walk(value: &toml::Value, path: &mut Vec<Segment>) {
+            match value {
+                toml::Value::Table(table) => {
+                    let fields = known_fields(path);
+                    for (key, child) in table {
+                        assert!(fields.contains(&key.as_str()), "{} at {:?}", key, path);
+                        path.push(Segment::Key(key.clone()));
+                        walk(child, path);
+                        path.pop();
+                    }
+                }
+                toml::Value::Array(items) => {
+                    for (index, item) in items.iter().enumerate() {
+                        path.push(Segment::Index(index));
+                        walk(item, path);
+                        path.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // The themes and profiles tables themselves are keyed by user-chosen names
+        let mut value = toml::Value::try_from(&config).unwrap();
+        let named: Vec<(&str, toml::Value)> = ["themes", "profiles"]
+            .into_iter()
+            .map(|key| (key, value.as_table_mut().unwrap().remove(key).unwrap()))
+            .collect();
+        walk(&value, &mut Vec::new());
+        for (key, table) in &named {
+            for (name, item) in table.as_table().unwrap() {
+                let mut path = vec![Segment::Key(key.to_string()), Segment::Key(name.clone())];
+                walk(item, &mut path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_target_sections() {
+        let content = "include = [\"base.toml\"]\n\n[target.'cfg(macos)'.display]\ncompcat = true\n\n[target.linux.display]\ncompact = true\n";
+        let issues = validate_config(content);
+        assert_eq!(issues.len(), 2, "{:?}", issues);
+        assert_eq!(
+            issues[0].to_string(),
+            "4:1: unknown key `target.cfg(macos).display.compcat` (did you mean `compact`?)"
+        );
+        assert!(issues[1]
+            .to_string()
+            .starts_with("6:9: Invalid target `linux`"));
+    }
+
+    #[test]
+    fn test_dir_overrides() {
+        let content = "[[dir_overrides]]\npath = \"~/work/[\"\ntheme = { preset = \"nrod\" }\n\n[dir_overrides.extras]\ngit_stauts = false\n";
+        let issues = validate_config(content);
+        assert_eq!(issues.len(), 3, "{:?}", issues);
+        assert!(issues[0]
+            .to_string()
+            .starts_with("2:1: Invalid dir_overrides path ~/work/["));
+        assert_eq!(issues[1].to_string(), "3:1: unknown theme preset `nrod`");
+        assert_eq!(
+            issues[2].to_string(),
+            "6:1: unknown key `dir_overrides.0.extras.git_stauts` (did you mean `git_status`?)"
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        let content = "[profiles.infra]\nenable_tools = [\"kubectl\"]\ntheme = { preset = \"nrod\" }\nextras = { git_stauts = false }\n";
+        let issues = validate_config(content);
+        assert_eq!(issues.len(), 2, "{:?}", issues);
+        assert_eq!(issues[0].to_string(), "3:1: unknown theme preset `nrod`");
+        assert_eq!(
+            issues[1].to_string(),
+            "4:12: unknown key `profiles.infra.extras.git_stauts` (did you mean `git_status`?)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        let content =
+            "[[custom_tools]]\nname = \"X\"\ncommand = \"x\"\nparse_regex = \"v(\\\\d+\"\n";
+        let issues = validate_config(content);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].location, Some((4, 1)));
+        assert!(issues[0].message.starts_with("invalid parse_regex for X:"));
+    }
+
+    #[test]
+    fn test_parse_template_unknown_group() {
+        let content = "[[custom_tools]]\nname = \"X\"\ncommand = \"x\"\nparse_regex = 'v(?P<version>\\d+)-(?P<build>\\d+)'\nparse_template = \"{version}+{buidl}\"\n";
+        let issues = validate_config(content);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            issues[0].to_string(),
+            "5:1: parse_template for X uses `{buidl}`, which is not a named group of parse_regex"
+        );
+
+        let valid = content.replace("buidl", "build");
+        assert!(validate_config(&valid).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_json_pointer() {
+        let content = "[[custom_tools]]\nname = \"X\"\ncommand = \"x\"\nparse_json_pointer = \"clientVersion.gitVersion\"\n";
+        let issues = validate_config(content);
+        assert_eq!(
+            issues[0].to_string(),
+            "4:1: invalid parse_json_pointer for X: `clientVersion.gitVersion` must start with `/`"
+        );
+    }
+
+    #[test]
+    fn test_unknown_theme_preset() {
+        let issues = validate_config("[theme]\npreset = \"nrod\"\n");
+        assert_eq!(issues[0].to_string(), "2:1: unknown theme preset `nrod`");
+
+        // User-defined themes are valid presets
+        let content = "[theme]\npreset = \"work\"\n\n[themes.work]\npreset = \"nord\"\n";
+        assert!(validate_config(content).is_empty());
+
+        let issues = validate_config("[themes.work]\npreset = \"nope\"\n");
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("theme work"));
+    }
+
+    #[test]
+    fn test_line_column() {
+        let content = "ab\ncdé\nf";
+        assert_eq!(line_column(content, 0), (1, 1));
+        assert_eq!(line_column(content, 3), (2, 1));
+        assert_eq!(line_column(content, 8), (3, 1));
+        assert_eq!(line_column(content, 7), (2, 4));
+    }
+}
@@ -0,0 +1,322 @@
+//! Config hot-reload for long-running commands
+//!
+//! `toolbox watch` and `toolbox serve` keep running while the config is
+//! edited. A [`ConfigWatcher`] polls the modification time and size of the
+//! config file and of every file it includes, so a change is picked up on the
+//! next check without a file system notification dependency. With
+//! [`ConfigWatcher::with_project_dir`] the project's `.toolbox.toml` (and the
+//! trust store deciding how much of it applies) is watched as well, and
+//! [`ConfigWatcher::with_loader`] lets the caller build the reloaded config
+//! the same way as at startup. What a reload changed is described key by key
+//! with [`config_changes`].
+
+use crate::config::{read_config_text, Config};
+use crate::config_merge;
+use crate::error::Result;
+use crate::trust::{TrustStore, PROJECT_CONFIG_FILE};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+/// What identifies a version of a file: modification time and size
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The config file and the files it includes, recursively
+///
+/// Included files that can't be read are listed too, so creating them is
+/// noticed.
+pub fn config_files(path: &Path) -> Vec<PathBuf> {
+    let read = RefCell::new(vec![path.to_path_buf()]);
+    if let Ok(content) = read_config_text(path) {
+        if config_merge::is_layered(&content) {
+            let record = |file: &Path| {
+                read.borrow_mut().push(file.to_path_buf());
+                read_config_text(file)
+            };
+            let _ = config_merge::resolve(path, &content, &record);
+        }
+    }
+    let mut files = read.into_inner();
+    let mut seen = BTreeSet::new();
+    files.retain(|file| seen.insert(file.clone()));
+    files
+}
+
+/// Notices changes to the config file and its includes
+#[derive(Clone)]
+pub struct ConfigWatcher {
+    /// Config given with `--config` (None: the default location)
+    path: Option<PathBuf>,
+    /// Directory whose project config is watched (None: no project config)
+    project_dir: Option<PathBuf>,
+    /// Builds the config in `load` (None: just the config file)
+    loader: Option<Rc<dyn Fn() -> Result<Config>>>,
+    /// Watched files and their stamps when last checked
+    files: Vec<(PathBuf, Stamp)>,
+}
+
+impl std::fmt::Debug for ConfigWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigWatcher")
+            .field("path", &self.path)
+            .field("project_dir", &self.project_dir)
+            .field("files", &self.files)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConfigWatcher {
+    /// Watch a config file, or the default one if `path` is None (which may
+    /// not exist yet)
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let mut watcher = Self {
+            path,
+            project_dir: None,
+            loader: None,
+            files: Vec::new(),
+        };
+        watcher.rescan();
+        watcher
+    }
+
+    /// Also watch the project config of `dir`: a `.toolbox.toml` in it or
+    /// any parent (created, edited or removed) and the trust store
+    pub fn with_project_dir(mut self, dir: PathBuf) -> Self {
+        self.project_dir = Some(dir);
+        self.rescan();
+        self
+    }
+
+    /// Build the config with `loader` in [`load`](Self::load), e.g. to apply
+    /// the project config, `[[dir_overrides]]` and the profile like at startup
+    pub fn with_loader(mut self, loader: impl Fn() -> Result<Config> + 'static) -> Self {
+        self.loader = Some(Rc::new(loader));
+        self
+    }
+
+    /// The watched files
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(file, _)| file.as_path())
+    }
+
+    /// Whether a watched file was changed, created or removed since the last
+    /// check; the list of included files is refreshed when so
+    pub fn changed(&mut self) -> bool {
+        let changed = self.files.iter().any(|(file, last)| stamp(file) != *last);
+        if changed {
+            self.rescan();
+        }
+        changed
+    }
+
+    /// Load the watched config (with the loader, if one was given)
+    pub fn load(&self) -> Result<Config> {
+        if let Some(ref loader) = self.loader {
+            return loader();
+        }
+        match self.path {
+            Some(ref path) => Config::load_from_path(path),
+            None => Config::load(),
+        }
+    }
+
+    fn rescan(&mut self) {
+        let main = self
+            .path
+            .clone()
+            .or_else(|| Config::find_config_path().or_else(Config::config_path));
+        let mut files = main.map(|main| config_files(&main)).unwrap_or_default();
+        if let Some(ref dir) = self.project_dir {
+            // Every candidate location, so a new `.toolbox.toml` is noticed
+            files.extend(dir.ancestors().map(|dir| dir.join(PROJECT_CONFIG_FILE)));
+            files.extend(TrustStore::default_path());
+        }
+        self.files = files
+            .into_iter()
+            .map(|file| {
+                let stamp = stamp(&file);
+                (file, stamp)
+            })
+            .collect();
+    }
+}
+
+/// The settings a reload changed, as `dotted.key: old -> new` (or `key
+/// changed` for lists such as `custom_tools`)
+pub fn config_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    if let (Ok(old), Ok(new)) = (serde_json::to_value(old), serde_json::to_value(new)) {
+        diff_values("", &old, &new, &mut changes);
+    }
+    changes
+}
+
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<String>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let keys: BTreeSet<&String> = old_map.keys().chain(new_map.keys()).collect();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let (old, new) = (
+                    old_map.get(key).unwrap_or(&Value::Null),
+                    new_map.get(key).unwrap_or(&Value::Null),
+                );
+                diff_values(&path, old, new, changes);
+            }
+        }
+        (Value::Array(_), _)
+        | (_, Value::Array(_))
+        | (Value::Object(_), _)
+        | (_, Value::Object(_)) => changes.push(format!("{} changed", path)),
+        _ => changes.push(format!("{}: {} -> {}", path, scalar(old), scalar(new))),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "unset".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Log line for a reload that changed `changes`
+pub fn reload_message(changes: &[String]) -> String {
+    if changes.is_empty() {
+        "Config reloaded (no changes)".to_string()
+    } else {
+        format!("Config reloaded: {}", changes.join(", "))
+    }
+}
+
+/// Log line for a reload that failed
+pub fn reload_failed_message(error: &dyn std::fmt::Display) -> String {
+    format!(
+        "Config reload failed, keeping the previous config: {}",
+        error
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_files_follow_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("config.toml");
+        std::fs::write(&main, "include = [\"base.toml\", \"missing.toml\"]\n").unwrap();
+        std::fs::write(dir.path().join("base.toml"), "include = [\"os.toml\"]\n").unwrap();
+        std::fs::write(dir.path().join("os.toml"), "").unwrap();
+
+        let files = config_files(&main);
+        assert_eq!(files[0], main);
+        assert!(files.contains(&dir.path().join("base.toml")));
+        assert!(files.contains(&dir.path().join("os.toml")));
+        // Listed though it can't be read (yet)
+        assert!(files.contains(&dir.path().join("missing.toml")));
+
+        std::fs::write(&main, "[display]\ncompact = true\n").unwrap();
+        assert_eq!(config_files(&main), [main]);
+    }
+
+    #[test]
+    fn test_watcher_notices_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("config.toml");
+        std::fs::write(&main, "[display]\ncompact = true\n").unwrap();
+
+        let mut watcher = ConfigWatcher::new(Some(main.clone()));
+        assert!(!watcher.changed());
+
+        std::fs::write(
+            &main,
+            "include = [\"extra.toml\"]\n[display]\ncompact = false\n",
+        )
+        .unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+        assert!(watcher.load().is_err());
+
+        // Newly included files are watched from then on
+        assert!(watcher.files().any(|file| file.ends_with("extra.toml")));
+        std::fs::write(dir.path().join("extra.toml"), "[cache]\nenabled = false\n").unwrap();
+        assert!(watcher.changed());
+        let config = watcher.load().unwrap();
+        assert!(!config.display.compact);
+        assert!(!config.cache.enabled);
+
+        std::fs::remove_file(&main).unwrap();
+        assert!(watcher.changed());
+        assert!(watcher.load().is_err());
+    }
+
+    #[test]
+    fn test_watcher_project_dir_and_loader() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("config.toml");
+        std::fs::write(&main, "").unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+
+        let mut watcher = ConfigWatcher::new(Some(main))
+            .with_project_dir(project.clone())
+            .with_loader(|| {
+                let mut config = Config::default();
+                config.display.compact = false;
+                Ok(config)
+            });
+        assert!(!watcher.changed());
+        assert!(watcher
+            .files()
+            .any(|file| file == dir.path().join(".toolbox.toml")));
+
+        std::fs::write(project.join(".toolbox.toml"), "[display]\n").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.load().unwrap().display.compact);
+    }
+
+    #[test]
+    fn test_config_changes() {
+        let old = Config::default();
+        let new: Config = toml::from_str(
+            r#"
+[display]
+compact = false
+
+[theme]
+preset = "nord"
+
+[[custom_tools]]
+name = "App"
+command = "app --version"
+"#,
+        )
+        .unwrap();
+
+        assert!(config_changes(&old, &old).is_empty());
+        let changes = config_changes(&old, &new);
+        assert!(changes.contains(&"display.compact: true -> false".to_string()));
+        assert!(changes.contains(&"theme.preset: \"default\" -> \"nord\"".to_string()));
+        assert!(changes.contains(&"custom_tools changed".to_string()));
+        assert_eq!(
+            reload_message(&changes[..1]),
+            format!("Config reloaded: {}", changes[0])
+        );
+        assert_eq!(reload_message(&[]), "Config reloaded (no changes)");
+    }
+}
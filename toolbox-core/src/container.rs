@@ -0,0 +1,129 @@
+//! Version detection inside container images
+//!
+//! A tool with `container_image` runs its version command through
+//! `docker run --rm <image>`, so teams whose toolchain lives in a dev
+//! container still see the effective versions from the host. Starting a
+//! container is slow, so outputs are cached on disk per image ID: they stay
+//! valid until the image itself changes.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Container runtime CLI
+pub const DOCKER: &str = "docker";
+
+/// `docker run` arguments for a version command in an image
+pub fn run_args<'a>(image: &'a str, command: &[&'a str]) -> Vec<&'a str> {
+    let mut args = vec!["run", "--rm", image];
+    args.extend_from_slice(command);
+    args
+}
+
+/// ID of a local image; `None` if docker is unavailable or the image isn't
+/// pulled yet (`docker run` pulls it, uncached)
+pub fn image_id(image: &str) -> Option<String> {
+    let output = Command::new(DOCKER)
+        .args(["image", "inspect", "--format", "{{.Id}}", image])
+        .output()
+        .ok()?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !id.is_empty()).then_some(id)
+}
+
+/// Cache key of a version command in an image
+pub fn cache_key(image_id: &str, command: &str) -> String {
+    format!("{}\n{}", image_id, command)
+}
+
+/// Output of a version command run in a container
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// A cached output with its key
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedEntry {
+    key: String,
+    output: CachedOutput,
+}
+
+/// Version command outputs per image ID, kept on disk
+#[derive(Debug, Clone)]
+pub struct ContainerCache {
+    dir: PathBuf,
+}
+
+impl ContainerCache {
+    /// Cache in a directory
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Get the default cache directory
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|p| p.join("toolbox").join("containers"))
+    }
+
+    /// File holding the output for a key
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.json", crate::prompt::fnv1a(key)))
+    }
+
+    /// Cached output for a key
+    pub fn load(&self, key: &str) -> Option<CachedOutput> {
+        let json = std::fs::read_to_string(self.path(key)).ok()?;
+        let entry: CachedEntry = serde_json::from_str(&json).ok()?;
+        // Guard against hash collisions
+        (entry.key == key).then_some(entry.output)
+    }
+
+    /// Store the output for a key
+    pub fn store(&self, key: &str, output: &CachedOutput) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension("json.tmp");
+        let entry = CachedEntry {
+            key: key.to_string(),
+            output: output.clone(),
+        };
+        std::fs::write(&tmp, serde_json::to_string(&entry)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_args() {
+        assert_eq!(
+            run_args("node:20-bookworm", &["node", "--version"]),
+            vec!["run", "--rm", "node:20-bookworm", "node", "--version"]
+        );
+    }
+
+    #[test]
+    fn test_cache_store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ContainerCache::new(dir.path().join("containers"));
+        let key = cache_key("sha256:abc", "node --version");
+        assert_eq!(cache.load(&key), None);
+
+        let output = CachedOutput {
+            stdout: "v20.11.0\n".to_string(),
+            stderr: String::new(),
+        };
+        cache.store(&key, &output).unwrap();
+        assert_eq!(cache.load(&key), Some(output));
+
+        // A new image ID misses
+        assert_eq!(cache.load(&cache_key("sha256:def", "node --version")), None);
+    }
+}
@@ -0,0 +1,102 @@
+//! Corepack `packageManager` pins
+//!
+//! A project's `package.json` can pin its package manager
+//! (`"packageManager": "pnpm@8.15.1+sha512.…"`), which Corepack enforces.
+//! Doctor compares the pin with the detected npm/pnpm/yarn version, and
+//! `toolbox require` checks it alongside the `[requirements]` table.
+
+use crate::info::PinnedVersion;
+use std::path::Path;
+
+/// Package managers Corepack manages
+pub const PACKAGE_MANAGERS: &[&str] = &["npm", "pnpm", "yarn"];
+
+/// A `packageManager` pin found in a `package.json`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageManagerPin {
+    /// Package manager command (npm, pnpm, yarn)
+    pub name: String,
+    pub pinned: PinnedVersion,
+}
+
+/// Split a `packageManager` value (`pnpm@8.15.1+sha512.abc`) into name and
+/// version, dropping the hash
+pub fn parse_spec(spec: &str) -> Option<(&str, &str)> {
+    let (name, rest) = spec.trim().split_once('@')?;
+    let version = rest.split('+').next().unwrap_or(rest);
+    (PACKAGE_MANAGERS.contains(&name) && !version.is_empty()).then_some((name, version))
+}
+
+/// The nearest `packageManager` pin at or above `start`; like Corepack,
+/// `package.json` files without the field are skipped
+pub fn find_pin(start: &Path) -> Option<PackageManagerPin> {
+    start.ancestors().find_map(|dir| {
+        let file = dir.join("package.json");
+        let json = std::fs::read_to_string(&file).ok()?;
+        let value: serde_json::Value = serde_json::from_str(&json).ok()?;
+        let (name, version) = parse_spec(value.get("packageManager")?.as_str()?)?;
+        Some(PackageManagerPin {
+            name: name.to_string(),
+            pinned: PinnedVersion {
+                version: version.to_string(),
+                source: file.to_string_lossy().into_owned(),
+            },
+        })
+    })
+}
+
+/// Whether a detected version differs from the pin (a leading `v` is ignored)
+pub fn is_mismatch(pinned: &str, detected: &str) -> bool {
+    pinned.trim_start_matches('v') != detected.trim().trim_start_matches('v')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(parse_spec("pnpm@8.15.1"), Some(("pnpm", "8.15.1")));
+        assert_eq!(
+            parse_spec(
+                "yarn@4.1.0+sha224.953c8233f7a92884eee2de69a1b92d1f2ec1655e66d08071ba9a02fa"
+            ),
+            Some(("yarn", "4.1.0"))
+        );
+        assert_eq!(parse_spec("bun@1.0.0"), None);
+        assert_eq!(parse_spec("npm"), None);
+        assert_eq!(parse_spec("npm@"), None);
+    }
+
+    #[test]
+    fn test_find_pin() {
+        let root = tempfile::tempdir().unwrap();
+        let app = root.path().join("packages/app");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(
+            root.path().join("package.json"),
+            r#"{"name": "monorepo", "packageManager": "pnpm@8.15.1+sha512.abc"}"#,
+        )
+        .unwrap();
+        // A nested package.json without the field doesn't stop the search
+        std::fs::write(app.join("package.json"), r#"{"name": "app"}"#).unwrap();
+
+        let pin = find_pin(&app).unwrap();
+        assert_eq!(pin.name, "pnpm");
+        assert_eq!(pin.pinned.version, "8.15.1");
+        assert_eq!(
+            pin.pinned.source,
+            root.path().join("package.json").to_string_lossy()
+        );
+
+        let empty = tempfile::tempdir().unwrap();
+        assert_eq!(find_pin(empty.path()), None);
+    }
+
+    #[test]
+    fn test_is_mismatch() {
+        assert!(!is_mismatch("8.15.1", "8.15.1\n"));
+        assert!(!is_mismatch("10.2.4", "v10.2.4"));
+        assert!(is_mismatch("8.15.1", "9.0.0"));
+    }
+}
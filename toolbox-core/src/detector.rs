@@ -1,14 +1,73 @@
 //! Tool version detection
 
 use crate::cache::VersionCache;
-use crate::config::{Config, ToolConfig};
-use crate::error::{Result, ToolboxError};
+use crate::cancel::{self, CancellationToken};
+use crate::config::{Config, ToolConfig, VersionStream};
+use crate::container::{self, CachedOutput, ContainerCache};
+use crate::corepack;
+use crate::env_report;
+use crate::error::{Result, ToolError, ToolboxError};
+use crate::external;
+use crate::gomod;
 use crate::info::{
-    DiagnosticStatus, DiagnosticSummary, GitInfo, SystemInfo, ToolDiagnostic, ToolInfo, ToolboxInfo,
+    CacheState, CachedVersion, DiagnosticStatus, DiagnosticSummary, GitInfo, InstalledVersion,
+    SystemInfo, ToolDiagnostic, ToolExplanation, ToolInfo, ToolboxInfo,
 };
+use crate::installs;
+use crate::messages::{fill, Message};
+#[cfg(feature = "plugins")]
+use crate::plugin::WasmPlugin;
+use crate::project_env::ProjectEnv;
+use crate::rustup;
+use crate::sandbox::Sandbox;
+#[cfg(feature = "scripting")]
+use crate::script;
+use crate::shim;
+use crate::source::{self, ToolSource};
+use crate::template;
+use crate::venv;
 use regex::Regex;
-use std::path::Path;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+#[cfg(feature = "tokio")]
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(feature = "tokio")]
+use tokio::{sync::Semaphore, task::JoinSet};
+use tracing::{debug, trace};
+
+/// Default limit of concurrent version commands in `detect_all_async`
+#[cfg(feature = "tokio")]
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Default time a version command may take in `detect_all_async`, and an
+/// external detector in any detection
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A programmatic tool source registered with [`ToolDetector::register`]
+///
+/// Its tools are merged into [`ToolDetector::detect_all`] after the configured
+/// ones, replacing tools of the same name; an error becomes an unavailable
+/// tool named after the detector.
+pub trait Detector: Send {
+    /// Name used in logs and for the unavailable tool on error
+    fn name(&self) -> &str;
+
+    /// Detect tools in a working directory (`None`: the current directory)
+    fn detect(&self, working_dir: Option<&str>) -> Result<Vec<ToolInfo>>;
+}
+
+/// Progress of [`ToolDetector::detect_streaming`]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DetectEvent<'a> {
+    /// A tool was detected
+    Tool(&'a ToolInfo),
+    /// Detection finished: the complete result
+    Done(&'a ToolboxInfo),
+}
 
 /// Main detector for tool versions and system info
 pub struct ToolDetector {
@@ -17,13 +76,29 @@ pub struct ToolDetector {
     working_dir: Option<String>,
     /// Version cache for avoiding redundant detections
     cache: Option<VersionCache>,
+    /// WASM plugins, compiled on first use (load errors are kept per plugin)
+    #[cfg(feature = "plugins")]
+    plugins: Option<Vec<(String, std::result::Result<WasmPlugin, String>)>>,
+    /// Detectors registered by the embedding program
+    registered: Vec<Box<dyn Detector>>,
+    /// Token that aborts a running detection
+    cancellation: Option<CancellationToken>,
+    /// Limit of concurrent version commands in `detect_all_async`
+    #[cfg(feature = "tokio")]
+    concurrency: usize,
+    /// Time a version command may take in `detect_all_async`, and an external
+    /// detector in any detection
+    timeout: Duration,
 }
 
 impl ToolDetector {
     /// Create a new detector with the given configuration
     pub fn new(config: Config) -> Self {
         let cache = if config.cache.enabled {
-            Some(VersionCache::new(config.cache.default_ttl))
+            Some(
+                VersionCache::new(config.cache.default_ttl)
+                    .with_max_entries(config.cache.max_entries),
+            )
         } else {
             None
         };
@@ -31,6 +106,13 @@ impl ToolDetector {
             config,
             working_dir: None,
             cache,
+            #[cfg(feature = "plugins")]
+            plugins: None,
+            registered: Vec::new(),
+            cancellation: None,
+            #[cfg(feature = "tokio")]
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: DEFAULT_TIMEOUT,
         }
     }
 
@@ -60,13 +142,260 @@ impl ToolDetector {
         self
     }
 
+    /// Run at most `limit` version commands at once in `detect_all_async`
+    #[cfg(feature = "tokio")]
+    pub fn with_concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit;
+        self
+    }
+
+    /// Kill version commands that take longer than `timeout` in
+    /// `detect_all_async`, and external detectors in any detection
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Abort detections when `token` is cancelled
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Add a detector whose tools are merged into `detect_all`
+    pub fn register(&mut self, detector: Box<dyn Detector>) {
+        self.registered.push(detector);
+    }
+
     /// Get a reference to the cache (if enabled)
     pub fn cache(&self) -> Option<&VersionCache> {
         self.cache.as_ref()
     }
 
+    /// Get a mutable reference to the cache (if enabled)
+    pub fn cache_mut(&mut self) -> Option<&mut VersionCache> {
+        self.cache.as_mut()
+    }
+
+    /// Change the working directory of a long-lived detector
+    pub fn set_working_dir(&mut self, dir: Option<String>) {
+        self.working_dir = dir;
+    }
+
+    /// Replace the config of a long-lived detector, e.g. after the config
+    /// file was edited; cached versions are dropped since commands may have
+    /// changed
+    pub fn set_config(&mut self, config: Config) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
+            cache.set_max_entries(config.cache.max_entries);
+        }
+        self.config = config;
+        #[cfg(feature = "plugins")]
+        {
+            self.plugins = None;
+        }
+    }
+
+    /// Switch a long-lived detector to the config of another directory (the
+    /// same files with that directory's project config and overrides);
+    /// cached versions are kept, as they are per directory
+    pub fn set_dir_config(&mut self, config: Config) {
+        self.config = config;
+        #[cfg(feature = "plugins")]
+        {
+            self.plugins = None;
+        }
+    }
+
+    /// Change the cancellation token of a long-lived detector, e.g. a fresh
+    /// one for each request it answers
+    pub fn set_cancellation(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// Whether the current detection was cancelled
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
     /// Detect all enabled tools and gather information
     pub fn detect_all(&mut self) -> ToolboxInfo {
+        self.detect_streaming(|_| {})
+    }
+
+    /// Detect like [`detect_all`](Self::detect_all), passing each tool to
+    /// `on_event` as soon as it is detected
+    ///
+    /// A detector plugin can report a tool that was already reported; it
+    /// replaces the earlier one of the same name. The last event is
+    /// [`DetectEvent::Done`] with the same result as the return value, its
+    /// tools ordered by `display.sort` and `display.pinned`.
+    ///
+    /// Once the [cancellation token](Self::with_cancellation) is cancelled,
+    /// the running version command is killed, the remaining tools are
+    /// reported unavailable and the plugins and extras are skipped.
+    pub fn detect_streaming(&mut self, mut on_event: impl FnMut(DetectEvent<'_>)) -> ToolboxInfo {
+        let start = Instant::now();
+        let mut info = self.detect_location();
+
+        // Tool versions
+        let enabled_tools = self.config.enabled_tools();
+        for tool_config in &enabled_tools {
+            let tool_info = if self.is_cancelled() {
+                cancelled_tool(tool_config)
+            } else {
+                self.detect_tool(tool_config)
+            };
+            on_event(DetectEvent::Tool(&tool_info));
+            info.tools.push(tool_info);
+        }
+
+        if self.is_cancelled() {
+            debug!("detection cancelled");
+        } else {
+            self.detect_remaining(&mut info, &mut on_event);
+        }
+        self.config.display.order_tools(&mut info.tools);
+        debug!(
+            tools = info.tools.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "detection finished"
+        );
+        on_event(DetectEvent::Done(&info));
+        info
+    }
+
+    /// Detect like [`detect_all`](Self::detect_all), running the version
+    /// commands concurrently on the tokio runtime
+    ///
+    /// At most [`with_concurrency`](Self::with_concurrency) commands run at
+    /// once; a command still running after [`with_timeout`](Self::with_timeout)
+    /// is killed and its tool reported unavailable. Detector plugins and the
+    /// extras run afterwards on the calling task. Cancelling the
+    /// [cancellation token](Self::with_cancellation) kills the running
+    /// commands, as in [`detect_streaming`](Self::detect_streaming).
+    #[cfg(feature = "tokio")]
+    pub async fn detect_all_async(&mut self) -> ToolboxInfo {
+        let start = Instant::now();
+        let mut info = self.detect_location();
+
+        let enabled_tools = self.config.enabled_tools();
+        let mut slots: Vec<Option<ToolInfo>> = enabled_tools
+            .iter()
+            .map(|tool_config| self.cached_tool(tool_config))
+            .collect();
+        let mut container_caches: Vec<Option<(ContainerCache, String)>> =
+            Vec::with_capacity(enabled_tools.len());
+        for (index, tool_config) in enabled_tools.iter().enumerate() {
+            let container_cache = slots[index]
+                .is_none()
+                .then(|| self.container_cache(tool_config))
+                .flatten();
+            if let Some((ref cache, ref key)) = container_cache {
+                if let Some(output) = CommandOutput::cached(cache, key, tool_config) {
+                    let tool_info = self.tool_from_output(tool_config, Ok(output));
+                    self.cache_tool(tool_config, &tool_info);
+                    slots[index] = Some(tool_info);
+                }
+            }
+            container_caches.push(container_cache);
+        }
+        let project_env = self.project_env();
+        let sandbox = self.sandbox();
+        let semaphore = Arc::new(Semaphore::new(self.concurrency.max(1)));
+        let mut tasks = JoinSet::new();
+        for (index, tool_config) in enabled_tools.iter().enumerate() {
+            if slots[index].is_some() {
+                continue;
+            }
+            let semaphore = semaphore.clone();
+            let tool_config = tool_config.clone();
+            let project_env = project_env.clone();
+            let sandbox = sandbox.clone();
+            let working_dir = self.working_dir.clone();
+            let timeout = self.timeout;
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let start = Instant::now();
+                let output = run_version_command_async(
+                    &tool_config,
+                    project_env.as_ref(),
+                    sandbox.as_ref(),
+                    working_dir.as_deref(),
+                    timeout,
+                )
+                .await;
+                (index, output, start.elapsed())
+            });
+        }
+        loop {
+            // Wake up regularly to notice a cancellation
+            let joined = match tokio::time::timeout(cancel::POLL_INTERVAL, tasks.join_next()).await
+            {
+                Ok(Some(joined)) => joined,
+                Ok(None) => break,
+                Err(_) if self.is_cancelled() => {
+                    // Dropping the tasks kills their commands
+                    tasks.abort_all();
+                    break;
+                }
+                Err(_) => continue,
+            };
+            let Ok((index, output, elapsed)) = joined else {
+                continue;
+            };
+            let tool_config = &enabled_tools[index];
+            if let (Ok(output), Some((cache, key))) = (&output, &container_caches[index]) {
+                output.store(cache, key);
+            }
+            let mut tool_info = self.tool_from_output(tool_config, output);
+            tool_info.duration_ms = Some(elapsed.as_millis() as u64);
+            debug!(
+                tool = %tool_config.name,
+                available = tool_info.available,
+                version = tool_info.version.as_deref().unwrap_or(""),
+                elapsed_ms = tool_info.duration_ms,
+                "detected"
+            );
+            self.cache_tool(tool_config, &tool_info);
+            slots[index] = Some(tool_info);
+        }
+        info.tools = slots
+            .into_iter()
+            .zip(&enabled_tools)
+            .map(|(slot, tool_config)| {
+                slot.unwrap_or_else(|| {
+                    if self.is_cancelled() {
+                        cancelled_tool(tool_config)
+                    } else {
+                        ToolInfo::unavailable(
+                            tool_config.name.clone(),
+                            Some("detection task failed".to_string()),
+                        )
+                    }
+                })
+            })
+            .collect();
+
+        if self.is_cancelled() {
+            debug!("async detection cancelled");
+        } else {
+            self.detect_remaining(&mut info, &mut |_| {});
+        }
+        self.config.display.order_tools(&mut info.tools);
+        debug!(
+            tools = info.tools.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "async detection finished"
+        );
+        info
+    }
+
+    /// Current directory and git info: the part of a result before the tools
+    fn detect_location(&self) -> ToolboxInfo {
         let mut info = ToolboxInfo::new();
 
         // Current directory
@@ -78,17 +407,58 @@ impl ToolDetector {
         if self.config.extras.git_branch || self.config.extras.git_status {
             info.git = self.get_git_info();
         }
+        info
+    }
 
-        // Tool versions
-        let enabled_tools = self.config.enabled_tools();
-        for tool_config in &enabled_tools {
-            let tool_info = self.detect_tool(tool_config);
-            info.tools.push(tool_info);
+    /// Detector plugins and the extras: the part of a result after the tools
+    fn detect_remaining(
+        &mut self,
+        info: &mut ToolboxInfo,
+        on_event: &mut dyn FnMut(DetectEvent<'_>),
+    ) {
+        // Detector plugins
+        if self.config.detectors.external {
+            self.detect_external(&mut info.tools, on_event);
+        }
+        #[cfg(feature = "plugins")]
+        if self.config.detectors.wasm {
+            self.detect_plugins(&mut info.tools, on_event);
         }
+        self.detect_registered(&mut info.tools, on_event);
 
         // Virtual environment
         if self.config.extras.virtual_env {
             info.virtual_env = self.get_virtual_env();
+            let extras = &self.config.extras;
+            let conda_env = std::env::var("CONDA_DEFAULT_ENV")
+                .ok()
+                .filter(|env| !env.is_empty());
+            if let Some(venv_dir) = std::env::var_os("VIRTUAL_ENV").filter(|dir| !dir.is_empty()) {
+                // A Python venv, maybe activated inside a conda environment
+                if extras.virtual_env_python {
+                    info.virtual_env_python = venv::python_version(Path::new(&venv_dir));
+                }
+                if extras.virtual_env_manager {
+                    info.virtual_env_manager = self
+                        .get_current_dir()
+                        .map(|dir| venv::package_manager(Path::new(&dir)).to_string());
+                }
+                info.conda_env = conda_env.clone();
+            } else if let Some(prefix) =
+                std::env::var_os("CONDA_PREFIX").filter(|_| conda_env.is_some())
+            {
+                if extras.virtual_env_python {
+                    info.virtual_env_python = venv::conda_python_version(Path::new(&prefix));
+                }
+                if extras.virtual_env_manager {
+                    info.virtual_env_manager =
+                        Some(venv::conda_manager(&|var| std::env::var(var).ok()).to_string());
+                }
+            }
+            if extras.conda_env_count && conda_env.is_some() {
+                info.conda_env_count =
+                    dirs::home_dir().and_then(|home| venv::conda_env_count(&home));
+            }
         }
 
         // Shell
@@ -98,26 +468,62 @@ impl ToolDetector {
 
         // System info
         #[cfg(feature = "sysinfo")]
-        if self.config.extras.system_memory || self.config.extras.system_cpu {
+        if self.config.extras.system_memory
+            || self.config.extras.system_cpu
+            || self.config.extras.system_swap
+            || self.config.extras.system_cpu_cores
+        {
             info.system = self.get_system_info();
         }
-
-        info
     }
 
     /// Detect a single tool's version, using cache if available
     pub fn detect_tool(&mut self, tool_config: &ToolConfig) -> ToolInfo {
-        // Try cache first
-        if let Some(ref mut cache) = self.cache {
-            if let Some(cached) = cache.get(&tool_config.name, &self.working_dir) {
-                return cached.clone();
-            }
+        if let Some(cached) = self.cached_tool(tool_config) {
+            return cached;
         }
 
         // Cache miss or disabled — run detection
-        let tool_info = self.detect_tool_uncached(tool_config);
+        let start = Instant::now();
+        let mut tool_info = self.detect_tool_uncached(tool_config);
+        tool_info.duration_ms = Some(start.elapsed().as_millis() as u64);
+        debug!(
+            tool = %tool_config.name,
+            available = tool_info.available,
+            version = tool_info.version.as_deref().unwrap_or(""),
+            source = tool_info.source.map(|s| s.as_str()).unwrap_or(""),
+            elapsed_ms = tool_info.duration_ms,
+            "detected"
+        );
+        self.cache_tool(tool_config, &tool_info);
+        tool_info
+    }
 
-        // Store in cache
+    /// A tool's cached info, if the cache is enabled and has it
+    fn cached_tool(&mut self, tool_config: &ToolConfig) -> Option<ToolInfo> {
+        let start = Instant::now();
+        let cache = self.cache.as_mut()?;
+        match cache.get(&tool_config.name, &self.working_dir) {
+            Some(cached) => {
+                debug!(tool = %tool_config.name, "cache hit");
+                let mut tool_info = cached.clone();
+                tool_info.duration_ms = Some(start.elapsed().as_millis() as u64);
+                tool_info.cached = true;
+                Some(tool_info)
+            }
+            None => {
+                debug!(tool = %tool_config.name, "cache miss");
+                None
+            }
+        }
+    }
+
+    /// Store a detected tool in the cache (if enabled and the detection
+    /// wasn't cancelled)
+    fn cache_tool(&mut self, tool_config: &ToolConfig, tool_info: &ToolInfo) {
+        if self.is_cancelled() {
+            return;
+        }
         if let Some(ref mut cache) = self.cache {
             cache.put(
                 tool_config.name.clone(),
@@ -125,76 +531,312 @@ impl ToolDetector {
                 self.working_dir.clone(),
             );
         }
-
-        tool_info
     }
 
     /// Detect a single tool's version without cache
     fn detect_tool_uncached(&self, tool_config: &ToolConfig) -> ToolInfo {
-        match self.run_version_command(&tool_config.command) {
-            Ok(output) => {
-                let version = if let Some(ref regex_str) = tool_config.parse_regex {
-                    self.parse_version(&output, regex_str)
-                        .unwrap_or_else(|| output.trim().to_string())
+        self.tool_from_output(tool_config, self.run_version_command(tool_config))
+    }
+
+    /// Build a tool's info from the output of its version command
+    fn tool_from_output(
+        &self,
+        tool_config: &ToolConfig,
+        output: Result<CommandOutput>,
+    ) -> ToolInfo {
+        match output.and_then(|output| self.parse_output(tool_config, &output)) {
+            Ok(mut fields) => {
+                let version = fields.get("version").cloned().unwrap_or_default();
+                let (path, source) = if tool_config.container_image.is_some() {
+                    (None, Some(ToolSource::Docker))
                 } else {
-                    output.trim().to_string()
+                    let cmd_name = tool_config.command.split_whitespace().next().unwrap_or("");
+                    let path = Self::which_command(cmd_name);
+                    let source = path.as_deref().and_then(source::detect);
+                    (path, source)
                 };
-
-                ToolInfo::available(tool_config.name.clone(), version)
+                let mut tool_info = ToolInfo::available(tool_config.name.clone(), version)
                     .with_icon(tool_config.icon.clone())
                     .with_short_name(tool_config.short_name.clone())
+                    .with_path(path)
+                    .with_source(source);
+                if let Some(ref parse_template) = tool_config.parse_template {
+                    let display = template::render(parse_template, &fields);
+                    tool_info.display_version = (!display.is_empty()).then_some(display);
+                }
+                tool_info.build = fields.remove("build");
+                tool_info.runtime = fields.remove("runtime");
+                if tool_info.source == Some(ToolSource::Rustup) {
+                    self.add_toolchain(&mut tool_info);
+                }
+                if self.config.extras.go_module
+                    && tool_config.command.split_whitespace().next() == Some("go")
+                {
+                    self.add_go_module(&mut tool_info);
+                }
+                tool_info
+            }
+            Err(e) => {
+                let mut tool_info =
+                    ToolInfo::unavailable(tool_config.name.clone(), Some(e.to_string()))
+                        .with_icon(tool_config.icon.clone())
+                        .with_short_name(tool_config.short_name.clone());
+                tool_info.failure = e.tool_error().cloned();
+                tool_info
+            }
+        }
+    }
+
+    /// Add the active rustup toolchain to a tool run through a rustup proxy;
+    /// one selected by an override also shows next to the version:
+    /// `1.84.0 (nightly via rust-toolchain.toml)`
+    fn add_toolchain(&self, tool_info: &mut ToolInfo) {
+        let env = |var: &str| std::env::var(var).ok();
+        let (Some(dir), Some(home)) = (self.get_current_dir(), rustup::rustup_home(&env)) else {
+            return;
+        };
+        let Some(toolchain) = rustup::active_toolchain(Path::new(&dir), &home, &env) else {
+            return;
+        };
+        if toolchain.selected_by != rustup::DEFAULT && tool_info.display_version.is_none() {
+            tool_info.display_version = tool_info.version.as_ref().map(|version| {
+                format!(
+                    "{} ({} via {})",
+                    version, toolchain.name, toolchain.selected_by
+                )
+            });
+        }
+        tool_info.toolchain = Some(toolchain);
+    }
+
+    /// Add the working directory's Go module; a Go older than its `go`
+    /// directive shows next to the version: `1.21.5 (go.mod requires 1.22)`
+    fn add_go_module(&self, tool_info: &mut ToolInfo) {
+        let Some(module) = self
+            .get_current_dir()
+            .and_then(|dir| gomod::find_module(Path::new(&dir)))
+        else {
+            return;
+        };
+        if let (Some(version), Some(required)) = (&tool_info.version, &module.go) {
+            if gomod::is_too_old(version, required) && tool_info.display_version.is_none() {
+                tool_info.display_version =
+                    Some(format!("{} (go.mod requires {})", version, required));
             }
-            Err(e) => ToolInfo::unavailable(tool_config.name.clone(), Some(e.to_string()))
-                .with_icon(tool_config.icon.clone())
-                .with_short_name(tool_config.short_name.clone()),
         }
+        tool_info.go_module = Some(module);
+    }
+
+    /// Version fields of a tool's command output: `version` plus the named
+    /// groups of `parse_regex` (applied to the `parse_json_pointer` value if
+    /// set), or the fields returned by `parse_script`
+    fn parse_output(
+        &self,
+        tool_config: &ToolConfig,
+        output: &CommandOutput,
+    ) -> Result<BTreeMap<String, String>> {
+        if let Some(ref parse_script) = tool_config.parse_script {
+            return self.run_parse_script(parse_script, output).map_err(|e| {
+                let reason = match e {
+                    ToolboxError::VersionParse(reason) => reason,
+                    e => e.to_string(),
+                };
+                ToolError::ParseFailed {
+                    parser: "parse_script".to_string(),
+                    output: output.text.clone(),
+                    reason,
+                }
+                .into()
+            });
+        }
+        let text = match tool_config.parse_json_pointer {
+            Some(ref pointer) => json_pointer_value(&output.text, pointer)?,
+            None => output.text.clone(),
+        };
+        let mut fields = tool_config
+            .parse_regex
+            .as_deref()
+            .map(|regex_str| parse_groups(&text, regex_str))
+            .unwrap_or_default();
+        fields.insert("version".to_string(), self.version_of(tool_config, &text));
+        Ok(fields)
+    }
+
+    /// Evaluate a tool's `parse_script` on its command output
+    #[cfg(feature = "scripting")]
+    fn run_parse_script(
+        &self,
+        parse_script: &str,
+        output: &CommandOutput,
+    ) -> Result<BTreeMap<String, String>> {
+        script::evaluate(parse_script, &output.stdout, &output.stderr, &output.text)
+    }
+
+    /// Without the `scripting` feature, `parse_script` can't be evaluated
+    #[cfg(not(feature = "scripting"))]
+    fn run_parse_script(
+        &self,
+        _parse_script: &str,
+        _output: &CommandOutput,
+    ) -> Result<BTreeMap<String, String>> {
+        Err(ToolboxError::VersionParse(
+            "parse_script requires the `scripting` feature".to_string(),
+        ))
     }
 
-    /// Run a command and get its output
-    fn run_version_command(&self, command: &str) -> Result<String> {
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        if parts.is_empty() {
-            return Err(ToolboxError::CommandFailed("Empty command".to_string()));
+    /// The version in a tool's command output (all of it without a match)
+    fn version_of(&self, tool_config: &ToolConfig, output: &str) -> String {
+        if let Some(ref regex_str) = tool_config.parse_regex {
+            self.parse_version(output, regex_str).unwrap_or_else(|| {
+                debug!(tool = %tool_config.name, regex = %regex_str, "parse_regex did not match, using raw output");
+                output.trim().to_string()
+            })
+        } else {
+            output.trim().to_string()
         }
+    }
 
-        let mut cmd = Command::new(parts[0]);
-        if parts.len() > 1 {
-            cmd.args(&parts[1..]);
+    /// Run a tool's version command and get its output
+    fn run_version_command(&self, tool_config: &ToolConfig) -> Result<CommandOutput> {
+        self.run_version_command_retrying(tool_config).0
+    }
+
+    /// Run a tool's version command, retrying a failure up to `retries`
+    /// times with doubling delays; also returns the number of retries made
+    fn run_version_command_retrying(
+        &self,
+        tool_config: &ToolConfig,
+    ) -> (Result<CommandOutput>, u32) {
+        let max_retries = tool_config.retries.unwrap_or(0);
+        let mut retries = 0;
+        loop {
+            let result = self.run_version_command_once(tool_config);
+            match result {
+                Err(ref e) if retries < max_retries && !self.is_cancelled() => {
+                    retries += 1;
+                    let delay = tool_config.retry_delay(retries);
+                    debug!(tool = %tool_config.name, error = %e, retry = retries, delay_ms = delay.as_millis() as u64, "retrying");
+                    std::thread::sleep(delay);
+                }
+                result => return (result, retries),
+            }
         }
+    }
 
-        // Set working directory if specified
-        if let Some(ref dir) = self.working_dir {
-            cmd.current_dir(dir);
+    /// Run a tool's version command once
+    fn run_version_command_once(&self, tool_config: &ToolConfig) -> Result<CommandOutput> {
+        let parts = command_line(tool_config, self.project_env().as_ref());
+        let Some((program, args)) = parts.split_first() else {
+            return Err(ToolError::Failed {
+                message: "Empty command".to_string(),
+            }
+            .into());
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let Some(ref image) = tool_config.container_image else {
+            return self.run_program(program, &args, tool_config);
+        };
+
+        let container_cache = self.container_cache(tool_config);
+        if let Some((ref cache, ref key)) = container_cache {
+            if let Some(output) = CommandOutput::cached(cache, key, tool_config) {
+                debug!(tool = %tool_config.name, image = %image, "container cache hit");
+                return Ok(output);
+            }
+        }
+        let output = self.run_program(program, &args, tool_config)?;
+        if let Some((cache, key)) = container_cache {
+            output.store(&cache, &key);
         }
+        Ok(output)
+    }
+
+    /// The direnv or Nix flake environment of the working directory, if
+    /// enabled in `[environment]`
+    fn project_env(&self) -> Option<ProjectEnv> {
+        let dir = self.get_current_dir()?;
+        ProjectEnv::find(Path::new(&dir), &self.config.environment)
+    }
+
+    /// How version commands are sandboxed, if enabled in `[sandbox]`
+    fn sandbox(&self) -> Option<Sandbox> {
+        Sandbox::new(
+            &self.config.sandbox,
+            self.get_current_dir().map(PathBuf::from),
+            std::env::vars_os(),
+        )
+    }
+
+    /// On-disk cache and key of a tool run in a container image; `None` if
+    /// caching is disabled, the tool has no image or the image isn't pulled
+    fn container_cache(&self, tool_config: &ToolConfig) -> Option<(ContainerCache, String)> {
+        let image = tool_config.container_image.as_deref()?;
+        self.cache.as_ref()?;
+        let cache = ContainerCache::new(ContainerCache::default_dir()?);
+        let key = container::cache_key(&container::image_id(image)?, &tool_config.command);
+        Some((cache, key))
+    }
+
+    /// Run a program with arguments and get its output, read as the tool's
+    /// version command
+    fn run_program(
+        &self,
+        program: &str,
+        args: &[&str],
+        tool_config: &ToolConfig,
+    ) -> Result<CommandOutput> {
+        let output = self.program_output(program, args)?;
+        version_output(program, &output, tool_config)
+    }
+
+    /// Run a program with arguments (sandboxed if enabled) and get its raw
+    /// output, whatever its exit status
+    fn program_output(&self, program: &str, args: &[&str]) -> Result<std::process::Output> {
+        let mut cmd = match self.sandbox() {
+            Some(sandbox) => sandbox.command(program, args),
+            None => {
+                let mut cmd = Command::new(spawn_program(program));
+                cmd.args(args);
+                // Set working directory if specified
+                if let Some(ref dir) = self.working_dir {
+                    cmd.current_dir(dir);
+                }
+                cmd
+            }
+        };
 
         // Inherit PATH and other environment variables for asdf/mise support
-        let output = cmd
-            .output()
-            .map_err(|e| ToolboxError::CommandFailed(format!("{}: {}", parts[0], e)))?;
-
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            // Some tools output to stderr
-            if stdout.trim().is_empty() {
-                Ok(String::from_utf8_lossy(&output.stderr).to_string())
-            } else {
-                Ok(stdout)
-            }
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(ToolboxError::CommandFailed(format!(
-                "{}: {}",
-                parts[0],
-                stderr.trim()
-            )))
+        trace!(command = program, dir = ?self.working_dir, "spawning");
+        let start = Instant::now();
+        let output = match self.cancellation {
+            Some(ref token) => cancel::output(&mut cmd, token),
+            None => cmd.output().map(Some),
         }
+        .map_err(|e| {
+            debug!(command = program, error = %e, "spawn failed");
+            ToolError::spawn(program, &e)
+        })?;
+        let Some(output) = output else {
+            debug!(command = program, "killed: detection cancelled");
+            return Err(ToolboxError::Cancelled);
+        };
+        trace!(
+            command = program,
+            status = ?output.status.code(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "command exited"
+        );
+        Ok(output)
     }
 
-    /// Parse version from output using regex
+    /// Parse version from output using regex: the `version` named group, or
+    /// the first group
     fn parse_version(&self, output: &str, regex_str: &str) -> Option<String> {
         let re = Regex::new(regex_str).ok()?;
         let caps = re.captures(output)?;
-        caps.get(1).map(|m| m.as_str().to_string())
+        caps.name("version")
+            .or_else(|| caps.get(1))
+            .map(|m| m.as_str().to_string())
     }
 
     /// Get current working directory
@@ -211,8 +853,15 @@ impl ToolDetector {
     /// Get git repository information
     #[cfg(feature = "git")]
     fn get_git_info(&self) -> Option<GitInfo> {
+        let start = Instant::now();
         let dir = self.working_dir.as_deref().unwrap_or(".");
-        let repo = git2::Repository::discover(dir).ok()?;
+        let repo = match git2::Repository::discover(dir) {
+            Ok(repo) => repo,
+            Err(e) => {
+                debug!(dir, error = %e.message(), "no git repository");
+                return None;
+            }
+        };
 
         // Get current branch
         let head = repo.head().ok()?;
@@ -252,6 +901,14 @@ impl ToolDetector {
         }
 
         let is_dirty = modified_count > 0 || staged_count > 0 || untracked_count > 0;
+        debug!(
+            branch = %branch,
+            modified_count,
+            staged_count,
+            untracked_count,
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "git status"
+        );
 
         // Get ahead/behind counts
         let (ahead, behind) = if head.is_branch() {
@@ -353,12 +1010,7 @@ impl ToolDetector {
 
         let mut sys = System::new();
 
-        let mut info = SystemInfo {
-            memory_percent: None,
-            memory_total_gb: None,
-            memory_used_gb: None,
-            cpu_percent: None,
-        };
+        let mut info = SystemInfo::default();
 
         if self.config.extras.system_memory {
             sys.refresh_memory();
@@ -369,14 +1021,32 @@ impl ToolDetector {
             info.memory_percent = Some((used / total) * 100.0);
         }
 
-        if self.config.extras.system_cpu {
+        if self.config.extras.system_swap {
+            sys.refresh_memory();
+            let total = sys.total_swap() as f32 / 1_073_741_824.0;
+            let used = sys.used_swap() as f32 / 1_073_741_824.0;
+            info.swap_total_gb = Some(total);
+            info.swap_used_gb = Some(used);
+            // Machines without swap report a total of 0
+            if total > 0.0 {
+                info.swap_percent = Some((used / total) * 100.0);
+            }
+        }
+
+        if self.config.extras.system_cpu || self.config.extras.system_cpu_cores {
             sys.refresh_cpu_usage();
             // Need to wait a bit for accurate CPU readings
             std::thread::sleep(std::time::Duration::from_millis(100));
             sys.refresh_cpu_usage();
-            let cpu_usage: f32 =
-                sys.cpus().iter().map(|c| c.cpu_usage()).sum::<f32>() / sys.cpus().len() as f32;
-            info.cpu_percent = Some(cpu_usage);
+
+            if self.config.extras.system_cpu {
+                let cpu_usage: f32 =
+                    sys.cpus().iter().map(|c| c.cpu_usage()).sum::<f32>() / sys.cpus().len() as f32;
+                info.cpu_percent = Some(cpu_usage);
+            }
+            if self.config.extras.system_cpu_cores {
+                info.cpu_per_core = Some(sys.cpus().iter().map(|c| c.cpu_usage()).collect());
+            }
         }
 
         Some(info)
@@ -395,79 +1065,97 @@ impl ToolDetector {
 
     /// Run diagnostics on a single tool, returning detailed results
     pub fn diagnose_tool(&self, tool_config: &ToolConfig) -> ToolDiagnostic {
-        let cmd_name = tool_config.command.split_whitespace().next().unwrap_or("");
+        let cmd_name = match tool_config.container_image {
+            Some(_) => container::DOCKER,
+            None => tool_config.command.split_whitespace().next().unwrap_or(""),
+        };
 
         // Try to find the command in PATH
         let command_path = Self::which_command(cmd_name);
 
         // Try to run the version command
-        match self.run_version_command(&tool_config.command) {
+        let (result, retries) = self.run_version_command_retrying(tool_config);
+        let mut diagnostic = match result {
             Ok(output) => {
-                if let Some(ref regex_str) = tool_config.parse_regex {
-                    match self.parse_version(&output, regex_str) {
-                        Some(version) => ToolDiagnostic {
-                            name: tool_config.name.clone(),
-                            icon: tool_config.icon.clone(),
-                            status: DiagnosticStatus::Ok,
-                            command: tool_config.command.clone(),
-                            command_path,
-                            version: Some(version),
-                            error_detail: None,
-                            suggestion: None,
-                            enabled: tool_config.enabled,
-                        },
-                        None => {
-                            // Command ran but regex didn't match
-                            let raw_output = output.trim().to_string();
-                            ToolDiagnostic {
-                                name: tool_config.name.clone(),
-                                icon: tool_config.icon.clone(),
-                                status: DiagnosticStatus::Warning,
-                                command: tool_config.command.clone(),
-                                command_path,
-                                version: Some(raw_output.clone()),
-                                error_detail: Some(format!(
-                                    "version parse: regex '{}' did not match output '{}'",
-                                    regex_str,
-                                    truncate_string(&raw_output, 80)
-                                )),
-                                suggestion: Some(
-                                    "Check parse_regex in config matches the command output"
-                                        .to_string(),
-                                ),
-                                enabled: tool_config.enabled,
-                            }
+                let shim = command_path
+                    .as_deref()
+                    .and_then(|path| shim::resolve(path, self.working_dir.as_deref()));
+                let raw_output = output.text.trim().to_string();
+                let structured = if tool_config.parse_script.is_some() {
+                    Some("parse_script")
+                } else if tool_config.parse_json_pointer.is_some() {
+                    Some("parse_json_pointer")
+                } else {
+                    None
+                };
+                let (status, version, error_detail, suggestion) = if let Some(option) = structured {
+                    match self.parse_output(tool_config, &output) {
+                        Ok(mut fields) => {
+                            (DiagnosticStatus::Ok, fields.remove("version"), None, None)
                         }
+                        // Script or pointer failed on the command output
+                        Err(e) => (
+                            DiagnosticStatus::Warning,
+                            Some(raw_output),
+                            Some(e.to_string()),
+                            Some(format!(
+                                "Check {} in config handles the command output",
+                                option
+                            )),
+                        ),
+                    }
+                } else if let Some(ref regex_str) = tool_config.parse_regex {
+                    match self.parse_version(&output.text, regex_str) {
+                        Some(version) => (DiagnosticStatus::Ok, Some(version), None, None),
+                        // Command ran but regex didn't match
+                        None => (
+                            DiagnosticStatus::Warning,
+                            Some(raw_output.clone()),
+                            Some(format!(
+                                "version parse: regex '{}' did not match output '{}'",
+                                regex_str,
+                                truncate_string(&raw_output, 80)
+                            )),
+                            Some(
+                                "Check parse_regex in config matches the command output"
+                                    .to_string(),
+                            ),
+                        ),
                     }
                 } else {
                     // No regex, use raw output
-                    ToolDiagnostic {
-                        name: tool_config.name.clone(),
-                        icon: tool_config.icon.clone(),
-                        status: DiagnosticStatus::Ok,
-                        command: tool_config.command.clone(),
-                        command_path,
-                        version: Some(output.trim().to_string()),
-                        error_detail: None,
-                        suggestion: None,
-                        enabled: tool_config.enabled,
-                    }
+                    (DiagnosticStatus::Ok, Some(raw_output), None, None)
+                };
+                ToolDiagnostic {
+                    name: tool_config.name.clone(),
+                    icon: tool_config.icon.clone(),
+                    status,
+                    command: tool_config.command.clone(),
+                    command_path,
+                    shim,
+                    path_matches: Vec::new(),
+                    source: None,
+                    package: None,
+                    pinned: None,
+                    retries: None,
+                    provenance: None,
+                    version,
+                    error_detail,
+                    suggestion,
+                    enabled: tool_config.enabled,
                 }
             }
             Err(e) => {
-                let error_str = e.to_string();
-                let (error_detail, suggestion) = if error_str.contains("No such file or directory")
-                    || error_str.contains("not found")
-                {
-                    (
-                        format!("command not found: '{}'", cmd_name),
-                        Some(format!(
-                            "Install {} or add it to your PATH",
-                            tool_config.name
-                        )),
-                    )
-                } else {
-                    (error_str, None)
+                let (error_detail, suggestion) = match e.tool_error() {
+                    Some(ToolError::NotFound { .. }) => (
+                        fill(Message::DiagnosticNotFound, &[&cmd_name]),
+                        Some(fill(Message::SuggestInstall, &[&tool_config.name])),
+                    ),
+                    Some(ToolError::PermissionDenied { .. }) => (
+                        e.to_string(),
+                        Some(fill(Message::SuggestExecutable, &[&cmd_name])),
+                    ),
+                    _ => (e.to_string(), None),
                 };
 
                 ToolDiagnostic {
@@ -476,18 +1164,321 @@ impl ToolDetector {
                     status: DiagnosticStatus::Error,
                     command: tool_config.command.clone(),
                     command_path: None,
+                    shim: None,
+                    path_matches: Vec::new(),
+                    source: None,
+                    package: None,
+                    pinned: None,
+                    retries: None,
+                    provenance: None,
                     version: None,
                     error_detail: Some(error_detail),
                     suggestion,
                     enabled: tool_config.enabled,
                 }
             }
+        };
+
+        diagnostic.source = diagnostic.command_path.as_deref().and_then(source::detect);
+        diagnostic.retries = (retries > 0).then_some(retries);
+        // Show the command as run (in a container, `direnv exec`, `nix develop`,
+        // without network)
+        let sandbox = self.sandbox();
+        if tool_config.container_image.is_some()
+            || self.project_env().is_some()
+            || sandbox.is_some()
+        {
+            let parts = command_line(tool_config, self.project_env().as_ref());
+            diagnostic.command = match sandbox {
+                Some(sandbox) => sandbox.wrap(parts),
+                None => parts,
+            }
+            .join(" ");
+        }
+
+        // An earlier PATH entry hiding the version manager's binary
+        if diagnostic.status != DiagnosticStatus::Error {
+            diagnostic.path_matches = std::env::var_os("PATH")
+                .map(|path_var| which_all(cmd_name, &path_var))
+                .unwrap_or_default();
+            if let Some(shadowing) = shim::find_shadowing(&diagnostic.path_matches) {
+                diagnostic.status = DiagnosticStatus::Warning;
+                let detail = shadowing.detail();
+                diagnostic.error_detail = Some(match diagnostic.error_detail.take() {
+                    Some(existing) => format!("{}; {}", existing, detail),
+                    None => detail,
+                });
+                diagnostic.suggestion = Some(shadowing.suggestion());
+            }
+        }
+
+        // The package manager the project pins through Corepack
+        if let Some(pin) = self
+            .get_current_dir()
+            .and_then(|dir| corepack::find_pin(Path::new(&dir)))
+            .filter(|pin| pin.name == cmd_name && diagnostic.status != DiagnosticStatus::Error)
+        {
+            let mismatch = diagnostic
+                .version
+                .as_deref()
+                .is_some_and(|version| corepack::is_mismatch(&pin.pinned.version, version));
+            if mismatch {
+                diagnostic.status = DiagnosticStatus::Warning;
+                let detail = format!("packageManager pins {} {}", pin.name, pin.pinned.version);
+                diagnostic.error_detail = Some(match diagnostic.error_detail.take() {
+                    Some(existing) => format!("{}; {}", existing, detail),
+                    None => detail,
+                });
+                diagnostic.suggestion = Some(format!(
+                    "Run `corepack enable` so {} resolves to the pinned version",
+                    pin.name
+                ));
+            }
+            diagnostic.pinned = Some(pin.pinned);
+        }
+
+        // The Go version the working directory's module requires
+        if cmd_name == "go" && diagnostic.status != DiagnosticStatus::Error {
+            let module = self
+                .get_current_dir()
+                .and_then(|dir| gomod::find_module(Path::new(&dir)));
+            let required = module.and_then(|module| module.go);
+            if let (Some(version), Some(required)) = (&diagnostic.version, required) {
+                if gomod::is_too_old(version, &required) {
+                    diagnostic.status = DiagnosticStatus::Warning;
+                    let detail = format!("go.mod requires go {}", required);
+                    diagnostic.error_detail = Some(match diagnostic.error_detail.take() {
+                        Some(existing) => format!("{}; {}", existing, detail),
+                        None => detail,
+                    });
+                    diagnostic.suggestion = Some(format!("Install Go {} or later", required));
+                }
+            }
+        }
+        diagnostic
+    }
+
+    /// Every installation of a tool: the binary that runs, version-suffixed
+    /// binaries in PATH and version manager installs, the active one marked
+    ///
+    /// `active` is the tool's detection result. When it runs through a
+    /// version manager shim, the install of the same version is marked
+    /// active instead of the shim.
+    pub fn installed_versions(
+        &self,
+        tool_config: &ToolConfig,
+        active: &ToolInfo,
+    ) -> Vec<InstalledVersion> {
+        let parts: Vec<&str> = tool_config.command.split_whitespace().collect();
+        let Some((cmd, args)) = parts.split_first() else {
+            return Vec::new();
+        };
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let active_path = active.path.as_deref().map(Path::new);
+        let via_shim =
+            active_path.is_some_and(|path| path.parent().is_some_and(|dir| dir.ends_with("shims")));
+
+        let mut seen = std::collections::HashSet::new();
+        let mut versions = Vec::new();
+        if let (Some(path), false) = (active_path, via_shim) {
+            seen.insert(canonical(path));
+            versions.push(InstalledVersion {
+                version: active.version.clone(),
+                path: path.to_string_lossy().into_owned(),
+                source: active.source,
+                active: true,
+            });
+        }
+
+        let path_var = std::env::var_os("PATH").unwrap_or_default();
+        for path in installs::suffixed_binaries(cmd, &path_var) {
+            if !seen.insert(canonical(&path)) {
+                continue;
+            }
+            let program = path.to_string_lossy().into_owned();
+            let version = self
+                .run_program(&program, args, tool_config)
+                .and_then(|output| self.parse_output(tool_config, &output))
+                .ok()
+                .and_then(|mut fields| fields.remove("version"));
+            versions.push(InstalledVersion {
+                version,
+                source: source::detect(&program),
+                path: program,
+                active: false,
+            });
+        }
+
+        let dirs = dirs::home_dir()
+            .map(|home| installs::version_dirs(&home, &|var| std::env::var(var).ok()))
+            .unwrap_or_default();
+        for install in installs::managed_installs(cmd, &dirs) {
+            if !seen.insert(canonical(&install.path)) {
+                continue;
+            }
+            let is_active = via_shim
+                && active.version.as_deref().is_some_and(|v| {
+                    v.trim_start_matches('v') == install.version
+                        && active.source == Some(install.source)
+                });
+            versions.push(InstalledVersion {
+                version: Some(install.version),
+                path: install.path.to_string_lossy().into_owned(),
+                source: Some(install.source),
+                active: is_active,
+            });
+        }
+
+        // A shim whose install wasn't found still runs something
+        if via_shim && !versions.iter().any(|v| v.active) {
+            if let Some(path) = active_path {
+                versions.insert(
+                    0,
+                    InstalledVersion {
+                        version: active.version.clone(),
+                        path: path.to_string_lossy().into_owned(),
+                        source: active.source,
+                        active: true,
+                    },
+                );
+            }
         }
+        versions
     }
 
     /// Run diagnostics on all configured tools (both enabled and disabled)
     pub fn diagnose_all(&self) -> DiagnosticSummary {
-        let all_tools = self.config.effective_tools();
+        self.diagnose_matching(|_| true)
+    }
+
+    /// Run diagnostics on the named tools (names or short names,
+    /// case-insensitively, enabled or not)
+    pub fn diagnose_tools(&self, names: &[String]) -> Result<DiagnosticSummary> {
+        for name in names {
+            if self.config.find_tool(name).is_none() {
+                return Err(ToolboxError::Config(format!("Unknown tool: {}", name)));
+            }
+        }
+        Ok(self.diagnose_matching(|tool| names.iter().any(|name| tool.matches_name(name))))
+    }
+
+    /// Attach doctor's diagnostic to each configured tool of a detection
+    /// result (tools reported by detector plugins have none)
+    ///
+    /// The version commands run again, so this costs about one more detection.
+    pub fn attach_diagnostics(&self, info: &mut ToolboxInfo) {
+        let tools = self.config.effective_tools_with_provenance();
+        for tool in &mut info.tools {
+            if let Some((tool_config, provenance)) = tools
+                .iter()
+                .find(|(tool_config, _)| tool_config.name == tool.name)
+            {
+                tool.diagnostic = Some(ToolDiagnostic {
+                    provenance: Some(provenance.clone()),
+                    ..self.diagnose_tool(tool_config)
+                });
+            }
+        }
+    }
+
+    /// Explain a tool's detection step by step: the command line as run, its
+    /// raw output, what the regex captured and the cache entry
+    ///
+    /// The command always runs, bypassing the caches, so the output is what
+    /// the tool prints now.
+    pub fn explain_tool(&self, name: &str) -> Result<ToolExplanation> {
+        let Some((tool_config, provenance)) = self
+            .config
+            .effective_tools_with_provenance()
+            .into_iter()
+            .find(|(tool, _)| tool.matches_name(name))
+        else {
+            return Err(ToolboxError::Config(format!("Unknown tool: {}", name)));
+        };
+        let cmd_name = match tool_config.container_image {
+            Some(_) => container::DOCKER,
+            None => tool_config.command.split_whitespace().next().unwrap_or(""),
+        };
+        let command_path = Self::which_command(cmd_name);
+        let parts = command_line(&tool_config, self.project_env().as_ref());
+        let shown = match self.sandbox() {
+            Some(sandbox) => sandbox.wrap(parts.clone()),
+            None => parts.clone(),
+        };
+
+        let mut explanation = ToolExplanation {
+            name: tool_config.name.clone(),
+            command: tool_config.command.clone(),
+            command_line: shown.join(" "),
+            source: command_path.as_deref().and_then(source::detect),
+            command_path,
+            provenance,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            parse_regex: tool_config.parse_regex.clone(),
+            captures: None,
+            version: None,
+            error: None,
+            failure: None,
+            cache: self.cache_state(&tool_config),
+        };
+
+        let output = match parts.split_first() {
+            Some((program, args)) => {
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                self.program_output(program, &args).and_then(|output| {
+                    explanation.exit_code = output.status.code();
+                    explanation.stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+                    explanation.stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+                    version_output(program, &output, &tool_config)
+                })
+            }
+            None => Err(ToolError::Failed {
+                message: "Empty command".to_string(),
+            }
+            .into()),
+        };
+        if let (Ok(output), Some(regex_str)) = (&output, &tool_config.parse_regex) {
+            let text = match tool_config.parse_json_pointer {
+                Some(ref pointer) => json_pointer_value(&output.text, pointer).ok(),
+                None => Some(output.text.clone()),
+            };
+            explanation.captures = text.and_then(|text| regex_captures(&text, regex_str));
+        }
+        let tool_info = self.tool_from_output(&tool_config, output);
+        explanation.version = tool_info.display_version.or(tool_info.version);
+        explanation.error = tool_info.error;
+        explanation.failure = tool_info.failure;
+        Ok(explanation)
+    }
+
+    /// A tool's entry in the version cache
+    fn cache_state(&self, tool_config: &ToolConfig) -> CacheState {
+        let Some(ref cache) = self.cache else {
+            return CacheState::default();
+        };
+        CacheState {
+            enabled: true,
+            ttl_seconds: cache.default_ttl(),
+            entry: cache
+                .peek(&tool_config.name, &self.working_dir)
+                .map(|entry| CachedVersion {
+                    version: entry.tool_info.version.clone(),
+                    age_seconds: entry.age(),
+                    expired: entry.is_expired(),
+                }),
+        }
+    }
+
+    /// Run diagnostics on the configured tools `keep` accepts
+    fn diagnose_matching(&self, keep: impl Fn(&ToolConfig) -> bool) -> DiagnosticSummary {
+        let all_tools: Vec<_> = self
+            .config
+            .effective_tools_with_provenance()
+            .into_iter()
+            .filter(|(tool_config, _)| keep(tool_config))
+            .collect();
 
         let config_path = Config::config_path().map(|p| p.display().to_string());
         let config_exists = config_path
@@ -495,8 +1486,13 @@ impl ToolDetector {
             .map(|p| std::path::Path::new(p).exists())
             .unwrap_or(false);
 
-        let diagnostics: Vec<ToolDiagnostic> =
-            all_tools.iter().map(|t| self.diagnose_tool(t)).collect();
+        let diagnostics: Vec<ToolDiagnostic> = all_tools
+            .iter()
+            .map(|(tool_config, provenance)| ToolDiagnostic {
+                provenance: Some(provenance.clone()),
+                ..self.diagnose_tool(tool_config)
+            })
+            .collect();
 
         let ok_count = diagnostics
             .iter()
@@ -518,26 +1514,492 @@ impl ToolDetector {
             ok_count,
             warning_count,
             error_count,
+            environment: env_report::report(
+                &std::env::var_os("PATH").unwrap_or_default(),
+                &diagnostics,
+                dirs::home_dir().as_deref(),
+                &|var| std::env::var(var).ok(),
+            ),
+            update_available: None,
             tools: diagnostics,
         }
     }
 
-    /// Look up the full path of a command using `which`
-    fn which_command(cmd: &str) -> Option<String> {
-        if cmd.is_empty() {
-            return None;
+    /// Run the `toolbox-detect-*` executables in PATH and merge their tools
+    fn detect_external(
+        &self,
+        tools: &mut Vec<ToolInfo>,
+        on_event: &mut dyn FnMut(DetectEvent<'_>),
+    ) {
+        let Some(path_var) = std::env::var_os("PATH") else {
+            return;
+        };
+        // Discovery is cached along with the versions, so `--no-cache` rescans PATH
+        let detectors = match external::cache_path().filter(|_| self.cache.is_some()) {
+            Some(cache) => external::discover_cached(&path_var, &cache),
+            None => external::discover(&path_var),
+        };
+        let sandbox = self.sandbox();
+        for detector in detectors {
+            if self.is_cancelled() {
+                return;
+            }
+            let start = Instant::now();
+            let mut found = detector.run(
+                self.working_dir.as_deref(),
+                sandbox.as_ref(),
+                self.cancellation.as_ref(),
+                self.timeout,
+            );
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+            for tool in &mut found {
+                tool.duration_ms = Some(elapsed_ms);
+            }
+            debug!(detector = %detector.name, tools = found.len(), elapsed_ms, "external detector finished");
+            for tool in &found {
+                on_event(DetectEvent::Tool(tool));
+            }
+            external::merge(tools, found);
+        }
+    }
+
+    /// Run the detectors of the configured WASM plugins and merge their tools
+    #[cfg(feature = "plugins")]
+    fn detect_plugins(
+        &mut self,
+        tools: &mut Vec<ToolInfo>,
+        on_event: &mut dyn FnMut(DetectEvent<'_>),
+    ) {
+        let config = &self.config;
+        let plugins = self.plugins.get_or_insert_with(|| {
+            config
+                .plugins
+                .iter()
+                .filter(|(_, plugin)| plugin.enabled)
+                .map(|(name, plugin)| {
+                    let loaded = WasmPlugin::load(name, plugin).map_err(|e| e.to_string());
+                    (name.clone(), loaded)
+                })
+                .collect()
+        });
+        for (name, plugin) in plugins.iter() {
+            let start = Instant::now();
+            let mut found = match plugin {
+                Ok(plugin) if !plugin.can_detect() => continue,
+                Ok(plugin) => plugin
+                    .detect(self.working_dir.as_deref())
+                    .unwrap_or_else(|e| {
+                        vec![ToolInfo::unavailable(name.clone(), Some(e.to_string()))]
+                    }),
+                Err(e) => vec![ToolInfo::unavailable(name.clone(), Some(e.clone()))],
+            };
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+            for tool in &mut found {
+                tool.duration_ms = Some(elapsed_ms);
+            }
+            debug!(plugin = %name, tools = found.len(), elapsed_ms, "wasm plugin finished");
+            for tool in &found {
+                on_event(DetectEvent::Tool(tool));
+            }
+            external::merge(tools, found);
+        }
+    }
+
+    /// Run the registered detectors and merge their tools
+    fn detect_registered(
+        &self,
+        tools: &mut Vec<ToolInfo>,
+        on_event: &mut dyn FnMut(DetectEvent<'_>),
+    ) {
+        for detector in &self.registered {
+            let start = Instant::now();
+            let mut found = detector
+                .detect(self.working_dir.as_deref())
+                .unwrap_or_else(|e| {
+                    vec![ToolInfo::unavailable(
+                        detector.name().to_string(),
+                        Some(e.to_string()),
+                    )]
+                });
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+            for tool in &mut found {
+                tool.duration_ms = Some(elapsed_ms);
+            }
+            debug!(
+                detector = detector.name(),
+                tools = found.len(),
+                elapsed_ms,
+                "registered detector finished"
+            );
+            for tool in &found {
+                on_event(DetectEvent::Tool(tool));
+            }
+            external::merge(tools, found);
+        }
+    }
+
+    /// Look up the full path of a command in PATH (on Windows also with the
+    /// `PATHEXT` extensions, e.g. `npm` → `npm.cmd`)
+    fn which_command(cmd: &str) -> Option<String> {
+        if cmd.is_empty() {
+            return None;
+        }
+
+        if has_separator(cmd) {
+            return executable_names(cmd)
+                .into_iter()
+                .find(|name| is_executable(Path::new(name)));
+        }
+
+        let path_var = std::env::var_os("PATH")?;
+        std::env::split_paths(&path_var)
+            .find_map(|dir| find_in_dir(&dir, cmd))
+            .map(|path| path.to_string_lossy().into_owned())
+    }
+}
+
+/// Run a version command on the tokio runtime, retrying a failure up to
+/// `retries` times with doubling delays
+#[cfg(feature = "tokio")]
+async fn run_version_command_async(
+    tool_config: &ToolConfig,
+    project_env: Option<&ProjectEnv>,
+    sandbox: Option<&Sandbox>,
+    working_dir: Option<&str>,
+    timeout: Duration,
+) -> Result<CommandOutput> {
+    let max_retries = tool_config.retries.unwrap_or(0);
+    let mut retries = 0;
+    loop {
+        let result =
+            run_version_command_once_async(tool_config, project_env, sandbox, working_dir, timeout)
+                .await;
+        match result {
+            Err(ref e) if retries < max_retries => {
+                retries += 1;
+                let delay = tool_config.retry_delay(retries);
+                debug!(tool = %tool_config.name, error = %e, retry = retries, delay_ms = delay.as_millis() as u64, "retrying");
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Run a version command once on the tokio runtime, killing it after `timeout`
+#[cfg(feature = "tokio")]
+async fn run_version_command_once_async(
+    tool_config: &ToolConfig,
+    project_env: Option<&ProjectEnv>,
+    sandbox: Option<&Sandbox>,
+    working_dir: Option<&str>,
+    timeout: Duration,
+) -> Result<CommandOutput> {
+    let command = tool_config.command.as_str();
+    let parts = command_line(tool_config, project_env);
+    let Some((program, args)) = parts.split_first() else {
+        return Err(ToolError::Failed {
+            message: "Empty command".to_string(),
+        }
+        .into());
+    };
+
+    let mut cmd = match sandbox {
+        Some(sandbox) => {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            tokio::process::Command::from(sandbox.command(program, &args))
+        }
+        None => {
+            let mut cmd = tokio::process::Command::new(spawn_program(program));
+            cmd.args(args);
+            if let Some(dir) = working_dir {
+                cmd.current_dir(dir);
+            }
+            cmd
+        }
+    };
+    cmd.kill_on_drop(true);
+
+    trace!(command, dir = ?working_dir, "spawning");
+    let start = Instant::now();
+    let output = match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(output) => output.map_err(|e| {
+            debug!(command, error = %e, "spawn failed");
+            ToolError::spawn(program, &e)
+        })?,
+        Err(_) => {
+            debug!(
+                command,
+                timeout_ms = timeout.as_millis() as u64,
+                "timed out"
+            );
+            return Err(ToolError::Timeout {
+                program: program.to_string(),
+                timeout_ms: timeout.as_millis() as u64,
+            }
+            .into());
+        }
+    };
+    trace!(
+        command,
+        status = ?output.status.code(),
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        "command exited"
+    );
+    version_output(program, &output, tool_config)
+}
+
+/// A tool skipped or killed because the detection was cancelled
+fn cancelled_tool(tool_config: &ToolConfig) -> ToolInfo {
+    ToolInfo::unavailable(
+        tool_config.name.clone(),
+        Some(ToolboxError::Cancelled.to_string()),
+    )
+}
+
+/// The command line a tool's version command runs as: in its container
+/// image, inside the project environment, or as written
+fn command_line(tool_config: &ToolConfig, project_env: Option<&ProjectEnv>) -> Vec<String> {
+    let parts: Vec<&str> = tool_config.command.split_whitespace().collect();
+    if parts.is_empty() {
+        return Vec::new();
+    }
+    match (&tool_config.container_image, project_env) {
+        (Some(image), _) => std::iter::once(container::DOCKER)
+            .chain(container::run_args(image, &parts))
+            .map(String::from)
+            .collect(),
+        (None, Some(project_env)) => project_env.wrap(&parts),
+        (None, None) => parts.into_iter().map(String::from).collect(),
+    }
+}
+
+/// Every executable named `cmd` in a PATH value, in PATH order (a file
+/// reached through several entries, e.g. `/bin` linked to `/usr/bin`, once)
+fn which_all(cmd: &str, path_var: &std::ffi::OsStr) -> Vec<String> {
+    if cmd.is_empty() || has_separator(cmd) {
+        return Vec::new();
+    }
+    let mut seen = std::collections::HashSet::new();
+    std::env::split_paths(path_var)
+        .filter_map(|dir| find_in_dir(&dir, cmd))
+        .filter(|path| seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Output of a version command that exited successfully (or with an
+/// accepted code)
+#[derive(Debug, Clone, Default)]
+struct CommandOutput {
+    /// The version text, from the tool's `version_stream`
+    text: String,
+    /// Both streams, for `parse_script` and the container cache
+    stdout: String,
+    stderr: String,
+}
+
+impl CommandOutput {
+    /// Output of both streams, with the version text from the tool's
+    /// `version_stream`: by default stdout, or stderr when stdout is empty
+    /// (some tools print their version there)
+    fn from_streams(stdout: String, stderr: String, tool_config: &ToolConfig) -> Self {
+        let text = match tool_config.version_stream {
+            Some(VersionStream::Stdout) => stdout.clone(),
+            Some(VersionStream::Stderr) => stderr.clone(),
+            Some(VersionStream::Both) => format!("{}\n{}", stdout, stderr),
+            None if stdout.trim().is_empty() => stderr.clone(),
+            None => stdout.clone(),
+        };
+        Self {
+            text,
+            stdout,
+            stderr,
+        }
+    }
+
+    /// Output cached for a container image
+    fn cached(cache: &ContainerCache, key: &str, tool_config: &ToolConfig) -> Option<Self> {
+        let cached = cache.load(key)?;
+        Some(Self::from_streams(
+            cached.stdout,
+            cached.stderr,
+            tool_config,
+        ))
+    }
+
+    /// Cache the output for a container image
+    fn store(&self, cache: &ContainerCache, key: &str) {
+        let cached = CachedOutput {
+            stdout: self.stdout.clone(),
+            stderr: self.stderr.clone(),
+        };
+        if let Err(e) = cache.store(key, &cached) {
+            debug!(error = %e, "container cache write failed");
+        }
+    }
+}
+
+/// The output of a finished command; exit codes in `accept_exit_codes` count
+/// as success
+fn version_output(
+    program: &str,
+    output: &std::process::Output,
+    tool_config: &ToolConfig,
+) -> Result<CommandOutput> {
+    let accepted = output.status.success()
+        || output
+            .status
+            .code()
+            .is_some_and(|code| tool_config.accept_exit_codes.contains(&code));
+    if accepted {
+        Ok(CommandOutput::from_streams(
+            String::from_utf8_lossy(&output.stdout).to_string(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+            tool_config,
+        ))
+    } else {
+        Err(ToolError::NonZeroExit {
+            program: program.to_string(),
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
         }
-        Command::new("which")
-            .arg(cmd)
-            .output()
-            .ok()
-            .and_then(|output| {
-                if output.status.success() {
-                    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
-                } else {
-                    None
-                }
+        .into())
+    }
+}
+
+/// The value at a tool's `parse_json_pointer` in its JSON output
+fn json_pointer_value(output: &str, pointer: &str) -> Result<String> {
+    let failed = |reason: String| ToolError::ParseFailed {
+        parser: "parse_json_pointer".to_string(),
+        output: output.to_string(),
+        reason,
+    };
+    let value: serde_json::Value = serde_json::from_str(output.trim())
+        .map_err(|e| failed(format!("parse_json_pointer: output is not JSON: {}", e)))?;
+    match value.pointer(pointer) {
+        Some(serde_json::Value::String(text)) => Ok(text.clone()),
+        Some(value @ serde_json::Value::Number(_)) => Ok(value.to_string()),
+        _ => Err(failed(format!("parse_json_pointer: no version at {}", pointer)).into()),
+    }
+}
+
+/// Named groups of a `parse_regex` that matched in a command's output
+fn parse_groups(output: &str, regex_str: &str) -> BTreeMap<String, String> {
+    let Ok(re) = Regex::new(regex_str) else {
+        return BTreeMap::new();
+    };
+    let Some(caps) = re.captures(output) else {
+        return BTreeMap::new();
+    };
+    re.capture_names()
+        .flatten()
+        .filter_map(|name| Some((name.to_string(), caps.name(name)?.as_str().to_string())))
+        .collect()
+}
+
+/// Every group a regex captured in a command's output, by name or number
+/// (`0` is the whole match); `None` if it is invalid or didn't match
+fn regex_captures(output: &str, regex_str: &str) -> Option<BTreeMap<String, String>> {
+    let re = Regex::new(regex_str).ok()?;
+    let caps = re.captures(output)?;
+    Some(
+        re.capture_names()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let value = caps.get(i)?.as_str().to_string();
+                Some((name.map_or_else(|| i.to_string(), String::from), value))
             })
+            .collect(),
+    )
+}
+
+/// Check whether a path is an executable file
+pub(crate) fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// Whether a command is a path rather than a name looked up in PATH (`/` is a
+/// separator on Windows too)
+fn has_separator(cmd: &str) -> bool {
+    cmd.chars().any(std::path::is_separator)
+}
+
+/// `PATHEXT` on Windows (with the shell's default when unset); `None`
+/// elsewhere, where executables have no extension
+fn path_extensions() -> Option<String> {
+    if cfg!(windows) {
+        Some(
+            std::env::var("PATHEXT")
+                .ok()
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| ".COM;.EXE;.BAT;.CMD".to_string()),
+        )
+    } else {
+        None
+    }
+}
+
+/// File names a command can have on disk
+fn executable_names(cmd: &str) -> Vec<String> {
+    executable_names_with(cmd, path_extensions().as_deref())
+}
+
+/// File names a command can have given a `PATHEXT` value: a name with one of
+/// its extensions as is, any other with each extension appended (like
+/// `cmd.exe`, which skips npm's extensionless shell script next to `npm.cmd`)
+fn executable_names_with(cmd: &str, path_extensions: Option<&str>) -> Vec<String> {
+    let Some(path_extensions) = path_extensions else {
+        return vec![cmd.to_string()];
+    };
+    let extensions: Vec<&str> = path_extensions
+        .split(';')
+        .map(str::trim)
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    let has_extension = Path::new(cmd)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            extensions
+                .iter()
+                .any(|known| known.trim_start_matches('.').eq_ignore_ascii_case(ext))
+        });
+    if has_extension {
+        return vec![cmd.to_string()];
+    }
+    extensions
+        .iter()
+        .map(|ext| format!("{}{}", cmd, ext.to_lowercase()))
+        .collect()
+}
+
+/// The executable for a command name in one directory
+pub(crate) fn find_in_dir(dir: &Path, cmd: &str) -> Option<PathBuf> {
+    executable_names(cmd)
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|path| is_executable(path))
+}
+
+/// The program to spawn for a command: on Windows its full path, as process
+/// spawning only appends `.exe` and would miss `.cmd`/`.bat` shims (npm,
+/// yarn, pnpm); elsewhere the command itself
+pub(crate) fn spawn_program(program: &str) -> String {
+    if cfg!(windows) {
+        ToolDetector::which_command(program).unwrap_or_else(|| program.to_string())
+    } else {
+        program.to_string()
     }
 }
 
@@ -553,7 +2015,8 @@ fn truncate_string(s: &str, max_len: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::ToolConfig;
+    use crate::config::{ToolConfig, ToolOrigin};
+    use std::time::Duration;
 
     // Helper to create a simple ToolDetector for testing
     fn test_detector() -> ToolDetector {
@@ -570,6 +2033,48 @@ mod tests {
         assert_eq!(version, Some("3.11.4".to_string()));
     }
 
+    fn text_output(text: &str) -> CommandOutput {
+        CommandOutput {
+            text: text.to_string(),
+            stdout: text.to_string(),
+            stderr: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_version_named_groups() {
+        let detector = test_detector();
+        let output = "openjdk 21.0.2 2024-01-16\nOpenJDK Runtime Environment Temurin-21.0.2+13 (build 21.0.2+13)";
+        let regex = r"(?s)openjdk (?P<version>\d+\.\d+\.\d+).*\n(?P<runtime>\S+) Runtime.*\+(?P<build>\d+)\)";
+        assert_eq!(
+            detector.parse_version(output, regex),
+            Some("21.0.2".to_string())
+        );
+
+        let tool_config = ToolConfig {
+            name: "Java".to_string(),
+            command: "java --version".to_string(),
+            parse_regex: Some(regex.to_string()),
+            parse_template: Some("{version}+{build} ({runtime})".to_string()),
+            ..Default::default()
+        };
+        let info = detector.tool_from_output(&tool_config, Ok(text_output(output)));
+        assert_eq!(info.version.as_deref(), Some("21.0.2"));
+        assert_eq!(info.build.as_deref(), Some("13"));
+        assert_eq!(info.runtime.as_deref(), Some("OpenJDK"));
+        assert_eq!(info.display_version.as_deref(), Some("21.0.2+13 (OpenJDK)"));
+        assert_eq!(info.version_label(), Some("21.0.2+13 (OpenJDK)"));
+
+        // Without a template the version is displayed
+        let tool_config = ToolConfig {
+            parse_template: None,
+            ..tool_config
+        };
+        let info = detector.tool_from_output(&tool_config, Ok(text_output(output)));
+        assert_eq!(info.display_version, None);
+        assert_eq!(info.version_label(), Some("21.0.2"));
+    }
+
     #[test]
     fn test_parse_version_node() {
         let detector = test_detector();
@@ -682,6 +2187,28 @@ mod tests {
         assert!(!detector.config().effective_tools().is_empty());
     }
 
+    #[test]
+    fn test_set_config_drops_cached_versions() {
+        let echo = |version: &str| -> Config {
+            toml::from_str(&format!(
+                "use_default_tools = false\n\n[[custom_tools]]\nname = \"Echo\"\ncommand = \"echo {}\"\nenabled = true\n",
+                version
+            ))
+            .unwrap()
+        };
+        let mut detector = ToolDetector::new(echo("1.0.0"));
+        assert_eq!(
+            detector.detect_all().tools[0].version.as_deref(),
+            Some("1.0.0")
+        );
+
+        detector.set_config(echo("2.0.0"));
+        assert_eq!(
+            detector.detect_all().tools[0].version.as_deref(),
+            Some("2.0.0")
+        );
+    }
+
     // detect_tool tests
     #[test]
     fn test_detect_tool_unavailable() {
@@ -693,11 +2220,18 @@ mod tests {
             icon: Some("❓".to_string()),
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         let info = detector.detect_tool(&tool_config);
         assert!(!info.available);
         assert!(info.error.is_some());
+        assert_eq!(
+            info.failure,
+            Some(ToolError::NotFound {
+                program: "nonexistent_command_12345".to_string()
+            })
+        );
         assert_eq!(info.name, "NonExistent");
     }
 
@@ -711,6 +2245,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: Some("echo".to_string()),
+            ..Default::default()
         };
 
         let info = detector.detect_tool(&tool_config);
@@ -729,6 +2264,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         let info = detector.detect_tool(&tool_config);
@@ -737,28 +2273,206 @@ mod tests {
     }
 
     // run_version_command tests
+    fn command_tool(command: &str) -> ToolConfig {
+        ToolConfig {
+            name: "Test".to_string(),
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_run_version_command_empty() {
         let detector = test_detector();
-        let result = detector.run_version_command("");
+        let result = detector.run_version_command(&command_tool(""));
         assert!(result.is_err());
     }
 
     #[test]
     fn test_run_version_command_success() {
         let detector = test_detector();
-        let result = detector.run_version_command("echo test");
+        let result = detector.run_version_command(&command_tool("echo test"));
         assert!(result.is_ok());
-        assert_eq!(result.unwrap().trim(), "test");
+        assert_eq!(result.unwrap().text.trim(), "test");
     }
 
     #[test]
     fn test_run_version_command_not_found() {
         let detector = test_detector();
-        let result = detector.run_version_command("nonexistent_cmd_xyz --version");
+        let result = detector.run_version_command(&command_tool("nonexistent_cmd_xyz --version"));
         assert!(result.is_err());
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_run_version_command_exit_codes_and_streams() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("oldjava");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\necho 'usage: oldjava'\necho 'java version 1.8.0_392' >&2\nexit 1\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let detector = test_detector();
+        let mut tool = command_tool(&format!("{} -version", script.display()));
+        let err = detector.run_version_command(&tool).unwrap_err();
+        assert_eq!(
+            err.tool_error(),
+            Some(&ToolError::NonZeroExit {
+                program: script.to_string_lossy().into_owned(),
+                code: Some(1),
+                stderr: "java version 1.8.0_392".to_string(),
+            })
+        );
+        assert!(err.to_string().contains("java version 1.8.0_392"));
+
+        tool.accept_exit_codes = vec![1];
+        let output = detector.run_version_command(&tool).unwrap().text;
+        assert_eq!(output.trim(), "usage: oldjava");
+
+        tool.version_stream = Some(VersionStream::Stderr);
+        let output = detector.run_version_command(&tool).unwrap().text;
+        assert_eq!(output.trim(), "java version 1.8.0_392");
+
+        tool.version_stream = Some(VersionStream::Both);
+        let output = detector.run_version_command(&tool).unwrap().text;
+        assert!(output.contains("usage: oldjava") && output.contains("1.8.0_392"));
+
+        tool.version_stream = Some(VersionStream::Stdout);
+        tool.accept_exit_codes = vec![2];
+        assert!(detector.run_version_command(&tool).is_err());
+    }
+
+    /// A tool whose command fails `failures` times before printing 1.2.3
+    #[cfg(unix)]
+    fn flaky_tool(dir: &Path, failures: u32) -> ToolConfig {
+        use std::os::unix::fs::PermissionsExt;
+        let script = dir.join("flaky");
+        let count = dir.join("count");
+        std::fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\nn=$(cat {count} 2>/dev/null || echo 0)\necho $((n + 1)) > {count}\n[ $n -ge {failures} ] || exit 1\necho 1.2.3\n",
+                count = count.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        ToolConfig {
+            retry_delay_ms: Some(1),
+            ..command_tool(&script.to_string_lossy())
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_retries_transient_failures() {
+        let detector = test_detector();
+
+        let dir = tempfile::tempdir().unwrap();
+        let tool = flaky_tool(dir.path(), 2);
+        assert!(detector.run_version_command(&tool).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let tool = ToolConfig {
+            retries: Some(2),
+            ..flaky_tool(dir.path(), 2)
+        };
+        let diagnostic = detector.diagnose_tool(&tool);
+        assert_eq!(diagnostic.status, DiagnosticStatus::Ok);
+        assert_eq!(diagnostic.version.as_deref(), Some("1.2.3"));
+        assert_eq!(diagnostic.retries, Some(2));
+
+        // Out of retries
+        let dir = tempfile::tempdir().unwrap();
+        let tool = ToolConfig {
+            retries: Some(1),
+            ..flaky_tool(dir.path(), 2)
+        };
+        let diagnostic = detector.diagnose_tool(&tool);
+        assert_eq!(diagnostic.status, DiagnosticStatus::Error);
+        assert_eq!(diagnostic.retries, Some(1));
+    }
+
+    #[test]
+    fn test_parse_json_pointer() {
+        let detector = test_detector();
+        let kubectl = r#"{
+  "clientVersion": {"major": "1", "minor": "29", "gitVersion": "v1.29.2", "platform": "linux/amd64"},
+  "kustomizeVersion": "v5.0.4-0.20230601165947-6ce0bf390ce3"
+}"#;
+        let tool_config = ToolConfig {
+            name: "kubectl".to_string(),
+            command: "kubectl version --client --output json".to_string(),
+            parse_regex: Some(r"v?(\d+\.\d+(?:\.\d+)?)".to_string()),
+            parse_json_pointer: Some("/clientVersion/gitVersion".to_string()),
+            ..Default::default()
+        };
+        let info = detector.tool_from_output(&tool_config, Ok(text_output(kubectl)));
+        assert_eq!(info.version.as_deref(), Some("1.29.2"));
+
+        // Without a regex the value is the version
+        let terraform = ToolConfig {
+            name: "terraform".to_string(),
+            command: "terraform version -json".to_string(),
+            parse_json_pointer: Some("/terraform_version".to_string()),
+            ..Default::default()
+        };
+        let output = r#"{"terraform_version": "1.7.3", "platform": "linux_amd64"}"#;
+        let info = detector.tool_from_output(&terraform, Ok(text_output(output)));
+        assert_eq!(info.version.as_deref(), Some("1.7.3"));
+
+        let info = detector.tool_from_output(&terraform, Ok(text_output("Terraform v1.7.3")));
+        assert!(!info.available);
+        assert!(info.error.unwrap().contains("output is not JSON"));
+        let info = detector.tool_from_output(&tool_config, Ok(text_output(output)));
+        assert_eq!(
+            info.error.as_deref(),
+            Some("parse_json_pointer: no version at /clientVersion/gitVersion")
+        );
+        assert_eq!(
+            info.failure,
+            Some(ToolError::ParseFailed {
+                parser: "parse_json_pointer".to_string(),
+                output: output.to_string(),
+                reason: "parse_json_pointer: no version at /clientVersion/gitVersion".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let detector = test_detector();
+        let tool_config = ToolConfig {
+            name: "Deno".to_string(),
+            command: "deno --version --json".to_string(),
+            parse_script: Some(
+                r#"let v = parse_json(stdout); #{ version: v.deno, runtime: "v8 " + v.v8 }"#
+                    .to_string(),
+            ),
+            parse_template: Some("{version} ({runtime})".to_string()),
+            ..Default::default()
+        };
+        let info = detector.tool_from_output(
+            &tool_config,
+            Ok(text_output(r#"{"deno": "1.40.0", "v8": "12.1.285.6"}"#)),
+        );
+        if cfg!(feature = "scripting") {
+            assert_eq!(info.version.as_deref(), Some("1.40.0"));
+            assert_eq!(info.runtime.as_deref(), Some("v8 12.1.285.6"));
+            assert_eq!(info.version_label(), Some("1.40.0 (v8 12.1.285.6)"));
+        } else {
+            assert!(!info.available);
+            assert!(info
+                .error
+                .unwrap()
+                .contains("parse_script requires the `scripting` feature"));
+        }
+    }
+
     // Environment variable tests
     #[test]
     fn test_get_virtual_env_none() {
@@ -817,6 +2531,27 @@ mod tests {
         assert!(info.tools.is_empty());
     }
 
+    #[cfg(feature = "sysinfo")]
+    #[test]
+    fn test_get_system_info_swap_and_cores() {
+        let config = Config {
+            extras: crate::config::ExtrasConfig {
+                system_swap: true,
+                system_cpu_cores: true,
+                ..Default::default()
+            },
+            ..Config::default()
+        };
+        let detector = ToolDetector::new(config);
+        let sys = detector.get_system_info().unwrap();
+
+        assert!(sys.swap_total_gb.is_some());
+        assert!(!sys.cpu_per_core.unwrap().is_empty());
+        // Not requested, so not collected
+        assert!(sys.memory_percent.is_none());
+        assert!(sys.cpu_percent.is_none());
+    }
+
     // --- diagnose_tool tests ---
 
     #[test]
@@ -829,6 +2564,7 @@ mod tests {
             icon: Some("T".to_string()),
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         let diag = detector.diagnose_tool(&tool_config);
@@ -850,6 +2586,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         let diag = detector.diagnose_tool(&tool_config);
@@ -867,6 +2604,7 @@ mod tests {
             icon: Some("?".to_string()),
             enabled: false,
             short_name: None,
+            ..Default::default()
         };
 
         let diag = detector.diagnose_tool(&tool_config);
@@ -888,6 +2626,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         let diag = detector.diagnose_tool(&tool_config);
@@ -933,6 +2672,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         });
         config.custom_tools.push(ToolConfig {
             name: "BadTool".to_string(),
@@ -941,6 +2681,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         });
 
         let detector = ToolDetector::new(config);
@@ -949,6 +2690,127 @@ mod tests {
         assert_eq!(summary.total, 2);
         assert_eq!(summary.ok_count, 1);
         assert_eq!(summary.error_count, 1);
+        let provenance = summary.tools[0].provenance.as_ref().unwrap();
+        assert_eq!(provenance.origin, crate::config::ToolOrigin::CustomTools);
+    }
+
+    #[test]
+    fn test_diagnose_tools() {
+        let detector = ToolDetector::new(Config {
+            use_default_tools: false,
+            custom_tools: vec![
+                ToolConfig {
+                    name: "First".to_string(),
+                    command: "echo 1.0.0".to_string(),
+                    short_name: Some("one".to_string()),
+                    ..Default::default()
+                },
+                ToolConfig {
+                    name: "Second".to_string(),
+                    command: "echo 2.0.0".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Config::default()
+        });
+
+        let summary = detector.diagnose_tools(&["ONE".to_string()]).unwrap();
+        assert_eq!(summary.total, 1);
+        assert_eq!(summary.tools[0].name, "First");
+        assert!(detector.diagnose_tools(&["third".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_attach_diagnostics() {
+        let detector = ToolDetector::new(Config {
+            use_default_tools: false,
+            custom_tools: vec![ToolConfig {
+                name: "Echo".to_string(),
+                command: "echo 1.0.0".to_string(),
+                ..Default::default()
+            }],
+            ..Config::default()
+        });
+        let mut info = ToolboxInfo::new();
+        info.tools = vec![
+            ToolInfo::available("Echo".to_string(), "1.0.0".to_string()),
+            ToolInfo::available("Plugin".to_string(), "2.0.0".to_string()),
+        ];
+
+        detector.attach_diagnostics(&mut info);
+        let diagnostic = info.tools[0].diagnostic.as_ref().unwrap();
+        assert_eq!(diagnostic.status, DiagnosticStatus::Ok);
+        assert_eq!(diagnostic.command, "echo 1.0.0");
+        assert!(diagnostic.provenance.is_some());
+        assert!(info.tools[1].diagnostic.is_none());
+    }
+
+    #[test]
+    fn test_explain_tool() {
+        let tool = ToolConfig {
+            name: "Echo".to_string(),
+            command: "echo echo 1.2.3 (stable)".to_string(),
+            parse_regex: Some(r"(?P<version>\d+\.\d+)\.(\d+)".to_string()),
+            ..Default::default()
+        };
+        let mut detector = ToolDetector::new(Config {
+            use_default_tools: false,
+            custom_tools: vec![tool.clone()],
+            ..Config::default()
+        });
+
+        let explanation = detector.explain_tool("echo").unwrap();
+        assert_eq!(explanation.command_line, "echo echo 1.2.3 (stable)");
+        assert_eq!(explanation.exit_code, Some(0));
+        assert_eq!(explanation.stdout, "echo 1.2.3 (stable)\n");
+        assert_eq!(
+            explanation.captures,
+            Some(BTreeMap::from([
+                ("0".to_string(), "1.2.3".to_string()),
+                ("2".to_string(), "3".to_string()),
+                ("version".to_string(), "1.2".to_string()),
+            ]))
+        );
+        assert_eq!(explanation.version.as_deref(), Some("1.2"));
+        assert_eq!(explanation.provenance.origin, ToolOrigin::CustomTools);
+        assert!(explanation.cache.enabled);
+        assert_eq!(explanation.cache.entry, None);
+
+        detector.detect_tool(&tool);
+        let entry = detector.explain_tool("Echo").unwrap().cache.entry.unwrap();
+        assert_eq!(entry.version.as_deref(), Some("1.2"));
+        assert!(!entry.expired);
+        assert!(detector.explain_tool("other").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_explain_tool_failing() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("broken");
+        std::fs::write(&script, "#!/bin/sh\necho oops >&2\necho v9\nexit 3\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let detector = ToolDetector::new(Config {
+            use_default_tools: false,
+            custom_tools: vec![ToolConfig {
+                name: "Broken".to_string(),
+                command: script.to_string_lossy().into_owned(),
+                parse_regex: Some(r"(\d+)\.(\d+)".to_string()),
+                ..Default::default()
+            }],
+            ..Config::default()
+        })
+        .with_cache_disabled();
+
+        let explanation = detector.explain_tool("Broken").unwrap();
+        assert_eq!(explanation.exit_code, Some(3));
+        assert_eq!(explanation.stdout, "v9\n");
+        assert_eq!(explanation.stderr, "oops\n");
+        assert_eq!(explanation.captures, None);
+        assert_eq!(explanation.version, None);
+        assert!(explanation.error.unwrap().contains("oops"));
+        assert_eq!(explanation.cache, CacheState::default());
     }
 
     #[test]
@@ -1001,6 +2863,52 @@ mod tests {
         assert!(path.is_none());
     }
 
+    #[test]
+    fn test_which_command_absolute_path() {
+        let echo = ToolDetector::which_command("echo").unwrap();
+        assert_eq!(ToolDetector::which_command(&echo), Some(echo.clone()));
+        assert!(ToolDetector::which_command("/nonexistent/dir/echo").is_none());
+    }
+
+    #[test]
+    fn test_executable_names_with_pathext() {
+        let pathext = Some(".COM;.EXE;.BAT;.CMD");
+        assert_eq!(executable_names_with("rustc", None), ["rustc"]);
+        assert_eq!(
+            executable_names_with("npm", pathext),
+            ["npm.com", "npm.exe", "npm.bat", "npm.cmd"]
+        );
+        assert_eq!(executable_names_with("npm.CMD", pathext), ["npm.CMD"]);
+        // A dot that isn't an executable extension
+        assert_eq!(
+            executable_names_with("python3.12", Some(".EXE")),
+            ["python3.12.exe"]
+        );
+    }
+
+    #[test]
+    fn test_has_separator() {
+        assert!(has_separator("/usr/bin/echo"));
+        assert!(has_separator("./bin/tool"));
+        assert!(!has_separator("echo"));
+    }
+
+    #[test]
+    fn test_detect_tool_records_path() {
+        let detector = test_detector();
+        let tool_config = ToolConfig {
+            name: "Echo".to_string(),
+            command: "echo 1.0.0".to_string(),
+            parse_regex: None,
+            icon: None,
+            enabled: true,
+            short_name: None,
+            ..Default::default()
+        };
+        let info = detector.detect_tool_uncached(&tool_config);
+        assert!(info.path.unwrap().ends_with("echo"));
+    }
+
     // --- Cache integration tests ---
 
     #[test]
@@ -1013,12 +2921,15 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         // First call should be a miss
         let info1 = detector.detect_tool(&tool_config);
         assert!(info1.available);
         assert_eq!(info1.version, Some("1.0.0".to_string()));
+        assert!(info1.duration_ms.is_some());
+        assert!(!info1.cached);
 
         let cache = detector.cache().unwrap();
         assert_eq!(cache.hits(), 0);
@@ -1028,6 +2939,8 @@ mod tests {
         let info2 = detector.detect_tool(&tool_config);
         assert!(info2.available);
         assert_eq!(info2.version, Some("1.0.0".to_string()));
+        assert!(info2.cached);
+        assert!(info2.duration_ms.is_some());
 
         let cache = detector.cache().unwrap();
         assert_eq!(cache.hits(), 1);
@@ -1044,6 +2957,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         let info = detector.detect_tool(&tool_config);
@@ -1061,6 +2975,7 @@ mod tests {
             icon: None,
             enabled: true,
             short_name: None,
+            ..Default::default()
         };
 
         // Populate cache
@@ -1093,4 +3008,366 @@ mod tests {
         let detector = ToolDetector::new(config);
         assert_eq!(detector.cache().unwrap().default_ttl(), 60);
     }
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn test_detect_all_runs_wasm_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let wasm = dir.path().join("corp.wasm");
+        let output = r#"{"version": "4.2.0"}"#;
+        let module = wat::parse_str(format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "toolbox_alloc") (param i32) (result i32) (i32.const 1024))
+                (data (i32.const 16) "{}")
+                (func (export "toolbox_detect") (param i32 i32) (result i64)
+                    (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const {}))))"#,
+            output.replace('"', "\\\""),
+            output.len()
+        ))
+        .unwrap();
+        std::fs::write(&wasm, module).unwrap();
+
+        let mut config = Config {
+            use_default_tools: false,
+            ..Config::default()
+        };
+        config.detectors.external = false;
+        for (name, path) in [
+            ("corp", wasm.clone()),
+            ("broken", dir.path().join("none.wasm")),
+        ] {
+            config.plugins.insert(
+                name.to_string(),
+                crate::config::PluginConfig {
+                    path: path.to_string_lossy().into_owned(),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut detector = ToolDetector::new(config);
+        let info = detector.detect_all();
+        let tools: Vec<(&str, bool)> = info
+            .tools
+            .iter()
+            .map(|t| (t.name.as_str(), t.available))
+            .collect();
+        assert_eq!(tools, vec![("broken", false), ("corp", true)]);
+        assert_eq!(info.tools[1].version.as_deref(), Some("4.2.0"));
+    }
+
+    struct StaticDetector {
+        name: &'static str,
+        tools: Vec<(&'static str, &'static str)>,
+    }
+
+    impl Detector for StaticDetector {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn detect(&self, working_dir: Option<&str>) -> Result<Vec<ToolInfo>> {
+            if self.tools.is_empty() {
+                return Err(ToolboxError::CommandFailed(format!(
+                    "nothing in {}",
+                    working_dir.unwrap_or(".")
+                )));
+            }
+            Ok(self
+                .tools
+                .iter()
+                .map(|(name, version)| ToolInfo::available(name.to_string(), version.to_string()))
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_detect_all_merges_registered_detectors() {
+        let mut config = Config {
+            use_default_tools: false,
+            custom_tools: vec![ToolConfig {
+                name: "Echo".to_string(),
+                command: "echo 1.0.0".to_string(),
+                parse_regex: None,
+                icon: None,
+                enabled: true,
+                short_name: None,
+                ..Default::default()
+            }],
+            ..Config::default()
+        };
+        config.detectors.external = false;
+        let mut detector = ToolDetector::new(config).with_working_dir("/work".to_string());
+        detector.register(Box::new(StaticDetector {
+            name: "corp",
+            tools: vec![("Echo", "2.0.0"), ("Corp", "3.1.0")],
+        }));
+        detector.register(Box::new(StaticDetector {
+            name: "empty",
+            tools: Vec::new(),
+        }));
+
+        let info = detector.detect_all();
+        let tools: Vec<(&str, Option<&str>)> = info
+            .tools
+            .iter()
+            .map(|t| (t.name.as_str(), t.version.as_deref()))
+            .collect();
+        assert_eq!(
+            tools,
+            vec![
+                ("Echo", Some("2.0.0")),
+                ("Corp", Some("3.1.0")),
+                ("empty", None)
+            ]
+        );
+        assert_eq!(
+            info.tools[2].error.as_deref(),
+            Some("Command execution failed: nothing in /work")
+        );
+        assert!(info.tools.iter().all(|t| t.duration_ms.is_some()));
+    }
+
+    #[test]
+    fn test_detect_streaming_events() {
+        let mut config = Config {
+            use_default_tools: false,
+            custom_tools: ["1.0.0", "2.0.0"]
+                .iter()
+                .map(|version| ToolConfig {
+                    name: format!("Echo {}", version),
+                    command: format!("echo {}", version),
+                    parse_regex: None,
+                    icon: None,
+                    enabled: true,
+                    short_name: None,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Config::default()
+        };
+        config.detectors.external = false;
+        let mut detector = ToolDetector::new(config);
+        detector.register(Box::new(StaticDetector {
+            name: "corp",
+            tools: vec![("Echo 1.0.0", "1.0.1")],
+        }));
+
+        let mut lines = Vec::new();
+        let info = detector.detect_streaming(|event| {
+            lines.push(serde_json::to_value(event).unwrap());
+        });
+        let events: Vec<(&str, Option<&str>)> = lines
+            .iter()
+            .map(|line| (line["event"].as_str().unwrap(), line["version"].as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                ("tool", Some("1.0.0")),
+                ("tool", Some("2.0.0")),
+                ("tool", Some("1.0.1")),
+                ("done", None)
+            ]
+        );
+        assert_eq!(lines[0]["name"], "Echo 1.0.0");
+        assert_eq!(
+            lines[3]["tools"],
+            serde_json::to_value(&info.tools).unwrap()
+        );
+        assert_eq!(info.tools[0].version.as_deref(), Some("1.0.1"));
+    }
+
+    #[cfg(unix)]
+    fn sleep_tool(name: &str, seconds: &str) -> ToolConfig {
+        ToolConfig {
+            name: name.to_string(),
+            command: format!("sleep {}", seconds),
+            parse_regex: None,
+            icon: None,
+            enabled: true,
+            short_name: None,
+            ..Default::default()
+        }
+    }
+
+    /// Detector over `tools`, cancelled 100 ms from now
+    #[cfg(unix)]
+    fn cancelled_detector(tools: Vec<ToolConfig>) -> ToolDetector {
+        let mut config = Config {
+            use_default_tools: false,
+            custom_tools: tools,
+            ..Config::default()
+        };
+        config.detectors.external = false;
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        ToolDetector::new(config).with_cancellation(token)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_all_cancelled() {
+        let mut detector =
+            cancelled_detector(vec![sleep_tool("slow", "5"), sleep_tool("next", "5")]);
+        let mut events = 0;
+        let start = Instant::now();
+        let info = detector.detect_streaming(|_| events += 1);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(events, 3);
+        for tool in &info.tools {
+            assert!(!tool.available);
+            assert_eq!(tool.error.as_deref(), Some("Detection cancelled"));
+        }
+        assert!(detector.cache().unwrap().is_empty());
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[test]
+    fn test_detect_all_async_cancelled() {
+        let mut detector =
+            cancelled_detector(vec![sleep_tool("slow", "5"), sleep_tool("other", "5")]);
+        let start = Instant::now();
+        let info = block_on(detector.detect_all_async());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert_eq!(info.tools.len(), 2);
+        for tool in &info.tools {
+            assert_eq!(tool.error.as_deref(), Some("Detection cancelled"));
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[test]
+    fn test_detect_all_async_runs_concurrently() {
+        let mut config = Config {
+            use_default_tools: false,
+            custom_tools: ["a", "b", "c", "d"]
+                .iter()
+                .map(|name| sleep_tool(name, "0.3"))
+                .collect(),
+            ..Config::default()
+        };
+        config.detectors.external = false;
+
+        let mut detector = ToolDetector::new(config.clone()).with_concurrency(4);
+        let start = Instant::now();
+        let info = block_on(detector.detect_all_async());
+        assert!(start.elapsed() < Duration::from_millis(1000));
+        let names: Vec<&str> = info.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c", "d"]);
+        assert!(info.tools.iter().all(|t| t.available));
+
+        let mut detector = ToolDetector::new(config)
+            .with_cache_disabled()
+            .with_concurrency(1);
+        let start = Instant::now();
+        block_on(detector.detect_all_async());
+        assert!(start.elapsed() >= Duration::from_millis(1200));
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[test]
+    fn test_detect_all_async_timeout_and_cache() {
+        let mut config = Config {
+            use_default_tools: false,
+            custom_tools: vec![
+                sleep_tool("slow", "5"),
+                ToolConfig {
+                    name: "Echo".to_string(),
+                    command: "echo 1.0.0".to_string(),
+                    parse_regex: None,
+                    icon: None,
+                    enabled: true,
+                    short_name: None,
+                    ..Default::default()
+                },
+            ],
+            ..Config::default()
+        };
+        config.detectors.external = false;
+        let mut detector = ToolDetector::new(config).with_timeout(Duration::from_millis(100));
+
+        let start = Instant::now();
+        let info = block_on(detector.detect_all_async());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(!info.tools[0].available);
+        assert_eq!(
+            info.tools[0].error.as_deref(),
+            Some("sleep: timed out after 100 ms")
+        );
+        assert_eq!(
+            info.tools[0].failure,
+            Some(ToolError::Timeout {
+                program: "sleep".to_string(),
+                timeout_ms: 100
+            })
+        );
+        assert_eq!(info.tools[1].version.as_deref(), Some("1.0.0"));
+        assert!(!info.tools[1].cached);
+
+        // The second run reads the cache, like detect_all
+        let info = block_on(detector.detect_all_async());
+        assert!(info.tools[1].cached);
+    }
+
+    #[cfg(all(unix, feature = "tokio"))]
+    #[test]
+    fn test_detect_all_async_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config {
+            use_default_tools: false,
+            custom_tools: vec![ToolConfig {
+                retries: Some(3),
+                ..flaky_tool(dir.path(), 2)
+            }],
+            ..Config::default()
+        };
+        config.detectors.external = false;
+        let mut detector = ToolDetector::new(config).with_cache_disabled();
+
+        let info = block_on(detector.detect_all_async());
+        assert_eq!(info.tools[0].version.as_deref(), Some("1.2.3"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_which_all() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let linked = second.path().join("linked");
+        std::os::unix::fs::symlink(first.path(), &linked).unwrap();
+        for dir in [first.path(), second.path()] {
+            let tool = dir.join("tool");
+            std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+            std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        std::fs::write(second.path().join("data"), "").unwrap();
+
+        let path_var =
+            std::env::join_paths([first.path(), linked.as_path(), second.path()]).unwrap();
+        assert_eq!(
+            which_all("tool", &path_var),
+            vec![
+                first.path().join("tool").to_string_lossy().into_owned(),
+                second.path().join("tool").to_string_lossy().into_owned(),
+            ]
+        );
+        assert!(which_all("data", &path_var).is_empty());
+        assert!(which_all("", &path_var).is_empty());
+    }
 }
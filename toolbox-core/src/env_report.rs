@@ -0,0 +1,262 @@
+//! Doctor's environment section
+//!
+//! Most "wrong version" reports come down to the environment rather than the
+//! tool: PATH order, a version manager whose shims aren't in PATH, a direnv
+//! environment that isn't loaded. The report lists the PATH entries with the
+//! tools resolving from each, the activation status of the version managers
+//! and the environment variables that affect tool resolution.
+
+use crate::detector::find_in_dir;
+use crate::info::{EnvironmentReport, ManagerStatus, PathEntry, ToolDiagnostic};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Environment variables shown in the report when set
+pub const VARIABLES: &[&str] = &[
+    "SHELL",
+    "ASDF_DIR",
+    "ASDF_DATA_DIR",
+    "MISE_DATA_DIR",
+    "MISE_SHELL",
+    "DIRENV_DIR",
+    "NVM_DIR",
+    "NVM_BIN",
+    "PYENV_ROOT",
+    "PYENV_VERSION",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_HOME",
+    "GOROOT",
+    "GOPATH",
+    "JAVA_HOME",
+    "VIRTUAL_ENV",
+    "CONDA_PREFIX",
+    "CONDA_DEFAULT_ENV",
+];
+
+/// Build the report from PATH, the tool diagnostics (for the tools resolving
+/// from each entry) and the environment
+pub fn report(
+    path_var: &OsStr,
+    diagnostics: &[ToolDiagnostic],
+    home: Option<&Path>,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> EnvironmentReport {
+    let dirs: Vec<PathBuf> = std::env::split_paths(path_var)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect();
+    let path = dirs
+        .iter()
+        .enumerate()
+        .map(|(i, dir)| {
+            let duplicate = dirs[..i].contains(dir);
+            // Tools resolve from the first occurrence
+            let tools = diagnostics
+                .iter()
+                .filter(|diag| {
+                    !duplicate
+                        && diag
+                            .command_path
+                            .as_deref()
+                            .and_then(|path| Path::new(path).parent())
+                            == Some(dir.as_path())
+                })
+                .map(|diag| diag.name.clone())
+                .collect();
+            PathEntry {
+                dir: dir.to_string_lossy().into_owned(),
+                exists: dir.is_dir(),
+                duplicate,
+                tools,
+            }
+        })
+        .collect();
+
+    let variables = VARIABLES
+        .iter()
+        .filter_map(|var| {
+            let value = env(var).filter(|value| !value.is_empty())?;
+            Some((var.to_string(), value))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    EnvironmentReport {
+        path,
+        managers: managers(&dirs, home, env),
+        variables,
+    }
+}
+
+/// Activation status of asdf, mise, direnv and nvm
+fn managers(
+    dirs: &[PathBuf],
+    home: Option<&Path>,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Vec<ManagerStatus> {
+    let var = |name: &str| env(name).filter(|value| !value.is_empty());
+    let in_path = |program: &str| dirs.iter().any(|dir| find_in_dir(dir, program).is_some());
+    let data_dir =
+        |var_name: &str, default: Option<PathBuf>| var(var_name).map(PathBuf::from).or(default);
+
+    let shims_status = |name: &str, data_dir: Option<PathBuf>| {
+        let shims = data_dir.map(|dir| dir.join("shims"));
+        let installed = in_path(name) || shims.as_ref().is_some_and(|shims| shims.is_dir());
+        let active = shims.as_ref().is_some_and(|shims| dirs.contains(shims));
+        let detail = shims.map(|shims| {
+            if active {
+                format!("{} in PATH", shims.display())
+            } else {
+                format!("{} not in PATH", shims.display())
+            }
+        });
+        (installed, active, detail)
+    };
+
+    let (installed, active, detail) = shims_status(
+        "asdf",
+        data_dir("ASDF_DATA_DIR", home.map(|home| home.join(".asdf"))),
+    );
+    let asdf = ManagerStatus {
+        name: "asdf".to_string(),
+        installed,
+        active,
+        detail: installed.then_some(detail).flatten(),
+    };
+
+    let mise_default = var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.map(|home| home.join(".local/share")))
+        .map(|dir| dir.join("mise"));
+    let (installed, shims_active, detail) =
+        shims_status("mise", data_dir("MISE_DATA_DIR", mise_default));
+    // `mise activate` puts installs in PATH instead of shims
+    let activated = var("MISE_SHELL");
+    let mise = ManagerStatus {
+        name: "mise".to_string(),
+        installed: installed || activated.is_some(),
+        active: shims_active || activated.is_some(),
+        detail: match activated {
+            Some(shell) => Some(format!("activated in {}", shell)),
+            None => installed.then_some(detail).flatten(),
+        },
+    };
+
+    let loaded = var("DIRENV_DIR");
+    let direnv = ManagerStatus {
+        name: "direnv".to_string(),
+        installed: in_path("direnv"),
+        active: loaded.is_some(),
+        detail: match loaded {
+            // direnv prefixes the directory with `-`
+            Some(dir) => Some(format!("loaded {}", dir.trim_start_matches('-'))),
+            None => in_path("direnv").then(|| "no .envrc loaded".to_string()),
+        },
+    };
+
+    let nvm_bin = var("NVM_BIN");
+    let nvm = ManagerStatus {
+        name: "nvm".to_string(),
+        installed: var("NVM_DIR").is_some(),
+        active: nvm_bin.is_some(),
+        detail: nvm_bin,
+    };
+
+    vec![asdf, mise, direnv, nvm]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::DiagnosticStatus;
+
+    fn diagnostic(name: &str, path: &Path) -> ToolDiagnostic {
+        ToolDiagnostic {
+            name: name.to_string(),
+            icon: None,
+            status: DiagnosticStatus::Ok,
+            command: String::new(),
+            command_path: Some(path.to_string_lossy().into_owned()),
+            shim: None,
+            path_matches: Vec::new(),
+            source: None,
+            package: None,
+            pinned: None,
+            retries: None,
+            provenance: None,
+            version: None,
+            error_detail: None,
+            suggestion: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_report_path_entries() {
+        let bin = tempfile::tempdir().unwrap();
+        let missing = bin.path().join("missing");
+        let path_var = std::env::join_paths([bin.path(), missing.as_path(), bin.path()]).unwrap();
+        let diagnostics = vec![
+            diagnostic("Node", &bin.path().join("node")),
+            diagnostic("Go", Path::new("/elsewhere/go")),
+        ];
+
+        let report = report(&path_var, &diagnostics, None, &|_| None);
+        assert_eq!(report.path.len(), 3);
+        assert!(report.path[0].exists);
+        assert_eq!(report.path[0].tools, vec!["Node"]);
+        assert!(!report.path[1].exists);
+        assert!(report.path[2].duplicate);
+        assert!(report.path[2].tools.is_empty());
+        assert!(report.variables.is_empty());
+    }
+
+    #[test]
+    fn test_report_variables() {
+        let env = |var: &str| match var {
+            "SHELL" => Some("/bin/zsh".to_string()),
+            "GOPATH" => Some(String::new()),
+            "AWS_SECRET_ACCESS_KEY" => Some("secret".to_string()),
+            _ => None,
+        };
+        let report = report(OsStr::new(""), &[], None, &env);
+        assert_eq!(
+            report.variables,
+            BTreeMap::from([("SHELL".to_string(), "/bin/zsh".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_managers() {
+        let home = tempfile::tempdir().unwrap();
+        let asdf_shims = home.path().join(".asdf/shims");
+        let mise_shims = home.path().join(".local/share/mise/shims");
+        std::fs::create_dir_all(&asdf_shims).unwrap();
+        std::fs::create_dir_all(&mise_shims).unwrap();
+        let env = |var: &str| match var {
+            "DIRENV_DIR" => Some("-/work/app".to_string()),
+            _ => None,
+        };
+
+        let managers = managers(std::slice::from_ref(&asdf_shims), Some(home.path()), &env);
+        let by_name = |name: &str| managers.iter().find(|m| m.name == name).unwrap();
+
+        let asdf = by_name("asdf");
+        assert!(asdf.installed && asdf.active);
+        assert_eq!(
+            asdf.detail,
+            Some(format!("{} in PATH", asdf_shims.display()))
+        );
+        let mise = by_name("mise");
+        assert!(mise.installed && !mise.active);
+        assert_eq!(
+            mise.detail,
+            Some(format!("{} not in PATH", mise_shims.display()))
+        );
+        let direnv = by_name("direnv");
+        assert!(direnv.active);
+        assert_eq!(direnv.detail.as_deref(), Some("loaded /work/app"));
+        let nvm = by_name("nvm");
+        assert!(!nvm.installed && !nvm.active);
+    }
+}
@@ -1,5 +1,8 @@
 //! Error types for toolbox
 
+use crate::messages::{fill, tr, Message};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -13,18 +16,131 @@ pub enum ToolboxError {
     #[error("TOML parse error: {0}")]
     TomlParse(#[from] toml::de::Error),
 
+    #[error(transparent)]
+    ConfigParse(Box<ConfigParseError>),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("YAML parse error: {0}")]
+    YamlParse(#[from] serde_yaml::Error),
+
     #[error("Command execution failed: {0}")]
     CommandFailed(String),
 
+    #[error(transparent)]
+    Tool(#[from] ToolError),
+
     #[error("Version parse error: {0}")]
     VersionParse(String),
 
     #[error("Regex error: {0}")]
     Regex(#[from] regex::Error),
 
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+
+    #[error("Detection cancelled")]
+    Cancelled,
+
     #[cfg(feature = "git")]
     #[error("Git error: {0}")]
     Git(#[from] git2::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// A config file that doesn't parse, with its text so the error can be shown
+/// in context
+#[derive(Error, Debug)]
+#[error("{}: {error}", path.display())]
+pub struct ConfigParseError {
+    /// The config file
+    pub path: PathBuf,
+    /// Its text, which the error's span points into
+    pub content: String,
+    /// The parse error
+    pub error: toml::de::Error,
+}
+
+impl ToolboxError {
+    /// The structured failure of a tool's version command, if this is one
+    pub fn tool_error(&self) -> Option<&ToolError> {
+        match self {
+            Self::Tool(failure) => Some(failure),
+            _ => None,
+        }
+    }
+}
+
+/// Why a tool's version command failed, by kind (`failure` of a tool in JSON
+/// output)
+#[derive(Error, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolError {
+    /// The program isn't installed or isn't in PATH
+    #[error("{}", fill(Message::CommandNotFound, &[program]))]
+    NotFound { program: String },
+
+    /// The program exists but may not be run
+    #[error("{}", fill(Message::PermissionDenied, &[program]))]
+    PermissionDenied { program: String },
+
+    /// The command exited with a code not in `accept_exit_codes` (`None` if
+    /// it was killed by a signal)
+    #[error("{program}: {}", exit_detail(*.code, .stderr))]
+    NonZeroExit {
+        program: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+
+    /// The command ran longer than the detection timeout
+    #[error("{}", fill(Message::TimedOut, &[program, timeout_ms]))]
+    Timeout { program: String, timeout_ms: u64 },
+
+    /// `parse_json_pointer` or `parse_script` failed on the command output
+    #[error("{reason}")]
+    ParseFailed {
+        /// The config option that failed
+        parser: String,
+        output: String,
+        reason: String,
+    },
+
+    /// The command couldn't be run for another reason
+    #[error("{message}")]
+    Failed { message: String },
+}
+
+impl ToolError {
+    /// The failure to spawn `program`
+    pub fn spawn(program: &str, error: &std::io::Error) -> Self {
+        let program = program.to_string();
+        match error.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound { program },
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied { program },
+            _ => Self::Failed {
+                message: format!("{}: {}", program, error),
+            },
+        }
+    }
+}
+
+/// `exited with status 2: <stderr>`, or `killed by a signal`
+fn exit_detail(code: Option<i32>, stderr: &str) -> String {
+    let status = match code {
+        Some(code) => fill(Message::ExitedWithStatus, &[&code]),
+        None => tr(Message::KilledBySignal).to_string(),
+    };
+    if stderr.is_empty() {
+        status
+    } else {
+        format!("{}: {}", status, stderr)
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ToolboxError>;
@@ -39,6 +155,11 @@ mod tests {
         assert_eq!(err.to_string(), "Configuration error: bad config");
     }
 
+    #[test]
+    fn test_error_display_cancelled() {
+        assert_eq!(ToolboxError::Cancelled.to_string(), "Detection cancelled");
+    }
+
     #[test]
     fn test_error_display_command_failed() {
         let err = ToolboxError::CommandFailed("python: not found".to_string());
@@ -48,12 +169,81 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tool_error_display() {
+        let err = ToolboxError::from(ToolError::NonZeroExit {
+            program: "java".to_string(),
+            code: Some(1),
+            stderr: "Unrecognized option".to_string(),
+        });
+        assert_eq!(
+            err.to_string(),
+            "java: exited with status 1: Unrecognized option"
+        );
+        let err = ToolError::NonZeroExit {
+            program: "java".to_string(),
+            code: None,
+            stderr: String::new(),
+        };
+        assert_eq!(err.to_string(), "java: killed by a signal");
+        let err = ToolError::Timeout {
+            program: "sleep".to_string(),
+            timeout_ms: 100,
+        };
+        assert_eq!(err.to_string(), "sleep: timed out after 100 ms");
+    }
+
+    #[test]
+    fn test_tool_error_spawn() {
+        let not_found = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        assert_eq!(
+            ToolError::spawn("go", &not_found),
+            ToolError::NotFound {
+                program: "go".to_string()
+            }
+        );
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        assert!(matches!(
+            ToolError::spawn("go", &denied),
+            ToolError::PermissionDenied { .. }
+        ));
+        let other = std::io::Error::other("broken pipe");
+        assert_eq!(
+            ToolError::spawn("go", &other).to_string(),
+            "go: broken pipe"
+        );
+    }
+
+    #[test]
+    fn test_tool_error_json() {
+        let err = ToolError::NonZeroExit {
+            program: "node".to_string(),
+            code: Some(2),
+            stderr: "oops".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "non_zero_exit",
+                "program": "node",
+                "code": 2,
+                "stderr": "oops"
+            })
+        );
+    }
+
     #[test]
     fn test_error_display_version_parse() {
         let err = ToolboxError::VersionParse("no match".to_string());
         assert_eq!(err.to_string(), "Version parse error: no match");
     }
 
+    #[test]
+    fn test_error_display_plugin() {
+        let err = ToolboxError::Plugin("sdkman: out of fuel".to_string());
+        assert_eq!(err.to_string(), "Plugin error: sdkman: out of fuel");
+    }
+
     #[test]
     fn test_error_from_io() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file missing");
@@ -69,6 +259,20 @@ mod tests {
         assert!(err.to_string().contains("TOML parse error"));
     }
 
+    #[test]
+    fn test_error_from_json() {
+        let json_err = serde_json::from_str::<serde_json::Value>("{invalid").unwrap_err();
+        let err = ToolboxError::from(json_err);
+        assert!(err.to_string().contains("JSON error"));
+    }
+
+    #[test]
+    fn test_error_from_yaml() {
+        let yaml_err = serde_yaml::from_str::<serde_json::Value>("a: [b").unwrap_err();
+        let err = ToolboxError::from(yaml_err);
+        assert!(err.to_string().contains("YAML parse error"));
+    }
+
     #[test]
     fn test_error_from_regex() {
         let bad_regex = "[invalid(";
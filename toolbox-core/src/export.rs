@@ -0,0 +1,290 @@
+//! SBOM-style export of the detected toolchain
+//!
+//! Emits CycloneDX 1.5 or SPDX 2.3 JSON documents listing the available tools
+//! (name, version, path) so environment snapshots can feed supply-chain tooling.
+
+use crate::info::{ToolInfo, ToolboxInfo};
+use crate::trust::sha256_hex;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Supported SBOM document formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 (JSON)
+    CycloneDx,
+    /// SPDX 2.3 (JSON)
+    Spdx,
+}
+
+impl std::str::FromStr for SbomFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cyclonedx" => Ok(SbomFormat::CycloneDx),
+            "spdx" => Ok(SbomFormat::Spdx),
+            _ => Err(format!(
+                "Invalid SBOM format: {} (expected cyclonedx or spdx)",
+                s
+            )),
+        }
+    }
+}
+
+/// Build an SBOM document for the available tools, timestamped now
+pub fn to_sbom(info: &ToolboxInfo, format: SbomFormat) -> Value {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    // Sub-second time and the process id tell apart documents of the same second
+    let nonce = format!("{}.{}", now.as_nanos(), std::process::id());
+    to_sbom_at(info, format, now.as_secs(), &nonce)
+}
+
+fn to_sbom_at(info: &ToolboxInfo, format: SbomFormat, timestamp: u64, nonce: &str) -> Value {
+    let tools: Vec<&ToolInfo> = info.tools.iter().filter(|t| t.available).collect();
+    let ids = unique_slugs(&tools);
+    let created = format_rfc3339(timestamp);
+
+    match format {
+        SbomFormat::CycloneDx => {
+            let components: Vec<Value> = tools
+                .iter()
+                .zip(&ids)
+                .map(|(tool, id)| {
+                    let mut component = json!({
+                        "type": "application",
+                        "bom-ref": format!("tool:{}", id),
+                        "name": tool.name,
+                        "version": tool.version.as_deref().unwrap_or(""),
+                    });
+                    if let Some(ref path) = tool.path {
+                        component["properties"] =
+                            json!([{ "name": "toolbox:path", "value": path }]);
+                    }
+                    component
+                })
+                .collect();
+
+            json!({
+                "bomFormat": "CycloneDX",
+                "specVersion": "1.5",
+                "version": 1,
+                "metadata": {
+                    "timestamp": created,
+                    "tools": {
+                        "components": [{
+                            "type": "application",
+                            "name": "toolbox",
+                            "version": env!("CARGO_PKG_VERSION"),
+                        }]
+                    }
+                },
+                "components": components,
+            })
+        }
+        SbomFormat::Spdx => {
+            let packages: Vec<Value> = tools
+                .iter()
+                .zip(&ids)
+                .map(|(tool, id)| {
+                    let mut package = json!({
+                        "SPDXID": format!("SPDXRef-Tool-{}", id),
+                        "name": tool.name,
+                        "versionInfo": tool.version.as_deref().unwrap_or(""),
+                        "downloadLocation": "NOASSERTION",
+                        "filesAnalyzed": false,
+                    });
+                    if let Some(ref path) = tool.path {
+                        package["comment"] = json!(format!("path: {}", path));
+                    }
+                    package
+                })
+                .collect();
+            // Unique per document: a hash of the contents, the time and the nonce
+            let hash =
+                sha256_hex(format!("{}\n{}\n{}", timestamp, nonce, json!(packages)).as_bytes());
+
+            json!({
+                "spdxVersion": "SPDX-2.3",
+                "dataLicense": "CC0-1.0",
+                "SPDXID": "SPDXRef-DOCUMENT",
+                "name": "toolbox-environment",
+                "documentNamespace": format!("urn:toolbox:environment:{}:{}", timestamp, &hash[..32]),
+                "creationInfo": {
+                    "created": created,
+                    "creators": [format!("Tool: toolbox-{}", env!("CARGO_PKG_VERSION"))],
+                },
+                "packages": packages,
+            })
+        }
+    }
+}
+
+/// Slugs of the tools' names, numbered from the second use on ("c-", "c--2")
+/// so tools whose names map to the same slug get distinct identifiers
+fn unique_slugs(tools: &[&ToolInfo]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tools
+        .iter()
+        .map(|tool| {
+            let base = slug(&tool.name);
+            let id = (1..)
+                .map(|n| match n {
+                    1 => base.clone(),
+                    n => format!("{}-{}", base, n),
+                })
+                .find(|id| !seen.contains(id))
+                .unwrap_or(base);
+            seen.insert(id.clone());
+            id
+        })
+        .collect()
+}
+
+/// Identifier-safe form of a tool name ("AWS CLI" -> "aws-cli")
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Format a Unix timestamp as an RFC 3339 UTC string (e.g. 2024-01-31T12:00:00Z)
+pub fn format_rfc3339(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let secs = timestamp % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        (secs % 3_600) / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ToolboxInfo {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(
+            ToolInfo::available("AWS CLI".to_string(), "2.15.0".to_string())
+                .with_path(Some("/usr/local/bin/aws".to_string())),
+        );
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+        info.tools
+            .push(ToolInfo::unavailable("Ruby".to_string(), None));
+        info
+    }
+
+    #[test]
+    fn test_sbom_format_from_str() {
+        assert_eq!("CycloneDX".parse(), Ok(SbomFormat::CycloneDx));
+        assert_eq!("spdx".parse(), Ok(SbomFormat::Spdx));
+        assert!("swid".parse::<SbomFormat>().is_err());
+    }
+
+    #[test]
+    fn test_cyclonedx_document() {
+        let doc = to_sbom_at(&sample(), SbomFormat::CycloneDx, 0, "");
+        assert_eq!(doc["bomFormat"], "CycloneDX");
+        assert_eq!(doc["specVersion"], "1.5");
+        assert_eq!(doc["metadata"]["timestamp"], "1970-01-01T00:00:00Z");
+
+        let components = doc["components"].as_array().unwrap();
+        assert_eq!(components.len(), 2);
+        assert_eq!(components[0]["bom-ref"], "tool:aws-cli");
+        assert_eq!(components[0]["version"], "2.15.0");
+        assert_eq!(
+            components[0]["properties"][0]["value"],
+            "/usr/local/bin/aws"
+        );
+        assert!(components[1].get("properties").is_none());
+    }
+
+    #[test]
+    fn test_spdx_document() {
+        let doc = to_sbom_at(&sample(), SbomFormat::Spdx, 86_400, "");
+        assert_eq!(doc["spdxVersion"], "SPDX-2.3");
+        assert_eq!(doc["creationInfo"]["created"], "1970-01-02T00:00:00Z");
+
+        let packages = doc["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0]["SPDXID"], "SPDXRef-Tool-aws-cli");
+        assert_eq!(packages[0]["comment"], "path: /usr/local/bin/aws");
+        assert_eq!(packages[1]["versionInfo"], "1.75.0");
+    }
+
+    #[test]
+    fn test_spdx_namespace_is_unique() {
+        let namespace = |nonce: &str| {
+            to_sbom_at(&sample(), SbomFormat::Spdx, 86_400, nonce)["documentNamespace"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert!(namespace("1").starts_with("urn:toolbox:environment:86400:"));
+        assert_eq!(namespace("1"), namespace("1"));
+        assert_ne!(namespace("1"), namespace("2"));
+    }
+
+    #[test]
+    fn test_colliding_slugs_get_distinct_ids() {
+        let mut info = ToolboxInfo::new();
+        for name in ["C++", "C--", "C#", "c-2"] {
+            info.tools
+                .push(ToolInfo::available(name.to_string(), "1.0".to_string()));
+        }
+        let doc = to_sbom_at(&info, SbomFormat::Spdx, 0, "");
+        let ids: Vec<&str> = doc["packages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|package| package["SPDXID"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            ids,
+            [
+                "SPDXRef-Tool-c--",
+                "SPDXRef-Tool-c---2",
+                "SPDXRef-Tool-c-",
+                "SPDXRef-Tool-c-2"
+            ]
+        );
+        let doc = to_sbom_at(&info, SbomFormat::CycloneDx, 0, "");
+        assert_eq!(doc["components"][1]["bom-ref"], "tool:c---2");
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_rfc3339(1_706_702_400), "2024-01-31T12:00:00Z");
+    }
+}
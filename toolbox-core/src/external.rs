@@ -0,0 +1,422 @@
+//! External detector plugins
+//!
+//! Any executable named `toolbox-detect-<name>` in PATH is a detector. It runs
+//! in the detection directory (also passed as `$TOOLBOX_DIR`) and prints one
+//! tool or an array of tools as JSON:
+//!
+//! ```json
+//! {"name": "SDKMAN Java", "version": "21.0.2", "icon": "☕", "short_name": "java"}
+//! ```
+//!
+//! Only `version` is needed: `name` defaults to `<name>` and `available` to
+//! whether a version was given. The tools are merged into `ToolboxInfo.tools`,
+//! replacing a configured tool of the same name.
+//!
+//! Detectors run like version commands: sandboxed when `[sandbox]` is on,
+//! killed after the detection timeout or when the detection is cancelled.
+//! Which detectors PATH holds is cached ([`discover_cached`]) until PATH or
+//! one of its directories changes, so a prompt doesn't list every PATH
+//! directory on each refresh.
+
+use crate::cancel::{self, CancellationToken, Finished};
+use crate::error::{Result, ToolError, ToolboxError};
+use crate::info::ToolInfo;
+use crate::sandbox::Sandbox;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+/// File name prefix of detector executables
+pub const PREFIX: &str = "toolbox-detect-";
+
+/// A detector executable found in PATH
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalDetector {
+    /// Name after the prefix (`sdkman` for `toolbox-detect-sdkman`)
+    pub name: String,
+    /// Executable path
+    pub path: PathBuf,
+}
+
+/// Tool as printed by a detector
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExternalTool {
+    name: Option<String>,
+    version: Option<String>,
+    icon: Option<String>,
+    short_name: Option<String>,
+    path: Option<String>,
+    available: Option<bool>,
+    error: Option<String>,
+}
+
+/// A detector prints one tool or several
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ExternalOutput {
+    One(ExternalTool),
+    Many(Vec<ExternalTool>),
+}
+
+/// Detector executables in a PATH value, by name (the first one in PATH wins)
+pub fn discover(path_var: &OsStr) -> Vec<ExternalDetector> {
+    let mut found = BTreeMap::new();
+    for dir in std::env::split_paths(path_var) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(name) = file_name.to_str().and_then(|f| f.strip_prefix(PREFIX)) else {
+                continue;
+            };
+            // Windows executables carry an extension
+            let name = name.strip_suffix(".exe").unwrap_or(name);
+            let path = entry.path();
+            if !name.is_empty()
+                && !found.contains_key(name)
+                && crate::detector::is_executable(&path)
+            {
+                found.insert(name.to_string(), path);
+            }
+        }
+    }
+    found
+        .into_iter()
+        .map(|(name, path)| ExternalDetector { name, path })
+        .collect()
+}
+
+/// PATH value and directory stamps a discovery was made for
+#[derive(Debug, Serialize, Deserialize)]
+struct Discovery {
+    path_var: String,
+    /// Modification time of each PATH directory (None if missing)
+    dirs: Vec<Option<SystemTime>>,
+    detectors: Vec<ExternalDetector>,
+}
+
+impl Discovery {
+    fn new(path_var: &OsStr, detectors: Vec<ExternalDetector>) -> Self {
+        Self {
+            path_var: path_var.to_string_lossy().into_owned(),
+            dirs: dir_stamps(path_var),
+            detectors,
+        }
+    }
+
+    /// Whether PATH and its directories are as they were; adding or removing
+    /// a file changes its directory's modification time
+    fn is_current(&self, path_var: &OsStr) -> bool {
+        self.path_var == path_var.to_string_lossy() && self.dirs == dir_stamps(path_var)
+    }
+}
+
+fn dir_stamps(path_var: &OsStr) -> Vec<Option<SystemTime>> {
+    std::env::split_paths(path_var)
+        .map(|dir| std::fs::metadata(dir).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Default discovery cache: `<cache dir>/toolbox/detectors.json`
+pub fn cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("toolbox").join("detectors.json"))
+}
+
+/// [`discover`], reusing the result saved in `cache` while PATH and its
+/// directories are unchanged
+pub fn discover_cached(path_var: &OsStr, cache: &Path) -> Vec<ExternalDetector> {
+    let saved = std::fs::read_to_string(cache)
+        .ok()
+        .and_then(|json| serde_json::from_str::<Discovery>(&json).ok())
+        .filter(|saved| saved.is_current(path_var));
+    if let Some(saved) = saved {
+        return saved.detectors;
+    }
+    let discovery = Discovery::new(path_var, discover(path_var));
+    if let (Some(parent), Ok(json)) = (cache.parent(), serde_json::to_string(&discovery)) {
+        // Written aside and renamed, as other processes may be reading it
+        let tmp = cache.with_extension(format!("{}.tmp", std::process::id()));
+        let _ = std::fs::create_dir_all(parent)
+            .and_then(|_| std::fs::write(&tmp, json))
+            .and_then(|_| std::fs::rename(&tmp, cache));
+    }
+    discovery.detectors
+}
+
+impl ExternalDetector {
+    /// Run the detector in a directory like a version command: sandboxed if
+    /// `sandbox` is given, killed when `token` is cancelled or after
+    /// `timeout`. Failures become an unavailable tool
+    pub fn run(
+        &self,
+        working_dir: Option<&str>,
+        sandbox: Option<&Sandbox>,
+        token: Option<&CancellationToken>,
+        timeout: Duration,
+    ) -> Vec<ToolInfo> {
+        self.try_run(working_dir, sandbox, token, timeout)
+            .unwrap_or_else(|e| {
+                let mut tool = ToolInfo::unavailable(self.name.clone(), Some(e.to_string()));
+                tool.failure = e.tool_error().cloned();
+                vec![tool]
+            })
+    }
+
+    fn try_run(
+        &self,
+        working_dir: Option<&str>,
+        sandbox: Option<&Sandbox>,
+        token: Option<&CancellationToken>,
+        timeout: Duration,
+    ) -> Result<Vec<ToolInfo>> {
+        let program = self.path.to_string_lossy();
+        let mut cmd = match sandbox {
+            Some(sandbox) => sandbox.command(&program, &[]),
+            None => Command::new(&self.path),
+        };
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir).env("TOOLBOX_DIR", dir);
+        }
+        let output = match cancel::output_within(&mut cmd, token, Some(timeout))
+            .map_err(|e| ToolError::spawn(&program, &e))?
+        {
+            Finished::Exited(output) => output,
+            Finished::Cancelled => return Err(ToolboxError::Cancelled),
+            Finished::TimedOut => {
+                return Err(ToolError::Timeout {
+                    program: program.into_owned(),
+                    timeout_ms: timeout.as_millis() as u64,
+                }
+                .into())
+            }
+        };
+        if !output.status.success() {
+            return Err(ToolboxError::CommandFailed(format!(
+                "{}: {}",
+                self.path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let mut tools = parse_output(&self.name, &String::from_utf8_lossy(&output.stdout))?;
+        let path = self.path.to_string_lossy().into_owned();
+        for tool in &mut tools {
+            tool.path.get_or_insert_with(|| path.clone());
+        }
+        Ok(tools)
+    }
+}
+
+/// Tools in a detector's output (`name` names tools that don't)
+pub fn parse_output(name: &str, output: &str) -> Result<Vec<ToolInfo>> {
+    let parsed: ExternalOutput = serde_json::from_str(output.trim()).map_err(|e| {
+        ToolboxError::CommandFailed(format!("{}{}: invalid output: {}", PREFIX, name, e))
+    })?;
+    let tools = match parsed {
+        ExternalOutput::One(tool) => vec![tool],
+        ExternalOutput::Many(tools) => tools,
+    };
+    Ok(tools
+        .into_iter()
+        .map(|tool| {
+            let name = tool.name.unwrap_or_else(|| name.to_string());
+            let info = match (
+                tool.available.unwrap_or(tool.version.is_some()),
+                tool.version,
+            ) {
+                (true, version) => ToolInfo::available(name, version.unwrap_or_default()),
+                (false, _) => ToolInfo::unavailable(name, tool.error),
+            };
+            info.with_icon(tool.icon)
+                .with_short_name(tool.short_name)
+                .with_path(tool.path)
+        })
+        .collect())
+}
+
+/// Put detector results into a tool list, replacing tools of the same name
+pub fn merge(tools: &mut Vec<ToolInfo>, found: Vec<ToolInfo>) {
+    for tool in found {
+        match tools.iter_mut().find(|t| t.name == tool.name) {
+            Some(existing) => *existing = tool,
+            None => tools.push(tool),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_tool() {
+        let tools = parse_output(
+            "sdkman",
+            r#"{"name": "Java", "version": "21.0.2", "icon": "☕", "short_name": "java"}"#,
+        )
+        .unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "Java");
+        assert_eq!(tools[0].version.as_deref(), Some("21.0.2"));
+        assert_eq!(tools[0].short_name.as_deref(), Some("java"));
+        assert!(tools[0].available);
+    }
+
+    #[test]
+    fn test_parse_defaults_and_arrays() {
+        let tools = parse_output(
+            "corp",
+            r#"[{"version": "1.2.3"}, {"name": "vpn", "error": "not connected"}]"#,
+        )
+        .unwrap();
+        assert_eq!(tools[0].name, "corp");
+        assert!(tools[0].available);
+        assert_eq!(tools[1].name, "vpn");
+        assert!(!tools[1].available);
+        assert_eq!(tools[1].error.as_deref(), Some("not connected"));
+    }
+
+    #[test]
+    fn test_parse_invalid_output() {
+        let err = parse_output("corp", "java 21").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("toolbox-detect-corp: invalid output"));
+        assert!(parse_output("corp", r#"{"versoin": "1"}"#).is_err());
+    }
+
+    #[test]
+    fn test_merge_replaces_by_name() {
+        let mut tools = vec![
+            ToolInfo::available("Java".to_string(), "17".to_string()),
+            ToolInfo::available("Go".to_string(), "1.22".to_string()),
+        ];
+        merge(
+            &mut tools,
+            vec![
+                ToolInfo::available("Java".to_string(), "21".to_string()),
+                ToolInfo::available("Corp".to_string(), "3".to_string()),
+            ],
+        );
+        let versions: Vec<(&str, &str)> = tools
+            .iter()
+            .map(|t| (t.name.as_str(), t.version.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![("Java", "21"), ("Go", "1.22"), ("Corp", "3")]
+        );
+    }
+
+    #[cfg(unix)]
+    fn write_script(path: &std::path::Path, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::write(path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_and_run() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        write_script(
+            &first.path().join("toolbox-detect-sdk"),
+            r#"echo "{\"version\": \"1.0.0\", \"short_name\": \"$TOOLBOX_DIR\"}""#,
+        );
+        write_script(
+            &second.path().join("toolbox-detect-sdk"),
+            r#"echo '{"version": "2.0.0"}'"#,
+        );
+        write_script(&second.path().join("toolbox-detect-broken"), "exit 3");
+        // Not executable: ignored
+        std::fs::write(second.path().join("toolbox-detect-text"), "").unwrap();
+
+        let path_var = std::env::join_paths([
+            first.path(),
+            second.path(),
+            std::path::Path::new("/nonexistent"),
+        ])
+        .unwrap();
+        let detectors = discover(&path_var);
+        let names: Vec<&str> = detectors.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["broken", "sdk"]);
+        assert_eq!(detectors[1].path, first.path().join("toolbox-detect-sdk"));
+
+        let dir = second.path().to_str().unwrap();
+        let tools = detectors[1].run(Some(dir), None, None, Duration::from_secs(10));
+        assert_eq!(tools[0].name, "sdk");
+        assert_eq!(tools[0].version.as_deref(), Some("1.0.0"));
+        assert_eq!(tools[0].short_name.as_deref(), Some(dir));
+        assert_eq!(
+            tools[0].path.as_deref(),
+            first.path().join("toolbox-detect-sdk").to_str()
+        );
+
+        let tools = detectors[0].run(None, None, None, Duration::from_secs(10));
+        assert_eq!(tools[0].name, "broken");
+        assert!(!tools[0].available);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("toolbox-detect-slow");
+        write_script(&path, "sleep 5");
+        let detector = ExternalDetector {
+            name: "slow".to_string(),
+            path,
+        };
+
+        let start = std::time::Instant::now();
+        let tools = detector.run(None, None, None, Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(!tools[0].available);
+        assert!(matches!(
+            tools[0].failure,
+            Some(ToolError::Timeout {
+                timeout_ms: 100,
+                ..
+            })
+        ));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let tools = detector.run(None, None, Some(&token), Duration::from_secs(10));
+        assert!(!tools[0].available);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_cached() {
+        let bin = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = cache_dir.path().join("detectors.json");
+        let path_var = std::env::join_paths([bin.path()]).unwrap();
+
+        assert!(discover_cached(&path_var, &cache).is_empty());
+        assert!(cache.exists());
+
+        // A new detector changes the directory, so the cache is refreshed
+        write_script(&bin.path().join("toolbox-detect-sdk"), "echo '{}'");
+        let stamp = SystemTime::now() + Duration::from_secs(5);
+        std::fs::File::open(bin.path())
+            .unwrap()
+            .set_modified(stamp)
+            .unwrap();
+        let detectors = discover_cached(&path_var, &cache);
+        assert_eq!(detectors.len(), 1);
+
+        // Unchanged: the saved result is used as is
+        let mut saved: Discovery =
+            serde_json::from_str(&std::fs::read_to_string(&cache).unwrap()).unwrap();
+        saved.detectors[0].name = "from-cache".to_string();
+        std::fs::write(&cache, serde_json::to_string(&saved).unwrap()).unwrap();
+        assert_eq!(discover_cached(&path_var, &cache)[0].name, "from-cache");
+    }
+}
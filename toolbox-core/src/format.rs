@@ -0,0 +1,1955 @@
+//! Output formats for detection results
+//!
+//! Every format implements [`Formatter`] and is looked up by name in a
+//! [`FormatRegistry`], so new formats are added without touching
+//! [`ToolboxInfo`].
+
+use crate::color::{
+    ansi, fit_segments, render_powerline, render_powerline_multiline, render_powerline_split,
+    ResolvedTheme, Segment, SEPARATOR_RIGHT_THIN,
+};
+use crate::config::{PowerlineLayout, Section, DEFAULT_SECTIONS};
+use crate::detector::DetectEvent;
+use crate::error::Result;
+use crate::info::{ToolInfo, ToolboxInfo};
+use crate::messages::{tr, Message};
+
+/// An output format for detection results
+pub trait Formatter: Send + Sync {
+    /// Format name, as given to `--format`
+    fn name(&self) -> &str;
+
+    /// Render the complete output, including the trailing newline
+    fn format(&self, info: &ToolboxInfo, options: &FormatOptions) -> Result<String>;
+}
+
+/// Options passed to every [`Formatter`]
+#[derive(Debug, Clone, Copy)]
+pub struct FormatOptions<'a> {
+    /// Display options (the text format uses `compact`, `show_icons`,
+    /// `sections` and `show_unavailable`)
+    pub display: PowerlineOptions<'a>,
+    /// Colors for the powerline format
+    pub theme: &'a ResolvedTheme,
+}
+
+/// Plain text, one line per item
+pub struct TextFormatter;
+
+impl Formatter for TextFormatter {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn format(&self, info: &ToolboxInfo, options: &FormatOptions) -> Result<String> {
+        let display = &options.display;
+        Ok(format!(
+            "{}\n",
+            text(
+                info,
+                display.compact,
+                display.show_icons,
+                display.sections,
+                display.show_unavailable,
+                display.use_color
+            )
+        ))
+    }
+}
+
+/// Powerline-style colored segments
+pub struct PowerlineFormatter;
+
+impl Formatter for PowerlineFormatter {
+    fn name(&self) -> &str {
+        "powerline"
+    }
+
+    fn format(&self, info: &ToolboxInfo, options: &FormatOptions) -> Result<String> {
+        Ok(format!(
+            "{}\n",
+            powerline(info, &options.display, options.theme)
+        ))
+    }
+}
+
+/// The complete result as one JSON document
+pub struct JsonFormatter {
+    /// Indent the output
+    pub pretty: bool,
+}
+
+impl Formatter for JsonFormatter {
+    fn name(&self) -> &str {
+        if self.pretty {
+            "json-pretty"
+        } else {
+            "json"
+        }
+    }
+
+    fn format(&self, info: &ToolboxInfo, _options: &FormatOptions) -> Result<String> {
+        let json = if self.pretty {
+            serde_json::to_string_pretty(info)?
+        } else {
+            serde_json::to_string(info)?
+        };
+        Ok(format!("{}\n", json))
+    }
+}
+
+/// The final `done` event of streamed JSON lines
+///
+/// The `tool` events are printed by the caller while detection runs.
+pub struct JsonlFormatter;
+
+impl Formatter for JsonlFormatter {
+    fn name(&self) -> &str {
+        "jsonl"
+    }
+
+    fn format(&self, info: &ToolboxInfo, _options: &FormatOptions) -> Result<String> {
+        Ok(format!(
+            "{}\n",
+            serde_json::to_string(&DetectEvent::Done(info))?
+        ))
+    }
+}
+
+/// Markdown report
+pub struct MarkdownFormatter;
+
+impl Formatter for MarkdownFormatter {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn format(&self, info: &ToolboxInfo, _options: &FormatOptions) -> Result<String> {
+        Ok(markdown(info))
+    }
+}
+
+/// Standalone HTML report
+pub struct HtmlFormatter;
+
+impl Formatter for HtmlFormatter {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn format(&self, info: &ToolboxInfo, _options: &FormatOptions) -> Result<String> {
+        Ok(html(info))
+    }
+}
+
+/// One row per tool, separated by commas (`csv`) or tabs (`tsv`)
+pub struct DelimitedFormatter {
+    /// Field separator: `,` or `\t`
+    pub delimiter: char,
+}
+
+impl Formatter for DelimitedFormatter {
+    fn name(&self) -> &str {
+        if self.delimiter == '\t' {
+            "tsv"
+        } else {
+            "csv"
+        }
+    }
+
+    fn format(&self, info: &ToolboxInfo, _options: &FormatOptions) -> Result<String> {
+        Ok(delimited(info, self.delimiter))
+    }
+}
+
+/// Formatters keyed by name
+pub struct FormatRegistry {
+    formatters: Vec<Box<dyn Formatter>>,
+}
+
+impl FormatRegistry {
+    /// Registry with the built-in formats
+    pub fn new() -> Self {
+        let mut registry = Self {
+            formatters: Vec::new(),
+        };
+        registry.register(Box::new(TextFormatter));
+        registry.register(Box::new(PowerlineFormatter));
+        registry.register(Box::new(JsonFormatter { pretty: false }));
+        registry.register(Box::new(JsonFormatter { pretty: true }));
+        registry.register(Box::new(JsonlFormatter));
+        registry.register(Box::new(MarkdownFormatter));
+        registry.register(Box::new(HtmlFormatter));
+        registry.register(Box::new(DelimitedFormatter { delimiter: ',' }));
+        registry.register(Box::new(DelimitedFormatter { delimiter: '\t' }));
+        registry
+    }
+
+    /// Add a formatter, replacing any with the same name
+    pub fn register(&mut self, formatter: Box<dyn Formatter>) {
+        match self
+            .formatters
+            .iter_mut()
+            .find(|f| f.name() == formatter.name())
+        {
+            Some(existing) => *existing = formatter,
+            None => self.formatters.push(formatter),
+        }
+    }
+
+    /// Look up a formatter by name
+    pub fn get(&self, name: &str) -> Option<&dyn Formatter> {
+        self.formatters
+            .iter()
+            .find(|f| f.name() == name)
+            .map(|f| f.as_ref())
+    }
+
+    /// Names of all formatters, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.formatters.iter().map(|f| f.name()).collect()
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Virtual environment name with its details: `myenv (3.12.1, uv)`,
+/// `.venv (3.12.1, pip, conda: base, 4 envs)`
+fn venv_label(info: &ToolboxInfo) -> Option<String> {
+    let venv = info.virtual_env.as_ref()?;
+    let details: Vec<String> = [
+        info.virtual_env_python.clone(),
+        info.virtual_env_manager.clone(),
+        info.conda_env.as_ref().map(|env| format!("conda: {}", env)),
+        info.conda_env_count.map(|count| format!("{} envs", count)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    Some(if details.is_empty() {
+        venv.clone()
+    } else {
+        format!("{} ({})", venv, details.join(", "))
+    })
+}
+
+/// Format for display (simple text format)
+/// Sections are rendered in the given order; groups are divided by separators
+/// Unavailable tools are hidden unless `show_unavailable` is set (marked with ✗)
+/// With `use_color`, versions are colored by their drift from the snapshot
+pub fn text(
+    info: &ToolboxInfo,
+    compact: bool,
+    show_icons: bool,
+    sections: &[Section],
+    show_unavailable: bool,
+    use_color: bool,
+) -> String {
+    let separator = "─".repeat(15);
+    // Directory and git lines stay together when adjacent
+    let mut blocks: Vec<(bool, Vec<String>)> = Vec::new();
+
+    for section in sections {
+        let lines = match section {
+            Section::Dir => display_dir_lines(info, compact, show_icons),
+            Section::Git => display_git_lines(info, show_icons),
+            Section::Tools => {
+                display_tool_lines(info, compact, show_icons, show_unavailable, use_color)
+            }
+            Section::Venv => display_venv_lines(info, show_icons),
+            Section::System => display_system_lines(info, show_icons),
+        };
+        if lines.is_empty() {
+            continue;
+        }
+
+        let is_header = matches!(section, Section::Dir | Section::Git);
+        match blocks.last_mut() {
+            Some((true, block)) if is_header => block.extend(lines),
+            _ => blocks.push((is_header, lines)),
+        }
+    }
+
+    blocks
+        .into_iter()
+        .map(|(_, lines)| lines.join("\n"))
+        .collect::<Vec<_>>()
+        .join(&format!("\n{}\n", separator))
+}
+
+fn display_dir_lines(info: &ToolboxInfo, compact: bool, show_icons: bool) -> Vec<String> {
+    let Some(ref dir) = info.current_dir else {
+        return Vec::new();
+    };
+    let display_dir = if compact {
+        shorten_path(dir)
+    } else {
+        dir.clone()
+    };
+    if show_icons {
+        vec![format!(" 📂 {}", display_dir)]
+    } else {
+        vec![format!(" {}", display_dir)]
+    }
+}
+
+fn display_git_lines(info: &ToolboxInfo, show_icons: bool) -> Vec<String> {
+    let Some(ref git) = info.git else {
+        return Vec::new();
+    };
+    let branch_display = if show_icons {
+        format!(" 🌿 {}", git.branch)
+    } else {
+        format!(" {}", git.branch)
+    };
+
+    let mut suffixes = Vec::new();
+    if let Some(summary) = git.changes_summary() {
+        suffixes.push(summary);
+    }
+    if let Some(ab_summary) = git.ahead_behind_summary() {
+        suffixes.push(ab_summary);
+    }
+
+    if !suffixes.is_empty() {
+        vec![format!("{} ({})", branch_display, suffixes.join(" "))]
+    } else {
+        vec![branch_display]
+    }
+}
+
+fn display_tool_lines(
+    info: &ToolboxInfo,
+    compact: bool,
+    show_icons: bool,
+    show_unavailable: bool,
+    use_color: bool,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    for tool in &info.tools {
+        if !tool.available && !show_unavailable {
+            continue;
+        }
+
+        let name = if compact {
+            tool.short_name.as_ref().unwrap_or(&tool.name)
+        } else {
+            &tool.name
+        };
+
+        let version = if tool.available {
+            tool.version_label().unwrap_or("?")
+        } else {
+            MISSING_MARKER
+        };
+        let version = drift_colored(tool, version, use_color);
+
+        if show_icons {
+            let icon = tool.icon.as_deref().unwrap_or(" ");
+            lines.push(format!(" {} {} {}", icon, name, version));
+        } else {
+            lines.push(format!(" {} {}", name, version));
+        }
+    }
+    lines
+}
+
+/// A tool's version, colored by its snapshot drift with `use_color`
+pub fn drift_colored(tool: &ToolInfo, version: &str, use_color: bool) -> String {
+    match tool.drift {
+        Some(drift) if use_color => format!("{}{}{}", drift.ansi_fg(), version, ansi::RESET),
+        _ => version.to_string(),
+    }
+}
+
+fn display_venv_lines(info: &ToolboxInfo, show_icons: bool) -> Vec<String> {
+    let Some(venv) = venv_label(info) else {
+        return Vec::new();
+    };
+    if show_icons {
+        vec![format!(" 🐍 {}", venv)]
+    } else {
+        vec![format!(" {} {}", tr(Message::Venv), venv)]
+    }
+}
+
+fn display_system_lines(info: &ToolboxInfo, show_icons: bool) -> Vec<String> {
+    let Some(ref sys) = info.system else {
+        return Vec::new();
+    };
+    let mut lines = Vec::new();
+    if let Some(mem) = sys.memory_percent {
+        if show_icons {
+            lines.push(format!(" 💾 {:.0}%", mem));
+        } else {
+            lines.push(format!(" {} {:.0}%", tr(Message::Memory), mem));
+        }
+    }
+    if let Some(cpu) = sys.cpu_percent {
+        if show_icons {
+            lines.push(format!(" 🔥 {:.0}%", cpu));
+        } else {
+            lines.push(format!(" {} {:.0}%", tr(Message::Cpu), cpu));
+        }
+    }
+    if let Some(cores) = sys.cpu_sparkline() {
+        if show_icons {
+            lines.push(format!(" 📊 {}", cores));
+        } else {
+            lines.push(format!(" {} {}", tr(Message::Cores), cores));
+        }
+    }
+    if let Some(swap) = sys.swap_percent {
+        if show_icons {
+            lines.push(format!(" 🔄 {:.0}%", swap));
+        } else {
+            lines.push(format!(" {} {:.0}%", tr(Message::Swap), swap));
+        }
+    }
+    lines
+}
+
+/// Format for display as a powerline-style colored output
+/// If `options.single_line` is true, all segments are joined in one line,
+/// otherwise each segment is on its own line with colored background.
+/// With `options.max_width`, the output is fitted with [`fit_segments`]: in
+/// single-line mode lower-priority segments are dropped first, then segments
+/// are abbreviated (tools to icon and major version, the directory to its
+/// shortened path), and a last segment still too wide (or each row that is,
+/// in multiline mode) is truncated with `…`. Right-aligned segments are
+/// padded to `max_width` (without it they follow the left ones after a
+/// single space).
+pub fn powerline(info: &ToolboxInfo, options: &PowerlineOptions, theme: &ResolvedTheme) -> String {
+    let render = |segments: &[Segment]| {
+        let on_right = |segment: &&Segment| match options.layout {
+            PowerlineLayout::Left => false,
+            PowerlineLayout::Right => true,
+            PowerlineLayout::Split => segment
+                .section
+                .is_some_and(|section| options.right_sections.contains(&section)),
+        };
+        let (right, left): (Vec<Segment>, Vec<Segment>) =
+            segments.iter().cloned().partition(|s| on_right(&s));
+
+        if !options.single_line {
+            render_powerline_multiline(segments, options.use_color)
+        } else if right.is_empty() {
+            render_powerline(&left, options.use_color)
+        } else {
+            render_powerline_split(&left, &right, options.use_color, options.max_width)
+        }
+    };
+    let segments = powerline_segments(info, options, theme);
+    match options.max_width {
+        Some(max_width) => render(&fit_segments(
+            segments,
+            max_width,
+            options.single_line,
+            render,
+        )),
+        None => render(&segments),
+    }
+}
+
+/// Semantic powerline segments in section order, with icons, theme colors
+/// and priorities, for callers composing their own layouts
+pub fn segments(
+    info: &ToolboxInfo,
+    options: &PowerlineOptions,
+    theme: &ResolvedTheme,
+) -> Vec<Segment> {
+    powerline_segments(info, options, theme)
+}
+
+/// Priority of a section's segments: tools are kept longest, then git, the
+/// virtual environment and the directory
+fn section_priority(section: Section) -> u8 {
+    match section {
+        Section::Tools => 40,
+        Section::Git => 30,
+        Section::Venv => 20,
+        Section::Dir | Section::System => 10,
+    }
+}
+
+fn powerline_segments(
+    info: &ToolboxInfo,
+    options: &PowerlineOptions,
+    theme: &ResolvedTheme,
+) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    for section in options.sections {
+        let start = segments.len();
+        match section {
+            Section::Dir => segments.extend(powerline_dir_segment(info, options, theme)),
+            Section::Git => segments.extend(powerline_git_segment(info, options.show_icons, theme)),
+            Section::Tools => segments.extend(powerline_tool_segments(info, options, theme)),
+            Section::Venv => {
+                segments.extend(powerline_venv_segment(info, options.show_icons, theme))
+            }
+            Section::System => {}
+        }
+        for segment in &mut segments[start..] {
+            segment.section = Some(*section);
+            segment.priority = section_priority(*section);
+        }
+    }
+    segments
+}
+
+fn powerline_dir_segment(
+    info: &ToolboxInfo,
+    options: &PowerlineOptions,
+    theme: &ResolvedTheme,
+) -> Option<Segment> {
+    let dir = info.current_dir.as_ref()?;
+    let display_dir = if options.compact {
+        shorten_path(dir)
+    } else {
+        dir.clone()
+    };
+    let segment = Segment::from_theme_colors(display_dir, &theme.directory_fg, &theme.directory_bg)
+        .with_short(shorten_path(dir));
+    Some(if options.show_icons {
+        segment.with_icon("📂")
+    } else {
+        segment
+    })
+}
+
+fn powerline_git_segment(
+    info: &ToolboxInfo,
+    show_icons: bool,
+    theme: &ResolvedTheme,
+) -> Option<Segment> {
+    let git = info.git.as_ref()?;
+    let text = if show_icons {
+        format!(" {}", git.branch)
+    } else {
+        git.branch.clone()
+    };
+
+    // Use clean/dirty colors from theme
+    let mut segment = if git.is_dirty {
+        Segment::from_theme_colors(text.clone(), &theme.git_dirty_fg, &theme.git_dirty_bg)
+    } else {
+        Segment::from_theme_colors(text.clone(), &theme.git_clean_fg, &theme.git_clean_bg)
+    };
+
+    // Status and ahead/behind as sub-segments: `main  +3  ↑2`
+    for part in [git.changes_summary(), git.ahead_behind_summary()]
+        .into_iter()
+        .flatten()
+    {
+        segment = segment.with_part(part);
+    }
+    if !segment.parts.is_empty() {
+        segment = segment.with_short(text);
+    }
+    Some(segment)
+}
+
+fn powerline_tool_segments(
+    info: &ToolboxInfo,
+    options: &PowerlineOptions,
+    theme: &ResolvedTheme,
+) -> Vec<Segment> {
+    let mut color_index = 0;
+
+    let segments: Vec<Segment> = info
+        .tools
+        .iter()
+        .filter(|t| t.available || options.show_unavailable)
+        .map(|tool| {
+            let short_name = tool.short_name.as_ref().unwrap_or(&tool.name);
+            let name = if options.compact {
+                short_name
+            } else {
+                &tool.name
+            };
+            let (version, major) = if tool.available {
+                (
+                    tool.version_label().unwrap_or("?").to_string(),
+                    abbreviate_version(tool.version.as_deref().unwrap_or("?"), 1),
+                )
+            } else {
+                (MISSING_MARKER.to_string(), MISSING_MARKER.to_string())
+            };
+            let icon = tool
+                .icon
+                .as_deref()
+                .filter(|icon| options.show_icons && !icon.is_empty());
+
+            let (text, short) = match icon {
+                // The icon alone identifies the tool once abbreviated
+                Some(_) => (format!("{} {}", name, version), major),
+                None if options.show_icons => (
+                    format!(" {} {}", name, version),
+                    format!(" {} {}", short_name, major),
+                ),
+                None => (
+                    format!("{} {}", name, version),
+                    format!("{} {}", short_name, major),
+                ),
+            };
+
+            let segment = if tool.available {
+                let (ref bg, ref fg) = theme.tool_colors[color_index % theme.tool_colors.len()];
+                color_index += 1;
+                Segment::from_theme_colors(text, fg, bg)
+            } else {
+                Segment::dark_gray(text)
+            };
+            let mut segment = segment.with_short(short);
+            if let Some(drift) = tool.drift {
+                segment.fg = drift.ansi_fg().to_string();
+            }
+            match icon {
+                Some(icon) => segment.with_icon(icon),
+                None => segment,
+            }
+        })
+        .collect();
+
+    if !options.group_tools || segments.is_empty() {
+        return segments;
+    }
+
+    // All tools in one segment, divided by thin separators
+    let separator = if options.use_color {
+        format!(" {} ", SEPARATOR_RIGHT_THIN)
+    } else {
+        " | ".to_string()
+    };
+    let text = segments
+        .iter()
+        .map(Segment::label)
+        .collect::<Vec<_>>()
+        .join(&separator);
+    let short = segments
+        .into_iter()
+        .map(|mut segment| {
+            segment.abbreviate();
+            segment.label()
+        })
+        .collect::<Vec<_>>()
+        .join(&separator);
+    let (ref bg, ref fg) = theme.tool_colors[0];
+    vec![Segment::from_theme_colors(text, fg, bg).with_short(short)]
+}
+
+fn powerline_venv_segment(
+    info: &ToolboxInfo,
+    show_icons: bool,
+    theme: &ResolvedTheme,
+) -> Option<Segment> {
+    let venv = venv_label(info)?;
+    if show_icons {
+        Some(Segment::from_theme_colors(venv, &theme.venv_fg, &theme.venv_bg).with_icon("🐍"))
+    } else {
+        Some(Segment::from_theme_colors(
+            format!("{} {}", tr(Message::Venv), venv),
+            &theme.venv_fg,
+            &theme.venv_bg,
+        ))
+    }
+}
+
+/// Format as a Markdown report (environment list and tool table)
+pub fn markdown(info: &ToolboxInfo) -> String {
+    let mut out = String::from("## Toolbox report\n\n");
+
+    let rows = report_rows(info);
+    if !rows.is_empty() {
+        for (key, value) in &rows {
+            out.push_str(&format!("- **{}:** {}\n", key, escape_markdown(value)));
+        }
+        out.push('\n');
+    }
+
+    out.push_str("| Tool | Version | Path |\n");
+    out.push_str("|------|---------|------|\n");
+    for tool in &info.tools {
+        out.push_str(&format!(
+            "| {} | {} | {} |\n",
+            escape_markdown(&tool.name),
+            escape_markdown(&report_version(tool)),
+            tool.path
+                .as_deref()
+                .map(|p| format!("`{}`", escape_markdown(p)))
+                .unwrap_or_default()
+        ));
+    }
+
+    out
+}
+
+/// Format as a standalone HTML report (environment list and tool table)
+pub fn html(info: &ToolboxInfo) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Toolbox report</title>\n</head>\n<body>\n<h2>Toolbox report</h2>\n",
+    );
+
+    let rows = report_rows(info);
+    if !rows.is_empty() {
+        out.push_str("<dl>\n");
+        for (key, value) in &rows {
+            out.push_str(&format!(
+                "<dt>{}</dt><dd>{}</dd>\n",
+                key,
+                escape_html(value)
+            ));
+        }
+        out.push_str("</dl>\n");
+    }
+
+    out.push_str(
+        "<table>\n<thead><tr><th>Tool</th><th>Version</th><th>Path</th></tr></thead>\n<tbody>\n",
+    );
+    for tool in &info.tools {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td><code>{}</code></td></tr>\n",
+            escape_html(&tool.name),
+            escape_html(&report_version(tool)),
+            escape_html(tool.path.as_deref().unwrap_or(""))
+        ));
+    }
+    out.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+
+    out
+}
+
+/// Format as CSV, one row per tool (name, version, available, path, error)
+pub fn csv(info: &ToolboxInfo) -> String {
+    delimited(info, ',')
+}
+
+/// Format as TSV, one row per tool (name, version, available, path, error)
+pub fn tsv(info: &ToolboxInfo) -> String {
+    delimited(info, '\t')
+}
+
+fn delimited(info: &ToolboxInfo, delimiter: char) -> String {
+    let field = |value: &str| {
+        if delimiter == '\t' {
+            // TSV has no quoting: flatten tabs and newlines
+            value.replace(['\t', '\n', '\r'], " ")
+        } else if value.contains([delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+
+    let mut out = ["name", "version", "available", "path", "error"].join(&delimiter.to_string());
+    out.push('\n');
+    for tool in &info.tools {
+        let row = [
+            field(&tool.name),
+            field(tool.version.as_deref().unwrap_or("")),
+            tool.available.to_string(),
+            field(tool.path.as_deref().unwrap_or("")),
+            field(tool.error.as_deref().unwrap_or("")),
+        ];
+        out.push_str(&row.join(&delimiter.to_string()));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Environment key/value pairs shown above the tool table in reports
+fn report_rows(info: &ToolboxInfo) -> Vec<(&'static str, String)> {
+    let mut rows = Vec::new();
+
+    if let Some(ref dir) = info.current_dir {
+        rows.push(("Directory", dir.clone()));
+    }
+    if let Some(ref git) = info.git {
+        let mut suffixes = Vec::new();
+        if let Some(summary) = git.changes_summary() {
+            suffixes.push(summary);
+        }
+        if let Some(ab_summary) = git.ahead_behind_summary() {
+            suffixes.push(ab_summary);
+        }
+        let status = if suffixes.is_empty() {
+            "clean".to_string()
+        } else {
+            suffixes.join(" ")
+        };
+        rows.push(("Git", format!("{} ({})", git.branch, status)));
+    }
+    if let Some(venv) = venv_label(info) {
+        rows.push(("Virtual env", venv));
+    }
+    if let Some(ref shell) = info.shell {
+        rows.push(("Shell", shell.clone()));
+    }
+    if let Some(ref sys) = info.system {
+        if let Some(mem) = sys.memory_percent {
+            rows.push(("Memory", format!("{:.0}%", mem)));
+        }
+        if let Some(cpu) = sys.cpu_percent {
+            rows.push(("CPU", format!("{:.0}%", cpu)));
+        }
+        if let Some(swap) = sys.swap_percent {
+            rows.push(("Swap", format!("{:.0}%", swap)));
+        }
+    }
+
+    rows
+}
+
+/// Version cell for reports ("✗ not found" for unavailable tools)
+fn report_version(tool: &ToolInfo) -> String {
+    if tool.available {
+        tool.version_label().unwrap_or("?").to_string()
+    } else {
+        format!("{} not found", MISSING_MARKER)
+    }
+}
+
+/// Escape characters that would break a Markdown table cell
+fn escape_markdown(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Escape HTML special characters
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Options for [`powerline`]
+#[derive(Debug, Clone, Copy)]
+pub struct PowerlineOptions<'a> {
+    /// Compact mode (short names, shortened paths)
+    pub compact: bool,
+    /// Show icons (emoji)
+    pub show_icons: bool,
+    /// Emit ANSI colors (plain text with " | " separators otherwise)
+    pub use_color: bool,
+    /// Join all segments in one line
+    pub single_line: bool,
+    /// Sections to render, in order (the system section is not shown)
+    pub sections: &'a [Section],
+    /// Maximum display width in columns (also the padded width of right/split layouts)
+    pub max_width: Option<usize>,
+    /// Show unavailable tools as dimmed segments marked with ✗
+    pub show_unavailable: bool,
+    /// Render all tools in a single segment
+    pub group_tools: bool,
+    /// Single-line alignment
+    pub layout: PowerlineLayout,
+    /// Sections placed on the right in the split layout
+    pub right_sections: &'a [Section],
+}
+
+impl Default for PowerlineOptions<'_> {
+    fn default() -> Self {
+        Self {
+            compact: false,
+            show_icons: true,
+            use_color: false,
+            single_line: false,
+            sections: DEFAULT_SECTIONS,
+            max_width: None,
+            show_unavailable: false,
+            group_tools: false,
+            layout: PowerlineLayout::Left,
+            right_sections: &[],
+        }
+    }
+}
+
+/// Marker shown in place of the version of an unavailable tool
+const MISSING_MARKER: &str = "✗";
+
+/// Keep the first `parts` dot-separated components of a version
+fn abbreviate_version(version: &str, parts: usize) -> String {
+    version.split('.').take(parts).collect::<Vec<_>>().join(".")
+}
+
+/// Shorten a path for compact display
+fn shorten_path(path: &str) -> String {
+    // Replace home directory with ~
+    if let Some(home) = dirs::home_dir() {
+        if let Some(home_str) = home.to_str() {
+            if path.starts_with(home_str) {
+                return path.replacen(home_str, "~", 1);
+            }
+        }
+    }
+
+    // If path is too long, show only last 2 components
+    let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if parts.len() > 2 {
+        format!("…/{}", parts[parts.len() - 2..].join("/"))
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::{GitInfo, SystemInfo};
+
+    fn options(theme: &ResolvedTheme) -> FormatOptions<'_> {
+        FormatOptions {
+            display: PowerlineOptions::default(),
+            theme,
+        }
+    }
+
+    #[test]
+    fn test_registry_builtin_names() {
+        let registry = FormatRegistry::new();
+        assert_eq!(
+            registry.names(),
+            vec![
+                "text",
+                "powerline",
+                "json",
+                "json-pretty",
+                "jsonl",
+                "markdown",
+                "html",
+                "csv",
+                "tsv"
+            ]
+        );
+        assert!(registry.get("yaml").is_none());
+    }
+
+    #[test]
+    fn test_registry_formats() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+        let theme = ResolvedTheme::default_theme();
+        let registry = FormatRegistry::new();
+        let format = |name: &str| {
+            registry
+                .get(name)
+                .unwrap()
+                .format(&info, &options(&theme))
+                .unwrap()
+        };
+
+        assert_eq!(
+            format("text"),
+            format!(
+                "{}\n",
+                text(&info, false, true, DEFAULT_SECTIONS, false, false)
+            )
+        );
+        assert_eq!(format("csv"), csv(&info));
+        assert_eq!(format("tsv"), tsv(&info));
+        let json: serde_json::Value = serde_json::from_str(&format("json")).unwrap();
+        assert_eq!(json["tools"][0]["version"], "1.75.0");
+        assert!(format("json-pretty").contains("\n  \"tools\""));
+        assert!(format("jsonl").starts_with(r#"{"event":"done""#));
+    }
+
+    struct Upper;
+
+    impl Formatter for Upper {
+        fn name(&self) -> &str {
+            "text"
+        }
+
+        fn format(&self, info: &ToolboxInfo, options: &FormatOptions) -> Result<String> {
+            Ok(TextFormatter.format(info, options)?.to_uppercase())
+        }
+    }
+
+    #[test]
+    fn test_registry_register_replaces() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+        let theme = ResolvedTheme::default_theme();
+        let mut registry = FormatRegistry::new();
+        registry.register(Box::new(Upper));
+
+        assert_eq!(registry.names().len(), 9);
+        let output = registry
+            .get("text")
+            .unwrap()
+            .format(&info, &options(&theme))
+            .unwrap();
+        assert!(output.contains("RUST"));
+    }
+
+    #[test]
+    fn test_text_empty() {
+        let info = ToolboxInfo::new();
+        let output = text(&info, true, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_text_with_tools() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(
+            ToolInfo::available("Rust".to_string(), "1.75.0".to_string())
+                .with_icon(Some("🦀".to_string()))
+                .with_short_name(Some("rust".to_string())),
+        );
+
+        let output = text(&info, true, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("🦀"));
+        assert!(output.contains("rust"));
+        assert!(output.contains("1.75.0"));
+    }
+
+    #[test]
+    fn test_text_no_icons() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(
+            ToolInfo::available("Rust".to_string(), "1.75.0".to_string())
+                .with_icon(Some("🦀".to_string())),
+        );
+
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(!output.contains("🦀"));
+        assert!(output.contains("Rust"));
+        assert!(output.contains("1.75.0"));
+    }
+
+    #[test]
+    fn test_text_with_git() {
+        let mut info = ToolboxInfo::new();
+        info.git = Some(GitInfo {
+            branch: "main".to_string(),
+            modified_count: Some(2),
+            staged_count: None,
+            untracked_count: None,
+            is_dirty: true,
+            ahead: None,
+            behind: None,
+        });
+
+        let output = text(&info, true, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("main"));
+        assert!(output.contains("+2"));
+    }
+
+    #[test]
+    fn test_text_unavailable_tools_hidden() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::unavailable(
+            "Ruby".to_string(),
+            Some("not found".to_string()),
+        ));
+
+        let output = text(&info, true, true, DEFAULT_SECTIONS, false, false);
+        assert!(!output.contains("Ruby"));
+    }
+
+    #[test]
+    fn test_shorten_path_long_path() {
+        let path = "/very/long/path/to/project";
+        let shortened = shorten_path(path);
+        assert_eq!(shortened, "…/to/project");
+    }
+
+    #[test]
+    fn test_shorten_path_short_path() {
+        let path = "/short/path";
+        let shortened = shorten_path(path);
+        assert_eq!(shortened, "/short/path");
+    }
+
+    #[test]
+    fn test_shorten_path_root() {
+        let path = "/";
+        let shortened = shorten_path(path);
+        assert_eq!(shortened, "/");
+    }
+
+    #[test]
+    fn test_text_swap_and_cores() {
+        let mut info = ToolboxInfo::new();
+        info.system = Some(SystemInfo {
+            swap_percent: Some(12.0),
+            cpu_per_core: Some(vec![0.0, 100.0]),
+            ..Default::default()
+        });
+
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("swap: 12%"));
+        assert!(output.contains("cores: ▁█"));
+
+        let output = text(&info, false, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("🔄 12%"));
+        assert!(output.contains("📊 ▁█"));
+    }
+
+    // --- text additional tests ---
+
+    #[test]
+    fn test_text_with_virtual_env() {
+        let mut info = ToolboxInfo::new();
+        info.virtual_env = Some("myenv".to_string());
+        info.tools.push(ToolInfo::available(
+            "Python".to_string(),
+            "3.12.0".to_string(),
+        ));
+
+        let output = text(&info, false, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("myenv"));
+    }
+
+    #[test]
+    fn test_text_virtual_env_no_icons() {
+        let mut info = ToolboxInfo::new();
+        info.virtual_env = Some("myenv".to_string());
+        info.tools.push(ToolInfo::available(
+            "Python".to_string(),
+            "3.12.0".to_string(),
+        ));
+
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("venv: myenv"));
+    }
+
+    #[test]
+    fn test_text_with_system_info() {
+        let mut info = ToolboxInfo::new();
+        info.system = Some(SystemInfo {
+            memory_percent: Some(50.0),
+            memory_total_gb: Some(16.0),
+            memory_used_gb: Some(8.0),
+            cpu_percent: Some(25.0),
+            ..Default::default()
+        });
+
+        let output = text(&info, false, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("50%"));
+        assert!(output.contains("25%"));
+    }
+
+    #[test]
+    fn test_text_system_no_icons() {
+        let mut info = ToolboxInfo::new();
+        info.system = Some(SystemInfo {
+            memory_percent: Some(75.0),
+            memory_total_gb: None,
+            memory_used_gb: None,
+            cpu_percent: Some(50.0),
+            ..Default::default()
+        });
+
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("mem: 75%"));
+        assert!(output.contains("cpu: 50%"));
+    }
+
+    #[test]
+    fn test_text_with_current_dir() {
+        let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/home/user/project".to_string());
+
+        let output = text(&info, false, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("/home/user/project"));
+    }
+
+    #[test]
+    fn test_text_compact_dir() {
+        let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/very/long/path/to/project".to_string());
+
+        let output = text(&info, true, false, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("to/project"));
+    }
+
+    #[test]
+    fn test_text_git_clean() {
+        let mut info = ToolboxInfo::new();
+        info.git = Some(GitInfo {
+            branch: "main".to_string(),
+            modified_count: Some(0),
+            staged_count: Some(0),
+            untracked_count: Some(0),
+            is_dirty: false,
+            ahead: None,
+            behind: None,
+        });
+
+        let output = text(&info, false, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("main"));
+        // Clean repo should not show change count
+        assert!(!output.contains("+"));
+    }
+
+    #[test]
+    fn test_text_git_ahead_behind() {
+        let mut info = ToolboxInfo::new();
+        info.git = Some(GitInfo {
+            branch: "feature".to_string(),
+            modified_count: Some(0),
+            staged_count: Some(0),
+            untracked_count: Some(0),
+            is_dirty: false,
+            ahead: Some(3),
+            behind: Some(1),
+        });
+
+        let output = text(&info, false, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("feature"));
+        assert!(output.contains("\u{2191}3")); // ↑3
+        assert!(output.contains("\u{2193}1")); // ↓1
+    }
+
+    #[test]
+    fn test_text_separator_between_sections() {
+        let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/tmp".to_string());
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+
+        let output = text(&info, false, true, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("\u{2500}")); // ─ separator
+    }
+
+    // --- powerline tests ---
+
+    #[test]
+    fn test_powerline_empty() {
+        let info = ToolboxInfo::new();
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_powerline_with_tools_no_color() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(
+            ToolInfo::available("Rust".to_string(), "1.75.0".to_string())
+                .with_icon(Some("\u{1f980}".to_string())),
+        );
+
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains("Rust"));
+        assert!(output.contains("1.75.0"));
+    }
+
+    #[test]
+    fn test_powerline_with_color() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                use_color: true,
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains("\x1b[")); // ANSI codes
+        assert!(output.contains("Rust"));
+    }
+
+    #[test]
+    fn test_powerline_multiline() {
+        let mut info = ToolboxInfo::new();
+        info.tools
+            .push(ToolInfo::available("A".to_string(), "1.0".to_string()));
+        info.tools
+            .push(ToolInfo::available("B".to_string(), "2.0".to_string()));
+
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                use_color: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains('\n'));
+    }
+
+    #[test]
+    fn test_powerline_git_clean() {
+        let mut info = ToolboxInfo::new();
+        info.git = Some(GitInfo {
+            branch: "main".to_string(),
+            modified_count: Some(0),
+            staged_count: Some(0),
+            untracked_count: Some(0),
+            is_dirty: false,
+            ahead: None,
+            behind: None,
+        });
+
+        // Green segment for clean repo (no color for easy assertion)
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains("main"));
+    }
+
+    #[test]
+    fn test_powerline_git_dirty() {
+        let mut info = ToolboxInfo::new();
+        info.git = Some(GitInfo {
+            branch: "dev".to_string(),
+            modified_count: Some(3),
+            staged_count: Some(0),
+            untracked_count: Some(0),
+            is_dirty: true,
+            ahead: None,
+            behind: None,
+        });
+
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains("dev"));
+        assert!(output.contains("+3"));
+    }
+
+    #[test]
+    fn test_powerline_git_sub_segments() {
+        let mut info = ToolboxInfo::new();
+        info.git = Some(GitInfo {
+            branch: "dev".to_string(),
+            modified_count: Some(3),
+            staged_count: Some(0),
+            untracked_count: Some(0),
+            is_dirty: true,
+            ahead: Some(2),
+            behind: None,
+        });
+        let theme = crate::color::ResolvedTheme::default_theme();
+        let options = PowerlineOptions {
+            show_icons: false,
+            single_line: true,
+            ..Default::default()
+        };
+
+        assert_eq!(powerline(&info, &options, &theme), "dev +3 ↑2");
+        let colored = powerline(
+            &info,
+            &PowerlineOptions {
+                use_color: true,
+                ..options
+            },
+            &theme,
+        );
+        assert!(
+            colored.contains("dev \u{E0B1} +3 \u{E0B1} ↑2"),
+            "{}",
+            colored
+        );
+
+        let fitted = powerline(
+            &info,
+            &PowerlineOptions {
+                max_width: Some(4),
+                ..options
+            },
+            &theme,
+        );
+        assert_eq!(fitted, "dev");
+    }
+
+    #[test]
+    fn test_text_and_powerline_drift_colors() {
+        use crate::snapshot::Drift;
+
+        let mut info = ToolboxInfo::new();
+        let mut rust = ToolInfo::available("Rust".to_string(), "1.76.0".to_string());
+        rust.drift = Some(Drift::Changed);
+        info.tools.push(rust);
+        let mut ruby = ToolInfo::unavailable("Ruby".to_string(), None);
+        ruby.drift = Some(Drift::Missing);
+        info.tools.push(ruby);
+
+        let plain = text(&info, false, false, &[Section::Tools], true, false);
+        assert_eq!(plain, " Rust 1.76.0\n Ruby ✗");
+        let colored = text(&info, false, false, &[Section::Tools], true, true);
+        assert!(colored.contains(&format!(" Rust {}1.76.0{}", ansi::FG_YELLOW, ansi::RESET)));
+        assert!(colored.contains(&format!(" Ruby {}✗{}", ansi::FG_RED, ansi::RESET)));
+
+        let segments = powerline_segments(
+            &info,
+            &PowerlineOptions {
+                show_unavailable: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert_eq!(segments[0].fg, ansi::FG_YELLOW);
+        assert_eq!(segments[1].fg, ansi::FG_RED);
+    }
+
+    #[test]
+    fn test_powerline_compact() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(
+            ToolInfo::available("Python".to_string(), "3.12.0".to_string())
+                .with_short_name(Some("py".to_string())),
+        );
+
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                compact: true,
+                show_icons: false,
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains("py"));
+        assert!(!output.contains("Python"));
+    }
+
+    #[test]
+    fn test_powerline_virtual_env() {
+        let mut info = ToolboxInfo::new();
+        info.virtual_env = Some("myenv".to_string());
+
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains("venv: myenv"));
+    }
+
+    #[test]
+    fn test_venv_label() {
+        let mut info = ToolboxInfo::new();
+        info.virtual_env = Some("myenv".to_string());
+        info.virtual_env_python = Some("3.12.1".to_string());
+        info.virtual_env_manager = Some("uv".to_string());
+
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.contains("venv: myenv (3.12.1, uv)"));
+
+        info.virtual_env_python = None;
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("venv: myenv (uv)"));
+
+        // A venv activated inside a conda environment
+        info.conda_env = Some("base".to_string());
+        info.conda_env_count = Some(3);
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("venv: myenv (uv, conda: base, 3 envs)"));
+    }
+
+    // --- section order/visibility ---
+
+    fn sections_sample() -> ToolboxInfo {
+        let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/tmp".to_string());
+        info.git = Some(GitInfo {
+            branch: "main".to_string(),
+            modified_count: None,
+            staged_count: None,
+            untracked_count: None,
+            is_dirty: false,
+            ahead: None,
+            behind: None,
+        });
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+        info.virtual_env = Some("myenv".to_string());
+        info
+    }
+
+    #[test]
+    fn test_text_sections_order_and_visibility() {
+        let info = sections_sample();
+        let output = text(
+            &info,
+            false,
+            false,
+            &[Section::Venv, Section::Tools],
+            false,
+            false,
+        );
+        let venv = output.find("venv: myenv").unwrap();
+        let rust = output.find("Rust 1.75.0").unwrap();
+        assert!(venv < rust);
+        assert!(!output.contains("/tmp"));
+        assert!(!output.contains("main"));
+    }
+
+    #[test]
+    fn test_text_sections_header_stays_together() {
+        let info = sections_sample();
+        let output = text(
+            &info,
+            false,
+            false,
+            &[Section::Git, Section::Dir, Section::Tools],
+            false,
+            false,
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], " main");
+        assert_eq!(lines[1], " /tmp");
+        assert!(lines[2].starts_with('\u{2500}'));
+        assert_eq!(lines[3], " Rust 1.75.0");
+    }
+
+    #[test]
+    fn test_text_no_trailing_separator() {
+        let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/tmp".to_string());
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert_eq!(output, " /tmp");
+    }
+
+    #[test]
+    fn test_powerline_sections_order_and_visibility() {
+        let info = sections_sample();
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                single_line: true,
+                sections: &[Section::Tools, Section::Dir],
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        let rust = output.find("Rust").unwrap();
+        let dir = output.find("/tmp").unwrap();
+        assert!(rust < dir);
+        assert!(!output.contains("main"));
+        assert!(!output.contains("myenv"));
+    }
+
+    // --- unavailable tools ---
+
+    fn missing_sample() -> ToolboxInfo {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+        info.tools.push(
+            ToolInfo::unavailable("Ruby".to_string(), Some("not found".to_string()))
+                .with_icon(Some("💎".to_string())),
+        );
+        info
+    }
+
+    #[test]
+    fn test_text_show_unavailable() {
+        let info = missing_sample();
+        let hidden = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(!hidden.contains("Ruby"));
+
+        let shown = text(&info, false, false, DEFAULT_SECTIONS, true, false);
+        assert!(shown.contains(" Rust 1.75.0"));
+        assert!(shown.contains(" Ruby ✗"));
+    }
+
+    #[test]
+    fn test_powerline_show_unavailable() {
+        let info = missing_sample();
+        let options = PowerlineOptions {
+            use_color: true,
+            single_line: true,
+            show_unavailable: true,
+            ..Default::default()
+        };
+        let theme = crate::color::ResolvedTheme::default_theme();
+        let output = powerline(&info, &options, &theme);
+        assert!(output.contains("💎 Ruby ✗"));
+        assert!(output.contains(crate::color::ansi::BG_DARK_GRAY));
+
+        let hidden = powerline(
+            &info,
+            &PowerlineOptions {
+                show_unavailable: false,
+                ..options
+            },
+            &theme,
+        );
+        assert!(!hidden.contains("Ruby"));
+    }
+
+    #[test]
+    fn test_powerline_group_tools() {
+        let info = width_sample();
+        let theme = crate::color::ResolvedTheme::default_theme();
+        let options = PowerlineOptions {
+            use_color: true,
+            single_line: true,
+            group_tools: true,
+            sections: &[Section::Tools],
+            ..Default::default()
+        };
+        let output = powerline(&info, &options, &theme);
+        assert!(output.contains(&format!(
+            "Node.js 22.11.0 {} 🐍 Python",
+            crate::color::SEPARATOR_RIGHT_THIN
+        )));
+        // One segment: a single closing separator
+        assert_eq!(output.matches(crate::color::SEPARATOR_RIGHT).count(), 1);
+
+        let plain = powerline(
+            &info,
+            &PowerlineOptions {
+                use_color: false,
+                ..options
+            },
+            &theme,
+        );
+        assert_eq!(plain, "⬢ Node.js 22.11.0 | 🐍 Python 3.12.7");
+    }
+
+    // --- markdown/html reports ---
+
+    fn report_sample() -> ToolboxInfo {
+        let mut info = sections_sample();
+        info.tools[0].path = Some("/usr/bin/rustc".to_string());
+        info.tools.push(ToolInfo::unavailable(
+            "Ruby".to_string(),
+            Some("not found".to_string()),
+        ));
+        info
+    }
+
+    #[test]
+    fn test_markdown() {
+        let output = markdown(&report_sample());
+        assert!(output.contains("- **Directory:** /tmp"));
+        assert!(output.contains("- **Git:** main (clean)"));
+        assert!(output.contains("- **Virtual env:** myenv"));
+        assert!(output.contains("| Tool | Version | Path |"));
+        assert!(output.contains("| Rust | 1.75.0 | `/usr/bin/rustc` |"));
+        assert!(output.contains("| Ruby | ✗ not found |  |"));
+    }
+
+    #[test]
+    fn test_markdown_escapes_pipes() {
+        let mut info = ToolboxInfo::new();
+        info.tools
+            .push(ToolInfo::available("A|B".to_string(), "1.0".to_string()));
+        info.tools.push(
+            ToolInfo::available("C".to_string(), "2.0".to_string())
+                .with_path(Some("/opt/a|b/c".to_string())),
+        );
+        let output = markdown(&info);
+        assert!(output.contains("| A\\|B | 1.0 |"));
+        assert!(
+            output.contains("| C | 2.0 | `/opt/a\\|b/c` |"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let mut info = report_sample();
+        info.tools.push(ToolInfo::available(
+            "<script>".to_string(),
+            "1 & 2".to_string(),
+        ));
+        let output = html(&info);
+        assert!(output.starts_with("<!DOCTYPE html>"));
+        assert!(output.contains("<dt>Git</dt><dd>main (clean)</dd>"));
+        assert!(output
+            .contains("<tr><td>Rust</td><td>1.75.0</td><td><code>/usr/bin/rustc</code></td></tr>"));
+        assert!(output.contains("<td>&lt;script&gt;</td><td>1 &amp; 2</td>"));
+        assert!(!output.contains("<script>"));
+    }
+
+    #[test]
+    fn test_csv() {
+        let output = csv(&report_sample());
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "name,version,available,path,error");
+        assert_eq!(lines[1], "Rust,1.75.0,true,/usr/bin/rustc,");
+        assert_eq!(lines[2], "Ruby,,false,,not found");
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::unavailable(
+            "Tool, \"X\"".to_string(),
+            Some("line1\nline2".to_string()),
+        ));
+        let output = csv(&info);
+        assert!(output.contains("\"Tool, \"\"X\"\"\",,false,,\"line1\nline2\""));
+    }
+
+    #[test]
+    fn test_tsv() {
+        let mut info = report_sample();
+        info.tools[1].error = Some("a\tb\nc".to_string());
+        let output = tsv(&info);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "name\tversion\tavailable\tpath\terror");
+        assert_eq!(lines[1], "Rust\t1.75.0\ttrue\t/usr/bin/rustc\t");
+        assert_eq!(lines[2], "Ruby\t\tfalse\t\ta b c");
+    }
+
+    // --- max_width fitting ---
+
+    fn width_sample() -> ToolboxInfo {
+        let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/tmp/some/deep/project".to_string());
+        info.tools.push(
+            ToolInfo::available("Node.js".to_string(), "22.11.0".to_string())
+                .with_icon(Some("⬢".to_string())),
+        );
+        info.tools.push(
+            ToolInfo::available("Python".to_string(), "3.12.7".to_string())
+                .with_icon(Some("🐍".to_string())),
+        );
+        info
+    }
+
+    fn fit(info: &ToolboxInfo, max_width: usize) -> String {
+        powerline(
+            info,
+            &PowerlineOptions {
+                single_line: true,
+                max_width: Some(max_width),
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        )
+    }
+
+    #[test]
+    fn test_abbreviate_version() {
+        assert_eq!(abbreviate_version("22.11.0", 2), "22.11");
+        assert_eq!(abbreviate_version("22.11.0", 1), "22");
+        assert_eq!(abbreviate_version("latest", 1), "latest");
+    }
+
+    #[test]
+    fn test_powerline_max_width_unconstrained() {
+        let info = width_sample();
+        let full = powerline(
+            &info,
+            &PowerlineOptions {
+                single_line: true,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert_eq!(fit(&info, 200), full);
+    }
+
+    #[test]
+    fn test_powerline_max_width_drops_low_priority_first() {
+        let info = width_sample();
+        let output = fit(&info, 52);
+        assert!(!output.contains("📂"), "{}", output);
+        assert!(output.contains("⬢ Node.js 22.11.0"), "{}", output);
+        assert!(output.contains("🐍 Python 3.12.7"), "{}", output);
+        assert!(crate::color::display_width(&output) <= 52);
+    }
+
+    #[test]
+    fn test_powerline_max_width_then_abbreviates() {
+        let info = width_sample();
+        let output = fit(&info, 30);
+        assert!(output.contains("⬢ Node.js 22.11.0"), "{}", output);
+        assert!(!output.contains("Python"), "{}", output);
+        assert!(output.ends_with("🐍 3"));
+        assert!(crate::color::display_width(&output) <= 30);
+
+        let output = fit(&info, 16);
+        assert!(!output.contains("Node.js"), "{}", output);
+        assert!(output.contains("⬢ 22"));
+        assert!(crate::color::display_width(&output) <= 16);
+    }
+
+    #[test]
+    fn test_powerline_max_width_drops_segments() {
+        let info = width_sample();
+        let output = fit(&info, 10);
+        assert!(!output.contains("🐍"), "{}", output);
+        assert!(output.contains("⬢ 22"));
+        assert!(crate::color::display_width(&output) <= 10);
+
+        // Never drops the last segment, truncates it instead
+        let output = fit(&info, 3);
+        assert_eq!(output, "⬢…");
+    }
+
+    #[test]
+    fn test_powerline_max_width_truncates_wide_segment() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Visual Studio Code".to_string(),
+            "1.95.0".to_string(),
+        ));
+        let options = |single_line| PowerlineOptions {
+            show_icons: false,
+            use_color: true,
+            single_line,
+            max_width: Some(12),
+            ..Default::default()
+        };
+        let theme = crate::color::ResolvedTheme::default_theme();
+
+        let output = powerline(&info, &options(true), &theme);
+        assert!(output.contains(" Visual S… "), "{:?}", output);
+        assert_eq!(crate::color::display_width(&output), 12);
+
+        let output = powerline(&info, &options(false), &theme);
+        assert!(output.contains(" Visual S… "), "{:?}", output);
+        assert_eq!(crate::color::display_width(&output), 12);
+    }
+
+    #[test]
+    fn test_powerline_split_layout() {
+        let info = sections_sample();
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                single_line: true,
+                max_width: Some(60),
+                layout: PowerlineLayout::Split,
+                right_sections: &[Section::Tools, Section::Venv],
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.starts_with("/tmp | main "), "{}", output);
+        assert!(output.ends_with(" Rust 1.75.0 | venv: myenv"), "{}", output);
+        assert_eq!(crate::color::display_width(&output), 60);
+    }
+
+    #[test]
+    fn test_powerline_right_layout() {
+        let info = sections_sample();
+        let output = powerline(
+            &info,
+            &PowerlineOptions {
+                show_icons: false,
+                use_color: true,
+                single_line: true,
+                max_width: Some(80),
+                layout: PowerlineLayout::Right,
+                ..Default::default()
+            },
+            &crate::color::ResolvedTheme::default_theme(),
+        );
+        assert!(output.starts_with(' '));
+        assert!(output.contains(crate::color::SEPARATOR_LEFT));
+        assert!(!output.contains(crate::color::SEPARATOR_RIGHT));
+        assert_eq!(crate::color::display_width(&output), 80);
+    }
+
+    #[test]
+    fn test_powerline_right_layout_without_width() {
+        let info = sections_sample();
+        let options = |layout| PowerlineOptions {
+            show_icons: false,
+            use_color: true,
+            single_line: true,
+            layout,
+            ..Default::default()
+        };
+        let theme = crate::color::ResolvedTheme::default_theme();
+        let left = powerline(&info, &options(PowerlineLayout::Left), &theme);
+        let right = powerline(&info, &options(PowerlineLayout::Right), &theme);
+        // Nothing to align to: no padding, only the separators point left
+        assert!(!right.starts_with(' '), "{}", right);
+        assert!(right.contains(crate::color::SEPARATOR_LEFT));
+        assert_eq!(
+            crate::color::display_width(&right),
+            crate::color::display_width(&left)
+        );
+    }
+
+    // --- Multiple available and unavailable tools ---
+
+    #[test]
+    fn test_text_mixed_tools() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+        info.tools.push(ToolInfo::unavailable(
+            "Ruby".to_string(),
+            Some("not found".to_string()),
+        ));
+        info.tools
+            .push(ToolInfo::available("Go".to_string(), "1.21.0".to_string()));
+
+        let output = text(&info, false, false, DEFAULT_SECTIONS, false, false);
+        assert!(output.contains("Rust"));
+        assert!(output.contains("Go"));
+        assert!(!output.contains("Ruby")); // Unavailable hidden
+    }
+}
@@ -0,0 +1,91 @@
+//! Go module info
+//!
+//! The nearest `go.mod` names the module and, with its `go` directive, the
+//! minimum Go version the module needs. Shown next to the installed Go, a
+//! compiler older than the directive is flagged before `go build` fails.
+
+use crate::info::GoModule;
+use std::path::Path;
+
+/// Module path and `go` directive of a `go.mod`
+pub fn parse_go_mod(contents: &str) -> (Option<String>, Option<String>) {
+    let mut module = None;
+    let mut go = None;
+    for line in contents.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("module"), Some(path)) => module = Some(path.trim_matches('"').to_string()),
+            (Some("go"), Some(version)) => go = Some(version.to_string()),
+            _ => {}
+        }
+    }
+    (module, go)
+}
+
+/// The nearest `go.mod` at or above `start`
+pub fn find_module(start: &Path) -> Option<GoModule> {
+    start.ancestors().find_map(|dir| {
+        let file = dir.join("go.mod");
+        let contents = std::fs::read_to_string(&file).ok()?;
+        let (path, go) = parse_go_mod(&contents);
+        Some(GoModule {
+            path: path?,
+            go,
+            file: file.to_string_lossy().into_owned(),
+        })
+    })
+}
+
+/// Whether an installed Go is older than a module's `go` directive
+pub fn is_too_old(installed: &str, required: &str) -> bool {
+    use crate::require::parse_version;
+    match (parse_version(installed), parse_version(required)) {
+        (Some(installed), Some(required)) => installed < required,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_go_mod() {
+        let go_mod = "// Service\nmodule github.com/acme/service // main module\n\ngo 1.22.1\n\ntoolchain go1.22.4\n\nrequire (\n\tgolang.org/x/sync v0.7.0\n)\n";
+        assert_eq!(
+            parse_go_mod(go_mod),
+            (
+                Some("github.com/acme/service".to_string()),
+                Some("1.22.1".to_string())
+            )
+        );
+        assert_eq!(parse_go_mod("require x v1\n"), (None, None));
+    }
+
+    #[test]
+    fn test_find_module() {
+        let root = tempfile::tempdir().unwrap();
+        let pkg = root.path().join("internal/api");
+        std::fs::create_dir_all(&pkg).unwrap();
+        assert_eq!(find_module(&pkg), None);
+
+        std::fs::write(
+            root.path().join("go.mod"),
+            "module example.com/app\n\ngo 1.21\n",
+        )
+        .unwrap();
+        let module = find_module(&pkg).unwrap();
+        assert_eq!(module.path, "example.com/app");
+        assert_eq!(module.go.as_deref(), Some("1.21"));
+        assert_eq!(module.file, root.path().join("go.mod").to_string_lossy());
+    }
+
+    #[test]
+    fn test_is_too_old() {
+        assert!(is_too_old("1.21.5", "1.22"));
+        assert!(!is_too_old("1.22.0", "1.22"));
+        assert!(!is_too_old("1.23.1", "1.22.4"));
+        assert!(!is_too_old("devel", "1.22"));
+    }
+}
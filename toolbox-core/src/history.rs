@@ -0,0 +1,328 @@
+//! Version change history
+//!
+//! Each detection run appends the tools whose version changed since the last
+//! recorded entry to an append-only JSON Lines journal, so `toolbox history`
+//! can show when a tool changed on this machine. With the `sqlite` feature the
+//! journal can be the `history` table of a database instead.
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::export::format_rfc3339;
+use crate::info::ToolboxInfo;
+#[cfg(feature = "sqlite")]
+use crate::store::Store;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A recorded version change of a single tool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the change was observed (RFC 3339, UTC)
+    pub at: String,
+    /// Tool name
+    pub tool: String,
+    /// New version, or None if the tool disappeared
+    pub version: Option<String>,
+}
+
+/// Append-only version history journal
+#[derive(Debug, Clone)]
+pub struct History {
+    path: PathBuf,
+    /// `path` is a SQLite database rather than a JSON Lines file
+    #[cfg(feature = "sqlite")]
+    sqlite: bool,
+}
+
+impl History {
+    /// Open the journal at the given path (created on first write)
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            #[cfg(feature = "sqlite")]
+            sqlite: false,
+        }
+    }
+
+    /// Keep the journal in a SQLite database (created on first use)
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite(path: PathBuf) -> Self {
+        Self { path, sqlite: true }
+    }
+
+    /// The journal of a config: the `[storage]` database, or the
+    /// `history.path` file (None if no path can be determined)
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = config.storage.database()? {
+            return Ok(Some(Self::sqlite(db)));
+        }
+        // Without SQLite support, a config asking for it is an error
+        #[cfg(not(feature = "sqlite"))]
+        config.storage.database()?;
+        Ok(config
+            .history
+            .path
+            .clone()
+            .or_else(Self::default_path)
+            .map(Self::new))
+    }
+
+    /// Get the default journal path
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|p| p.join("toolbox").join("history.jsonl"))
+    }
+
+    /// Path of the journal file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read all entries in order (an absent journal is empty)
+    /// Malformed lines are skipped so a torn write doesn't hide the history.
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        #[cfg(feature = "sqlite")]
+        if self.sqlite {
+            return Store::open(&self.path)?.history(None);
+        }
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for line in BufReader::new(file).lines() {
+            if let Ok(entry) = serde_json::from_str(&line?) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Append entries for tools whose version changed since their last entry
+    /// Returns the newly recorded entries.
+    pub fn record(&self, info: &ToolboxInfo) -> Result<Vec<HistoryEntry>> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.record_at(info, &format_rfc3339(now))
+    }
+
+    fn record_at(&self, info: &ToolboxInfo, at: &str) -> Result<Vec<HistoryEntry>> {
+        // Reading the last versions and appending is one transaction there
+        #[cfg(feature = "sqlite")]
+        if self.sqlite {
+            return Store::open(&self.path)?.record_history(|last| changes(last, info, at));
+        }
+
+        let mut last: HashMap<String, Option<String>> = HashMap::new();
+        for entry in self.entries()? {
+            last.insert(entry.tool, entry.version);
+        }
+
+        let changes = changes(&last, info, at);
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+        tracing::debug!(path = %self.path.display(), changes = changes.len(), "recording history");
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut lines = String::new();
+        for entry in &changes {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes())?;
+
+        Ok(changes)
+    }
+}
+
+/// Entries for the tools of `info` whose version differs from `last`
+fn changes(
+    last: &HashMap<String, Option<String>>,
+    info: &ToolboxInfo,
+    at: &str,
+) -> Vec<HistoryEntry> {
+    info.tools
+        .iter()
+        .filter_map(|tool| {
+            let version = if tool.available {
+                tool.version.clone()
+            } else {
+                None
+            };
+            match last.get(&tool.name) {
+                Some(previous) if *previous == version => None,
+                // Never seen and not installed: nothing to record
+                None if version.is_none() => None,
+                _ => Some(HistoryEntry {
+                    at: at.to_string(),
+                    tool: tool.name.clone(),
+                    version,
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Format history entries as "<time>  <tool>  <from> -> <to>" lines
+/// `tool` filters by name (case-insensitive).
+pub fn format_history(entries: &[HistoryEntry], tool: Option<&str>) -> String {
+    let mut previous: HashMap<&str, Option<&str>> = HashMap::new();
+    let mut lines = Vec::new();
+
+    for entry in entries {
+        let before = previous.insert(&entry.tool, entry.version.as_deref());
+        if tool.is_some_and(|t| !t.eq_ignore_ascii_case(&entry.tool)) {
+            continue;
+        }
+
+        let to = entry.version.as_deref().unwrap_or("(missing)");
+        let change = match before {
+            None => format!("{} (first seen)", to),
+            Some(from) => format!("{} -> {}", from.unwrap_or("(missing)"), to),
+        };
+        lines.push(format!("{}  {}  {}", entry.at, entry.tool, change));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::ToolInfo;
+
+    fn info(tools: &[(&str, Option<&str>)]) -> ToolboxInfo {
+        let mut info = ToolboxInfo::new();
+        for (name, version) in tools {
+            info.tools.push(match version {
+                Some(v) => ToolInfo::available(name.to_string(), v.to_string()),
+                None => ToolInfo::unavailable(name.to_string(), None),
+            });
+        }
+        info
+    }
+
+    fn temp_history() -> (tempfile::TempDir, History) {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(dir.path().join("toolbox").join("history.jsonl"));
+        (dir, history)
+    }
+
+    #[test]
+    fn test_history_empty_when_missing() {
+        let (_dir, history) = temp_history();
+        assert!(history.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_history_records_only_changes() {
+        let (_dir, history) = temp_history();
+
+        let first = history
+            .record_at(&info(&[("Rust", Some("1.75.0")), ("Ruby", None)]), "t1")
+            .unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].tool, "Rust");
+
+        // Same versions: nothing appended
+        let same = history
+            .record_at(&info(&[("Rust", Some("1.75.0"))]), "t2")
+            .unwrap();
+        assert!(same.is_empty());
+
+        history
+            .record_at(&info(&[("Rust", Some("1.76.0"))]), "t3")
+            .unwrap();
+        history.record_at(&info(&[("Rust", None)]), "t4").unwrap();
+
+        let entries = history.entries().unwrap();
+        let versions: Vec<_> = entries.iter().map(|e| e.version.as_deref()).collect();
+        assert_eq!(versions, vec![Some("1.75.0"), Some("1.76.0"), None]);
+        assert_eq!(entries[2].at, "t4");
+    }
+
+    #[test]
+    fn test_history_skips_malformed_lines() {
+        let (_dir, history) = temp_history();
+        history
+            .record_at(&info(&[("Rust", Some("1.75.0"))]), "t1")
+            .unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(history.path())
+            .unwrap();
+        writeln!(file, "{{truncated").unwrap();
+
+        assert_eq!(history.entries().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_history_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::sqlite(dir.path().join("toolbox.db"));
+        history
+            .record_at(&info(&[("Rust", Some("1.75.0"))]), "t1")
+            .unwrap();
+        let same = history
+            .record_at(&info(&[("Rust", Some("1.75.0"))]), "t2")
+            .unwrap();
+        assert!(same.is_empty());
+        history
+            .record_at(&info(&[("Rust", Some("1.76.0"))]), "t3")
+            .unwrap();
+
+        let versions: Vec<_> = history
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.version)
+            .collect();
+        assert_eq!(
+            versions,
+            [Some("1.75.0".to_string()), Some("1.76.0".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_format_history() {
+        let entry = |at: &str, tool: &str, version: Option<&str>| HistoryEntry {
+            at: at.to_string(),
+            tool: tool.to_string(),
+            version: version.map(String::from),
+        };
+        let entries = vec![
+            entry("t1", "Rust", Some("1.75.0")),
+            entry("t1", "Node", Some("20.11.0")),
+            entry("t2", "Rust", Some("1.76.0")),
+            entry("t3", "Node", None),
+        ];
+
+        let all = format_history(&entries, None);
+        assert_eq!(
+            all,
+            "t1  Rust  1.75.0 (first seen)\n\
+             t1  Node  20.11.0 (first seen)\n\
+             t2  Rust  1.75.0 -> 1.76.0\n\
+             t3  Node  20.11.0 -> (missing)"
+        );
+
+        let rust = format_history(&entries, Some("rust"));
+        assert_eq!(rust.lines().count(), 2);
+        assert!(!rust.contains("Node"));
+    }
+}
@@ -0,0 +1,419 @@
+//! Importing tool definitions from other tools' configs (`toolbox import`)
+//!
+//! The modules of a `starship.toml`, the `[tools]` of a `mise.toml` and the
+//! plugins of an asdf `.tool-versions` become `tool_overrides` entries for the
+//! built-in tools they name and `custom_tools` entries for the others. Names
+//! that don't correspond to a version command (starship's `git_branch`, mise
+//! backends like `npm:prettier`) are reported as skipped.
+
+use crate::config::{Config, ToolConfig, ToolOverride};
+use crate::error::{Result, ToolboxError};
+use std::path::{Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
+
+/// A config format tool definitions are imported from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    /// `starship.toml` prompt modules
+    Starship,
+    /// `mise.toml` `[tools]`
+    Mise,
+    /// asdf `.tool-versions`
+    Asdf,
+}
+
+impl std::str::FromStr for ImportSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "starship" => Ok(ImportSource::Starship),
+            "mise" => Ok(ImportSource::Mise),
+            "asdf" => Ok(ImportSource::Asdf),
+            _ => Err(format!(
+                "Unknown import source: {} (expected starship, mise or asdf)",
+                s
+            )),
+        }
+    }
+}
+
+impl ImportSource {
+    /// The file read when none is given: `$STARSHIP_CONFIG` or
+    /// `~/.config/starship.toml`, and `mise.toml` / `.tool-versions` in `dir`
+    pub fn default_path(self, dir: &Path) -> Option<PathBuf> {
+        match self {
+            ImportSource::Starship => std::env::var_os("STARSHIP_CONFIG")
+                .map(PathBuf::from)
+                .or_else(|| dirs::home_dir().map(|home| home.join(".config/starship.toml"))),
+            ImportSource::Mise => ["mise.toml", ".mise.toml"]
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.exists())
+                .or_else(|| Some(dir.join("mise.toml"))),
+            ImportSource::Asdf => Some(dir.join(".tool-versions")),
+        }
+    }
+}
+
+/// Names other tools use for the built-in tools (beyond their own names and
+/// short names)
+const ALIASES: &[(&str, &str)] = &[
+    ("nodejs", "Node"),
+    ("golang", "Go"),
+    ("awscli", "aws-cli"),
+    ("kubernetes", "kubectl"),
+];
+
+/// Starship language modules without a built-in tool, with their version
+/// commands
+const STARSHIP_MODULES: &[(&str, &str)] = &[
+    ("cmake", "cmake --version"),
+    ("crystal", "crystal --version"),
+    ("dart", "dart --version"),
+    ("dotnet", "dotnet --version"),
+    ("elm", "elm --version"),
+    ("gleam", "gleam --version"),
+    ("haskell", "ghc --version"),
+    ("helm", "helm version --short"),
+    ("julia", "julia --version"),
+    ("kotlin", "kotlin -version"),
+    ("lua", "lua -v"),
+    ("nim", "nim --version"),
+    ("ocaml", "ocaml -version"),
+    ("perl", "perl --version"),
+    ("scala", "scala --version"),
+    ("swift", "swift --version"),
+];
+
+/// Tool definitions read from another tool's config
+#[derive(Debug, Clone, Default)]
+pub struct Import {
+    /// Entries for built-in tools
+    pub tool_overrides: Vec<ToolOverride>,
+    /// Entries for tools without a built-in definition
+    pub custom_tools: Vec<ToolConfig>,
+    /// Names that don't correspond to a tool
+    pub skipped: Vec<String>,
+}
+
+impl Import {
+    /// Read the contents of a config of `source`
+    pub fn parse(source: ImportSource, content: &str) -> Result<Self> {
+        let mut import = Import::default();
+        match source {
+            ImportSource::Starship => {
+                let table: toml::Table = toml::from_str(content)
+                    .map_err(|e| ToolboxError::Config(format!("Invalid starship.toml: {}", e)))?;
+                for (module, settings) in &table {
+                    // Top-level options like `format` and `add_newline`
+                    let Some(settings) = settings.as_table() else {
+                        continue;
+                    };
+                    let disabled = settings.get("disabled").and_then(|v| v.as_bool());
+                    let icon = settings
+                        .get("symbol")
+                        .and_then(|v| v.as_str())
+                        .map(|symbol| symbol.trim().to_string())
+                        .filter(|symbol| !symbol.is_empty());
+                    let command = STARSHIP_MODULES
+                        .iter()
+                        .find(|(name, _)| name == module)
+                        .map(|(_, command)| command.to_string());
+                    import.add(module, disabled != Some(true), icon, command);
+                }
+            }
+            ImportSource::Mise => {
+                let table: toml::Table = toml::from_str(content)
+                    .map_err(|e| ToolboxError::Config(format!("Invalid mise.toml: {}", e)))?;
+                let tools = table.get("tools").and_then(|v| v.as_table());
+                for name in tools.into_iter().flat_map(|tools| tools.keys()) {
+                    import.add_plugin(name);
+                }
+            }
+            ImportSource::Asdf => {
+                for line in content.lines() {
+                    let line = line.split('#').next().unwrap_or_default();
+                    if let Some(name) = line.split_whitespace().next() {
+                        import.add_plugin(name);
+                    }
+                }
+            }
+        }
+        Ok(import)
+    }
+
+    /// A mise or asdf plugin: its binary usually has the plugin's name
+    fn add_plugin(&mut self, name: &str) {
+        // Backends (`npm:prettier`, `ubi:owner/repo`) name packages, not programs
+        let command = (!name.contains(':')).then(|| format!("{} --version", name));
+        self.add(name, true, None, command);
+    }
+
+    fn add(&mut self, name: &str, enabled: bool, icon: Option<String>, command: Option<String>) {
+        let defaults = Config::default();
+        let builtin = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .and_then(|(_, tool)| defaults.find_tool(tool))
+            .or_else(|| defaults.find_tool(name));
+
+        if let Some(tool) = builtin {
+            if self.tool_overrides.iter().any(|o| o.name == tool.name) {
+                return;
+            }
+            self.tool_overrides.push(ToolOverride {
+                name: tool.name,
+                enabled: Some(enabled),
+                icon,
+                short_name: None,
+                retries: None,
+                retry_delay_ms: None,
+            });
+        } else if let Some(command) = command {
+            self.custom_tools.push(ToolConfig {
+                name: name.to_string(),
+                command,
+                icon,
+                enabled,
+                ..Default::default()
+            });
+        } else {
+            self.skipped.push(name.to_string());
+        }
+    }
+
+    /// Whether nothing was imported
+    pub fn is_empty(&self) -> bool {
+        self.tool_overrides.is_empty() && self.custom_tools.is_empty()
+    }
+
+    /// The imported entries as TOML, ready to append to a config
+    pub fn to_toml(&self) -> Result<String> {
+        let mut doc = DocumentMut::new();
+        self.insert_into(&mut doc)?;
+        Ok(doc.to_string())
+    }
+
+    /// Add the imported entries to a config, replacing entries of the same
+    /// tools
+    pub fn apply(&self, config: &mut Config) {
+        for entry in &self.tool_overrides {
+            config.tool_overrides.retain(|o| o.name != entry.name);
+            config.tool_overrides.push(entry.clone());
+        }
+        for entry in &self.custom_tools {
+            config.custom_tools.retain(|t| t.name != entry.name);
+            config.custom_tools.push(entry.clone());
+        }
+    }
+
+    /// Add the imported entries to the config file contents, returning the
+    /// edited contents (comments and formatting are preserved)
+    pub fn apply_to_toml(&self, content: &str) -> Result<String> {
+        let mut doc: DocumentMut = content
+            .parse()
+            .map_err(|e| ToolboxError::Config(format!("Invalid TOML: {}", e)))?;
+        self.insert_into(&mut doc)?;
+        let content = doc.to_string();
+        toml::from_str::<Config>(&content)
+            .map_err(|e| ToolboxError::Config(format!("Invalid config after import: {}", e)))?;
+        Ok(content)
+    }
+
+    fn insert_into(&self, doc: &mut DocumentMut) -> Result<()> {
+        let overrides = self
+            .tool_overrides
+            .iter()
+            .map(|entry| Ok((entry.name.as_str(), to_table(entry)?)));
+        append_entries(doc, "tool_overrides", overrides.collect::<Result<_>>()?)?;
+        let custom_tools = self
+            .custom_tools
+            .iter()
+            .map(|entry| Ok((entry.name.as_str(), to_table(entry)?)));
+        append_entries(doc, "custom_tools", custom_tools.collect::<Result<_>>()?)
+    }
+}
+
+fn to_table<T: serde::Serialize>(entry: &T) -> Result<Table> {
+    let text = toml::to_string(entry).map_err(|e| ToolboxError::Config(e.to_string()))?;
+    let doc: DocumentMut = text
+        .parse()
+        .map_err(|e: toml_edit::TomlError| ToolboxError::Config(e.to_string()))?;
+    Ok(doc.as_table().clone())
+}
+
+/// Append `[[key]]` entries (or inline tables to an existing `key = [...]`),
+/// dropping existing entries of the same names
+fn append_entries(doc: &mut DocumentMut, key: &str, entries: Vec<(&str, Table)>) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<&str> = entries.iter().map(|(name, _)| *name).collect();
+    let replaced = |name: Option<&str>| name.is_some_and(|name| names.contains(&name));
+
+    match doc.get_mut(key) {
+        None => {
+            let mut array = ArrayOfTables::new();
+            for (_, table) in entries {
+                array.push(table);
+            }
+            doc.insert(key, Item::ArrayOfTables(array));
+        }
+        Some(Item::ArrayOfTables(array)) => {
+            array.retain(|table| !replaced(table.get("name").and_then(Item::as_str)));
+            for (_, table) in entries {
+                array.push(table);
+            }
+        }
+        Some(Item::Value(toml_edit::Value::Array(array))) => {
+            array.retain(|value| {
+                let name = value.as_inline_table().and_then(|table| table.get("name"));
+                !replaced(name.and_then(toml_edit::Value::as_str))
+            });
+            for (_, table) in entries {
+                array.push(table.into_inline_table());
+            }
+            array.fmt();
+        }
+        Some(_) => {
+            return Err(ToolboxError::Config(format!(
+                "{} must be a list of tables",
+                key
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn override_of<'a>(import: &'a Import, name: &str) -> &'a ToolOverride {
+        import
+            .tool_overrides
+            .iter()
+            .find(|o| o.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_import_source_parse() {
+        assert_eq!("starship".parse(), Ok(ImportSource::Starship));
+        assert_eq!("Mise".parse(), Ok(ImportSource::Mise));
+        assert_eq!("asdf".parse(), Ok(ImportSource::Asdf));
+        assert!("rtx".parse::<ImportSource>().is_err());
+    }
+
+    #[test]
+    fn test_import_starship() {
+        let import = Import::parse(
+            ImportSource::Starship,
+            r#"
+format = "$all"
+add_newline = false
+
+[python]
+symbol = "🐍 "
+
+[java]
+disabled = true
+
+[nodejs]
+
+[julia]
+symbol = "ஃ "
+
+[git_branch]
+symbol = " "
+"#,
+        )
+        .unwrap();
+
+        let python = override_of(&import, "Python");
+        assert_eq!(python.enabled, Some(true));
+        assert_eq!(python.icon.as_deref(), Some("🐍"));
+        assert_eq!(override_of(&import, "Java").enabled, Some(false));
+        assert_eq!(override_of(&import, "Node").icon, None);
+
+        assert_eq!(import.custom_tools.len(), 1);
+        assert_eq!(import.custom_tools[0].name, "julia");
+        assert_eq!(import.custom_tools[0].command, "julia --version");
+        assert_eq!(import.custom_tools[0].icon.as_deref(), Some("ஃ"));
+        assert_eq!(import.skipped, ["git_branch"]);
+    }
+
+    #[test]
+    fn test_import_mise_and_asdf() {
+        let mise = Import::parse(
+            ImportSource::Mise,
+            r#"
+[env]
+NODE_ENV = "production"
+
+[tools]
+node = "20"
+golang = "1.22"
+"npm:prettier" = "latest"
+just = { version = "1.25" }
+"#,
+        )
+        .unwrap();
+        let asdf = Import::parse(
+            ImportSource::Asdf,
+            "# pinned\nnodejs 20.11.0\ngolang 1.22.0 # toolchain\n\njust 1.25.0\n",
+        )
+        .unwrap();
+
+        for import in [mise, asdf] {
+            assert_eq!(override_of(&import, "Node").enabled, Some(true));
+            assert_eq!(override_of(&import, "Go").enabled, Some(true));
+            assert_eq!(import.custom_tools.len(), 1);
+            assert_eq!(import.custom_tools[0].name, "just");
+            assert_eq!(import.custom_tools[0].command, "just --version");
+            assert!(import.custom_tools[0].enabled);
+        }
+    }
+
+    #[test]
+    fn test_import_to_toml_and_apply() {
+        let import = Import::parse(ImportSource::Asdf, "java 21\njust 1.25.0\n").unwrap();
+        let text = import.to_toml().unwrap();
+        let config: Config = toml::from_str(&text).unwrap();
+        let enabled: Vec<String> = config.enabled_tools().into_iter().map(|t| t.name).collect();
+        assert!(enabled.contains(&"Java".to_string()));
+        assert!(enabled.contains(&"just".to_string()));
+
+        let mut config = Config::default();
+        import.apply(&mut config);
+        import.apply(&mut config);
+        assert_eq!(config.tool_overrides.len(), 1);
+        assert_eq!(config.custom_tools.len(), 1);
+    }
+
+    #[test]
+    fn test_import_apply_to_toml() {
+        let import = Import::parse(ImportSource::Asdf, "java 21\njust 1.25.0\n").unwrap();
+        let content = "# my config\n\
+                       tool_overrides = [{ name = \"Java\", enabled = false }, { name = \"Node\", icon = \"N\" }]\n\
+                       \n\
+                       [display]\n\
+                       compact = true\n";
+        let edited = import.apply_to_toml(content).unwrap();
+        assert!(edited.starts_with("# my config\n"));
+        let config: Config = toml::from_str(&edited).unwrap();
+        assert!(config.display.compact);
+        // Java's entry is replaced, Node's kept
+        assert_eq!(config.tool_overrides.len(), 2);
+        let java = config
+            .tool_overrides
+            .iter()
+            .find(|o| o.name == "Java")
+            .unwrap();
+        assert_eq!(java.enabled, Some(true));
+        assert_eq!(config.custom_tools[0].name, "just");
+
+        assert!(import.apply_to_toml("tool_overrides = 1\n").is_err());
+    }
+}
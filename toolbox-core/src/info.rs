@@ -1,10 +1,62 @@
 //! Information structures for toolbox output
 
+use crate::color::{display_width, ResolvedTheme, Segment};
+use crate::config::ToolProvenance;
+use crate::error::ToolError;
+use crate::format::{self, PowerlineOptions};
+use crate::messages::{fill, tr, Message};
+use crate::package::PackageOrigin;
+use crate::snapshot::Drift;
+use crate::source::ToolSource;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Current version of the JSON output schema (`schema_version`)
+///
+/// Within a schema version fields are only added, never removed, renamed or
+/// retyped; consumers should ignore fields they don't know. Anything else
+/// bumps the version, and `--schema` keeps producing the older versions.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A version of the JSON output schema that `--schema` can pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaVersion {
+    /// The first versioned schema
+    V1,
+}
+
+impl SchemaVersion {
+    /// The `schema_version` number
+    pub fn number(self) -> u32 {
+        match self {
+            SchemaVersion::V1 => 1,
+        }
+    }
+}
+
+impl std::str::FromStr for SchemaVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().trim_start_matches('v') {
+            "1" => Ok(SchemaVersion::V1),
+            _ => Err(format!("Unsupported schema version: {} (supported: v1)", s)),
+        }
+    }
+}
+
+fn default_schema_version() -> u32 {
+    // Output from before versioning is the first version
+    1
+}
 
 /// Complete toolbox information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolboxInfo {
+    /// Version of this output's schema (see [`SCHEMA_VERSION`])
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
     /// Current directory
     #[serde(skip_serializing_if = "Option::is_none")]
     pub current_dir: Option<String>,
@@ -19,13 +71,33 @@ pub struct ToolboxInfo {
     /// Virtual environment name
     #[serde(skip_serializing_if = "Option::is_none")]
     pub virtual_env: Option<String>,
+    /// Python version of the virtual environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub virtual_env_python: Option<String>,
+    /// Package manager of the environment (pip, poetry, uv, pipenv; conda, mamba)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub virtual_env_manager: Option<String>,
+    /// Conda environment a venv was activated in (both `VIRTUAL_ENV` and
+    /// `CONDA_DEFAULT_ENV` set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conda_env: Option<String>,
+    /// Number of conda environments on this machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conda_env_count: Option<usize>,
     /// Shell name
     #[serde(skip_serializing_if = "Option::is_none")]
     pub shell: Option<String>,
+    /// Config profile applied (`--profile`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Profiles defined in the config, to switch between
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
 }
 
 /// Information about a single tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolInfo {
     /// Tool name
     pub name: String,
@@ -35,6 +107,16 @@ pub struct ToolInfo {
     /// Detected version
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
+    /// Build identifier (the `build` group of `parse_regex`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    /// Runtime or distribution (the `runtime` group of `parse_regex`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<String>,
+    /// Display string composed by `parse_template`, or the version with an
+    /// overriding rustup toolchain; shown instead of the version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_version: Option<String>,
     /// Icon/emoji
     #[serde(skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
@@ -43,6 +125,79 @@ pub struct ToolInfo {
     /// Error message if detection failed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Why the version command failed, by kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<ToolError>,
+    /// Resolved path of the command binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Where the binary comes from (system, homebrew, asdf, venv...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ToolSource>,
+    /// Wall-clock time spent detecting this tool, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Whether the result came from the version cache
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+    /// Active rustup toolchain (tools run through rustup proxies)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<RustToolchain>,
+    /// The `go.mod` of the working directory (Go, with `extras.go_module`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub go_module: Option<GoModule>,
+    /// Every installation found by `toolbox tool info` (empty otherwise)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub installed_versions: Vec<InstalledVersion>,
+    /// Comparison with the snapshot lockfile, when one exists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drift: Option<Drift>,
+    /// Doctor's diagnostic of the tool (`--with-diagnostics`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagnostic: Option<ToolDiagnostic>,
+}
+
+/// A Go module and the Go version it requires
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GoModule {
+    /// Module path
+    pub path: String,
+    /// `go` directive (minimum Go version)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub go: Option<String>,
+    /// The `go.mod` file
+    pub file: String,
+}
+
+/// The rustup toolchain a tool runs from, and what selected it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RustToolchain {
+    /// Toolchain without the host triple (stable, nightly-2024-11-28, 1.84.0)
+    pub name: String,
+    /// default, RUSTUP_TOOLCHAIN, directory override, rust-toolchain.toml or rust-toolchain
+    pub selected_by: String,
+    /// Toolchain file or overridden directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// One installation of a tool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct InstalledVersion {
+    /// Version (`None` if the binary gave none)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Binary path
+    pub path: String,
+    /// Where the binary comes from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ToolSource>,
+    /// Whether this is the installation that runs
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub active: bool,
 }
 
 impl ToolInfo {
@@ -52,9 +207,22 @@ impl ToolInfo {
             name,
             short_name: None,
             version: Some(version),
+            build: None,
+            runtime: None,
+            display_version: None,
             icon: None,
             available: true,
             error: None,
+            failure: None,
+            path: None,
+            source: None,
+            duration_ms: None,
+            cached: false,
+            toolchain: None,
+            go_module: None,
+            installed_versions: Vec::new(),
+            drift: None,
+            diagnostic: None,
         }
     }
 
@@ -64,9 +232,22 @@ impl ToolInfo {
             name,
             short_name: None,
             version: None,
+            build: None,
+            runtime: None,
+            display_version: None,
             icon: None,
             available: false,
             error,
+            failure: None,
+            path: None,
+            source: None,
+            duration_ms: None,
+            cached: false,
+            toolchain: None,
+            go_module: None,
+            installed_versions: Vec::new(),
+            drift: None,
+            diagnostic: None,
         }
     }
 
@@ -81,10 +262,74 @@ impl ToolInfo {
         self.short_name = short_name;
         self
     }
+
+    /// Set the resolved command path
+    pub fn with_path(mut self, path: Option<String>) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Set where the binary comes from
+    pub fn with_source(mut self, source: Option<ToolSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// The version as displayed: the `parse_template` string if any
+    pub fn version_label(&self) -> Option<&str> {
+        self.display_version.as_deref().or(self.version.as_deref())
+    }
+
+    /// Format the tool and its installations for `toolbox tool info`
+    pub fn format_installed(&self) -> String {
+        let mut out = self.name.clone();
+        match (&self.version, self.available) {
+            (Some(version), true) => out.push_str(&format!(" {}", version)),
+            _ => out.push_str(&format!(
+                " not available ({})",
+                self.error.as_deref().unwrap_or("unknown error")
+            )),
+        }
+        if let Some(ref path) = self.path {
+            match self.source {
+                Some(source) => out.push_str(&format!(" ({}, {})", path, source)),
+                None => out.push_str(&format!(" ({})", path)),
+            }
+        }
+        out.push('\n');
+
+        if self.installed_versions.is_empty() {
+            out.push_str("\nNo installations found\n");
+            return out;
+        }
+        out.push_str("\nInstalled versions:\n");
+        let width = self
+            .installed_versions
+            .iter()
+            .map(|v| display_width(v.version.as_deref().unwrap_or("?")))
+            .max()
+            .unwrap_or(0);
+        for installed in &self.installed_versions {
+            let version = installed.version.as_deref().unwrap_or("?");
+            out.push_str(&format!(
+                "  {} {}{}  {}",
+                if installed.active { "*" } else { " " },
+                version,
+                " ".repeat(width - display_width(version)),
+                installed.path
+            ));
+            if let Some(source) = installed.source {
+                out.push_str(&format!(" ({})", source));
+            }
+            out.push('\n');
+        }
+        out
+    }
 }
 
 /// Git repository information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GitInfo {
     /// Current branch name
     pub branch: String,
@@ -148,6 +393,7 @@ impl GitInfo {
 
 /// Status of a tool diagnostic check
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum DiagnosticStatus {
     /// Tool found and version parsed successfully
     Ok,
@@ -159,6 +405,7 @@ pub enum DiagnosticStatus {
 
 /// Diagnostic result for a single tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ToolDiagnostic {
     /// Tool name
     pub name: String,
@@ -169,9 +416,30 @@ pub struct ToolDiagnostic {
     pub status: DiagnosticStatus,
     /// The command that was checked
     pub command: String,
-    /// Resolved path of the command binary (from which)
+    /// Resolved path of the command binary (from PATH)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub command_path: Option<String>,
+    /// What an asdf/mise shim at `command_path` runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shim: Option<ShimInfo>,
+    /// Every match of the command in PATH, in PATH order (the first one runs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub path_matches: Vec<String>,
+    /// Where the binary at `command_path` comes from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<ToolSource>,
+    /// Package that installed the binary (`doctor --packages`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<PackageOrigin>,
+    /// Version the project pins (Corepack `packageManager`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<PinnedVersion>,
+    /// Times the version command was retried (`retries` in the tool config)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Where the tool definition came from and what overrode it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ToolProvenance>,
     /// Detected version (if successful)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
@@ -185,7 +453,65 @@ pub struct ToolDiagnostic {
     pub enabled: bool,
 }
 
+/// The binary behind a version manager shim, and what selected it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ShimInfo {
+    /// Version manager owning the shim (asdf, mise)
+    pub manager: String,
+    /// Binary the shim runs in the working directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub real_path: Option<String>,
+    /// Manager plugin providing the binary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+    /// Installed version the shim runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// File (or environment variable) that selected the version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// A version pinned by a project file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PinnedVersion {
+    /// Pinned version
+    pub version: String,
+    /// File pinning it
+    pub source: String,
+}
+
+impl ShimInfo {
+    /// One-line description: `asdf -> /real/bin/node (nodejs 20.11.0 from ./.tool-versions)`
+    pub fn format_display(&self) -> String {
+        let mut line = format!(
+            "{} -> {}",
+            self.manager,
+            self.real_path.as_deref().unwrap_or("unresolved")
+        );
+        if let (Some(plugin), Some(version)) = (&self.plugin, &self.version) {
+            line.push_str(&format!(" ({} {}", plugin, version));
+            if let Some(ref source) = self.source {
+                line.push_str(&format!(" from {}", source));
+            }
+            line.push(')');
+        }
+        line
+    }
+}
+
 impl ToolDiagnostic {
+    /// ` (/path/to/bin, source)`, or nothing without a path
+    fn format_path(&self) -> String {
+        match (&self.command_path, self.source) {
+            (Some(path), Some(source)) => format!(" ({}, {})", path, source),
+            (Some(path), None) => format!(" ({})", path),
+            (None, _) => String::new(),
+        }
+    }
+
     /// Format a single diagnostic line for display
     pub fn format_display(&self) -> String {
         let status_icon = match self.status {
@@ -195,16 +521,16 @@ impl ToolDiagnostic {
         };
 
         let icon = self.icon.as_deref().unwrap_or(" ");
-        let enabled_tag = if self.enabled { "" } else { " (disabled)" };
+        let enabled_tag = if self.enabled {
+            String::new()
+        } else {
+            format!(" {}", tr(Message::Disabled))
+        };
 
         let mut line = match &self.status {
             DiagnosticStatus::Ok => {
                 let version = self.version.as_deref().unwrap_or("?");
-                let path = self
-                    .command_path
-                    .as_deref()
-                    .map(|p| format!(" ({})", p))
-                    .unwrap_or_default();
+                let path = self.format_path();
                 format!(
                     " {} {} {}{}{} {}",
                     status_icon, icon, self.name, enabled_tag, path, version
@@ -212,18 +538,17 @@ impl ToolDiagnostic {
             }
             DiagnosticStatus::Warning => {
                 let version = self.version.as_deref().unwrap_or("?");
-                let path = self
-                    .command_path
-                    .as_deref()
-                    .map(|p| format!(" ({})", p))
-                    .unwrap_or_default();
+                let path = self.format_path();
                 format!(
                     " {} {} {}{}{} {}",
                     status_icon, icon, self.name, enabled_tag, path, version
                 )
             }
             DiagnosticStatus::Error => {
-                let detail = self.error_detail.as_deref().unwrap_or("unknown error");
+                let detail = self
+                    .error_detail
+                    .as_deref()
+                    .unwrap_or(tr(Message::UnknownError));
                 format!(
                     " {} {} {}{} - {}",
                     status_icon, icon, self.name, enabled_tag, detail
@@ -231,6 +556,40 @@ impl ToolDiagnostic {
             }
         };
 
+        if let Some(ref shim) = self.shim {
+            line.push_str(&format!(
+                "\n      {}",
+                fill(Message::Shim, &[&shim.format_display()])
+            ));
+        }
+        if let Some(retries) = self.retries {
+            line.push_str(&format!("\n      {}", fill(Message::Retried, &[&retries])));
+        }
+        // A plain built-in default goes without saying
+        if let Some(provenance) = self.provenance.as_ref().filter(|p| !p.is_plain_default()) {
+            line.push_str(&format!(
+                "\n      {}",
+                fill(Message::From, &[&provenance.format_display()])
+            ));
+        }
+        if let Some(ref pinned) = self.pinned {
+            line.push_str(&format!(
+                "\n      {}",
+                fill(Message::Pinned, &[&pinned.version, &pinned.source])
+            ));
+        }
+        if let Some(ref package) = self.package {
+            line.push_str(&format!(
+                "\n      {}",
+                fill(Message::Package, &[&package.format_display()])
+            ));
+        }
+        if self.path_matches.len() > 1 {
+            line.push_str(&format!(
+                "\n      {}",
+                fill(Message::AlsoInPath, &[&self.path_matches[1..].join(", ")])
+            ));
+        }
         if let Some(ref suggestion) = self.suggestion {
             line.push_str(&format!("\n      -> {}", suggestion));
         }
@@ -256,6 +615,90 @@ pub struct DiagnosticSummary {
     pub error_count: usize,
     /// Individual tool diagnostics
     pub tools: Vec<ToolDiagnostic>,
+    /// PATH, version managers and environment variables
+    #[serde(default)]
+    pub environment: EnvironmentReport,
+    /// Newer toolbox release, if one is out (`update.check`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_available: Option<String>,
+}
+
+/// The environment tools are resolved in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentReport {
+    /// PATH entries, in order
+    pub path: Vec<PathEntry>,
+    /// Version managers and whether they are active
+    pub managers: Vec<ManagerStatus>,
+    /// Set environment variables that affect tool resolution
+    pub variables: BTreeMap<String, String>,
+}
+
+/// A PATH entry and the diagnosed tools resolving from it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathEntry {
+    pub dir: String,
+    /// Whether the directory exists
+    pub exists: bool,
+    /// Whether an earlier entry is the same directory
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
+    /// Tools whose command resolves from this directory
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+}
+
+/// Activation status of a version manager (asdf, mise, direnv, nvm)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManagerStatus {
+    pub name: String,
+    /// Whether it is installed
+    pub installed: bool,
+    /// Whether it affects this shell (shims in PATH, activated, loaded)
+    pub active: bool,
+    /// How it is active, or why not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl EnvironmentReport {
+    /// Indented lines of the doctor's environment section
+    pub fn format_display(&self) -> Vec<String> {
+        let mut lines = vec![format!(" {}", tr(Message::EnvPath))];
+        for entry in &self.path {
+            let mut line = format!("   {}", entry.dir);
+            if !entry.exists {
+                line.push_str(&format!(" {}", tr(Message::PathMissing)));
+            } else if entry.duplicate {
+                line.push_str(&format!(" {}", tr(Message::PathDuplicate)));
+            }
+            if !entry.tools.is_empty() {
+                line.push_str(&format!(" <- {}", entry.tools.join(", ")));
+            }
+            lines.push(line);
+        }
+        lines.push(format!(" {}", tr(Message::EnvManagers)));
+        for manager in &self.managers {
+            let status = match (manager.installed, manager.active) {
+                (_, true) => tr(Message::ManagerActive),
+                (true, false) => tr(Message::ManagerInactive),
+                (false, false) => tr(Message::ManagerNotInstalled),
+            };
+            let detail = manager
+                .detail
+                .as_ref()
+                .map(|detail| format!(" ({})", detail))
+                .unwrap_or_default();
+            lines.push(format!("   {:<7} {}{}", manager.name, status, detail));
+        }
+        if !self.variables.is_empty() {
+            lines.push(format!(" {}", tr(Message::EnvVariables)));
+            for (name, value) in &self.variables {
+                lines.push(format!("   {}={}", name, value));
+            }
+        }
+        lines
+    }
 }
 
 impl DiagnosticSummary {
@@ -263,41 +706,198 @@ impl DiagnosticSummary {
     pub fn format_display(&self) -> String {
         let mut lines = Vec::new();
 
-        lines.push("Toolbox Doctor".to_string());
+        lines.push(tr(Message::DoctorTitle).to_string());
         lines.push("=".repeat(40));
 
         // Config info
         if let Some(ref path) = self.config_path {
             if self.config_exists {
-                lines.push(format!(" Config: {}", path));
+                lines.push(format!(" {}", fill(Message::DoctorConfig, &[path])));
             } else {
-                lines.push(format!(" Config: {} (not found, using defaults)", path));
+                lines.push(format!(" {}", fill(Message::DoctorConfigNotFound, &[path])));
             }
         } else {
-            lines.push(" Config: (no config path available)".to_string());
+            lines.push(format!(" {}", tr(Message::DoctorConfigUnavailable)));
         }
 
         lines.push(String::new());
-        lines.push("Tool Status:".to_string());
+        lines.push(tr(Message::DoctorTools).to_string());
         lines.push("-".repeat(40));
 
         for diag in &self.tools {
             lines.push(diag.format_display());
         }
 
+        lines.push(String::new());
+        lines.push(tr(Message::DoctorEnvironment).to_string());
+        lines.push("-".repeat(40));
+        lines.extend(self.environment.format_display());
+
         lines.push(String::new());
         lines.push("-".repeat(40));
         lines.push(format!(
-            " {} tools checked: {} ok, {} warning, {} error",
-            self.total, self.ok_count, self.warning_count, self.error_count
+            " {}",
+            fill(
+                Message::DoctorSummary,
+                &[
+                    &self.total,
+                    &self.ok_count,
+                    &self.warning_count,
+                    &self.error_count
+                ]
+            )
         ));
 
+        if let Some(ref version) = self.update_available {
+            lines.push(format!(" {}", fill(Message::UpdateAvailable, &[version])));
+        }
+
         lines.join("\n")
     }
 }
 
-/// System resource information
+/// Every step of one tool's detection (`toolbox explain`)
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolExplanation {
+    /// Tool name
+    pub name: String,
+    /// The version command as configured
+    pub command: String,
+    /// The command line as run (in a container, `direnv exec`, `nix develop`,
+    /// without network)
+    pub command_line: String,
+    /// Resolved path of the command binary (from PATH)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command_path: Option<String>,
+    /// Where the binary comes from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ToolSource>,
+    /// Where the tool definition came from and what overrode it
+    pub provenance: ToolProvenance,
+    /// Exit code of the command (`None` if it didn't run or was killed)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Raw standard output
+    pub stdout: String,
+    /// Raw standard error
+    pub stderr: String,
+    /// The tool's `parse_regex`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_regex: Option<String>,
+    /// Groups the regex captured, by name or number (`0` is the whole
+    /// match); `None` if it didn't match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub captures: Option<BTreeMap<String, String>>,
+    /// Version as displayed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Why detection failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Why the version command failed, by kind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<ToolError>,
+    /// The tool's entry in the cache
+    pub cache: CacheState,
+}
+
+/// A tool's entry in a version cache
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheState {
+    /// Whether caching is enabled
+    pub enabled: bool,
+    /// Seconds a result stays fresh
+    pub ttl_seconds: u64,
+    /// The cached result, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<CachedVersion>,
+}
+
+/// A cached detection result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedVersion {
+    /// Cached version (`None` if the tool was unavailable)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Seconds since it was detected
+    pub age_seconds: u64,
+    /// Whether it is older than the TTL
+    pub expired: bool,
+}
+
+impl CacheState {
+    /// `3.12.0, 42s old (ttl 300s)`, `not cached (ttl 300s)` or `disabled`
+    pub fn format_display(&self) -> String {
+        if !self.enabled {
+            return "disabled".to_string();
+        }
+        match self.entry {
+            Some(ref entry) => format!(
+                "{}, {}s old{} (ttl {}s)",
+                entry.version.as_deref().unwrap_or("unavailable"),
+                entry.age_seconds,
+                if entry.expired { ", expired" } else { "" },
+                self.ttl_seconds
+            ),
+            None => format!("not cached (ttl {}s)", self.ttl_seconds),
+        }
+    }
+}
+
+impl ToolExplanation {
+    /// Format the explanation for display
+    pub fn format_display(&self) -> String {
+        let mut lines = vec![self.name.clone(), "=".repeat(40)];
+        lines.push(format!(" Command:   {}", self.command));
+        if self.command_line != self.command {
+            lines.push(format!(" Runs as:   {}", self.command_line));
+        }
+        let path = match (&self.command_path, self.source) {
+            (Some(path), Some(source)) => format!("{} ({})", path, source),
+            (Some(path), None) => path.clone(),
+            (None, _) => "not found in PATH".to_string(),
+        };
+        lines.push(format!(" Path:      {}", path));
+        lines.push(format!(" From:      {}", self.provenance.format_display()));
+        if let Some(code) = self.exit_code {
+            lines.push(format!(" Exit code: {}", code));
+        }
+        for (label, output) in [("Stdout:", &self.stdout), ("Stderr:", &self.stderr)] {
+            if output.trim().is_empty() {
+                lines.push(format!(" {:<10} (empty)", label));
+            } else {
+                lines.push(format!(" {}", label));
+                lines.extend(output.trim_end().lines().map(|line| format!("   {}", line)));
+            }
+        }
+        if let Some(ref regex) = self.parse_regex {
+            lines.push(format!(" Regex:     {}", regex));
+            match self.captures {
+                Some(ref captures) => {
+                    for (group, value) in captures {
+                        lines.push(format!("   {} = {:?}", group, value));
+                    }
+                }
+                // Nothing to match if the command didn't run
+                None if self.exit_code.is_some() => lines.push("   (no match)".to_string()),
+                None => {}
+            }
+        }
+        lines.push(format!(
+            " Version:   {}",
+            self.version.as_deref().unwrap_or("?")
+        ));
+        if let Some(ref error) = self.error {
+            lines.push(format!(" Error:     {}", error));
+        }
+        lines.push(format!(" Cache:     {}", self.cache.format_display()));
+        lines.join("\n")
+    }
+}
+
+/// System resource information
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct SystemInfo {
     /// Memory usage percentage
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -311,236 +911,112 @@ pub struct SystemInfo {
     /// CPU usage percentage
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cpu_percent: Option<f32>,
+    /// Swap usage percentage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap_percent: Option<f32>,
+    /// Total swap in GB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap_total_gb: Option<f32>,
+    /// Used swap in GB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap_used_gb: Option<f32>,
+    /// Per-core CPU usage percentages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_per_core: Option<Vec<f32>>,
+}
+
+impl SystemInfo {
+    /// Get per-core CPU usage as a sparkline string like "▁▃▇▂"
+    pub fn cpu_sparkline(&self) -> Option<String> {
+        self.cpu_per_core
+            .as_ref()
+            .filter(|cores| !cores.is_empty())
+            .map(|cores| sparkline(cores))
+    }
+}
+
+/// Render percentages (0-100) as a string of block characters
+pub fn sparkline(values: &[f32]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    values
+        .iter()
+        .map(|v| {
+            let level = (v.clamp(0.0, 100.0) / 100.0 * (BARS.len() - 1) as f32).round();
+            BARS[level as usize]
+        })
+        .collect()
 }
 
 impl ToolboxInfo {
     /// Create a new empty ToolboxInfo
     pub fn new() -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
             current_dir: None,
             git: None,
             tools: Vec::new(),
             system: None,
             virtual_env: None,
+            virtual_env_python: None,
+            virtual_env_manager: None,
+            conda_env: None,
+            conda_env_count: None,
             shell: None,
+            profile: None,
+            profiles: Vec::new(),
         }
     }
 
-    /// Format for display (simple text format)
-    pub fn format_display(&self, compact: bool, show_icons: bool) -> String {
-        let mut lines = Vec::new();
-        let separator = "─".repeat(15);
-
-        // Current directory
-        if let Some(ref dir) = self.current_dir {
-            let display_dir = if compact {
-                shorten_path(dir)
-            } else {
-                dir.clone()
-            };
-            if show_icons {
-                lines.push(format!(" 📂 {}", display_dir));
-            } else {
-                lines.push(format!(" {}", display_dir));
-            }
+    /// Shape this output as an older schema version (`--schema`)
+    pub fn pin_schema(&mut self, version: SchemaVersion) {
+        match version {
+            // The current version: nothing to convert
+            SchemaVersion::V1 => {}
         }
-
-        // Git info
-        if let Some(ref git) = self.git {
-            let branch_display = if show_icons {
-                format!(" 🌿 {}", git.branch)
-            } else {
-                format!(" {}", git.branch)
-            };
-
-            let mut suffixes = Vec::new();
-            if let Some(summary) = git.changes_summary() {
-                suffixes.push(summary);
-            }
-            if let Some(ab_summary) = git.ahead_behind_summary() {
-                suffixes.push(ab_summary);
-            }
-
-            if !suffixes.is_empty() {
-                lines.push(format!("{} ({})", branch_display, suffixes.join(" ")));
-            } else {
-                lines.push(branch_display);
-            }
-        }
-
-        if !lines.is_empty() {
-            lines.push(separator.clone());
-        }
-
-        // Tools
-        for tool in &self.tools {
-            if !tool.available {
-                continue;
-            }
-
-            let name = if compact {
-                tool.short_name.as_ref().unwrap_or(&tool.name)
-            } else {
-                &tool.name
-            };
-
-            let version = tool.version.as_deref().unwrap_or("?");
-
-            if show_icons {
-                let icon = tool.icon.as_deref().unwrap_or(" ");
-                lines.push(format!(" {} {} {}", icon, name, version));
-            } else {
-                lines.push(format!(" {} {}", name, version));
-            }
-        }
-
-        // Virtual env
-        if let Some(ref venv) = self.virtual_env {
-            if !lines.is_empty() && !self.tools.is_empty() {
-                lines.push(separator.clone());
-            }
-            if show_icons {
-                lines.push(format!(" 🐍 {}", venv));
-            } else {
-                lines.push(format!(" venv: {}", venv));
-            }
-        }
-
-        // System info
-        if let Some(ref sys) = self.system {
-            if !lines.is_empty() {
-                lines.push(separator);
-            }
-            if let Some(mem) = sys.memory_percent {
-                if show_icons {
-                    lines.push(format!(" 💾 {:.0}%", mem));
-                } else {
-                    lines.push(format!(" mem: {:.0}%", mem));
-                }
-            }
-            if let Some(cpu) = sys.cpu_percent {
-                if show_icons {
-                    lines.push(format!(" 🔥 {:.0}%", cpu));
-                } else {
-                    lines.push(format!(" cpu: {:.0}%", cpu));
-                }
-            }
-        }
-
-        lines.join("\n")
-    }
-
-    /// Format for display as a powerline-style colored output
-    /// If single_line is true, all segments are joined in one line
-    /// If false, each segment is on its own line with colored background
-    pub fn format_powerline(
-        &self,
-        compact: bool,
-        show_icons: bool,
-        use_color: bool,
-        single_line: bool,
-        theme: &crate::color::ResolvedTheme,
-    ) -> String {
-        use crate::color::{render_powerline, render_powerline_multiline, Segment};
-
-        let mut segments = Vec::new();
-
-        // Current directory
-        if let Some(ref dir) = self.current_dir {
-            let display_dir = if compact {
-                shorten_path(dir)
-            } else {
-                dir.clone()
-            };
-            let text = if show_icons {
-                format!("📂 {}", display_dir)
-            } else {
-                display_dir
-            };
-            segments.push(Segment::from_theme_colors(
-                text,
-                &theme.directory_fg,
-                &theme.directory_bg,
+        self.schema_version = version.number();
+    }
+
+    /// Powerline segments (default display options) for composing custom
+    /// layouts; render them with the functions in [`crate::color`]
+    pub fn to_segments(&self, theme: &ResolvedTheme) -> Vec<Segment> {
+        format::segments(self, &PowerlineOptions::default(), theme)
+    }
+
+    /// Format per-tool detection times, slowest first, with the total
+    pub fn format_timings(&self) -> String {
+        let mut tools: Vec<&ToolInfo> = self.tools.iter().collect();
+        tools.sort_by_key(|t| std::cmp::Reverse(t.duration_ms));
+
+        let name_width = tools
+            .iter()
+            .map(|t| t.name.len())
+            .chain(["Total".len()])
+            .max()
+            .unwrap_or(0);
+        let mut lines = vec![tr(Message::Timings).to_string()];
+        for tool in &tools {
+            let duration = tool
+                .duration_ms
+                .map(|ms| format!("{:>6} ms", ms))
+                .unwrap_or_else(|| format!("{:>9}", "-"));
+            let note = if tool.cached { " (cached)" } else { "" };
+            lines.push(format!(
+                "  {:<width$}  {}{}",
+                tool.name,
+                duration,
+                note,
+                width = name_width
             ));
         }
 
-        // Git info
-        if let Some(ref git) = self.git {
-            let mut text = if show_icons {
-                format!(" {}", git.branch)
-            } else {
-                git.branch.clone()
-            };
-
-            let mut suffixes = Vec::new();
-            if let Some(summary) = git.changes_summary() {
-                suffixes.push(summary);
-            }
-            if let Some(ab_summary) = git.ahead_behind_summary() {
-                suffixes.push(ab_summary);
-            }
-
-            if !suffixes.is_empty() {
-                text = format!("{} {}", text, suffixes.join(" "));
-            }
-
-            // Use clean/dirty colors from theme
-            if git.is_dirty {
-                segments.push(Segment::from_theme_colors(
-                    text,
-                    &theme.git_dirty_fg,
-                    &theme.git_dirty_bg,
-                ));
-            } else {
-                segments.push(Segment::from_theme_colors(
-                    text,
-                    &theme.git_clean_fg,
-                    &theme.git_clean_bg,
-                ));
-            }
-        }
-
-        // Tools - group them or show individually
-        let available_tools: Vec<_> = self.tools.iter().filter(|t| t.available).collect();
-
-        for (i, tool) in available_tools.iter().enumerate() {
-            let name = if compact {
-                tool.short_name.as_ref().unwrap_or(&tool.name)
-            } else {
-                &tool.name
-            };
-            let version = tool.version.as_deref().unwrap_or("?");
-
-            let text = if show_icons {
-                let icon = tool.icon.as_deref().unwrap_or("");
-                format!("{} {} {}", icon, name, version)
-            } else {
-                format!("{} {}", name, version)
-            };
-
-            let (ref bg, ref fg) = theme.tool_colors[i % theme.tool_colors.len()];
-            segments.push(Segment::from_theme_colors(text, fg, bg));
-        }
-
-        // Virtual env
-        if let Some(ref venv) = self.virtual_env {
-            let text = if show_icons {
-                format!("🐍 {}", venv)
-            } else {
-                format!("venv: {}", venv)
-            };
-            segments.push(Segment::from_theme_colors(
-                text,
-                &theme.venv_fg,
-                &theme.venv_bg,
-            ));
-        }
-
-        if single_line {
-            render_powerline(&segments, use_color)
-        } else {
-            render_powerline_multiline(&segments, use_color)
-        }
+        let total: u64 = self.tools.iter().filter_map(|t| t.duration_ms).sum();
+        lines.push(format!(
+            "  {:<width$}  {:>6} ms",
+            "Total",
+            total,
+            width = name_width
+        ));
+        lines.join("\n")
     }
 }
 
@@ -550,29 +1026,10 @@ impl Default for ToolboxInfo {
     }
 }
 
-/// Shorten a path for compact display
-fn shorten_path(path: &str) -> String {
-    // Replace home directory with ~
-    if let Some(home) = dirs::home_dir() {
-        if let Some(home_str) = home.to_str() {
-            if path.starts_with(home_str) {
-                return path.replacen(home_str, "~", 1);
-            }
-        }
-    }
-
-    // If path is too long, show only last 2 components
-    let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-    if parts.len() > 2 {
-        format!("…/{}", parts[parts.len() - 2..].join("/"))
-    } else {
-        path.to_string()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Section;
 
     // ToolInfo tests
     #[test]
@@ -736,104 +1193,58 @@ mod tests {
     }
 
     #[test]
-    fn test_toolbox_info_format_display_empty() {
-        let info = ToolboxInfo::new();
-        let output = info.format_display(true, true);
-        assert!(output.is_empty());
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_with_tools() {
-        let mut info = ToolboxInfo::new();
-        info.tools.push(
-            ToolInfo::available("Rust".to_string(), "1.75.0".to_string())
-                .with_icon(Some("🦀".to_string()))
-                .with_short_name(Some("rust".to_string())),
-        );
-
-        let output = info.format_display(true, true);
-        assert!(output.contains("🦀"));
-        assert!(output.contains("rust"));
-        assert!(output.contains("1.75.0"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_no_icons() {
+    fn test_toolbox_info_to_segments() {
         let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/home/user/project".to_string());
         info.tools.push(
             ToolInfo::available("Rust".to_string(), "1.75.0".to_string())
                 .with_icon(Some("🦀".to_string())),
         );
+        let segments = info.to_segments(&ResolvedTheme::default_theme());
 
-        let output = info.format_display(false, false);
-        assert!(!output.contains("🦀"));
-        assert!(output.contains("Rust"));
-        assert!(output.contains("1.75.0"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_with_git() {
-        let mut info = ToolboxInfo::new();
-        info.git = Some(GitInfo {
-            branch: "main".to_string(),
-            modified_count: Some(2),
-            staged_count: None,
-            untracked_count: None,
-            is_dirty: true,
-            ahead: None,
-            behind: None,
-        });
-
-        let output = info.format_display(true, true);
-        assert!(output.contains("main"));
-        assert!(output.contains("+2"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_unavailable_tools_hidden() {
-        let mut info = ToolboxInfo::new();
-        info.tools.push(ToolInfo::unavailable(
-            "Ruby".to_string(),
-            Some("not found".to_string()),
-        ));
-
-        let output = info.format_display(true, true);
-        assert!(!output.contains("Ruby"));
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].section, Some(Section::Dir));
+        assert_eq!(segments[0].icon.as_deref(), Some("📂"));
+        assert_eq!(segments[0].text, "/home/user/project");
+        assert_eq!(segments[1].section, Some(Section::Tools));
+        assert_eq!(segments[1].label(), "🦀 Rust 1.75.0");
+        assert!(segments[1].priority > segments[0].priority);
     }
 
-    // shorten_path tests
+    // SystemInfo tests
     #[test]
-    fn test_shorten_path_long_path() {
-        let path = "/very/long/path/to/project";
-        let shortened = shorten_path(path);
-        assert_eq!(shortened, "…/to/project");
+    fn test_system_info_default() {
+        let sys = SystemInfo::default();
+        assert!(sys.memory_percent.is_none());
+        assert!(sys.cpu_percent.is_none());
+        assert!(sys.swap_percent.is_none());
+        assert!(sys.cpu_per_core.is_none());
     }
 
     #[test]
-    fn test_shorten_path_short_path() {
-        let path = "/short/path";
-        let shortened = shorten_path(path);
-        assert_eq!(shortened, "/short/path");
+    fn test_sparkline_levels() {
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0]), "▁▅█");
+        assert_eq!(sparkline(&[]), "");
     }
 
     #[test]
-    fn test_shorten_path_root() {
-        let path = "/";
-        let shortened = shorten_path(path);
-        assert_eq!(shortened, "/");
+    fn test_sparkline_clamps_out_of_range() {
+        assert_eq!(sparkline(&[-10.0, 150.0]), "▁█");
     }
 
-    // SystemInfo tests
     #[test]
-    fn test_system_info_default() {
+    fn test_system_info_cpu_sparkline() {
         let sys = SystemInfo {
-            memory_percent: None,
-            memory_total_gb: None,
-            memory_used_gb: None,
-            cpu_percent: None,
+            cpu_per_core: Some(vec![0.0, 30.0, 90.0, 15.0]),
+            ..Default::default()
         };
-        assert!(sys.memory_percent.is_none());
-        assert!(sys.cpu_percent.is_none());
+        assert_eq!(sys.cpu_sparkline(), Some("▁▃▇▂".to_string()));
+
+        let empty = SystemInfo {
+            cpu_per_core: Some(vec![]),
+            ..Default::default()
+        };
+        assert!(empty.cpu_sparkline().is_none());
     }
 
     // Serialization tests
@@ -864,284 +1275,38 @@ mod tests {
         assert_eq!(parsed.modified_count, git.modified_count);
     }
 
-    // --- format_display additional tests ---
-
-    #[test]
-    fn test_toolbox_info_format_display_with_virtual_env() {
-        let mut info = ToolboxInfo::new();
-        info.virtual_env = Some("myenv".to_string());
-        info.tools.push(ToolInfo::available(
-            "Python".to_string(),
-            "3.12.0".to_string(),
-        ));
-
-        let output = info.format_display(false, true);
-        assert!(output.contains("myenv"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_virtual_env_no_icons() {
-        let mut info = ToolboxInfo::new();
-        info.virtual_env = Some("myenv".to_string());
-        info.tools.push(ToolInfo::available(
-            "Python".to_string(),
-            "3.12.0".to_string(),
-        ));
-
-        let output = info.format_display(false, false);
-        assert!(output.contains("venv: myenv"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_with_system_info() {
-        let mut info = ToolboxInfo::new();
-        info.system = Some(SystemInfo {
-            memory_percent: Some(50.0),
-            memory_total_gb: Some(16.0),
-            memory_used_gb: Some(8.0),
-            cpu_percent: Some(25.0),
-        });
-
-        let output = info.format_display(false, true);
-        assert!(output.contains("50%"));
-        assert!(output.contains("25%"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_system_no_icons() {
-        let mut info = ToolboxInfo::new();
-        info.system = Some(SystemInfo {
-            memory_percent: Some(75.0),
-            memory_total_gb: None,
-            memory_used_gb: None,
-            cpu_percent: Some(50.0),
-        });
-
-        let output = info.format_display(false, false);
-        assert!(output.contains("mem: 75%"));
-        assert!(output.contains("cpu: 50%"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_with_current_dir() {
-        let mut info = ToolboxInfo::new();
-        info.current_dir = Some("/home/user/project".to_string());
-
-        let output = info.format_display(false, true);
-        assert!(output.contains("/home/user/project"));
-    }
-
     #[test]
-    fn test_toolbox_info_format_display_compact_dir() {
+    fn test_format_timings_sorted() {
         let mut info = ToolboxInfo::new();
-        info.current_dir = Some("/very/long/path/to/project".to_string());
-
-        let output = info.format_display(true, false);
-        assert!(output.contains("to/project"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_git_clean() {
-        let mut info = ToolboxInfo::new();
-        info.git = Some(GitInfo {
-            branch: "main".to_string(),
-            modified_count: Some(0),
-            staged_count: Some(0),
-            untracked_count: Some(0),
-            is_dirty: false,
-            ahead: None,
-            behind: None,
-        });
-
-        let output = info.format_display(false, true);
-        assert!(output.contains("main"));
-        // Clean repo should not show change count
-        assert!(!output.contains("+"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_git_ahead_behind() {
-        let mut info = ToolboxInfo::new();
-        info.git = Some(GitInfo {
-            branch: "feature".to_string(),
-            modified_count: Some(0),
-            staged_count: Some(0),
-            untracked_count: Some(0),
-            is_dirty: false,
-            ahead: Some(3),
-            behind: Some(1),
-        });
-
-        let output = info.format_display(false, true);
-        assert!(output.contains("feature"));
-        assert!(output.contains("\u{2191}3")); // ↑3
-        assert!(output.contains("\u{2193}1")); // ↓1
-    }
-
-    #[test]
-    fn test_toolbox_info_format_display_separator_between_sections() {
-        let mut info = ToolboxInfo::new();
-        info.current_dir = Some("/tmp".to_string());
-        info.tools.push(ToolInfo::available(
-            "Rust".to_string(),
-            "1.75.0".to_string(),
-        ));
-
-        let output = info.format_display(false, true);
-        assert!(output.contains("\u{2500}")); // ─ separator
-    }
-
-    // --- format_powerline tests ---
-
-    #[test]
-    fn test_toolbox_info_format_powerline_empty() {
-        let info = ToolboxInfo::new();
-        let output = info.format_powerline(
-            false,
-            true,
-            false,
-            true,
-            &crate::color::ResolvedTheme::default_theme(),
-        );
-        assert!(output.is_empty());
-    }
-
-    #[test]
-    fn test_toolbox_info_format_powerline_with_tools_no_color() {
-        let mut info = ToolboxInfo::new();
-        info.tools.push(
-            ToolInfo::available("Rust".to_string(), "1.75.0".to_string())
-                .with_icon(Some("\u{1f980}".to_string())),
-        );
-
-        let output = info.format_powerline(
-            false,
-            true,
-            false,
-            true,
-            &crate::color::ResolvedTheme::default_theme(),
-        );
-        assert!(output.contains("Rust"));
-        assert!(output.contains("1.75.0"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_powerline_with_color() {
-        let mut info = ToolboxInfo::new();
-        info.tools.push(ToolInfo::available(
-            "Rust".to_string(),
-            "1.75.0".to_string(),
-        ));
-
-        let output = info.format_powerline(
-            false,
-            false,
-            true,
-            true,
-            &crate::color::ResolvedTheme::default_theme(),
-        );
-        assert!(output.contains("\x1b[")); // ANSI codes
-        assert!(output.contains("Rust"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_powerline_multiline() {
-        let mut info = ToolboxInfo::new();
-        info.tools
-            .push(ToolInfo::available("A".to_string(), "1.0".to_string()));
-        info.tools
-            .push(ToolInfo::available("B".to_string(), "2.0".to_string()));
-
-        let output = info.format_powerline(
-            false,
-            false,
-            true,
-            false,
-            &crate::color::ResolvedTheme::default_theme(),
-        );
-        assert!(output.contains('\n'));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_powerline_git_clean() {
-        let mut info = ToolboxInfo::new();
-        info.git = Some(GitInfo {
-            branch: "main".to_string(),
-            modified_count: Some(0),
-            staged_count: Some(0),
-            untracked_count: Some(0),
-            is_dirty: false,
-            ahead: None,
-            behind: None,
-        });
-
-        // Green segment for clean repo (no color for easy assertion)
-        let output = info.format_powerline(
-            false,
-            false,
-            false,
-            true,
-            &crate::color::ResolvedTheme::default_theme(),
-        );
-        assert!(output.contains("main"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_powerline_git_dirty() {
-        let mut info = ToolboxInfo::new();
-        info.git = Some(GitInfo {
-            branch: "dev".to_string(),
-            modified_count: Some(3),
-            staged_count: Some(0),
-            untracked_count: Some(0),
-            is_dirty: true,
-            ahead: None,
-            behind: None,
-        });
+        for (name, ms, cached) in [("Node", 3, true), ("Docker", 152, false), ("Go", 48, false)] {
+            let mut tool = ToolInfo::available(name.to_string(), "1.0".to_string());
+            tool.duration_ms = Some(ms);
+            tool.cached = cached;
+            info.tools.push(tool);
+        }
 
-        let output = info.format_powerline(
-            false,
-            false,
-            false,
-            true,
-            &crate::color::ResolvedTheme::default_theme(),
+        assert_eq!(
+            info.format_timings(),
+            "Detection timings (slowest first):\n\
+             \x20 Docker     152 ms\n\
+             \x20 Go          48 ms\n\
+             \x20 Node         3 ms (cached)\n\
+             \x20 Total      203 ms"
         );
-        assert!(output.contains("dev"));
-        assert!(output.contains("+3"));
     }
 
     #[test]
-    fn test_toolbox_info_format_powerline_compact() {
-        let mut info = ToolboxInfo::new();
-        info.tools.push(
-            ToolInfo::available("Python".to_string(), "3.12.0".to_string())
-                .with_short_name(Some("py".to_string())),
-        );
+    fn test_tool_info_timing_json() {
+        let mut tool = ToolInfo::available("Go".to_string(), "1.22".to_string());
+        let json = serde_json::to_string(&tool).unwrap();
+        assert!(!json.contains("duration_ms"));
+        assert!(!json.contains("cached"));
 
-        let output = info.format_powerline(
-            true,
-            false,
-            false,
-            true,
-            &crate::color::ResolvedTheme::default_theme(),
-        );
-        assert!(output.contains("py"));
-        assert!(!output.contains("Python"));
-    }
-
-    #[test]
-    fn test_toolbox_info_format_powerline_virtual_env() {
-        let mut info = ToolboxInfo::new();
-        info.virtual_env = Some("myenv".to_string());
-
-        let output = info.format_powerline(
-            false,
-            false,
-            false,
-            true,
-            &crate::color::ResolvedTheme::default_theme(),
-        );
-        assert!(output.contains("venv: myenv"));
+        tool.duration_ms = Some(12);
+        tool.cached = true;
+        let json = serde_json::to_string(&tool).unwrap();
+        assert!(json.contains("\"duration_ms\":12"));
+        assert!(json.contains("\"cached\":true"));
     }
 
     // --- ToolboxInfo JSON roundtrip ---
@@ -1165,12 +1330,28 @@ mod tests {
         });
 
         let json = serde_json::to_string(&info).unwrap();
+        assert!(json.starts_with("{\"schema_version\":1,"));
         let parsed: ToolboxInfo = serde_json::from_str(&json).unwrap();
         assert_eq!(parsed.current_dir, info.current_dir);
         assert_eq!(parsed.tools.len(), 1);
         assert!(parsed.git.is_some());
     }
 
+    #[test]
+    fn test_schema_version() {
+        // Output from before versioning is version 1
+        let info: ToolboxInfo = serde_json::from_str(r#"{"tools": []}"#).unwrap();
+        assert_eq!(info.schema_version, 1);
+
+        assert_eq!("v1".parse::<SchemaVersion>(), Ok(SchemaVersion::V1));
+        assert_eq!("1".parse::<SchemaVersion>(), Ok(SchemaVersion::V1));
+        assert!("v2".parse::<SchemaVersion>().is_err());
+
+        let mut info = ToolboxInfo::new();
+        info.pin_schema(SchemaVersion::V1);
+        assert_eq!(info.schema_version, 1);
+    }
+
     // --- SystemInfo JSON roundtrip ---
 
     #[test]
@@ -1180,6 +1361,7 @@ mod tests {
             memory_total_gb: Some(16.0),
             memory_used_gb: Some(10.48),
             cpu_percent: Some(42.0),
+            ..Default::default()
         };
         let json = serde_json::to_string(&sys).unwrap();
         let parsed: SystemInfo = serde_json::from_str(&json).unwrap();
@@ -1187,28 +1369,6 @@ mod tests {
         assert_eq!(parsed.cpu_percent, sys.cpu_percent);
     }
 
-    // --- Multiple available and unavailable tools ---
-
-    #[test]
-    fn test_format_display_mixed_tools() {
-        let mut info = ToolboxInfo::new();
-        info.tools.push(ToolInfo::available(
-            "Rust".to_string(),
-            "1.75.0".to_string(),
-        ));
-        info.tools.push(ToolInfo::unavailable(
-            "Ruby".to_string(),
-            Some("not found".to_string()),
-        ));
-        info.tools
-            .push(ToolInfo::available("Go".to_string(), "1.21.0".to_string()));
-
-        let output = info.format_display(false, false);
-        assert!(output.contains("Rust"));
-        assert!(output.contains("Go"));
-        assert!(!output.contains("Ruby")); // Unavailable hidden
-    }
-
     // --- DiagnosticStatus tests ---
 
     #[test]
@@ -1229,6 +1389,13 @@ mod tests {
             status: DiagnosticStatus::Ok,
             command: "rustc --version".to_string(),
             command_path: Some("/usr/bin/rustc".to_string()),
+            shim: None,
+            path_matches: Vec::new(),
+            source: None,
+            package: None,
+            pinned: None,
+            retries: None,
+            provenance: None,
             version: Some("1.75.0".to_string()),
             error_detail: None,
             suggestion: None,
@@ -1243,6 +1410,103 @@ mod tests {
         assert!(!output.contains("disabled"));
     }
 
+    #[test]
+    fn test_format_installed() {
+        let mut tool = ToolInfo::available("Python".to_string(), "3.12.1".to_string())
+            .with_path(Some("/home/me/.asdf/shims/python".to_string()))
+            .with_source(Some(ToolSource::Asdf));
+        assert!(tool
+            .format_installed()
+            .ends_with("\nNo installations found\n"));
+
+        tool.installed_versions = vec![
+            InstalledVersion {
+                version: Some("3.10.12".to_string()),
+                path: "/usr/bin/python3.10".to_string(),
+                source: Some(ToolSource::System),
+                active: false,
+            },
+            InstalledVersion {
+                version: Some("3.12.1".to_string()),
+                path: "/home/me/.asdf/installs/python/3.12.1/bin/python".to_string(),
+                source: Some(ToolSource::Asdf),
+                active: true,
+            },
+            InstalledVersion {
+                version: None,
+                path: "/opt/python9".to_string(),
+                source: None,
+                active: false,
+            },
+        ];
+        assert_eq!(
+            tool.format_installed(),
+            "Python 3.12.1 (/home/me/.asdf/shims/python, asdf)\n\
+             \n\
+             Installed versions:\n\
+             \x20   3.10.12  /usr/bin/python3.10 (system)\n\
+             \x20 * 3.12.1   /home/me/.asdf/installs/python/3.12.1/bin/python (asdf)\n\
+             \x20   ?        /opt/python9\n"
+        );
+
+        let missing = ToolInfo::unavailable("Go".to_string(), Some("go: not found".to_string()));
+        assert!(missing
+            .format_installed()
+            .starts_with("Go not available (go: not found)\n"));
+    }
+
+    #[test]
+    fn test_diagnostic_format_shim() {
+        let mut diag = ToolDiagnostic {
+            name: "Node.js".to_string(),
+            icon: None,
+            status: DiagnosticStatus::Ok,
+            command: "node --version".to_string(),
+            command_path: Some("/home/me/.asdf/shims/node".to_string()),
+            shim: Some(ShimInfo {
+                manager: "asdf".to_string(),
+                real_path: Some("/home/me/.asdf/installs/nodejs/20.11.0/bin/node".to_string()),
+                plugin: Some("nodejs".to_string()),
+                version: Some("20.11.0".to_string()),
+                source: Some("/home/me/app/.tool-versions".to_string()),
+            }),
+            path_matches: Vec::new(),
+            source: None,
+            package: None,
+            pinned: None,
+            retries: None,
+            provenance: None,
+            version: Some("v20.11.0".to_string()),
+            error_detail: None,
+            suggestion: None,
+            enabled: true,
+        };
+        let output = diag.format_display();
+        assert!(output.contains("(/home/me/.asdf/shims/node) v20.11.0"));
+        assert!(output.ends_with(
+            "shim: asdf -> /home/me/.asdf/installs/nodejs/20.11.0/bin/node \
+             (nodejs 20.11.0 from /home/me/app/.tool-versions)"
+        ));
+
+        diag.shim = Some(ShimInfo {
+            manager: "mise".to_string(),
+            real_path: None,
+            plugin: None,
+            version: None,
+            source: None,
+        });
+        assert!(diag.format_display().ends_with("shim: mise -> unresolved"));
+
+        diag.package = Some(PackageOrigin {
+            manager: crate::package::PackageManager::Homebrew,
+            package: "node".to_string(),
+            version: Some("20.11.0".to_string()),
+        });
+        assert!(diag
+            .format_display()
+            .ends_with("package: homebrew node 20.11.0 (upgrade: brew upgrade node)"));
+    }
+
     #[test]
     fn test_diagnostic_format_ok_disabled() {
         let diag = ToolDiagnostic {
@@ -1251,6 +1515,13 @@ mod tests {
             status: DiagnosticStatus::Ok,
             command: "ruby --version".to_string(),
             command_path: Some("/usr/bin/ruby".to_string()),
+            shim: None,
+            path_matches: Vec::new(),
+            source: None,
+            package: None,
+            pinned: None,
+            retries: None,
+            provenance: None,
             version: Some("3.2.0".to_string()),
             error_detail: None,
             suggestion: None,
@@ -1259,6 +1530,19 @@ mod tests {
 
         let output = diag.format_display();
         assert!(output.contains("(disabled)"));
+        assert!(!output.contains("from:"));
+
+        // Why it is disabled
+        let diag = ToolDiagnostic {
+            provenance: Some(ToolProvenance {
+                origin: crate::config::ToolOrigin::Default,
+                overrides: vec!["enabled = false".to_string()],
+            }),
+            ..diag
+        };
+        assert!(diag
+            .format_display()
+            .ends_with("\n      from: built-in default, tool_overrides: enabled = false"));
     }
 
     #[test]
@@ -1269,6 +1553,13 @@ mod tests {
             status: DiagnosticStatus::Warning,
             command: "java --version".to_string(),
             command_path: Some("/usr/bin/java".to_string()),
+            shim: None,
+            path_matches: Vec::new(),
+            source: None,
+            package: None,
+            pinned: None,
+            retries: None,
+            provenance: None,
             version: Some("java 21.0.1 2023-10-17".to_string()),
             error_detail: Some("regex did not match".to_string()),
             suggestion: Some("Check parse_regex".to_string()),
@@ -1289,6 +1580,13 @@ mod tests {
             status: DiagnosticStatus::Error,
             command: "docker --version".to_string(),
             command_path: None,
+            shim: None,
+            path_matches: Vec::new(),
+            source: None,
+            package: None,
+            pinned: None,
+            retries: None,
+            provenance: None,
             version: None,
             error_detail: Some("command not found: 'docker'".to_string()),
             suggestion: Some("Install Docker or add it to your PATH".to_string()),
@@ -1314,6 +1612,8 @@ mod tests {
             warning_count: 0,
             error_count: 0,
             tools: vec![],
+            environment: EnvironmentReport::default(),
+            update_available: None,
         };
 
         let output = summary.format_display();
@@ -1338,6 +1638,13 @@ mod tests {
                     status: DiagnosticStatus::Ok,
                     command: "rustc --version".to_string(),
                     command_path: Some("/usr/bin/rustc".to_string()),
+                    shim: None,
+                    path_matches: Vec::new(),
+                    source: None,
+                    package: None,
+                    pinned: None,
+                    retries: None,
+                    provenance: None,
                     version: Some("1.75.0".to_string()),
                     error_detail: None,
                     suggestion: None,
@@ -1349,6 +1656,13 @@ mod tests {
                     status: DiagnosticStatus::Ok,
                     command: "python3 --version".to_string(),
                     command_path: Some("/usr/bin/python3".to_string()),
+                    shim: None,
+                    path_matches: Vec::new(),
+                    source: None,
+                    package: None,
+                    pinned: None,
+                    retries: None,
+                    provenance: None,
                     version: Some("3.12.0".to_string()),
                     error_detail: None,
                     suggestion: None,
@@ -1360,12 +1674,43 @@ mod tests {
                     status: DiagnosticStatus::Error,
                     command: "docker --version".to_string(),
                     command_path: None,
+                    shim: None,
+                    path_matches: Vec::new(),
+                    source: None,
+                    package: None,
+                    pinned: None,
+                    retries: None,
+                    provenance: None,
                     version: None,
                     error_detail: Some("not found".to_string()),
                     suggestion: None,
                     enabled: true,
                 },
             ],
+            environment: EnvironmentReport {
+                path: vec![
+                    PathEntry {
+                        dir: "/usr/bin".to_string(),
+                        exists: true,
+                        duplicate: false,
+                        tools: vec!["Rust".to_string(), "Python".to_string()],
+                    },
+                    PathEntry {
+                        dir: "/opt/gone/bin".to_string(),
+                        exists: false,
+                        duplicate: false,
+                        tools: Vec::new(),
+                    },
+                ],
+                managers: vec![ManagerStatus {
+                    name: "direnv".to_string(),
+                    installed: true,
+                    active: false,
+                    detail: Some("no .envrc loaded".to_string()),
+                }],
+                variables: BTreeMap::from([("SHELL".to_string(), "/bin/zsh".to_string())]),
+            },
+            update_available: None,
         };
 
         let output = summary.format_display();
@@ -1373,6 +1718,9 @@ mod tests {
         assert!(output.contains("Config:"));
         assert!(!output.contains("not found, using defaults"));
         assert!(output.contains("3 tools checked: 2 ok, 0 warning, 1 error"));
+        assert!(output.contains("   /usr/bin <- Rust, Python\n   /opt/gone/bin (missing)"));
+        assert!(output.contains("   direnv  inactive (no .envrc loaded)"));
+        assert!(output.contains("   SHELL=/bin/zsh"));
     }
 
     // --- DiagnosticSummary JSON roundtrip ---
@@ -1392,11 +1740,20 @@ mod tests {
                 status: DiagnosticStatus::Ok,
                 command: "echo test".to_string(),
                 command_path: Some("/bin/echo".to_string()),
+                shim: None,
+                path_matches: Vec::new(),
+                source: None,
+                package: None,
+                pinned: None,
+                retries: None,
+                provenance: None,
                 version: Some("test".to_string()),
                 error_detail: None,
                 suggestion: None,
                 enabled: true,
             }],
+            environment: EnvironmentReport::default(),
+            update_available: None,
         };
 
         let json = serde_json::to_string(&summary).unwrap();
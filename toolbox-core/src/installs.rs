@@ -0,0 +1,221 @@
+//! Installed versions of a tool
+//!
+//! Besides the binary that runs, a tool is often installed several times:
+//! version-suffixed binaries in PATH (`python3.10`, `python3.12`) and the
+//! versions kept by version managers (asdf, mise, nvm, pyenv, rbenv, rustup).
+//! `toolbox tool info` lists them and marks the active one.
+
+use crate::source::ToolSource;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// A directory with one subdirectory per installed version, each holding
+/// `bin/<command>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionDir {
+    pub dir: PathBuf,
+    pub source: ToolSource,
+}
+
+/// A version manager install of a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManagedInstall {
+    /// Version directory name (`v` prefix removed)
+    pub version: String,
+    pub path: PathBuf,
+    pub source: ToolSource,
+}
+
+/// Version-suffixed variants of a command in PATH (`python3.10`, `python3.12`
+/// for `python3`) by name, the first one of each name in PATH
+pub fn suffixed_binaries(cmd: &str, path_var: &OsStr) -> Vec<PathBuf> {
+    let base = cmd.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if base.is_empty() || cmd.contains(std::path::MAIN_SEPARATOR) {
+        return Vec::new();
+    }
+    let mut names = std::collections::BTreeMap::new();
+    for dir in std::env::split_paths(path_var) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let Some(name) = file_name.to_str() else {
+                continue;
+            };
+            let is_variant = name.strip_prefix(base).is_some_and(|suffix| {
+                suffix.starts_with(|c: char| c.is_ascii_digit())
+                    && suffix.chars().all(|c| c.is_ascii_digit() || c == '.')
+            });
+            let path = entry.path();
+            if is_variant && !names.contains_key(name) && crate::detector::is_executable(&path) {
+                names.insert(name.to_string(), path);
+            }
+        }
+    }
+    names.into_values().collect()
+}
+
+/// Version directories of the version managers under a home directory
+/// (`env` overrides their default locations, e.g. `ASDF_DATA_DIR`)
+pub fn version_dirs(home: &Path, env: &dyn Fn(&str) -> Option<String>) -> Vec<VersionDir> {
+    let root = |var: &str, default: &str| {
+        env(var)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(default))
+    };
+    let mut dirs = Vec::new();
+    // asdf and mise keep one directory per plugin
+    for (data_dir, source) in [
+        (root("ASDF_DATA_DIR", ".asdf"), ToolSource::Asdf),
+        (root("MISE_DATA_DIR", ".local/share/mise"), ToolSource::Mise),
+    ] {
+        for plugin in subdirs(&data_dir.join("installs")) {
+            dirs.push(VersionDir {
+                dir: plugin,
+                source,
+            });
+        }
+    }
+    for (dir, source) in [
+        (
+            root("NVM_DIR", ".nvm").join("versions/node"),
+            ToolSource::Nvm,
+        ),
+        (
+            root("PYENV_ROOT", ".pyenv").join("versions"),
+            ToolSource::Pyenv,
+        ),
+        (
+            root("RBENV_ROOT", ".rbenv").join("versions"),
+            ToolSource::Rbenv,
+        ),
+        (
+            root("RUSTUP_HOME", ".rustup").join("toolchains"),
+            ToolSource::Rustup,
+        ),
+    ] {
+        if dir.is_dir() {
+            dirs.push(VersionDir { dir, source });
+        }
+    }
+    dirs
+}
+
+/// Installs of `cmd` in version directories, by version within each
+pub fn managed_installs(cmd: &str, dirs: &[VersionDir]) -> Vec<ManagedInstall> {
+    dirs.iter()
+        .flat_map(|version_dir| {
+            subdirs(&version_dir.dir)
+                .into_iter()
+                .filter_map(move |dir| {
+                    let path = dir.join("bin").join(cmd);
+                    if !crate::detector::is_executable(&path) {
+                        return None;
+                    }
+                    let name = dir.file_name()?.to_string_lossy();
+                    Some(ManagedInstall {
+                        version: name.strip_prefix('v').unwrap_or(&name).to_string(),
+                        path,
+                        source: version_dir.source,
+                    })
+                })
+        })
+        .collect()
+}
+
+/// Subdirectories, sorted; symlinks (mise's `latest`, `20` aliases) skipped
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn executable(path: &Path) {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_suffixed_binaries() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        for name in ["python3", "python3.12", "python3-config", "pythonista"] {
+            executable(&first.path().join(name));
+        }
+        executable(&second.path().join("python3.10"));
+        executable(&second.path().join("python3.12"));
+        std::fs::write(second.path().join("python2.7"), "").unwrap();
+
+        let path_var = std::env::join_paths([first.path(), second.path()]).unwrap();
+        assert_eq!(
+            suffixed_binaries("python3", &path_var),
+            vec![
+                first.path().join("python3"),
+                second.path().join("python3.10"),
+                first.path().join("python3.12"),
+            ]
+        );
+        assert!(suffixed_binaries("7", &path_var).is_empty());
+    }
+
+    #[test]
+    fn test_managed_installs() {
+        let home = tempfile::tempdir().unwrap();
+        let h = home.path();
+        executable(&h.join(".asdf/installs/nodejs/18.19.0/bin/node"));
+        executable(&h.join(".asdf/installs/nodejs/20.11.0/bin/node"));
+        executable(&h.join(".asdf/installs/python/3.12.1/bin/python"));
+        executable(&h.join(".nvm/versions/node/v16.20.2/bin/node"));
+        std::os::unix::fs::symlink(
+            h.join(".asdf/installs/nodejs/20.11.0"),
+            h.join(".asdf/installs/nodejs/latest"),
+        )
+        .unwrap();
+
+        let dirs = version_dirs(h, &|_| None);
+        assert_eq!(
+            dirs.iter().map(|d| d.source).collect::<Vec<_>>(),
+            vec![ToolSource::Asdf, ToolSource::Asdf, ToolSource::Nvm]
+        );
+        let installs = managed_installs("node", &dirs);
+        let versions: Vec<(&str, ToolSource)> = installs
+            .iter()
+            .map(|i| (i.version.as_str(), i.source))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                ("18.19.0", ToolSource::Asdf),
+                ("20.11.0", ToolSource::Asdf),
+                ("16.20.2", ToolSource::Nvm),
+            ]
+        );
+        assert_eq!(
+            installs[2].path,
+            h.join(".nvm/versions/node/v16.20.2/bin/node")
+        );
+
+        // Overridden locations
+        let nvm = tempfile::tempdir().unwrap();
+        executable(&nvm.path().join("versions/node/v21.0.0/bin/node"));
+        let nvm_dir = nvm.path().to_string_lossy().into_owned();
+        let dirs = version_dirs(h, &|var| (var == "NVM_DIR").then(|| nvm_dir.clone()));
+        let installs = managed_installs("node", &dirs);
+        assert_eq!(installs.last().unwrap().version, "21.0.0");
+    }
+}
@@ -1,24 +1,113 @@
 //! toolbox-core: Core library for tool version detection and system info
 //!
 //! This library provides:
-//! - Configuration loading and management
+//! - Configuration loading, management and migration (includes, per-OS sections)
+//! - Config hot-reload for long-running commands (`watch`, `serve`)
+//! - Fully commented default config for `toolbox init` (`schema` feature)
+//! - Importing tool definitions from starship, mise and asdf configs
 //! - Tool version detection (Python, Node, Docker, etc.)
+//! - Structured version parsing (named regex groups, display templates, rhai
+//!   scripts with the `scripting` feature)
+//! - Custom detectors for embedding programs (`Detector` trait)
+//! - Async concurrent detection with timeouts (`tokio` feature)
+//! - Cancelling a running detection (`CancellationToken`)
+//! - External detector plugins (`toolbox-detect-<name>` executables)
+//! - Sandboxed WASM detector and formatter plugins (`plugins` feature)
 //! - Directory-aware version detection (asdf, mise, nvm support)
+//! - Version detection inside container images
+//! - Running detection inside direnv / Nix flake project environments
+//! - Sandboxed version commands (allowlisted environment, no network)
+//! - Trust store for project-local configs (`toolbox trust`)
+//! - asdf/mise shim resolution for diagnostics
+//! - Active rustup toolchain and what selected it
+//! - Go module info (module path, `go` directive vs the installed Go)
+//! - Corepack `packageManager` pins checked against detected versions
+//! - Version source attribution (system, Homebrew, asdf, venv...)
+//! - Package origin lookup (Homebrew, apt, Scoop, winget) with upgrade commands
+//! - Listing every installed version of a tool
+//! - Doctor's environment report (PATH, version managers, variables)
+//! - Step-by-step explanation of a tool's detection (`toolbox explain`)
+//! - Python virtual environment details (Python version, package manager)
 //! - Git repository information
 //! - System resource information
+//! - Localized labels and messages (`language`: en, ja)
+//! - Output formats behind a `Formatter` trait and name-keyed registry
+//! - SBOM-style export of the detected toolchain
+//! - Environment snapshots and drift detection
+//! - Version change history journal
+//! - SQLite store for the prompt cache, history and snapshots (`sqlite` feature)
+//! - Version requirement checks for CI gating
+//! - Detection benchmarking
+//! - MCP server for coding agents
+//! - HTTP JSON API server
+//! - Shell prompt integration
+//! - Self-update from GitHub releases (checksum-verified) and update notices
+//! - JSON Schema for the config file and JSON output
+//! - CLI capabilities for the Zellij plugin handshake
+//! - Zellij layout generation and plugin installation
 
+pub mod bench;
 pub mod cache;
+pub mod cancel;
+pub mod capabilities;
 pub mod color;
 pub mod config;
+pub mod config_edit;
+pub mod config_merge;
+pub mod config_migrate;
+#[cfg(feature = "schema")]
+pub mod config_template;
+pub mod config_validate;
+pub mod config_watch;
+pub mod container;
+pub mod corepack;
 pub mod detector;
+pub mod env_report;
 pub mod error;
+pub mod export;
+pub mod external;
+pub mod format;
+pub mod gomod;
+pub mod history;
+pub mod import;
 pub mod info;
+pub mod installs;
+pub mod mcp;
+pub mod messages;
+pub mod package;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod project_env;
+pub mod prompt;
+pub mod require;
+pub mod rustup;
+pub mod sandbox;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod serve;
+pub mod shim;
+pub mod snapshot;
+pub mod source;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod template;
+pub mod trust;
+pub mod update;
+pub mod venv;
+pub mod zellij;
 
 pub use cache::VersionCache;
-pub use color::ResolvedTheme;
+pub use cancel::CancellationToken;
+pub use color::{ResolvedTheme, Segment};
 pub use config::Config;
-pub use detector::ToolDetector;
-pub use error::ToolboxError;
+pub use detector::{DetectEvent, Detector, ToolDetector};
+pub use error::{ToolError, ToolboxError};
+pub use format::{FormatOptions, FormatRegistry, Formatter, PowerlineOptions};
 pub use info::{
-    DiagnosticStatus, DiagnosticSummary, GitInfo, SystemInfo, ToolDiagnostic, ToolInfo, ToolboxInfo,
+    CacheState, CachedVersion, DiagnosticStatus, DiagnosticSummary, EnvironmentReport, GitInfo,
+    GoModule, InstalledVersion, PinnedVersion, RustToolchain, ShimInfo, SystemInfo, ToolDiagnostic,
+    ToolExplanation, ToolInfo, ToolboxInfo,
 };
+pub use source::ToolSource;
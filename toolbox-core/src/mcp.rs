@@ -0,0 +1,430 @@
+//! Model Context Protocol server over stdio
+//!
+//! `toolbox mcp` reads newline-delimited JSON-RPC 2.0 messages on stdin and
+//! answers on stdout, so coding agents can query the developer's environment
+//! through the `get_tool_versions`, `diagnose` and `check_requirements` tools.
+
+use crate::config::{expand_home, Config};
+use crate::detector::ToolDetector;
+use crate::error::Result;
+use crate::require::{check_requirements, Requirement};
+use crate::serve::DirConfigLoader;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+/// MCP revision implemented by the server
+pub const MCP_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Answers MCP requests with detection results for a configuration
+pub struct McpServer {
+    config: Config,
+    /// Directory used when a call gives none
+    working_dir: Option<String>,
+    /// Use the version cache
+    use_cache: bool,
+    /// Loads the config for a call's `dir`
+    dir_config: Option<DirConfigLoader>,
+}
+
+impl McpServer {
+    /// Server for a configuration
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            working_dir: None,
+            use_cache: true,
+            dir_config: None,
+        }
+    }
+
+    /// Load the config for a call's `dir` argument (with its project config
+    /// and `[[dir_overrides]]`) instead of using the server's
+    pub fn with_dir_config(
+        mut self,
+        loader: impl Fn(Option<&str>) -> Result<Config> + 'static,
+    ) -> Self {
+        self.dir_config = Some(Box::new(loader));
+        self
+    }
+
+    /// Default directory for detection
+    pub fn with_working_dir(mut self, dir: String) -> Self {
+        self.working_dir = Some(dir);
+        self
+    }
+
+    /// Detect without the version cache
+    pub fn with_cache_disabled(mut self) -> Self {
+        self.use_cache = false;
+        self
+    }
+
+    /// Serve requests until the input ends
+    pub fn serve<R: BufRead, W: Write>(&self, input: R, mut output: W) -> Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_line(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer one JSON-RPC message (None for notifications)
+    pub fn handle_line(&self, line: &str) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(line) {
+            Ok(message) => self.handle(&message)?,
+            Err(err) => error_response(Value::Null, PARSE_ERROR, &err.to_string()),
+        };
+        Some(response.to_string())
+    }
+
+    /// Answer a parsed JSON-RPC message (None for notifications)
+    pub fn handle(&self, message: &Value) -> Option<Value> {
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            let id = message.get("id").cloned().unwrap_or(Value::Null);
+            return Some(error_response(id, INVALID_REQUEST, "missing method"));
+        };
+        // Notifications (no id) get no answer
+        let id = message.get("id")?.clone();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": MCP_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "toolbox", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => self.call_tool(&params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn call_tool(&self, params: &Value) -> std::result::Result<Value, (i64, String)> {
+        let name = params
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or((INVALID_PARAMS, "missing tool name".to_string()))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+        let outcome = match name {
+            "get_tool_versions" => self.get_tool_versions(&arguments),
+            "diagnose" => self.diagnose(&arguments),
+            "check_requirements" => self.check_requirements(&arguments),
+            _ => return Err((INVALID_PARAMS, format!("Unknown tool: {}", name))),
+        };
+        // Tool failures are results the agent can read, not protocol errors
+        Ok(match outcome {
+            Ok(value) => json!({
+                "content": [{ "type": "text", "text": pretty(&value) }],
+                "isError": false,
+            }),
+            Err(message) => json!({
+                "content": [{ "type": "text", "text": message }],
+                "isError": true,
+            }),
+        })
+    }
+
+    /// A call's `dir` argument
+    fn call_dir(arguments: &Value) -> Option<String> {
+        arguments
+            .get("dir")
+            .and_then(Value::as_str)
+            .map(|dir| expand_home(dir).to_string_lossy().into_owned())
+    }
+
+    /// Config for a call's `dir` argument (or the server's)
+    fn config(&self, arguments: &Value) -> std::result::Result<Config, String> {
+        match (&self.dir_config, Self::call_dir(arguments)) {
+            (Some(loader), Some(dir)) => loader(Some(&dir)).map_err(|err| err.to_string()),
+            _ => Ok(self.config.clone()),
+        }
+    }
+
+    /// Detector for a call's `dir` argument (or the default directory)
+    fn detector(&self, config: Config, arguments: &Value) -> ToolDetector {
+        let mut detector = ToolDetector::new(config);
+        let dir = Self::call_dir(arguments).or_else(|| self.working_dir.clone());
+        if let Some(dir) = dir {
+            detector = detector.with_working_dir(dir);
+        }
+        if !self.use_cache {
+            detector = detector.with_cache_disabled();
+        }
+        detector
+    }
+
+    fn get_tool_versions(&self, arguments: &Value) -> std::result::Result<Value, String> {
+        let mut config = self.config(arguments)?;
+        if let Some(tools) = arguments.get("tools").and_then(Value::as_array) {
+            let names: Vec<String> = tools
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect();
+            config.only_tools(&names).map_err(|err| err.to_string())?;
+        }
+        let info = self.detector(config, arguments).detect_all();
+        serde_json::to_value(info).map_err(|err| err.to_string())
+    }
+
+    fn diagnose(&self, arguments: &Value) -> std::result::Result<Value, String> {
+        let summary = self
+            .detector(self.config(arguments)?, arguments)
+            .diagnose_all();
+        serde_json::to_value(summary).map_err(|err| err.to_string())
+    }
+
+    fn check_requirements(&self, arguments: &Value) -> std::result::Result<Value, String> {
+        let config = self.config(arguments)?;
+        let requirements = match arguments.get("requirements").and_then(Value::as_object) {
+            Some(table) => table
+                .iter()
+                .map(|(tool, constraint)| Requirement {
+                    tool: tool.clone(),
+                    constraint: constraint.as_str().unwrap_or_default().to_string(),
+                })
+                .collect(),
+            None => Requirement::from_config(&config),
+        };
+        if requirements.is_empty() {
+            return Err(
+                "No requirements given (pass {\"tool\": \"constraint\"} or add a [requirements] table to the config)"
+                    .to_string(),
+            );
+        }
+        let mut detector = self.detector(config, arguments);
+        let results = check_requirements(&mut detector, &requirements);
+        Ok(json!({
+            "passed": results.iter().all(|r| r.passed()),
+            "results": results,
+        }))
+    }
+}
+
+/// Tools advertised by `tools/list`
+fn tool_definitions() -> Value {
+    let dir = json!({
+        "type": "string",
+        "description": "Project directory (default: the server's working directory)"
+    });
+    json!([
+        {
+            "name": "get_tool_versions",
+            "description": "Detect installed development tool versions (plus git, virtualenv and directory info) for a project directory",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "dir": dir,
+                    "tools": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Only these tools (names or short names)"
+                    }
+                }
+            }
+        },
+        {
+            "name": "diagnose",
+            "description": "Diagnose tool detection: which tools were found, their paths, versions and errors",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "dir": dir }
+            }
+        },
+        {
+            "name": "check_requirements",
+            "description": "Check tool versions against semver constraints (default: the [requirements] table of the config)",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "dir": dir,
+                    "requirements": {
+                        "type": "object",
+                        "additionalProperties": { "type": "string" },
+                        "description": "Tool name → constraint, e.g. {\"node\": \">=20\"}"
+                    }
+                }
+            }
+        }
+    ])
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> McpServer {
+        let config: Config = toml::from_str(
+            r#"
+use_default_tools = false
+
+[cache]
+enabled = false
+
+[requirements]
+Echo = ">=2"
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#,
+        )
+        .unwrap();
+        McpServer::new(config)
+    }
+
+    fn request(server: &McpServer, method: &str, params: Value) -> Value {
+        server
+            .handle(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .unwrap()
+    }
+
+    fn call(server: &McpServer, name: &str, arguments: Value) -> (bool, String) {
+        let response = request(
+            server,
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        );
+        let result = &response["result"];
+        (
+            result["isError"].as_bool().unwrap(),
+            result["content"][0]["text"].as_str().unwrap().to_string(),
+        )
+    }
+
+    #[test]
+    fn test_dir_config() {
+        let project = tempfile::tempdir().unwrap();
+        let overridden = project.path().to_string_lossy().into_owned();
+        let dir = overridden.clone();
+        let server = server().with_dir_config(move |dir_arg| {
+            let mut config = server().config;
+            if dir_arg == Some(dir.as_str()) {
+                config.custom_tools[0].command = "echo v3.0.0".to_string();
+            }
+            Ok(config)
+        });
+        let (_, text) = call(&server, "get_tool_versions", json!({}));
+        assert!(text.contains("\"2.0.0\""), "{}", text);
+        let (_, text) = call(&server, "get_tool_versions", json!({ "dir": overridden }));
+        assert!(text.contains("\"3.0.0\""), "{}", text);
+    }
+
+    #[test]
+    fn test_initialize_and_list() {
+        let server = server();
+        let response = request(&server, "initialize", json!({}));
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocolVersion"], MCP_VERSION);
+        assert_eq!(response["result"]["serverInfo"]["name"], "toolbox");
+
+        let response = request(&server, "tools/list", Value::Null);
+        let names: Vec<&str> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["get_tool_versions", "diagnose", "check_requirements"]
+        );
+    }
+
+    #[test]
+    fn test_notifications_and_errors() {
+        let server = server();
+        assert!(server
+            .handle(&json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .is_none());
+
+        let response = request(&server, "resources/list", Value::Null);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response: Value =
+            serde_json::from_str(&server.handle_line("{not json").unwrap()).unwrap();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = request(&server, "tools/call", json!({ "name": "nope" }));
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_tool_calls() {
+        let server = server();
+        let (is_error, text) = call(&server, "get_tool_versions", json!({}));
+        assert!(!is_error);
+        let info: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(info["tools"][0]["version"], "2.0.0");
+
+        let (is_error, text) = call(&server, "get_tool_versions", json!({ "tools": ["nope"] }));
+        assert!(is_error);
+        assert!(text.contains("Unknown tool: nope"));
+
+        let (_, text) = call(&server, "check_requirements", json!({}));
+        let results: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(results["passed"], true);
+
+        let (_, text) = call(
+            &server,
+            "check_requirements",
+            json!({ "requirements": { "Echo": ">=3" } }),
+        );
+        let results: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(results["passed"], false);
+
+        let (is_error, text) = call(&server, "diagnose", json!({}));
+        assert!(!is_error);
+        assert!(text.contains("Echo"));
+    }
+
+    #[test]
+    fn test_serve_reads_lines() {
+        let server = server();
+        let input = b"{\"jsonrpc\":\"2.0\",\"id\":7,\"method\":\"ping\"}\n\n{\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}\n";
+        let mut output = Vec::new();
+        server.serve(&input[..], &mut output).unwrap();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![json!({ "jsonrpc": "2.0", "id": 7, "result": {} })]
+        );
+    }
+}
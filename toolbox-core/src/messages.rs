@@ -0,0 +1,300 @@
+//! Message catalog for user-facing labels and messages
+//!
+//! Labels ("venv:", "mem:"), doctor headings and tool error messages are
+//! looked up by [`Message`] key in the current [`Language`]. The language is
+//! process-wide: the CLI sets it from the `language` config option before
+//! producing any output. Placeholders (`{}`) are filled in order by [`fill`].
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Language of labels and messages (`language` in the config)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// English
+    #[default]
+    En,
+    /// Japanese
+    Ja,
+}
+
+/// A user-facing label or message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    /// Virtual environment label (`venv: .venv`)
+    Venv,
+    /// Memory usage label
+    Memory,
+    /// CPU usage label
+    Cpu,
+    /// Per-core CPU usage label
+    Cores,
+    /// Swap usage label
+    Swap,
+    /// Doctor report title
+    DoctorTitle,
+    /// Config file line of the doctor report (`{}`: path)
+    DoctorConfig,
+    /// Config file line when it doesn't exist (`{}`: path)
+    DoctorConfigNotFound,
+    /// Config file line without a config path
+    DoctorConfigUnavailable,
+    /// Tool section heading of the doctor report
+    DoctorTools,
+    /// Environment section heading of the doctor report
+    DoctorEnvironment,
+    /// Doctor summary (`{}`: total, ok, warning, error)
+    DoctorSummary,
+    /// PATH heading of the environment report
+    EnvPath,
+    /// Version manager heading of the environment report
+    EnvManagers,
+    /// Variable heading of the environment report
+    EnvVariables,
+    /// A version manager that is active
+    ManagerActive,
+    /// A version manager that is installed but not active
+    ManagerInactive,
+    /// A version manager that isn't installed
+    ManagerNotInstalled,
+    /// A PATH entry that doesn't exist
+    PathMissing,
+    /// A PATH entry listed twice
+    PathDuplicate,
+    /// A tool that is disabled in the config
+    Disabled,
+    /// A failure without details
+    UnknownError,
+    /// Shim resolution line of a diagnostic (`{}`: shim)
+    Shim,
+    /// Retry count line of a diagnostic (`{}`: retries)
+    Retried,
+    /// Provenance line of a diagnostic (`{}`: where it came from)
+    From,
+    /// Pin line of a diagnostic (`{}`: version, pin file)
+    Pinned,
+    /// Package line of a diagnostic (`{}`: package and upgrade command)
+    Package,
+    /// Other PATH matches of a diagnostic (`{}`: paths)
+    AlsoInPath,
+    /// Doctor notice of a newer release (`{}`: version)
+    UpdateAvailable,
+    /// Heading of `--timings`
+    Timings,
+    /// Doctor detail of a tool that isn't installed (`{}`: program)
+    DiagnosticNotFound,
+    /// Doctor suggestion for a tool that isn't installed (`{}`: tool)
+    SuggestInstall,
+    /// Doctor suggestion for a program that may not be run (`{}`: program)
+    SuggestExecutable,
+    /// Tool error: not installed (`{}`: program)
+    CommandNotFound,
+    /// Tool error: not executable (`{}`: program)
+    PermissionDenied,
+    /// Tool error: too slow (`{}`: program, timeout)
+    TimedOut,
+    /// Tool error: exit status (`{}`: code)
+    ExitedWithStatus,
+    /// Tool error: killed by a signal
+    KilledBySignal,
+}
+
+impl Language {
+    /// All languages, in `u8` order
+    const ALL: [Language; 2] = [Language::En, Language::Ja];
+
+    /// The text of a message in this language
+    pub fn get(self, message: Message) -> &'static str {
+        match self {
+            Language::En => english(message),
+            Language::Ja => japanese(message),
+        }
+    }
+}
+
+impl std::str::FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" => Ok(Language::En),
+            "ja" => Ok(Language::Ja),
+            _ => Err(format!("Unknown language: {} (expected en or ja)", s)),
+        }
+    }
+}
+
+fn english(message: Message) -> &'static str {
+    match message {
+        Message::Venv => "venv:",
+        Message::Memory => "mem:",
+        Message::Cpu => "cpu:",
+        Message::Cores => "cores:",
+        Message::Swap => "swap:",
+        Message::DoctorTitle => "Toolbox Doctor",
+        Message::DoctorConfig => "Config: {}",
+        Message::DoctorConfigNotFound => "Config: {} (not found, using defaults)",
+        Message::DoctorConfigUnavailable => "Config: (no config path available)",
+        Message::DoctorTools => "Tool Status:",
+        Message::DoctorEnvironment => "Environment:",
+        Message::DoctorSummary => "{} tools checked: {} ok, {} warning, {} error",
+        Message::EnvPath => "PATH:",
+        Message::EnvManagers => "Version managers:",
+        Message::EnvVariables => "Variables:",
+        Message::ManagerActive => "active",
+        Message::ManagerInactive => "inactive",
+        Message::ManagerNotInstalled => "not installed",
+        Message::PathMissing => "(missing)",
+        Message::PathDuplicate => "(duplicate)",
+        Message::Disabled => "(disabled)",
+        Message::UnknownError => "unknown error",
+        Message::Shim => "shim: {}",
+        Message::Retried => "retried: {} time(s)",
+        Message::From => "from: {}",
+        Message::Pinned => "pinned: {} ({})",
+        Message::Package => "package: {}",
+        Message::AlsoInPath => "also in PATH: {}",
+        Message::UpdateAvailable => "toolbox {} is available (run `toolbox self-update`)",
+        Message::Timings => "Detection timings (slowest first):",
+        Message::DiagnosticNotFound => "command not found: '{}'",
+        Message::SuggestInstall => "Install {} or add it to your PATH",
+        Message::SuggestExecutable => "Check that {} is executable",
+        Message::CommandNotFound => "{}: command not found",
+        Message::PermissionDenied => "{}: permission denied",
+        Message::TimedOut => "{}: timed out after {} ms",
+        Message::ExitedWithStatus => "exited with status {}",
+        Message::KilledBySignal => "killed by a signal",
+    }
+}
+
+fn japanese(message: Message) -> &'static str {
+    match message {
+        Message::Venv => "仮想環境:",
+        Message::Memory => "メモリ:",
+        Message::Cpu => "CPU:",
+        Message::Cores => "コア:",
+        Message::Swap => "スワップ:",
+        Message::DoctorTitle => "Toolbox 診断",
+        Message::DoctorConfig => "設定: {}",
+        Message::DoctorConfigNotFound => "設定: {} (見つからないためデフォルトを使用)",
+        Message::DoctorConfigUnavailable => "設定: (設定パスがありません)",
+        Message::DoctorTools => "ツールの状態:",
+        Message::DoctorEnvironment => "環境:",
+        Message::DoctorSummary => "{} 個のツールを確認: 正常 {}、警告 {}、エラー {}",
+        Message::EnvPath => "PATH:",
+        Message::EnvManagers => "バージョンマネージャー:",
+        Message::EnvVariables => "環境変数:",
+        Message::ManagerActive => "有効",
+        Message::ManagerInactive => "無効",
+        Message::ManagerNotInstalled => "未インストール",
+        Message::PathMissing => "(存在しません)",
+        Message::PathDuplicate => "(重複)",
+        Message::Disabled => "(無効)",
+        Message::UnknownError => "不明なエラー",
+        Message::Shim => "shim: {}",
+        Message::Retried => "再試行: {} 回",
+        Message::From => "設定元: {}",
+        Message::Pinned => "固定: {} ({})",
+        Message::Package => "パッケージ: {}",
+        Message::AlsoInPath => "PATH 上の他の候補: {}",
+        Message::UpdateAvailable => "toolbox {} が利用可能です (`toolbox self-update` で更新)",
+        Message::Timings => "検出時間 (遅い順):",
+        Message::DiagnosticNotFound => "コマンドが見つかりません: '{}'",
+        Message::SuggestInstall => "{} をインストールするか PATH に追加してください",
+        Message::SuggestExecutable => "{} が実行可能か確認してください",
+        Message::CommandNotFound => "{}: コマンドが見つかりません",
+        Message::PermissionDenied => "{}: 実行権限がありません",
+        Message::TimedOut => "{}: {} ms でタイムアウトしました",
+        Message::ExitedWithStatus => "終了ステータス {}",
+        Message::KilledBySignal => "シグナルで終了しました",
+    }
+}
+
+/// The process-wide language, as its index in [`Language::ALL`]
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+/// Set the language of all following labels and messages
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+/// The current language (English unless set)
+pub fn language() -> Language {
+    Language::ALL
+        .get(LANGUAGE.load(Ordering::Relaxed) as usize)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// The text of a message in the current language
+pub fn tr(message: Message) -> &'static str {
+    language().get(message)
+}
+
+/// A message in the current language with its placeholders filled in
+pub fn fill(message: Message, args: &[&dyn Display]) -> String {
+    substitute(tr(message), args)
+}
+
+/// Replace each `{}` in `template` with the next argument (extra
+/// placeholders are kept)
+fn substitute(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(index) = rest.find("{}") {
+        out.push_str(&rest[..index]);
+        match args.next() {
+            Some(arg) => out.push_str(&arg.to_string()),
+            None => out.push_str("{}"),
+        }
+        rest = &rest[index + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_get() {
+        assert_eq!(Language::En.get(Message::Venv), "venv:");
+        assert_eq!(Language::Ja.get(Message::DoctorTitle), "Toolbox 診断");
+        assert_eq!(Language::default(), Language::En);
+    }
+
+    #[test]
+    fn test_language_parse() {
+        assert_eq!("ja".parse::<Language>(), Ok(Language::Ja));
+        assert_eq!("EN".parse::<Language>(), Ok(Language::En));
+        assert!("fr".parse::<Language>().is_err());
+        let language: Language = serde_json::from_str("\"ja\"").unwrap();
+        assert_eq!(language, Language::Ja);
+    }
+
+    #[test]
+    fn test_language_indices() {
+        for (index, language) in Language::ALL.iter().enumerate() {
+            assert_eq!(*language as usize, index);
+        }
+    }
+
+    #[test]
+    fn test_substitute() {
+        assert_eq!(
+            substitute(Language::En.get(Message::DoctorSummary), &[&3, &1, &1, &1]),
+            "3 tools checked: 1 ok, 1 warning, 1 error"
+        );
+        assert_eq!(
+            substitute(Language::Ja.get(Message::TimedOut), &[&"sleep", &100]),
+            "sleep: 100 ms でタイムアウトしました"
+        );
+        // Missing arguments keep their placeholder
+        assert_eq!(substitute("{} and {}", &[&"a"]), "a and {}");
+    }
+}
@@ -0,0 +1,322 @@
+//! Package origin lookup
+//!
+//! Maps a tool's resolved binary to the package that installed it (a Homebrew
+//! formula, a Debian package, a Scoop app or a winget package) and the command
+//! that upgrades it. Debian lookups run `dpkg`, so doctor only does them with
+//! `--packages`.
+
+use crate::info::DiagnosticSummary;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// Package manager that installed a binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum PackageManager {
+    /// A formula or cask in the Homebrew Cellar / Caskroom
+    Homebrew,
+    /// A Debian/Ubuntu package (found with `dpkg -S`)
+    Apt,
+    /// A Scoop app (`scoop\apps\<app>\<version>`)
+    Scoop,
+    /// A winget portable package (`WinGet\Packages\<id>_<source>`)
+    Winget,
+}
+
+impl PackageManager {
+    /// Lowercase name, as in JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PackageManager::Homebrew => "homebrew",
+            PackageManager::Apt => "apt",
+            PackageManager::Scoop => "scoop",
+            PackageManager::Winget => "winget",
+        }
+    }
+}
+
+impl std::fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The package a binary belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PackageOrigin {
+    /// Package manager that installed it
+    pub manager: PackageManager,
+    /// Package name (formula, Debian package, app or package id)
+    pub package: String,
+    /// Installed package version, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl PackageOrigin {
+    /// The command that upgrades the package
+    pub fn upgrade_command(&self) -> String {
+        match self.manager {
+            PackageManager::Homebrew => format!("brew upgrade {}", self.package),
+            PackageManager::Apt => format!("sudo apt install --only-upgrade {}", self.package),
+            PackageManager::Scoop => format!("scoop update {}", self.package),
+            PackageManager::Winget => format!("winget upgrade --id {}", self.package),
+        }
+    }
+
+    /// One-line description: `homebrew node 22.1.0 (upgrade: brew upgrade node)`
+    pub fn format_display(&self) -> String {
+        let version = self
+            .version
+            .as_deref()
+            .map(|version| format!(" {}", version))
+            .unwrap_or_default();
+        format!(
+            "{} {}{} (upgrade: {})",
+            self.manager,
+            self.package,
+            version,
+            self.upgrade_command()
+        )
+    }
+}
+
+/// Look up the package of every diagnosed tool with a resolved path
+pub fn annotate(summary: &mut DiagnosticSummary) {
+    for tool in &mut summary.tools {
+        // A version manager shim isn't packaged; the binary it runs may be
+        let path = tool
+            .shim
+            .as_ref()
+            .and_then(|shim| shim.real_path.as_deref())
+            .or(tool.command_path.as_deref());
+        tool.package = path.and_then(lookup);
+    }
+}
+
+/// The package a binary belongs to: from its path (or the path it links to),
+/// else from the dpkg database
+pub fn lookup(path: &str) -> Option<PackageOrigin> {
+    let path = Path::new(path);
+    let real = std::fs::canonicalize(path).ok();
+    let paths: Vec<&Path> = std::iter::once(path).chain(real.as_deref()).collect();
+    paths
+        .iter()
+        .find_map(|path| from_path(path).or_else(|| scoop_shim_target(path)))
+        .or_else(|| paths.iter().find_map(|path| dpkg_owner(path)))
+}
+
+/// The package of a path inside a package manager's install tree
+pub fn from_path(path: &Path) -> Option<PackageOrigin> {
+    let parts: Vec<&str> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    let after = |index: usize| parts.get(index + 1).copied();
+    let origin = |manager, package: &str, version: Option<&str>| {
+        Some(PackageOrigin {
+            manager,
+            package: package.to_string(),
+            version: version.map(String::from),
+        })
+    };
+    for (index, part) in parts.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|i| parts[i]);
+        match *part {
+            // Cellar/<formula>/<version>/bin/..., Caskroom/<cask>/<version>/...
+            "Cellar" | "Caskroom" => {
+                if let (Some(package), Some(version)) = (after(index), after(index + 1)) {
+                    return origin(PackageManager::Homebrew, package, Some(version));
+                }
+            }
+            // scoop/apps/<app>/<version or current>/...
+            "apps" if previous.is_some_and(|p| p.eq_ignore_ascii_case("scoop")) => {
+                if let (Some(package), Some(version)) = (after(index), after(index + 1)) {
+                    let version = (version != "current").then_some(version);
+                    return origin(PackageManager::Scoop, package, version);
+                }
+            }
+            // WinGet/Packages/<Publisher.Name>_<source hash>/...
+            "Packages" if previous.is_some_and(|p| p.eq_ignore_ascii_case("winget")) => {
+                if let Some(directory) = after(index) {
+                    let package = directory.split('_').next().unwrap_or(directory);
+                    return origin(PackageManager::Winget, package, None);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// What a Scoop shim (`scoop\shims\node.exe`) runs, from the `path = "..."`
+/// line of the `.shim` file next to it
+fn scoop_shim_target(path: &Path) -> Option<PackageOrigin> {
+    let dir = path.parent()?;
+    if !dir.ends_with("shims") {
+        return None;
+    }
+    let content = std::fs::read_to_string(path.with_extension("shim")).ok()?;
+    let target = content.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "path").then(|| value.trim().trim_matches('"').to_string())
+    })?;
+    from_path(&PathBuf::from(target))
+}
+
+/// The Debian package owning a file, with its installed version
+fn dpkg_owner(path: &Path) -> Option<PackageOrigin> {
+    if !cfg!(unix) {
+        return None;
+    }
+    // With merged /usr, packages still list `/bin/ls` for `/usr/bin/ls`
+    let unmerged = path
+        .strip_prefix("/usr")
+        .ok()
+        .map(|rest| Path::new("/").join(rest));
+    let package = std::iter::once(path)
+        .chain(unmerged.as_deref())
+        .find_map(|path| {
+            let output = Command::new("dpkg").arg("-S").arg(path).output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            parse_dpkg_search(&String::from_utf8_lossy(&output.stdout))
+        })?;
+    let version = Command::new("dpkg-query")
+        .args(["-W", "-f=${Version}", &package])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|version| !version.is_empty());
+    Some(PackageOrigin {
+        manager: PackageManager::Apt,
+        package,
+        version,
+    })
+}
+
+/// The first package of `dpkg -S` output (`coreutils: /usr/bin/ls`,
+/// `libc-bin:amd64, other: /usr/bin/ldd`), skipping diversion notes
+pub fn parse_dpkg_search(output: &str) -> Option<String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with("diversion "))
+        .find_map(|line| {
+            let (packages, _) = line.split_once(": ")?;
+            let package = packages.split(", ").next()?.trim();
+            let package = package.split(':').next().unwrap_or(package);
+            (!package.is_empty()).then(|| package.to_string())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin(manager: PackageManager, package: &str, version: Option<&str>) -> PackageOrigin {
+        PackageOrigin {
+            manager,
+            package: package.to_string(),
+            version: version.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_from_path_homebrew() {
+        assert_eq!(
+            from_path(Path::new("/opt/homebrew/Cellar/node/22.1.0/bin/node")),
+            Some(origin(PackageManager::Homebrew, "node", Some("22.1.0")))
+        );
+        assert_eq!(
+            from_path(Path::new(
+                "/opt/homebrew/Caskroom/temurin/21.0.2/jdk/Contents/Home/bin/java"
+            )),
+            Some(origin(PackageManager::Homebrew, "temurin", Some("21.0.2")))
+        );
+        assert_eq!(from_path(Path::new("/opt/homebrew/bin/node")), None);
+    }
+
+    #[test]
+    fn test_from_path_scoop_and_winget() {
+        assert_eq!(
+            from_path(Path::new("/c/Users/me/scoop/apps/nodejs/22.1.0/node.exe")),
+            Some(origin(PackageManager::Scoop, "nodejs", Some("22.1.0")))
+        );
+        assert_eq!(
+            from_path(Path::new("/c/Users/me/scoop/apps/go/current/bin/go.exe")),
+            Some(origin(PackageManager::Scoop, "go", None))
+        );
+        assert_eq!(
+            from_path(Path::new(
+                "/c/Users/me/AppData/Local/Microsoft/WinGet/Packages/BurntSushi.ripgrep.MSVC_Microsoft.Winget.Source_8wekyb3d8bbwe/rg.exe"
+            )),
+            Some(origin(PackageManager::Winget, "BurntSushi.ripgrep.MSVC", None))
+        );
+        assert_eq!(from_path(Path::new("/home/me/apps/tool/1.0/tool")), None);
+    }
+
+    #[test]
+    fn test_scoop_shim_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let shims = dir.path().join("shims");
+        std::fs::create_dir(&shims).unwrap();
+        std::fs::write(
+            shims.join("node.shim"),
+            "path = \"/c/Users/me/scoop/apps/nodejs/22.1.0/node.exe\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            scoop_shim_target(&shims.join("node.exe")),
+            Some(origin(PackageManager::Scoop, "nodejs", Some("22.1.0")))
+        );
+        assert_eq!(scoop_shim_target(&shims.join("go.exe")), None);
+    }
+
+    #[test]
+    fn test_parse_dpkg_search() {
+        assert_eq!(
+            parse_dpkg_search("coreutils: /usr/bin/ls\n"),
+            Some("coreutils".to_string())
+        );
+        assert_eq!(
+            parse_dpkg_search("libc-bin:amd64, other: /usr/bin/ldd\n"),
+            Some("libc-bin".to_string())
+        );
+        assert_eq!(
+            parse_dpkg_search(
+                "diversion by dash from: /bin/sh\ndiversion by dash to: /bin/sh.distrib\ndash: /bin/sh\n"
+            ),
+            Some("dash".to_string())
+        );
+        assert_eq!(parse_dpkg_search(""), None);
+    }
+
+    #[test]
+    fn test_upgrade_command_and_display() {
+        let brew = origin(PackageManager::Homebrew, "node", Some("22.1.0"));
+        assert_eq!(
+            brew.format_display(),
+            "homebrew node 22.1.0 (upgrade: brew upgrade node)"
+        );
+        assert_eq!(
+            origin(PackageManager::Apt, "golang-go", None).upgrade_command(),
+            "sudo apt install --only-upgrade golang-go"
+        );
+        assert_eq!(
+            origin(PackageManager::Winget, "GoLang.Go", None).upgrade_command(),
+            "winget upgrade --id GoLang.Go"
+        );
+        assert_eq!(
+            serde_json::to_value(&brew).unwrap(),
+            serde_json::json!({"manager": "homebrew", "package": "node", "version": "22.1.0"})
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! Sandboxed WASM plugins
+//!
+//! `[plugins.<name>]` modules extend detection (`toolbox_detect`) and
+//! formatting (`toolbox_format`) without native code: each call runs in a
+//! fresh wasmtime instance with no WASI, a fuel budget and a memory cap, and
+//! the only host access is reading files below the detection directory or the
+//! plugin's `allow_read` directories. The guest interface is described in the
+//! `toolbox-plugin-sdk` crate.
+
+use crate::config::{expand_home, PluginConfig};
+use crate::error::{Result, ToolboxError};
+use crate::external;
+use crate::info::{ToolInfo, ToolboxInfo};
+use std::path::{Path, PathBuf};
+use wasmtime::{Caller, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// Detector export of a plugin
+pub const DETECT_EXPORT: &str = "toolbox_detect";
+
+/// Formatter export of a plugin
+pub const FORMAT_EXPORT: &str = "toolbox_format";
+
+/// Allocator export the host writes input through
+const ALLOC_EXPORT: &str = "toolbox_alloc";
+
+/// Linear memory a plugin may grow to
+const MEMORY_LIMIT: usize = 64 << 20;
+
+/// Largest file `read_file` hands to a plugin
+const READ_LIMIT: u64 = 1 << 20;
+
+/// Largest result, path or log message the host copies out of a plugin
+const OUTPUT_LIMIT: usize = 4 << 20;
+
+/// Host state of one call
+struct HostState {
+    limits: StoreLimits,
+    plugin: String,
+    /// Canonical directories the plugin may read
+    readable: Vec<PathBuf>,
+    /// Directory relative paths are resolved against
+    base: Option<PathBuf>,
+}
+
+impl HostState {
+    /// Resolve a path requested by the plugin, if it may read it
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let path = Path::new(path);
+        let candidate = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.base.as_ref()?.join(path)
+        };
+        // Canonical paths: no escaping through `..` or symlinks
+        let canonical = candidate.canonicalize().ok()?;
+        self.readable
+            .iter()
+            .any(|dir| canonical.starts_with(dir))
+            .then_some(canonical)
+    }
+}
+
+/// A compiled plugin
+pub struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+    linker: Linker<HostState>,
+    allow_read: Vec<PathBuf>,
+    fuel: u64,
+}
+
+impl WasmPlugin {
+    /// Compile the module of a configured plugin
+    pub fn load(name: &str, config: &PluginConfig) -> Result<Self> {
+        let path = expand_home(&config.path);
+        let bytes = std::fs::read(&path)
+            .map_err(|e| ToolboxError::Plugin(format!("{}: {}: {}", name, path.display(), e)))?;
+        let allow_read = config
+            .allow_read
+            .iter()
+            .filter_map(|dir| expand_home(dir).canonicalize().ok())
+            .collect();
+        Self::from_bytes(name, &bytes, allow_read, config.fuel)
+    }
+
+    /// Compile a module from its bytes
+    pub fn from_bytes(
+        name: &str,
+        bytes: &[u8],
+        allow_read: Vec<PathBuf>,
+        fuel: u64,
+    ) -> Result<Self> {
+        let error = |e: wasmtime::Error| ToolboxError::Plugin(format!("{}: {:#}", name, e));
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).map_err(error)?;
+        let module = Module::new(&engine, bytes).map_err(error)?;
+
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap(
+                "toolbox",
+                "read_file",
+                |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| {
+                    read_file(&mut caller, ptr, len).unwrap_or(u64::MAX)
+                },
+            )
+            .map_err(error)?;
+        linker
+            .func_wrap("toolbox", "log", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| {
+                if let Some(message) = read_guest(&mut caller, ptr, len) {
+                    tracing::debug!(plugin = %caller.data().plugin, "{}", String::from_utf8_lossy(&message));
+                }
+            })
+            .map_err(error)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            engine,
+            module,
+            linker,
+            allow_read,
+            fuel,
+        })
+    }
+
+    /// Plugin name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the plugin exports a detector
+    pub fn can_detect(&self) -> bool {
+        self.module.get_export(DETECT_EXPORT).is_some()
+    }
+
+    /// Whether the plugin exports a formatter
+    pub fn can_format(&self) -> bool {
+        self.module.get_export(FORMAT_EXPORT).is_some()
+    }
+
+    /// Run the detector for a directory
+    pub fn detect(&self, dir: Option<&str>) -> Result<Vec<ToolInfo>> {
+        let input = serde_json::to_vec(&serde_json::json!({ "dir": dir }))?;
+        let output = self.call(DETECT_EXPORT, dir, &input)?;
+        external::parse_output(&self.name, &String::from_utf8_lossy(&output))
+    }
+
+    /// Render a detection result with the formatter
+    pub fn format(&self, info: &ToolboxInfo) -> Result<String> {
+        let input = serde_json::to_vec(info)?;
+        let output = self.call(FORMAT_EXPORT, info.current_dir.as_deref(), &input)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    /// Call an export in a fresh instance
+    fn call(&self, export: &str, dir: Option<&str>, input: &[u8]) -> Result<Vec<u8>> {
+        let error = |e: wasmtime::Error| ToolboxError::Plugin(format!("{}: {:#}", self.name, e));
+        let base = dir.and_then(|dir| Path::new(dir).canonicalize().ok());
+        let state = HostState {
+            limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+            plugin: self.name.clone(),
+            readable: base.iter().chain(&self.allow_read).cloned().collect(),
+            base,
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.fuel).map_err(error)?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .map_err(error)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| ToolboxError::Plugin(format!("{}: no exported memory", self.name)))?;
+        let alloc = instance
+            .get_typed_func::<u32, u32>(&mut store, ALLOC_EXPORT)
+            .map_err(error)?;
+        let func = instance
+            .get_typed_func::<(u32, u32), u64>(&mut store, export)
+            .map_err(error)?;
+
+        let len = input.len() as u32;
+        let ptr = alloc.call(&mut store, len).map_err(error)?;
+        memory
+            .write(&mut store, ptr as usize, input)
+            .map_err(|e| error(e.into()))?;
+        let (ptr, len) = unpack(func.call(&mut store, (ptr, len)).map_err(error)?);
+        // The length comes from the guest: check it before allocating
+        if !in_guest_memory(memory.data_size(&store), ptr, len) {
+            return Err(ToolboxError::Plugin(format!(
+                "{}: result out of bounds ({} bytes at {:#x})",
+                self.name, len, ptr
+            )));
+        }
+        let mut output = vec![0; len];
+        memory
+            .read(&store, ptr, &mut output)
+            .map_err(|e| error(e.into()))?;
+        Ok(output)
+    }
+}
+
+/// Split a guest result into pointer and length
+fn unpack(packed: u64) -> (usize, usize) {
+    ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize)
+}
+
+/// Whether `len` bytes at `ptr` lie within guest memory of `size` bytes and
+/// within [`OUTPUT_LIMIT`]
+fn in_guest_memory(size: usize, ptr: usize, len: usize) -> bool {
+    len <= OUTPUT_LIMIT && ptr.checked_add(len).is_some_and(|end| end <= size)
+}
+
+/// Copy bytes out of the guest's memory
+fn read_guest(caller: &mut Caller<'_, HostState>, ptr: u32, len: u32) -> Option<Vec<u8>> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    if !in_guest_memory(memory.data_size(&*caller), ptr as usize, len as usize) {
+        return None;
+    }
+    let mut bytes = vec![0; len as usize];
+    memory.read(&*caller, ptr as usize, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// `toolbox.read_file`: copy an allowed file into guest memory
+fn read_file(caller: &mut Caller<'_, HostState>, ptr: u32, len: u32) -> Option<u64> {
+    let path = String::from_utf8(read_guest(caller, ptr, len)?).ok()?;
+    let Some(file) = caller.data().resolve(&path) else {
+        tracing::debug!(plugin = %caller.data().plugin, path, "read denied");
+        return None;
+    };
+    if std::fs::metadata(&file).ok()?.len() > READ_LIMIT {
+        return None;
+    }
+    let contents = std::fs::read(&file).ok()?;
+
+    let alloc = caller
+        .get_export(ALLOC_EXPORT)
+        .and_then(Extern::into_func)?
+        .typed::<u32, u32>(&*caller)
+        .ok()?;
+    let dest = alloc.call(&mut *caller, contents.len() as u32).ok()?;
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    memory.write(&mut *caller, dest as usize, &contents).ok()?;
+    Some(((dest as u64) << 32) | contents.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Host import, bump allocator, memory and a `(data, len)` result helper
+    const PRELUDE: &str = r#"
+        (import "toolbox" "read_file" (func $read_file (param i32 i32) (result i64)))
+        (memory (export "memory") 2)
+        (global $next (mut i32) (i32.const 4096))
+        (func $alloc (export "toolbox_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+        (func $pack (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+                (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                (i64.extend_i32_u (local.get $len))))
+    "#;
+
+    fn plugin(body: &str, allow_read: Vec<PathBuf>) -> WasmPlugin {
+        let wat = format!("(module {} {})", PRELUDE, body);
+        let bytes = wat::parse_str(&wat).unwrap();
+        WasmPlugin::from_bytes("test", &bytes, allow_read, 10_000_000).unwrap()
+    }
+
+    #[test]
+    fn test_detect() {
+        let output = r#"{"name": "Corp", "version": "4.2.0"}"#;
+        let plugin = plugin(
+            &format!(
+                r#"(data (i32.const 16) "{}")
+                (func (export "toolbox_detect") (param i32 i32) (result i64)
+                    (call $pack (i32.const 16) (i32.const {})))"#,
+                output.replace('"', "\\\""),
+                output.len()
+            ),
+            Vec::new(),
+        );
+        assert!(plugin.can_detect());
+        assert!(!plugin.can_format());
+        let tools = plugin.detect(None).unwrap();
+        assert_eq!(tools[0].name, "Corp");
+        assert_eq!(tools[0].version.as_deref(), Some("4.2.0"));
+    }
+
+    #[test]
+    fn test_format_echoes_input() {
+        let plugin = plugin(
+            r#"(func (export "toolbox_format") (param $ptr i32) (param $len i32) (result i64)
+                (call $pack (local.get $ptr) (local.get $len)))"#,
+            Vec::new(),
+        );
+        assert!(plugin.can_format());
+        let mut info = ToolboxInfo::new();
+        info.virtual_env = Some("venv".to_string());
+        let text = plugin.format(&info).unwrap();
+        assert!(text.contains(r#""virtual_env":"venv""#), "{}", text);
+    }
+
+    #[test]
+    fn test_fuel_limit() {
+        let plugin = plugin(
+            r#"(func (export "toolbox_detect") (param i32 i32) (result i64)
+                (loop $forever (br $forever))
+                (i64.const 0))"#,
+            Vec::new(),
+        );
+        let err = plugin.detect(None).unwrap_err();
+        assert!(err.to_string().contains("fuel"), "{}", err);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let plugin = plugin(
+            r#"(func (export "toolbox_detect") (param i32 i32) (result i64)
+                (drop (memory.grow (i32.const 2048)))
+                (i64.extend_i32_s (memory.size)))"#,
+            Vec::new(),
+        );
+        // The 128 MiB grow fails, leaving the initial two pages
+        let err = plugin.detect(None).unwrap_err();
+        assert!(err.to_string().contains("invalid output"), "{}", err);
+    }
+
+    #[test]
+    fn test_lengths_checked_against_memory() {
+        // A 4 GiB result is refused instead of allocated
+        let detector = plugin(
+            r#"(func (export "toolbox_detect") (param i32 i32) (result i64)
+                (call $pack (i32.const 0) (i32.const -1)))"#,
+            Vec::new(),
+        );
+        let err = detector.detect(None).unwrap_err();
+        assert!(err.to_string().contains("result out of bounds"), "{}", err);
+
+        // So is a 4 GiB path, which reads as a missing file (u64::MAX)
+        let detector = plugin(
+            r#"(func (export "toolbox_detect") (param i32 i32) (result i64)
+                (call $read_file (i32.const 16) (i32.const -1)))"#,
+            Vec::new(),
+        );
+        let err = detector.detect(None).unwrap_err();
+        assert!(err.to_string().contains("result out of bounds"), "{}", err);
+
+        assert!(in_guest_memory(128, 64, 64));
+        assert!(!in_guest_memory(128, 64, 65));
+        assert!(!in_guest_memory(usize::MAX, usize::MAX, 1));
+        assert!(!in_guest_memory(usize::MAX, 0, OUTPUT_LIMIT + 1));
+    }
+
+    /// Detector returning the contents of the file named by the data segment
+    fn reader(path: &str, allow_read: Vec<PathBuf>) -> WasmPlugin {
+        plugin(
+            &format!(
+                r#"(data (i32.const 16) "{}")
+                (func (export "toolbox_detect") (param i32 i32) (result i64)
+                    (call $read_file (i32.const 16) (i32.const {})))"#,
+                path,
+                path.len()
+            ),
+            allow_read,
+        )
+    }
+
+    #[test]
+    fn test_read_file_sandbox() {
+        let project = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let json = r#"{"name": "Sdk", "version": "1.0.0"}"#;
+        std::fs::write(project.path().join("tool.json"), json).unwrap();
+        std::fs::write(outside.path().join("tool.json"), json).unwrap();
+        let dir = project.path().to_str().unwrap();
+
+        // Relative to the detection directory
+        let tools = reader("tool.json", Vec::new()).detect(Some(dir)).unwrap();
+        assert_eq!(tools[0].version.as_deref(), Some("1.0.0"));
+
+        // Outside it: denied (u64::MAX is not a valid result)
+        let escape = format!("{}/tool.json", outside.path().display());
+        assert!(reader(&escape, Vec::new()).detect(Some(dir)).is_err());
+        assert!(reader("../x/tool.json", Vec::new())
+            .detect(Some(dir))
+            .is_err());
+
+        // Unless the directory is allowed
+        let allowed = vec![outside.path().canonicalize().unwrap()];
+        let tools = reader(&escape, allowed).detect(Some(dir)).unwrap();
+        assert_eq!(tools[0].name, "Sdk");
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let err = WasmPlugin::load(
+            "missing",
+            &PluginConfig {
+                path: "/nonexistent/plugin.wasm".to_string(),
+                ..PluginConfig::default()
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(err
+            .to_string()
+            .starts_with("Plugin error: missing: /nonexistent/plugin.wasm"));
+
+        let err = WasmPlugin::from_bytes("junk", b"not wasm", Vec::new(), 1)
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("Plugin error: junk:"));
+    }
+}
@@ -0,0 +1,130 @@
+//! Project environments (direnv, Nix flakes)
+//!
+//! In a project with an `.envrc` or a `flake.nix`, the user's shell resolves
+//! tools through direnv or `nix develop` rather than the plain PATH. With
+//! `[environment]` enabled, version commands run inside that environment
+//! (`direnv exec <dir>`, `nix develop <dir> --command`) so the displayed
+//! versions match what the shell resolves in the project.
+
+use crate::config::EnvironmentConfig;
+use std::path::{Path, PathBuf};
+
+/// How a project sets up its environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectEnvKind {
+    /// `.envrc`, run through `direnv exec`
+    Direnv,
+    /// `flake.nix`, run through `nix develop --command`
+    Nix,
+}
+
+/// A project environment found at or above the working directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectEnv {
+    pub kind: ProjectEnvKind,
+    /// Directory holding the `.envrc` or `flake.nix`
+    pub dir: PathBuf,
+}
+
+impl ProjectEnv {
+    /// The nearest environment of an enabled kind at or above `start`; direnv
+    /// wins in a directory with both, as its `.envrc` usually loads the flake
+    pub fn find(start: &Path, config: &EnvironmentConfig) -> Option<Self> {
+        if !config.direnv && !config.nix {
+            return None;
+        }
+        start.ancestors().find_map(|dir| {
+            let kind = if config.direnv && dir.join(".envrc").is_file() {
+                ProjectEnvKind::Direnv
+            } else if config.nix && dir.join("flake.nix").is_file() {
+                ProjectEnvKind::Nix
+            } else {
+                return None;
+            };
+            Some(Self {
+                kind,
+                dir: dir.to_path_buf(),
+            })
+        })
+    }
+
+    /// A command line run inside the environment
+    pub fn wrap(&self, command: &[&str]) -> Vec<String> {
+        let dir = self.dir.to_string_lossy().into_owned();
+        let prefix = match self.kind {
+            ProjectEnvKind::Direnv => vec!["direnv".to_string(), "exec".to_string(), dir],
+            ProjectEnvKind::Nix => vec![
+                "nix".to_string(),
+                "develop".to_string(),
+                dir,
+                "--command".to_string(),
+            ],
+        };
+        prefix
+            .into_iter()
+            .chain(command.iter().map(|part| part.to_string()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> EnvironmentConfig {
+        EnvironmentConfig {
+            direnv: true,
+            nix: true,
+        }
+    }
+
+    #[test]
+    fn test_find_nearest_environment() {
+        let root = tempfile::tempdir().unwrap();
+        let app = root.path().join("app/src");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(root.path().join("flake.nix"), "{}").unwrap();
+
+        let env = ProjectEnv::find(&app, &enabled()).unwrap();
+        assert_eq!(env.kind, ProjectEnvKind::Nix);
+        assert_eq!(env.dir, root.path());
+
+        // A closer .envrc wins, and direnv wins next to a flake
+        std::fs::write(root.path().join("app/.envrc"), "use flake ..").unwrap();
+        let env = ProjectEnv::find(&app, &enabled()).unwrap();
+        assert_eq!(env.kind, ProjectEnvKind::Direnv);
+        assert_eq!(env.dir, root.path().join("app"));
+        std::fs::write(root.path().join(".envrc"), "use flake").unwrap();
+        let env = ProjectEnv::find(root.path(), &enabled()).unwrap();
+        assert_eq!(env.kind, ProjectEnvKind::Direnv);
+
+        // Disabled kinds are skipped
+        let nix_only = EnvironmentConfig {
+            direnv: false,
+            nix: true,
+        };
+        let env = ProjectEnv::find(&app, &nix_only).unwrap();
+        assert_eq!(env.kind, ProjectEnvKind::Nix);
+        assert_eq!(ProjectEnv::find(&app, &EnvironmentConfig::default()), None);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let direnv = ProjectEnv {
+            kind: ProjectEnvKind::Direnv,
+            dir: PathBuf::from("/work/app"),
+        };
+        assert_eq!(
+            direnv.wrap(&["node", "--version"]),
+            vec!["direnv", "exec", "/work/app", "node", "--version"]
+        );
+        let nix = ProjectEnv {
+            kind: ProjectEnvKind::Nix,
+            dir: PathBuf::from("/work/app"),
+        };
+        assert_eq!(
+            nix.wrap(&["go", "version"]),
+            vec!["nix", "develop", "/work/app", "--command", "go", "version"]
+        );
+    }
+}
@@ -0,0 +1,632 @@
+//! Shell prompt integration
+//!
+//! `toolbox init-shell <bash|zsh|fish>` prints a hook that runs
+//! `toolbox prompt` before each prompt and puts its one-line output in front
+//! of the existing prompt. `toolbox prompt` renders the same config (sections,
+//! theme) as the Zellij pane and keeps the last result per directory on disk,
+//! so most prompts are drawn without running any tool.
+//!
+//! When the cached result is missing or expired, the detection runs in a
+//! background process; the prompt waits for it at most `prompt.budget_ms` and
+//! otherwise shows the last result, which the next prompt picks up fresh.
+
+use crate::color::{resolve_color, ColorMode};
+use crate::config::{Config, Section};
+use crate::error::Result;
+use crate::info::ToolboxInfo;
+#[cfg(feature = "sqlite")]
+use crate::store::Store;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Seconds after which a background refresh is presumed dead
+const REFRESH_TIMEOUT: u64 = 60;
+
+/// Interval at which the prompt checks for a finished refresh
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Shells with a prompt hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl std::str::FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("Invalid shell: {} (expected bash, zsh or fish)", s)),
+        }
+    }
+}
+
+/// Hook script installing the prompt segment (`exe` is the toolbox binary)
+pub fn init_script(shell: Shell, exe: &str) -> String {
+    match shell {
+        Shell::Bash => {
+            let exe = quote_posix(exe);
+            format!(
+                r#"# toolbox prompt segment: eval "$(toolbox init-shell bash)"
+_toolbox_prompt() {{
+    local status=$?
+    if [ -z "${{_TOOLBOX_PS1+x}}" ]; then
+        _TOOLBOX_PS1=$PS1
+    fi
+    PS1="$({exe} prompt --shell bash 2>/dev/null)$_TOOLBOX_PS1"
+    return $status
+}}
+if [[ ";${{PROMPT_COMMAND:-}};" != *";_toolbox_prompt;"* ]]; then
+    PROMPT_COMMAND="_toolbox_prompt${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+fi
+"#
+            )
+        }
+        Shell::Zsh => {
+            let exe = quote_posix(exe);
+            format!(
+                r#"# toolbox prompt segment: eval "$(toolbox init-shell zsh)"
+_toolbox_precmd() {{
+    TOOLBOX_PROMPT="$({exe} prompt --shell zsh 2>/dev/null)"
+}}
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd _toolbox_precmd
+setopt prompt_subst
+if [[ $PROMPT != *'${{TOOLBOX_PROMPT}}'* ]]; then
+    PROMPT='${{TOOLBOX_PROMPT}}'$PROMPT
+fi
+"#
+            )
+        }
+        Shell::Fish => {
+            let exe = quote_fish(exe);
+            format!(
+                r#"# toolbox prompt segment: {exe} init-shell fish | source
+if not functions -q _toolbox_original_fish_prompt
+    functions -c fish_prompt _toolbox_original_fish_prompt
+end
+function fish_prompt
+    set -l last_status $status
+    {exe} prompt --shell fish 2>/dev/null | string collect
+    _toolbox_set_status $last_status
+    _toolbox_original_fish_prompt
+end
+function _toolbox_set_status
+    return $argv[1]
+end
+"#
+            )
+        }
+    }
+}
+
+/// Single-quote a word for bash and zsh
+fn quote_posix(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Single-quote a word for fish
+fn quote_fish(s: &str) -> String {
+    format!("'{}'", s.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Prepare rendered output for the shell's prompt string
+///
+/// ANSI sequences are marked as zero-width (`\[…\]` in bash, `%{…%}` in zsh)
+/// so line editing keeps the right cursor column, and characters the prompt
+/// would expand (a `$(…)` in a branch name, `%` in zsh) are escaped. Fish
+/// prints the output as is.
+pub fn escape(shell: Shell, s: &str) -> String {
+    let (open, close) = match shell {
+        Shell::Bash => (r"\[", r"\]"),
+        Shell::Zsh => ("%{", "%}"),
+        Shell::Fish => return s.to_string(),
+    };
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.peek() == Some(&'[') {
+            out.push_str(open);
+            out.push(c);
+            out.extend(chars.next());
+            // Parameters and intermediates up to the final byte (@ to ~)
+            for c in chars.by_ref() {
+                out.push(c);
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+            out.push_str(close);
+            continue;
+        }
+        match (shell, c) {
+            // Decoded to `\`, `\$` and `` \` `` first, then quote-removed
+            (Shell::Bash, '\\') => out.push_str(r"\\\\"),
+            (Shell::Bash, '$') => out.push_str(r"\\$"),
+            (Shell::Bash, '`') => out.push_str(r"\\`"),
+            (Shell::Zsh, '%') => out.push_str("%%"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether the prompt is colored: stdout is captured by the shell, so `auto`
+/// only honors `NO_COLOR` and friends instead of checking for a terminal
+pub fn use_color(mode: ColorMode) -> bool {
+    resolve_color(mode, |key| std::env::var(key).ok(), true)
+}
+
+/// Drop the `prompt.exclude` sections from the display and turn off their
+/// detection, so the prompt neither shows nor waits for them
+pub fn apply_exclusions(config: &mut Config) {
+    let exclude = config.prompt.exclude.clone();
+    config
+        .display
+        .sections
+        .retain(|section| !exclude.contains(section));
+    for section in exclude {
+        let extras = &mut config.extras;
+        match section {
+            Section::Dir => extras.current_directory = false,
+            Section::Git => {
+                extras.git_branch = false;
+                extras.git_status = false;
+            }
+            Section::Tools => {
+                config.use_default_tools = false;
+                config.tools.clear();
+                config.custom_tools.clear();
+            }
+            Section::Venv => extras.virtual_env = false,
+            Section::System => {
+                extras.system_memory = false;
+                extras.system_cpu = false;
+                extras.system_swap = false;
+                extras.system_cpu_cores = false;
+            }
+        }
+    }
+}
+
+/// A cached prompt result
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedPrompt {
+    key: String,
+    info: ToolboxInfo,
+    /// Seconds since the Unix epoch when the result was stored (the file's
+    /// modification time records its last use)
+    #[serde(default)]
+    stored_at: u64,
+}
+
+/// Last detection result per directory, kept on disk between prompts
+#[derive(Debug, Clone)]
+pub struct PromptCache {
+    dir: PathBuf,
+    /// Seconds a result stays fresh
+    ttl: u64,
+    /// Most results kept (0: unlimited)
+    max_entries: usize,
+    /// SQLite database holding the results instead of `dir`
+    #[cfg(feature = "sqlite")]
+    db: Option<PathBuf>,
+}
+
+impl PromptCache {
+    /// Cache in a directory with a freshness limit in seconds
+    pub fn new(dir: PathBuf, ttl: u64) -> Self {
+        Self {
+            dir,
+            ttl,
+            max_entries: 0,
+            #[cfg(feature = "sqlite")]
+            db: None,
+        }
+    }
+
+    /// Keep at most `max_entries` results (0: unlimited); storing one more
+    /// drops the least recently used
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Cache in a SQLite database with a freshness limit in seconds
+    #[cfg(feature = "sqlite")]
+    pub fn sqlite(db: PathBuf, ttl: u64) -> Self {
+        Self {
+            dir: PathBuf::new(),
+            ttl,
+            max_entries: 0,
+            db: Some(db),
+        }
+    }
+
+    /// The database, if the cache is kept in one
+    #[cfg(feature = "sqlite")]
+    fn database(&self) -> Option<Result<Store>> {
+        self.db.as_deref().map(Store::open)
+    }
+
+    /// The cache of a config: in the `[storage]` database, or files in the
+    /// default directory (None if it can't be determined)
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let (ttl, max_entries) = (config.cache.default_ttl, config.cache.max_entries);
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = config.storage.database()? {
+            return Ok(Some(Self::sqlite(db, ttl).with_max_entries(max_entries)));
+        }
+        // Without SQLite support, a config asking for it is an error
+        #[cfg(not(feature = "sqlite"))]
+        config.storage.database()?;
+        Ok(Self::default_dir().map(|dir| Self::new(dir, ttl).with_max_entries(max_entries)))
+    }
+
+    /// Get the default cache directory
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|p| p.join("toolbox").join("prompt"))
+    }
+
+    /// File holding the result for a key
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.json", fnv1a(key)))
+    }
+
+    /// Fresh result for a key (None if missing, expired or unreadable)
+    pub fn load(&self, key: &str) -> Option<ToolboxInfo> {
+        let (info, age) = self.get(key)?;
+        (age.as_secs() < self.ttl).then_some(info)
+    }
+
+    /// Last result for a key, however old
+    pub fn load_stale(&self, key: &str) -> Option<ToolboxInfo> {
+        self.get(key).map(|(info, _)| info)
+    }
+
+    /// Time since the result for a key was stored
+    pub fn age(&self, key: &str) -> Option<Duration> {
+        self.get(key).map(|(_, age)| age)
+    }
+
+    /// Mark the result for a key as the most recently used (reads don't, so
+    /// that polling and inspecting the cache leave the eviction order alone)
+    pub fn touch(&self, key: &str) {
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = self.database() {
+            let _ = store.and_then(|store| store.cache_touch(key));
+            return;
+        }
+        // The modification time orders the results for eviction
+        let _ = std::fs::File::options()
+            .write(true)
+            .open(self.path(key))
+            .and_then(|file| file.set_modified(SystemTime::now()));
+    }
+
+    /// Result for a key and its age
+    fn get(&self, key: &str) -> Option<(ToolboxInfo, Duration)> {
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = self.database() {
+            let (json, age) = store.ok()?.cache_get(key).ok()??;
+            return Some((serde_json::from_str(&json).ok()?, age));
+        }
+        let json = std::fs::read_to_string(self.path(key)).ok()?;
+        let cached: CachedPrompt = serde_json::from_str(&json).ok()?;
+        // Guard against hash collisions
+        if cached.key != key {
+            return None;
+        }
+        let stored_at = UNIX_EPOCH + Duration::from_secs(cached.stored_at);
+        let age = SystemTime::now()
+            .duration_since(stored_at)
+            .unwrap_or_default();
+        Some((cached.info, age))
+    }
+
+    /// Wait up to `budget` for a fresh result (e.g. from a background refresh)
+    pub fn wait_fresh(&self, key: &str, budget: Duration) -> Option<ToolboxInfo> {
+        let deadline = Instant::now() + budget;
+        loop {
+            if let Some(info) = self.load(key) {
+                return Some(info);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return None;
+            }
+            std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+    }
+
+    /// Claim the refresh of a key; false if another refresh is running
+    pub fn try_lock(&self, key: &str) -> bool {
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = self.database() {
+            return matches!(
+                store.and_then(|mut store| store.try_lock(key, REFRESH_TIMEOUT)),
+                Ok(true)
+            );
+        }
+        let lock = self.path(key).with_extension("lock");
+        // A refresh that died must not block the next ones forever
+        if is_fresh(&lock, REFRESH_TIMEOUT) {
+            return false;
+        }
+        let _ = std::fs::remove_file(&lock);
+        std::fs::create_dir_all(&self.dir).is_ok()
+            && std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock)
+                .is_ok()
+    }
+
+    /// Release the refresh of a key
+    pub fn unlock(&self, key: &str) {
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = self.database() {
+            let _ = store.and_then(|store| store.unlock(key));
+            return;
+        }
+        let _ = std::fs::remove_file(self.path(key).with_extension("lock"));
+    }
+
+    /// Store a result for a key, replacing the previous one
+    pub fn store(&self, key: &str, info: &ToolboxInfo) -> Result<()> {
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = self.database() {
+            let store = store?;
+            store.cache_put(key, &serde_json::to_string(info)?)?;
+            if self.max_entries > 0 {
+                store.cache_prune(self.max_entries)?;
+            }
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension("json.tmp");
+        let cached = CachedPrompt {
+            key: key.to_string(),
+            info: info.clone(),
+            stored_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+        std::fs::write(&tmp, serde_json::to_string(&cached)?)?;
+        std::fs::rename(&tmp, &path)?;
+        self.prune()
+    }
+
+    /// Remove the least recently used results beyond `max_entries`
+    fn prune(&self) -> Result<()> {
+        if self.max_entries == 0 {
+            return Ok(());
+        }
+        let mut results: Vec<(SystemTime, PathBuf)> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| Some((std::fs::metadata(&path).ok()?.modified().ok()?, path)))
+            .collect();
+        if results.len() <= self.max_entries {
+            return Ok(());
+        }
+        results.sort();
+        for (_, path) in &results[..results.len() - self.max_entries] {
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a hash of a cache key: stable across builds, unlike the std hasher
+pub(crate) fn fnv1a(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn is_fresh(path: &Path, ttl: u64) -> bool {
+    age(path).is_some_and(|age| age.as_secs() < ttl)
+}
+
+/// Time since a file was last modified
+fn age(path: &Path) -> Option<Duration> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shell() {
+        assert_eq!("bash".parse::<Shell>().unwrap(), Shell::Bash);
+        assert_eq!(" ZSH ".parse::<Shell>().unwrap(), Shell::Zsh);
+        assert_eq!("fish".parse::<Shell>().unwrap(), Shell::Fish);
+        assert!("nu"
+            .parse::<Shell>()
+            .unwrap_err()
+            .contains("bash, zsh or fish"));
+    }
+
+    #[test]
+    fn test_init_scripts_call_prompt() {
+        let bash = init_script(Shell::Bash, "/usr/bin/toolbox");
+        assert!(bash.contains("'/usr/bin/toolbox' prompt --shell bash"));
+        assert!(bash.contains("PROMPT_COMMAND=\"_toolbox_prompt"));
+
+        let zsh = init_script(Shell::Zsh, "/usr/bin/toolbox");
+        assert!(zsh.contains("'/usr/bin/toolbox' prompt --shell zsh"));
+        assert!(zsh.contains("add-zsh-hook precmd _toolbox_precmd"));
+
+        let fish = init_script(Shell::Fish, "/usr/bin/toolbox");
+        assert!(fish.contains("'/usr/bin/toolbox' prompt --shell fish"));
+        assert!(fish.contains("function fish_prompt"));
+    }
+
+    #[test]
+    fn test_init_script_quotes_exe() {
+        assert!(init_script(Shell::Bash, "/it's/toolbox").contains(r"'/it'\''s/toolbox' prompt"));
+        assert!(init_script(Shell::Fish, "/it's/toolbox").contains(r"'/it\'s/toolbox' prompt"));
+    }
+
+    #[test]
+    fn test_escape_marks_ansi_zero_width() {
+        let colored = "\x1b[38;2;1;2;3m main \x1b[0m";
+        assert_eq!(
+            escape(Shell::Bash, colored),
+            "\\[\x1b[38;2;1;2;3m\\] main \\[\x1b[0m\\]"
+        );
+        assert_eq!(
+            escape(Shell::Zsh, colored),
+            "%{\x1b[38;2;1;2;3m%} main %{\x1b[0m%}"
+        );
+        assert_eq!(escape(Shell::Fish, colored), colored);
+    }
+
+    #[test]
+    fn test_escape_prompt_expansions() {
+        assert_eq!(escape(Shell::Bash, "$(id)`x`\\"), r"\\$(id)\\`x\\`\\\\");
+        assert_eq!(escape(Shell::Zsh, "100% $(id)"), "100%% $(id)");
+        assert_eq!(escape(Shell::Fish, "100% $(id)"), "100% $(id)");
+    }
+
+    #[test]
+    fn test_use_color_ignores_terminal() {
+        assert!(use_color(ColorMode::Always));
+        assert!(!use_color(ColorMode::Never));
+    }
+
+    #[test]
+    fn test_prompt_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PromptCache::new(dir.path().join("prompt"), 60);
+        assert!(cache.load("/home/user/app").is_none());
+
+        let mut info = ToolboxInfo::new();
+        info.current_dir = Some("/home/user/app".to_string());
+        cache.store("/home/user/app", &info).unwrap();
+        let loaded = cache.load("/home/user/app").unwrap();
+        assert_eq!(loaded.current_dir.as_deref(), Some("/home/user/app"));
+        assert!(cache.load("/home/user/other").is_none());
+        assert!(cache.age("/home/user/app").unwrap() < Duration::from_secs(60));
+        assert!(cache.age("/home/user/other").is_none());
+
+        // A zero TTL never serves from the cache, except as a stale result
+        let expired = PromptCache::new(dir.path().join("prompt"), 0);
+        assert!(expired.load("/home/user/app").is_none());
+        assert!(expired.load_stale("/home/user/app").is_some());
+        assert!(expired
+            .wait_fresh("/home/user/app", Duration::from_millis(10))
+            .is_none());
+        assert!(cache
+            .wait_fresh("/home/user/app", Duration::from_millis(10))
+            .is_some());
+    }
+
+    #[test]
+    fn test_prompt_cache_max_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PromptCache::new(dir.path().to_path_buf(), 60).with_max_entries(2);
+        let info = ToolboxInfo::new();
+        cache.store("/a", &info).unwrap();
+        cache.store("/b", &info).unwrap();
+        let last_used = |key: &str, hours: u64| {
+            std::fs::File::options()
+                .write(true)
+                .open(cache.path(key))
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(hours * 3600))
+                .unwrap();
+        };
+        last_used("/a", 2);
+        last_used("/b", 1);
+        // /a was stored first but used last; reads don't count
+        assert!(cache.load("/b").is_some());
+        assert!(cache.age("/b").is_some());
+        cache.touch("/a");
+        // The refresh lock of a key isn't a result
+        assert!(cache.try_lock("/a"));
+
+        cache.store("/c", &info).unwrap();
+        assert!(cache.load("/a").is_some());
+        assert!(cache.load("/b").is_none());
+        assert!(cache.load("/c").is_some());
+
+        // Reading a result doesn't make it fresh again
+        let stored = CachedPrompt {
+            key: "/a".to_string(),
+            info,
+            stored_at: 0,
+        };
+        std::fs::write(cache.path("/a"), serde_json::to_string(&stored).unwrap()).unwrap();
+        assert!(cache.load("/a").is_none());
+        assert!(cache.load("/a").is_none());
+        assert!(cache.load_stale("/a").is_some());
+    }
+
+    #[test]
+    fn test_refresh_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PromptCache::new(dir.path().join("prompt"), 60);
+        assert!(cache.try_lock("/app"));
+        assert!(!cache.try_lock("/app"));
+        assert!(cache.try_lock("/other"));
+        cache.unlock("/app");
+        assert!(cache.try_lock("/app"));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_prompt_cache_sqlite() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("toolbox.db");
+        let cache = PromptCache::sqlite(db.clone(), 60);
+        assert!(cache.load("/app").is_none());
+        cache.store("/app", &ToolboxInfo::new()).unwrap();
+        assert!(cache.load("/app").is_some());
+        assert!(PromptCache::sqlite(db, 0).load("/app").is_none());
+
+        assert!(cache.try_lock("/app"));
+        assert!(!cache.try_lock("/app"));
+        cache.unlock("/app");
+        assert!(cache.try_lock("/app"));
+
+        // The least recently used result is evicted
+        let cache = cache.with_max_entries(2);
+        cache.store("/other", &ToolboxInfo::new()).unwrap();
+        cache.touch("/app");
+        assert!(cache.load("/other").is_some());
+        cache.store("/new", &ToolboxInfo::new()).unwrap();
+        assert!(cache.load("/app").is_some());
+        assert!(cache.load("/other").is_none());
+        assert!(cache.load("/new").is_some());
+    }
+
+    #[test]
+    fn test_apply_exclusions() {
+        let mut config = Config::default();
+        config.display.sections = vec![Section::Dir, Section::Git, Section::System];
+        config.extras.system_cpu = true;
+        config.prompt.exclude = vec![Section::Git, Section::System];
+        apply_exclusions(&mut config);
+        assert_eq!(config.display.sections, vec![Section::Dir]);
+        assert!(!config.extras.git_branch && !config.extras.git_status);
+        assert!(!config.extras.system_cpu);
+        assert!(config.extras.current_directory);
+
+        config.prompt.exclude = vec![Section::Tools];
+        apply_exclusions(&mut config);
+        assert!(config.enabled_tools().is_empty());
+    }
+}
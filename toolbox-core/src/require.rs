@@ -0,0 +1,361 @@
+//! Version requirements for CI gating
+//!
+//! Checks detected tool versions against semver constraints, taken from the
+//! `[requirements]` config table or `tool=constraint` arguments, so CI and
+//! onboarding scripts can fail fast on an unsuitable environment.
+
+use crate::color::ansi;
+use crate::config::Config;
+use crate::detector::ToolDetector;
+use crate::info::ToolInfo;
+use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// A version constraint on a single tool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// Tool name or short name (case-insensitive)
+    pub tool: String,
+    /// Semver requirement (e.g. ">=20", "^1.75", ">=18, <21")
+    pub constraint: String,
+}
+
+/// Characters of semver operators, which can't appear in a tool name
+const OPERATOR_CHARS: &[char] = &['<', '>', '=', '~', '^'];
+
+impl std::str::FromStr for Requirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `node>=20` would otherwise be split into tool "node>" and constraint "20"
+        if let Some(pos) = s
+            .split('=')
+            .next()
+            .and_then(|tool| tool.find(OPERATOR_CHARS))
+        {
+            return Err(format!(
+                "Invalid requirement: {} (the tool name can't contain an operator; write {}=\"{}\")",
+                s,
+                s[..pos].trim(),
+                s[pos..].trim()
+            ));
+        }
+        match s.split_once('=') {
+            Some((tool, constraint)) if !tool.trim().is_empty() => Ok(Requirement {
+                tool: tool.trim().to_string(),
+                constraint: constraint.trim().to_string(),
+            }),
+            _ => Err(format!(
+                "Invalid requirement: {} (expected tool=constraint, e.g. node=\">=20\")",
+                s
+            )),
+        }
+    }
+}
+
+impl Requirement {
+    /// Requirements from the `[requirements]` config table
+    pub fn from_config(config: &Config) -> Vec<Requirement> {
+        config
+            .requirements
+            .iter()
+            .map(|(tool, constraint)| Requirement {
+                tool: tool.clone(),
+                constraint: constraint.clone(),
+            })
+            .collect()
+    }
+
+    /// The package manager version pinned by the nearest `package.json` at or
+    /// above `dir` (Corepack `packageManager`), as an exact requirement
+    pub fn from_package_manager(dir: &std::path::Path) -> Option<Requirement> {
+        let pin = crate::corepack::find_pin(dir)?;
+        Some(Requirement {
+            tool: pin.name,
+            constraint: format!("={}", pin.pinned.version),
+        })
+    }
+}
+
+/// Outcome of checking a single requirement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequirementStatus {
+    /// Installed version satisfies the constraint
+    Pass,
+    /// Installed version does not satisfy the constraint
+    Fail,
+    /// Tool is unknown or not installed
+    Missing,
+    /// Constraint or detected version could not be parsed
+    Invalid,
+}
+
+/// Result of checking a single requirement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequirementResult {
+    /// Tool name (display name if the tool is known)
+    pub tool: String,
+    /// Constraint as written
+    pub constraint: String,
+    /// Detected version
+    pub version: Option<String>,
+    /// Check outcome
+    pub status: RequirementStatus,
+    /// Why the check could not be evaluated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl RequirementResult {
+    /// Whether the requirement is satisfied
+    pub fn passed(&self) -> bool {
+        self.status == RequirementStatus::Pass
+    }
+}
+
+/// Check a requirement against a detected tool (None if the tool is unknown)
+pub fn evaluate(requirement: &Requirement, tool: Option<&ToolInfo>) -> RequirementResult {
+    let mut result = RequirementResult {
+        tool: tool
+            .map(|t| t.name.clone())
+            .unwrap_or_else(|| requirement.tool.clone()),
+        constraint: requirement.constraint.clone(),
+        version: tool.filter(|t| t.available).and_then(|t| t.version.clone()),
+        status: RequirementStatus::Invalid,
+        detail: None,
+    };
+
+    let req = match VersionReq::parse(&requirement.constraint) {
+        Ok(req) => req,
+        Err(e) => {
+            result.detail = Some(format!("invalid constraint: {}", e));
+            return result;
+        }
+    };
+
+    let Some(tool) = tool else {
+        result.status = RequirementStatus::Missing;
+        result.detail = Some("unknown tool".to_string());
+        return result;
+    };
+    if !tool.available {
+        result.status = RequirementStatus::Missing;
+        result.detail = Some(
+            tool.error
+                .clone()
+                .unwrap_or_else(|| "not found".to_string()),
+        );
+        return result;
+    }
+
+    let raw = tool.version.as_deref().unwrap_or("");
+    match parse_version(raw) {
+        Some(version) => {
+            result.status = if req.matches(&version) {
+                RequirementStatus::Pass
+            } else {
+                RequirementStatus::Fail
+            };
+        }
+        None => result.detail = Some(format!("unparsable version: {}", raw)),
+    }
+    result
+}
+
+/// Detect each required tool (enabled or not) and check its constraint
+pub fn check_requirements(
+    detector: &mut ToolDetector,
+    requirements: &[Requirement],
+) -> Vec<RequirementResult> {
+    requirements
+        .iter()
+        .map(|requirement| {
+            let tool = detector
+                .config()
+                .find_tool(&requirement.tool)
+                .map(|tool_config| detector.detect_tool(&tool_config));
+            evaluate(requirement, tool.as_ref())
+        })
+        .collect()
+}
+
+/// Leniently parse a detected version ("v20", "1.75", "3.12.1rc1") as semver
+/// Missing minor/patch components are zero; pre-release suffixes are ignored.
+pub fn parse_version(s: &str) -> Option<Version> {
+    static VERSION_RE: OnceLock<Regex> = OnceLock::new();
+    let re = VERSION_RE.get_or_init(|| Regex::new(r"(\d+)(?:\.(\d+))?(?:\.(\d+))?").unwrap());
+
+    let caps = re.captures(s)?;
+    let part = |i: usize| {
+        caps.get(i)
+            .map_or(Some(0), |m| m.as_str().parse::<u64>().ok())
+    };
+    Some(Version::new(part(1)?, part(2)?, part(3)?))
+}
+
+/// Format results as a pass/fail table with a summary line
+pub fn format_requirements(results: &[RequirementResult], use_color: bool) -> String {
+    let tool_width = results.iter().map(|r| r.tool.len()).max().unwrap_or(0);
+    let constraint_width = results
+        .iter()
+        .map(|r| r.constraint.len())
+        .max()
+        .unwrap_or(0);
+
+    let mut lines: Vec<String> = results
+        .iter()
+        .map(|r| {
+            let (label, color) = match r.status {
+                RequirementStatus::Pass => ("PASS", ansi::FG_GREEN),
+                RequirementStatus::Fail => ("FAIL", ansi::FG_RED),
+                RequirementStatus::Missing => ("MISS", ansi::FG_RED),
+                RequirementStatus::Invalid => ("ERR ", ansi::FG_YELLOW),
+            };
+            let label = if use_color {
+                format!("{}{}{}", color, label, ansi::RESET)
+            } else {
+                label.to_string()
+            };
+            let found = match (&r.detail, &r.version) {
+                (Some(detail), _) => detail.clone(),
+                (None, Some(version)) => version.clone(),
+                (None, None) => "?".to_string(),
+            };
+            format!(
+                " {}  {:<tw$}  {:<cw$}  {}",
+                label,
+                r.tool,
+                r.constraint,
+                found,
+                tw = tool_width,
+                cw = constraint_width
+            )
+        })
+        .collect();
+
+    let passed = results.iter().filter(|r| r.passed()).count();
+    lines.push(String::new());
+    lines.push(format!(
+        " {} requirements: {} passed, {} failed",
+        results.len(),
+        passed,
+        results.len() - passed
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirement(spec: &str) -> Requirement {
+        spec.parse().unwrap()
+    }
+
+    #[test]
+    fn test_requirement_from_str() {
+        let req = requirement("node=>=20");
+        assert_eq!(req.tool, "node");
+        assert_eq!(req.constraint, ">=20");
+
+        // Only the first '=' separates the tool from the constraint
+        assert_eq!(requirement("rust==1.75.0").constraint, "=1.75.0");
+        assert!("node".parse::<Requirement>().is_err());
+        assert!("=>=20".parse::<Requirement>().is_err());
+
+        // An operator before the first '=' belongs to the constraint
+        let err = "node>=20".parse::<Requirement>().unwrap_err();
+        assert!(err.contains(r#"write node=">=20""#), "{}", err);
+        let err = "python~3.12".parse::<Requirement>().unwrap_err();
+        assert!(err.contains(r#"write python="~3.12""#), "{}", err);
+    }
+
+    #[test]
+    fn test_requirement_from_config() {
+        let mut config = Config::default();
+        config
+            .requirements
+            .insert("node".to_string(), ">=20".to_string());
+        assert_eq!(
+            Requirement::from_config(&config),
+            vec![requirement("node=>=20")]
+        );
+    }
+
+    #[test]
+    fn test_requirement_from_package_manager() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(Requirement::from_package_manager(dir.path()), None);
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"packageManager": "yarn@4.1.0+sha224.abc"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Requirement::from_package_manager(dir.path()),
+            Some(requirement("yarn==4.1.0"))
+        );
+    }
+
+    #[test]
+    fn test_parse_version_lenient() {
+        assert_eq!(parse_version("20"), Some(Version::new(20, 0, 0)));
+        assert_eq!(parse_version("v1.75"), Some(Version::new(1, 75, 0)));
+        assert_eq!(parse_version("3.12.1rc1"), Some(Version::new(3, 12, 1)));
+        assert_eq!(parse_version("go1.22.0"), Some(Version::new(1, 22, 0)));
+        assert_eq!(parse_version("unknown"), None);
+    }
+
+    #[test]
+    fn test_evaluate_pass_and_fail() {
+        let node = ToolInfo::available("Node".to_string(), "22.11.0".to_string());
+        let result = evaluate(&requirement("node=>=20"), Some(&node));
+        assert_eq!(result.status, RequirementStatus::Pass);
+        assert_eq!(result.tool, "Node");
+        assert_eq!(result.version.as_deref(), Some("22.11.0"));
+
+        let rust = ToolInfo::available("Rust".to_string(), "1.70.0".to_string());
+        let result = evaluate(&requirement("rust=^1.75"), Some(&rust));
+        assert_eq!(result.status, RequirementStatus::Fail);
+        assert!(!result.passed());
+    }
+
+    #[test]
+    fn test_evaluate_missing_and_invalid() {
+        let result = evaluate(&requirement("nope=>=1"), None);
+        assert_eq!(result.status, RequirementStatus::Missing);
+        assert_eq!(result.detail.as_deref(), Some("unknown tool"));
+
+        let ruby = ToolInfo::unavailable("Ruby".to_string(), None);
+        let result = evaluate(&requirement("ruby=>=3"), Some(&ruby));
+        assert_eq!(result.status, RequirementStatus::Missing);
+        assert_eq!(result.detail.as_deref(), Some("not found"));
+
+        let node = ToolInfo::available("Node".to_string(), "22.11.0".to_string());
+        let result = evaluate(&requirement("node=>>20"), Some(&node));
+        assert_eq!(result.status, RequirementStatus::Invalid);
+        assert!(result.detail.unwrap().starts_with("invalid constraint"));
+    }
+
+    #[test]
+    fn test_format_requirements() {
+        let node = ToolInfo::available("Node".to_string(), "22.11.0".to_string());
+        let rust = ToolInfo::available("Rust".to_string(), "1.70.0".to_string());
+        let results = vec![
+            evaluate(&requirement("node=>=20"), Some(&node)),
+            evaluate(&requirement("rust=^1.75"), Some(&rust)),
+        ];
+
+        assert_eq!(
+            format_requirements(&results, false),
+            " PASS  Node  >=20   22.11.0\n \
+             FAIL  Rust  ^1.75  1.70.0\n\
+             \n \
+             2 requirements: 1 passed, 1 failed"
+        );
+        assert!(format_requirements(&results, true).contains(ansi::FG_GREEN));
+    }
+}
@@ -0,0 +1,202 @@
+//! Active rustup toolchain
+//!
+//! `rustc --version` alone doesn't say why a workspace builds with nightly.
+//! For tools run through rustup proxies, the active toolchain and what
+//! selected it are resolved the way rustup does, from files only:
+//! `RUSTUP_TOOLCHAIN`, then per directory (nearest first) a directory
+//! override in `settings.toml` or a `rust-toolchain.toml` / `rust-toolchain`
+//! file, then the default toolchain.
+
+use crate::info::RustToolchain;
+use std::path::{Path, PathBuf};
+
+/// What selects the toolchain when nothing overrides it
+pub const DEFAULT: &str = "default";
+
+/// Toolchain files, checked in order in each directory
+const TOOLCHAIN_FILES: &[&str] = &["rust-toolchain.toml", "rust-toolchain"];
+
+/// Leading components of host triples (`x86_64-unknown-linux-gnu`)
+const ARCHES: &[&str] = &[
+    "x86_64",
+    "aarch64",
+    "i686",
+    "i586",
+    "arm",
+    "riscv",
+    "powerpc",
+    "s390x",
+    "loongarch",
+    "mips",
+    "sparc",
+];
+
+/// The rustup home directory (`RUSTUP_HOME`, default `~/.rustup`)
+pub fn rustup_home(env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    env("RUSTUP_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".rustup")))
+}
+
+/// Toolchain name without the host triple: `stable-x86_64-unknown-linux-gnu`
+/// -> `stable`, `nightly-2024-11-28-aarch64-apple-darwin` -> `nightly-2024-11-28`
+pub fn short_name(toolchain: &str) -> String {
+    let parts: Vec<&str> = toolchain.split('-').collect();
+    let end = parts
+        .iter()
+        .skip(1)
+        .position(|part| ARCHES.iter().any(|arch| part.starts_with(arch)))
+        .map_or(parts.len(), |i| i + 1);
+    parts[..end].join("-")
+}
+
+/// Channel of a toolchain file: `[toolchain] channel` in TOML, or the whole
+/// file in the legacy one-line format
+pub fn toolchain_file_channel(contents: &str) -> Option<String> {
+    if let Ok(value) = contents.parse::<toml::Table>() {
+        return value
+            .get("toolchain")?
+            .get("channel")?
+            .as_str()
+            .map(String::from);
+    }
+    let channel = contents.trim();
+    (!channel.is_empty() && !channel.contains('\n')).then(|| channel.to_string())
+}
+
+/// The toolchain active in `start`
+pub fn active_toolchain(
+    start: &Path,
+    rustup_home: &Path,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Option<RustToolchain> {
+    if let Some(toolchain) = env("RUSTUP_TOOLCHAIN").filter(|t| !t.is_empty()) {
+        return Some(RustToolchain {
+            name: short_name(&toolchain),
+            selected_by: "RUSTUP_TOOLCHAIN".to_string(),
+            path: None,
+        });
+    }
+
+    let settings = std::fs::read_to_string(rustup_home.join("settings.toml"))
+        .ok()
+        .and_then(|s| s.parse::<toml::Table>().ok())
+        .unwrap_or_default();
+    let overrides = settings.get("overrides").and_then(|o| o.as_table());
+    for dir in start.ancestors() {
+        let overridden = overrides
+            .and_then(|o| o.get(dir.to_string_lossy().as_ref()))
+            .and_then(|t| t.as_str());
+        if let Some(toolchain) = overridden {
+            return Some(RustToolchain {
+                name: short_name(toolchain),
+                selected_by: "directory override".to_string(),
+                path: Some(dir.to_string_lossy().into_owned()),
+            });
+        }
+        for file in TOOLCHAIN_FILES {
+            let path = dir.join(file);
+            let Ok(contents) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            if let Some(channel) = toolchain_file_channel(&contents) {
+                return Some(RustToolchain {
+                    name: short_name(&channel),
+                    selected_by: file.to_string(),
+                    path: Some(path.to_string_lossy().into_owned()),
+                });
+            }
+        }
+    }
+
+    let default = settings.get("default_toolchain")?.as_str()?;
+    Some(RustToolchain {
+        name: short_name(default),
+        selected_by: DEFAULT.to_string(),
+        path: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_name() {
+        assert_eq!(short_name("stable-x86_64-unknown-linux-gnu"), "stable");
+        assert_eq!(
+            short_name("nightly-2024-11-28-aarch64-apple-darwin"),
+            "nightly-2024-11-28"
+        );
+        assert_eq!(short_name("1.84.0-x86_64-pc-windows-msvc"), "1.84.0");
+        assert_eq!(short_name("nightly"), "nightly");
+    }
+
+    #[test]
+    fn test_toolchain_file_channel() {
+        assert_eq!(
+            toolchain_file_channel(
+                "[toolchain]\nchannel = \"nightly-2024-11-28\"\ncomponents = [\"rustfmt\"]\n"
+            )
+            .as_deref(),
+            Some("nightly-2024-11-28")
+        );
+        assert_eq!(
+            toolchain_file_channel("1.84.0\n").as_deref(),
+            Some("1.84.0")
+        );
+        assert_eq!(
+            toolchain_file_channel("[toolchain]\nprofile = \"minimal\"\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_active_toolchain() {
+        let home = tempfile::tempdir().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let crate_dir = root.path().join("crates/app");
+        std::fs::create_dir_all(&crate_dir).unwrap();
+        let no_env = |_: &str| None;
+
+        // Nothing configured
+        assert_eq!(active_toolchain(&crate_dir, home.path(), &no_env), None);
+
+        std::fs::write(
+            home.path().join("settings.toml"),
+            "default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\nversion = \"12\"\n",
+        )
+        .unwrap();
+        let toolchain = active_toolchain(&crate_dir, home.path(), &no_env).unwrap();
+        assert_eq!(toolchain.name, "stable");
+        assert_eq!(toolchain.selected_by, DEFAULT);
+
+        // A toolchain file in an ancestor
+        let file = root.path().join("rust-toolchain.toml");
+        std::fs::write(&file, "[toolchain]\nchannel = \"nightly\"\n").unwrap();
+        let toolchain = active_toolchain(&crate_dir, home.path(), &no_env).unwrap();
+        assert_eq!(toolchain.name, "nightly");
+        assert_eq!(toolchain.selected_by, "rust-toolchain.toml");
+        assert_eq!(toolchain.path.as_deref(), Some(file.to_str().unwrap()));
+
+        // A nearer directory override wins
+        std::fs::write(
+            home.path().join("settings.toml"),
+            format!(
+                "default_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n\n[overrides]\n\"{}\" = \"1.80.0-x86_64-unknown-linux-gnu\"\n",
+                crate_dir.display()
+            ),
+        )
+        .unwrap();
+        let toolchain = active_toolchain(&crate_dir, home.path(), &no_env).unwrap();
+        assert_eq!(toolchain.name, "1.80.0");
+        assert_eq!(toolchain.selected_by, "directory override");
+
+        // RUSTUP_TOOLCHAIN beats everything
+        let env = |var: &str| (var == "RUSTUP_TOOLCHAIN").then(|| "beta".to_string());
+        let toolchain = active_toolchain(&crate_dir, home.path(), &env).unwrap();
+        assert_eq!(toolchain.name, "beta");
+        assert_eq!(toolchain.selected_by, "RUSTUP_TOOLCHAIN");
+    }
+}
@@ -0,0 +1,256 @@
+//! Sandboxed version commands
+//!
+//! Version commands come from config, and a config that isn't fully trusted
+//! can run anything. With `[sandbox]` enabled, version commands see only
+//! allowlisted environment variables, run in the detection directory and,
+//! where the system supports it, without network access (`unshare --net` on
+//! Linux, `sandbox-exec` on macOS).
+
+use crate::config::SandboxConfig;
+use crate::detector;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+/// Environment variables passed to sandboxed commands by default; a trailing
+/// `*` matches a prefix
+pub const DEFAULT_ENV_ALLOWLIST: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "LANG",
+    "LC_*",
+    "TERM",
+    "TMPDIR",
+    "XDG_*",
+    // Version managers and toolchains
+    "ASDF_*",
+    "MISE_*",
+    "NVM_DIR",
+    "PYENV_*",
+    "RBENV_*",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_HOME",
+    "GOROOT",
+    "GOPATH",
+    "JAVA_HOME",
+    "VIRTUAL_ENV",
+    "CONDA_*",
+];
+
+/// Command prefixes that cut a command off the network, tried in order
+#[cfg(target_os = "linux")]
+const NETWORK_WRAPPERS: &[&[&str]] = &[
+    &["unshare", "--net", "--map-current-user", "--"],
+    &["unshare", "--net", "--map-root-user", "--"],
+];
+#[cfg(target_os = "macos")]
+const NETWORK_WRAPPERS: &[&[&str]] = &[&[
+    "sandbox-exec",
+    "-p",
+    "(version 1) (allow default) (deny network*)",
+]];
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+const NETWORK_WRAPPERS: &[&[&str]] = &[];
+
+/// How version commands are sandboxed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    /// The whole environment the commands see
+    pub env: Vec<(OsString, OsString)>,
+    /// Directory the commands run in
+    pub dir: Option<PathBuf>,
+    /// Prefix that cuts the commands off the network; `None` if network is
+    /// allowed or the system has no way to deny it
+    pub network_wrapper: Option<Vec<String>>,
+}
+
+impl Sandbox {
+    /// The sandbox of `config` for commands run in `dir`, with the allowed
+    /// part of `vars` as environment; `None` if sandboxing is disabled
+    pub fn new(
+        config: &SandboxConfig,
+        dir: Option<PathBuf>,
+        vars: impl IntoIterator<Item = (OsString, OsString)>,
+    ) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let mut env: Vec<(OsString, OsString)> = vars
+            .into_iter()
+            .filter(|(name, _)| {
+                name.to_str()
+                    .is_some_and(|name| name != "PWD" && is_allowed(name, &config.env_allowlist))
+            })
+            .collect();
+        if let Some(ref dir) = dir {
+            env.push(("PWD".into(), dir.clone().into_os_string()));
+        }
+        let network_wrapper = if config.allow_network {
+            None
+        } else {
+            network_wrapper().map(|wrapper| wrapper.iter().map(|part| part.to_string()).collect())
+        };
+        Some(Self {
+            env,
+            dir,
+            network_wrapper,
+        })
+    }
+
+    /// A command line run without network access (if denied and supported)
+    pub fn wrap(&self, command: Vec<String>) -> Vec<String> {
+        match self.network_wrapper {
+            Some(ref wrapper) if !command.is_empty() => {
+                wrapper.iter().cloned().chain(command).collect()
+            }
+            _ => command,
+        }
+    }
+
+    /// A sandboxed command: network denied, restricted environment and
+    /// pinned working directory
+    pub fn command(&self, program: &str, args: &[&str]) -> Command {
+        let parts = self.wrap(
+            std::iter::once(program)
+                .chain(args.iter().copied())
+                .map(String::from)
+                .collect(),
+        );
+        let mut cmd = Command::new(detector::spawn_program(&parts[0]));
+        cmd.args(&parts[1..])
+            .env_clear()
+            .envs(self.env.iter().cloned());
+        if let Some(ref dir) = self.dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+}
+
+/// Whether an environment variable matches an allowlist entry
+pub fn is_allowed(name: &str, allowlist: &[String]) -> bool {
+    allowlist.iter().any(|entry| match entry.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == entry,
+    })
+}
+
+/// The first network wrapper that works on this system, probed once
+fn network_wrapper() -> Option<&'static [&'static str]> {
+    static WRAPPER: OnceLock<Option<&'static [&'static str]>> = OnceLock::new();
+    *WRAPPER.get_or_init(|| {
+        NETWORK_WRAPPERS.iter().copied().find(|wrapper| {
+            let Some((program, args)) = wrapper.split_first() else {
+                return false;
+            };
+            Command::new(program)
+                .args(args)
+                .arg("true")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> SandboxConfig {
+        SandboxConfig {
+            enabled: true,
+            allow_network: true,
+            ..SandboxConfig::default()
+        }
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(OsString, OsString)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect()
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let allowlist = vec!["PATH".to_string(), "LC_*".to_string()];
+        assert!(is_allowed("PATH", &allowlist));
+        assert!(is_allowed("LC_ALL", &allowlist));
+        assert!(!is_allowed("PATHS", &allowlist));
+        assert!(!is_allowed("AWS_SECRET_ACCESS_KEY", &allowlist));
+    }
+
+    #[test]
+    fn test_new_disabled() {
+        assert_eq!(
+            Sandbox::new(&SandboxConfig::default(), None, Vec::new()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_new_filters_env_and_pins_dir() {
+        let sandbox = Sandbox::new(
+            &enabled(),
+            Some(PathBuf::from("/work/app")),
+            vars(&[
+                ("PATH", "/usr/bin"),
+                ("GITHUB_TOKEN", "secret"),
+                ("MISE_DATA_DIR", "/data"),
+                ("PWD", "/elsewhere"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            sandbox.env,
+            vars(&[
+                ("PATH", "/usr/bin"),
+                ("MISE_DATA_DIR", "/data"),
+                ("PWD", "/work/app"),
+            ])
+        );
+        assert_eq!(sandbox.network_wrapper, None);
+        assert_eq!(sandbox.wrap(vec!["go".to_string()]), vec!["go"]);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let sandbox = Sandbox {
+            env: Vec::new(),
+            dir: None,
+            network_wrapper: Some(vec!["unshare".to_string(), "--net".to_string()]),
+        };
+        assert_eq!(
+            sandbox.wrap(vec!["node".to_string(), "--version".to_string()]),
+            vec!["unshare", "--net", "node", "--version"]
+        );
+        assert!(sandbox.wrap(Vec::new()).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = Sandbox::new(
+            &enabled(),
+            Some(dir.path().to_path_buf()),
+            vars(&[("PATH", "/usr/bin:/bin"), ("SECRET", "x")]),
+        )
+        .unwrap();
+        let output = sandbox
+            .command("sh", &["-c", "echo \"$SECRET|$(pwd -P)\""])
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            format!("|{}", dir.path().canonicalize().unwrap().display())
+        );
+    }
+}
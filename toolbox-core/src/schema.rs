@@ -0,0 +1,58 @@
+//! JSON Schema generation
+//!
+//! Schemas are derived from the serde types, so they always match what the
+//! config loader accepts and what `--format json` emits.
+
+use crate::config::Config;
+use crate::info::ToolboxInfo;
+use serde_json::Value;
+
+/// JSON Schema for `config.toml`
+pub fn config_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default()
+}
+
+/// JSON Schema for `toolbox --format json` output
+pub fn output_schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(ToolboxInfo)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_schema() {
+        let schema = config_schema();
+        assert_eq!(schema["title"], "Config");
+        let properties = schema["properties"].as_object().unwrap();
+        for key in ["display", "cache", "history", "theme", "custom_tools"] {
+            assert!(properties.contains_key(key), "missing {}", key);
+        }
+
+        let definitions = &schema["definitions"];
+        assert_eq!(definitions["ThemeColor"]["type"], "string");
+        // Documented variants are listed individually
+        let sections = definitions["Section"]["oneOf"].as_array().unwrap();
+        assert_eq!(sections.len(), 5);
+        assert_eq!(sections[0]["enum"], serde_json::json!(["dir"]));
+        // Doc comments become descriptions
+        assert!(
+            definitions["CacheConfig"]["properties"]["default_ttl"]["description"]
+                .as_str()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_output_schema() {
+        let schema = output_schema();
+        assert_eq!(schema["title"], "ToolboxInfo");
+        assert_eq!(schema["properties"]["tools"]["type"], "array");
+        let required = schema["definitions"]["ToolInfo"]["required"]
+            .as_array()
+            .unwrap();
+        assert!(required.contains(&serde_json::json!("name")));
+        assert!(required.contains(&serde_json::json!("available")));
+    }
+}
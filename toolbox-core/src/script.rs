@@ -0,0 +1,104 @@
+//! `parse_script` evaluation (`scripting` feature)
+//!
+//! For version outputs a regex can't reasonably handle (JSON from
+//! `deno --version --json`, multi-line `aws --version`), a tool's
+//! `parse_script` is a [rhai](https://rhai.rs) script. It sees `stdout`,
+//! `stderr` and `output` (the text `parse_regex` would see) and returns the
+//! version as a string, or a map of version fields:
+//!
+//! ```text
+//! let v = parse_json(stdout);
+//! #{ version: v.deno, runtime: "v8 " + v.v8 }
+//! ```
+
+use crate::error::{Result, ToolboxError};
+use rhai::{Dynamic, Engine, Scope};
+use std::collections::BTreeMap;
+
+/// Operation budget per evaluation, so a runaway script can't hang detection
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Evaluate a parse script, returning its version fields, trimmed (`version`
+/// always present)
+pub fn evaluate(
+    script: &str,
+    stdout: &str,
+    stderr: &str,
+    output: &str,
+) -> Result<BTreeMap<String, String>> {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    let mut scope = Scope::new();
+    scope.push("stdout", stdout.to_string());
+    scope.push("stderr", stderr.to_string());
+    scope.push("output", output.to_string());
+
+    let result: Dynamic = engine
+        .eval_with_scope(&mut scope, script)
+        .map_err(|e| ToolboxError::VersionParse(format!("parse_script: {}", e)))?;
+    let fields: BTreeMap<String, String> = if result.is_map() {
+        result
+            .cast::<rhai::Map>()
+            .into_iter()
+            .filter(|(_, value)| !value.is_unit())
+            .map(|(key, value)| (key.to_string(), value.to_string().trim().to_string()))
+            .collect()
+    } else if result.is_unit() {
+        BTreeMap::new()
+    } else {
+        BTreeMap::from([("version".to_string(), result.to_string().trim().to_string())])
+    };
+    match fields.get("version") {
+        Some(version) if !version.trim().is_empty() => Ok(fields),
+        _ => Err(ToolboxError::VersionParse(
+            "parse_script returned no version".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_string() {
+        let output = "aws-cli/2.15.10 Python/3.11.6 Linux/6.5.0 exe/x86_64.ubuntu.22\n";
+        let fields = evaluate(r#"stdout.split(" ")[0].split("/")[1]"#, output, "", output).unwrap();
+        assert_eq!(fields.get("version").map(String::as_str), Some("2.15.10"));
+    }
+
+    #[test]
+    fn test_evaluate_map() {
+        let json = r#"{"deno": "1.40.0", "v8": "12.1.285.6", "typescript": "5.3.3"}"#;
+        let fields = evaluate(
+            r#"let v = parse_json(stdout); #{ version: v.deno, runtime: "v8 " + v.v8, build: () }"#,
+            json,
+            "",
+            json,
+        )
+        .unwrap();
+        assert_eq!(fields.get("version").map(String::as_str), Some("1.40.0"));
+        assert_eq!(
+            fields.get("runtime").map(String::as_str),
+            Some("v8 12.1.285.6")
+        );
+        assert!(!fields.contains_key("build"));
+    }
+
+    #[test]
+    fn test_evaluate_stderr() {
+        let fields = evaluate("stderr", "", "1.2.3\n", "1.2.3\n").unwrap();
+        assert_eq!(fields.get("version").map(String::as_str), Some("1.2.3"));
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        let err = evaluate("let x = ;", "", "", "").unwrap_err();
+        assert!(err.to_string().contains("parse_script:"));
+        let err = evaluate("#{ build: \"13\" }", "", "", "").unwrap_err();
+        assert!(err.to_string().contains("parse_script returned no version"));
+        // Runaway scripts hit the operation budget
+        let err = evaluate("loop {}", "", "", "").unwrap_err();
+        assert!(err.to_string().contains("parse_script:"));
+    }
+}
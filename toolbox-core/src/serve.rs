@@ -0,0 +1,585 @@
+//! HTTP JSON API server
+//!
+//! `toolbox serve --http 127.0.0.1:7654` answers `GET /info`, `/doctor` and
+//! `/cache/stats` with the same JSON as `--format json`, `doctor --json` and
+//! the cache counters, for editor extensions and dashboards. One long-lived
+//! detector serves all requests, so its version cache stays warm. With a
+//! [`ConfigWatcher`] the config is reloaded when its files change (checked
+//! before each request), or on `POST /reload` (`toolbox daemon reload`).
+//!
+//! The server is deliberately small: HTTP/1.1 GET (and `POST /reload`) only,
+//! one request per connection, handled one at a time. Against DNS rebinding,
+//! requests must name the server itself in `Host` (`localhost` or the address
+//! it was reached at) and must not carry an `Origin`, which browsers send on
+//! cross-site requests.
+
+use crate::cache::CacheStats;
+use crate::config::{expand_home, Config};
+use crate::config_watch::{self, ConfigWatcher};
+use crate::detector::ToolDetector;
+use crate::error::{Result, ToolboxError};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+/// Default listen address of `toolbox serve`
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7654";
+
+/// Endpoints listed at `/`
+const ENDPOINTS: &[&str] = &[
+    "/info?dir=PATH&refresh=1",
+    "/doctor?dir=PATH",
+    "/cache/stats",
+    "POST /reload",
+];
+
+/// Seconds a client may take to send its request
+const READ_TIMEOUT: u64 = 5;
+
+/// Status code and JSON body of a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &serde_json::Value) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string_pretty(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "error": message }))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// Answers API requests from one detector
+pub struct ApiServer {
+    detector: ToolDetector,
+    /// Directory used when a request gives none
+    default_dir: Option<String>,
+    /// Config files to reload the detector's config from
+    watcher: Option<ConfigWatcher>,
+    /// Receives a log line for each reload
+    on_reload: Box<dyn FnMut(&str)>,
+    /// Loads the config for a directory (`None`: the default one)
+    dir_config: Option<DirConfigLoader>,
+    /// Directory the detector's config was loaded for
+    config_dir: Option<String>,
+}
+
+/// Loads the config that applies in a directory (`None`: the default one),
+/// with its project config and `[[dir_overrides]]`
+pub type DirConfigLoader = Box<dyn Fn(Option<&str>) -> Result<Config>>;
+
+impl ApiServer {
+    /// Server around a detector; `default_dir` applies when `?dir=` is absent
+    pub fn new(detector: ToolDetector, default_dir: Option<String>) -> Self {
+        Self {
+            detector,
+            config_dir: default_dir.clone(),
+            default_dir,
+            watcher: None,
+            on_reload: Box::new(|_| {}),
+            dir_config: None,
+        }
+    }
+
+    /// Load the config for the directory of each request (`?dir=`), instead
+    /// of using the default directory's for all of them
+    pub fn with_dir_config(
+        mut self,
+        loader: impl Fn(Option<&str>) -> Result<Config> + 'static,
+    ) -> Self {
+        self.dir_config = Some(Box::new(loader));
+        self
+    }
+
+    /// Reload the config when the watched files change, passing what changed
+    /// (or why the previous config was kept) to `on_reload`
+    pub fn with_config_reload(
+        mut self,
+        watcher: ConfigWatcher,
+        on_reload: impl FnMut(&str) + 'static,
+    ) -> Self {
+        self.watcher = Some(watcher);
+        self.on_reload = Box::new(on_reload);
+        self
+    }
+
+    /// Load the watched config into the detector; the changed settings
+    pub fn reload_config(&mut self) -> Result<Vec<String>> {
+        let Some(ref watcher) = self.watcher else {
+            return Err(ToolboxError::Config(
+                "This server does not reload its config".to_string(),
+            ));
+        };
+        let result = watcher.load().map(|config| {
+            let changes = config_watch::config_changes(self.detector.config(), &config);
+            self.detector.set_config(config);
+            self.config_dir = self.default_dir.clone();
+            changes
+        });
+        let message = match result {
+            Ok(ref changes) => config_watch::reload_message(changes),
+            Err(ref err) => config_watch::reload_failed_message(err),
+        };
+        (self.on_reload)(&message);
+        result
+    }
+
+    /// Switch the detector to the config of a request's directory
+    fn use_dir_config(&mut self, dir: &Option<String>) -> Result<()> {
+        let Some(ref loader) = self.dir_config else {
+            return Ok(());
+        };
+        if *dir != self.config_dir {
+            self.detector.set_dir_config(loader(dir.as_deref())?);
+            self.config_dir = dir.clone();
+        }
+        Ok(())
+    }
+
+    fn reload_if_changed(&mut self) {
+        if self.watcher.as_mut().is_some_and(ConfigWatcher::changed) {
+            let _ = self.reload_config();
+        }
+    }
+
+    /// Accept connections until the listener fails
+    pub fn serve(&mut self, listener: &TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            // A misbehaving client must not stop the server
+            if let Err(err) = stream.and_then(|stream| self.handle_connection(stream)) {
+                tracing::debug!("connection failed: {}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read one request from a connection and answer it
+    pub fn handle_connection(&mut self, mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT)))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Keep Host and Origin; requests have no body
+        let (mut host, mut origin) = (None, false);
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("host") {
+                    host = Some(value.trim().to_string());
+                } else if name.eq_ignore_ascii_case("origin") {
+                    origin = true;
+                }
+            }
+            header.clear();
+        }
+
+        let response = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+            _ if origin => Response::error(403, "Cross-origin requests are not allowed"),
+            _ if !host.is_some_and(|host| is_own_host(&host, stream.local_addr())) => {
+                Response::error(403, "Host must be localhost or the server's address")
+            }
+            [method, target, _] => self.route(method, target),
+            _ => Response::error(400, "Malformed request"),
+        };
+        tracing::debug!("{} -> {}", request_line.trim_end(), response.status);
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.reason(),
+            response.body.len(),
+            response.body
+        )?;
+        stream.flush()
+    }
+
+    /// Answer a request for a method and target (path with query)
+    pub fn route(&mut self, method: &str, target: &str) -> Response {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (target, BTreeMap::new()),
+        };
+        let dir = query
+            .get("dir")
+            .map(|dir| expand_home(dir).to_string_lossy().into_owned())
+            .or_else(|| self.default_dir.clone());
+
+        let path = path.trim_end_matches('/');
+        match (method, path) {
+            ("POST", "/reload") => {}
+            (_, "/reload") => return Response::error(405, "Use POST /reload"),
+            ("GET", _) => {}
+            _ => return Response::error(405, "Only GET is supported"),
+        }
+        if path == "/reload" {
+            // Take in the current file stamps, so the next request doesn't reload again
+            if let Some(ref mut watcher) = self.watcher {
+                watcher.changed();
+            }
+            return match self.reload_config() {
+                Ok(changes) => Response::json(200, &json!({ "changes": changes })),
+                Err(err) => Response::error(500, &err.to_string()),
+            };
+        }
+        self.reload_if_changed();
+        if matches!(path, "/info" | "/doctor") {
+            if let Err(err) = self.use_dir_config(&dir) {
+                return Response::error(400, &err.to_string());
+            }
+        }
+
+        match path {
+            "" => Response::json(200, &json!({ "endpoints": ENDPOINTS })),
+            "/info" => {
+                if query.get("refresh").is_some_and(|v| v != "0") {
+                    if let Some(cache) = self.detector.cache_mut() {
+                        cache.clear();
+                    }
+                }
+                self.detector.set_working_dir(dir);
+                let info = self.detector.detect_all();
+                to_response(&info)
+            }
+            "/doctor" => {
+                self.detector.set_working_dir(dir);
+                to_response(&self.detector.diagnose_all())
+            }
+            "/cache/stats" => to_response(&CacheStats::of(self.detector.cache())),
+            _ => Response::error(404, &format!("Not found: {}", path)),
+        }
+    }
+}
+
+/// Whether a `Host` header names the server reached at `local`: `localhost`
+/// or its address, with its port or none
+fn is_own_host(host: &str, local: std::io::Result<SocketAddr>) -> bool {
+    let Ok(local) = local else {
+        return false;
+    };
+    let (name, port) = match host.strip_prefix('[') {
+        // [::1]:7654
+        Some(rest) => match rest.split_once(']') {
+            Some((name, "")) => (name, None),
+            Some((name, port)) => match port.strip_prefix(':') {
+                Some(port) => (name, Some(port)),
+                None => return false,
+            },
+            None => return false,
+        },
+        None => match host.split_once(':') {
+            Some((name, port)) => (name, Some(port)),
+            None => (host, None),
+        },
+    };
+    port.is_none_or(|port| port.parse() == Ok(local.port()))
+        && (name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>() == Ok(local.ip()))
+}
+
+/// Send `method target` to a server at `addr`; the status and JSON body
+pub fn request(addr: &str, method: &str, target: &str) -> Result<Response> {
+    let mut stream = TcpStream::connect(addr).map_err(|err| {
+        ToolboxError::CommandFailed(format!(
+            "No toolbox server at {} (start one with `toolbox serve`): {}",
+            addr, err
+        ))
+    })?;
+    stream.set_read_timeout(Some(Duration::from_secs(READ_TIMEOUT)))?;
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        method, target, addr
+    )?;
+    let mut response = String::new();
+    std::io::Read::read_to_string(&mut stream, &mut response)?;
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| ToolboxError::CommandFailed(format!("Invalid response from {}", addr)))?;
+    Ok(Response {
+        status,
+        body: body.to_string(),
+    })
+}
+
+fn to_response<T: serde::Serialize>(value: &T) -> Response {
+    match serde_json::to_value(value) {
+        Ok(value) => Response::json(200, &value),
+        Err(err) => Response::error(500, &err.to_string()),
+    }
+}
+
+/// Parse `a=1&b=x%20y` into decoded key/value pairs
+pub fn parse_query(query: &str) -> BTreeMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes and `+` (space); invalid escapes are kept as is
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = |b: u8| (b as char).to_digit(16);
+                match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                    (Some(high), Some(low)) => {
+                        out.push((high * 16 + low) as u8);
+                        i += 2;
+                    }
+                    _ => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::io::Read;
+
+    fn server() -> ApiServer {
+        let config: Config = toml::from_str(
+            r#"
+use_default_tools = false
+
+[[custom_tools]]
+name = "Echo"
+command = "echo v2.0.0"
+parse_regex = 'v?(\d+\.\d+\.\d+)'
+enabled = true
+"#,
+        )
+        .unwrap();
+        ApiServer::new(ToolDetector::new(config), None)
+    }
+
+    fn body(response: &Response) -> serde_json::Value {
+        serde_json::from_str(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("dir=%2Fhome%2Fme%20x&refresh=1&flag&bad=%zz");
+        assert_eq!(query["dir"], "/home/me x");
+        assert_eq!(query["refresh"], "1");
+        assert_eq!(query["flag"], "");
+        assert_eq!(query["bad"], "%zz");
+        assert_eq!(percent_decode("a+b%2"), "a b%2");
+    }
+
+    #[test]
+    fn test_routes() {
+        let mut server = server();
+
+        let response = server.route("GET", "/info");
+        assert_eq!(response.status, 200);
+        assert_eq!(body(&response)["tools"][0]["version"], "2.0.0");
+
+        // The second lookup is served from the cache
+        server.route("GET", "/info?dir=/tmp");
+        server.route("GET", "/info?dir=/tmp");
+        let stats = body(&server.route("GET", "/cache/stats"));
+        assert_eq!(stats["enabled"], true);
+        assert!(stats["hits"].as_u64().unwrap() >= 1);
+        assert_eq!(stats["evictions"], 0);
+
+        let response = server.route("GET", "/doctor");
+        assert_eq!(response.status, 200);
+        assert_eq!(body(&response)["tools"][0]["name"], "Echo");
+
+        assert_eq!(server.route("GET", "/").status, 200);
+        assert_eq!(server.route("GET", "/nope").status, 404);
+        assert_eq!(server.route("POST", "/info").status, 405);
+        assert_eq!(server.route("GET", "/reload").status, 405);
+    }
+
+    #[test]
+    fn test_dir_config() {
+        let echo = |version: &str| -> Config {
+            toml::from_str(&format!(
+                "use_default_tools = false\n\n[[custom_tools]]\nname = \"Echo\"\ncommand = \"echo {}\"\nenabled = true\n",
+                version
+            ))
+            .unwrap()
+        };
+        let project = tempfile::tempdir().unwrap();
+        let overridden = project.path().to_string_lossy().into_owned();
+        let target = format!("/info?dir={}", overridden);
+        let mut server =
+            ApiServer::new(ToolDetector::new(echo("1.0.0")), None).with_dir_config(move |dir| {
+                match dir {
+                    Some("/broken") => Err(ToolboxError::Config("bad override".to_string())),
+                    Some(dir) if dir == overridden => Ok(echo("3.0.0")),
+                    _ => Ok(echo("1.0.0")),
+                }
+            });
+        let version = |server: &mut ApiServer, target: &str| {
+            body(&server.route("GET", target))["tools"][0]["version"].clone()
+        };
+        assert_eq!(version(&mut server, "/info"), "1.0.0");
+        assert_eq!(version(&mut server, &target), "3.0.0");
+        assert_eq!(version(&mut server, "/info"), "1.0.0");
+
+        let response = server.route("GET", "/doctor?dir=/broken");
+        assert_eq!(response.status, 400);
+        assert!(body(&response)["error"]
+            .as_str()
+            .unwrap()
+            .contains("bad override"));
+    }
+
+    #[test]
+    fn test_config_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let echo = |version: &str| {
+            format!(
+                "use_default_tools = false\n\n[[custom_tools]]\nname = \"Echo\"\ncommand = \"echo {}\"\nenabled = true\n",
+                version
+            )
+        };
+        std::fs::write(&path, echo("1.0.0")).unwrap();
+        let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let messages = log.clone();
+        let watcher = ConfigWatcher::new(Some(path.clone()));
+        let mut server = ApiServer::new(ToolDetector::new(watcher.load().unwrap()), None)
+            .with_config_reload(watcher, move |message| {
+                messages.borrow_mut().push(message.to_string())
+            });
+        assert_eq!(
+            body(&server.route("GET", "/info"))["tools"][0]["version"],
+            "1.0.0"
+        );
+
+        // Picked up before the next request, cached versions dropped
+        std::fs::write(&path, echo("10.0.0")).unwrap();
+        assert_eq!(
+            body(&server.route("GET", "/info"))["tools"][0]["version"],
+            "10.0.0"
+        );
+        assert_eq!(log.borrow().len(), 1);
+        assert_eq!(log.borrow()[0], "Config reloaded: custom_tools changed");
+
+        let response = server.route("POST", "/reload");
+        assert_eq!(response.status, 200);
+        assert_eq!(body(&response)["changes"], json!([]));
+
+        // A broken config is reported and the previous one kept
+        std::fs::write(&path, "use_default_tools = [").unwrap();
+        let response = server.route("POST", "/reload");
+        assert_eq!(response.status, 500);
+        assert!(log.borrow()[2].starts_with("Config reload failed, keeping the previous config"));
+        assert_eq!(
+            body(&server.route("GET", "/info"))["tools"][0]["version"],
+            "10.0.0"
+        );
+
+        assert_eq!(self::server().route("POST", "/reload").status, 500);
+    }
+
+    /// Send a raw request to a fresh server; the response
+    fn exchange(request: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream
+                .write_all(request.replace("PORT", &addr.port().to_string()).as_bytes())
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        server().handle_connection(stream).unwrap();
+        client.join().unwrap()
+    }
+
+    #[test]
+    fn test_handle_connection() {
+        let response = exchange("GET /cache/stats HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json"));
+        let (_, json) = response.split_once("\r\n\r\n").unwrap();
+        let stats: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(stats["entries"], 0);
+
+        let response = exchange("GET /cache/stats HTTP/1.1\r\nhost: 127.0.0.1:PORT\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    }
+
+    #[test]
+    fn test_rejects_foreign_host_and_origin() {
+        // A page on evil.example rebound to 127.0.0.1
+        for request in [
+            "GET /info HTTP/1.1\r\nHost: evil.example:PORT\r\n\r\n",
+            "GET /info HTTP/1.1\r\nHost: localhost:1\r\n\r\n",
+            "GET /info HTTP/1.1\r\n\r\n",
+            "GET /info HTTP/1.1\r\nHost: localhost:PORT\r\nOrigin: http://evil.example\r\n\r\n",
+            "POST /reload HTTP/1.1\r\nHost: localhost\r\nOrigin: null\r\n\r\n",
+        ] {
+            let response = exchange(request);
+            assert!(
+                response.starts_with("HTTP/1.1 403 Forbidden\r\n"),
+                "{}",
+                response
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_own_host() {
+        let local = || Ok("127.0.0.1:7654".parse().unwrap());
+        assert!(is_own_host("localhost", local()));
+        assert!(is_own_host("LOCALHOST:7654", local()));
+        assert!(is_own_host("127.0.0.1:7654", local()));
+        assert!(!is_own_host("127.0.0.1:80", local()));
+        assert!(!is_own_host("127.0.0.2", local()));
+        assert!(!is_own_host("127.0.0.1:7654@evil.example", local()));
+        let local6 = || Ok("[::1]:7654".parse().unwrap());
+        assert!(is_own_host("[::1]:7654", local6()));
+        assert!(is_own_host("[::1]", local6()));
+        assert!(!is_own_host("[::1]x", local6()));
+        assert!(!is_own_host(
+            "localhost",
+            Err(std::io::ErrorKind::Other.into())
+        ));
+    }
+}
@@ -0,0 +1,350 @@
+//! Version manager shim resolution
+//!
+//! With asdf or mise, `which node` finds a shim that picks the real binary at
+//! run time. To explain "wrong version" reports, `toolbox doctor` asks the
+//! manager which binary the shim runs (`asdf which`, `mise which`) and which
+//! file selected its version (`asdf current`, `mise ls --current`), falling
+//! back to the nearest `.tool-versions`. It also flags PATH entries that
+//! shadow a manager's binary.
+
+use crate::info::ShimInfo;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Version manager owning a shim
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShimManager {
+    Asdf,
+    Mise,
+}
+
+impl ShimManager {
+    /// Executable name of the manager
+    pub fn command(self) -> &'static str {
+        match self {
+            ShimManager::Asdf => "asdf",
+            ShimManager::Mise => "mise",
+        }
+    }
+}
+
+/// The manager owning a shim path (`~/.asdf/shims/node`,
+/// `~/.local/share/mise/shims/node`), if it is one
+pub fn shim_manager(path: &Path) -> Option<ShimManager> {
+    let shims = path.parent()?;
+    if shims.file_name()? != "shims" {
+        return None;
+    }
+    manager_of_data_dir(shims.parent()?)
+}
+
+/// The manager owning a binary: a shim, or an install
+/// (`~/.asdf/installs/nodejs/20.11.0/bin/node`, as put in PATH by `mise activate`)
+pub fn managed_by(path: &Path) -> Option<ShimManager> {
+    shim_manager(path).or_else(|| {
+        let installs = path.ancestors().find(|dir| dir.ends_with("installs"))?;
+        manager_of_data_dir(installs.parent()?)
+    })
+}
+
+/// The manager whose data directory (`~/.asdf`, `~/.local/share/mise`) this is
+fn manager_of_data_dir(data_dir: &Path) -> Option<ShimManager> {
+    for (var, manager) in [
+        ("ASDF_DATA_DIR", ShimManager::Asdf),
+        ("MISE_DATA_DIR", ShimManager::Mise),
+    ] {
+        if std::env::var_os(var).is_some_and(|dir| Path::new(&dir) == data_dir) {
+            return Some(manager);
+        }
+    }
+    let name = data_dir.file_name()?.to_string_lossy();
+    if name.contains("asdf") {
+        Some(ShimManager::Asdf)
+    } else if name.contains("mise") || name.contains("rtx") {
+        Some(ShimManager::Mise)
+    } else {
+        None
+    }
+}
+
+/// A command found earlier in PATH than the version manager's binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shadowing {
+    /// The binary that runs (first in PATH)
+    pub winner: String,
+    /// The manager's binary it hides
+    pub managed: String,
+    /// The manager (asdf, mise)
+    pub manager: ShimManager,
+}
+
+impl Shadowing {
+    /// What is wrong, for `ToolDiagnostic.error_detail`
+    pub fn detail(&self) -> String {
+        format!(
+            "{} shadows the {} binary {}",
+            self.winner,
+            self.manager.command(),
+            self.managed
+        )
+    }
+
+    /// Which entry wins and why, and how to fix it
+    pub fn suggestion(&self) -> String {
+        let dir = |path: &str| {
+            Path::new(path)
+                .parent()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default()
+        };
+        format!(
+            "{} wins because {} comes before {} in PATH; move {} earlier or remove {}",
+            self.winner,
+            dir(&self.winner),
+            dir(&self.managed),
+            dir(&self.managed),
+            self.winner
+        )
+    }
+}
+
+/// Shadowing among the PATH matches of a command (in PATH order)
+pub fn find_shadowing(matches: &[String]) -> Option<Shadowing> {
+    let winner = matches.first()?;
+    if managed_by(Path::new(winner)).is_some() {
+        return None;
+    }
+    matches[1..].iter().find_map(|path| {
+        Some(Shadowing {
+            winner: winner.clone(),
+            managed: path.clone(),
+            manager: managed_by(Path::new(path))?,
+        })
+    })
+}
+
+/// Resolve a shim to the binary it runs and the file that selected its
+/// version; `None` if the path is not a shim
+pub fn resolve(path: &str, working_dir: Option<&str>) -> Option<ShimInfo> {
+    let manager = shim_manager(Path::new(path))?;
+    let command = Path::new(path).file_name()?.to_string_lossy().into_owned();
+
+    let real_path = run(manager, &["which", &command], working_dir)
+        .map(|out| out.trim().to_string())
+        .filter(|out| !out.is_empty());
+    let (plugin, version) = match real_path.as_deref().and_then(install_of) {
+        Some((plugin, version)) => (Some(plugin), Some(version)),
+        None => (None, None),
+    };
+    let source = plugin.as_deref().and_then(|plugin| {
+        let reported = match manager {
+            ShimManager::Asdf => run(manager, &["current", plugin], working_dir)
+                .and_then(|out| parse_asdf_current(&out, plugin)),
+            ShimManager::Mise => run(manager, &["ls", "--current", "--json", plugin], working_dir)
+                .and_then(|out| parse_mise_current(&out)),
+        };
+        reported.or_else(|| {
+            let start = working_dir
+                .map(PathBuf::from)
+                .or_else(|| std::env::current_dir().ok())?;
+            find_tool_versions(&start, plugin).map(|p| p.display().to_string())
+        })
+    });
+
+    Some(ShimInfo {
+        manager: manager.command().to_string(),
+        real_path,
+        plugin,
+        version,
+        source,
+    })
+}
+
+/// Run a manager subcommand, returning stdout on success
+fn run(manager: ShimManager, args: &[&str], working_dir: Option<&str>) -> Option<String> {
+    let mut cmd = Command::new(manager.command());
+    cmd.args(args);
+    if let Some(dir) = working_dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Plugin and version of an installed binary
+/// (`.../installs/<plugin>/<version>/bin/<command>`)
+pub fn install_of(real_path: &str) -> Option<(String, String)> {
+    let mut components = Path::new(real_path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy());
+    components.find(|c| c == "installs")?;
+    let plugin = components.next()?.into_owned();
+    let version = components.next()?.into_owned();
+    Some((plugin, version))
+}
+
+/// Source column of `asdf current <plugin>`: the selecting file, or the
+/// environment variable that set the version
+///
+/// ```text
+/// nodejs          20.11.0         /home/me/app/.tool-versions
+/// Name            Version         Source                          Installed
+/// nodejs          20.11.0         ASDF_NODEJS_VERSION env var     true
+/// ```
+pub fn parse_asdf_current(output: &str, plugin: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        if fields.next()? != plugin {
+            return None;
+        }
+        let _version = fields.next()?;
+        let source = fields.next()?;
+        Some(source.to_string())
+    })
+}
+
+/// Source of the first entry of `mise ls --current --json <plugin>`
+pub fn parse_mise_current(output: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(output).ok()?;
+    let path = value.get(0)?.get("source")?.get("path")?.as_str()?;
+    Some(path.to_string())
+}
+
+/// The nearest `.tool-versions` at or above `start` that pins `plugin`
+pub fn find_tool_versions(start: &Path, plugin: &str) -> Option<PathBuf> {
+    start.ancestors().find_map(|dir| {
+        let file = dir.join(".tool-versions");
+        let content = std::fs::read_to_string(&file).ok()?;
+        content
+            .lines()
+            .any(|line| line.split_whitespace().next() == Some(plugin))
+            .then_some(file)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shim_manager() {
+        assert_eq!(
+            shim_manager(Path::new("/home/me/.asdf/shims/node")),
+            Some(ShimManager::Asdf)
+        );
+        assert_eq!(
+            shim_manager(Path::new("/home/me/.local/share/mise/shims/python")),
+            Some(ShimManager::Mise)
+        );
+        assert_eq!(shim_manager(Path::new("/usr/bin/node")), None);
+        assert_eq!(shim_manager(Path::new("/opt/tool/shims/node")), None);
+    }
+
+    #[test]
+    fn test_find_shadowing() {
+        let matches = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let shadowing = find_shadowing(&matches(&[
+            "/usr/local/bin/node",
+            "/usr/bin/node",
+            "/home/me/.asdf/shims/node",
+        ]))
+        .unwrap();
+        assert_eq!(shadowing.winner, "/usr/local/bin/node");
+        assert_eq!(shadowing.managed, "/home/me/.asdf/shims/node");
+        assert_eq!(shadowing.manager, ShimManager::Asdf);
+        assert_eq!(
+            shadowing.detail(),
+            "/usr/local/bin/node shadows the asdf binary /home/me/.asdf/shims/node"
+        );
+        assert_eq!(
+            shadowing.suggestion(),
+            "/usr/local/bin/node wins because /usr/local/bin comes before \
+             /home/me/.asdf/shims in PATH; move /home/me/.asdf/shims earlier \
+             or remove /usr/local/bin/node"
+        );
+
+        // mise puts install directories in PATH
+        let shadowing = find_shadowing(&matches(&[
+            "/usr/bin/python",
+            "/home/me/.local/share/mise/installs/python/3.12.1/bin/python",
+        ]))
+        .unwrap();
+        assert_eq!(shadowing.manager, ShimManager::Mise);
+
+        // The manager's binary wins, or there is none
+        assert_eq!(
+            find_shadowing(&matches(&["/home/me/.asdf/shims/node", "/usr/bin/node"])),
+            None
+        );
+        assert_eq!(
+            find_shadowing(&matches(&["/usr/local/bin/node", "/usr/bin/node"])),
+            None
+        );
+        assert_eq!(find_shadowing(&[]), None);
+    }
+
+    #[test]
+    fn test_install_of() {
+        assert_eq!(
+            install_of("/home/me/.asdf/installs/nodejs/20.11.0/bin/node"),
+            Some(("nodejs".to_string(), "20.11.0".to_string()))
+        );
+        assert_eq!(
+            install_of("/home/me/.local/share/mise/installs/python/3.12.1/bin/python"),
+            Some(("python".to_string(), "3.12.1".to_string()))
+        );
+        assert_eq!(install_of("/usr/bin/node"), None);
+    }
+
+    #[test]
+    fn test_parse_asdf_current() {
+        assert_eq!(
+            parse_asdf_current(
+                "nodejs          20.11.0         /home/me/app/.tool-versions\n",
+                "nodejs"
+            )
+            .as_deref(),
+            Some("/home/me/app/.tool-versions")
+        );
+        let newer = "Name            Version         Source                          Installed\n\
+                     nodejs          20.11.0         /home/me/.tool-versions         true\n";
+        assert_eq!(
+            parse_asdf_current(newer, "nodejs").as_deref(),
+            Some("/home/me/.tool-versions")
+        );
+        assert_eq!(parse_asdf_current(newer, "python"), None);
+    }
+
+    #[test]
+    fn test_parse_mise_current() {
+        let output = r#"[{"version": "20.11.0", "source": {"type": "mise.toml", "path": "/home/me/app/mise.toml"}}]"#;
+        assert_eq!(
+            parse_mise_current(output).as_deref(),
+            Some("/home/me/app/mise.toml")
+        );
+        assert_eq!(parse_mise_current("[]"), None);
+        assert_eq!(parse_mise_current("not json"), None);
+    }
+
+    #[test]
+    fn test_find_tool_versions() {
+        let root = tempfile::tempdir().unwrap();
+        let app = root.path().join("app/src");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(root.path().join(".tool-versions"), "nodejs 20.11.0\n").unwrap();
+        std::fs::write(root.path().join("app/.tool-versions"), "python 3.12.1\n").unwrap();
+
+        assert_eq!(
+            find_tool_versions(&app, "python"),
+            Some(root.path().join("app/.tool-versions"))
+        );
+        assert_eq!(
+            find_tool_versions(&app, "nodejs"),
+            Some(root.path().join(".tool-versions"))
+        );
+        assert_eq!(find_tool_versions(&app, "ruby"), None);
+    }
+}
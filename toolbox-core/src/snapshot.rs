@@ -0,0 +1,312 @@
+//! Environment snapshots and drift detection
+//!
+//! `toolbox snapshot` records detected tool versions to a lockfile
+//! (`toolbox.lock.json`); `toolbox diff` compares the live environment against it.
+//! When a lockfile exists, text and powerline output color each recorded tool
+//! by its [`Drift`].
+
+use crate::color::ansi;
+use crate::error::Result;
+use crate::export::format_rfc3339;
+use crate::info::{ToolInfo, ToolboxInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default lockfile name
+pub const DEFAULT_SNAPSHOT_FILE: &str = "toolbox.lock.json";
+
+/// Recorded tool versions at a point in time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// When the snapshot was recorded (RFC 3339, UTC)
+    pub created_at: String,
+    /// Detected version per available tool
+    pub tools: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    /// Capture the available tools of a detection result
+    pub fn from_info(info: &ToolboxInfo) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self {
+            created_at: format_rfc3339(now),
+            tools: info
+                .tools
+                .iter()
+                .filter(|t| t.available)
+                .map(|t| {
+                    (
+                        t.name.clone(),
+                        t.version.clone().unwrap_or_else(|| "?".to_string()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Load a snapshot from a lockfile
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Write the snapshot to a lockfile
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// How a detected tool compares with this snapshot (`None`: not recorded)
+    pub fn drift(&self, tool: &ToolInfo) -> Option<Drift> {
+        let recorded = self.tools.get(&tool.name)?;
+        Some(if !tool.available {
+            Drift::Missing
+        } else if tool.version.as_deref().unwrap_or("?") == recorded {
+            Drift::Match
+        } else {
+            Drift::Changed
+        })
+    }
+
+    /// Set [`ToolInfo::drift`] for every tool of a detection result
+    pub fn annotate(&self, info: &mut ToolboxInfo) {
+        for tool in &mut info.tools {
+            tool.drift = self.drift(tool);
+        }
+    }
+
+    /// Changes from this (recorded) snapshot to `current`
+    pub fn diff(&self, current: &Snapshot) -> Vec<ToolChange> {
+        let mut changes = Vec::new();
+
+        for (name, version) in &self.tools {
+            match current.tools.get(name) {
+                None => changes.push(ToolChange::Removed {
+                    name: name.clone(),
+                    version: version.clone(),
+                }),
+                Some(new_version) if new_version != version => changes.push(ToolChange::Changed {
+                    name: name.clone(),
+                    from: version.clone(),
+                    to: new_version.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for (name, version) in &current.tools {
+            if !self.tools.contains_key(name) {
+                changes.push(ToolChange::Added {
+                    name: name.clone(),
+                    version: version.clone(),
+                });
+            }
+        }
+
+        changes
+    }
+}
+
+/// A tool's version compared with the snapshot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Drift {
+    /// Same version as recorded
+    Match,
+    /// A different (usually newer) version than recorded
+    Changed,
+    /// Recorded, but not detected now
+    Missing,
+}
+
+impl Drift {
+    /// Foreground color: green, yellow or red
+    pub fn ansi_fg(&self) -> &'static str {
+        match self {
+            Drift::Match => ansi::FG_GREEN,
+            Drift::Changed => ansi::FG_YELLOW,
+            Drift::Missing => ansi::FG_RED,
+        }
+    }
+}
+
+/// A single difference between a snapshot and the live environment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum ToolChange {
+    /// Tool present now but not in the snapshot
+    Added { name: String, version: String },
+    /// Tool in the snapshot but no longer detected
+    Removed { name: String, version: String },
+    /// Tool version differs from the snapshot
+    Changed {
+        name: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl ToolChange {
+    /// Format as a diff line ("+", "-" or "~" prefix), optionally colored
+    pub fn format_display(&self, use_color: bool) -> String {
+        let (color, line) = match self {
+            ToolChange::Added { name, version } => {
+                (ansi::FG_GREEN, format!("+ {} {}", name, version))
+            }
+            ToolChange::Removed { name, version } => {
+                (ansi::FG_RED, format!("- {} {}", name, version))
+            }
+            ToolChange::Changed { name, from, to } => {
+                (ansi::FG_YELLOW, format!("~ {} {} -> {}", name, from, to))
+            }
+        };
+
+        if use_color {
+            format!("{}{}{}", color, line, ansi::RESET)
+        } else {
+            line
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::ToolInfo;
+
+    fn snapshot(tools: &[(&str, &str)]) -> Snapshot {
+        Snapshot {
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            tools: tools
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_snapshot_from_info_skips_unavailable() {
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.75.0".to_string(),
+        ));
+        info.tools
+            .push(ToolInfo::unavailable("Ruby".to_string(), None));
+
+        let snap = Snapshot::from_info(&info);
+        assert_eq!(snap.tools.len(), 1);
+        assert_eq!(snap.tools["Rust"], "1.75.0");
+        assert!(snap.created_at.ends_with('Z'));
+    }
+
+    #[test]
+    fn test_snapshot_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_SNAPSHOT_FILE);
+        let snap = snapshot(&[("Node", "20.11.0"), ("Rust", "1.75.0")]);
+
+        snap.save(&path).unwrap();
+        assert_eq!(Snapshot::load(&path).unwrap(), snap);
+    }
+
+    #[test]
+    fn test_snapshot_load_invalid_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DEFAULT_SNAPSHOT_FILE);
+        std::fs::write(&path, "{not json").unwrap();
+        assert!(Snapshot::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_annotate() {
+        let recorded = snapshot(&[("Node", "20.11.0"), ("Ruby", "3.2.0"), ("Rust", "1.75.0")]);
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Node".to_string(),
+            "20.11.0".to_string(),
+        ));
+        info.tools.push(ToolInfo::available(
+            "Rust".to_string(),
+            "1.76.0".to_string(),
+        ));
+        info.tools
+            .push(ToolInfo::unavailable("Ruby".to_string(), None));
+        info.tools
+            .push(ToolInfo::available("Go".to_string(), "1.22.0".to_string()));
+
+        recorded.annotate(&mut info);
+        let drift: Vec<Option<Drift>> = info.tools.iter().map(|t| t.drift).collect();
+        assert_eq!(
+            drift,
+            vec![
+                Some(Drift::Match),
+                Some(Drift::Changed),
+                Some(Drift::Missing),
+                None
+            ]
+        );
+        assert_eq!(Drift::Changed.ansi_fg(), ansi::FG_YELLOW);
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let recorded = snapshot(&[("Node", "20.11.0"), ("Ruby", "3.2.0"), ("Rust", "1.75.0")]);
+        let current = snapshot(&[("Go", "1.22.0"), ("Node", "20.11.0"), ("Rust", "1.76.0")]);
+
+        let changes = recorded.diff(&current);
+        assert_eq!(
+            changes,
+            vec![
+                ToolChange::Removed {
+                    name: "Ruby".to_string(),
+                    version: "3.2.0".to_string()
+                },
+                ToolChange::Changed {
+                    name: "Rust".to_string(),
+                    from: "1.75.0".to_string(),
+                    to: "1.76.0".to_string()
+                },
+                ToolChange::Added {
+                    name: "Go".to_string(),
+                    version: "1.22.0".to_string()
+                },
+            ]
+        );
+        assert!(recorded.diff(&recorded).is_empty());
+    }
+
+    #[test]
+    fn test_tool_change_format_display() {
+        let change = ToolChange::Changed {
+            name: "Rust".to_string(),
+            from: "1.75.0".to_string(),
+            to: "1.76.0".to_string(),
+        };
+        assert_eq!(change.format_display(false), "~ Rust 1.75.0 -> 1.76.0");
+        assert_eq!(
+            change.format_display(true),
+            format!("{}~ Rust 1.75.0 -> 1.76.0{}", ansi::FG_YELLOW, ansi::RESET)
+        );
+    }
+
+    #[test]
+    fn test_tool_change_json() {
+        let change = ToolChange::Added {
+            name: "Go".to_string(),
+            version: "1.22.0".to_string(),
+        };
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["change"], "added");
+        assert_eq!(json["name"], "Go");
+    }
+}
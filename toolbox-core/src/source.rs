@@ -0,0 +1,276 @@
+//! Version source attribution
+//!
+//! Infers where a tool comes from (system package, Homebrew, asdf, nvm, a
+//! virtualenv...) from its resolved binary path and the environment, so a
+//! surprising version can be traced to its installer at a glance.
+
+use crate::shim::{self, ShimManager};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+/// rustup proxies installed in `~/.cargo/bin`
+const RUSTUP_PROXIES: &[&str] = &[
+    "cargo",
+    "cargo-clippy",
+    "cargo-fmt",
+    "clippy-driver",
+    "rust-analyzer",
+    "rust-gdb",
+    "rust-lldb",
+    "rustc",
+    "rustdoc",
+    "rustfmt",
+    "rustup",
+];
+
+/// Where a tool's binary comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ToolSource {
+    /// OS package (`/usr/bin`, `/usr/local/bin`...)
+    System,
+    /// A system path inside a container
+    Docker,
+    Homebrew,
+    Nix,
+    Asdf,
+    Mise,
+    Nvm,
+    Pyenv,
+    Rbenv,
+    Rustup,
+    /// `cargo install`
+    Cargo,
+    /// The active Python virtualenv
+    Venv,
+    /// The active conda environment
+    Conda,
+    /// A user directory (`~/.local/bin`, `~/bin`)
+    User,
+}
+
+impl ToolSource {
+    /// Lowercase name, as in JSON
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToolSource::System => "system",
+            ToolSource::Docker => "docker",
+            ToolSource::Homebrew => "homebrew",
+            ToolSource::Nix => "nix",
+            ToolSource::Asdf => "asdf",
+            ToolSource::Mise => "mise",
+            ToolSource::Nvm => "nvm",
+            ToolSource::Pyenv => "pyenv",
+            ToolSource::Rbenv => "rbenv",
+            ToolSource::Rustup => "rustup",
+            ToolSource::Cargo => "cargo",
+            ToolSource::Venv => "venv",
+            ToolSource::Conda => "conda",
+            ToolSource::User => "user",
+        }
+    }
+}
+
+impl std::fmt::Display for ToolSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Source of a binary in the current process environment; symlinks (e.g.
+/// Homebrew's `bin` into its Cellar) are followed when the path itself
+/// doesn't tell
+pub fn detect(path: &str) -> Option<ToolSource> {
+    let env = |name: &str| std::env::var(name).ok();
+    let in_container =
+        Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists();
+    infer(Path::new(path), &env, in_container).or_else(|| {
+        let real = std::fs::canonicalize(path).ok()?;
+        infer(&real, &env, in_container)
+    })
+}
+
+/// Source of a binary path, given environment lookups and whether this runs
+/// in a container
+pub fn infer(
+    path: &Path,
+    env: &dyn Fn(&str) -> Option<String>,
+    in_container: bool,
+) -> Option<ToolSource> {
+    let under = |var: &str| {
+        env(var)
+            .filter(|dir| !dir.is_empty())
+            .is_some_and(|dir| path.starts_with(dir))
+    };
+    if under("VIRTUAL_ENV") {
+        return Some(ToolSource::Venv);
+    }
+    if under("CONDA_PREFIX") {
+        return Some(ToolSource::Conda);
+    }
+    match shim::managed_by(path) {
+        Some(ShimManager::Asdf) => return Some(ToolSource::Asdf),
+        Some(ShimManager::Mise) => return Some(ToolSource::Mise),
+        None => {}
+    }
+    if under("NVM_DIR") || has_component(path, ".nvm") {
+        return Some(ToolSource::Nvm);
+    }
+    if under("PYENV_ROOT") || has_component(path, ".pyenv") {
+        return Some(ToolSource::Pyenv);
+    }
+    if has_component(path, ".rbenv") {
+        return Some(ToolSource::Rbenv);
+    }
+    if has_component(path, ".rustup") {
+        return Some(ToolSource::Rustup);
+    }
+    if under("CARGO_HOME") || has_component(path, ".cargo") {
+        let name = path.file_name()?.to_string_lossy();
+        let name = name.strip_suffix(".exe").unwrap_or(&name);
+        return Some(if RUSTUP_PROXIES.contains(&name) {
+            ToolSource::Rustup
+        } else {
+            ToolSource::Cargo
+        });
+    }
+    if path.starts_with("/nix") || has_component(path, ".nix-profile") {
+        return Some(ToolSource::Nix);
+    }
+    if path.starts_with("/opt/homebrew")
+        || path.starts_with("/home/linuxbrew/.linuxbrew")
+        || has_component(path, "Cellar")
+    {
+        return Some(ToolSource::Homebrew);
+    }
+    let dir = path.parent()?;
+    if ["/bin", "/sbin", "/usr/bin", "/usr/sbin", "/usr/local/bin"]
+        .iter()
+        .any(|system| dir == Path::new(system))
+    {
+        return Some(if in_container {
+            ToolSource::Docker
+        } else {
+            ToolSource::System
+        });
+    }
+    if dir.ends_with(".local/bin")
+        || env("HOME").is_some_and(|home| dir == Path::new(&home).join("bin"))
+    {
+        return Some(ToolSource::User);
+    }
+    None
+}
+
+fn has_component(path: &Path, name: &str) -> bool {
+    path.components()
+        .any(|c| matches!(c, Component::Normal(n) if n == name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer_with(path: &str, vars: &[(&str, &str)]) -> Option<ToolSource> {
+        let env = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        infer(Path::new(path), &env, false)
+    }
+
+    #[test]
+    fn test_infer_from_path() {
+        let cases = [
+            ("/usr/bin/python3", Some(ToolSource::System)),
+            ("/usr/local/bin/docker", Some(ToolSource::System)),
+            ("/opt/homebrew/bin/node", Some(ToolSource::Homebrew)),
+            (
+                "/usr/local/Cellar/go/1.22.0/bin/go",
+                Some(ToolSource::Homebrew),
+            ),
+            ("/home/me/.asdf/shims/node", Some(ToolSource::Asdf)),
+            (
+                "/home/me/.local/share/mise/installs/python/3.12.1/bin/python",
+                Some(ToolSource::Mise),
+            ),
+            (
+                "/home/me/.nvm/versions/node/v20.11.0/bin/node",
+                Some(ToolSource::Nvm),
+            ),
+            ("/home/me/.pyenv/shims/python", Some(ToolSource::Pyenv)),
+            ("/home/me/.rbenv/shims/ruby", Some(ToolSource::Rbenv)),
+            ("/home/me/.cargo/bin/rustc", Some(ToolSource::Rustup)),
+            ("/home/me/.cargo/bin/ripgrep", Some(ToolSource::Cargo)),
+            ("/nix/store/abc-go-1.22/bin/go", Some(ToolSource::Nix)),
+            ("/home/me/.nix-profile/bin/go", Some(ToolSource::Nix)),
+            ("/home/me/.local/bin/poetry", Some(ToolSource::User)),
+            ("/opt/custom/bin/tool", None),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(infer_with(path, &[]), expected, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_infer_from_environment() {
+        assert_eq!(
+            infer_with(
+                "/work/app/.venv/bin/python",
+                &[("VIRTUAL_ENV", "/work/app/.venv")]
+            ),
+            Some(ToolSource::Venv)
+        );
+        assert_eq!(
+            infer_with(
+                "/opt/conda/envs/ml/bin/python",
+                &[("CONDA_PREFIX", "/opt/conda/envs/ml")]
+            ),
+            Some(ToolSource::Conda)
+        );
+        assert_eq!(
+            infer_with(
+                "/srv/nvm/versions/node/v20/bin/node",
+                &[("NVM_DIR", "/srv/nvm")]
+            ),
+            Some(ToolSource::Nvm)
+        );
+        assert_eq!(
+            infer_with("/home/me/bin/tool", &[("HOME", "/home/me")]),
+            Some(ToolSource::User)
+        );
+        // An empty variable matches nothing
+        assert_eq!(
+            infer_with("/opt/custom/bin/tool", &[("VIRTUAL_ENV", "")]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_infer_in_container() {
+        let env = |_: &str| None;
+        assert_eq!(
+            infer(Path::new("/usr/bin/node"), &env, true),
+            Some(ToolSource::Docker)
+        );
+        assert_eq!(
+            infer(
+                Path::new("/home/me/.nvm/versions/node/v20/bin/node"),
+                &env,
+                true
+            ),
+            Some(ToolSource::Nvm)
+        );
+    }
+
+    #[test]
+    fn test_source_json() {
+        assert_eq!(
+            serde_json::to_string(&ToolSource::Homebrew).unwrap(),
+            "\"homebrew\""
+        );
+        assert_eq!(ToolSource::Rustup.to_string(), "rustup");
+    }
+}
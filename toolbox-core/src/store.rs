@@ -0,0 +1,376 @@
+//! SQLite store for the prompt cache, history and snapshots (`sqlite` feature)
+//!
+//! With `[storage] backend = "sqlite"` the per-directory prompt results, the
+//! version history and every recorded snapshot live in one database instead
+//! of files in the cache dir. Shells refreshing their prompts at the same time
+//! are serialized by SQLite (WAL mode, busy timeout), and the data can be
+//! queried directly:
+//!
+//! ```sh
+//! sqlite3 ~/.local/share/toolbox/toolbox.db \
+//!   "SELECT at, version FROM history WHERE tool = 'Node' ORDER BY id"
+//! ```
+
+use crate::error::Result;
+use crate::history::HistoryEntry;
+use crate::snapshot::Snapshot;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a write waits for another process to finish its own
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS cache (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    stored_at INTEGER NOT NULL,
+    used_at INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS locks (
+    key TEXT PRIMARY KEY,
+    locked_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY,
+    at TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    version TEXT
+);
+CREATE INDEX IF NOT EXISTS history_tool ON history (tool);
+CREATE TABLE IF NOT EXISTS snapshots (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    snapshot TEXT NOT NULL
+);
+";
+
+/// Next `used_at` of the cache: milliseconds since the Unix epoch (`?1`),
+/// moved past the latest use so that uses in the same millisecond stay ordered
+const NEXT_USE: &str = "MAX(?1, (SELECT IFNULL(MAX(used_at), 0) + 1 FROM cache))";
+
+/// Seconds since the Unix epoch
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Milliseconds since the Unix epoch
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// A toolbox database
+#[derive(Debug)]
+pub struct Store {
+    conn: Connection,
+    path: PathBuf,
+}
+
+impl Store {
+    /// Default location: `~/.local/share/toolbox/toolbox.db`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|p| p.join("toolbox").join("toolbox.db"))
+    }
+
+    /// Open (or create) a database
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Readers don't block the writer (and the other way round)
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        conn.execute_batch(SCHEMA)?;
+        // Databases created before uses were recorded
+        if conn.prepare("SELECT used_at FROM cache").is_err() {
+            conn.execute_batch("ALTER TABLE cache ADD COLUMN used_at INTEGER NOT NULL DEFAULT 0")?;
+        }
+        Ok(Self {
+            conn,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Path of the database file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A cached value and its age
+    pub fn cache_get(&self, key: &str) -> Result<Option<(String, Duration)>> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT value, stored_at FROM cache WHERE key = ?1",
+                params![key],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .optional()?;
+        Ok(row.map(|(value, stored_at)| {
+            let age = now().saturating_sub(stored_at).max(0) as u64;
+            (value, Duration::from_secs(age))
+        }))
+    }
+
+    /// Mark a value as the most recently used
+    pub fn cache_touch(&self, key: &str) -> Result<()> {
+        self.conn.execute(
+            &format!("UPDATE cache SET used_at = {} WHERE key = ?2", NEXT_USE),
+            params![now_millis(), key],
+        )?;
+        Ok(())
+    }
+
+    /// Store a value, replacing the previous one
+    pub fn cache_put(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO cache (key, value, stored_at, used_at)
+                 VALUES (?2, ?3, ?4, {})",
+                NEXT_USE
+            ),
+            params![now_millis(), key, value, now()],
+        )?;
+        Ok(())
+    }
+
+    /// Keep the `max_entries` most recently used values (stored or touched),
+    /// returning how many were removed
+    pub fn cache_prune(&self, max_entries: usize) -> Result<usize> {
+        let removed = self.conn.execute(
+            "DELETE FROM cache WHERE key NOT IN
+             (SELECT key FROM cache ORDER BY used_at DESC LIMIT ?1)",
+            params![max_entries as i64],
+        )?;
+        Ok(removed)
+    }
+
+    /// Claim a key unless another process claimed it less than `timeout`
+    /// seconds ago
+    pub fn try_lock(&mut self, key: &str, timeout: u64) -> Result<bool> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let locked_at: Option<i64> = tx
+            .query_row(
+                "SELECT locked_at FROM locks WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if locked_at.is_some_and(|at| now().saturating_sub(at) < timeout as i64) {
+            return Ok(false);
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO locks (key, locked_at) VALUES (?1, ?2)",
+            params![key, now()],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Release a key claimed with [`try_lock`](Self::try_lock)
+    pub fn unlock(&self, key: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM locks WHERE key = ?1", params![key])?;
+        Ok(())
+    }
+
+    /// History entries in the order they were recorded, optionally of one
+    /// tool (case-insensitive)
+    pub fn history(&self, tool: Option<&str>) -> Result<Vec<HistoryEntry>> {
+        let mut statement = self.conn.prepare(
+            "SELECT at, tool, version FROM history
+             WHERE ?1 IS NULL OR tool = ?1 COLLATE NOCASE
+             ORDER BY id",
+        )?;
+        let entries = statement
+            .query_map(params![tool], |row| {
+                Ok(HistoryEntry {
+                    at: row.get(0)?,
+                    tool: row.get(1)?,
+                    version: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// Append the entries `changes` derives from the last version of each
+    /// tool, atomically with respect to other processes
+    pub fn record_history(
+        &mut self,
+        changes: impl FnOnce(&HashMap<String, Option<String>>) -> Vec<HistoryEntry>,
+    ) -> Result<Vec<HistoryEntry>> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let last = {
+            let mut statement = tx.prepare(
+                "SELECT tool, version FROM history
+                 WHERE id IN (SELECT MAX(id) FROM history GROUP BY tool)",
+            )?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<rusqlite::Result<HashMap<String, Option<String>>>>()?
+        };
+        let entries = changes(&last);
+        for entry in &entries {
+            tx.execute(
+                "INSERT INTO history (at, tool, version) VALUES (?1, ?2, ?3)",
+                params![entry.at, entry.tool, entry.version],
+            )?;
+        }
+        tx.commit()?;
+        Ok(entries)
+    }
+
+    /// Keep a snapshot written to a lockfile
+    pub fn save_snapshot(&self, path: &Path, snapshot: &Snapshot) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO snapshots (path, created_at, snapshot) VALUES (?1, ?2, ?3)",
+            params![
+                path.to_string_lossy().into_owned(),
+                snapshot.created_at,
+                serde_json::to_string(snapshot)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Recorded snapshots with their lockfile path, oldest first
+    pub fn snapshots(&self) -> Result<Vec<(PathBuf, Snapshot)>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT path, snapshot FROM snapshots ORDER BY id")?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(path, json)| Ok((PathBuf::from(path), serde_json::from_str(&json)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::{ToolInfo, ToolboxInfo};
+
+    fn temp_store() -> (tempfile::TempDir, Store) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::open(&dir.path().join("toolbox").join("toolbox.db")).unwrap();
+        (dir, store)
+    }
+
+    #[test]
+    fn test_cache_and_locks() {
+        let (_dir, mut store) = temp_store();
+        assert_eq!(store.cache_get("a").unwrap(), None);
+        store.cache_put("a", "1").unwrap();
+        store.cache_put("a", "2").unwrap();
+        let (value, age) = store.cache_get("a").unwrap().unwrap();
+        assert_eq!(value, "2");
+        assert!(age < Duration::from_secs(5));
+        store.cache_put("b", "3").unwrap();
+        store.cache_put("c", "4").unwrap();
+        // a was stored first but used last; reads don't count
+        store.cache_touch("a").unwrap();
+        assert!(store.cache_get("b").unwrap().is_some());
+        assert_eq!(store.cache_prune(2).unwrap(), 1);
+        assert_eq!(store.cache_get("b").unwrap(), None);
+        assert!(store.cache_get("a").unwrap().is_some());
+        assert!(store.cache_get("c").unwrap().is_some());
+
+        assert!(store.try_lock("a", 60).unwrap());
+        assert!(!store.try_lock("a", 60).unwrap());
+        // A lock older than the timeout is taken over
+        assert!(store.try_lock("a", 0).unwrap());
+        store.unlock("a").unwrap();
+        assert!(store.try_lock("a", 60).unwrap());
+    }
+
+    #[test]
+    fn test_cache_without_used_at() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("toolbox.db");
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE cache (key TEXT PRIMARY KEY, value TEXT NOT NULL, stored_at INTEGER NOT NULL);
+                 INSERT INTO cache VALUES ('old', '1', 0);",
+            )
+            .unwrap();
+        let store = Store::open(&path).unwrap();
+        store.cache_put("new", "2").unwrap();
+        // Values from before uses were recorded go first
+        assert_eq!(store.cache_prune(1).unwrap(), 1);
+        assert_eq!(store.cache_get("old").unwrap(), None);
+        assert!(store.cache_get("new").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_history() {
+        let (_dir, mut store) = temp_store();
+        let entry = |at: &str, tool: &str, version: Option<&str>| HistoryEntry {
+            at: at.to_string(),
+            tool: tool.to_string(),
+            version: version.map(String::from),
+        };
+        store
+            .record_history(|last| {
+                assert!(last.is_empty());
+                vec![
+                    entry("t1", "Node", Some("20.0.0")),
+                    entry("t1", "Rust", Some("1.75.0")),
+                ]
+            })
+            .unwrap();
+        store
+            .record_history(|last| {
+                assert_eq!(last["Node"].as_deref(), Some("20.0.0"));
+                vec![entry("t2", "Node", None)]
+            })
+            .unwrap();
+
+        assert_eq!(store.history(None).unwrap().len(), 3);
+        let node = store.history(Some("node")).unwrap();
+        assert_eq!(
+            node,
+            [
+                entry("t1", "Node", Some("20.0.0")),
+                entry("t2", "Node", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let (_dir, store) = temp_store();
+        let mut info = ToolboxInfo::new();
+        info.tools.push(ToolInfo::available(
+            "Node".to_string(),
+            "20.0.0".to_string(),
+        ));
+        let snapshot = Snapshot::from_info(&info);
+        store
+            .save_snapshot(Path::new("/work/toolbox.lock.json"), &snapshot)
+            .unwrap();
+
+        let snapshots = store.snapshots().unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].0, PathBuf::from("/work/toolbox.lock.json"));
+        assert_eq!(snapshots[0].1.tools, snapshot.tools);
+    }
+}
@@ -0,0 +1,78 @@
+//! `parse_template` expansion
+//!
+//! A tool's `parse_template` composes its display string from the named
+//! groups of its `parse_regex`, e.g. `{version}+{build} ({runtime})`.
+
+use std::collections::BTreeMap;
+
+/// Placeholder names in a template, in order
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        names.push(&after[..end]);
+        rest = &after[end + 1..];
+    }
+    names
+}
+
+/// Expand `{name}` placeholders from captured groups; missing groups expand
+/// to nothing and surrounding whitespace is trimmed
+pub fn render(template: &str, groups: &BTreeMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        if let Some(value) = groups.get(&after[..end]) {
+            out.push_str(value);
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn groups(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            placeholders("{version}+{build} ({runtime})"),
+            vec!["version", "build", "runtime"]
+        );
+        assert!(placeholders("plain").is_empty());
+        assert_eq!(placeholders("{version} {unclosed"), vec!["version"]);
+    }
+
+    #[test]
+    fn test_render() {
+        let fields = groups(&[
+            ("version", "21.0.2"),
+            ("build", "13"),
+            ("runtime", "OpenJDK"),
+        ]);
+        assert_eq!(
+            render("{version}+{build} ({runtime})", &fields),
+            "21.0.2+13 (OpenJDK)"
+        );
+        assert_eq!(render("{version} {missing}", &fields), "21.0.2");
+        assert_eq!(render("v{version} {", &fields), "v21.0.2 {");
+    }
+}
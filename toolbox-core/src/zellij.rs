@@ -0,0 +1,282 @@
+//! Zellij layouts and plugin installation
+//!
+//! `toolbox zellij-layout` prints a ready-to-use KDL layout that loads the
+//! plugin, and `toolbox zellij-install` copies the built plugin into the Zellij
+//! plugins directory the layouts refer to. `toolbox zellij-update` keeps that
+//! copy at the CLI's version, read from the plugin's `toolbox_version` custom
+//! section.
+
+use crate::error::{Result, ToolboxError};
+use std::path::{Path, PathBuf};
+
+/// File name of the built plugin
+pub const PLUGIN_FILE: &str = "toolbox_zellij.wasm";
+
+/// Where `cargo build -p toolbox-zellij --target wasm32-wasip1 --release` puts the plugin
+pub const BUILD_OUTPUT: &str = "target/wasm32-wasip1/release/toolbox_zellij.wasm";
+
+/// Custom section holding the plugin version (the plugin's `#[link_section]`)
+pub const VERSION_SECTION: &str = "toolbox_version";
+
+/// Shape of a generated layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutStyle {
+    /// One-line powerline bar above the panes
+    #[default]
+    Statusbar,
+    /// Multi-line pane to the right of the panes
+    Sidebar,
+}
+
+/// Zellij configuration directory (`$ZELLIJ_CONFIG_DIR`, else `~/.config/zellij`)
+pub fn config_dir() -> Option<PathBuf> {
+    match std::env::var_os("ZELLIJ_CONFIG_DIR") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => dirs::home_dir().map(|home| home.join(".config").join("zellij")),
+    }
+}
+
+/// Zellij plugins directory
+pub fn plugins_dir() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("plugins"))
+}
+
+/// `file:` plugin location for a path, with the home directory written as `~`
+pub fn plugin_location(path: &Path) -> String {
+    let shown = match dirs::home_dir() {
+        Some(home) => match path.strip_prefix(&home) {
+            Ok(rest) => Path::new("~").join(rest),
+            Err(_) => path.to_path_buf(),
+        },
+        None => path.to_path_buf(),
+    };
+    format!("file:{}", shown.display())
+}
+
+/// KDL layout loading the plugin from `location`
+pub fn layout(style: LayoutStyle, location: &str) -> String {
+    let location = location.replace('\\', "\\\\").replace('"', "\\\"");
+    match style {
+        LayoutStyle::Statusbar => format!(
+            r#"// toolbox status bar: zellij --layout <this file>
+layout {{
+    pane size=1 borderless=true {{
+        plugin location="{location}" {{
+            single_line "true"
+            powerline "true"
+            layout "split"
+            theme "zellij"
+            refresh_interval "5"
+        }}
+    }}
+    pane
+    pane size=1 borderless=true {{
+        plugin location="zellij:compact-bar"
+    }}
+}}
+"#
+        ),
+        LayoutStyle::Sidebar => format!(
+            r#"// toolbox sidebar: zellij --layout <this file>
+layout {{
+    pane split_direction="vertical" {{
+        pane
+        pane size=32 {{
+            plugin location="{location}" {{
+                refresh_interval "5"
+                show_status "true"
+            }}
+        }}
+    }}
+    pane size=1 borderless=true {{
+        plugin location="zellij:compact-bar"
+    }}
+}}
+"#
+        ),
+    }
+}
+
+/// First existing plugin file among the candidates
+pub fn find_plugin(candidates: &[PathBuf]) -> Result<PathBuf> {
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .ok_or_else(|| {
+            ToolboxError::Config(format!(
+                "{} not found (looked in {}); build it with `cargo build -p toolbox-zellij --target wasm32-wasip1 --release` or pass its path",
+                PLUGIN_FILE,
+                candidates
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })
+}
+
+/// Copy the plugin into `dir` (created if missing); returns the installed path
+pub fn install(plugin: &Path, dir: &Path, force: bool) -> Result<PathBuf> {
+    let target = dir.join(PLUGIN_FILE);
+    if target.exists() && !force {
+        return Err(ToolboxError::Config(format!(
+            "{} already exists (use --force to overwrite)",
+            target.display()
+        )));
+    }
+    std::fs::create_dir_all(dir)?;
+    std::fs::copy(plugin, &target)?;
+    Ok(target)
+}
+
+/// Write a plugin build into `dir` (created if missing), replacing an
+/// installed one; returns the installed path
+pub fn write_plugin(dir: &Path, wasm: &[u8]) -> Result<PathBuf> {
+    let target = dir.join(PLUGIN_FILE);
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&target, wasm)?;
+    Ok(target)
+}
+
+/// Version a plugin build was made from (its [`VERSION_SECTION`]), if the
+/// module has one
+pub fn plugin_version(wasm: &[u8]) -> Option<String> {
+    // Magic number and format version, then (id, size, contents) sections
+    let mut rest = wasm.strip_prefix(b"\0asm")?.get(4..)?;
+    while let Some((&id, after_id)) = rest.split_first() {
+        let (size, read) = read_leb128(after_id)?;
+        let contents = after_id.get(read..read + size)?;
+        rest = &after_id[read + size..];
+        if id != 0 {
+            continue;
+        }
+        let (name_len, read) = read_leb128(contents)?;
+        let name = contents.get(read..read + name_len)?;
+        if name == VERSION_SECTION.as_bytes() {
+            return String::from_utf8(contents[read + name_len..].to_vec()).ok();
+        }
+    }
+    None
+}
+
+/// An unsigned LEB128 number and the bytes it took
+fn read_leb128(bytes: &[u8]) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    for (index, byte) in bytes.iter().enumerate().take(5) {
+        value |= usize::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module with a type section and the given custom sections
+    fn module(custom: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        wasm.extend_from_slice(&[1, 1, 0]);
+        for (name, payload) in custom {
+            let size = 1 + name.len() + payload.len();
+            assert!(size < 0x80);
+            wasm.extend_from_slice(&[0, size as u8, name.len() as u8]);
+            wasm.extend_from_slice(name.as_bytes());
+            wasm.extend_from_slice(payload);
+        }
+        wasm
+    }
+
+    #[test]
+    fn test_plugin_version() {
+        assert_eq!(
+            plugin_version(&module(&[("name", b"x"), (VERSION_SECTION, b"0.3.1")])).as_deref(),
+            Some("0.3.1")
+        );
+        assert_eq!(plugin_version(&module(&[("name", b"x")])), None);
+        assert_eq!(plugin_version(b"not wasm"), None);
+        // Truncated section
+        let mut truncated = module(&[(VERSION_SECTION, b"0.3.1")]);
+        truncated.pop();
+        assert_eq!(plugin_version(&truncated), None);
+    }
+
+    #[test]
+    fn test_read_leb128() {
+        assert_eq!(read_leb128(&[0x05]), Some((5, 1)));
+        assert_eq!(read_leb128(&[0xe5, 0x8e, 0x26]), Some((624485, 3)));
+        assert_eq!(read_leb128(&[0x80]), None);
+    }
+
+    #[test]
+    fn test_write_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugins = dir.path().join("plugins");
+        let path = write_plugin(&plugins, b"one").unwrap();
+        write_plugin(&plugins, b"two").unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"two");
+    }
+
+    #[test]
+    fn test_layouts_reference_the_plugin() {
+        let location = "file:~/.config/zellij/plugins/toolbox_zellij.wasm";
+
+        let statusbar = layout(LayoutStyle::Statusbar, location);
+        assert!(statusbar.contains(&format!(r#"plugin location="{}""#, location)));
+        assert!(statusbar.contains(r#"single_line "true""#));
+        assert!(statusbar.contains("pane size=1 borderless=true"));
+
+        let sidebar = layout(LayoutStyle::Sidebar, location);
+        assert!(sidebar.contains(&format!(r#"plugin location="{}""#, location)));
+        assert!(sidebar.contains(r#"split_direction="vertical""#));
+        assert!(!sidebar.contains("single_line"));
+    }
+
+    #[test]
+    fn test_layout_escapes_location() {
+        let kdl = layout(
+            LayoutStyle::Statusbar,
+            r#"file:/my "plugins"/toolbox_zellij.wasm"#,
+        );
+        assert!(kdl.contains(r#"location="file:/my \"plugins\"/toolbox_zellij.wasm""#));
+    }
+
+    #[test]
+    fn test_plugin_location() {
+        assert_eq!(
+            plugin_location(Path::new("/opt/zellij/toolbox_zellij.wasm")),
+            "file:/opt/zellij/toolbox_zellij.wasm"
+        );
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(
+                plugin_location(&home.join("plugins").join(PLUGIN_FILE)),
+                "file:~/plugins/toolbox_zellij.wasm"
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_plugin_and_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let built = dir.path().join(PLUGIN_FILE);
+        let missing = dir.path().join("missing.wasm");
+
+        let err = find_plugin(std::slice::from_ref(&missing)).unwrap_err();
+        assert!(err.to_string().contains("missing.wasm"));
+
+        std::fs::write(&built, b"\0asm").unwrap();
+        assert_eq!(find_plugin(&[missing, built.clone()]).unwrap(), built);
+
+        let plugins = dir.path().join("zellij").join("plugins");
+        let installed = install(&built, &plugins, false).unwrap();
+        assert_eq!(installed, plugins.join(PLUGIN_FILE));
+        assert_eq!(std::fs::read(&installed).unwrap(), b"\0asm");
+
+        let err = install(&built, &plugins, false).unwrap_err();
+        assert!(err.to_string().contains("--force"));
+        assert!(install(&built, &plugins, true).is_ok());
+    }
+}
@@ -0,0 +1,290 @@
+//! toolbox-plugin-sdk: Guest SDK for toolbox WASM plugins
+//!
+//! A plugin is a `cdylib` built for `wasm32-unknown-unknown` and listed in the
+//! toolbox config as `[plugins.<name>] path = "..."`. It runs sandboxed: no
+//! WASI, bounded fuel and memory, and files can only be read through
+//! [`read_file`] from the detection directory or the plugin's `allow_read`
+//! directories.
+//!
+//! ```ignore
+//! use toolbox_plugin_sdk::{DetectInput, Tool};
+//!
+//! fn detect(input: DetectInput) -> Vec<Tool> {
+//!     match toolbox_plugin_sdk::read_file(".sdkmanrc") {
+//!         Some(rc) => vec![Tool::new("Java", rc.trim_start_matches("java=").trim())],
+//!         None => Vec::new(),
+//!     }
+//! }
+//!
+//! toolbox_plugin_sdk::detector!(detect);
+//! ```
+//!
+//! # Interface (ABI version 1)
+//!
+//! Strings cross the boundary as UTF-8 in linear memory; results are packed
+//! into a `u64` as `ptr << 32 | len`. The guest exports `memory`,
+//! `toolbox_alloc(len) -> ptr` and at least one of:
+//!
+//! - `toolbox_detect(ptr, len) -> u64`: [`DetectInput`] JSON in, a [`Tool`]
+//!   or an array of tools out (the format of `toolbox-detect-*` executables)
+//! - `toolbox_format(ptr, len) -> u64`: [`Info`] JSON in, text out
+//!
+//! The host provides `toolbox.read_file(ptr, len) -> u64` (`u64::MAX` when
+//! the file is missing or not allowed) and `toolbox.log(ptr, len)`. Each call
+//! gets a fresh instance, so allocations are never freed.
+
+use serde::{Deserialize, Serialize};
+
+/// Version of the guest interface
+pub const ABI_VERSION: u32 = 1;
+
+/// Input of a detector call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectInput {
+    /// Detection directory
+    pub dir: Option<String>,
+}
+
+/// A tool reported by a detector (also the tools of [`Info`])
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tool {
+    /// Tool name (default: the plugin name)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Detected version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Icon/emoji
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Short name for compact display
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_name: Option<String>,
+    /// Whether the tool is available (default: whether a version is given)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<bool>,
+    /// Why the tool is unavailable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Tool {
+    /// An available tool
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            version: Some(version.into()),
+            ..Self::default()
+        }
+    }
+
+    /// An unavailable tool
+    pub fn missing(name: impl Into<String>, error: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            available: Some(false),
+            error: Some(error.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Set the icon
+    pub fn with_icon(mut self, icon: impl Into<String>) -> Self {
+        self.icon = Some(icon.into());
+        self
+    }
+
+    /// Set the short name
+    pub fn with_short_name(mut self, short_name: impl Into<String>) -> Self {
+        self.short_name = Some(short_name.into());
+        self
+    }
+}
+
+/// Detection result given to a formatter (the `--format json` document)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Info {
+    /// Current directory
+    pub current_dir: Option<String>,
+    /// Tool versions
+    pub tools: Vec<Tool>,
+    /// Virtual environment name
+    pub virtual_env: Option<String>,
+    /// Git information, as in `--format json`
+    pub git: Option<serde_json::Value>,
+}
+
+/// Read a file from the detection directory (relative paths) or an
+/// `allow_read` directory of the plugin
+pub fn read_file(path: &str) -> Option<String> {
+    host::read_file(path)
+}
+
+/// Write a message to the toolbox log (`-v`)
+pub fn log(message: &str) {
+    host::log(message)
+}
+
+/// Run a detector on JSON input and return its JSON output
+pub fn run_detect(input: &[u8], detect: impl FnOnce(DetectInput) -> Vec<Tool>) -> Vec<u8> {
+    let input = serde_json::from_slice(input).unwrap_or_default();
+    serde_json::to_vec(&detect(input)).unwrap_or_default()
+}
+
+/// Run a formatter on JSON input and return its text output
+pub fn run_format(input: &[u8], format: impl FnOnce(Info) -> String) -> Vec<u8> {
+    let input = serde_json::from_slice(input).unwrap_or_default();
+    format(input).into_bytes()
+}
+
+/// Export `toolbox_detect` calling `fn(DetectInput) -> Vec<Tool>`
+#[macro_export]
+macro_rules! detector {
+    ($detect:path) => {
+        #[no_mangle]
+        pub extern "C" fn toolbox_detect(ptr: u32, len: u32) -> u64 {
+            $crate::guest::call(ptr, len, |input| $crate::run_detect(input, $detect))
+        }
+    };
+}
+
+/// Export `toolbox_format` calling `fn(Info) -> String`
+#[macro_export]
+macro_rules! formatter {
+    ($format:path) => {
+        #[no_mangle]
+        pub extern "C" fn toolbox_format(ptr: u32, len: u32) -> u64 {
+            $crate::guest::call(ptr, len, |input| $crate::run_format(input, $format))
+        }
+    };
+}
+
+/// Linear-memory glue used by the exported functions
+#[cfg(target_arch = "wasm32")]
+pub mod guest {
+    /// Allocate `len` bytes for the host (never freed: instances are short-lived)
+    #[no_mangle]
+    pub extern "C" fn toolbox_alloc(len: u32) -> u32 {
+        let mut buf = Vec::<u8>::with_capacity(len as usize);
+        let ptr = buf.as_mut_ptr();
+        std::mem::forget(buf);
+        ptr as u32
+    }
+
+    /// Pass guest bytes back to the host as `ptr << 32 | len`
+    pub fn pack(bytes: Vec<u8>) -> u64 {
+        let bytes = bytes.leak();
+        ((bytes.as_ptr() as u64) << 32) | bytes.len() as u64
+    }
+
+    /// Call `f` on the bytes the host wrote at `ptr`
+    pub fn call(ptr: u32, len: u32, f: impl FnOnce(&[u8]) -> Vec<u8>) -> u64 {
+        // SAFETY: the host wrote `len` bytes at `ptr`, allocated by toolbox_alloc
+        let input = unsafe { std::slice::from_raw_parts(ptr as *const u8, len as usize) };
+        pack(f(input))
+    }
+}
+
+/// Outside wasm32 there is no host: exports are never called
+#[cfg(not(target_arch = "wasm32"))]
+pub mod guest {
+    /// Call `f` on no input (native builds only type-check plugins)
+    pub fn call(_ptr: u32, _len: u32, f: impl FnOnce(&[u8]) -> Vec<u8>) -> u64 {
+        f(&[]).len() as u64
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod host {
+    #[link(wasm_import_module = "toolbox")]
+    extern "C" {
+        #[link_name = "read_file"]
+        fn host_read_file(ptr: u32, len: u32) -> u64;
+        #[link_name = "log"]
+        fn host_log(ptr: u32, len: u32);
+    }
+
+    pub fn read_file(path: &str) -> Option<String> {
+        // SAFETY: the host only reads `path` and returns memory from toolbox_alloc
+        let packed = unsafe { host_read_file(path.as_ptr() as u32, path.len() as u32) };
+        if packed == u64::MAX {
+            return None;
+        }
+        let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        // SAFETY: the host wrote `len` bytes at `ptr`
+        let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    pub fn log(message: &str) {
+        // SAFETY: the host only reads the message
+        unsafe { host_log(message.as_ptr() as u32, message.len() as u32) }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod host {
+    pub fn read_file(_path: &str) -> Option<String> {
+        None
+    }
+
+    pub fn log(_message: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_json() {
+        let tool = Tool::new("Java", "21.0.2").with_icon("☕");
+        assert_eq!(
+            serde_json::to_string(&tool).unwrap(),
+            r#"{"name":"Java","version":"21.0.2","icon":"☕"}"#
+        );
+        let missing = Tool::missing("vpn", "not connected");
+        assert_eq!(
+            serde_json::to_string(&missing).unwrap(),
+            r#"{"name":"vpn","available":false,"error":"not connected"}"#
+        );
+    }
+
+    #[test]
+    fn test_run_detect() {
+        let output = run_detect(br#"{"dir": "/home/user/app"}"#, |input| {
+            vec![Tool::new("dir", input.dir.unwrap_or_default())]
+        });
+        assert_eq!(output, br#"[{"name":"dir","version":"/home/user/app"}]"#);
+
+        // Unreadable input gives the detector an empty one
+        let output = run_detect(b"not json", |input| {
+            assert!(input.dir.is_none());
+            Vec::new()
+        });
+        assert_eq!(output, b"[]");
+    }
+
+    #[test]
+    fn test_run_format() {
+        let output = run_format(
+            br#"{"tools": [{"name": "Rust", "version": "1.85.0", "available": true}], "shell": "zsh"}"#,
+            |info| {
+                info.tools
+                    .iter()
+                    .map(|t| format!("{}={}", t.name.as_deref().unwrap_or("?"), t.version.as_deref().unwrap_or("?")))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            },
+        );
+        assert_eq!(output, b"Rust=1.85.0");
+    }
+
+    #[test]
+    fn test_no_host_outside_wasm() {
+        assert!(read_file("Cargo.toml").is_none());
+        log("ignored");
+    }
+}
@@ -0,0 +1,100 @@
+//! Highlighting of tools whose version changed between refreshes
+//!
+//! An upgrade that happens mid-session (e.g. `rustup update` in another pane)
+//! is shown in inverse video for a few refreshes. Versions are compared per
+//! directory: switching to another project is not a change.
+
+use std::collections::BTreeMap;
+use toolbox_core::info::ToolInfo;
+
+/// Refreshes a changed entry stays highlighted
+pub const HIGHLIGHT_REFRESHES: u32 = 3;
+
+/// Versions seen in the last refresh and the entries currently highlighted
+#[derive(Debug, Default, Clone)]
+pub struct VersionChanges {
+    /// Directory the versions were detected in
+    dir: Option<String>,
+    /// Tool name → version of the last refresh
+    versions: BTreeMap<String, String>,
+    /// Tool name → remaining highlighted refreshes
+    highlighted: BTreeMap<String, u32>,
+}
+
+impl VersionChanges {
+    /// Record a refresh result; returns whether any version changed
+    pub fn update(&mut self, dir: Option<&str>, tools: &[ToolInfo]) -> bool {
+        let versions: BTreeMap<String, String> = tools
+            .iter()
+            .filter_map(|tool| Some((tool.name.clone(), tool.version.clone()?)))
+            .collect();
+
+        if self.dir.as_deref() != dir {
+            // Another project: start over without highlighting
+            self.dir = dir.map(String::from);
+            self.versions = versions;
+            self.highlighted.clear();
+            return false;
+        }
+
+        self.highlighted.retain(|_, remaining| {
+            *remaining -= 1;
+            *remaining > 0
+        });
+        let mut changed = false;
+        for (name, version) in &versions {
+            if self
+                .versions
+                .get(name)
+                .is_some_and(|previous| previous != version)
+            {
+                self.highlighted.insert(name.clone(), HIGHLIGHT_REFRESHES);
+                changed = true;
+            }
+        }
+        self.versions = versions;
+        changed
+    }
+
+    /// Names of the highlighted tools
+    pub fn highlighted(&self) -> Vec<String> {
+        self.highlighted.keys().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools(node: &str) -> Vec<ToolInfo> {
+        vec![
+            ToolInfo::available("Node".to_string(), node.to_string()),
+            ToolInfo::available("Rust".to_string(), "1.85.0".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_change_is_highlighted_for_a_few_refreshes() {
+        let mut changes = VersionChanges::default();
+        assert!(!changes.update(Some("/app"), &tools("20.1.0")));
+        assert!(changes.highlighted().is_empty());
+
+        assert!(changes.update(Some("/app"), &tools("22.0.0")));
+        assert_eq!(changes.highlighted(), vec!["Node"]);
+
+        for _ in 1..HIGHLIGHT_REFRESHES {
+            assert!(!changes.update(Some("/app"), &tools("22.0.0")));
+            assert_eq!(changes.highlighted(), vec!["Node"]);
+        }
+        changes.update(Some("/app"), &tools("22.0.0"));
+        assert!(changes.highlighted().is_empty());
+    }
+
+    #[test]
+    fn test_directory_switch_is_not_a_change() {
+        let mut changes = VersionChanges::default();
+        changes.update(Some("/a"), &tools("20.1.0"));
+        assert!(!changes.update(Some("/b"), &tools("18.0.0")));
+        assert!(changes.highlighted().is_empty());
+    }
+}
@@ -0,0 +1,74 @@
+//! Copying the pane content to the system clipboard
+//!
+//! `y` copies the whole report and `Y` the line at the top of the view, as
+//! plain text (colors and highlighting stripped) for pasting into bug reports.
+
+/// Remove ANSI escape sequences (CSI sequences such as colors)
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            // Parameters and intermediates up to the final byte (@ to ~)
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Plain text of the given lines, without trailing padding
+pub fn plain_text(lines: &[String]) -> String {
+    lines
+        .iter()
+        .map(|line| strip_ansi(line).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Confirmation shown after copying
+pub fn confirmation(lines: usize) -> String {
+    let plural = if lines == 1 { "" } else { "s" };
+    format!(" ✓ copied {} line{} to the clipboard", lines, plural)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1b[7m 🦀 Rust 1.85.0\x1b[0m"),
+            " 🦀 Rust 1.85.0"
+        );
+        assert_eq!(
+            strip_ansi("\x1b[38;2;255;0;0mred\x1b[0m plain"),
+            "red plain"
+        );
+        assert_eq!(strip_ansi("no escapes"), "no escapes");
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let lines = vec![
+            " a 1    c 3  ".to_string(),
+            "\x1b[7m bb 2\x1b[0m   ".to_string(),
+        ];
+        assert_eq!(plain_text(&lines), " a 1    c 3\n bb 2");
+        assert_eq!(plain_text(&[]), "");
+    }
+
+    #[test]
+    fn test_confirmation() {
+        assert_eq!(confirmation(1), " ✓ copied 1 line to the clipboard");
+        assert_eq!(confirmation(12), " ✓ copied 12 lines to the clipboard");
+    }
+}
@@ -0,0 +1,110 @@
+//! Directory of the focused pane and per-directory result cache
+//!
+//! Zellij does not report a pane's working directory, but most shells put it
+//! in the terminal title (e.g. `user@host: ~/projects/app`). When the focused
+//! pane moves to another project, the last result for that directory is shown
+//! immediately while a refresh runs in the background.
+
+/// Results kept for this many directories
+pub const CACHE_CAPACITY: usize = 16;
+
+/// Working directory from a shell-set pane title (None if it holds no path)
+pub fn cwd_from_title(title: &str) -> Option<String> {
+    title
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .rev()
+        .find(|word| word.starts_with('/') || *word == "~" || word.starts_with("~/"))
+        .map(|word| word.trim_end_matches('/').to_string())
+        .map(|word| {
+            if word.is_empty() {
+                "/".to_string()
+            } else {
+                word
+            }
+        })
+}
+
+/// Most recently used results by directory, with a fixed capacity
+#[derive(Debug, Clone)]
+pub struct ResultCache<T> {
+    /// Oldest first
+    entries: Vec<(String, T)>,
+    capacity: usize,
+}
+
+impl<T> Default for ResultCache<T> {
+    fn default() -> Self {
+        Self::new(CACHE_CAPACITY)
+    }
+}
+
+impl<T> ResultCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        ResultCache {
+            entries: Vec::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Result cached for a directory
+    pub fn get(&self, dir: &str) -> Option<&T> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key == dir)
+            .map(|(_, value)| value)
+    }
+
+    /// Store the latest result for a directory, evicting the least recently stored
+    pub fn insert(&mut self, dir: String, value: T) {
+        self.entries.retain(|(key, _)| *key != dir);
+        if self.entries.len() >= self.capacity {
+            self.entries.remove(0);
+        }
+        self.entries.push((dir, value));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cwd_from_title() {
+        assert_eq!(
+            cwd_from_title("user@host: ~/projects/app").as_deref(),
+            Some("~/projects/app")
+        );
+        assert_eq!(
+            cwd_from_title("fish /tmp/work/").as_deref(),
+            Some("/tmp/work")
+        );
+        assert_eq!(cwd_from_title("~").as_deref(), Some("~"));
+        assert_eq!(cwd_from_title("/").as_deref(), Some("/"));
+        assert_eq!(cwd_from_title("vim main.rs"), None);
+        assert_eq!(cwd_from_title("Pane #1"), None);
+    }
+
+    #[test]
+    fn test_cache_lookup_and_eviction() {
+        let mut cache = ResultCache::new(2);
+        cache.insert("/a".to_string(), 1);
+        cache.insert("/b".to_string(), 2);
+        assert_eq!(cache.get("/a"), Some(&1));
+
+        // Re-inserting refreshes the entry, so /b is the oldest
+        cache.insert("/a".to_string(), 3);
+        cache.insert("/c".to_string(), 4);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("/a"), Some(&3));
+        assert_eq!(cache.get("/b"), None);
+        assert_eq!(cache.get("/c"), Some(&4));
+    }
+}
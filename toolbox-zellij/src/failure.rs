@@ -0,0 +1,134 @@
+//! Failed CLI runs: one-line summary, expandable details and retry backoff
+//!
+//! A failure keeps the last good content on screen with a one-line error
+//! above it; `e` expands the full stderr. Retries start quickly and back off
+//! while the CLI keeps failing.
+
+/// Delay before the first retry, in seconds
+pub const FIRST_RETRY: f64 = 2.0;
+
+/// Longest delay between retries, in seconds
+pub const MAX_RETRY: f64 = 60.0;
+
+/// Consecutive failures of the CLI
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// Full error output
+    details: Vec<String>,
+    /// Failed runs in a row
+    attempts: u32,
+}
+
+impl Failure {
+    /// First failure with the given error output
+    pub fn new(stderr: &str) -> Self {
+        Failure {
+            details: stderr.trim_end().lines().map(String::from).collect(),
+            attempts: 1,
+        }
+    }
+
+    /// Another failure in a row (the latest error output replaces the old one)
+    pub fn again(self, stderr: &str) -> Self {
+        Failure {
+            attempts: self.attempts.saturating_add(1),
+            ..Failure::new(stderr)
+        }
+    }
+
+    /// Failed runs in a row
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Seconds before the next retry: doubles with each failure, up to MAX_RETRY
+    pub fn retry_delay(&self) -> f64 {
+        let exponent = self.attempts.saturating_sub(1).min(16) as i32;
+        (FIRST_RETRY * 2f64.powi(exponent)).min(MAX_RETRY)
+    }
+
+    /// First non-empty line of the error output
+    pub fn summary(&self) -> &str {
+        self.details
+            .iter()
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty())
+            .unwrap_or("toolbox failed")
+    }
+
+    /// One-line error shown above the content
+    pub fn banner(&self) -> String {
+        let count = if self.attempts > 1 {
+            format!(" (×{})", self.attempts)
+        } else {
+            String::new()
+        };
+        format!(" ✗ {}{} · e: details", self.summary(), count)
+    }
+
+    /// Expanded view: a header and the full error output
+    pub fn detail_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!(
+                " toolbox failed{} · retry after {}s · e: close",
+                if self.attempts > 1 {
+                    format!(" {} times", self.attempts)
+                } else {
+                    String::new()
+                },
+                self.retry_delay()
+            ),
+            "---".to_string(),
+        ];
+        lines.extend(self.details.iter().map(|line| format!(" {}", line)));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let failure = Failure::new("boom");
+        assert_eq!(failure.retry_delay(), FIRST_RETRY);
+        let failure = failure.again("boom");
+        assert_eq!(failure.retry_delay(), FIRST_RETRY * 2.0);
+
+        let mut failure = failure;
+        for _ in 0..40 {
+            failure = failure.again("boom");
+        }
+        assert_eq!(failure.attempts(), 42);
+        assert_eq!(failure.retry_delay(), MAX_RETRY);
+    }
+
+    #[test]
+    fn test_summary_and_banner() {
+        let failure = Failure::new("\nError: Configuration error: bad key\n  at line 3\n");
+        assert_eq!(failure.summary(), "Error: Configuration error: bad key");
+        assert_eq!(
+            failure.banner(),
+            " ✗ Error: Configuration error: bad key · e: details"
+        );
+        assert_eq!(failure.again("x").banner(), " ✗ x (×2) · e: details");
+        assert_eq!(Failure::new("").summary(), "toolbox failed");
+    }
+
+    #[test]
+    fn test_detail_lines() {
+        let lines = Failure::new("first\nsecond")
+            .again("line 1\nline 2")
+            .detail_lines();
+        assert_eq!(
+            lines,
+            vec![
+                " toolbox failed 2 times · retry after 4s · e: close",
+                "---",
+                " line 1",
+                " line 2",
+            ]
+        );
+    }
+}
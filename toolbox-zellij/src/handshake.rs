@@ -0,0 +1,152 @@
+//! Startup check of the installed CLI against what the plugin expects
+//!
+//! The plugin probes `toolbox --capabilities` before its first run. A CLI
+//! that is older or newer than the plugin, or lacks flags the plugin passes,
+//! still runs, but with a warning explaining what to upgrade.
+//!
+//! The other way round, the plugin's version sits in a custom section of the
+//! module, where `toolbox zellij-update` reads it without loading the plugin.
+
+use toolbox_core::capabilities::{Capabilities, Compatibility, PROTOCOL_VERSION};
+
+/// Plugin version
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// [`VERSION`] in the `toolbox_version` custom section
+/// (`toolbox_core::zellij::VERSION_SECTION`)
+#[cfg(target_arch = "wasm32")]
+#[used]
+#[link_section = "toolbox_version"]
+static VERSION_SECTION: [u8; VERSION.len()] = version_bytes();
+
+#[cfg(target_arch = "wasm32")]
+const fn version_bytes() -> [u8; VERSION.len()] {
+    let mut bytes = [0; VERSION.len()];
+    let mut index = 0;
+    while index < bytes.len() {
+        bytes[index] = VERSION.as_bytes()[index];
+        index += 1;
+    }
+    bytes
+}
+
+/// Flags the plugin may pass to the CLI
+pub const REQUIRED_FLAGS: &[&str] = &[
+    "--format",
+    "--compact",
+    "--no-compact",
+    "--no-icons",
+    "--refresh",
+    "--powerline",
+    "--color",
+    "--single-line",
+    "--layout",
+    "--max-width",
+    "--theme",
+    "--theme-colors",
+    "--profile",
+    "--dir",
+    "--sections",
+    "--only",
+    "--system",
+];
+
+/// Outcome of the capabilities probe
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Handshake {
+    /// The CLI matches the plugin
+    Compatible,
+    /// The CLI runs but does not match the plugin
+    Warning(String),
+    /// No toolbox CLI answered
+    NotFound,
+}
+
+/// Evaluate the result of `toolbox --capabilities`
+pub fn evaluate(exit_code: Option<i32>, stdout: &str, stderr: &str) -> Handshake {
+    match exit_code {
+        Some(0) => match serde_json::from_str::<Capabilities>(stdout) {
+            Ok(capabilities) => check(&capabilities),
+            Err(_) => Handshake::NotFound,
+        },
+        // clap rejects the flag: a CLI from before the handshake
+        Some(_) if stderr.contains("--capabilities") => Handshake::Warning(format!(
+            "toolbox CLI is older than this plugin ({}); upgrade the CLI",
+            VERSION
+        )),
+        _ => Handshake::NotFound,
+    }
+}
+
+fn check(capabilities: &Capabilities) -> Handshake {
+    let cli = &capabilities.version;
+    let plugin = VERSION;
+    match capabilities.check(PROTOCOL_VERSION, REQUIRED_FLAGS) {
+        Compatibility::Compatible => Handshake::Compatible,
+        Compatibility::CliOlder => Handshake::Warning(format!(
+            "toolbox CLI {} is older than this plugin ({}); upgrade the CLI",
+            cli, plugin
+        )),
+        Compatibility::CliNewer => Handshake::Warning(format!(
+            "toolbox CLI {} is newer than this plugin ({}); update the plugin",
+            cli, plugin
+        )),
+        Compatibility::MissingFlags(flags) => Handshake::Warning(format!(
+            "toolbox CLI {} does not support {}; upgrade the CLI",
+            cli,
+            flags.join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(protocol: u32, flags: &[&str]) -> String {
+        let mut capabilities =
+            Capabilities::new(Vec::new(), flags.iter().map(|f| f.to_string()).collect());
+        capabilities.protocol = protocol;
+        serde_json::to_string(&capabilities).unwrap()
+    }
+
+    #[test]
+    fn test_compatible() {
+        let stdout = capabilities(PROTOCOL_VERSION, REQUIRED_FLAGS);
+        assert_eq!(evaluate(Some(0), &stdout, ""), Handshake::Compatible);
+    }
+
+    #[test]
+    fn test_mismatches_warn() {
+        let older = capabilities(PROTOCOL_VERSION - 1, REQUIRED_FLAGS);
+        assert!(matches!(
+            evaluate(Some(0), &older, ""),
+            Handshake::Warning(message) if message.contains("older") && message.ends_with("upgrade the CLI")
+        ));
+
+        let newer = capabilities(PROTOCOL_VERSION + 1, REQUIRED_FLAGS);
+        assert!(matches!(
+            evaluate(Some(0), &newer, ""),
+            Handshake::Warning(message) if message.ends_with("update the plugin")
+        ));
+
+        let missing = capabilities(PROTOCOL_VERSION, &["--format"]);
+        assert!(matches!(
+            evaluate(Some(0), &missing, ""),
+            Handshake::Warning(message) if message.contains("does not support --compact, --no-compact")
+        ));
+
+        let stderr = "error: unexpected argument '--capabilities' found";
+        assert!(matches!(
+            evaluate(Some(2), "", stderr),
+            Handshake::Warning(message) if message.starts_with("toolbox CLI is older")
+        ));
+    }
+
+    #[test]
+    fn test_not_found() {
+        assert_eq!(evaluate(None, "", "No such file"), Handshake::NotFound);
+        assert_eq!(evaluate(Some(0), "other 1.0", ""), Handshake::NotFound);
+        assert_eq!(evaluate(Some(1), "", "boom"), Handshake::NotFound);
+    }
+}
@@ -0,0 +1,151 @@
+//! How the plugin invokes the toolbox CLI
+//!
+//! `cli_path` points at a toolbox binary outside PATH and `extra_args` adds
+//! flags such as `--config` to every run. Before the first run the binary is
+//! probed with `--capabilities` (see `handshake`), so a missing CLI is
+//! reported clearly instead of as a raw spawn error.
+
+use std::collections::BTreeMap;
+
+/// Binary used when `cli_path` is not set
+pub const DEFAULT_PROGRAM: &str = "toolbox";
+
+/// The toolbox command line from the plugin configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// Binary name or path
+    pub program: String,
+    /// Flags appended to every run
+    pub extra_args: Vec<String>,
+}
+
+impl Default for Invocation {
+    fn default() -> Self {
+        Invocation {
+            program: DEFAULT_PROGRAM.to_string(),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+impl Invocation {
+    /// Read `cli_path` and `extra_args` from the plugin configuration
+    pub fn from_config(configuration: &BTreeMap<String, String>) -> Self {
+        Invocation {
+            program: configuration
+                .get("cli_path")
+                .map(|path| path.trim())
+                .filter(|path| !path.is_empty())
+                .unwrap_or(DEFAULT_PROGRAM)
+                .to_string(),
+            extra_args: configuration
+                .get("extra_args")
+                .map(|args| split_args(args))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Command that checks the binary can be run and what it supports
+    pub fn probe(&self) -> Vec<String> {
+        vec![self.program.clone(), "--capabilities".to_string()]
+    }
+
+    /// Error output reported when the probe fails
+    pub fn not_found(&self, stderr: &str) -> String {
+        let mut message = format!(
+            "Cannot run the toolbox CLI `{}`: install it or set cli_path in the plugin configuration",
+            self.program
+        );
+        if !stderr.trim().is_empty() {
+            message.push('\n');
+            message.push_str(stderr.trim_end());
+        }
+        message
+    }
+}
+
+/// Split arguments on whitespace, keeping single- or double-quoted parts together
+pub fn split_args(input: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+
+    for c in input.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_config() {
+        assert_eq!(Invocation::from_config(&config(&[])), Invocation::default());
+
+        let invocation = Invocation::from_config(&config(&[
+            ("cli_path", "/opt/toolbox/bin/toolbox"),
+            ("extra_args", "--config ~/work.toml --only rust,node"),
+        ]));
+        assert_eq!(invocation.program, "/opt/toolbox/bin/toolbox");
+        assert_eq!(
+            invocation.extra_args,
+            vec!["--config", "~/work.toml", "--only", "rust,node"]
+        );
+        assert_eq!(
+            invocation.probe(),
+            vec!["/opt/toolbox/bin/toolbox", "--capabilities"]
+        );
+    }
+
+    #[test]
+    fn test_split_args_quotes() {
+        assert_eq!(
+            split_args(r#"--config "/my configs/a.toml" --theme-colors 'tool_bg=red' """#),
+            vec![
+                "--config",
+                "/my configs/a.toml",
+                "--theme-colors",
+                "tool_bg=red",
+                ""
+            ]
+        );
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn test_not_found() {
+        let message = Invocation::default().not_found("No such file or directory\n");
+        assert!(message.starts_with("Cannot run the toolbox CLI `toolbox`"));
+        assert!(message.ends_with("\nNo such file or directory"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Keybindings of the plugin pane
+//!
+//! Keys only reach the plugin while its pane is focused. Toggles change the
+//! arguments of the next CLI run; they are not written back to any config.
+
+/// What a key press does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    /// Re-run detection now, bypassing the version cache
+    Refresh,
+    /// Switch between compact and full version strings
+    ToggleCompact,
+    /// Show or hide icons
+    ToggleIcons,
+    /// Switch to the next theme
+    CycleTheme,
+    /// Switch to the next config profile (then back to none)
+    CycleProfile,
+    /// Show or hide the help overlay
+    ToggleHelp,
+    /// Expand or collapse the error details of a failed run
+    ToggleDetails,
+    /// Copy the whole content to the clipboard
+    CopyAll,
+    /// Copy the line at the top of the view to the clipboard
+    CopyLine,
+    /// Scroll by lines (negative is up)
+    Scroll(isize),
+    /// Scroll by pages (negative is up)
+    ScrollPage(isize),
+    /// Jump to the first line
+    ScrollTop,
+    /// Jump to the last line
+    ScrollBottom,
+}
+
+/// Map an unmodified character key to its action
+pub fn action_for_char(c: char) -> Option<KeyAction> {
+    match c {
+        'r' => Some(KeyAction::Refresh),
+        'c' => Some(KeyAction::ToggleCompact),
+        'i' => Some(KeyAction::ToggleIcons),
+        't' => Some(KeyAction::CycleTheme),
+        'p' => Some(KeyAction::CycleProfile),
+        '?' => Some(KeyAction::ToggleHelp),
+        'e' => Some(KeyAction::ToggleDetails),
+        'y' => Some(KeyAction::CopyAll),
+        'Y' => Some(KeyAction::CopyLine),
+        'j' => Some(KeyAction::Scroll(1)),
+        'k' => Some(KeyAction::Scroll(-1)),
+        'g' => Some(KeyAction::ScrollTop),
+        'G' => Some(KeyAction::ScrollBottom),
+        _ => None,
+    }
+}
+
+/// Themes cycled with `t`: the CLI's built-in presets, then the Zellij palette
+pub const THEMES: &[&str] = &[
+    "default",
+    "dark",
+    "light",
+    "solarized",
+    "nord",
+    "dracula",
+    "gruvbox",
+    "catppuccin",
+    "zellij",
+];
+
+/// Theme after `current` (the first one if unset or not in the cycle)
+pub fn next_theme(current: Option<&str>) -> &'static str {
+    let index = current
+        .and_then(|name| THEMES.iter().position(|t| *t == name))
+        .map_or(0, |i| (i + 1) % THEMES.len());
+    THEMES[index]
+}
+
+/// Profile after `current`: each of `profiles` in turn, then none
+pub fn next_profile(current: Option<&str>, profiles: &[String]) -> Option<String> {
+    let index = match current {
+        Some(name) => profiles.iter().position(|p| p == name).map_or(0, |i| i + 1),
+        None => 0,
+    };
+    profiles.get(index).cloned()
+}
+
+/// Help overlay contents
+pub const HELP: &[&str] = &[
+    " Toolbox keys",
+    "---",
+    " r  refresh now",
+    " c  toggle compact mode",
+    " i  toggle icons",
+    " t  next theme",
+    " p  next profile",
+    " e  show / hide error details",
+    " y/Y  copy all / top line to the clipboard",
+    " j/k, ↓/↑  scroll (also mouse wheel)",
+    " PgDn/PgUp  scroll a page",
+    " g/G  top / bottom",
+    " ?  close this help (also Esc)",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_for_char() {
+        assert_eq!(action_for_char('r'), Some(KeyAction::Refresh));
+        assert_eq!(action_for_char('c'), Some(KeyAction::ToggleCompact));
+        assert_eq!(action_for_char('i'), Some(KeyAction::ToggleIcons));
+        assert_eq!(action_for_char('t'), Some(KeyAction::CycleTheme));
+        assert_eq!(action_for_char('p'), Some(KeyAction::CycleProfile));
+        assert_eq!(action_for_char('?'), Some(KeyAction::ToggleHelp));
+        assert_eq!(action_for_char('e'), Some(KeyAction::ToggleDetails));
+        assert_eq!(action_for_char('y'), Some(KeyAction::CopyAll));
+        assert_eq!(action_for_char('Y'), Some(KeyAction::CopyLine));
+        assert_eq!(action_for_char('j'), Some(KeyAction::Scroll(1)));
+        assert_eq!(action_for_char('G'), Some(KeyAction::ScrollBottom));
+        assert_eq!(action_for_char('R'), None);
+        assert_eq!(action_for_char('x'), None);
+    }
+
+    #[test]
+    fn test_next_theme_cycles() {
+        assert_eq!(next_theme(None), "default");
+        assert_eq!(next_theme(Some("default")), "dark");
+        assert_eq!(next_theme(Some("catppuccin")), "zellij");
+        assert_eq!(next_theme(Some("zellij")), "default");
+        // User-defined themes are not in the cycle
+        assert_eq!(next_theme(Some("work")), "default");
+    }
+
+    #[test]
+    fn test_next_profile_cycles() {
+        let profiles = vec!["infra".to_string(), "web".to_string()];
+        assert_eq!(next_profile(None, &profiles).as_deref(), Some("infra"));
+        assert_eq!(
+            next_profile(Some("infra"), &profiles).as_deref(),
+            Some("web")
+        );
+        assert_eq!(next_profile(Some("web"), &profiles), None);
+        // A profile that went away restarts the cycle
+        assert_eq!(
+            next_profile(Some("old"), &profiles).as_deref(),
+            Some("infra")
+        );
+        assert_eq!(next_profile(None, &[]), None);
+    }
+
+    #[test]
+    fn test_help_lists_every_key() {
+        for key in ['r', 'c', 'i', 't', 'p', 'e', 'y', '?'] {
+            assert!(action_for_char(key).is_some());
+            assert!(
+                HELP.iter().any(|line| line.trim_start().starts_with(key)),
+                "{} missing from help",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Adaptive layout of the multi-line pane by its width
+//!
+//! The plugin asks the CLI for structured output (`--format json`) and lays
+//! it out for the current pane width: narrow panes get icon-only lines, medium
+//! ones compact names, wide ones the full text. Content taller than a wide
+//! pane (e.g. a bottom strip) is arranged in columns. Resizing only re-runs
+//! the layout, not the CLI. Tools whose version just changed are shown in
+//! inverse video; versions are colored by their drift from the snapshot
+//! lockfile when the CLI found one.
+
+use toolbox_core::color::display_width;
+use toolbox_core::config::Section;
+use toolbox_core::format;
+use toolbox_core::info::ToolboxInfo;
+
+/// Panes narrower than this show icons and short versions only
+pub const ICONS_BELOW: usize = 20;
+
+/// Panes narrower than this use compact names and paths
+pub const COMPACT_BELOW: usize = 40;
+
+/// Spaces between columns
+const COLUMN_GAP: usize = 2;
+
+/// Inverse video for highlighted entries
+const HIGHLIGHT: &str = "\x1b[7m";
+const RESET: &str = "\x1b[0m";
+
+/// How much detail fits in the pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Density {
+    /// Icon (or short name) and major.minor version per tool
+    Icons,
+    /// Short names and shortened paths
+    Compact,
+    /// Everything, as configured
+    Full,
+}
+
+impl Density {
+    /// Density for a pane width
+    pub fn for_width(cols: usize) -> Self {
+        if cols < ICONS_BELOW {
+            Density::Icons
+        } else if cols < COMPACT_BELOW {
+            Density::Compact
+        } else {
+            Density::Full
+        }
+    }
+}
+
+/// Display settings applied on top of the density
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutOptions<'a> {
+    /// Compact mode (toggled with `c`), used when the pane is wide
+    pub compact: bool,
+    /// Show icons (toggled with `i`)
+    pub show_icons: bool,
+    /// Sections to show, in order
+    pub sections: &'a [Section],
+    /// Tools to highlight (see `changes`)
+    pub highlighted: &'a [String],
+}
+
+/// Content lines for a pane size
+pub fn lines(info: &ToolboxInfo, cols: usize, rows: usize, options: &LayoutOptions) -> Vec<String> {
+    let density = Density::for_width(cols);
+    let text = match density {
+        Density::Icons => icon_lines(info, options).join("\n"),
+        Density::Compact => format::text(
+            info,
+            true,
+            options.show_icons,
+            options.sections,
+            false,
+            true,
+        ),
+        Density::Full => format::text(
+            info,
+            options.compact,
+            options.show_icons,
+            options.sections,
+            false,
+            true,
+        ),
+    };
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    if lines.is_empty() {
+        return vec![" No tools detected".to_string()];
+    }
+    let compact = density == Density::Compact || options.compact;
+    for tool in info
+        .tools
+        .iter()
+        .filter(|t| t.available && options.highlighted.contains(&t.name))
+    {
+        let version = tool.version.as_deref().unwrap_or("?");
+        let entry = match density {
+            Density::Icons => format!(
+                " {}",
+                format::drift_colored(tool, major_minor(version), true)
+            ),
+            _ => {
+                let name = match tool.short_name {
+                    Some(ref short) if compact => short,
+                    _ => &tool.name,
+                };
+                let version = tool.version_label().unwrap_or("?");
+                format!(" {} {}", name, format::drift_colored(tool, version, true))
+            }
+        };
+        if let Some(line) = lines.iter_mut().find(|line| line.ends_with(&entry)) {
+            // Keep the inverse video past the drift color's reset
+            let inner = line.replace(RESET, &format!("{}{}", RESET, HIGHLIGHT));
+            *line = format!("{}{}{}", HIGHLIGHT, inner, RESET);
+        }
+    }
+    columns(lines, cols, rows)
+}
+
+fn is_separator(line: &str) -> bool {
+    line.starts_with('─') || line == "---"
+}
+
+/// Arrange lines top-to-bottom in aligned columns when they overflow the rows
+/// and at least two columns fit (separators are dropped); otherwise unchanged
+pub fn columns(lines: Vec<String>, cols: usize, rows: usize) -> Vec<String> {
+    if rows == 0 || lines.len() <= rows {
+        return lines;
+    }
+    let items: Vec<&str> = lines
+        .iter()
+        .map(String::as_str)
+        .filter(|line| !is_separator(line))
+        .collect();
+    let width = items
+        .iter()
+        .map(|line| display_width(line))
+        .max()
+        .unwrap_or(0)
+        + COLUMN_GAP;
+    let fitting = (cols + COLUMN_GAP) / width;
+    if fitting < 2 || items.is_empty() {
+        return lines;
+    }
+    // As few columns as needed to fit the rows (still scrolls if too many)
+    let count = ((items.len() - 1) / rows + 1).min(fitting);
+    let height = (items.len() - 1) / count + 1;
+
+    (0..height)
+        .map(|row| {
+            let cells: Vec<&str> = items.iter().skip(row).step_by(height).copied().collect();
+            let last = cells.len() - 1;
+            cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    let pad = if i == last {
+                        0
+                    } else {
+                        width - display_width(cell)
+                    };
+                    format!("{}{}", cell, " ".repeat(pad))
+                })
+                .collect::<String>()
+        })
+        .collect()
+}
+
+/// Ultra-compact lines: git branch and one short entry per available tool
+fn icon_lines(info: &ToolboxInfo, options: &LayoutOptions) -> Vec<String> {
+    let mut lines = Vec::new();
+    for section in options.sections {
+        match section {
+            Section::Git => {
+                if let Some(ref git) = info.git {
+                    let marker = if git.is_dirty { "*" } else { "" };
+                    if options.show_icons {
+                        lines.push(format!(" 🌿 {}{}", git.branch, marker));
+                    } else {
+                        lines.push(format!(" {}{}", git.branch, marker));
+                    }
+                }
+            }
+            Section::Tools => {
+                for tool in info.tools.iter().filter(|t| t.available) {
+                    let label = match tool.icon {
+                        Some(ref icon) if options.show_icons => icon.as_str(),
+                        _ => tool.short_name.as_deref().unwrap_or(&tool.name),
+                    };
+                    let version = tool.version.as_deref().map_or("?", major_minor);
+                    lines.push(format!(
+                        " {} {}",
+                        label,
+                        format::drift_colored(tool, version, true)
+                    ));
+                }
+            }
+            // Paths, environments and usage figures do not fit
+            Section::Dir | Section::Venv | Section::System => {}
+        }
+    }
+    lines
+}
+
+/// "1.85.0" → "1.85" (other formats are kept)
+fn major_minor(version: &str) -> &str {
+    match version.match_indices('.').nth(1) {
+        Some((index, _)) => &version[..index],
+        None => version,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toolbox_core::config::DEFAULT_SECTIONS;
+    use toolbox_core::snapshot::Drift;
+
+    fn info() -> ToolboxInfo {
+        serde_json::from_str(
+            r#"{
+                "current_dir": "/home/user/projects/toolbox",
+                "git": {"branch": "main", "is_dirty": true},
+                "tools": [
+                    {"name": "Rust", "short_name": "rust", "version": "1.85.0", "icon": "🦀", "available": true},
+                    {"name": "Go", "short_name": "go", "icon": "🔷", "available": false}
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn options(show_icons: bool) -> LayoutOptions<'static> {
+        LayoutOptions {
+            compact: false,
+            show_icons,
+            sections: DEFAULT_SECTIONS,
+            highlighted: &[],
+        }
+    }
+
+    #[test]
+    fn test_density_breakpoints() {
+        assert_eq!(Density::for_width(12), Density::Icons);
+        assert_eq!(Density::for_width(ICONS_BELOW), Density::Compact);
+        assert_eq!(Density::for_width(39), Density::Compact);
+        assert_eq!(Density::for_width(COMPACT_BELOW), Density::Full);
+    }
+
+    #[test]
+    fn test_icon_lines() {
+        assert_eq!(
+            lines(&info(), 10, 24, &options(true)),
+            vec![" 🌿 main*", " 🦀 1.85"]
+        );
+        assert_eq!(
+            lines(&info(), 10, 24, &options(false)),
+            vec![" main*", " rust 1.85"]
+        );
+    }
+
+    #[test]
+    fn test_compact_and_full_lines() {
+        let compact = lines(&info(), 30, 24, &options(false));
+        assert!(compact.contains(&" rust 1.85.0".to_string()));
+
+        let full = lines(&info(), 80, 24, &options(false));
+        assert!(full.contains(&" /home/user/projects/toolbox".to_string()));
+        assert!(full.contains(&" Rust 1.85.0".to_string()));
+        assert!(!full.iter().any(|line| line.contains("Go")));
+    }
+
+    #[test]
+    fn test_highlighted_tools() {
+        let highlighted = vec!["Rust".to_string()];
+        let options = LayoutOptions {
+            highlighted: &highlighted,
+            ..options(true)
+        };
+        let full = lines(&info(), 80, 24, &options);
+        assert!(full.contains(&"\x1b[7m 🦀 Rust 1.85.0\x1b[0m".to_string()));
+        assert_eq!(
+            full.iter().filter(|line| line.contains("\x1b[7m")).count(),
+            1
+        );
+
+        assert_eq!(
+            lines(&info(), 10, 24, &options),
+            vec![" 🌿 main*", "\x1b[7m 🦀 1.85\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn test_drift_colors() {
+        let mut info = info();
+        info.tools[0].drift = Some(Drift::Changed);
+        let full = lines(&info, 80, 24, &options(true));
+        assert!(full.contains(&" 🦀 Rust \x1b[33m1.85.0\x1b[0m".to_string()));
+        assert_eq!(
+            lines(&info, 10, 24, &options(true)),
+            vec![" 🌿 main*", " 🦀 \x1b[33m1.85\x1b[0m"]
+        );
+
+        let highlighted = vec!["Rust".to_string()];
+        let options = LayoutOptions {
+            highlighted: &highlighted,
+            ..options(true)
+        };
+        let full = lines(&info, 80, 24, &options);
+        assert!(full.contains(&"\x1b[7m 🦀 Rust \x1b[33m1.85.0\x1b[0m\x1b[7m\x1b[0m".to_string()));
+    }
+
+    #[test]
+    fn test_columns_for_wide_short_panes() {
+        let lines: Vec<String> = [" a 1", "───", " bb 2", " c 3", " d 4", " e 5"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        // Fits: unchanged, separators kept
+        assert_eq!(columns(lines.clone(), 80, 6), lines);
+
+        // 5 items in 2 rows: 3 columns of width 5 + 2
+        assert_eq!(
+            columns(lines.clone(), 80, 2),
+            vec![" a 1    c 3    e 5", " bb 2   d 4"]
+        );
+
+        // Too narrow for two columns: unchanged, left to scrolling
+        assert_eq!(columns(lines.clone(), 10, 2), lines);
+    }
+
+    #[test]
+    fn test_major_minor() {
+        assert_eq!(major_minor("1.85.0"), "1.85");
+        assert_eq!(major_minor("22.1"), "22.1");
+        assert_eq!(major_minor("nightly"), "nightly");
+    }
+}
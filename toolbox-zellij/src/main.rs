@@ -7,8 +7,54 @@
 // This crate is a Zellij WASM plugin. For native targets we build a tiny stub
 // binary so `cargo build` for the workspace succeeds.
 
+// Target-independent helpers (unit-tested natively)
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod changes;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod clipboard;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod context;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod failure;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod handshake;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod invocation;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod keys;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod layout;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod palette;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod permissions;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod scroll;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod sections;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod session;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod state;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod status;
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+mod worker;
+
+#[cfg(target_arch = "wasm32")]
+use std::borrow::Cow;
+
+#[cfg(target_arch = "wasm32")]
+use std::collections::{BTreeMap, VecDeque};
+
+#[cfg(target_arch = "wasm32")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_arch = "wasm32")]
+use toolbox_core::config::{Section, DEFAULT_SECTIONS};
+
 #[cfg(target_arch = "wasm32")]
-use std::collections::BTreeMap;
+use toolbox_core::info::ToolboxInfo;
 
 #[cfg(target_arch = "wasm32")]
 use unicode_width::UnicodeWidthChar;
@@ -21,25 +67,111 @@ use zellij_tile::prelude::*;
 struct ToolboxPlugin {
     /// Display content
     content: Vec<String>,
+    /// Structured CLI result, laid out for the pane width (multi-line mode)
+    info: Option<ToolboxInfo>,
+    /// Sections to lay out, in order (from the `show_*` keys)
+    sections: Vec<Section>,
+    /// Tools whose version changed in a recent refresh
+    changes: changes::VersionChanges,
     /// Plugin width
     cols: usize,
     /// Plugin height
     rows: usize,
     /// Refresh interval in seconds
     refresh_interval: f64,
-    /// Working directory for tool detection
+    /// Working directory for tool detection (fixed; disables following the focused pane)
     working_dir: Option<String>,
+    /// Working directory of the focused pane, from its title
+    pane_dir: Option<String>,
+    /// Position of the active tab
+    active_tab: Option<usize>,
+    /// Last result per directory, shown instantly when switching back
+    results: context::ResultCache<CachedResult>,
+    /// Directory of each result handed to the worker, oldest first
+    pending_dirs: VecDeque<Option<String>>,
+    /// Consecutive CLI failures (the last good content stays shown)
+    failure: Option<failure::Failure>,
+    /// Error details are expanded (toggled with `e`)
+    show_details: bool,
+    /// Confirmation of the last copy, until the next key press or result
+    notice: Option<String>,
+    /// CLI binary and extra flags (`cli_path`, `extra_args`)
+    invocation: invocation::Invocation,
+    /// The CLI answered the `--capabilities` probe
+    cli_found: bool,
+    /// Mismatch between the CLI and the plugin found by the probe
+    cli_warning: Option<String>,
     /// Single line display mode
     single_line: bool,
     /// Powerline style output
     powerline: bool,
-    /// Theme preset name (default, dark, light, solarized)
+    /// Theme preset name (default, dark, light, solarized, nord, dracula, gruvbox, catppuccin),
+    /// or "zellij" to follow the active Zellij theme
     theme: Option<String>,
+    /// Config profile passed as `--profile` (cycled with `p`)
+    profile: Option<String>,
+    /// Profiles cycled with `p` (default: those the CLI reports)
+    profiles: Vec<String>,
+    /// `--theme-colors` spec derived from the Zellij palette (theme "zellij")
+    theme_spec: Option<String>,
+    /// Single-line powerline layout (left, right, split)
+    layout: Option<String>,
+    /// Compact version strings (toggled with `c`)
+    compact: bool,
+    /// Show icons (toggled with `i`)
+    show_icons: bool,
+    /// Help overlay is shown (toggled with `?`)
+    show_help: bool,
+    /// Scroll position of multi-line content
+    scroll: scroll::Scroll,
+    /// Refresh timing (spinner and data age)
+    status: status::RefreshStatus,
+    /// Show the spinner / "updated Xs ago" status
+    show_status: bool,
+    /// Answer to the permission request
+    permission: permissions::Permission,
+    /// CLI flags from the `show_*` and `tools` configuration keys
+    section_args: Vec<String>,
+    /// Which of session, mode and tab to show
+    session_display: session::SessionDisplay,
+    /// Current session name, input mode and tab
+    session: session::SessionState,
+    /// Saved state was looked up for the session
+    state_loaded: bool,
+    /// Last state written, to skip identical writes
+    saved_state: String,
+}
+
+/// A CLI result for one directory
+#[cfg(target_arch = "wasm32")]
+#[derive(Clone)]
+struct CachedResult {
+    /// Display lines (text output or errors)
+    content: Vec<String>,
+    /// Structured result, laid out at render time
+    info: Option<ToolboxInfo>,
 }
 
 #[cfg(target_arch = "wasm32")]
 register_plugin!(ToolboxPlugin);
 
+/// Worker that parses CLI results off the render path
+#[cfg(target_arch = "wasm32")]
+#[derive(Default, Serialize, Deserialize)]
+struct DetectionWorker;
+
+#[cfg(target_arch = "wasm32")]
+impl<'de> ZellijWorker<'de> for DetectionWorker {
+    fn on_message(&mut self, message: String, payload: String) {
+        if let Some((reply, payload)) = worker::handle(&message, &payload) {
+            post_message_to_plugin(PluginMessage::new_to_plugin(reply, &payload));
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+register_worker!(DetectionWorker, detection_worker, DETECTION_WORKER);
+
 #[cfg(target_arch = "wasm32")]
 impl ZellijPlugin for ToolboxPlugin {
     fn load(&mut self, configuration: BTreeMap<String, String>) {
@@ -47,15 +179,21 @@ impl ZellijPlugin for ToolboxPlugin {
         request_permission(&[
             PermissionType::RunCommands,
             PermissionType::ReadApplicationState,
+            PermissionType::WriteToClipboard,
         ]);
 
         // Subscribe to events
         subscribe(&[
+            EventType::PermissionRequestResult,
             EventType::ModeUpdate,
             EventType::TabUpdate,
+            EventType::SessionUpdate,
             EventType::PaneUpdate,
             EventType::RunCommandResult,
+            EventType::CustomMessage,
             EventType::Timer,
+            EventType::Key,
+            EventType::Mouse,
         ]);
 
         // Read refresh interval from configuration (default: 5 seconds)
@@ -82,6 +220,51 @@ impl ZellijPlugin for ToolboxPlugin {
         // Read theme preset from configuration
         self.theme = configuration.get("theme").cloned();
 
+        // Read config profile (and the ones to cycle through) from configuration
+        self.profile = configuration
+            .get("profile")
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        self.profiles = configuration
+            .get("profiles")
+            .map(|s| {
+                s.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|p| !p.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Read powerline layout from configuration
+        self.layout = configuration.get("layout").cloned();
+
+        // Read compact mode and icons from configuration (default: true)
+        self.compact = configuration
+            .get("compact")
+            .map(|s| s == "true" || s == "1")
+            .unwrap_or(true);
+        self.show_icons = configuration
+            .get("show_icons")
+            .map(|s| s == "true" || s == "1")
+            .unwrap_or(true);
+
+        // Read status line visibility from configuration (default: true)
+        self.show_status = configuration
+            .get("show_status")
+            .map(|s| s == "true" || s == "1")
+            .unwrap_or(true);
+
+        // Read sections and tools to show from configuration (show_git, tools, ...)
+        self.section_args = sections::section_args(&configuration);
+        self.sections =
+            sections::sections(&configuration).unwrap_or_else(|| DEFAULT_SECTIONS.to_vec());
+
+        // Read CLI binary and extra flags from configuration (default: toolbox in PATH)
+        self.invocation = invocation::Invocation::from_config(&configuration);
+
+        // Read session / mode / tab display from configuration (default: false)
+        self.session_display = session::SessionDisplay::from_config(&configuration);
+
         // Initial content (use marker for dynamic separator)
         self.content = vec![
             "---".to_string(),
@@ -95,51 +278,302 @@ impl ZellijPlugin for ToolboxPlugin {
 
     fn update(&mut self, event: Event) -> bool {
         match event {
-            Event::RunCommandResult(exit_code, stdout, stderr, _context) => {
-                if exit_code == Some(0) {
-                    self.parse_output(&stdout);
-                } else {
-                    self.content = vec![
-                        "---".to_string(),
-                        " Error".to_string(),
-                        format!(" {}", String::from_utf8_lossy(&stderr)),
-                        "---".to_string(),
-                    ];
+            Event::PermissionRequestResult(status) => {
+                self.permission = match status {
+                    PermissionStatus::Granted => permissions::Permission::Granted,
+                    PermissionStatus::Denied => permissions::Permission::Denied,
+                };
+                if self.permission == permissions::Permission::Granted {
+                    self.request_tool_versions(false);
                 }
                 true
             }
-            Event::Timer(_elapsed) => {
-                // Periodic refresh
-                self.request_tool_versions();
-                // Schedule next refresh
-                set_timeout(self.refresh_interval);
+            Event::RunCommandResult(exit_code, stdout, stderr, context)
+                if context.contains_key("probe") =>
+            {
+                self.status.finished();
+                let stderr = String::from_utf8_lossy(&stderr);
+                match handshake::evaluate(exit_code, &String::from_utf8_lossy(&stdout), &stderr) {
+                    handshake::Handshake::NotFound => {
+                        // Reported like a failed run, so the probe is retried with backoff
+                        let message = self.invocation.not_found(&stderr);
+                        self.failure = Some(match self.failure.take() {
+                            Some(failure) => failure.again(&message),
+                            None => failure::Failure::new(&message),
+                        });
+                    }
+                    handshake::Handshake::Compatible => {
+                        self.cli_found = true;
+                        self.cli_warning = None;
+                        self.failure = None;
+                        self.request_tool_versions(false);
+                    }
+                    handshake::Handshake::Warning(warning) => {
+                        self.cli_found = true;
+                        self.cli_warning = Some(warning);
+                        self.failure = None;
+                        self.request_tool_versions(false);
+                    }
+                }
+                true
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context) => {
+                // Parsing happens in the worker; the result comes back as a message
+                let (message, payload) = if exit_code == Some(0) {
+                    if self.powerline {
+                        (worker::OUTPUT, stdout)
+                    } else {
+                        (worker::JSON, stdout)
+                    }
+                } else {
+                    (worker::ERROR, stderr)
+                };
+                post_message_to(PluginMessage::new_to_worker(
+                    worker::NAME,
+                    message,
+                    &String::from_utf8_lossy(&payload),
+                ));
+                self.pending_dirs.push_back(context.get("dir").cloned());
                 false
             }
+            Event::CustomMessage(message, payload) => {
+                if ![worker::CONTENT, worker::INFO, worker::FAILED].contains(&message.as_str()) {
+                    return false;
+                }
+                // The worker answers in order, so this is the oldest pending directory
+                let dir = self.pending_dirs.pop_front().flatten();
+                let current = dir.as_ref() == self.dir();
+                let result = match message.as_str() {
+                    worker::FAILED => {
+                        if !current {
+                            return false;
+                        }
+                        // Keep the last content; retry with backoff (see Timer)
+                        self.failure = Some(match self.failure.take() {
+                            Some(failure) => failure.again(&payload),
+                            None => failure::Failure::new(&payload),
+                        });
+                        self.status.finished();
+                        return true;
+                    }
+                    worker::INFO => match worker::decode_info(&payload) {
+                        Some(info) => CachedResult {
+                            content: Vec::new(),
+                            info: Some(info),
+                        },
+                        None => return false,
+                    },
+                    _ => CachedResult {
+                        content: worker::decode(&payload),
+                        info: None,
+                    },
+                };
+                if let Some(ref dir) = dir {
+                    self.results.insert(dir.clone(), result.clone());
+                }
+                if !current {
+                    // Result for a directory that is no longer focused
+                    return false;
+                }
+                if let Some(ref info) = result.info {
+                    self.changes.update(dir.as_deref(), &info.tools);
+                }
+                self.show_result(result);
+                self.save_state(dir);
+                self.notice = None;
+                self.failure = None;
+                self.show_details = false;
+                self.status.finished();
+                true
+            }
+            Event::Timer(elapsed) => {
+                // Short ticks drive the spinner and age; refresh when due
+                self.status.tick(elapsed);
+                let interval = self
+                    .failure
+                    .as_ref()
+                    .map_or(self.refresh_interval, failure::Failure::retry_delay);
+                // A settled pane width re-fits powerline output (see render)
+                if self.status.is_due(interval) || self.status.is_refit_due() {
+                    self.request_tool_versions(false);
+                }
+                set_timeout(self.status.next_tick());
+                self.show_status
+            }
+            Event::ModeUpdate(mode_info) => {
+                // Track the palette even when not in use, so `t` can switch to it
+                let spec = zellij_colors(&mode_info.style).to_theme_spec();
+                if self.theme_spec.as_ref() != Some(&spec) {
+                    self.theme_spec = Some(spec);
+                    if self.theme.as_deref() == Some("zellij") {
+                        // Palette changed: re-render with the new colors
+                        self.request_tool_versions(false);
+                    }
+                }
+                let before = self.session.clone();
+                self.session.mode = Some(format!("{:?}", mode_info.mode));
+                if mode_info.session_name.is_some() {
+                    self.session.session = mode_info.session_name;
+                }
+                let restored = self.restore_state();
+                restored || self.session_display.any() && self.session != before
+            }
+            Event::TabUpdate(tabs) => {
+                let active = tabs.into_iter().find(|t| t.active);
+                self.active_tab = active.as_ref().map(|t| t.position);
+                let tab = active.map(|t| t.name);
+                let changed = self.session.tab != tab;
+                self.session.tab = tab;
+                self.session_display.tab && changed
+            }
+            Event::SessionUpdate(sessions, _) => {
+                let Some(current) = sessions.into_iter().find(|s| s.is_current_session) else {
+                    return false;
+                };
+                let changed = self.session.session.as_ref() != Some(&current.name);
+                self.session.session = Some(current.name);
+                let restored = self.restore_state();
+                restored || self.session_display.session && changed
+            }
+            Event::Key(key) => self.handle_key(key),
+            Event::Mouse(Mouse::ScrollUp(lines)) => {
+                self.scroll_action(keys::KeyAction::Scroll(-(lines as isize)))
+            }
+            Event::Mouse(Mouse::ScrollDown(lines)) => {
+                self.scroll_action(keys::KeyAction::Scroll(lines as isize))
+            }
             Event::PaneUpdate(pane_manifest) => {
-                // Could track active pane's working directory here
-                // and refresh tool versions when it changes
-                let _ = pane_manifest;
-                false
+                // Follow the focused terminal pane's directory (unless one is configured)
+                if self.working_dir.is_some() {
+                    return false;
+                }
+                let Some(dir) = self
+                    .active_tab
+                    .and_then(|tab| pane_manifest.panes.get(&tab))
+                    .and_then(|panes| panes.iter().find(|p| p.is_focused && !p.is_plugin))
+                    .and_then(|pane| context::cwd_from_title(&pane.title))
+                else {
+                    return false;
+                };
+                if self.pane_dir.as_ref() == Some(&dir) {
+                    return false;
+                }
+                // Show the last result for this directory while refreshing it
+                let cached = self.results.get(&dir).cloned();
+                self.pane_dir = Some(dir);
+                self.request_tool_versions(false);
+                match cached {
+                    Some(result) => {
+                        self.show_result(result);
+                        true
+                    }
+                    None => false,
+                }
             }
             _ => false,
         }
     }
 
     fn render(&mut self, rows: usize, cols: usize) {
+        let resized = self.rows != rows || self.cols != cols;
+        let width_changed = self.cols != cols;
         self.rows = rows;
         self.cols = cols;
+        if self.powerline {
+            // Pane resized: the cached output is truncated to the new width
+            // below, and the CLI re-fits it once the width settles (Timer)
+            if width_changed {
+                self.status.resized();
+            }
+        } else if resized {
+            // Lay out the structured result again
+            self.relayout();
+        }
+
+        // Explain missing permissions instead of loading forever
+        let screen = match self.permission {
+            permissions::Permission::Denied => Some(permissions::DENIED),
+            permissions::Permission::Pending
+                if self.status.age().is_none() && !self.status.is_restored() =>
+            {
+                Some(permissions::PENDING)
+            }
+            _ => None,
+        };
+        if let Some(screen) = screen {
+            if self.single_line {
+                print!(
+                    "{}",
+                    truncate_to_width(&permissions::single_line(screen), cols)
+                );
+            } else {
+                for line in screen.iter().take(rows) {
+                    if *line == "---" {
+                        println!("{}", "─".repeat(cols));
+                    } else {
+                        println!("{}", truncate_to_width(line, cols));
+                    }
+                }
+            }
+            return;
+        }
+
+        if self.show_help {
+            if self.single_line {
+                let line = keys::HELP
+                    .iter()
+                    .filter(|line| **line != "---")
+                    .map(|line| line.trim())
+                    .collect::<Vec<_>>()
+                    .join(" · ");
+                print!("{}", truncate_to_width(&line, cols));
+            } else {
+                for line in keys::HELP.iter().take(rows) {
+                    if *line == "---" {
+                        println!("{}", "─".repeat(cols));
+                    } else {
+                        println!("{}", truncate_to_width(line, cols));
+                    }
+                }
+            }
+            return;
+        }
 
         if self.single_line {
             // Single line mode: join all non-separator lines (no trailing newline)
-            let line = self.build_single_line();
+            let mut line = self.build_single_line();
+            if let Some(ref failure) = self.failure {
+                line = format!("\x1b[31m✗ {}\x1b[0m | {}", failure.summary(), line);
+            }
+            if let Some(ref warning) = self.cli_warning {
+                line = format!("{} \x1b[33m(⚠ {})\x1b[0m", line, warning);
+            }
+            if let Some(session) = self.session_line() {
+                line = format!("{} | {}", session.trim(), line);
+            }
+            if self.show_status {
+                if let Some(spinner) = self.status.spinner() {
+                    line = format!("{} {}", spinner, line);
+                }
+                if self.status.is_stale(self.refresh_interval) {
+                    if let Some(text) = self.status.text() {
+                        line = format!("{} \x1b[33m({})\x1b[0m", line, text);
+                    }
+                }
+            }
             let display_line = truncate_to_width(&line, cols);
             print!("{}", display_line);
         } else {
-            // Multi-line mode
-            for (i, line) in self.content.iter().enumerate() {
-                if i >= rows {
-                    break;
-                }
+            // Session / mode / tab and the error line stay pinned on top
+            for (line, style) in self.pinned_lines() {
+                println!("{}{}\x1b[0m", style, truncate_to_width(&line, cols));
+            }
+
+            // Multi-line mode: scrollable, with an indicator row on overflow
+            let (total, content_rows) = (self.shown_lines().len(), self.content_rows());
+            let view = self.scroll.viewport(total, content_rows);
+            let lines = self.shown_lines();
+            for line in &lines[view.start..view.end] {
                 // Check if this is a separator line (starts with ─ or is "---" marker)
                 let display_line = if line.starts_with('─') || line == "---" {
                     "─".repeat(cols)
@@ -148,16 +582,310 @@ impl ZellijPlugin for ToolboxPlugin {
                 };
                 println!("{}", display_line);
             }
+            if let Some(indicator) = view.indicator().filter(|_| self.content_rows() > 1) {
+                println!("\x1b[2m{}\x1b[0m", truncate_to_width(&indicator, cols));
+            }
+
+            // Status on the last row: dim, yellow once the data is stale
+            if self.show_status && rows > 1 {
+                if let Some(text) = self.status.text() {
+                    let color = if self.status.is_stale(self.refresh_interval) {
+                        "\x1b[33m"
+                    } else {
+                        "\x1b[2m"
+                    };
+                    for _ in view.end - view.start + usize::from(view.indicator().is_some())
+                        ..self.content_rows()
+                    {
+                        println!();
+                    }
+                    print!(
+                        "{}{}\x1b[0m",
+                        color,
+                        truncate_to_width(&format!(" {}", text), cols)
+                    );
+                }
+            }
         }
     }
 }
 
 #[cfg(target_arch = "wasm32")]
 impl ToolboxPlugin {
-    fn request_tool_versions(&self) {
-        // Run the toolbox CLI to get versions
-        // The CLI should be installed and in PATH
-        let mut args = vec!["toolbox", "--format", "text", "--compact"];
+    /// React to a key press; returns whether to re-render
+    fn handle_key(&mut self, key: KeyWithModifier) -> bool {
+        if !key.has_no_modifiers() {
+            return false;
+        }
+        let had_notice = self.notice.take().is_some();
+        if self.show_help && key.bare_key == BareKey::Esc {
+            self.show_help = false;
+            return true;
+        }
+        let action = match key.bare_key {
+            BareKey::Char(c) => keys::action_for_char(c),
+            BareKey::Down => Some(keys::KeyAction::Scroll(1)),
+            BareKey::Up => Some(keys::KeyAction::Scroll(-1)),
+            BareKey::PageDown => Some(keys::KeyAction::ScrollPage(1)),
+            BareKey::PageUp => Some(keys::KeyAction::ScrollPage(-1)),
+            BareKey::Home => Some(keys::KeyAction::ScrollTop),
+            BareKey::End => Some(keys::KeyAction::ScrollBottom),
+            _ => None,
+        };
+
+        match action {
+            Some(keys::KeyAction::Refresh) => self.request_tool_versions(true),
+            Some(keys::KeyAction::ToggleCompact) => {
+                self.compact = !self.compact;
+                self.request_tool_versions(false);
+            }
+            Some(keys::KeyAction::ToggleIcons) => {
+                self.show_icons = !self.show_icons;
+                self.request_tool_versions(false);
+            }
+            Some(keys::KeyAction::CycleTheme) => {
+                self.theme = Some(keys::next_theme(self.theme.as_deref()).to_string());
+                self.request_tool_versions(false);
+            }
+            Some(keys::KeyAction::CycleProfile) => {
+                let known = self.info.as_ref().map(|info| info.profiles.as_slice());
+                let profiles = match known {
+                    Some(known) if self.profiles.is_empty() => known,
+                    _ => self.profiles.as_slice(),
+                };
+                if profiles.is_empty() && self.profile.is_none() {
+                    return had_notice;
+                }
+                self.profile = keys::next_profile(self.profile.as_deref(), profiles);
+                self.request_tool_versions(false);
+            }
+            Some(keys::KeyAction::ToggleHelp) => {
+                self.show_help = !self.show_help;
+                return true;
+            }
+            Some(keys::KeyAction::ToggleDetails) => {
+                if self.failure.is_none() && !self.show_details {
+                    return had_notice;
+                }
+                self.show_details = !self.show_details;
+                self.scroll.home();
+                return true;
+            }
+            Some(keys::KeyAction::CopyAll) => return self.copy(false),
+            Some(keys::KeyAction::CopyLine) => return self.copy(true),
+            Some(scroll) => return self.scroll_action(scroll) || had_notice,
+            None => {}
+        }
+        // New content arrives with the command result
+        had_notice
+    }
+
+    /// Copy the shown content (or its top visible line) as plain text
+    fn copy(&mut self, top_line: bool) -> bool {
+        let lines = self.shown_lines();
+        let lines: &[String] = if top_line {
+            let start = self.scroll.offset().min(lines.len().saturating_sub(1));
+            &lines[start..lines.len().min(start + 1)]
+        } else {
+            &lines
+        };
+        if lines.is_empty() {
+            return false;
+        }
+        copy_to_clipboard(clipboard::plain_text(lines));
+        self.notice = Some(clipboard::confirmation(lines.len()));
+        true
+    }
+
+    /// Directory detection runs in
+    fn dir(&self) -> Option<&String> {
+        self.working_dir.as_ref().or(self.pane_dir.as_ref())
+    }
+
+    /// Display a (possibly cached) result
+    fn show_result(&mut self, result: CachedResult) {
+        self.info = result.info;
+        if self.info.is_some() {
+            self.relayout();
+        } else {
+            self.content = result.content;
+        }
+    }
+
+    /// Show the content saved by a resurrected session, once the session
+    /// name is known and before the first result; returns whether it did
+    fn restore_state(&mut self) -> bool {
+        let Some(ref session) = self.session.session else {
+            return false;
+        };
+        if self.state_loaded {
+            return false;
+        }
+        self.state_loaded = true;
+        if self.status.age().is_some() {
+            return false;
+        }
+        let path = state::path(std::path::Path::new(state::STATE_DIR), session);
+        let Some(saved) = state::load(&path) else {
+            return false;
+        };
+        let result = CachedResult {
+            content: saved.content,
+            info: saved.info,
+        };
+        if result.content.is_empty() && result.info.is_none() {
+            return false;
+        }
+        if let Some(dir) = saved.dir {
+            self.results.insert(dir, result.clone());
+        }
+        self.show_result(result);
+        self.status.restored();
+        true
+    }
+
+    /// Save the shown result for session resurrection
+    fn save_state(&mut self, dir: Option<String>) {
+        let Some(ref session) = self.session.session else {
+            return;
+        };
+        let json = state::encode(&state::SavedState {
+            dir,
+            content: if self.info.is_some() {
+                Vec::new()
+            } else {
+                self.content.clone()
+            },
+            info: self.info.clone(),
+        });
+        if json == self.saved_state {
+            return;
+        }
+        let path = state::path(std::path::Path::new(state::STATE_DIR), session);
+        // Best effort: without a writable cache the pane just starts empty
+        if state::save(&path, &json).is_ok() {
+            self.saved_state = json;
+        }
+    }
+
+    /// Lay out the structured result for the current pane size
+    fn relayout(&mut self) {
+        if let Some(ref info) = self.info {
+            let highlighted = self.changes.highlighted();
+            let options = layout::LayoutOptions {
+                compact: self.compact,
+                show_icons: self.show_icons,
+                sections: &self.sections,
+                highlighted: &highlighted,
+            };
+            // Single-line mode joins the lines itself: never use columns there
+            let rows = if self.single_line {
+                usize::MAX
+            } else {
+                self.content_rows()
+            };
+            self.content = layout::lines(info, self.cols, rows, &options);
+        }
+    }
+
+    /// Rows available for content (the last row holds the status, if shown)
+    fn content_rows(&self) -> usize {
+        let status_rows = usize::from(self.show_status && self.rows > 1);
+        self.rows - status_rows - self.pinned_lines().len()
+    }
+
+    /// Lines pinned above the content with their style: session line, CLI
+    /// mismatch warning, copy confirmation and error line, as many as fit
+    /// while leaving a content row (the error goes first, then the others)
+    fn pinned_lines(&self) -> Vec<(String, &'static str)> {
+        let status_rows = usize::from(self.show_status && self.rows > 1);
+        let room = self.rows.saturating_sub(status_rows + 1);
+        let error = self
+            .failure
+            .as_ref()
+            .filter(|_| !self.show_details)
+            .map(|failure| (failure.banner(), "\x1b[31m"));
+        let notice = self
+            .notice
+            .as_ref()
+            .map(|notice| (notice.clone(), "\x1b[32m"));
+        let warning = self
+            .cli_warning
+            .as_ref()
+            .map(|warning| (format!(" ⚠ {}", warning), "\x1b[33m"));
+        let session = self.session_line().map(|line| (line, "\x1b[1m"));
+        let mut lines: Vec<_> = error
+            .into_iter()
+            .chain(notice)
+            .chain(warning)
+            .chain(session)
+            .take(room)
+            .collect();
+        lines.reverse();
+        lines
+    }
+
+    /// Content lines, or the error details when expanded
+    fn shown_lines(&self) -> Cow<'_, [String]> {
+        match self.failure {
+            Some(ref failure) if self.show_details => Cow::Owned(failure.detail_lines()),
+            _ => Cow::Borrowed(&self.content),
+        }
+    }
+
+    /// Session / mode / tab line, if enabled and known
+    fn session_line(&self) -> Option<String> {
+        self.session.line(self.session_display, self.show_icons)
+    }
+
+    /// Apply a scroll action; returns whether the position changed
+    fn scroll_action(&mut self, action: keys::KeyAction) -> bool {
+        if self.single_line || self.show_help {
+            return false;
+        }
+        let (total, rows) = (self.shown_lines().len(), self.content_rows());
+        let before = self.scroll;
+        match action {
+            keys::KeyAction::Scroll(lines) => self.scroll.by(lines, total, rows),
+            keys::KeyAction::ScrollPage(pages) => self.scroll.by_pages(pages, total, rows),
+            keys::KeyAction::ScrollTop => self.scroll.home(),
+            keys::KeyAction::ScrollBottom => self.scroll.end(total, rows),
+            _ => {}
+        }
+        self.scroll != before
+    }
+
+    /// Run the toolbox CLI; `refresh` bypasses the version cache
+    fn request_tool_versions(&mut self, refresh: bool) {
+        if !self.permission.can_run() {
+            return;
+        }
+
+        // Check the CLI can be run before the first real run
+        if !self.cli_found {
+            let probe = self.invocation.probe();
+            let probe: Vec<&str> = probe.iter().map(String::as_str).collect();
+            let context = BTreeMap::from([("probe".to_string(), String::new())]);
+            run_command(&probe, context);
+            self.status.started();
+            return;
+        }
+
+        // Run the toolbox CLI (`cli_path`, default: toolbox in PATH) to get versions
+        // Plain output is laid out by the plugin; powerline is rendered by the CLI
+        let format = if self.powerline { "text" } else { "json" };
+        let mut args = vec![self.invocation.program.as_str(), "--format", format];
+        args.push(if self.compact {
+            "--compact"
+        } else {
+            "--no-compact"
+        });
+        if !self.show_icons {
+            args.push("--no-icons");
+        }
+        if refresh {
+            args.push("--refresh");
+        }
 
         // Add powerline flag if enabled
         if self.powerline {
@@ -171,33 +899,59 @@ impl ToolboxPlugin {
             }
         }
 
-        // Add theme if configured
+        let layout_arg;
+        if let Some(ref layout) = self.layout {
+            args.push("--layout");
+            layout_arg = layout.clone();
+            args.push(&layout_arg);
+        }
+
+        // Let the CLI fit powerline output to the pane width
+        let width_arg;
+        if self.powerline && self.cols > 0 {
+            args.push("--max-width");
+            width_arg = self.cols.to_string();
+            args.push(&width_arg);
+        }
+
+        // Add theme if configured ("zellij" passes the palette as color overrides)
         let theme_arg;
         if let Some(ref theme) = self.theme {
-            args.push("--theme");
-            theme_arg = theme.clone();
-            args.push(&theme_arg);
+            if theme == "zellij" {
+                if let Some(ref spec) = self.theme_spec {
+                    args.push("--theme-colors");
+                    theme_arg = spec.clone();
+                    args.push(&theme_arg);
+                }
+            } else {
+                args.push("--theme");
+                theme_arg = theme.clone();
+                args.push(&theme_arg);
+            }
+        }
+
+        let profile_arg;
+        if let Some(ref profile) = self.profile {
+            args.push("--profile");
+            profile_arg = profile.clone();
+            args.push(&profile_arg);
         }
 
-        // Add working directory if configured
+        // Add working directory (configured, or the focused pane's)
         let dir_arg;
-        if let Some(ref dir) = self.working_dir {
+        let mut context = BTreeMap::new();
+        if let Some(dir) = self.dir() {
             args.push("--dir");
             dir_arg = dir.clone();
             args.push(&dir_arg);
+            context.insert("dir".to_string(), dir.clone());
         }
 
-        run_command(&args, BTreeMap::new());
-    }
-
-    fn parse_output(&mut self, stdout: &[u8]) {
-        let output = String::from_utf8_lossy(stdout);
-        self.content = output.lines().map(String::from).collect();
+        args.extend(self.section_args.iter().map(String::as_str));
+        args.extend(self.invocation.extra_args.iter().map(String::as_str));
 
-        // Ensure at least some content
-        if self.content.is_empty() {
-            self.content = vec![" No tools detected".to_string()];
-        }
+        run_command(&args, context);
+        self.status.started();
     }
 
     fn build_single_line(&self) -> String {
@@ -214,6 +968,34 @@ impl ToolboxPlugin {
     }
 }
 
+/// Convert a Zellij palette color to RGB
+#[cfg(target_arch = "wasm32")]
+fn palette_rgb(color: PaletteColor) -> (u8, u8, u8) {
+    match color {
+        PaletteColor::Rgb(rgb) => rgb,
+        PaletteColor::EightBit(index) => palette::eight_bit_to_rgb(index),
+    }
+}
+
+/// Pick the colors toolbox needs from the active Zellij theme
+#[cfg(target_arch = "wasm32")]
+fn zellij_colors(style: &Style) -> palette::ZellijColors {
+    let colors = &style.colors;
+    palette::ZellijColors {
+        ribbon_selected_bg: palette_rgb(colors.ribbon_selected.background),
+        ribbon_selected_fg: palette_rgb(colors.ribbon_selected.base),
+        ribbon_unselected_bg: palette_rgb(colors.ribbon_unselected.background),
+        ribbon_unselected_fg: palette_rgb(colors.ribbon_unselected.base),
+        success: palette_rgb(colors.exit_code_success.base),
+        emphasis: [
+            palette_rgb(colors.text_unselected.emphasis_0),
+            palette_rgb(colors.text_unselected.emphasis_1),
+            palette_rgb(colors.text_unselected.emphasis_2),
+            palette_rgb(colors.text_unselected.emphasis_3),
+        ],
+    }
+}
+
 /// Truncate a string to fit within a given display width
 /// Accounts for Unicode character widths (e.g., emojis are width 2)
 /// Properly skips ANSI escape sequences (they have zero display width)
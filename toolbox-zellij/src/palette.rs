@@ -0,0 +1,156 @@
+//! Mapping of the active Zellij theme onto toolbox theme colors
+//!
+//! The plugin reads the palette from `ModeUpdate` and passes it to the CLI as a
+//! `--theme-colors` spec, so the pane matches the surrounding Zellij UI.
+
+/// Colors picked from the active Zellij theme, already converted to RGB
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZellijColors {
+    /// Selected ribbon background (active tab)
+    pub ribbon_selected_bg: (u8, u8, u8),
+    /// Selected ribbon text
+    pub ribbon_selected_fg: (u8, u8, u8),
+    /// Unselected ribbon background (inactive tabs)
+    pub ribbon_unselected_bg: (u8, u8, u8),
+    /// Unselected ribbon text
+    pub ribbon_unselected_fg: (u8, u8, u8),
+    /// Success color (exit code 0)
+    pub success: (u8, u8, u8),
+    /// Emphasis colors of unselected text (orange, cyan, green, magenta in most themes)
+    pub emphasis: [(u8, u8, u8); 4],
+}
+
+impl ZellijColors {
+    /// Build a `--theme-colors` spec understood by the toolbox CLI
+    pub fn to_theme_spec(&self) -> String {
+        let dark = self.ribbon_selected_fg;
+        [
+            ("directory_bg", hex(self.ribbon_selected_bg)),
+            ("directory_fg", hex(dark)),
+            ("git_clean_bg", hex(self.success)),
+            ("git_clean_fg", hex(dark)),
+            ("git_dirty_bg", hex(self.emphasis[0])),
+            ("git_dirty_fg", hex(dark)),
+            (
+                "tool_bg",
+                format!(
+                    "{};{};{}",
+                    hex(self.emphasis[1]),
+                    hex(self.emphasis[3]),
+                    hex(self.ribbon_unselected_bg)
+                ),
+            ),
+            (
+                "tool_fg",
+                format!(
+                    "{};{};{}",
+                    hex(dark),
+                    hex(dark),
+                    hex(self.ribbon_unselected_fg)
+                ),
+            ),
+            ("venv_bg", hex(self.emphasis[2])),
+            ("venv_fg", hex(dark)),
+        ]
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+/// Convert an xterm 256-color index to RGB
+pub fn eight_bit_to_rgb(index: u8) -> (u8, u8, u8) {
+    const BASE: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00),
+        (0x80, 0x00, 0x00),
+        (0x00, 0x80, 0x00),
+        (0x80, 0x80, 0x00),
+        (0x00, 0x00, 0x80),
+        (0x80, 0x00, 0x80),
+        (0x00, 0x80, 0x80),
+        (0xC0, 0xC0, 0xC0),
+        (0x80, 0x80, 0x80),
+        (0xFF, 0x00, 0x00),
+        (0x00, 0xFF, 0x00),
+        (0xFF, 0xFF, 0x00),
+        (0x00, 0x00, 0xFF),
+        (0xFF, 0x00, 0xFF),
+        (0x00, 0xFF, 0xFF),
+        (0xFF, 0xFF, 0xFF),
+    ];
+    const CUBE: [u8; 6] = [0x00, 0x5F, 0x87, 0xAF, 0xD7, 0xFF];
+
+    match index {
+        0..=15 => BASE[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE[(i / 36) as usize],
+                CUBE[((i / 6) % 6) as usize],
+                CUBE[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> ZellijColors {
+        ZellijColors {
+            ribbon_selected_bg: (0xA6, 0xE3, 0xA1),
+            ribbon_selected_fg: (0x1E, 0x1E, 0x2E),
+            ribbon_unselected_bg: (0x45, 0x47, 0x5A),
+            ribbon_unselected_fg: (0xCD, 0xD6, 0xF4),
+            success: (0x00, 0xFF, 0x00),
+            emphasis: [
+                (0xFF, 0x80, 0x00),
+                (0x00, 0xFF, 0xFF),
+                (0x00, 0xAA, 0x00),
+                (0xFF, 0x00, 0xFF),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_to_theme_spec_contains_all_keys() {
+        let spec = sample().to_theme_spec();
+        for key in [
+            "directory_bg=#A6E3A1",
+            "directory_fg=#1E1E2E",
+            "git_clean_bg=#00FF00",
+            "git_dirty_bg=#FF8000",
+            "tool_bg=#00FFFF;#FF00FF;#45475A",
+            "tool_fg=#1E1E2E;#1E1E2E;#CDD6F4",
+            "venv_bg=#00AA00",
+        ] {
+            assert!(spec.contains(key), "missing {} in {}", key, spec);
+        }
+    }
+
+    #[test]
+    fn test_eight_bit_to_rgb_base_colors() {
+        assert_eq!(eight_bit_to_rgb(0), (0, 0, 0));
+        assert_eq!(eight_bit_to_rgb(9), (0xFF, 0, 0));
+        assert_eq!(eight_bit_to_rgb(15), (0xFF, 0xFF, 0xFF));
+    }
+
+    #[test]
+    fn test_eight_bit_to_rgb_cube_and_grayscale() {
+        assert_eq!(eight_bit_to_rgb(16), (0, 0, 0));
+        assert_eq!(eight_bit_to_rgb(196), (0xFF, 0, 0));
+        assert_eq!(eight_bit_to_rgb(231), (0xFF, 0xFF, 0xFF));
+        assert_eq!(eight_bit_to_rgb(232), (8, 8, 8));
+        assert_eq!(eight_bit_to_rgb(255), (238, 238, 238));
+    }
+}
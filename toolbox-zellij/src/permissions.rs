@@ -0,0 +1,78 @@
+//! Permission state and the screens shown while it is not granted
+//!
+//! The plugin can only show anything useful once Zellij lets it run the
+//! toolbox CLI. Instead of a "Loading..." that never ends, it explains what
+//! is missing and how to grant it.
+
+/// State of the plugin's permission request
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// No answer yet (the prompt is shown in the plugin pane)
+    #[default]
+    Pending,
+    /// RunCommands and ReadApplicationState were granted
+    Granted,
+    /// The request was denied
+    Denied,
+}
+
+impl Permission {
+    /// Whether the CLI may be run (pending requests still try, in case the
+    /// grant was cached by Zellij and no answer event arrives)
+    pub fn can_run(self) -> bool {
+        self != Permission::Denied
+    }
+}
+
+/// Shown until the permission prompt is answered
+pub const PENDING: &[&str] = &[
+    "---",
+    " Waiting for permission",
+    " Focus this pane and answer the prompt (y) to allow",
+    " running the toolbox CLI.",
+    "---",
+];
+
+/// Shown instead of tool versions when permission was denied
+pub const DENIED: &[&str] = &[
+    "---",
+    " Permission denied",
+    " toolbox needs \"Run commands\" to call the toolbox CLI",
+    " and \"Read application state\" to follow the theme.",
+    " To grant it, remove this plugin from Zellij's",
+    " permissions.kdl (in the Zellij cache directory),",
+    " then reload the plugin and answer the prompt with y.",
+    "---",
+];
+
+/// One-line variant of a screen for single-line mode
+pub fn single_line(screen: &[&str]) -> String {
+    screen
+        .iter()
+        .filter(|line| **line != "---")
+        .map(|line| line.trim())
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(": ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_can_run() {
+        assert!(Permission::default().can_run());
+        assert!(Permission::Granted.can_run());
+        assert!(!Permission::Denied.can_run());
+    }
+
+    #[test]
+    fn test_single_line() {
+        assert_eq!(
+            single_line(DENIED),
+            "Permission denied: toolbox needs \"Run commands\" to call the toolbox CLI"
+        );
+        assert!(single_line(PENDING).starts_with("Waiting for permission: "));
+    }
+}
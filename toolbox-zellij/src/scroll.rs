@@ -0,0 +1,158 @@
+//! Scrolling of multi-line content taller than the pane
+//!
+//! When the content overflows, the last row shows how many lines are hidden
+//! above and below instead of the content being cut off silently.
+
+/// Scroll position: index of the first visible content line
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Scroll {
+    offset: usize,
+}
+
+/// Visible slice of the content for a given pane height
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// First visible line
+    pub start: usize,
+    /// One past the last visible line
+    pub end: usize,
+    /// Lines hidden above
+    pub above: usize,
+    /// Lines hidden below
+    pub below: usize,
+}
+
+impl Scroll {
+    /// Content lines that fit; one row is kept for the indicator on overflow
+    fn page(total: usize, rows: usize) -> usize {
+        if total <= rows {
+            rows
+        } else {
+            rows.saturating_sub(1).max(1)
+        }
+    }
+
+    fn max_offset(total: usize, rows: usize) -> usize {
+        total.saturating_sub(Self::page(total, rows))
+    }
+
+    /// Scroll by a number of lines (negative is up)
+    pub fn by(&mut self, lines: isize, total: usize, rows: usize) {
+        let offset = if lines < 0 {
+            self.offset.saturating_sub(lines.unsigned_abs())
+        } else {
+            self.offset.saturating_add(lines as usize)
+        };
+        self.offset = offset.min(Self::max_offset(total, rows));
+    }
+
+    /// Scroll by whole pages (negative is up)
+    pub fn by_pages(&mut self, pages: isize, total: usize, rows: usize) {
+        let page = Self::page(total, rows) as isize;
+        self.by(pages.saturating_mul(page), total, rows);
+    }
+
+    /// Index of the first visible line
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Jump to the first line
+    pub fn home(&mut self) {
+        self.offset = 0;
+    }
+
+    /// Jump so the last line is visible
+    pub fn end(&mut self, total: usize, rows: usize) {
+        self.offset = Self::max_offset(total, rows);
+    }
+
+    /// Visible lines, clamping the position if the content shrank
+    pub fn viewport(&mut self, total: usize, rows: usize) -> Viewport {
+        self.offset = self.offset.min(Self::max_offset(total, rows));
+        let end = (self.offset + Self::page(total, rows)).min(total);
+        Viewport {
+            start: self.offset,
+            end,
+            above: self.offset,
+            below: total - end,
+        }
+    }
+}
+
+impl Viewport {
+    /// Indicator line for hidden content (None if everything is visible)
+    pub fn indicator(&self) -> Option<String> {
+        match (self.above, self.below) {
+            (0, 0) => None,
+            (0, below) => Some(format!(" ↓ {} more", below)),
+            (above, 0) => Some(format!(" ↑ {} more", above)),
+            (above, below) => Some(format!(" ↑ {} · ↓ {} more", above, below)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_viewport_fits() {
+        let mut scroll = Scroll::default();
+        let view = scroll.viewport(3, 5);
+        assert_eq!((view.start, view.end), (0, 3));
+        assert_eq!(view.indicator(), None);
+
+        // Scrolling is a no-op when everything fits
+        scroll.by(2, 3, 5);
+        assert_eq!(scroll.viewport(3, 5).start, 0);
+    }
+
+    #[test]
+    fn test_viewport_overflow_reserves_indicator_row() {
+        let mut scroll = Scroll::default();
+        let view = scroll.viewport(10, 4);
+        assert_eq!((view.start, view.end), (0, 3));
+        assert_eq!(view.indicator().as_deref(), Some(" ↓ 7 more"));
+
+        scroll.by(2, 10, 4);
+        let view = scroll.viewport(10, 4);
+        assert_eq!((view.start, view.end), (2, 5));
+        assert_eq!(view.indicator().as_deref(), Some(" ↑ 2 · ↓ 5 more"));
+
+        scroll.end(10, 4);
+        let view = scroll.viewport(10, 4);
+        assert_eq!((view.start, view.end), (7, 10));
+        assert_eq!(view.indicator().as_deref(), Some(" ↑ 7 more"));
+    }
+
+    #[test]
+    fn test_scroll_clamps() {
+        let mut scroll = Scroll::default();
+        scroll.by(-3, 10, 4);
+        assert_eq!(scroll.viewport(10, 4).start, 0);
+        scroll.by(100, 10, 4);
+        assert_eq!(scroll.viewport(10, 4).start, 7);
+
+        // Content shrinking pulls the position back
+        assert_eq!(scroll.viewport(5, 4).start, 2);
+        scroll.home();
+        assert_eq!(scroll.viewport(10, 4).start, 0);
+    }
+
+    #[test]
+    fn test_scroll_by_pages() {
+        let mut scroll = Scroll::default();
+        scroll.by_pages(1, 20, 5);
+        assert_eq!(scroll.viewport(20, 5).start, 4);
+        scroll.by_pages(-1, 20, 5);
+        assert_eq!(scroll.viewport(20, 5).start, 0);
+    }
+
+    #[test]
+    fn test_single_row_pane() {
+        let mut scroll = Scroll::default();
+        let view = scroll.viewport(3, 1);
+        assert_eq!((view.start, view.end), (0, 1));
+    }
+}
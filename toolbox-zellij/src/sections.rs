@@ -0,0 +1,125 @@
+//! Section and tool selection from the plugin configuration
+//!
+//! Layout KDL keys like `show_git false` or `tools "rust,node"` are turned
+//! into CLI flags, so the pane can be customized without a toolbox config
+//! file. Sections not mentioned keep the CLI's defaults.
+
+use std::collections::BTreeMap;
+use toolbox_core::config::Section;
+
+/// Sections in the CLI's default order, with their name and `show_*` config key
+const SECTIONS: &[(Section, &str, &str)] = &[
+    (Section::Dir, "dir", "show_dir"),
+    (Section::Git, "git", "show_git"),
+    (Section::Tools, "tools", "show_tools"),
+    (Section::Venv, "venv", "show_venv"),
+    (Section::System, "system", "show_system"),
+];
+
+/// Sections hidden unless enabled explicitly
+const OFF_BY_DEFAULT: &[Section] = &[Section::System];
+
+fn flag(configuration: &BTreeMap<String, String>, key: &str) -> Option<bool> {
+    configuration
+        .get(key)
+        .map(|value| value == "true" || value == "1")
+}
+
+fn shown(configuration: &BTreeMap<String, String>) -> Option<Vec<(Section, &'static str)>> {
+    if !SECTIONS
+        .iter()
+        .any(|(_, _, key)| configuration.contains_key(*key))
+    {
+        return None;
+    }
+    Some(
+        SECTIONS
+            .iter()
+            .filter(|(section, _, key)| {
+                flag(configuration, key).unwrap_or(!OFF_BY_DEFAULT.contains(section))
+            })
+            .map(|(section, name, _)| (*section, *name))
+            .collect(),
+    )
+}
+
+/// Sections selected by the `show_*` keys (None if none is set)
+pub fn sections(configuration: &BTreeMap<String, String>) -> Option<Vec<Section>> {
+    shown(configuration).map(|shown| shown.into_iter().map(|(section, _)| section).collect())
+}
+
+/// CLI arguments for the `show_*` and `tools` configuration keys
+pub fn section_args(configuration: &BTreeMap<String, String>) -> Vec<String> {
+    let mut args = Vec::new();
+
+    if let Some(shown) = shown(configuration) {
+        let names: Vec<&str> = shown.into_iter().map(|(_, name)| name).collect();
+        args.push("--sections".to_string());
+        args.push(names.join(","));
+    }
+
+    if flag(configuration, "show_system") == Some(true) {
+        args.push("--system".to_string());
+    }
+
+    if let Some(tools) = configuration.get("tools") {
+        let tools: Vec<&str> = tools
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect();
+        if !tools.is_empty() {
+            args.push("--only".to_string());
+            args.push(tools.join(","));
+        }
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_no_keys_keep_defaults() {
+        assert!(section_args(&config(&[("theme", "nord")])).is_empty());
+    }
+
+    #[test]
+    fn test_show_flags() {
+        assert_eq!(
+            section_args(&config(&[("show_git", "false")])),
+            vec!["--sections", "dir,tools,venv"]
+        );
+        assert_eq!(
+            section_args(&config(&[("show_system", "true"), ("show_dir", "0")])),
+            vec!["--sections", "git,tools,venv,system", "--system"]
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        assert_eq!(sections(&config(&[])), None);
+        assert_eq!(
+            sections(&config(&[("show_dir", "false"), ("show_venv", "false")])),
+            Some(vec![Section::Git, Section::Tools])
+        );
+    }
+
+    #[test]
+    fn test_tools() {
+        assert_eq!(
+            section_args(&config(&[("tools", " rust, node ,")])),
+            vec!["--only", "rust,node"]
+        );
+        assert!(section_args(&config(&[("tools", "")])).is_empty());
+    }
+}
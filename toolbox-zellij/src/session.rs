@@ -0,0 +1,133 @@
+//! Zellij session, input mode and tab shown alongside the tool versions
+//!
+//! With these enabled the pane can replace Zellij's own status bar. Each part
+//! is off unless its `show_*` configuration key is set.
+
+use std::collections::BTreeMap;
+
+/// Which parts to show (`show_session`, `show_mode`, `show_tab`)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SessionDisplay {
+    pub session: bool,
+    pub mode: bool,
+    pub tab: bool,
+}
+
+impl SessionDisplay {
+    /// Read the `show_*` keys from the plugin configuration
+    pub fn from_config(configuration: &BTreeMap<String, String>) -> Self {
+        let flag = |key: &str| {
+            configuration
+                .get(key)
+                .is_some_and(|value| value == "true" || value == "1")
+        };
+        SessionDisplay {
+            session: flag("show_session"),
+            mode: flag("show_mode"),
+            tab: flag("show_tab"),
+        }
+    }
+
+    /// Whether anything is shown
+    pub fn any(&self) -> bool {
+        self.session || self.mode || self.tab
+    }
+}
+
+/// Latest session state reported by Zellij
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SessionState {
+    /// Session name (ModeUpdate / SessionUpdate)
+    pub session: Option<String>,
+    /// Input mode, e.g. "Normal" (ModeUpdate)
+    pub mode: Option<String>,
+    /// Name of the active tab (TabUpdate)
+    pub tab: Option<String>,
+}
+
+impl SessionState {
+    /// Status line, e.g. " 🗂 work · NORMAL · editor" (None if nothing to show)
+    pub fn line(&self, display: SessionDisplay, show_icons: bool) -> Option<String> {
+        let mut parts = Vec::new();
+        if display.session {
+            if let Some(ref session) = self.session {
+                parts.push(if show_icons {
+                    format!("🗂 {}", session)
+                } else {
+                    session.clone()
+                });
+            }
+        }
+        if display.mode {
+            if let Some(ref mode) = self.mode {
+                parts.push(mode.to_uppercase());
+            }
+        }
+        if display.tab {
+            if let Some(ref tab) = self.tab {
+                parts.push(tab.clone());
+            }
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(format!(" {}", parts.join(" · ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> SessionState {
+        SessionState {
+            session: Some("work".to_string()),
+            mode: Some("Locked".to_string()),
+            tab: Some("editor".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config: BTreeMap<String, String> = [("show_mode", "true"), ("show_tab", "false")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let display = SessionDisplay::from_config(&config);
+        assert_eq!(
+            display,
+            SessionDisplay {
+                session: false,
+                mode: true,
+                tab: false
+            }
+        );
+        assert!(display.any());
+        assert!(!SessionDisplay::default().any());
+    }
+
+    #[test]
+    fn test_line() {
+        let all = SessionDisplay {
+            session: true,
+            mode: true,
+            tab: true,
+        };
+        assert_eq!(
+            state().line(all, true).as_deref(),
+            Some(" 🗂 work · LOCKED · editor")
+        );
+        assert_eq!(
+            state().line(all, false).as_deref(),
+            Some(" work · LOCKED · editor")
+        );
+
+        let tab_only = SessionDisplay {
+            tab: true,
+            ..SessionDisplay::default()
+        };
+        assert_eq!(state().line(tab_only, true).as_deref(), Some(" editor"));
+        assert_eq!(SessionState::default().line(all, true), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Last content saved across session resurrection
+//!
+//! After each result the plugin writes what it shows to Zellij's persistent
+//! `/cache` folder, one file per session. When a resurrected session is
+//! attached, the plugin shows that content (marked stale) at once instead of
+//! "Loading..." until the first refresh completes.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use toolbox_core::info::ToolboxInfo;
+
+/// Plugin folder that outlives the session (mapped by Zellij)
+pub const STATE_DIR: &str = "/cache";
+
+/// What the plugin showed last
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedState {
+    /// Directory the result was detected in
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Display lines (text output)
+    #[serde(default)]
+    pub content: Vec<String>,
+    /// Structured result, laid out again for the pane size
+    #[serde(default)]
+    pub info: Option<ToolboxInfo>,
+}
+
+/// State file of a session (the name is reduced to file-safe characters)
+pub fn path(dir: &Path, session: &str) -> PathBuf {
+    let name: String = session
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("toolbox-{}.json", name))
+}
+
+/// Read a saved state (None if missing or unreadable)
+pub fn load(path: &Path) -> Option<SavedState> {
+    let json = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Serialize a state for saving
+pub fn encode(state: &SavedState) -> String {
+    serde_json::to_string(state).unwrap_or_default()
+}
+
+/// Write an encoded state, replacing the previous one
+pub fn save(path: &Path, json: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_is_file_safe() {
+        assert_eq!(
+            path(Path::new("/cache"), "work"),
+            PathBuf::from("/cache/toolbox-work.json")
+        );
+        assert_eq!(
+            path(Path::new("/cache"), "../my session"),
+            PathBuf::from("/cache/toolbox-___my_session.json")
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("toolbox-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = path(&dir, "work");
+
+        assert!(load(&file).is_none());
+
+        let state = SavedState {
+            dir: Some("/home/user/app".to_string()),
+            content: vec![" Rust 1.85.0".to_string()],
+            info: serde_json::from_str(r#"{"tools": [{"name": "Rust", "available": true}]}"#).ok(),
+        };
+        save(&file, &encode(&state)).unwrap();
+        let loaded = load(&file).unwrap();
+        assert_eq!(loaded.dir.as_deref(), Some("/home/user/app"));
+        assert_eq!(loaded.content, state.content);
+        assert_eq!(loaded.info.unwrap().tools[0].name, "Rust");
+
+        std::fs::write(&file, "not json").unwrap();
+        assert!(load(&file).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
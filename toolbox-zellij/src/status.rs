@@ -0,0 +1,245 @@
+//! Refresh scheduling, loading spinner and data age
+//!
+//! The plugin runs on a single short timer tick and keeps its own clock from
+//! the elapsed times, so it can animate a spinner while the CLI runs and show
+//! how old the displayed data is. Data older than twice the refresh interval
+//! is flagged as stale, which tells frozen output apart from fresh output.
+//! Pane resizes are debounced on the same clock: the CLI re-fits powerline
+//! output once the width has settled, not on every render of a drag.
+
+/// Spinner frames shown while a refresh is in flight
+pub const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Tick interval in seconds while a refresh is in flight (spinner animation)
+pub const BUSY_TICK: f64 = 0.25;
+
+/// Tick interval in seconds otherwise (age display granularity)
+pub const IDLE_TICK: f64 = 1.0;
+
+/// Seconds the pane width must stay unchanged before powerline output is re-fitted
+pub const RESIZE_DEBOUNCE: f64 = 0.5;
+
+/// Requests without a result after this long are assumed lost
+const LOST_AFTER: f64 = 30.0;
+
+/// Timing state of the CLI refreshes
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RefreshStatus {
+    /// Seconds since the plugin loaded
+    clock: f64,
+    /// When the last refresh was started
+    last_request: Option<f64>,
+    /// When the last result arrived
+    last_update: Option<f64>,
+    /// A refresh is running
+    in_flight: bool,
+    /// Current spinner frame
+    frame: usize,
+    /// Showing content saved by a previous session, until the first result
+    restored: bool,
+    /// When the pane width last changed, until a refresh uses the new width
+    resized_at: Option<f64>,
+}
+
+impl RefreshStatus {
+    /// Advance the clock by a timer's elapsed time
+    pub fn tick(&mut self, elapsed: f64) {
+        self.clock += elapsed.max(0.0);
+        if self.in_flight {
+            self.frame = (self.frame + 1) % SPINNER.len();
+        }
+    }
+
+    /// Whether a periodic refresh should start now
+    pub fn is_due(&self, interval: f64) -> bool {
+        match self.last_request {
+            None => true,
+            Some(started) if self.in_flight => {
+                self.clock - started >= LOST_AFTER.max(interval * 3.0)
+            }
+            Some(started) => self.clock - started >= interval,
+        }
+    }
+
+    /// Record that a refresh started (it uses the current pane width)
+    pub fn started(&mut self) {
+        self.last_request = Some(self.clock);
+        self.in_flight = true;
+        self.resized_at = None;
+    }
+
+    /// Record that the pane width changed
+    pub fn resized(&mut self) {
+        self.resized_at = Some(self.clock);
+    }
+
+    /// Whether the width changed and has been stable for [`RESIZE_DEBOUNCE`]
+    pub fn is_refit_due(&self) -> bool {
+        self.resized_at
+            .is_some_and(|resized| self.clock - resized >= RESIZE_DEBOUNCE)
+    }
+
+    /// Record that a refresh result (or error) arrived
+    pub fn finished(&mut self) {
+        self.last_update = Some(self.clock);
+        self.in_flight = false;
+        self.restored = false;
+    }
+
+    /// Record that content saved by a previous session is shown
+    pub fn restored(&mut self) {
+        self.restored = true;
+    }
+
+    /// Whether the shown content comes from a previous session
+    pub fn is_restored(&self) -> bool {
+        self.restored
+    }
+
+    /// Delay until the next tick
+    pub fn next_tick(&self) -> f64 {
+        if self.in_flight || self.resized_at.is_some() {
+            BUSY_TICK
+        } else {
+            IDLE_TICK
+        }
+    }
+
+    /// Spinner frame, while a refresh is in flight
+    pub fn spinner(&self) -> Option<char> {
+        self.in_flight.then(|| SPINNER[self.frame])
+    }
+
+    /// Seconds since the last result
+    pub fn age(&self) -> Option<f64> {
+        self.last_update.map(|updated| self.clock - updated)
+    }
+
+    /// Whether the data is older than twice the refresh interval (or restored)
+    pub fn is_stale(&self, interval: f64) -> bool {
+        self.restored || self.age().is_some_and(|age| age > interval * 2.0)
+    }
+
+    /// Status text, e.g. "⠹ updated 12s ago" (None before any result or refresh)
+    pub fn text(&self) -> Option<String> {
+        let spinner = self.spinner().map(|c| format!("{} ", c));
+        if self.restored {
+            return Some(format!("{}from last session", spinner.unwrap_or_default()));
+        }
+        match (spinner, self.age()) {
+            (None, None) => None,
+            (Some(spinner), None) => Some(format!("{}loading", spinner)),
+            (spinner, Some(age)) => Some(format!(
+                "{}updated {}",
+                spinner.unwrap_or_default(),
+                format_age(age)
+            )),
+        }
+    }
+}
+
+/// Human-friendly age: "just now", "42s ago", "3m ago", "2h ago"
+pub fn format_age(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    match seconds {
+        0..=1 => "just now".to_string(),
+        2..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        _ => format!("{}h ago", seconds / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_cycle() {
+        let mut status = RefreshStatus::default();
+        assert!(status.is_due(5.0));
+        assert_eq!(status.text(), None);
+
+        status.started();
+        assert!(!status.is_due(5.0));
+        assert_eq!(status.next_tick(), BUSY_TICK);
+        assert_eq!(status.text().as_deref(), Some("⠋ loading"));
+        status.tick(BUSY_TICK);
+        assert_eq!(status.spinner(), Some('⠙'));
+
+        status.finished();
+        assert_eq!(status.spinner(), None);
+        assert_eq!(status.next_tick(), IDLE_TICK);
+        assert_eq!(status.text().as_deref(), Some("updated just now"));
+
+        for _ in 0..5 {
+            status.tick(1.0);
+        }
+        assert!(status.is_due(5.0));
+        assert_eq!(status.text().as_deref(), Some("updated 5s ago"));
+        assert!(!status.is_stale(5.0));
+    }
+
+    #[test]
+    fn test_stale_data() {
+        let mut status = RefreshStatus::default();
+        status.started();
+        status.finished();
+        status.started();
+        // The CLI hangs: the old result ages past twice the interval
+        status.tick(11.0);
+        assert!(status.is_stale(5.0));
+        assert!(!status.is_due(5.0));
+        assert!(status.text().unwrap().ends_with("updated 11s ago"));
+
+        // A request without a result is eventually retried
+        status.tick(LOST_AFTER);
+        assert!(status.is_due(5.0));
+    }
+
+    #[test]
+    fn test_restored_content_is_stale_until_a_result() {
+        let mut status = RefreshStatus::default();
+        status.restored();
+        assert!(status.is_restored());
+        assert!(status.is_stale(5.0));
+        assert_eq!(status.text().as_deref(), Some("from last session"));
+
+        status.started();
+        assert_eq!(status.text().as_deref(), Some("⠋ from last session"));
+        status.finished();
+        assert!(!status.is_restored());
+        assert!(!status.is_stale(5.0));
+        assert_eq!(status.text().as_deref(), Some("updated just now"));
+    }
+
+    #[test]
+    fn test_resize_is_debounced() {
+        let mut status = RefreshStatus::default();
+        status.started();
+        status.finished();
+        status.resized();
+        assert_eq!(status.next_tick(), BUSY_TICK);
+        status.tick(BUSY_TICK);
+        // Still resizing: the debounce starts over
+        status.resized();
+        status.tick(BUSY_TICK);
+        assert!(!status.is_refit_due());
+        status.tick(BUSY_TICK);
+        assert!(status.is_refit_due());
+
+        // The refresh uses the settled width
+        status.started();
+        assert!(!status.is_refit_due());
+        status.finished();
+        assert_eq!(status.next_tick(), IDLE_TICK);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(0.4), "just now");
+        assert_eq!(format_age(42.0), "42s ago");
+        assert_eq!(format_age(190.0), "3m ago");
+        assert_eq!(format_age(7300.0), "2h ago");
+        assert_eq!(format_age(-1.0), "just now");
+    }
+}
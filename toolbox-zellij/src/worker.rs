@@ -0,0 +1,126 @@
+//! Message protocol of the detection worker
+//!
+//! The CLI result is handed to a plugin worker (`register_worker!`) that
+//! parses it and posts the result back, so parsing large outputs (and future
+//! in-plugin detection) stays off the render path.
+
+use toolbox_core::info::{ToolboxInfo, SCHEMA_VERSION};
+
+/// Worker name passed to `PluginMessage::new_to_worker`
+pub const NAME: &str = "detection";
+
+/// Plugin → worker: successful CLI output (payload: stdout)
+pub const OUTPUT: &str = "output";
+
+/// Plugin → worker: successful `--format json` output (payload: stdout)
+pub const JSON: &str = "json";
+
+/// Plugin → worker: failed CLI run (payload: stderr)
+pub const ERROR: &str = "error";
+
+/// Worker → plugin: display lines (payload: lines joined with `\n`)
+pub const CONTENT: &str = "content";
+
+/// Worker → plugin: parsed tool information (payload: compact JSON)
+pub const INFO: &str = "info";
+
+/// Worker → plugin: the CLI failed (payload: error output)
+pub const FAILED: &str = "failed";
+
+/// Reply (message and payload) to a worker message (None for unknown messages)
+pub fn handle(message: &str, payload: &str) -> Option<(&'static str, String)> {
+    match message {
+        OUTPUT => Some((CONTENT, encode(&output_lines(payload)))),
+        JSON => Some(match serde_json::from_str::<ToolboxInfo>(payload) {
+            // Fields may have changed meaning: don't show them as if they hadn't
+            Ok(info) if info.schema_version > SCHEMA_VERSION => (
+                FAILED,
+                format!(
+                    "toolbox output schema v{} is newer than this plugin (v{}); run `toolbox zellij-update`",
+                    info.schema_version, SCHEMA_VERSION
+                ),
+            ),
+            Ok(info) => (INFO, serde_json::to_string(&info).unwrap_or_default()),
+            Err(e) => (FAILED, format!("Invalid toolbox output: {}", e)),
+        }),
+        ERROR => Some((FAILED, payload.to_string())),
+        _ => None,
+    }
+}
+
+/// Decode an `INFO` payload
+pub fn decode_info(payload: &str) -> Option<ToolboxInfo> {
+    serde_json::from_str(payload).ok()
+}
+
+/// Lines of successful CLI output
+pub fn output_lines(stdout: &str) -> Vec<String> {
+    let lines: Vec<String> = stdout.lines().map(String::from).collect();
+    if lines.is_empty() {
+        vec![" No tools detected".to_string()]
+    } else {
+        lines
+    }
+}
+
+/// Encode display lines as a message payload
+pub fn encode(lines: &[String]) -> String {
+    lines.join("\n")
+}
+
+/// Decode a `CONTENT` payload
+pub fn decode(payload: &str) -> Vec<String> {
+    payload.split('\n').map(String::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_output() {
+        assert_eq!(
+            handle(OUTPUT, "---\n Rust 1.85\n"),
+            Some((CONTENT, "---\n Rust 1.85".to_string()))
+        );
+        assert_eq!(
+            handle(OUTPUT, ""),
+            Some((CONTENT, " No tools detected".to_string()))
+        );
+        assert_eq!(handle("ping", ""), None);
+    }
+
+    #[test]
+    fn test_handle_json() {
+        let (message, payload) = handle(
+            JSON,
+            r#"{"tools":[{"name":"Rust","version":"1.85.0","available":true}]}"#,
+        )
+        .unwrap();
+        assert_eq!(message, INFO);
+        let info = decode_info(&payload).unwrap();
+        assert_eq!(info.tools[0].version.as_deref(), Some("1.85.0"));
+
+        let (message, payload) = handle(JSON, r#"{"schema_version":99,"tools":[]}"#).unwrap();
+        assert_eq!(message, FAILED);
+        assert!(payload.contains("schema v99 is newer than this plugin"));
+
+        let (message, payload) = handle(JSON, "not json").unwrap();
+        assert_eq!(message, FAILED);
+        assert!(payload.starts_with("Invalid toolbox output"));
+    }
+
+    #[test]
+    fn test_handle_error() {
+        assert_eq!(
+            handle(ERROR, "toolbox: not found"),
+            Some((FAILED, "toolbox: not found".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_encode_roundtrip() {
+        let lines = vec!["---".to_string(), String::new(), " Node 22".to_string()];
+        assert_eq!(decode(&encode(&lines)), lines);
+    }
+}